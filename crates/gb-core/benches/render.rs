@@ -0,0 +1,65 @@
+//! Scanline rendering and RGB conversion at each SIMD level
+//!
+//!   cargo bench -p gb-core --bench render [-- FRAMES]
+//!
+//! "rgb" is `framebuffer_rgb` alone; "ppu" renders frames of busy VRAM (BG,
+//! window and sprites on every line) without the CPU; "batch" is a
+//! letsplay_batch-style loop, `run_frame` plus `framebuffer_rgb` per frame.
+//! Each figure is the best of three rounds.
+
+use gb_core::*;
+use std::time::Instant;
+
+fn noise(seed: u64, n: usize) -> Vec<u8> {
+    let mut x = seed | 1;
+    (0..n).map(|_| { x ^= x << 13; x ^= x >> 7; x ^= x << 17; x as u8 }).collect()
+}
+
+fn busy_core(level: SimdLevel) -> GbCore {
+    let rom = RomBuilder::new().code(&[0x18, 0xFE]).build();
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    core.bus.vram[0].copy_from_slice(&noise(3, 0x2000));
+    // Sprites spread over every line
+    for (i, s) in core.bus.oam.chunks_exact_mut(4).enumerate() {
+        s.copy_from_slice(&[16 + (i as u8 * 4) % 144, 8 + (i as u8 * 37) % 160, i as u8, (i as u8) << 4 & 0xF0]);
+    }
+    let ppu = &mut core.bus.ppu;
+    (ppu.lcdc, ppu.scx, ppu.scy, ppu.wx, ppu.wy, ppu.simd) = (0xE3, 13, 77, 87, 72, level);
+    core
+}
+
+/// Frames per second of `frame` over `frames` frames, best of 3 rounds
+fn fps(frames: u32, mut frame: impl FnMut()) -> f64 {
+    (0..3).map(|_| {
+        let start = Instant::now();
+        for _ in 0..frames { frame(); }
+        frames as f64 / start.elapsed().as_secs_f64()
+    }).fold(0.0, f64::max)
+}
+
+fn main() {
+    let frames: u32 = std::env::args().skip(1).find_map(|a| a.parse().ok()).unwrap_or(300);
+    let mut baseline = [0f64; 3];
+    for level in SimdLevel::supported() {
+        let mut core = busy_core(level);
+        let rgb_fps = fps(frames, || { std::hint::black_box(core.framebuffer_rgb()); });
+
+        let vram = core.bus.vram[0];
+        let oam = core.bus.oam;
+        // One call per PPU mode, so the renderer dominates
+        let ppu_fps = fps(frames, || {
+            for _ in 0..LCD_HEIGHT { for dots in [80, 172, 204] { core.bus.ppu.step(dots, &vram, &oam); } }
+            for _ in 0..10 { for _ in 0..2 { core.bus.ppu.step(228, &vram, &oam); } }
+        });
+
+        let mut core = busy_core(level);
+        let batch_fps = fps(frames, || {
+            core.run_frame().unwrap();
+            std::hint::black_box(core.framebuffer_rgb());
+        });
+
+        if level == SimdLevel::Scalar { baseline = [rgb_fps, ppu_fps, batch_fps]; }
+        println!("{:<7} rgb {:>8.0} fps ({:.2}x)   ppu {:>8.0} fps ({:.2}x)   batch {:>6.0} fps ({:.2}x)", level.as_str(),
+                 rgb_fps, rgb_fps / baseline[0], ppu_fps, ppu_fps / baseline[1], batch_fps, batch_fps / baseline[2]);
+    }
+}
@@ -0,0 +1,62 @@
+//! accuracy — one switch between throughput and hardware fidelity
+//!
+//! Several behaviours cost time in proportion to how faithfully they model
+//! the hardware. `GbCore::set_accuracy` sets them all from one
+//! `AccuracyProfile`:
+//!
+//! | profile    | fast halt | block cache | OAM DMA bus lock | open bus            | pixel FIFO |
+//! |------------|-----------|-------------|------------------|---------------------|------------|
+//! | `Fast`     | on        | on          | off              | `AllFF`             | off        |
+//! | `Balanced` | on        | off         | on               | `AllFF`             | off        |
+//! | `Cycle`    | off       | off         | on               | per model (CGB: `CgbBehavior`) | on |
+//!
+//! Fast halt and the block cache never change results; dropping the DMA bus
+//! lock does (CPU reads during OAM DMA see memory instead of 0xFF), which is
+//! harmless for the bulk of games and what batch training wants. `Cycle`
+//! steps halted CPUs 4 T-cycles at a time, so `debug_step` reports every
+//! M-cycle, models what prohibited reads return, and draws mode 3 dot by dot
+//! (`pixel_fifo.rs`), so its length varies and mid-line register writes
+//! show; the other profiles render a line at a time with a fixed mode 3.
+
+use crate::{HardwareModel, OpenBusPolicy};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccuracyProfile {
+    /// Batch throughput: every shortcut on
+    Fast,
+    /// Shortcuts that never change results
+    #[default]
+    Balanced,
+    /// Full fidelity for interactive and debug use
+    Cycle,
+}
+
+impl AccuracyProfile {
+    pub fn as_str(self) -> &'static str {
+        match self { AccuracyProfile::Fast => "fast", AccuracyProfile::Balanced => "balanced", AccuracyProfile::Cycle => "cycle" }
+    }
+    pub fn parse(s: &str) -> Option<AccuracyProfile> {
+        match s {
+            "fast" => Some(AccuracyProfile::Fast),
+            "balanced" => Some(AccuracyProfile::Balanced),
+            "cycle" => Some(AccuracyProfile::Cycle),
+            _ => None,
+        }
+    }
+
+    /// `GbCore::fast_halt`
+    pub fn fast_halt(self) -> bool { self != AccuracyProfile::Cycle }
+    /// `Bus::block_cache` is on
+    pub fn block_cache(self) -> bool { self == AccuracyProfile::Fast }
+    /// `Bus::dma_bus_lock`
+    pub fn dma_bus_lock(self) -> bool { self != AccuracyProfile::Fast }
+    /// `Ppu::fifo` is on
+    pub fn pixel_fifo(self) -> bool { self == AccuracyProfile::Cycle }
+    /// `Bus::open_bus` for `model`
+    pub fn open_bus(self, model: HardwareModel) -> OpenBusPolicy {
+        match (self, model) {
+            (AccuracyProfile::Cycle, HardwareModel::Cgb) => OpenBusPolicy::CgbBehavior,
+            _ => OpenBusPolicy::AllFF,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! artifacts — canonical per-ROM output layout for the letsplay binaries
+//!
+//! Every binary that writes per-ROM output puts it under one directory named
+//! by the ROM's hash (the same `rom_hash` savestate meta carries), so
+//! downstream tooling can find any artifact from the ROM alone:
+//!
+//! ```text
+//! <out>/<rom_hash>/
+//!   manifest.json   mrom.artifacts.v1: ROM identity + files present
+//!   session.json    mrom.session.v1: config, plan and checksummed files of the last run
+//!   train.json      mrom.train.v1 / v2
+//!   replay.json     mrom.replay
+//!   audio.wav
+//!   console.txt     serial / RAM console text
+//!   battery.sav     battery-backed cartridge RAM (see `sram_autosave.rs`)
+//!   io_writes.mriolog  cycle-stamped IO register writes (see `io_log.rs`)
+//!   exec_coverage.json executed-code ranges (see `exec_coverage.rs`)
+//!   profile.json    per-opcode / per-address execution profile (see `profile.rs`)
+//!   states/<name>.mrom.sav
+//!   frames/<frame:06>.png
+//!   triggers/triggers.json  mrom.triggers.v1 (see `triggers.rs`)
+//!   triggers/<label>_f<frame:06>.png / .mrom.sav
+//!   checkpoints/checkpoints.json  mrom.checkpoints.v1 (see `checkpoint.rs`)
+//!   checkpoints/<id>.mrom.sav
+//! ```
+//!
+//! Batch-level files (e.g. `batch_manifest.json`) stay at `<out>/`.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const ARTIFACTS_VERSION: &str = "mrom.artifacts.v1";
+
+/// Hash naming a ROM's artifact directory (FNV-1a of the image, hex)
+pub fn rom_hash(rom: &[u8]) -> String { format!("{:08x}", crate::fnv1a(rom)) }
+
+/// Paths of one ROM's artifacts under an output root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomArtifacts {
+    pub rom_hash: String,
+    pub dir: PathBuf,
+}
+
+impl RomArtifacts {
+    pub fn new(out: &Path, rom: &[u8]) -> Self { Self::for_hash(out, &rom_hash(rom)) }
+    pub fn for_hash(out: &Path, rom_hash: &str) -> Self {
+        RomArtifacts { rom_hash: rom_hash.to_string(), dir: out.join(rom_hash) }
+    }
+
+    /// Create the directory and its `states/` and `frames/` subdirectories
+    pub fn create(&self) -> io::Result<()> {
+        std::fs::create_dir_all(self.states_dir())?;
+        std::fs::create_dir_all(self.frames_dir())
+    }
+
+    pub fn manifest(&self) -> PathBuf { self.dir.join("manifest.json") }
+    pub fn session(&self) -> PathBuf { self.dir.join("session.json") }
+    pub fn train(&self) -> PathBuf { self.dir.join("train.json") }
+    pub fn replay(&self) -> PathBuf { self.dir.join("replay.json") }
+    pub fn audio(&self) -> PathBuf { self.dir.join("audio.wav") }
+    pub fn console(&self) -> PathBuf { self.dir.join("console.txt") }
+    pub fn battery(&self) -> PathBuf { self.dir.join("battery.sav") }
+    pub fn io_log(&self) -> PathBuf { self.dir.join("io_writes.mriolog") }
+    pub fn exec_coverage(&self) -> PathBuf { self.dir.join("exec_coverage.json") }
+    pub fn profile(&self) -> PathBuf { self.dir.join("profile.json") }
+    pub fn ppu_timeline(&self) -> PathBuf { self.dir.join("ppu_timeline.json") }
+    pub fn ppu_timeline_svg(&self) -> PathBuf { self.dir.join("ppu_timeline.svg") }
+    pub fn states_dir(&self) -> PathBuf { self.dir.join("states") }
+    pub fn frames_dir(&self) -> PathBuf { self.dir.join("frames") }
+    pub fn triggers_dir(&self) -> PathBuf { self.dir.join("triggers") }
+    pub fn checkpoints_dir(&self) -> PathBuf { self.dir.join("checkpoints") }
+    /// `states/<name>.mrom.sav` (the pattern `StateIndex::scan` picks up)
+    pub fn state(&self, name: &str) -> PathBuf { self.states_dir().join(format!("{name}.mrom.sav")) }
+    /// `frames/<frame:06>.png`
+    pub fn frame(&self, frame: u64) -> PathBuf { self.frames_dir().join(format!("{frame:06}.png")) }
+
+    /// Files present under the directory, relative and sorted (`states/a.mrom.sav`)
+    pub fn files(&self) -> Vec<String> {
+        fn walk(dir: &Path, prefix: &str, out: &mut Vec<String>) {
+            let Ok(entries) = std::fs::read_dir(dir) else { return };
+            for e in entries.flatten() {
+                let name = e.file_name().to_string_lossy().to_string();
+                let rel = format!("{prefix}{name}");
+                if e.path().is_dir() { walk(&e.path(), &format!("{rel}/"), out); }
+                else if rel != "manifest.json" { out.push(rel); }
+            }
+        }
+        let mut out = vec![];
+        walk(&self.dir, "", &mut out);
+        out.sort();
+        out
+    }
+
+    /// Write `manifest.json` listing the files currently present. `source`
+    /// is the ROM path as given on the command line.
+    pub fn write_manifest(&self, rom_title: &str, source: &str) -> io::Result<PathBuf> {
+        let esc = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let files: Vec<String> = self.files().iter().map(|f| format!("\"{}\"", esc(f))).collect();
+        let json = format!(
+            "{{\"version\":\"{}\",\"rom_hash\":\"{}\",\"rom_title\":\"{}\",\"source\":\"{}\",\"files\":[{}]}}",
+            ARTIFACTS_VERSION, self.rom_hash, esc(rom_title), esc(source), files.join(",")
+        );
+        std::fs::write(self.manifest(), json)?;
+        Ok(self.manifest())
+    }
+}
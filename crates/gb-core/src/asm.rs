@@ -0,0 +1,417 @@
+//! asm — a small SM83 macro-assembler for tests and synthetic ROMs
+//!
+//! Two passes over RGBDS-flavoured source: pass 1 sizes every line and binds
+//! labels, pass 2 encodes with all symbols known. Supported:
+//!
+//! - every documented SM83 instruction; memory operands as `[hl]` or `(hl)`,
+//!   `[hl+]`/`[hli]`, `[hl-]`/`[hld]`, `[c]`/`[$ff00+c]`, `ld hl, sp+e`
+//! - `label:`, local `.label` (scoped to the previous global label),
+//!   `NAME equ expr`
+//! - `org addr`, `db` (numbers and "strings"), `dw`, `ds count[, fill]`
+//! - `macro name` … `endm`, invoked as `name a, b`; `\1`-`\9` are the
+//!   arguments and `\@` is unique per expansion (for labels inside macros)
+//! - numbers as `$ff`, `0xff`, `%1010`, `255`, `'c'`; `@` is the current
+//!   address; expressions are terms joined by `+` / `-`
+//!
+//! Errors are `line N: message` strings, as elsewhere in the crate.
+
+use crate::rombuild::CODE_START;
+use std::collections::HashMap;
+
+/// Assembled output: contiguous chunks by start address, plus the symbols
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Assembly {
+    pub chunks: Vec<(u16, Vec<u8>)>,
+    pub symbols: HashMap<String, i64>,
+}
+
+impl Assembly {
+    /// Address of a label or value of an `equ`
+    pub fn symbol(&self, name: &str) -> Option<i64> { self.symbols.get(name).copied() }
+
+    /// All chunks laid into one buffer starting at the lowest address
+    /// (gaps filled with 0x00)
+    pub fn flatten(&self) -> (u16, Vec<u8>) {
+        let start = self.chunks.iter().map(|(a, _)| *a).min().unwrap_or(0);
+        let end = self.chunks.iter().map(|(a, b)| *a as usize + b.len()).max().unwrap_or(start as usize);
+        let mut out = vec![0u8; end - start as usize];
+        for (a, b) in &self.chunks {
+            let o = (*a - start) as usize;
+            out[o..o + b.len()].copy_from_slice(b);
+        }
+        (start, out)
+    }
+}
+
+/// Assemble `src`; code before the first `org` starts at CODE_START (0x0150)
+pub fn assemble(src: &str) -> Result<Assembly, String> { assemble_at(src, CODE_START) }
+
+pub fn assemble_at(src: &str, origin: u16) -> Result<Assembly, String> {
+    let lines = expand_macros(src)?;
+    let mut symbols = HashMap::new();
+    run(&lines, origin, &mut symbols, false)?;
+    let chunks = run(&lines, origin, &mut symbols, true)?;
+    Ok(Assembly { chunks, symbols })
+}
+
+// ── Macros ───────────────────────────────────────────────────────────────────
+
+const MAX_MACRO_DEPTH: usize = 16;
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = None;
+    for (i, c) in line.char_indices() {
+        match (c, in_str) {
+            ('"' | '\'', None) => in_str = Some(c),
+            (q, Some(open)) if q == open => in_str = None,
+            (';', None) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn expand_macros(src: &str) -> Result<Vec<(usize, String)>, String> {
+    let mut macros: HashMap<String, Vec<String>> = HashMap::new();
+    let mut body: Vec<(usize, String)> = vec![];
+    let mut defining: Option<(String, Vec<String>)> = None;
+    for (n, raw) in src.lines().enumerate() {
+        let line = strip_comment(raw).trim();
+        let lower = line.to_ascii_lowercase();
+        if let Some((name, lines)) = defining.as_mut() {
+            if lower == "endm" {
+                macros.insert(std::mem::take(name), std::mem::take(lines));
+                defining = None;
+            } else {
+                lines.push(line.to_string());
+            }
+        } else if let Some(name) = lower.strip_prefix("macro ") {
+            defining = Some((name.trim().to_string(), vec![]));
+        } else if !line.is_empty() {
+            body.push((n + 1, line.to_string()));
+        }
+    }
+    if let Some((name, _)) = defining { return Err(format!("macro {name}: missing endm")); }
+    let mut out = vec![];
+    let mut counter = 0usize;
+    for (n, line) in body { expand_line(n, &line, &macros, &mut counter, 0, &mut out)?; }
+    Ok(out)
+}
+
+fn expand_line(n: usize, line: &str, macros: &HashMap<String, Vec<String>>, counter: &mut usize, depth: usize,
+               out: &mut Vec<(usize, String)>) -> Result<(), String> {
+    let (label, rest) = split_label(line);
+    let (head, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let Some(lines) = macros.get(&head.to_ascii_lowercase()) else {
+        out.push((n, line.to_string()));
+        return Ok(());
+    };
+    if depth >= MAX_MACRO_DEPTH { return Err(format!("line {n}: macro nesting too deep")); }
+    if let Some(label) = label { out.push((n, format!("{label}:"))); }
+    *counter += 1;
+    let args = split_operands(args);
+    for l in lines {
+        let mut l = l.replace("\\@", &format!("_{}", counter));
+        for (i, a) in args.iter().enumerate().rev() { l = l.replace(&format!("\\{}", i + 1), a); }
+        expand_line(n, &l, macros, counter, depth + 1, out)?;
+    }
+    Ok(())
+}
+
+// ── Passes ───────────────────────────────────────────────────────────────────
+
+/// `label:` prefix of a line, if any (not inside quotes)
+fn split_label(line: &str) -> (Option<&str>, &str) {
+    let first = line.split_whitespace().next().unwrap_or("");
+    match first.find(':') {
+        Some(i) if i > 0 && !first.starts_with(['"', '\'', '[', '(']) => {
+            let label = &first[..i];
+            let rest = line[line.find(':').unwrap_or(0) + 1..].trim_start_matches(':').trim();
+            (Some(label), rest)
+        }
+        _ => (None, line),
+    }
+}
+
+fn split_operands(s: &str) -> Vec<String> {
+    let mut out = vec![];
+    let (mut cur, mut depth, mut quote) = (String::new(), 0i32, None);
+    for c in s.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (q, Some(open)) if q == open => quote = None,
+            ('[' | '(', None) => depth += 1,
+            (']' | ')', None) => depth -= 1,
+            (',', None) if depth == 0 => { out.push(cur.trim().to_string()); cur.clear(); continue; }
+            _ => {}
+        }
+        cur.push(c);
+    }
+    if !cur.trim().is_empty() || !out.is_empty() { out.push(cur.trim().to_string()); }
+    out
+}
+
+struct Ctx<'a> {
+    symbols: &'a HashMap<String, i64>,
+    scope: String,
+    pc: u16,
+    strict: bool,
+}
+
+impl Ctx<'_> {
+    fn qualify(&self, name: &str) -> String {
+        if name.starts_with('.') { format!("{}{}", self.scope, name) } else { name.to_string() }
+    }
+
+    fn term(&self, t: &str) -> Result<i64, String> {
+        let t = t.trim();
+        let num = |s: &str, radix| i64::from_str_radix(&s.replace('_', ""), radix).map_err(|_| format!("bad number {t:?}"));
+        if t.is_empty() { return Err("missing value".into()); }
+        if t == "@" { return Ok(self.pc as i64); }
+        if let Some(h) = t.strip_prefix('$') { return num(h, 16); }
+        if let Some(h) = t.strip_prefix("0x").or_else(|| t.strip_prefix("0X")) { return num(h, 16); }
+        if let Some(b) = t.strip_prefix('%') { return num(b, 2); }
+        if t.len() == 3 && t.starts_with('\'') && t.ends_with('\'') { return Ok(t.as_bytes()[1] as i64); }
+        if t.as_bytes()[0].is_ascii_digit() { return num(t, 10); }
+        match self.symbols.get(&self.qualify(t)) {
+            Some(v) => Ok(*v),
+            None if self.strict => Err(format!("unknown symbol {t:?}")),
+            None => Ok(0),
+        }
+    }
+
+    fn eval(&self, expr: &str) -> Result<i64, String> {
+        let (mut total, mut sign, mut start) = (0i64, 1i64, 0usize);
+        let bytes = expr.as_bytes();
+        let mut in_char = false;
+        for i in 0..=bytes.len() {
+            let c = bytes.get(i).copied();
+            if c == Some(b'\'') { in_char = !in_char; }
+            if in_char { continue; }
+            if matches!(c, Some(b'+') | Some(b'-') | None) {
+                let term = expr[start..i].trim();
+                if term.is_empty() {
+                    if c == Some(b'-') { sign = -sign; }
+                    if c.is_none() { return Err(format!("bad expression {expr:?}")); }
+                } else {
+                    total += sign * self.term(term)?;
+                    sign = if c == Some(b'-') { -1 } else { 1 };
+                }
+                start = i + 1;
+            }
+        }
+        Ok(total)
+    }
+
+    fn range(&self, v: i64, lo: i64, hi: i64, what: &str) -> Result<i64, String> {
+        if self.strict && (v < lo || v > hi) { return Err(format!("{what} out of range: {v}")); }
+        Ok(v)
+    }
+    fn imm8(&self, e: &str) -> Result<u8, String> { Ok(self.range(self.eval(e)?, -128, 255, "8-bit value")? as u8) }
+    fn imm16(&self, e: &str) -> Result<[u8; 2], String> {
+        Ok((self.range(self.eval(e)?, -32768, 65535, "16-bit value")? as u16).to_le_bytes())
+    }
+}
+
+fn run(lines: &[(usize, String)], origin: u16, symbols: &mut HashMap<String, i64>, strict: bool)
+       -> Result<Vec<(u16, Vec<u8>)>, String> {
+    let mut chunks: Vec<(u16, Vec<u8>)> = vec![(origin, vec![])];
+    let mut scope = String::new();
+    for (n, line) in lines {
+        let err = |e: String| format!("line {n}: {e}");
+        let pc = chunks.last().map_or(origin, |(a, b)| a.wrapping_add(b.len() as u16));
+        let (label, rest) = split_label(line);
+        if let Some(label) = label {
+            if !label.starts_with('.') { scope = label.to_string(); }
+            let name = if label.starts_with('.') { format!("{scope}{label}") } else { label.to_string() };
+            if !strict && symbols.contains_key(&name) { return Err(err(format!("duplicate label {name:?}"))); }
+            symbols.insert(name, pc as i64);
+        }
+        if rest.is_empty() { continue; }
+        let (head, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let ctx = Ctx { symbols, scope: scope.clone(), pc, strict };
+        // NAME equ expr
+        if let Some((_, expr)) = tail.trim().split_once(char::is_whitespace).filter(|(k, _)| k.eq_ignore_ascii_case("equ")) {
+            let v = ctx.eval(expr).map_err(err)?;
+            symbols.insert(head.to_string(), v);
+            continue;
+        }
+        let ops = split_operands(tail);
+        let mnemonic = head.to_ascii_lowercase();
+        if mnemonic == "org" {
+            let addr = ctx.eval(ops.first().map_or("", String::as_str)).map_err(err)?;
+            chunks.push((addr as u16, vec![]));
+            continue;
+        }
+        let bytes = match mnemonic.as_str() {
+            "db" => {
+                let mut out = vec![];
+                for o in &ops {
+                    if o.len() >= 2 && o.starts_with('"') && o.ends_with('"') { out.extend_from_slice(&o.as_bytes()[1..o.len() - 1]); }
+                    else { out.push(ctx.imm8(o).map_err(err)?); }
+                }
+                out
+            }
+            "dw" => { let mut out = vec![]; for o in &ops { out.extend_from_slice(&ctx.imm16(o).map_err(err)?); } out }
+            "ds" => {
+                let count = ctx.eval(ops.first().map_or("", String::as_str)).map_err(err)?;
+                let fill = match ops.get(1) { Some(f) => ctx.imm8(f).map_err(err)?, None => 0 };
+                vec![fill; count.max(0) as usize]
+            }
+            _ => encode(&mnemonic, &ops, &ctx).map_err(err)?,
+        };
+        if let Some((_, b)) = chunks.last_mut() { b.extend(bytes); }
+    }
+    chunks.retain(|(_, b)| !b.is_empty());
+    Ok(chunks)
+}
+
+// ── Encoding ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    /// b c d e h l [hl] a → 0-7
+    R8(u8),
+    /// bc de hl sp af
+    R16(&'static str),
+    MemBc, MemDe, MemHli, MemHld, MemC,
+    Mem(String),
+    SpOffset(String),
+    Imm(String),
+}
+
+fn parse_operand(s: &str) -> Operand {
+    let lower = s.to_ascii_lowercase().replace(' ', "");
+    const R8: [&str; 8] = ["b", "c", "d", "e", "h", "l", "[hl]", "a"];
+    if let Some(i) = R8.iter().position(|r| *r == lower.replace('(', "[").replace(')', "]")) { return Operand::R8(i as u8); }
+    for r in ["bc", "de", "hl", "sp", "af"] { if lower == r { return Operand::R16(r); } }
+    if let Some(off) = lower.strip_prefix("sp").filter(|o| o.starts_with(['+', '-'])) {
+        return Operand::SpOffset(off.trim_start_matches('+').to_string());
+    }
+    let inner = lower.strip_prefix('[').and_then(|x| x.strip_suffix(']'))
+        .or_else(|| lower.strip_prefix('(').and_then(|x| x.strip_suffix(')')));
+    match inner {
+        Some("bc") => Operand::MemBc,
+        Some("de") => Operand::MemDe,
+        Some("hl+") | Some("hli") => Operand::MemHli,
+        Some("hl-") | Some("hld") => Operand::MemHld,
+        Some("c") | Some("$ff00+c") | Some("0xff00+c") => Operand::MemC,
+        Some(_) => {
+            let t = s.trim();
+            Operand::Mem(t[1..t.len() - 1].trim().to_string())
+        }
+        None => Operand::Imm(s.trim().to_string()),
+    }
+}
+
+fn cond_code(s: &str) -> Option<u8> {
+    ["nz", "z", "nc", "c"].iter().position(|c| s.eq_ignore_ascii_case(c)).map(|i| i as u8)
+}
+
+fn r16_index(r: &str, af: bool) -> Option<u8> {
+    match r { "bc" => Some(0), "de" => Some(1), "hl" => Some(2), "sp" if !af => Some(3), "af" if af => Some(3), _ => None }
+}
+
+fn encode(m: &str, raw: &[String], ctx: &Ctx) -> Result<Vec<u8>, String> {
+    use Operand::*;
+    let ops: Vec<Operand> = raw.iter().map(|s| parse_operand(s)).collect();
+    let bad = || format!("unsupported operands for {m}: {}", raw.join(", "));
+    let imm16 = |e: &str| -> Result<Vec<u8>, String> { Ok(ctx.imm16(e)?.to_vec()) };
+    let with16 = |op: u8, e: &str| -> Result<Vec<u8>, String> { let mut v = vec![op]; v.extend(imm16(e)?); Ok(v) };
+    // Condition + target for jp / jr / call / ret
+    let (cond, rest) = match raw.first().and_then(|s| cond_code(s)) {
+        Some(cc) if matches!(m, "jp" | "jr" | "call" | "ret") => (Some(cc), &ops[1..]),
+        _ => (None, &ops[..]),
+    };
+    const ALU: [&str; 8] = ["add", "adc", "sub", "sbc", "and", "xor", "or", "cp"];
+    const CB: [&str; 8] = ["rlc", "rrc", "rl", "rr", "sla", "sra", "swap", "srl"];
+    let plain = match m {
+        "nop" => Some(0x00), "halt" => Some(0x76), "di" => Some(0xF3), "ei" => Some(0xFB),
+        "daa" => Some(0x27), "cpl" => Some(0x2F), "scf" => Some(0x37), "ccf" => Some(0x3F),
+        "rlca" => Some(0x07), "rrca" => Some(0x0F), "rla" => Some(0x17), "rra" => Some(0x1F),
+        "reti" => Some(0xD9),
+        _ => None,
+    };
+    if let Some(op) = plain { return if ops.is_empty() { Ok(vec![op]) } else { Err(bad()) }; }
+    if m == "stop" { return Ok(vec![0x10, 0x00]); }
+
+    if let Some(i) = ALU.iter().position(|a| *a == m) {
+        let i = i as u8;
+        match ops.as_slice() {
+            [R16("hl"), R16(r)] if m == "add" => return Ok(vec![0x09 | r16_index(r, false).ok_or_else(bad)? << 4]),
+            [R16("sp"), Imm(e)] if m == "add" => return Ok(vec![0xE8, ctx.imm8(e)?]),
+            [R8(7), src] | [src] => return match src {
+                R8(r) => Ok(vec![0x80 | i << 3 | r]),
+                Imm(e) => Ok(vec![0xC6 | i << 3, ctx.imm8(e)?]),
+                _ => Err(bad()),
+            },
+            _ => return Err(bad()),
+        }
+    }
+    if let Some(i) = CB.iter().position(|c| *c == m) {
+        return match ops.as_slice() { [R8(r)] => Ok(vec![0xCB, (i as u8) << 3 | r]), _ => Err(bad()) };
+    }
+    if let Some(base) = match m { "bit" => Some(0x40u8), "res" => Some(0x80), "set" => Some(0xC0), _ => None } {
+        return match ops.as_slice() {
+            [Imm(b), R8(r)] => {
+                let b = ctx.range(ctx.eval(b)?, 0, 7, "bit index")? as u8 & 7;
+                Ok(vec![0xCB, base | b << 3 | r])
+            }
+            _ => Err(bad()),
+        };
+    }
+
+    match (m, rest) {
+        ("inc", [R8(r)]) => Ok(vec![0x04 | r << 3]),
+        ("dec", [R8(r)]) => Ok(vec![0x05 | r << 3]),
+        ("inc", [R16(r)]) => Ok(vec![0x03 | r16_index(r, false).ok_or_else(bad)? << 4]),
+        ("dec", [R16(r)]) => Ok(vec![0x0B | r16_index(r, false).ok_or_else(bad)? << 4]),
+        ("push", [R16(r)]) => Ok(vec![0xC5 | r16_index(r, true).ok_or_else(bad)? << 4]),
+        ("pop", [R16(r)]) => Ok(vec![0xC1 | r16_index(r, true).ok_or_else(bad)? << 4]),
+
+        ("jp", [R16("hl")]) | ("jp", [R8(6)]) if cond.is_none() => Ok(vec![0xE9]),
+        ("jp", [Imm(e)]) => with16(cond.map_or(0xC3, |c| 0xC2 | c << 3), e),
+        ("call", [Imm(e)]) => with16(cond.map_or(0xCD, |c| 0xC4 | c << 3), e),
+        ("ret", []) => Ok(vec![cond.map_or(0xC9, |c| 0xC0 | c << 3)]),
+        ("jr", [Imm(e)]) => {
+            let off = ctx.eval(e)? - (ctx.pc as i64 + 2);
+            let off = ctx.range(off, -128, 127, "jr offset")?;
+            Ok(vec![cond.map_or(0x18, |c| 0x20 | c << 3), off as u8])
+        }
+        ("rst", [Imm(e)]) => {
+            let v = ctx.eval(e)?;
+            if ctx.strict && (v & !0x38 != 0) { return Err(format!("rst vector must be $00-$38 in steps of 8: {v}")); }
+            Ok(vec![0xC7 | (v as u8 & 0x38)])
+        }
+
+        ("ldh", [Mem(e), R8(7)]) | ("ldh", [R8(7), Mem(e)]) => {
+            let v = ctx.eval(e)?;
+            let v = if v >= 0xFF00 { v - 0xFF00 } else { v };
+            let v = ctx.range(v, 0, 0xFF, "ldh address")? as u8;
+            Ok(vec![if matches!(rest[0], R8(7)) { 0xF0 } else { 0xE0 }, v])
+        }
+        ("ldh" | "ld", [MemC, R8(7)]) => Ok(vec![0xE2]),
+        ("ldh" | "ld", [R8(7), MemC]) => Ok(vec![0xF2]),
+
+        ("ld", [R8(6), R8(6)]) => Err(bad()),
+        ("ld", [R8(d), R8(s)]) => Ok(vec![0x40 | d << 3 | s]),
+        ("ld", [R8(d), Imm(e)]) => Ok(vec![0x06 | d << 3, ctx.imm8(e)?]),
+        ("ld", [R16("sp"), R16("hl")]) => Ok(vec![0xF9]),
+        ("ld", [R16("hl"), SpOffset(e)]) => Ok(vec![0xF8, ctx.imm8(e)?]),
+        ("ld", [R16(r), Imm(e)]) => with16(0x01 | r16_index(r, false).ok_or_else(bad)? << 4, e),
+        ("ld", [MemBc, R8(7)]) => Ok(vec![0x02]),
+        ("ld", [MemDe, R8(7)]) => Ok(vec![0x12]),
+        ("ld", [MemHli, R8(7)]) => Ok(vec![0x22]),
+        ("ld", [MemHld, R8(7)]) => Ok(vec![0x32]),
+        ("ld", [R8(7), MemBc]) => Ok(vec![0x0A]),
+        ("ld", [R8(7), MemDe]) => Ok(vec![0x1A]),
+        ("ld", [R8(7), MemHli]) => Ok(vec![0x2A]),
+        ("ld", [R8(7), MemHld]) => Ok(vec![0x3A]),
+        ("ld", [Mem(e), R8(7)]) => with16(0xEA, e),
+        ("ld", [R8(7), Mem(e)]) => with16(0xFA, e),
+        ("ld", [Mem(e), R16("sp")]) => with16(0x08, e),
+        _ if m.chars().all(|c| c.is_ascii_alphabetic()) && !matches!(m, "ld" | "ldh" | "jp" | "jr" | "call" | "ret" | "rst" | "inc" | "dec" | "push" | "pop")
+            => Err(format!("unknown mnemonic {m:?}")),
+        _ => Err(bad()),
+    }
+}
@@ -0,0 +1,75 @@
+//! audio_features — lightweight per-frame audio descriptors for training records
+//!
+//! Multimodal models get an audio signal without storing raw PCM: the RMS of
+//! the frame's mixed output, which channels were (re)triggered since the last
+//! capture (note/SFX onsets), and the pitch of the active square channels.
+//! `audio_hash` fingerprints the exact output for regression checks.
+
+use crate::Apu;
+use std::fmt::Write;
+
+/// Channel bits used in `AudioFeatures::triggers` and `Apu::triggers`
+pub const TRIG_SQ1: u8 = 0x01;
+pub const TRIG_SQ2: u8 = 0x02;
+pub const TRIG_WAVE: u8 = 0x04;
+pub const TRIG_NOISE: u8 = 0x08;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioFeatures {
+    /// RMS of the frame's mixed samples, normalized to 0.0..=1.0
+    pub rms: f32,
+    /// Channels triggered during the frame (TRIG_* bits)
+    pub triggers: u8,
+    /// Square channel frequencies in Hz (0.0 when the channel is silent)
+    pub sq1_hz: f32, pub sq2_hz: f32,
+}
+
+impl AudioFeatures {
+    /// Extract features for the frame just run. Call before `drain_samples()`;
+    /// consumes the APU's pending trigger flags.
+    pub fn capture(apu: &mut Apu) -> Self {
+        let samples = &apu.sample_buffer;
+        let rms = if samples.is_empty() { 0.0 } else {
+            let sum: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+            ((sum / samples.len() as f64).sqrt() / 32768.0) as f32
+        };
+        let hz = |enabled: bool, nr3: u8, nr4: u8| {
+            let freq = ((nr4 as u32 & 0x07) << 8) | nr3 as u32;
+            if enabled { 131_072.0 / (2048 - freq) as f32 } else { 0.0 }
+        };
+        AudioFeatures {
+            rms,
+            triggers: apu.take_triggers(),
+            sq1_hz: hz(apu.sq1.enabled, apu.sq1.nr3, apu.sq1.nr4),
+            sq2_hz: hz(apu.sq2.enabled, apu.sq2.nr3, apu.sq2.nr4),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+
+    /// Append `to_json()` to `out`
+    pub fn write_json(&self, out: &mut String) {
+        let t = |bit: u8| (self.triggers & bit != 0) as u8;
+        let _ = write!(out,
+            "{{\"rms\":{:.5},\"trig\":[{},{},{},{}],\"sq1_hz\":{:.1},\"sq2_hz\":{:.1}}}",
+            self.rms, t(TRIG_SQ1), t(TRIG_SQ2), t(TRIG_WAVE), t(TRIG_NOISE), self.sq1_hz, self.sq2_hz
+        );
+    }
+}
+
+/// FNV-1a hash of a frame's mixed stereo output (`Apu::sample_buffer`, at
+/// `Apu::sample_rate()`). Output is deterministic, so any change in what the APU
+/// produces changes the hash even when the picture does not. Call before
+/// `drain_samples()`, once per frame.
+pub fn audio_hash(samples: &[i16]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in samples.iter().flat_map(|s| s.to_le_bytes()) {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h
+}
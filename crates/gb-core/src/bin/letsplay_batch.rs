@@ -4,15 +4,69 @@
 //! .mrom.train.json per ROM. Every ROM that runs becomes a training file.
 //!
 //! Usage:
-//!   cargo run --bin letsplay_batch -- <roms_dir> <output_dir> [frames_per_rom]
+//!   cargo run --bin letsplay_batch -- <roms_dir> <output_dir> [frames_per_rom] [--phash] [--audio-hash] [--ram-console=BASE:LEN:HEAD] [--rom-timeout=SECS] [--io-diffs] [--exec-coverage] [--sprites] [--raster] [--text[=FILE]] [--accuracy=fast|balanced|cycle] [--block-cache] [--fast-halt] [--open-bus=ff|last|cgb] [--queue=FILE [--queue-idle=SECS]]
+//!   cargo run --bin letsplay_batch -- --queue=FILE --enqueue=ROM... [--job-priority=N] [--job-frames=N] [--job-retries=N] [--job-retry=never|transient|always]
+//!   cargo run --bin letsplay_batch -- --queue=FILE --dequeue=ROM...
 //!
-//! Output:
-//!   <output_dir>/<rom_filename>.mrom.train.json  — one per ROM
-//!   <output_dir>/batch_manifest.json             — summary of all runs
+//! --phash adds a 64-bit perceptual hash of each frame ("phash", hex).
+//! --audio-hash adds a hash of each frame's audio output ("audio_hash", hex)
+//! to catch audio regressions the picture does not show.
+//! --io-diffs writes mrom.train.v2 with per-frame IO/HRAM changes
+//! ("io_diff" / "hram_diff": [[address, value], ...]).
+//! --sprites adds each frame's visible sprites as object labels
+//! ("sprites": [{"i", "x", "y", "w", "h", "tile", "pal", ...}], `sprites.rs`).
+//! --raster adds the scroll and window registers each line was drawn with,
+//! as runs of equal lines ("raster": [[ly, scx, scy, wx, wy, window,
+//! mid_line], ...], `raster.rs`); empty until the first full frame.
+//! --text adds the on-screen text of games with a glyph table, built in or
+//! from FILE ("text": [{"layer", "col", "row", "text"}], `text.rs`).
+//! --ram-console also captures a RAM ring-buffer console (hex addresses).
+//! --rom-timeout is the per-ROM wall-clock watchdog (default 120 s).
+//! --exec-coverage marks every executed ROM / RAM byte and writes the touched
+//! code ranges per ROM; the manifest gets "coverage" (bytes, share of the ROM
+//! and the last frame that reached new code — far behind "frames" means the
+//! ROM sat in a loop).
+//! --accuracy sets the block cache, fast halt, OAM DMA bus lock and open-bus
+//! policy from one profile (`accuracy.rs`); `fast` is the batch setting. The
+//! three flags below override it.
+//! --block-cache runs each ROM with the basic-block cache (`block_cache.rs`):
+//! same results, fewer bus fetches per instruction.
+//! --fast-halt lets a halted CPU jump to the next timer / PPU / APU event
+//! (`GbCore::fast_halt`): same results, far fewer steps for idle ROMs.
+//! --open-bus picks what FEA0-FEFF and unmapped IO read as (`open_bus.rs`):
+//! 0xFF (default), the last data bus value, or CGB revision E behaviour.
+//! --metrics-file=PATH rewrites a Prometheus textfile after every ROM;
+//! --metrics-push=HOST:PORT pushes the same metrics to a Pushgateway
+//! (job "letsplay_batch"). Both cover frames, fps, bytes written, watchdog
+//! trips, panics and per-ROM outcomes (`metrics.rs`).
+//!
+//! --queue runs the jobs of a persistent queue file (mrom.queue.v1,
+//! `scheduler.rs`) instead of one directory pass: ROMs in <roms_dir> that are
+//! not queued yet are added, then jobs run by priority with their own frame
+//! budgets and retry policies until none is pending. The file is re-read
+//! before every job, so --enqueue / --dequeue from another shell (or any
+//! process using `JobQueue::update`) change a run in progress. --queue-idle
+//! keeps waiting that long for new jobs once the queue runs dry (default 0).
+//! --enqueue adds ROMs (re-adding a finished one runs it again) with the
+//! --job-* settings, --dequeue removes them; both exit without running.
+//!
+//! A ROM that panics the core or trips the watchdog is recorded as failed in
+//! the manifest (panic message, location and backtrace hash) and the batch
+//! carries on with the next ROM. With --queue the manifest has one entry per
+//! attempt.
+//!
+//! Output (layout from `artifacts.rs`):
+//!   <output_dir>/<rom_hash>/train.json     — one per ROM
+//!   <output_dir>/<rom_hash>/console.txt    — serial/RAM console text, when the ROM printed any
+//!   <output_dir>/<rom_hash>/exec_coverage.json — executed-code ranges, with --exec-coverage
+//!   <output_dir>/<rom_hash>/manifest.json  — ROM identity and files written
+//!   <output_dir>/<rom_hash>/session.json   — mrom.session.v1: config and checksummed outputs of the run
+//!   <output_dir>/batch_manifest.json       — summary of all runs
 
-use gb_core::{Cartridge, GbCore, CYCLES_PER_FRAME};
+use gb_core::{audio_hash, catch_run, phash, rom_hash, screen_text, sha256_hex, screen_text_json, visible_sprites_into, write_raster_json, write_sprites_json, AccuracyProfile, AudioFeatures, ExecCoverage, GlyphTables, JobOutcome, JobQueue, JobStatus, QueueJob, RetryPolicy, MetricKind, Metrics, OpenBusPolicy, Cartridge, GbCore, RamConsole, RegDiff, RegDiffTracker, RomArtifacts, RunDeadline, RunPanic, SessionManifest, SessionRole, VisibleSprite, METRIC_BYTES_WRITTEN, METRIC_FPS, METRIC_FRAMES, METRIC_WATCHDOG_TRIPS};
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 fn fnv1a(data: &[u8]) -> u32 {
     let mut h: u32 = 0x811c9dc5;
@@ -26,125 +80,286 @@ fn epoch_for_cgb(is_cgb: bool) -> &'static str {
 
 #[derive(Debug)]
 struct RomResult {
+    #[allow(dead_code)]
     path: String,
     title: String,
+    rom_hash: String,
     mbc_kind: String,
     epoch: &'static str,
     frames: u64,
+    #[allow(dead_code)]
     cycles: u64,
     output_path: String,
     elapsed_ms: u128,
     error: Option<String>,
+    panic: Option<RunPanic>,
+    bytes_written: u64,
+    watchdog: bool,
+    /// bytes, rom_fraction, last_new_frame (--exec-coverage)
+    coverage: Option<(usize, f64, u64)>,
+}
+
+/// Optional per-ROM outputs (and core options) chosen on the command line
+#[derive(Debug, Clone, Copy)]
+struct Capture<'a> {
+    phash: bool,
+    audio_hash: bool,
+    io_diffs: bool,
+    exec_coverage: bool,
+    sprites: bool,
+    raster: bool,
+    text: Option<&'a GlyphTables>,
+    block_cache: bool,
+    fast_halt: bool,
+    accuracy: Option<AccuracyProfile>,
+    open_bus: Option<OpenBusPolicy>,
+}
+
+/// Typical size of one frame record, for sizing the output buffer
+const RECORD_BYTES: usize = 512;
+
+const METRIC_ROMS: &str = "mrom_roms_total";
+const METRIC_ROMS_FAILED: &str = "mrom_roms_failed_total";
+const METRIC_PANICS: &str = "mrom_panics_total";
+
+/// Whether a failed run may succeed if tried again (`RetryPolicy::Transient`)
+fn job_outcome(r: &RomResult) -> JobOutcome {
+    match &r.error {
+        None => JobOutcome::Done,
+        Some(e) if r.watchdog || e.starts_with("read error") || e.starts_with("write error") => JobOutcome::Transient(e.clone()),
+        Some(e) => JobOutcome::Failed(e.clone()),
+    }
+}
+
+/// Settings of jobs added with --enqueue (and of ROMs seeded from <roms_dir>)
+fn job_template() -> QueueJob {
+    fn arg<T: std::str::FromStr>(flag: &str) -> Option<T> {
+        std::env::args().find_map(|a| a.strip_prefix(flag).map(|s| s.parse().unwrap_or_else(|_| {
+            eprintln!("Bad {}{s}", flag); std::process::exit(1);
+        })))
+    }
+    let mut job = QueueJob::new("");
+    if let Some(p) = arg("--job-priority=") { job.priority = p; }
+    job.frames = arg("--job-frames=");
+    if let Some(r) = arg("--job-retries=") { job.retries = r; }
+    if let Some(r) = std::env::args().find_map(|a| a.strip_prefix("--job-retry=").map(str::to_string)) {
+        job.retry = RetryPolicy::parse(&r).unwrap_or_else(|| { eprintln!("Bad --job-retry: {r} (never, transient or always)"); std::process::exit(1); });
+    }
+    job
 }
 
-fn process_rom(rom_path: &Path, output_dir: &Path, frames: u64) -> RomResult {
+fn queue_or_exit<R>(path: &Path, f: impl FnOnce(&mut JobQueue) -> R) -> R {
+    JobQueue::update(path, f).unwrap_or_else(|e| { eprintln!("queue {}: {e}", path.display()); std::process::exit(1); })
+}
+
+fn process_rom(rom_path: &Path, output_dir: &Path, frames: u64, capture: Capture<'_>, ram_console: Option<RamConsole>, budget: Duration) -> RomResult {
     let start = Instant::now();
     let stem = rom_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
-    let out_name = format!("{}.mrom.train.json", stem);
-    let out_path = output_dir.join(&out_name);
 
     let rom_bytes = match std::fs::read(rom_path) {
         Ok(b) => b, Err(e) => return RomResult {
-            path: rom_path.to_string_lossy().to_string(), title: stem.clone(),
+            path: rom_path.to_string_lossy().to_string(), title: stem.clone(), rom_hash: String::new(),
             mbc_kind: "?".into(), epoch: "unknown", frames: 0, cycles: 0,
-            output_path: out_path.to_string_lossy().to_string(),
+            output_path: String::new(),
             elapsed_ms: start.elapsed().as_millis(),
-            error: Some(format!("read error: {e}")),
+            error: Some(format!("read error: {e}")), panic: None, bytes_written: 0, watchdog: false, coverage: None,
         }
     };
+    let artifacts = RomArtifacts::new(output_dir, &rom_bytes);
+    let rom_hash = artifacts.rom_hash.clone();
+    let out_path = artifacts.train();
 
     let cart = match Cartridge::from_bytes(rom_bytes) {
         Ok(c) => c, Err(e) => return RomResult {
-            path: rom_path.to_string_lossy().to_string(), title: stem.clone(),
+            path: rom_path.to_string_lossy().to_string(), title: stem.clone(), rom_hash,
             mbc_kind: "?".into(), epoch: "unknown", frames: 0, cycles: 0,
             output_path: out_path.to_string_lossy().to_string(),
             elapsed_ms: start.elapsed().as_millis(),
-            error: Some(format!("cart error: {e}")),
+            error: Some(format!("cart error: {e}")), panic: None, bytes_written: 0, watchdog: false, coverage: None,
         }
     };
 
     let title    = cart.title.clone();
     let mbc_kind = format!("{:?}", cart.kind);
     let epoch    = epoch_for_cgb(cart.is_cgb);
-    let rom_sha  = format!("{:08x}", fnv1a(&cart.rom));
+    let rom_sha  = sha256_hex(&cart.rom);
 
     let mut core = GbCore::new(cart);
-    let mut records: Vec<String> = Vec::with_capacity(frames as usize);
-    let mut vblank_count: u64 = 0;
+    core.set_ram_console(ram_console);
+    if capture.raster { core.bus.raster = Some(Box::default()); }
+    if capture.exec_coverage { core.exec_coverage = Some(Box::new(ExecCoverage::for_bus(&core.bus))); }
+    if let Some(profile) = capture.accuracy { core.set_accuracy(profile); }
+    if capture.block_cache { core.bus.block_cache = Some(Box::default()); }
+    if capture.fast_halt { core.fast_halt = true; }
+    if let Some(policy) = capture.open_bus { core.bus.open_bus = policy; }
+    let deadline = RunDeadline::arm(core.interrupt_handle(), budget);
+    // Records are written straight into one buffer, and the per-frame sprite
+    // list and register diff reuse theirs: no allocation per frame
+    let mut frames_json = String::with_capacity(frames as usize * RECORD_BYTES);
+    let mut frames_done: u64 = 0;
+    let mut reg_diffs = capture.io_diffs.then(|| RegDiffTracker::new(&core.bus));
+    let mut reg_diff = RegDiff::default();
+    let mut sprites: Vec<VisibleSprite> = Vec::with_capacity(40);
+    let glyphs = capture.text.and_then(|t| t.for_core(&core)).cloned();
 
     for frame in 0..frames {
         if core.run_frame().is_err() { break; }
-        vblank_count += 1;
-        let wh = fnv1a(&core.bus.wram);
-        let vh = fnv1a(&core.bus.vram);
+        let wh = fnv1a(core.bus.wram.as_flattened());
+        let vh = fnv1a(core.bus.vram.as_flattened());
         let oh = fnv1a(&core.bus.oam);
         let samp = core.bus.apu.sample_buffer.len() / 2;
-        let _ = core.bus.apu.drain_samples();
+        let audio = AudioFeatures::capture(&mut core.bus.apu);
 
-        records.push(format!(
+        if frame > 0 { frames_json.push_str(",\n  "); }
+        let out = &mut frames_json;
+        let _ = write!(out,
             concat!(
                 "{{\"frame\":{},\"t_cycles\":{},\"pc\":{},\"sp\":{},",
                 "\"a\":{},\"f\":{},\"bc\":{},\"de\":{},\"hl\":{},",
                 "\"ly\":{},\"lcdc\":{},\"ppu_mode\":{},",
-                "\"sq1\":{},\"sq2\":{},\"wave\":{},\"noise\":{},\"samples\":{},",
-                "\"rom_bank\":{},\"ram_bank\":{},",
-                "\"wh\":{},\"vh\":{},\"oh\":{}}}"
+                "\"sq1\":{},\"sq2\":{},\"wave\":{},\"noise\":{},\"samples\":{},\"audio\":"
             ),
             frame, core.clock.t_cycles, core.regs.pc, core.regs.sp,
             core.regs.a, core.regs.f,
             core.regs.bc(), core.regs.de(), core.regs.hl(),
             core.bus.ppu.ly, core.bus.ppu.lcdc, core.bus.ppu.mode as u8,
             core.bus.apu.sq1.enabled as u8, core.bus.apu.sq2.enabled as u8,
-            core.bus.apu.wave.enabled as u8, core.bus.apu.noise.enabled as u8, samp,
-            core.bus.mbc.rom_bank, core.bus.mbc.ram_bank,
-            wh, vh, oh
-        ));
+            core.bus.apu.wave.enabled as u8, core.bus.apu.noise.enabled as u8, samp
+        );
+        audio.write_json(out);
+        out.push(',');
+        if capture.phash { let _ = write!(out, "\"phash\":\"{:016x}\",", phash(&core.bus.ppu.framebuffer)); }
+        if capture.audio_hash { let _ = write!(out, "\"audio_hash\":\"{:016x}\",", audio_hash(&core.bus.apu.sample_buffer)); }
+        if let Some(tracker) = reg_diffs.as_mut() {
+            tracker.frame_diff_into(&core.bus, &mut reg_diff);
+            reg_diff.write_json_fields(out);
+        }
+        if capture.sprites {
+            visible_sprites_into(&core.bus, &mut sprites);
+            out.push_str("\"sprites\":");
+            write_sprites_json(out, &sprites);
+            out.push(',');
+        }
+        if let Some(r) = core.bus.raster.as_ref() {
+            out.push_str("\"raster\":");
+            write_raster_json(out, r.last_frame());
+            out.push(',');
+        }
+        if let Some(g) = glyphs.as_ref() { let _ = write!(out, "\"text\":{},", screen_text_json(&screen_text(&core.bus, g))); }
+        core.bus.apu.clear_samples();
+        let _ = write!(out, "\"rom_bank\":{},\"ram_bank\":{},\"wh\":{},\"vh\":{},\"oh\":{}}}",
+                       core.bus.mbc.rom_bank, core.bus.mbc.ram_bank, wh, vh, oh);
+        frames_done += 1;
     }
 
+    let watchdog = deadline.fired().then(|| format!("watchdog: exceeded {}s after {} frames", budget.as_secs(), frames_done));
     let total_cycles = core.clock.t_cycles;
-    let frames_done = records.len() as u64;
-    let frames_json = records.join(",\n  ");
+    let coverage = core.exec_coverage.as_ref().map(|c| (c.bytes(), c.rom_fraction(), c.last_new_frame()));
 
     let json = format!(
-        "{{\n  \"version\": \"mrom.train.v1\",\n  \"rom_title\": \"{}\",\n  \"rom_sha\": \"{}\",\n  \"mbc_kind\": \"{}\",\n  \"epoch\": \"{}\",\n  \"total_frames\": {},\n  \"total_cycles\": {},\n  \"frames\": [\n  {}\n  ]\n}}",
-        title, rom_sha, mbc_kind, epoch, frames_done, total_cycles, frames_json
+        "{{\n  \"version\": \"{}\",\n  \"rom_title\": \"{}\",\n  \"rom_sha\": \"{}\",\n  \"rom_hash\": \"{}\",\n  \"mbc_kind\": \"{}\",\n  \"epoch\": \"{}\",\n  \"total_frames\": {},\n  \"total_cycles\": {},\n  \"frames\": [\n  {}\n  ]\n}}",
+        if capture.io_diffs { "mrom.train.v2" } else { "mrom.train.v1" },
+        title, rom_sha, rom_hash, mbc_kind, epoch, frames_done, total_cycles, frames_json
     );
 
-    if let Err(e) = std::fs::write(&out_path, &json) {
-        return RomResult {
-            path: rom_path.to_string_lossy().to_string(), title, mbc_kind, epoch,
+    let written = artifacts.create().and_then(|_| {
+        let console = core.console_text();
+        if !console.is_empty() { std::fs::write(artifacts.console(), &console)?; }
+        std::fs::write(&out_path, &json)?;
+        let exec_json = core.exec_coverage.as_ref().map(|c| c.to_json());
+        if let Some(j) = &exec_json { std::fs::write(artifacts.exec_coverage(), j)?; }
+        let mut session = SessionManifest::new(&artifacts, &title, &rom_path.to_string_lossy(), &core.config);
+        session.frames = frames_done;
+        session.add(SessionRole::Training, &out_path)?;
+        if !console.is_empty() { session.add(SessionRole::Console, &artifacts.console())?; }
+        if exec_json.is_some() { session.add(SessionRole::ExecCoverage, &artifacts.exec_coverage())?; }
+        let session = session.write()?;
+        let manifest = artifacts.write_manifest(&title, &rom_path.to_string_lossy())?;
+        Ok((console.len() + json.len() + exec_json.map_or(0, |j| j.len())) as u64 + std::fs::metadata(session)?.len() + std::fs::metadata(manifest)?.len())
+    });
+    let bytes_written = match written {
+        Ok(n) => n,
+        Err(e) => return RomResult {
+            path: rom_path.to_string_lossy().to_string(), title, rom_hash, mbc_kind, epoch,
             frames: frames_done, cycles: total_cycles,
             output_path: out_path.to_string_lossy().to_string(),
             elapsed_ms: start.elapsed().as_millis(),
-            error: Some(format!("write error: {e}")),
-        };
-    }
+            error: Some(format!("write error: {e}")), panic: None, bytes_written: 0, watchdog: watchdog.is_some(), coverage,
+        },
+    };
 
     RomResult {
-        path: rom_path.to_string_lossy().to_string(), title, mbc_kind, epoch,
+        path: rom_path.to_string_lossy().to_string(), title, rom_hash, mbc_kind, epoch,
         frames: frames_done, cycles: total_cycles,
         output_path: out_path.to_string_lossy().to_string(),
         elapsed_ms: start.elapsed().as_millis(),
-        error: None,
+        watchdog: watchdog.is_some(), error: watchdog, panic: None, bytes_written, coverage,
     }
 }
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    let roms_dir    = args.get(1).map(|s| PathBuf::from(s)).unwrap_or_else(|| PathBuf::from("roms"));
-    let output_dir  = args.get(2).map(|s| PathBuf::from(s)).unwrap_or_else(|| PathBuf::from("training_output"));
+    let text = std::env::args().find(|a| a.starts_with("--text")).map(|a| {
+        GlyphTables::builtin_with(a.strip_prefix("--text=").map(Path::new))
+            .unwrap_or_else(|e| { eprintln!("Bad --text: {e}"); std::process::exit(1); })
+    });
+    let capture = Capture {
+        phash: std::env::args().any(|a| a == "--phash"),
+        audio_hash: std::env::args().any(|a| a == "--audio-hash"),
+        io_diffs: std::env::args().any(|a| a == "--io-diffs"),
+        exec_coverage: std::env::args().any(|a| a == "--exec-coverage"),
+        sprites: std::env::args().any(|a| a == "--sprites"),
+        raster: std::env::args().any(|a| a == "--raster"),
+        text: text.as_ref(),
+        accuracy: std::env::args().find_map(|a| a.strip_prefix("--accuracy=").map(|s| AccuracyProfile::parse(s).unwrap_or_else(|| {
+            eprintln!("Bad --accuracy: {s} (fast, balanced or cycle)"); std::process::exit(1);
+        }))),
+        block_cache: std::env::args().any(|a| a == "--block-cache"),
+        fast_halt: std::env::args().any(|a| a == "--fast-halt"),
+        open_bus: std::env::args().find_map(|a| a.strip_prefix("--open-bus=").map(|s| OpenBusPolicy::parse(s).unwrap_or_else(|| {
+            eprintln!("Bad --open-bus: {s} (ff, last or cgb)"); std::process::exit(1);
+        }))),
+    };
+    let ram_console = std::env::args().find_map(|a| a.strip_prefix("--ram-console=").and_then(RamConsole::parse));
+    let budget = Duration::from_secs(std::env::args().find_map(|a| a.strip_prefix("--rom-timeout=").and_then(|s| s.parse().ok())).unwrap_or(120));
+    let metrics_file = std::env::args().find_map(|a| a.strip_prefix("--metrics-file=").map(PathBuf::from));
+    let metrics_push = std::env::args().find_map(|a| a.strip_prefix("--metrics-push=").map(str::to_string));
+    let queue = std::env::args().find_map(|a| a.strip_prefix("--queue=").map(PathBuf::from));
+    let queue_idle = Duration::from_secs(std::env::args().find_map(|a| a.strip_prefix("--queue-idle=").and_then(|s| s.parse().ok())).unwrap_or(0));
+    let enqueue: Vec<String> = std::env::args().filter_map(|a| a.strip_prefix("--enqueue=").map(str::to_string)).collect();
+    let dequeue: Vec<String> = std::env::args().filter_map(|a| a.strip_prefix("--dequeue=").map(str::to_string)).collect();
+    if !enqueue.is_empty() || !dequeue.is_empty() {
+        let Some(queue) = &queue else { eprintln!("--enqueue / --dequeue need --queue=FILE"); std::process::exit(1); };
+        let template = job_template();
+        let pending = queue_or_exit(queue, |q| {
+            for rom in &enqueue { q.add(QueueJob { rom: rom.clone(), ..template.clone() }); }
+            for rom in &dequeue {
+                if !q.remove(rom) { eprintln!("{rom}: not queued"); }
+            }
+            q.pending()
+        });
+        println!("{}: {pending} job(s) pending", queue.display());
+        return;
+    }
+    let args: Vec<String> = std::env::args().filter(|a| !a.starts_with("--")).collect();
+    let roms_dir    = args.get(1).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("roms"));
+    let output_dir  = args.get(2).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("training_output"));
     let frames: u64 = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(300); // 5 seconds at 60fps
 
     println!("MetaROM Batch Training Runner");
     println!("  roms_dir:   {}", roms_dir.display());
     println!("  output_dir: {}", output_dir.display());
     println!("  frames/ROM: {}", frames);
+    if let Some(q) = &queue { println!("  queue:      {}", q.display()); }
 
     std::fs::create_dir_all(&output_dir).expect("Cannot create output dir");
 
-    // Collect ROM files
-    let rom_files: Vec<PathBuf> = std::fs::read_dir(&roms_dir)
-        .expect("Cannot read roms dir")
+    // Collect ROM files (a queue run may have no directory)
+    let dir = match std::fs::read_dir(&roms_dir) {
+        Ok(d) => Some(d),
+        Err(_) if queue.is_some() => None,
+        Err(e) => panic!("Cannot read roms dir: {e:?}"),
+    };
+    let rom_files: Vec<PathBuf> = dir.into_iter().flatten()
         .filter_map(|e| e.ok())
         .map(|e| e.path())
         .filter(|p| {
@@ -153,7 +368,7 @@ fn main() {
         })
         .collect();
 
-    if rom_files.is_empty() {
+    if rom_files.is_empty() && queue.is_none() {
         // No ROMs? Run the synthetic built-in ROM as smoke test
         println!("No ROMs found in {}. Running synthetic EVEZ-OS-TRAIN ROM...", roms_dir.display());
         // synthetic_rom path handled by letsplay_train; here we just report
@@ -164,30 +379,98 @@ fn main() {
 
     println!("Found {} ROM file(s). Processing...\n", rom_files.len());
 
-    let mut results: Vec<RomResult> = Vec::new();
-    for (i, path) in rom_files.iter().enumerate() {
-        print!("[{}/{}] {} ... ", i+1, rom_files.len(), path.file_name().unwrap_or_default().to_string_lossy());
-        let r = process_rom(path, &output_dir, frames);
+    let metrics = Metrics::new().with_label("job", "letsplay_batch");
+    metrics.describe(METRIC_ROMS, MetricKind::Counter, "ROMs processed");
+    metrics.describe(METRIC_ROMS_FAILED, MetricKind::Counter, "ROMs that failed (read, cart, write, panic or watchdog)");
+    metrics.describe(METRIC_PANICS, MetricKind::Counter, "ROMs that panicked the core");
+    let export = |metrics: &Metrics| {
+        if let Some(path) = &metrics_file {
+            metrics.write_textfile(path).unwrap_or_else(|e| eprintln!("metrics file {}: {e}", path.display()));
+        }
+        if let Some(gw) = &metrics_push {
+            metrics.push(gw, "letsplay_batch").unwrap_or_else(|e| eprintln!("metrics push: {e}"));
+        }
+    };
+
+    let run_rom = |label: &str, path: &Path, frames: u64| {
+        print!("{label} {} ... ", path.file_name().unwrap_or_default().to_string_lossy());
+        let start = Instant::now();
+        let r = catch_run(|| process_rom(path, &output_dir, frames, capture, ram_console, budget)).unwrap_or_else(|p| RomResult {
+            path: path.to_string_lossy().to_string(),
+            title: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            rom_hash: std::fs::read(path).map(|b| rom_hash(&b)).unwrap_or_default(),
+            mbc_kind: "?".into(), epoch: "unknown", frames: 0, cycles: 0, output_path: String::new(),
+            elapsed_ms: start.elapsed().as_millis(),
+            error: Some(format!("panic: {} at {}", p.message, p.location)), panic: Some(p),
+            bytes_written: 0, watchdog: false, coverage: None,
+        });
         match &r.error {
             None    => println!("OK ({} frames, {}ms) → {}", r.frames, r.elapsed_ms, r.output_path),
             Some(e) => println!("FAILED: {e}"),
         }
-        results.push(r);
+        metrics.inc(METRIC_ROMS);
+        metrics.add(METRIC_FRAMES, r.frames as f64);
+        metrics.add(METRIC_BYTES_WRITTEN, r.bytes_written as f64);
+        if r.elapsed_ms > 0 { metrics.set(METRIC_FPS, r.frames as f64 * 1000.0 / r.elapsed_ms as f64); }
+        if r.error.is_some() { metrics.inc(METRIC_ROMS_FAILED); }
+        if r.panic.is_some() { metrics.inc(METRIC_PANICS); }
+        if r.watchdog { metrics.inc(METRIC_WATCHDOG_TRIPS); }
+        export(&metrics);
+        r
+    };
+
+    let mut results: Vec<RomResult> = Vec::new();
+    match &queue {
+        None => for (i, path) in rom_files.iter().enumerate() {
+            results.push(run_rom(&format!("[{}/{}]", i + 1, rom_files.len()), path, frames));
+        },
+        Some(queue) => {
+            let template = job_template();
+            let requeued = queue_or_exit(queue, |q| {
+                for path in &rom_files {
+                    let rom = path.to_string_lossy();
+                    if q.job(&rom).is_none() { q.add(QueueJob { rom: rom.to_string(), ..template.clone() }); }
+                }
+                q.requeue_running()
+            });
+            if requeued > 0 { println!("{requeued} job(s) left running by an interrupted run are pending again"); }
+            let mut idle_since = Instant::now();
+            loop {
+                let Some((job, pending)) = queue_or_exit(queue, |q| q.claim_next().map(|j| (j, q.pending()))) else {
+                    if idle_since.elapsed() >= queue_idle { break; }
+                    std::thread::sleep(Duration::from_secs(1));
+                    continue;
+                };
+                let r = run_rom(&format!("[{pending} pending, p{}]", job.priority), Path::new(&job.rom), job.frames.unwrap_or(frames));
+                if queue_or_exit(queue, |q| q.finish(&job.rom, job_outcome(&r))) == Some(JobStatus::Pending) {
+                    println!("  will retry (attempt {} of {})", job.attempts + 1, job.retries + 1);
+                }
+                results.push(r);
+                idle_since = Instant::now();
+            }
+        }
     }
 
     // Write manifest
     let ok_count   = results.iter().filter(|r| r.error.is_none()).count();
     let fail_count = results.len() - ok_count;
+    let panic_count = results.iter().filter(|r| r.panic.is_some()).count();
     let total_frames: u64 = results.iter().map(|r| r.frames).sum();
 
-    let manifest_entries: Vec<String> = results.iter().map(|r| format!(
-        "  {{\"title\":\"{}\",\"epoch\":\"{}\",\"mbc\":\"{}\",\"frames\":{},\"ok\":{},\"path\":\"{}\"}}",
-        r.title, r.epoch, r.mbc_kind, r.frames, r.error.is_none(), r.output_path
-    )).collect();
+    let manifest_entries: Vec<String> = results.iter().map(|r| {
+        let error = r.error.as_ref().map_or("null".into(), |e| format!("\"{}\"", e.replace('\\', "\\\\").replace('"', "\\\"")));
+        let panic = r.panic.as_ref().map_or(String::new(), |p| format!(",\"panic\":{}", p.to_json()));
+        let coverage = r.coverage.map_or(String::new(), |(bytes, frac, last)|
+            format!(",\"coverage\":{{\"bytes\":{bytes},\"rom_fraction\":{frac:.4},\"last_new_frame\":{last}}}"));
+        format!(
+            "  {{\"title\":\"{}\",\"rom_hash\":\"{}\",\"epoch\":\"{}\",\"mbc\":\"{}\",\"frames\":{},\"ok\":{},\"path\":\"{}\",\"error\":{}{}{}}}",
+            r.title, r.rom_hash, r.epoch, r.mbc_kind, r.frames, r.error.is_none(), r.output_path, error, panic, coverage
+        )
+    }).collect();
 
     let manifest = format!(
-        "{{\n  \"total_roms\":{},\"ok\":{},\"failed\":{},\"total_frames\":{},\n  \"roms\":[\n{}\n  ]\n}}",
-        results.len(), ok_count, fail_count, total_frames,
+        "{{\n  \"total_roms\":{},\"ok\":{},\"failed\":{},\"panicked\":{},\"total_frames\":{},\n  \"roms\":[\n{}\n  ]\n}}",
+        results.len(), ok_count, fail_count, panic_count, total_frames,
         manifest_entries.join(",\n")
     );
 
@@ -198,6 +481,7 @@ fn main() {
     println!("  ROMs processed: {}", results.len());
     println!("  Succeeded:      {}", ok_count);
     println!("  Failed:         {}", fail_count);
+    println!("  Panicked:       {}", panic_count);
     println!("  Total frames:   {}", total_frames);
     println!("  Manifest:       {}", manifest_path.display());
     println!("\nEvery ROM that ran is now a training file.");
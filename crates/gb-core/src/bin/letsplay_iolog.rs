@@ -0,0 +1,50 @@
+//! letsplay_iolog — export an IO register write log to CSV
+//! Reads the io_writes.mriolog a `letsplay_live --io-log` run left in the
+//! ROM's artifact directory and writes one CSV row per write.
+//!
+//! Usage:
+//!   cargo run --bin letsplay_iolog -- <io_writes.mriolog> [out.csv] [--reg=LCDC,SCX,FF26]
+//!
+//! Without out.csv the CSV goes to stdout. --reg keeps only the listed
+//! registers, by name or by hex address.
+
+use gb_core::{io_reg_name, IoWriteLog};
+use std::path::Path;
+
+fn parse_reg(s: &str) -> Option<u8> {
+    let s = s.trim();
+    if let Some(reg) = (0..=0xFFu8).find(|&r| !io_reg_name(r).is_empty() && io_reg_name(r).eq_ignore_ascii_case(s)) {
+        return Some(reg);
+    }
+    let hex = s.trim_start_matches("0x").trim_start_matches('$');
+    match u16::from_str_radix(hex, 16).ok()? {
+        0xFFFF => Some(0xFF),
+        a @ 0xFF00..=0xFF7F => Some(a as u8),
+        _ => None,
+    }
+}
+
+fn main() {
+    let regs = std::env::args().find_map(|a| a.strip_prefix("--reg=").map(str::to_string)).map(|list| {
+        list.split(',').map(|r| parse_reg(r).unwrap_or_else(|| { eprintln!("Unknown IO register: {r}"); std::process::exit(2); }))
+            .collect::<Vec<u8>>()
+    });
+    let args: Vec<String> = std::env::args().filter(|a| !a.starts_with("--")).collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <io_writes.mriolog> [out.csv] [--reg=LCDC,SCX,FF26]", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut log = IoWriteLog::load(Path::new(&args[1])).unwrap_or_else(|e| {
+        eprintln!("Cannot read {}: {e}", args[1]); std::process::exit(1);
+    });
+    if let Some(regs) = regs { log.retain(|w| regs.contains(&w.reg)); }
+    let csv = log.to_csv();
+    match args.get(2) {
+        Some(out) => {
+            std::fs::write(out, csv).unwrap_or_else(|e| { eprintln!("Cannot write {out}: {e}"); std::process::exit(1); });
+            eprintln!("[letsplay_iolog] {} writes -> {out}", log.len());
+        }
+        None => print!("{csv}"),
+    }
+}
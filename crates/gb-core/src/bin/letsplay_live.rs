@@ -1,16 +1,70 @@
 //! letsplay_live — Live replay runner with frame capture + save state
-//! Usage: letsplay_live <rom_path> <n_frames> [output_dir] [--save-state]
+//! Usage: letsplay_live <rom_path> <n_frames> [output_dir] [--save-state] [--ram-console=BASE:LEN:HEAD] [--plan=FILE] [--io-log] [--palette-pack[=FILE]] [--profile] [--sprites] [--raster] [--text[=FILE]] [--audio-hash] [--ppu-timeline] [--replay-v2[=N]] [--triggers=FILE] [--autosave[=N]] [--sram-journal] [--turbo=X] [--checkpoints=FILE] [--accuracy=fast|balanced|cycle]
 //!
-//! Runs the emulator for N frames, captures mrom.replay.v1 JSON,
+//! Runs the emulator for N frames, captures mrom.replay.v1 (or v2) JSON,
 //! optionally saves state to .mrom.sav, broadcasts mrom.snap.v1 frames to stdout.
+//! Outputs follow the `artifacts.rs` layout under <output_dir>/<rom_hash>/:
+//! replay.json, states/final.mrom.sav, console.txt (if the ROM printed any),
+//! manifest.json and session.json (mrom.session.v1). --plan=FILE records the
+//! CompatibilityPlan the run was made under in the session manifest.
+//! --audit-determinism instead runs the ROM twice under perturbation, compares
+//! per-frame subsystem hashes and exits 1 on divergence.
+//! --checkpoints=FILE instead runs FILE's verification checkpoints
+//! (`checkpoint.rs`), writes checkpoints/checkpoints.json plus the state at
+//! each passed checkpoint, and exits 1 if any failed.
+//! --play runs in real time with keyboard / gamepad input (build with
+//! `--features keyboard,gamepad`); Esc quits, n_frames 0 plays until then.
+//! --mapping=FILE overrides the default `control = button` bindings.
+//! --turbo=X plays at X times realtime (0 for as fast as possible); pacing
+//! and the speed shown in the progress log come from `throttle.rs`.
+//! --accuracy picks the core's accuracy profile (`accuracy.rs`, default
+//! balanced); `cycle` is the one for debugging timing-sensitive code.
+//! --io-log records every IO register write to io_writes.mriolog
+//! (export with letsplay_iolog).
+//! --sprites records each frame's visible sprites in the replay ("spr").
+//! --raster records the scroll and window registers of each line ("rs",
+//! `raster.rs`).
+//! --audio-hash records a hash of each frame's audio output ("ah").
+//! --text records each frame's on-screen text ("txt") when the ROM has a
+//! glyph table, built in or from FILE (`text.rs`).
+//! --replay-v2 writes mrom.replay.v2 (`replay_delta.rs`): a full snapshot
+//! every N frames (default 300), memory change lists in between, and a
+//! perceptual hash ("ph") on every frame.
+//! --triggers=FILE evaluates FILE's watch rules (`triggers.rs`) every frame,
+//! tags the replay frames they fire on ("ev") and writes each capture under
+//! triggers/.
+//! --autosave loads battery.sav into a battery cart's RAM and keeps it
+//! current (`sram_autosave.rs`): on every RAM disable, every N frames while
+//! RAM changes (default 300, 0 for never) and at exit.
+//! --sram-journal makes those flushes append to battery.sav.journal
+//! (`sram_journal.rs`), folded back into battery.sav at exit.
+//! --ppu-timeline writes the last frame's per-line PPU mode timing to
+//! ppu_timeline.json and ppu_timeline.svg (`ppu_timeline.rs`).
+//! --profile writes per-opcode / per-address execution counts, cycles and
+//! host time to profile.json (build with `--features profile`).
+//! --palette-pack colours DMG frames with the built-in per-game palette pack
+//! (`palette_pack.rs`), with FILE's entries laid over it.
+//! With --play the user's settings store (`settings.rs`) supplies the game's
+//! palette, accuracy profile and input map; recorded runs ignore it.
 
-use gb_core::{Cartridge, GbCore, ReplayCapture};
-use std::{env, fs, path::Path, time::Instant};
+use gb_core::{audit_determinism, AccuracyProfile, open_backends, parse_checkpoints, run_checkpoints, Cartridge, CoreConfig, GameSettings, GbCore, GlyphTables, InputBackend, InputEvent, InputMapping, PalettePack, RamConsole, ReplayCapture, RomArtifacts, Throttle, DEFAULT_AUTOSAVE_INTERVAL, DEFAULT_KEYFRAME_INTERVAL, has_battery, SessionManifest, SessionRole, SettingsStore, SramAutosave, WatchTriggers};
+use std::{env, fs, path::Path};
+
+/// Ten minutes of frames
+const PLAY_REPLAY_FRAMES: usize = 36_000;
+
+#[cfg(feature = "profile")]
+fn enable_profiler(core: &mut GbCore) { core.profiler = Some(Box::default()); }
+#[cfg(not(feature = "profile"))]
+fn enable_profiler(_: &mut GbCore) {
+    eprintln!("--profile needs the profiler: rebuild with --features profile");
+    std::process::exit(1);
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 3 {
-        eprintln!("Usage: {} <rom_path> <n_frames> [output_dir] [--save-state] [--broadcast]", args[0]);
+        eprintln!("Usage: {} <rom_path> <n_frames> [output_dir] [--save-state] [--broadcast] [--ram-console=BASE:LEN:HEAD] [--audit-determinism] [--play] [--mapping=FILE] [--plan=FILE] [--io-log] [--palette-pack[=FILE]] [--profile] [--sprites] [--raster] [--text[=FILE]] [--audio-hash] [--ppu-timeline] [--replay-v2[=N]] [--triggers=FILE] [--autosave[=N]] [--sram-journal] [--turbo=X] [--checkpoints=FILE] [--accuracy=fast|balanced|cycle]", args[0]);
         std::process::exit(1);
     }
 
@@ -19,30 +73,168 @@ fn main() {
     let output_dir = if args.len() > 3 && !args[3].starts_with("--") { &args[3] } else { "." };
     let save_state = args.iter().any(|a| a == "--save-state");
     let broadcast  = args.iter().any(|a| a == "--broadcast");
+    let ram_console = args.iter().find_map(|a| a.strip_prefix("--ram-console=")).map(|s| {
+        RamConsole::parse(s).unwrap_or_else(|| { eprintln!("Bad --ram-console (want BASE:LEN:HEAD hex): {s}"); std::process::exit(1); })
+    });
+    let play = args.iter().any(|a| a == "--play");
+    let mapping = args.iter().find_map(|a| a.strip_prefix("--mapping=")).map(|path| {
+        fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|t| InputMapping::parse(&t))
+            .unwrap_or_else(|e| { eprintln!("Bad --mapping {path}: {e}"); std::process::exit(1); })
+    });
+    let plan_path = args.iter().find_map(|a| a.strip_prefix("--plan="));
+    let accuracy = args.iter().find_map(|a| a.strip_prefix("--accuracy=")).map(|s| AccuracyProfile::parse(s).unwrap_or_else(|| {
+        eprintln!("Bad --accuracy: {s} (fast, balanced or cycle)"); std::process::exit(1);
+    }));
+    let turbo = args.iter().find_map(|a| a.strip_prefix("--turbo=")).map(|x| {
+        x.parse::<f64>().ok().filter(|t| *t >= 0.0).unwrap_or_else(|| { eprintln!("Bad --turbo (want a speed multiplier): {x}"); std::process::exit(1); })
+    });
+    let io_log = args.iter().any(|a| a == "--io-log");
+    let profile = args.iter().any(|a| a == "--profile");
+    let sprites = args.iter().any(|a| a == "--sprites");
+    let audio_hash = args.iter().any(|a| a == "--audio-hash");
+    let ppu_timeline = args.iter().any(|a| a == "--ppu-timeline");
+    let raster = args.iter().any(|a| a == "--raster");
+    let keyframes = args.iter().find(|a| a.starts_with("--replay-v2")).map(|a| {
+        a.strip_prefix("--replay-v2=").map_or(Some(DEFAULT_KEYFRAME_INTERVAL), |n| n.parse().ok())
+            .unwrap_or_else(|| { eprintln!("Bad --replay-v2 (want a keyframe interval): {a}"); std::process::exit(1); })
+    });
+    let autosave = args.iter().find(|a| a.starts_with("--autosave")).map(|a| {
+        a.strip_prefix("--autosave=").map_or(Some(DEFAULT_AUTOSAVE_INTERVAL), |n| n.parse().ok())
+            .unwrap_or_else(|| { eprintln!("Bad --autosave (want a frame interval): {a}"); std::process::exit(1); })
+    });
+    let sram_journal = args.iter().any(|a| a == "--sram-journal");
+    let mut triggers = args.iter().find_map(|a| a.strip_prefix("--triggers=")).map(|path| {
+        fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|t| WatchTriggers::parse(&t))
+            .unwrap_or_else(|e| { eprintln!("Bad --triggers {path}: {e}"); std::process::exit(1); })
+    });
+    let text = args.iter().find(|a| a.starts_with("--text")).map(|a| {
+        GlyphTables::builtin_with(a.strip_prefix("--text=").map(Path::new))
+            .unwrap_or_else(|e| { eprintln!("Bad --text: {e}"); std::process::exit(1); })
+    });
+    let palettes = args.iter().find(|a| a.starts_with("--palette-pack")).map(|a| {
+        let mut pack = PalettePack::builtin();
+        if let Some(path) = a.strip_prefix("--palette-pack=") {
+            let user = PalettePack::load(Path::new(path)).unwrap_or_else(|e| { eprintln!("Bad --palette-pack: {e}"); std::process::exit(1); });
+            pack.merge(&user);
+        }
+        pack
+    });
 
     // Load ROM
     let rom_bytes = fs::read(rom_path).unwrap_or_else(|e| {
         eprintln!("Cannot read ROM: {e}"); std::process::exit(1);
     });
+    if args.iter().any(|a| a == "--audit-determinism") {
+        let report = audit_determinism(&rom_bytes, &[], n_frames).unwrap_or_else(|e| {
+            eprintln!("Audit failed: {e}"); std::process::exit(1);
+        });
+        match report.divergences.first() {
+            None => eprintln!("[letsplay_live] Deterministic over {} frames", report.frames_compared),
+            Some(d) => {
+                eprintln!("[letsplay_live] NONDETERMINISM at frame {}: {}", d.frame, d.subsystems.join(", "));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let artifacts = RomArtifacts::new(Path::new(output_dir), &rom_bytes);
+    if let Some(path) = args.iter().find_map(|a| a.strip_prefix("--checkpoints=")) {
+        let checkpoints = fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|t| parse_checkpoints(&t))
+            .unwrap_or_else(|e| { eprintln!("Bad --checkpoints {path}: {e}"); std::process::exit(1); });
+        let cart = Cartridge::from_bytes(rom_bytes).unwrap_or_else(|e| { eprintln!("Invalid ROM: {e}"); std::process::exit(1); });
+        let report = run_checkpoints(&mut GbCore::new(cart), &checkpoints).unwrap_or_else(|e| {
+            eprintln!("Checkpoint run failed: {e}"); std::process::exit(1);
+        });
+        for r in &report.results {
+            eprintln!("[letsplay_live] {} {:<12} {:<15} {}", if r.passed { "PASS" } else { "FAIL" }, r.checkpoint.id, r.checkpoint.level_name(), r.detail);
+        }
+        report.write(&artifacts.checkpoints_dir()).unwrap_or_else(|e| { eprintln!("Cannot write checkpoints: {e}"); std::process::exit(1); });
+        eprintln!("[letsplay_live] Achieved {} ({}/{}) — {}", report.achieved_level().unwrap_or("no level"), report.passed(),
+                  report.results.len(), artifacts.checkpoints_dir().join("checkpoints.json").display());
+        std::process::exit(if report.passed() == report.results.len() { 0 } else { 1 });
+    }
     let cart = Cartridge::from_bytes(rom_bytes).unwrap_or_else(|e| {
         eprintln!("Invalid ROM: {e}"); std::process::exit(1);
     });
 
     let rom_title = cart.title.clone();
-    let mut core = GbCore::new(cart);
-    let mut replay = ReplayCapture::new(n_frames as usize, &rom_title);
+    // Recorded runs stay reproducible: only interactive play reads user settings
+    let game = if !play { GameSettings::default() } else {
+        SettingsStore::open_default().map(|store| store.for_cartridge(&cart)).unwrap_or_else(|e| {
+            eprintln!("[letsplay_live] Ignoring settings: {e}"); GameSettings::default()
+        })
+    };
+    let mapping = mapping.or_else(|| game.input_map.clone()).unwrap_or_default();
+    let mut config = CoreConfig::default();
+    game.apply_config(&mut config);
+    let mut core = GbCore::with_config(cart, config);
+    if let Some(profile) = accuracy { core.set_accuracy(profile); }
+    if let Some(pack) = &palettes { pack.apply(&mut core); }
+    game.apply(&mut core);
+    core.set_ram_console(ram_console);
+    if io_log { core.bus.io_log = Some(Box::default()); }
+    if ppu_timeline { core.bus.ppu_timeline = Some(Box::default()); }
+    if raster { core.bus.raster = Some(Box::default()); }
+    if profile { enable_profiler(&mut core); }
+    if let Some(interval) = autosave {
+        if has_battery(&core.bus.rom) {
+            let mut save = SramAutosave::new(&artifacts.battery()).with_interval((interval > 0).then_some(interval))
+                .with_journal(sram_journal);
+            match save.load_into(&mut core.bus) {
+                Ok(true) => eprintln!("[letsplay_live] Battery save: {}", artifacts.battery().display()),
+                Ok(false) => {}
+                Err(e) => { eprintln!("Cannot read {}: {e}", artifacts.battery().display()); std::process::exit(1); }
+            }
+            core.autosave = Some(Box::new(save));
+        } else {
+            eprintln!("[letsplay_live] --autosave: cartridge has no battery, nothing to save");
+        }
+    }
+    // Open-ended play keeps the first PLAY_REPLAY_FRAMES in the replay
+    let mut replay = ReplayCapture::new(if n_frames == 0 { PLAY_REPLAY_FRAMES } else { n_frames as usize }, &rom_title).with_sprites(sprites).with_audio_hash(audio_hash)
+        .with_keyframes(keyframes).with_phash(keyframes.is_some())
+        .with_text(text.as_ref().and_then(|t| t.for_core(&core)).cloned());
+    let mut input = if play {
+        let (input, errors) = open_backends(&mapping);
+        for e in &errors { eprintln!("[input] {e}"); }
+        if input.0.is_empty() {
+            eprintln!("--play needs an input backend: rebuild with --features keyboard,gamepad");
+            std::process::exit(1);
+        }
+        Some(input)
+    } else {
+        None
+    };
 
-    let t0 = Instant::now();
+    let t0 = core.host_clock.now_us();
+    // Real-time pacing only when playing
+    let mut throttle = play.then(|| Throttle::new().with_turbo(turbo.unwrap_or(1.0)));
     let mut frame_count = 0u64;
 
     eprintln!("[letsplay_live] ROM: {} | Frames: {} | Save: {} | Broadcast: {}",
               rom_title, n_frames, save_state, broadcast);
 
-    for _ in 0..n_frames {
+    while (play && n_frames == 0) || frame_count < n_frames {
+        if let Some(input) = input.as_mut() {
+            let poll = input.poll();
+            for event in &poll.events {
+                match event {
+                    InputEvent::PadConnected(name) => eprintln!("[input] gamepad connected: {name}"),
+                    InputEvent::PadDisconnected(name) => eprintln!("[input] gamepad disconnected: {name}"),
+                }
+            }
+            if poll.quit { break; }
+            core.set_buttons(poll.buttons);
+        }
         if core.run_frame().is_err() { break; }
+        if let Some(throttle) = throttle.as_mut() { throttle.wait(&*core.host_clock, 0); }
 
         // Capture replay frame
         replay.capture(&core);
+        if let Some(triggers) = triggers.as_mut() {
+            for label in triggers.evaluate(&core) { replay.mark(&label); }
+        }
+        if audio_hash { core.bus.apu.sample_buffer.clear(); }
 
         // Live broadcast: emit snap JSON to stdout (NDJSON)
         if broadcast {
@@ -50,30 +242,76 @@ fn main() {
         }
 
         frame_count += 1;
-        if frame_count % 60 == 0 {
-            eprintln!("[letsplay_live] Frame {} — {}", frame_count, core.state_summary());
+        if frame_count.is_multiple_of(60) {
+            match &throttle {
+                Some(t) => eprintln!("[letsplay_live] Frame {} ({:.0}%) — {}", frame_count, t.speed_percent(), core.state_summary()),
+                None => eprintln!("[letsplay_live] Frame {} — {}", frame_count, core.state_summary()),
+            }
         }
     }
 
-    let elapsed = t0.elapsed().as_secs_f64();
+    drop(input);
+    if let Err(e) = core.flush_sram() { eprintln!("Battery save error: {e}"); }
+    if let Some(save) = core.autosave.as_ref() {
+        eprintln!("[letsplay_live] Battery save: {} ({} writes)", artifacts.battery().display(), save.flushes);
+    }
+    let elapsed = core.host_clock.now_us().saturating_sub(t0) as f64 / 1e6;
     eprintln!("[letsplay_live] Done: {} frames in {:.2}s ({:.1} fps)",
               frame_count, elapsed, frame_count as f64 / elapsed.max(0.001));
 
     // Save outputs
-    let stem = Path::new(rom_path).file_stem().unwrap_or_default().to_str().unwrap_or("rom");
-    fs::create_dir_all(output_dir).ok();
+    artifacts.create().unwrap_or_else(|e| eprintln!("Cannot create {}: {e}", artifacts.dir.display()));
 
-    let replay_path = format!("{}/{}.mrom.replay.json", output_dir, stem);
-    replay.save(Path::new(&replay_path)).unwrap_or_else(|e| eprintln!("Replay save error: {e}"));
-    eprintln!("[letsplay_live] Replay: {}", replay_path);
+    let replay_path = artifacts.replay();
+    replay.save(&replay_path).unwrap_or_else(|e| eprintln!("Replay save error: {e}"));
+    eprintln!("[letsplay_live] Replay: {}", replay_path.display());
+
+    if let Some(triggers) = triggers.as_ref() {
+        triggers.write_dataset(&artifacts.triggers_dir()).unwrap_or_else(|e| eprintln!("Trigger dataset save error: {e}"));
+        eprintln!("[letsplay_live] Triggers: {} ({} captures)", artifacts.triggers_dir().display(), triggers.captures.len());
+    }
 
     if save_state {
-        let sav_path = format!("{}/{}.mrom.sav", output_dir, stem);
-        core.save_state_to_file(Path::new(&sav_path))
+        let sav_path = artifacts.state("final");
+        core.save_state_to_file(&sav_path)
             .unwrap_or_else(|e| eprintln!("Save state error: {e}"));
-        eprintln!("[letsplay_live] State: {}", sav_path);
+        eprintln!("[letsplay_live] State: {}", sav_path.display());
     }
 
+    let console = core.console_text();
+    if !console.is_empty() {
+        let console_path = artifacts.console();
+        fs::write(&console_path, &console).unwrap_or_else(|e| eprintln!("Console save error: {e}"));
+        eprintln!("[letsplay_live] Console: {} ({} bytes)", console_path.display(), console.len());
+    }
+
+    if let Some(log) = core.bus.io_log.as_ref() {
+        log.save(&artifacts.io_log()).unwrap_or_else(|e| eprintln!("IO log save error: {e}"));
+        eprintln!("[letsplay_live] IO log: {} ({} writes)", artifacts.io_log().display(), log.len());
+    }
+    if let Some(frame) = core.bus.ppu_timeline.as_ref().and_then(|t| t.last_frame()) {
+        fs::write(artifacts.ppu_timeline(), frame.to_json()).unwrap_or_else(|e| eprintln!("PPU timeline save error: {e}"));
+        fs::write(artifacts.ppu_timeline_svg(), frame.to_svg()).unwrap_or_else(|e| eprintln!("PPU timeline save error: {e}"));
+        eprintln!("[letsplay_live] PPU timeline: {} (frame {})", artifacts.ppu_timeline().display(), frame.frame);
+    }
+    #[cfg(feature = "profile")]
+    if let Some(json) = core.profile_json() {
+        fs::write(artifacts.profile(), json).unwrap_or_else(|e| eprintln!("Profile save error: {e}"));
+        eprintln!("[letsplay_live] Profile: {}", artifacts.profile().display());
+    }
+
+    let mut session = SessionManifest::new(&artifacts, &rom_title, rom_path, &core.config);
+    session.frames = frame_count;
+    let recorded = plan_path.map_or(Ok(()), |p| session.set_plan(Path::new(p)))
+        .and_then(|_| session.add(SessionRole::Replay, &replay_path))
+        .and_then(|_| if save_state { session.add(SessionRole::State, &artifacts.state("final")) } else { Ok(()) })
+        .and_then(|_| if console.is_empty() { Ok(()) } else { session.add(SessionRole::Console, &artifacts.console()) })
+        .and_then(|_| if io_log { session.add(SessionRole::IoLog, &artifacts.io_log()) } else { Ok(()) })
+        .and_then(|_| if profile { session.add(SessionRole::Profile, &artifacts.profile()) } else { Ok(()) })
+        .and_then(|_| session.write());
+    if let Err(e) = recorded { eprintln!("Session save error: {e}"); }
+    if let Err(e) = artifacts.write_manifest(&rom_title, rom_path) { eprintln!("Manifest save error: {e}"); }
+
     // Final summary JSON to stdout (if not broadcasting frames)
     if !broadcast {
         println!("{}", core.state_json());
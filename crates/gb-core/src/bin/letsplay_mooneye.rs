@@ -0,0 +1,52 @@
+//! letsplay_mooneye — run the mooneye test suite headlessly
+//! Every .gb / .gbc under <suite_dir> (recursively) runs on the model its
+//! file name tags until it executes LD B,B; the register fingerprint there
+//! is the verdict. Prints one line per ROM and exits 1 unless all pass.
+//!
+//! Usage:
+//!   cargo run --release --bin letsplay_mooneye -- <suite_dir> [--frames=N] [--filter=text] [--json=results.json]
+//!
+//! --frames   per-ROM budget before a timeout (default 600, ten seconds)
+//! --filter   only ROMs whose path relative to <suite_dir> contains the text
+//! --json     also write [{"rom": ..., "outcome": ..., "registers": [...], ...}]
+
+use gb_core::{run_mooneye_rom, test_rom_files};
+use std::path::PathBuf;
+
+const DEFAULT_FRAMES: u64 = 600;
+
+fn main() {
+    let flag = |name: &str| std::env::args().find_map(|a| a.strip_prefix(name).map(str::to_string));
+    let args: Vec<String> = std::env::args().filter(|a| !a.starts_with("--")).collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <suite_dir> [--frames=N] [--filter=text] [--json=results.json]", args[0]);
+        std::process::exit(2);
+    }
+    let root = PathBuf::from(&args[1]);
+    let frames = flag("--frames=").and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_FRAMES);
+    let filter = flag("--filter=").unwrap_or_default();
+
+    let mut entries = vec![];
+    let (mut passed, mut total) = (0, 0);
+    for path in test_rom_files(&root) {
+        let rel = path.strip_prefix(&root).unwrap_or(&path).to_string_lossy().to_string();
+        if !rel.contains(&filter) { continue; }
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let result = match std::fs::read(&path) {
+            Ok(bytes) => run_mooneye_rom(&name, bytes, frames),
+            Err(e) => { eprintln!("  {rel}: read error: {e}"); continue; }
+        };
+        total += 1;
+        if result.passed() { passed += 1; }
+        println!("  {:<7} {rel} — {}", result.outcome.as_str().to_uppercase(), result.summary());
+        let json = result.to_json();
+        entries.push(format!("{{\"rom\":\"{}\",{}", rel.replace('\\', "/").replace('"', "\\\""), &json[1..]));
+    }
+
+    println!("\n{passed}/{total} passed");
+    if let Some(out) = flag("--json=") {
+        std::fs::write(&out, format!("[{}]\n", entries.join(","))).expect("Cannot write results");
+        println!("Written: {out}");
+    }
+    if passed != total { std::process::exit(1); }
+}
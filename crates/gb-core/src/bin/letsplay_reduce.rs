@@ -0,0 +1,87 @@
+//! letsplay_reduce — coverage-guided ROM corpus reducer
+//! Runs each ROM briefly with opcode / IO-register coverage recording, then
+//! greedily selects the smallest subset that keeps the combined coverage of
+//! the whole directory.
+//!
+//! Usage:
+//!   cargo run --bin letsplay_reduce -- <roms_dir> <output_dir> [frames_per_rom] [--max=N]
+//!
+//! Output (next to letsplay_batch's batch_manifest.json):
+//!   <output_dir>/corpus_selection.json — mrom.corpus.v1: selected ROMs in pick
+//!                                        order with gains, plus per-ROM coverage
+
+use gb_core::{coverage_of, select_corpus, CoverageVector};
+use std::path::{Path, PathBuf};
+
+fn rom_files(dir: &Path) -> Vec<PathBuf> {
+    let mut roms: Vec<PathBuf> = std::fs::read_dir(dir).into_iter().flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let ext = p.extension().and_then(|s| s.to_str()).unwrap_or("");
+            matches!(ext.to_lowercase().as_str(), "gb" | "gbc" | "rom")
+        })
+        .collect();
+    roms.sort();
+    roms
+}
+
+fn json_str(s: &str) -> String { s.replace('\\', "\\\\").replace('"', "\\\"") }
+
+fn main() {
+    let max = std::env::args().find_map(|a| a.strip_prefix("--max=").and_then(|n| n.parse().ok()));
+    let args: Vec<String> = std::env::args().filter(|a| !a.starts_with("--")).collect();
+    let roms_dir   = args.get(1).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("roms"));
+    let output_dir = args.get(2).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("training_output"));
+    let frames: u64 = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(120);
+
+    let roms = rom_files(&roms_dir);
+    if roms.is_empty() {
+        eprintln!("No ROMs found in {}", roms_dir.display());
+        std::process::exit(1);
+    }
+    std::fs::create_dir_all(&output_dir).expect("Cannot create output dir");
+
+    // Unreadable / invalid ROMs are reported but never selected
+    let mut vectors: Vec<CoverageVector> = Vec::with_capacity(roms.len());
+    let mut errors: Vec<Option<String>> = Vec::with_capacity(roms.len());
+    for (i, path) in roms.iter().enumerate() {
+        let cov = std::fs::read(path).map_err(|e| format!("read error: {e}"))
+            .and_then(|rom| coverage_of(rom, frames).map_err(|e| format!("cart error: {e}")));
+        match cov {
+            Ok(c) => {
+                println!("[{}/{}] {} — {} bits", i + 1, roms.len(), path.display(), c.count());
+                vectors.push(c); errors.push(None);
+            }
+            Err(e) => {
+                println!("[{}/{}] {} — FAILED: {e}", i + 1, roms.len(), path.display());
+                vectors.push(CoverageVector::default()); errors.push(Some(e));
+            }
+        }
+    }
+
+    let sel = select_corpus(&vectors, max);
+
+    let selected: Vec<String> = sel.picks.iter().map(|p| format!(
+        "    {{\"path\":\"{}\",\"gain\":{},\"covered\":{}}}",
+        json_str(&roms[p.index].to_string_lossy()), p.gain, p.covered
+    )).collect();
+    let per_rom: Vec<String> = roms.iter().zip(&vectors).zip(&errors).map(|((path, c), err)| {
+        let picked = sel.picks.iter().any(|p| roms[p.index] == *path);
+        let err = err.as_ref().map_or("null".to_string(), |e| format!("\"{}\"", json_str(e)));
+        format!("    {{\"path\":\"{}\",\"selected\":{},\"error\":{},\"coverage\":{}}}",
+                json_str(&path.to_string_lossy()), picked, err, c.to_json())
+    }).collect();
+    let report = format!(
+        "{{\n  \"version\": \"mrom.corpus.v1\",\n  \"frames_per_rom\": {},\n  \"total_roms\": {},\n  \"selected_roms\": {},\n  \"retained\": {:.4},\n  \"covered\": {},\n  \"total\": {},\n  \"selected\": [\n{}\n  ],\n  \"roms\": [\n{}\n  ]\n}}",
+        frames, roms.len(), sel.picks.len(), sel.retained(), sel.covered.to_json(), sel.total.to_json(),
+        selected.join(",\n"), per_rom.join(",\n")
+    );
+    let out = output_dir.join("corpus_selection.json");
+    std::fs::write(&out, report).expect("Cannot write corpus selection");
+
+    println!("\n=== CORPUS REDUCED ===");
+    println!("  ROMs:      {} → {}", roms.len(), sel.picks.len());
+    println!("  Coverage:  {} / {} bits ({:.1}%)", sel.covered.count(), sel.total.count(), sel.retained() * 100.0);
+    println!("  Selection: {}", out.display());
+}
@@ -0,0 +1,118 @@
+//! letsplay_scenes — scene segmentation + keyframe export
+//! Post-processes a replay (mrom.replay.v1/v2) or training (mrom.train.v1) file,
+//! cuts scenes at big visual deltas, LCD off/on and ROM bank switches, and
+//! writes keyframe PNGs plus a scene index (scenes.json, mrom.scenes.v1).
+//!
+//! Usage:
+//!   cargo run --bin letsplay_scenes -- <input.json> <output_dir> [--rom <path>] [--threshold N]
+//!
+//! Replay files carry framebuffers, so keyframes come straight from the file
+//! (v2 replays only on their own keyframes; record them with phash on).
+//! Training files only carry hashes: pass --rom to re-run the ROM from power-on
+//! (training runs take no input, so the replay is deterministic) to render
+//! keyframes, and to hash frames when the file was written without --phash.
+
+use gb_core::{
+    decode_rgb_hex, detect_scenes, encode_png_rgb, phash, read_sealed, scene_frames_from_manifest, scenes_to_json,
+    Cartridge, GbCore, Json, Scene, SceneConfig, LCD_HEIGHT, LCD_WIDTH, REPLAY_V2_VERSION,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+fn flag(args: &[String], name: &str) -> Option<String> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned()
+}
+
+/// Per-frame pHashes plus the RGB framebuffers of the requested keyframes
+type Rerun = (Vec<u64>, HashMap<u64, Vec<u8>>);
+
+/// Run `rom` from power-on for `frames` frames, returning each frame's pHash and
+/// the RGB framebuffer of every frame listed in `keep`
+fn rerun(rom: &Path, frames: u64, keep: &[u64]) -> Result<Rerun, String> {
+    let bytes = std::fs::read(rom).map_err(|e| format!("read {}: {e}", rom.display()))?;
+    let cart = Cartridge::from_bytes(bytes).map_err(|e| format!("cart: {e}"))?;
+    let mut core = GbCore::new(cart);
+    let mut hashes = Vec::with_capacity(frames as usize);
+    let mut shots = HashMap::new();
+    for frame in 0..frames {
+        core.run_frame().map_err(|e| format!("frame {frame}: {e}"))?;
+        hashes.push(phash(&core.bus.ppu.framebuffer));
+        if keep.contains(&frame) { shots.insert(frame, core.framebuffer_rgb()); }
+    }
+    Ok((hashes, shots))
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let positional: Vec<&String> = {
+        let mut v = Vec::new();
+        let mut i = 1;
+        while i < args.len() {
+            if args[i].starts_with("--") { i += 2; } else { v.push(&args[i]); i += 1; }
+        }
+        v
+    };
+    let input = PathBuf::from(positional.first().ok_or("missing <input.json>")?);
+    let out_dir = PathBuf::from(positional.get(1).map(|s| s.as_str()).unwrap_or("scenes_output"));
+    let rom = flag(args, "--rom").map(PathBuf::from);
+    let mut cfg = SceneConfig::default();
+    if let Some(t) = flag(args, "--threshold") { cfg.phash_threshold = t.parse().map_err(|_| "bad --threshold")?; }
+
+    let text = read_sealed(&input).map_err(|e| format!("read {}: {e}", input.display()))
+        .and_then(|b| String::from_utf8(b).map_err(|e| format!("read {}: {e}", input.display())))?;
+    let doc = Json::parse(&text).map_err(|e| e.to_string())?;
+    let mut frames = scene_frames_from_manifest(&doc)?;
+    let is_replay = matches!(doc.get("version").and_then(Json::as_str), Some("mrom.replay.v1" | REPLAY_V2_VERSION));
+    let span = frames.iter().map(|f| f.frame + 1).max().unwrap_or(0);
+
+    if !is_replay && frames.iter().any(|f| f.phash.is_none()) {
+        let rom = rom.as_ref().ok_or("training file has no phash fields; re-run with --rom or record with --phash")?;
+        let (hashes, _) = rerun(rom, span, &[])?;
+        for f in frames.iter_mut() { f.phash = hashes.get(f.frame as usize).copied(); }
+    }
+
+    let scenes = detect_scenes(&frames, &cfg);
+    let keyframes: Vec<u64> = scenes.iter().map(|s| s.keyframe).collect();
+
+    // Keyframe pixels: from the replay snapshots, or by re-running the ROM
+    let mut shots: HashMap<u64, Vec<u8>> = HashMap::new();
+    if is_replay {
+        for f in doc.get("frames").and_then(Json::as_array).unwrap_or(&[]) {
+            let fi = f.get("fi").and_then(Json::as_u64).unwrap_or(0);
+            if !keyframes.contains(&fi) { continue; }
+            if let Some(rgb) = f.get("snap").and_then(|s| s.get("fb")).and_then(Json::as_str).and_then(decode_rgb_hex) {
+                shots.insert(fi, rgb);
+            }
+        }
+    } else if let Some(rom) = &rom {
+        shots = rerun(rom, span, &keyframes)?.1;
+    }
+
+    std::fs::create_dir_all(&out_dir).map_err(|e| format!("create {}: {e}", out_dir.display()))?;
+    let png_name = |s: &Scene| format!("scene_{:03}_f{:06}.png", s.index, s.keyframe);
+    for s in &scenes {
+        if let Some(rgb) = shots.get(&s.keyframe) {
+            let png = encode_png_rgb(LCD_WIDTH as u32, LCD_HEIGHT as u32, rgb);
+            std::fs::write(out_dir.join(png_name(s)), png).map_err(|e| format!("write png: {e}"))?;
+        }
+    }
+    let index = scenes_to_json(&input.to_string_lossy(), &scenes,
+                               &|s| shots.contains_key(&s.keyframe).then(|| png_name(s)));
+    let index_path = out_dir.join("scenes.json");
+    std::fs::write(&index_path, index).map_err(|e| format!("write {}: {e}", index_path.display()))?;
+
+    println!("Scenes: {} over {} frames | keyframes written: {}", scenes.len(), frames.len(), shots.len());
+    for s in &scenes {
+        println!("  [{:03}] frames {:>6}..={:<6} key {:>6}  cut={}", s.index, s.start_frame, s.end_frame, s.keyframe, s.cut.as_str());
+    }
+    println!("Index: {}", index_path.display());
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    println!("MetaROM Scene Segmenter");
+    if let Err(e) = run(&args) {
+        eprintln!("letsplay_scenes: {e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,117 @@
+//! letsplay_scorecard — accuracy scorecard over suites of test ROMs
+//! Runs every configured suite through the test-ROM harness, aggregates
+//! pass/fail per subsystem, writes mrom.scorecard.v1 and compares it with the
+//! previous scorecard. Exits 1 on any regression (nightly-friendly).
+//!
+//! Usage:
+//!   cargo run --bin letsplay_scorecard -- <test_rom_dir> <scorecard.json> [--suites=suites.json] [--previous=prev.json] [--artifact=ID] [--rom=game.gb]
+//!
+//! --artifact / --rom bind the scorecard to the planner artifact it is
+//! evidence for (`artifact_id`, and `rom_sha` = SHA-256 of the game ROM).
+//!
+//! Without --suites, every subdirectory of <test_rom_dir> is a suite named
+//! (and graded) after itself. Without --previous, an existing <scorecard.json>
+//! is the baseline it is compared against before being overwritten.
+
+use gb_core::{parse_suites, run_test_rom, sha256_hex, Json, ScoreEntry, Scorecard, Suite, DEFAULT_SUITE_FRAMES};
+use std::path::{Path, PathBuf};
+
+fn rom_files(dir: &Path) -> Vec<PathBuf> {
+    let mut roms: Vec<PathBuf> = std::fs::read_dir(dir).into_iter().flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let ext = p.extension().and_then(|s| s.to_str()).unwrap_or("");
+            matches!(ext.to_lowercase().as_str(), "gb" | "gbc")
+        })
+        .collect();
+    roms.sort();
+    roms
+}
+
+fn default_suites(root: &Path) -> Vec<Suite> {
+    let mut dirs: Vec<String> = std::fs::read_dir(root).into_iter().flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    dirs.sort();
+    dirs.into_iter().map(|d| Suite { name: d.clone(), subsystem: d.clone(), dir: d, frames: DEFAULT_SUITE_FRAMES }).collect()
+}
+
+fn load_scorecard(path: &Path) -> Option<Scorecard> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let parsed = Json::parse(&text).map_err(|e| e.to_string()).and_then(|d| Scorecard::from_json(&d));
+    match parsed {
+        Ok(s) => Some(s),
+        Err(e) => { eprintln!("Ignoring previous scorecard {}: {e}", path.display()); None }
+    }
+}
+
+fn main() {
+    let flag = |name: &str| std::env::args().find_map(|a| a.strip_prefix(name).map(str::to_string));
+    let args: Vec<String> = std::env::args().filter(|a| !a.starts_with("--")).collect();
+    if args.len() < 3 {
+        eprintln!("Usage: {} <test_rom_dir> <scorecard.json> [--suites=suites.json] [--previous=prev.json] [--artifact=ID] [--rom=game.gb]", args[0]);
+        std::process::exit(2);
+    }
+    let root = PathBuf::from(&args[1]);
+    let out_path = PathBuf::from(&args[2]);
+
+    let suites = match flag("--suites=") {
+        Some(p) => {
+            let text = std::fs::read_to_string(&p).unwrap_or_else(|e| { eprintln!("Cannot read {p}: {e}"); std::process::exit(2); });
+            Json::parse(&text).map_err(|e| e.to_string()).and_then(|d| parse_suites(&d))
+                .unwrap_or_else(|e| { eprintln!("Bad suites file {p}: {e}"); std::process::exit(2); })
+        }
+        None => default_suites(&root),
+    };
+    let previous = load_scorecard(&flag("--previous=").map(PathBuf::from).unwrap_or_else(|| out_path.clone()));
+
+    println!("MetaROM Accuracy Scorecard");
+    println!("  test roms: {}", root.display());
+    println!("  suites:    {}", suites.len());
+
+    let rom_sha = flag("--rom=").map(|p| {
+        std::fs::read(&p).map(|b| sha256_hex(&b)).unwrap_or_else(|e| { eprintln!("Cannot read {p}: {e}"); std::process::exit(2); })
+    });
+    let mut card = Scorecard { core: "gb-core".into(), artifact_id: flag("--artifact="), rom_sha, entries: vec![] };
+    for suite in &suites {
+        let roms = rom_files(&root.join(&suite.dir));
+        println!("\n[{}] {} ROM(s), subsystem={}, frames≤{}", suite.name, roms.len(), suite.subsystem, suite.frames);
+        for rom_path in roms {
+            let name = rom_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let run = match std::fs::read(&rom_path) {
+                Ok(bytes) => run_test_rom(bytes, suite.frames),
+                Err(e) => { eprintln!("  {name}: read error: {e}"); continue; }
+            };
+            let entry = ScoreEntry::from_run(suite, &name, &run);
+            println!("  {:<7} {} ({} frames){}", entry.outcome, name, entry.frames,
+                     if entry.detail.is_empty() { String::new() } else { format!(" — {}", entry.detail) });
+            card.entries.push(entry);
+        }
+    }
+
+    println!("\n=== SCORECARD ===");
+    for (sub, passed, total) in card.by_subsystem() {
+        println!("  {:<12} {}/{}", sub, passed, total);
+    }
+    println!("  {:<12} {}/{} ({:.1}%)", "total", card.passed(), card.total(), card.pass_rate() * 100.0);
+
+    std::fs::write(&out_path, card.to_json()).expect("Cannot write scorecard");
+    println!("  Written:     {}", out_path.display());
+
+    let Some(previous) = previous else {
+        println!("  No previous scorecard; nothing to compare.");
+        return;
+    };
+    let regressions = card.regressions(&previous);
+    println!("  Previous:    {}/{}", previous.passed(), previous.total());
+    if regressions.is_empty() {
+        println!("  No regressions.");
+    } else {
+        println!("\n{} REGRESSION(S):", regressions.len());
+        for r in &regressions { println!("  {r}"); }
+        std::process::exit(1);
+    }
+}
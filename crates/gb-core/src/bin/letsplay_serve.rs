@@ -0,0 +1,117 @@
+//! letsplay_serve — long-running headless capture with a monitoring endpoint
+//! Usage: letsplay_serve <rom_path> [n_frames] [--http[=ADDR]] [--realtime] [--metrics-push=HOST:PORT] [--osd]
+//!
+//! Runs the ROM for n_frames (0, the default, runs until killed) and logs
+//! progress every 600 frames. --http (build with `--features http`) serves
+//! read-only /state, /memory/wram?offset&len, /screenshot.png and /metrics on
+//! ADDR (default 127.0.0.1:8088); see `serve.rs`. --realtime paces emulation
+//! to the hardware frame rate instead of running flat out. --metrics-push
+//! sends the same metrics to a Pushgateway (job "letsplay_serve") every 10 s.
+//! --osd draws the frame number, fps and sprite boxes over the served
+//! screenshot (`overlay.rs`).
+
+use gb_core::{visible_sprites, Cartridge, GbCore, Metrics, Rgba, Throttle, METRIC_FPS, METRIC_FRAMES};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8088";
+/// Snapshots are refreshed at most this often (state JSON carries the framebuffer)
+const PUBLISH_EVERY: Duration = Duration::from_millis(100);
+const PUSH_EVERY: Duration = Duration::from_secs(10);
+const OSD_TEXT: Rgba = [255, 255, 255, 255];
+const OSD_BACK: Rgba = [0, 0, 0, 160];
+const OSD_SPRITE: Rgba = [255, 64, 64, 200];
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let positional: Vec<&String> = args.iter().skip(1).filter(|a| !a.starts_with("--")).collect();
+    let Some(rom_path) = positional.first() else {
+        eprintln!("Usage: {} <rom_path> [n_frames] [--http[=ADDR]] [--realtime] [--metrics-push=HOST:PORT] [--osd]", args[0]);
+        std::process::exit(1);
+    };
+    let n_frames: u64 = positional.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+    let http = args.iter().find_map(|a| match a.as_str() {
+        "--http" => Some(DEFAULT_ADDR.to_string()),
+        _ => a.strip_prefix("--http=").map(str::to_string),
+    });
+    let realtime = args.iter().any(|a| a == "--realtime");
+    let metrics_push = args.iter().find_map(|a| a.strip_prefix("--metrics-push="));
+    let osd = args.iter().any(|a| a == "--osd");
+
+    let rom_bytes = std::fs::read(rom_path).unwrap_or_else(|e| { eprintln!("Cannot read ROM: {e}"); std::process::exit(1); });
+    let cart = Cartridge::from_bytes(rom_bytes).unwrap_or_else(|e| { eprintln!("Invalid ROM: {e}"); std::process::exit(1); });
+    let rom_title = cart.title.clone();
+    let mut core = GbCore::new(cart);
+    if osd { core.overlay = Some(Box::default()); }
+    let metrics = Arc::new(Metrics::new().with_label("rom", &rom_title));
+    let mut publish = start_http(http, &core, &rom_title, Arc::clone(&metrics));
+
+    eprintln!("[letsplay_serve] ROM: {} | Frames: {}", rom_title, if n_frames == 0 { "unlimited".into() } else { n_frames.to_string() });
+    let start = Instant::now();
+    let mut throttle = realtime.then(Throttle::new);
+    let (mut window_start, mut window_frames, mut fps) = (Instant::now(), 0u64, 0.0f64);
+    let mut frame = 0u64;
+    let (mut published, mut pushed) = (Instant::now(), Instant::now());
+    while n_frames == 0 || frame < n_frames {
+        if let Err(e) = core.run_frame() {
+            eprintln!("[letsplay_serve] Stopped at frame {frame}: {e}");
+            break;
+        }
+        frame += 1;
+        window_frames += 1;
+        if window_start.elapsed() >= Duration::from_secs(1) {
+            fps = window_frames as f64 / window_start.elapsed().as_secs_f64();
+            (window_start, window_frames) = (Instant::now(), 0);
+        }
+        if published.elapsed() >= PUBLISH_EVERY {
+            metrics.set(METRIC_FRAMES, frame as f64);
+            metrics.set(METRIC_FPS, fps);
+            if let Some(overlay) = core.overlay.as_mut() {
+                overlay.clear();
+                overlay.sprite_boxes(&visible_sprites(&core.bus), OSD_SPRITE);
+                overlay.label(0, 0, &format!("F{frame} {fps:.0}FPS"), OSD_TEXT, OSD_BACK);
+            }
+            publish(&core, fps);
+            published = Instant::now();
+        }
+        if let Some(gw) = metrics_push.filter(|_| pushed.elapsed() >= PUSH_EVERY) {
+            metrics.push(gw, "letsplay_serve").unwrap_or_else(|e| eprintln!("[letsplay_serve] metrics push: {e}"));
+            pushed = Instant::now();
+        }
+        if frame.is_multiple_of(600) {
+            eprintln!("[letsplay_serve] Frame {} ({:.1} fps) — {}", frame, fps, core.state_summary());
+        }
+        if let Some(throttle) = throttle.as_mut() { throttle.wait(&*core.host_clock, 0); }
+    }
+    eprintln!("[letsplay_serve] Done: {} frames in {:.2}s", frame, start.elapsed().as_secs_f64());
+}
+
+/// Pushes the latest core state to the server (no-op without --http)
+type Publisher = Box<dyn FnMut(&GbCore, f64)>;
+
+/// Start the server if asked; returns the snapshot publisher
+#[cfg(feature = "http")]
+fn start_http(addr: Option<String>, core: &GbCore, rom_title: &str, metrics: Arc<Metrics>) -> Publisher {
+    use gb_core::{HttpServer, ServeSnapshot};
+    use std::sync::Mutex;
+    let Some(addr) = addr else { return Box::new(|_, _| {}) };
+    let snapshot = Arc::new(Mutex::new(ServeSnapshot::capture(core, rom_title, 0.0)));
+    let server = HttpServer::spawn(&addr, Arc::clone(&snapshot), metrics)
+        .unwrap_or_else(|e| { eprintln!("Cannot bind {addr}: {e}"); std::process::exit(1); });
+    eprintln!("[letsplay_serve] HTTP on http://{}/ (/state /memory/wram /screenshot.png /metrics)", server.addr);
+    let title = rom_title.to_string();
+    Box::new(move |core, fps| {
+        let _server = &server; // the server lives as long as the publisher
+        let snap = ServeSnapshot::capture(core, &title, fps);
+        *snapshot.lock().unwrap_or_else(|p| p.into_inner()) = snap;
+    })
+}
+
+#[cfg(not(feature = "http"))]
+fn start_http(addr: Option<String>, _core: &GbCore, _rom_title: &str, _metrics: Arc<Metrics>) -> Publisher {
+    if addr.is_some() {
+        eprintln!("--http needs the HTTP server: rebuild with --features http");
+        std::process::exit(1);
+    }
+    Box::new(|_, _| {})
+}
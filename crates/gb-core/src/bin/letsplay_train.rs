@@ -2,13 +2,22 @@
 //! Plays a ROM (or synthetic test ROM) for N frames and dumps a .mrom.train.json.
 //!
 //! Usage:
-//!   cargo run --bin letsplay_train -- [frames] [output_path]
+//!   cargo run --bin letsplay_train -- [frames] [output_path] [--phash] [--audio-hash] [--io-diffs] [--sprites] [--text[=FILE]]
+//!
+//! --phash adds a 64-bit perceptual hash of each frame ("phash", hex).
+//! --audio-hash adds a hash of each frame's audio output ("audio_hash", hex).
+//! --io-diffs writes mrom.train.v2 with per-frame IO/HRAM changes
+//! ("io_diff" / "hram_diff": [[address, value], ...]).
+//! --sprites adds each frame's visible sprites as object labels ("sprites").
+//! --text adds the on-screen text when the ROM has a glyph table, built in
+//! or from FILE ("text").
+//! If output_path is an existing directory the `artifacts.rs` layout is used:
+//! <output_path>/<rom_hash>/train.json plus manifest.json and session.json.
 //!
 //! Every frame becomes one FrameRecord in the training file.
 //! Run until ROMs are exhausted = run until every ROM produces a complete training file.
 
-use gb_core::{Cartridge, GbCore, FrameRecord, LCD_WIDTH, LCD_HEIGHT, CYCLES_PER_FRAME};
-use std::collections::HashMap;
+use gb_core::{audio_hash, phash, rom_hash, screen_text, screen_text_json, sha256_hex, sprites_json, visible_sprites_into, AudioFeatures, Code, GlyphTable, GlyphTables, RegDiffTracker, RomArtifacts, RomBuilder, CODE_START, Cartridge, GbCore, CoreConfig, SessionManifest, SessionRole};
 
 fn fnv1a(data: &[u8]) -> u32 {
     let mut h: u32 = 0x811c9dc5;
@@ -17,24 +26,17 @@ fn fnv1a(data: &[u8]) -> u32 {
 }
 
 fn synthetic_rom() -> Vec<u8> {
-    let mut rom = vec![0u8; 0x8000];
-    rom[0x0100] = 0x00; rom[0x0101] = 0xC3; rom[0x0102] = 0x50; rom[0x0103] = 0x01;
-    let title = b"EVEZ-OS-TRAIN";
-    for (i, &b) in title.iter().enumerate() { rom[0x0134+i] = b; }
-    rom[0x0147] = 0x00; rom[0x0148] = 0x00; rom[0x0149] = 0x00; rom[0x014D] = 0xE7;
     // Program: LCD on, VBlank IRQ enabled, spin
-    let prog: &[u8] = &[
-        0x3E,0x00,0xE0,0x40,  // LCD off
-        0x01,0x00,0x98,       // LD BC,0x9800
-        0x3E,0x01,0x02,       // LD A,1 / LD (BC),A
-        0x3E,0x91,0xE0,0x40,  // LCD on, BG on
-        0x3E,0x01,0xE0,0xFF,  // IE = 1 (VBlank)
-        0xFB,                  // EI
-        0x3E,0xAA,0xE0,0x40,  // periodic LCDC write to exercise register path
-        0xC3,0x50,0x01,       // JP back (spin)
-    ];
-    for (i,&b) in prog.iter().enumerate() { rom[0x0150+i] = b; }
-    rom
+    let code = Code::new(CODE_START)
+        .set_io(0x40, 0x00)       // LCD off
+        .ld_bc(0x9800)
+        .ld_a(0x01).raw(&[0x02])  // LD (BC),A
+        .set_io(0x40, 0x91)       // LCD on, BG on
+        .set_io(0xFF, 0x01)       // IE = 1 (VBlank)
+        .ei()
+        .set_io(0x40, 0xAA)       // periodic LCDC write to exercise register path
+        .jp(CODE_START);          // JP back (spin)
+    RomBuilder::new().title("EVEZ-OS-TRAIN").code(code.bytes()).build()
 }
 
 /// Epoch classifier: DMG=gen1_nes, CGB=gen2_snes_genesis
@@ -43,45 +45,54 @@ fn epoch_for(cart: &Cartridge) -> &'static str {
 }
 
 /// Run a cart for max_frames and return all FrameRecords as JSON string
-fn play_to_json(cart: Cartridge, max_frames: u64) -> String {
-    use std::fmt::Write as FmtWrite;
+fn play_to_json(cart: Cartridge, max_frames: u64, with_phash: bool, with_audio_hash: bool, with_io_diffs: bool, with_sprites: bool, glyphs: Option<&GlyphTable>) -> String {
+    use std::fmt::Write;
 
     let rom_title = cart.title.clone();
     let mbc_kind = format!("{:?}", cart.kind);
     let epoch = epoch_for(&cart).to_string();
-    let rom_sha = {
-        // simple FNV over the whole ROM as a stand-in for sha256 in no-dep build
-        let h = fnv1a(&cart.rom);
-        format!("{:08x}", h)
-    };
+    let rom_sha = sha256_hex(&cart.rom);
+    let rom_hash = rom_hash(&cart.rom);
     let rom_size = cart.rom.len();
     let mut core = GbCore::new(cart);
-    let mut records: Vec<String> = Vec::with_capacity(max_frames as usize);
+    let mut frames_json = String::with_capacity(max_frames as usize * 768);
+    let mut sprites = Vec::with_capacity(40);
     let mut vblank_count: u64 = 0;
+    let mut reg_diffs = with_io_diffs.then(|| RegDiffTracker::new(&core.bus));
 
     for frame in 0..max_frames {
         let _ = core.run_frame();
         vblank_count += 1;
-        let fb = core.bus.ppu.framebuffer.clone();
-        let wram_hash = fnv1a(&core.bus.wram);
-        let vram_hash = fnv1a(&core.bus.vram);
+        let wram_hash = fnv1a(core.bus.wram.as_flattened());
+        let vram_hash = fnv1a(core.bus.vram.as_flattened());
         let oam_hash  = fnv1a(&core.bus.oam);
         let samples = core.bus.apu.sample_buffer.len() / 2;
-        let _ = core.bus.apu.drain_samples();
+        let audio = AudioFeatures::capture(&mut core.bus.apu);
+        let ph = if with_phash { format!("\"phash\":\"{:016x}\",", phash(&core.bus.ppu.framebuffer)) } else { String::new() };
+        let ah = if with_audio_hash { format!("\"audio_hash\":\"{:016x}\",", audio_hash(&core.bus.apu.sample_buffer)) } else { String::new() };
+        let regs = reg_diffs.as_mut().map_or(String::new(), |t| t.frame_diff(&core.bus).to_json_fields());
+        let spr = if with_sprites {
+            visible_sprites_into(&core.bus, &mut sprites);
+            format!("\"sprites\":{},", sprites_json(&sprites))
+        } else { String::new() };
+        let txt = glyphs.map_or(String::new(), |g| format!("\"text\":{},", screen_text_json(&screen_text(&core.bus, g))));
+        core.bus.apu.clear_samples();
 
-        let rec = format!(
+        if frame > 0 { frames_json.push_str(",\n  "); }
+        let _ = write!(frames_json,
             concat!(
-                "{{"frame":{},"t_cycles":{},",
-                ""pc":{},"sp":{},"a":{},"f":{},",
-                ""bc":{},"de":{},"hl":{},",
-                ""halted":{},"ime":{},",
-                ""ly":{},"lcdc":{},"ppu_mode":{},",
-                ""vblank_count":{},",
-                ""sq1_on":{},"sq2_on":{},"wave_on":{},"noise_on":{},",
-                ""samples":{},",
-                ""rom_bank":{},"ram_bank":{},",
-                ""wram_hash":{},"vram_hash":{},"oam_hash":{},",
-                ""rom_title":"{}","mbc_kind":"{}","epoch":"{}"}}"
+                "{{\"frame\":{},\"t_cycles\":{},",
+                "\"pc\":{},\"sp\":{},\"a\":{},\"f\":{},",
+                "\"bc\":{},\"de\":{},\"hl\":{},",
+                "\"halted\":{},\"ime\":{},",
+                "\"ly\":{},\"lcdc\":{},\"ppu_mode\":{},",
+                "\"vblank_count\":{},",
+                "\"sq1_on\":{},\"sq2_on\":{},\"wave_on\":{},\"noise_on\":{},",
+                "\"samples\":{},",
+                "\"audio\":{},{}{}{}{}{}",
+                "\"rom_bank\":{},\"ram_bank\":{},",
+                "\"wram_hash\":{},\"vram_hash\":{},\"oam_hash\":{},",
+                "\"rom_title\":\"{}\",\"mbc_kind\":\"{}\",\"epoch\":\"{}\"}}"
             ),
             frame, core.clock.t_cycles,
             core.regs.pc, core.regs.sp, core.regs.a, core.regs.f,
@@ -91,21 +102,20 @@ fn play_to_json(cart: Cartridge, max_frames: u64) -> String {
             vblank_count,
             core.bus.apu.sq1.enabled, core.bus.apu.sq2.enabled,
             core.bus.apu.wave.enabled, core.bus.apu.noise.enabled,
-            samples,
+            samples, audio.to_json(), ph, ah, regs, spr, txt,
             core.bus.mbc.rom_bank, core.bus.mbc.ram_bank,
             wram_hash, vram_hash, oam_hash,
             rom_title, mbc_kind, epoch
         );
-        records.push(rec);
     }
 
-    let frames_json = records.join(",\n  ");
     format!(
         concat!(
             "{{\n",
-            "  \"version\": \"mrom.train.v1\",\n",
+            "  \"version\": \"{}\",\n",
             "  \"rom_title\": \"{}\",\n",
             "  \"rom_sha\": \"{}\",\n",
+            "  \"rom_hash\": \"{}\",\n",
             "  \"rom_size_bytes\": {},\n",
             "  \"mbc_kind\": \"{}\",\n",
             "  \"epoch\": \"{}\",\n",
@@ -114,13 +124,22 @@ fn play_to_json(cart: Cartridge, max_frames: u64) -> String {
             "  \"frames\": [\n  {}\n  ]\n",
             "}}"
         ),
-        rom_title, rom_sha, rom_size, mbc_kind, epoch,
+        if with_io_diffs { "mrom.train.v2" } else { "mrom.train.v1" },
+        rom_title, rom_sha, rom_hash, rom_size, mbc_kind, epoch,
         max_frames, core.clock.t_cycles, frames_json
     )
 }
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let with_phash = std::env::args().any(|a| a == "--phash");
+    let with_audio_hash = std::env::args().any(|a| a == "--audio-hash");
+    let with_io_diffs = std::env::args().any(|a| a == "--io-diffs");
+    let with_sprites = std::env::args().any(|a| a == "--sprites");
+    let text = std::env::args().find(|a| a.starts_with("--text")).map(|a| {
+        GlyphTables::builtin_with(a.strip_prefix("--text=").map(std::path::Path::new))
+            .unwrap_or_else(|e| { eprintln!("Bad --text: {e}"); std::process::exit(1); })
+    });
+    let args: Vec<String> = std::env::args().filter(|a| !a.starts_with("--")).collect();
     let max_frames: u64 = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(60);
     let out_path = args.get(2).cloned().unwrap_or_else(|| "output.mrom.train.json".to_string());
 
@@ -128,13 +147,28 @@ fn main() {
     println!("frames={} output={}", max_frames, out_path);
     println!("Building synthetic EVEZ-OS-TRAIN ROM...");
 
-    let cart = Cartridge::from_bytes(synthetic_rom()).expect("ROM invalid");
+    let rom = synthetic_rom();
+    let artifacts = std::path::Path::new(&out_path).is_dir().then(|| RomArtifacts::new(std::path::Path::new(&out_path), &rom));
+    let cart = Cartridge::from_bytes(rom).expect("ROM invalid");
+    let title = cart.title.clone();
     println!("ROM: {} | MBC: {:?} | {}KB | is_cgb={}", cart.title, cart.kind, cart.rom_size_kb, cart.is_cgb);
 
-    let json = play_to_json(cart, max_frames);
+    let glyphs = text.as_ref().and_then(|t| t.for_cartridge(&cart)).cloned();
+    let json = play_to_json(cart, max_frames, with_phash, with_audio_hash, with_io_diffs, with_sprites, glyphs.as_ref());
 
+    let out_path = match &artifacts {
+        Some(a) => { a.create().expect("Failed to create artifact dir"); a.train() }
+        None => std::path::PathBuf::from(out_path),
+    };
     std::fs::write(&out_path, &json).expect("Failed to write training file");
-    println!("Training file written: {} ({} bytes)", out_path, json.len());
+    if let Some(a) = &artifacts {
+        let mut session = SessionManifest::new(a, &title, "synthetic", &CoreConfig::default());
+        session.frames = max_frames;
+        session.add(SessionRole::Training, &out_path).expect("Failed to checksum training file");
+        session.write().expect("Failed to write session");
+        a.write_manifest(&title, "synthetic").expect("Failed to write manifest");
+    }
+    println!("Training file written: {} ({} bytes)", out_path.display(), json.len());
     println!("=== TRAINING EXTRACTION COMPLETE ===");
     println!("Every ROM run now produces a .mrom.train.json.");
     println!("Feed these into the EVEZ-OS console_war_trainer for epoch progression.");
@@ -0,0 +1,70 @@
+//! mrom-microtest — single-instruction SM83 test records
+//! Writes one JSON file per opcode (`00.json` .. `cb ff.json`) of randomized
+//! CPU states and the state gb-core leaves after executing that instruction
+//! (`microtest.rs`), for differential comparison against other emulators.
+//! --check runs a directory of such files (from gb-core or elsewhere) and
+//! reports every record whose result differs.
+//!
+//! Usage:
+//!   cargo run --release --bin mrom-microtest -- <out_dir> [--count=N] [--seed=S] [--ops=3e,cb11,...]
+//!   cargo run --release --bin mrom-microtest -- --check=<dir>
+//!
+//! --count  records per opcode (default 1000)
+//! --seed   generator seed (default 0); the same seed writes the same files
+//! --ops    only these opcodes (hex; CB-prefixed as cbXX)
+
+use gb_core::{generate_microtests, microtest_op_name, microtest_opcodes, microtests_json, parse_microtests, run_microtests, Json, DEFAULT_MICROTESTS_PER_OP};
+use std::path::Path;
+
+fn main() {
+    let flag = |name: &str| std::env::args().find_map(|a| a.strip_prefix(name).map(str::to_string));
+    let args: Vec<String> = std::env::args().filter(|a| !a.starts_with("--")).collect();
+    if let Some(dir) = flag("--check=") {
+        std::process::exit(check(Path::new(&dir)));
+    }
+    if args.len() < 2 {
+        eprintln!("Usage: {} <out_dir> [--count=N] [--seed=S] [--ops=3e,cb11,...] | --check=<dir>", args[0]);
+        std::process::exit(2);
+    }
+    let out = Path::new(&args[1]);
+    let count = flag("--count=").and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_MICROTESTS_PER_OP);
+    let seed = flag("--seed=").and_then(|s| s.parse().ok()).unwrap_or(0);
+    let ops: Vec<u16> = match flag("--ops=") {
+        Some(list) => list.split(',').map(|op| u16::from_str_radix(op.trim(), 16).unwrap_or_else(|_| {
+            eprintln!("Bad --ops entry (want hex, e.g. 3e or cb11): {op}"); std::process::exit(2);
+        })).collect(),
+        None => microtest_opcodes(),
+    };
+
+    std::fs::create_dir_all(out).unwrap_or_else(|e| { eprintln!("Cannot create {}: {e}", out.display()); std::process::exit(2); });
+    for &op in &ops {
+        let tests = generate_microtests(op, count, seed).unwrap_or_else(|e| { eprintln!("{e}"); std::process::exit(2); });
+        let path = out.join(format!("{}.json", microtest_op_name(op)));
+        std::fs::write(&path, microtests_json(&tests)).expect("Cannot write microtests");
+    }
+    eprintln!("[mrom-microtest] {} opcode(s) × {count} record(s) written to {}", ops.len(), out.display());
+}
+
+/// Exit status: 0 when every record matches
+fn check(dir: &Path) -> i32 {
+    let mut files: Vec<_> = std::fs::read_dir(dir).unwrap_or_else(|e| { eprintln!("Cannot read {}: {e}", dir.display()); std::process::exit(2); })
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|x| x == "json"))
+        .collect();
+    files.sort();
+    let (mut total, mut failed) = (0usize, 0usize);
+    for path in files {
+        let tests = std::fs::read_to_string(&path).map_err(|e| e.to_string())
+            .and_then(|t| Json::parse(&t).map_err(|e| e.to_string()))
+            .and_then(|d| parse_microtests(&d))
+            .unwrap_or_else(|e| { eprintln!("Bad microtests {}: {e}", path.display()); std::process::exit(2); });
+        let results = run_microtests(&tests).unwrap_or_else(|e| { eprintln!("Cannot run microtests: {e}"); std::process::exit(2); });
+        for e in results.into_iter().filter_map(Result::err) {
+            failed += 1;
+            if failed <= 20 { eprintln!("  FAIL {e}"); }
+        }
+        total += tests.len();
+    }
+    eprintln!("[mrom-microtest] {}/{total} passed", total - failed);
+    (failed > 0) as i32
+}
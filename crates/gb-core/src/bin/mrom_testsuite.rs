@@ -0,0 +1,61 @@
+//! mrom-testsuite — accuracy report over a directory of test ROMs
+//! Runs every .gb / .gbc under <rom_dir> (blargg, mooneye, acid2, ...) and
+//! classifies each by serial output, the LD B,B register signature or a
+//! reference frame hash, then emits an mrom.testsuite.v1 JSON report.
+//!
+//! Usage:
+//!   cargo run --release --bin mrom-testsuite -- <rom_dir> [--out=report.json] [--frames=N]
+//!       [--hashes=frame_hashes.json] [--record-hashes=frame_hashes.json]
+//!
+//! --out            write the report there instead of stdout (progress goes to stderr)
+//! --frames         per-ROM budget (default 3600, about a minute)
+//! --hashes         reference frame hashes, {"<rom>": "<hex>"}; defaults to
+//!                  <rom_dir>/frame_hashes.json when that exists
+//! --record-hashes  write every ROM's final frame hash in --hashes form
+
+use gb_core::{parse_frame_hashes, run_suite_dir, Json, DEFAULT_SUITE_FRAMES};
+use std::path::PathBuf;
+
+fn main() {
+    let flag = |name: &str| std::env::args().find_map(|a| a.strip_prefix(name).map(str::to_string));
+    let args: Vec<String> = std::env::args().filter(|a| !a.starts_with("--")).collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <rom_dir> [--out=report.json] [--frames=N] [--hashes=frame_hashes.json] [--record-hashes=out.json]", args[0]);
+        std::process::exit(2);
+    }
+    let root = PathBuf::from(&args[1]);
+    let frames = flag("--frames=").and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_SUITE_FRAMES);
+    let hashes_path = flag("--hashes=").map(PathBuf::from)
+        .or_else(|| Some(root.join("frame_hashes.json")).filter(|p| p.exists()));
+    let hashes = match &hashes_path {
+        Some(p) => {
+            let text = std::fs::read_to_string(p).unwrap_or_else(|e| { eprintln!("Cannot read {}: {e}", p.display()); std::process::exit(2); });
+            Json::parse(&text).map_err(|e| e.to_string()).and_then(|d| parse_frame_hashes(&d))
+                .unwrap_or_else(|e| { eprintln!("Bad frame hashes {}: {e}", p.display()); std::process::exit(2); })
+        }
+        None => vec![],
+    };
+
+    eprintln!("MetaROM test suite: {} ({} reference hash(es), frames≤{frames})", root.display(), hashes.len());
+    let report = run_suite_dir(&root, &hashes, frames, |r| {
+        let detail = match &r.outcome { gb_core::TestOutcome::Fail(d) => format!(" — {d}"), _ => String::new() };
+        eprintln!("  {:<7} {:<10} {} ({} frames){detail}", r.outcome.as_str(), r.method.as_str(), r.rom, r.frames);
+    }).unwrap_or_else(|e| { eprintln!("Cannot run {}: {e}", root.display()); std::process::exit(2); });
+
+    for (method, passed, total) in report.by_method() {
+        eprintln!("  {:<12} {passed}/{total}", method.as_str());
+    }
+    eprintln!("  {:<12} {}/{} ({:.1}%)", "total", report.passed(), report.total(), report.pass_rate() * 100.0);
+
+    match flag("--out=") {
+        Some(out) => {
+            std::fs::write(&out, report.to_json()).expect("Cannot write report");
+            eprintln!("  Written: {out}");
+        }
+        None => println!("{}", report.to_json()),
+    }
+    if let Some(out) = flag("--record-hashes=") {
+        std::fs::write(&out, report.frame_hashes_json()).expect("Cannot write frame hashes");
+        eprintln!("  Hashes:  {out}");
+    }
+}
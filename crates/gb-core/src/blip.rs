@@ -0,0 +1,153 @@
+//! blip — band-limited step synthesis for APU output
+//!
+//! The APU's channels are square waves, a 4-bit wave table and an LFSR: their
+//! output only ever changes in steps. Sampling them every `CPU_HZ / rate`
+//! cycles aliases every edge to the nearest output sample (audible as
+//! inharmonic fizz on high notes) and costs four channel ticks per T-cycle.
+//! A `BlipBuffer` instead takes each amplitude change as a delta at its
+//! exact clock time and adds a windowed-sinc band-limited step to the output,
+//! so the result is alias-free at any output rate and work scales with the
+//! number of edges, not cycles (the blip_buf approach).
+//!
+//! Deltas are accumulated as impulses (`BLIP_WIDTH` taps, one of
+//! `BLIP_PHASES` sub-sample offsets) and integrated when read. Each phase's
+//! taps sum to exactly `1 << DELTA_BITS`, so a step settles at exactly its
+//! level — no drift, and the DC level matches plain sampling. Output lags
+//! the input by `BLIP_WIDTH / 2` samples.
+
+use std::sync::OnceLock;
+
+/// Taps per band-limited step
+pub const BLIP_WIDTH: usize = 16;
+/// Sub-sample positions a step can start at
+pub const BLIP_PHASES: usize = 64;
+/// Passband edge as a fraction of the output Nyquist frequency
+pub const BLIP_CUTOFF: f64 = 0.9;
+
+/// Fixed-point fraction bits of sample positions
+const FRAC_BITS: u32 = 32;
+/// Fixed-point fraction bits of kernel taps
+const DELTA_BITS: u32 = 15;
+
+#[derive(Debug, Clone)]
+pub struct BlipBuffer {
+    clock_rate: u64,
+    sample_rate: u32,
+    /// Output samples per input clock (32.32 fixed point)
+    factor: u64,
+    /// Output position of clock 0 of the current frame (32.32, relative to `buf[0]`)
+    offset: u64,
+    /// Pending impulses, not yet integrated
+    buf: Vec<i32>,
+    integrator: i64,
+}
+
+impl BlipBuffer {
+    /// A buffer turning `clock_rate` Hz input time into `sample_rate` Hz output
+    pub fn new(clock_rate: u64, sample_rate: u32) -> Self {
+        let mut b = BlipBuffer { clock_rate: clock_rate.max(1), sample_rate: 0, factor: 0, offset: 0,
+                                 buf: vec![0; BLIP_WIDTH * 2], integrator: 0 };
+        b.set_sample_rate(sample_rate);
+        b
+    }
+
+    pub fn sample_rate(&self) -> u32 { self.sample_rate }
+    pub fn clock_rate(&self) -> u64 { self.clock_rate }
+
+    /// Change the output rate; pending output is discarded
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate.max(1);
+        self.factor = ((self.sample_rate as u64) << FRAC_BITS) / self.clock_rate;
+        self.clear();
+    }
+
+    /// Drop pending output and return to silence
+    pub fn clear(&mut self) {
+        self.offset = 0;
+        self.integrator = 0;
+        self.buf.iter_mut().for_each(|s| *s = 0);
+    }
+
+    /// Sub-sample position of the current frame start (32.32 fixed point);
+    /// part of the emulated state since it decides where future edges land
+    pub fn position(&self) -> u64 { self.offset }
+
+    /// Output samples that can be read
+    pub fn samples_avail(&self) -> usize { (self.offset >> FRAC_BITS) as usize }
+
+    /// Step the output by `delta` at `clock_time` clocks into the current frame
+    pub fn add_delta(&mut self, clock_time: u64, delta: i32) {
+        if delta == 0 { return; }
+        let pos = self.offset + clock_time * self.factor;
+        let index = (pos >> FRAC_BITS) as usize;
+        let phase = (pos >> (FRAC_BITS - BLIP_PHASES.trailing_zeros())) as usize & (BLIP_PHASES - 1);
+        if self.buf.len() < index + BLIP_WIDTH {
+            self.buf.resize(index + BLIP_WIDTH, 0);
+        }
+        for (s, &k) in self.buf[index..index + BLIP_WIDTH].iter_mut().zip(&kernel()[phase]) {
+            *s += k * delta;
+        }
+    }
+
+    /// End the current frame after `clocks` clocks; its output becomes readable
+    pub fn end_frame(&mut self, clocks: u64) {
+        self.offset += clocks * self.factor;
+        let needed = self.samples_avail() + BLIP_WIDTH;
+        if self.buf.len() < needed { self.buf.resize(needed, 0); }
+    }
+
+    /// Read up to `out.len()` samples; returns how many were written
+    pub fn read_samples(&mut self, out: &mut [i16]) -> usize {
+        let n = out.len().min(self.samples_avail());
+        for (o, &d) in out[..n].iter_mut().zip(&self.buf) {
+            self.integrator += d as i64;
+            *o = (self.integrator >> DELTA_BITS).clamp(i16::MIN as i64, i16::MAX as i64) as i16;
+        }
+        self.remove(n);
+        n
+    }
+
+    /// Discard up to `n` readable samples (still integrated, so later output
+    /// stays at the right level)
+    pub fn skip_samples(&mut self, n: usize) {
+        let n = n.min(self.samples_avail());
+        self.integrator += self.buf[..n].iter().map(|&d| d as i64).sum::<i64>();
+        self.remove(n);
+    }
+
+    fn remove(&mut self, n: usize) {
+        self.buf.drain(..n);
+        if self.buf.len() < BLIP_WIDTH * 2 { self.buf.resize(BLIP_WIDTH * 2, 0); }
+        self.offset -= (n as u64) << FRAC_BITS;
+    }
+}
+
+/// Band-limited impulse taps for each phase, each summing to `1 << DELTA_BITS`
+fn kernel() -> &'static [[i32; BLIP_WIDTH]; BLIP_PHASES] {
+    static KERNEL: OnceLock<[[i32; BLIP_WIDTH]; BLIP_PHASES]> = OnceLock::new();
+    KERNEL.get_or_init(|| {
+        let mut table = [[0i32; BLIP_WIDTH]; BLIP_PHASES];
+        let half = BLIP_WIDTH as f64 / 2.0;
+        for (phase, taps) in table.iter_mut().enumerate() {
+            let frac = phase as f64 / BLIP_PHASES as f64;
+            let mut shape = [0f64; BLIP_WIDTH];
+            for (k, v) in shape.iter_mut().enumerate() {
+                // Tap k is output sample index+k; the step sits at index+frac+half
+                let t = k as f64 - frac - half + 1.0;
+                let x = std::f64::consts::PI * BLIP_CUTOFF * t;
+                let sinc = if x == 0.0 { 1.0 } else { x.sin() / x };
+                let u = (t / half).clamp(-1.0, 1.0) * std::f64::consts::PI;
+                let blackman = 0.42 + 0.5 * u.cos() + 0.08 * (2.0 * u).cos();
+                *v = sinc * blackman;
+            }
+            let total: f64 = shape.iter().sum();
+            let unit = (1i32 << DELTA_BITS) as f64;
+            for (tap, v) in taps.iter_mut().zip(shape) { *tap = (v / total * unit).round() as i32; }
+            // Rounding leftovers go to the largest tap so the step is exact
+            let error = (1i32 << DELTA_BITS) - taps.iter().sum::<i32>();
+            let peak = (0..BLIP_WIDTH).max_by_key(|&k| taps[k]).unwrap_or(0);
+            taps[peak] += error;
+        }
+        table
+    })
+}
@@ -0,0 +1,208 @@
+//! block_cache — pre-decoded straight-line code for the interpreter
+//!
+//! With `Bus::block_cache` set, `step` takes each opcode and its immediates
+//! from a cached basic block instead of fetching them through the bus. A
+//! block is the run of instructions from an entry point up to and including
+//! the first branch, call, return, RST, HALT or STOP (at most
+//! `BLOCK_MAX_OPS`), keyed by (bank, entry PC): the ROM bank mapped at the
+//! PC, or the WRAM bank for D000-DFFF. Only ROM, WRAM (C000-DFFF) and HRAM
+//! code is cached; anything else runs through the bus as before.
+//!
+//! `Bus::write` drops every RAM block covering the written byte, so
+//! self-modifying code and routines copied into HRAM stay correct. Writes to
+//! the MBC range or SVBK only end the block being run (its bank may have
+//! been switched out). Host-side changes that skip `Bus::write` (poking
+//! `bus.wram` directly, say) must `clear()` the cache; `load_state` does.
+//!
+//! The cache is bypassed while OAM DMA holds the bus, while watchpoints are
+//! set, under `OpenBusPolicy::LastValue` and for the halt bug's re-fetch,
+//! where the bus access itself matters.
+
+use crate::{Bus, OPCODES};
+use std::collections::HashMap;
+
+/// Longest block decoded at once
+pub const BLOCK_MAX_OPS: usize = 32;
+/// Blocks kept before the cache starts over
+const MAX_BLOCKS: usize = 1 << 15;
+/// RAM blocks are tracked in 128-byte chunks (keeps IO writes off HRAM code)
+const CHUNK_SHIFT: u16 = 7;
+
+/// One pre-decoded instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachedOp {
+    pub pc: u16,
+    pub op: u8,
+    /// The bytes after the opcode (0 past the instruction's length)
+    pub operands: [u8; 2],
+    pub len: u8,
+}
+
+impl CachedOp {
+    /// Last byte of the instruction (the imm8 of a 2-byte instruction)
+    pub fn imm8(&self) -> u8 {
+        match self.len { 1 => self.op, 2 => self.operands[0], _ => self.operands[1] }
+    }
+    /// Little-endian immediate of a 3-byte instruction
+    pub fn imm16(&self) -> u16 { u16::from_le_bytes(self.operands) }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockCacheStats {
+    /// Instructions served from the cache
+    pub hits: u64,
+    /// Instructions fetched through the bus (code outside ROM / WRAM / HRAM)
+    pub uncached: u64,
+    /// Blocks decoded
+    pub built: u64,
+    /// Blocks dropped by writes to their bytes
+    pub invalidated: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Block {
+    key: u32,
+    start: u16,
+    /// Last byte covered
+    last: u16,
+    ops: Vec<CachedOp>,
+}
+
+#[derive(Debug, Default)]
+pub struct BlockCache {
+    blocks: Vec<Block>,
+    free: Vec<usize>,
+    index: HashMap<u32, usize>,
+    /// Chunks holding RAM blocks, one bit each
+    ram_chunks: [u64; 8],
+    chunk_blocks: HashMap<u16, Vec<usize>>,
+    /// Block being run and the index of its next instruction
+    cursor: Option<(usize, usize)>,
+    pub stats: BlockCacheStats,
+}
+
+/// Cache key for code at `pc`; None where code is not cached
+fn block_key(bus: &Bus, pc: u16) -> Option<u32> {
+    let bank = match pc {
+        0x0000..=0x7FFF => bus.mbc.rom_addr(pc) / 0x4000,
+        0xD000..=0xDFFF => bus.wram_bank as usize,
+        0xC000..=0xCFFF | 0xFF80..=0xFFFE => 0,
+        _ => return None,
+    };
+    Some((bank as u32) << 16 | pc as u32)
+}
+
+/// Last address of the region (and bank window) `pc` is in
+fn region_last(pc: u16) -> u16 {
+    match pc {
+        0x0000..=0x3FFF => 0x3FFF,
+        0x4000..=0x7FFF => 0x7FFF,
+        0xC000..=0xCFFF => 0xCFFF,
+        0xD000..=0xDFFF => 0xDFFF,
+        _ => 0xFFFE,
+    }
+}
+
+/// Instructions that leave straight-line code
+fn ends_block(op: u8) -> bool {
+    let name = OPCODES[op as usize].mnemonic;
+    matches!(name.split(' ').next(), Some("jr" | "jp" | "call" | "ret" | "reti" | "rst" | "halt" | "stop"))
+}
+
+impl BlockCache {
+    pub fn new() -> Self { Self::default() }
+
+    /// Blocks currently cached
+    pub fn len(&self) -> usize { self.index.len() }
+    pub fn is_empty(&self) -> bool { self.index.is_empty() }
+
+    /// Drop every block (statistics are kept)
+    pub fn clear(&mut self) { *self = BlockCache { stats: self.stats, ..Default::default() }; }
+
+    /// The instruction at `pc`, from the block being run or the block
+    /// entered there (decoded on a miss); None for uncached code
+    pub(crate) fn fetch(&mut self, bus: &Bus, pc: u16) -> Option<CachedOp> {
+        if let Some((b, i)) = self.cursor {
+            if let Some(op) = self.blocks[b].ops.get(i).filter(|op| op.pc == pc) {
+                self.cursor = Some((b, i + 1));
+                self.stats.hits += 1;
+                return Some(*op);
+            }
+        }
+        let Some(key) = block_key(bus, pc) else {
+            self.cursor = None;
+            self.stats.uncached += 1;
+            return None;
+        };
+        let b = match self.index.get(&key) {
+            Some(&b) => b,
+            None => match self.build(bus, pc, key) {
+                Some(b) => b,
+                None => { self.cursor = None; self.stats.uncached += 1; return None; }
+            },
+        };
+        self.cursor = Some((b, 1));
+        self.stats.hits += 1;
+        Some(self.blocks[b].ops[0])
+    }
+
+    fn build(&mut self, bus: &Bus, start: u16, key: u32) -> Option<usize> {
+        let end = region_last(start) as u32;
+        let mut ops = Vec::new();
+        let mut pc = start as u32;
+        while ops.len() < BLOCK_MAX_OPS {
+            let op = bus.peek(pc as u16);
+            let info = &OPCODES[op as usize];
+            if info.is_illegal() || pc + info.len as u32 - 1 > end { break; }
+            let byte = |n: u32| if n < info.len as u32 { bus.peek((pc + n) as u16) } else { 0 };
+            ops.push(CachedOp { pc: pc as u16, op, operands: [byte(1), byte(2)], len: info.len });
+            pc += info.len as u32;
+            if ends_block(op) { break; }
+        }
+        if ops.is_empty() { return None; }
+        if self.index.len() >= MAX_BLOCKS { self.clear(); }
+        let block = Block { key, start, last: (pc - 1) as u16, ops };
+        let b = match self.free.pop() {
+            Some(b) => { self.blocks[b] = block; b }
+            None => { self.blocks.push(block); self.blocks.len() - 1 }
+        };
+        self.index.insert(key, b);
+        if start >= 0x8000 {
+            for chunk in start >> CHUNK_SHIFT..=self.blocks[b].last >> CHUNK_SHIFT {
+                self.ram_chunks[chunk as usize / 64] |= 1 << (chunk % 64);
+                self.chunk_blocks.entry(chunk).or_default().push(b);
+            }
+        }
+        self.stats.built += 1;
+        Some(b)
+    }
+
+    /// A CPU write to `addr`: drop the RAM blocks it lands in
+    pub(crate) fn on_write(&mut self, addr: u16) {
+        // MBC and SVBK writes may switch the running block's bank out
+        if addr < 0x8000 || addr == 0xFF70 { self.cursor = None; return; }
+        let chunk = addr >> CHUNK_SHIFT;
+        if self.ram_chunks[chunk as usize / 64] & 1 << (chunk % 64) == 0 { return; }
+        let hit: Vec<usize> = self.chunk_blocks.get(&chunk).into_iter().flatten().copied()
+            .filter(|&b| (self.blocks[b].start..=self.blocks[b].last).contains(&addr))
+            .collect();
+        if hit.is_empty() { return; }
+        for b in hit { self.remove(b); }
+        self.cursor = None;
+    }
+
+    fn remove(&mut self, b: usize) {
+        let block = std::mem::take(&mut self.blocks[b]);
+        self.index.remove(&block.key);
+        for chunk in block.start >> CHUNK_SHIFT..=block.last >> CHUNK_SHIFT {
+            let Some(list) = self.chunk_blocks.get_mut(&chunk) else { continue };
+            list.retain(|&x| x != b);
+            if list.is_empty() {
+                self.chunk_blocks.remove(&chunk);
+                self.ram_chunks[chunk as usize / 64] &= !(1 << (chunk % 64));
+            }
+        }
+        self.free.push(b);
+        self.stats.invalidated += 1;
+    }
+}
@@ -0,0 +1,188 @@
+//! breakpoints — PC breakpoints with register conditions and hit counts
+//!
+//! `GbCore::breakpoints` is checked before each instruction is fetched. When
+//! one fires, `step` / `run_frame` return `Err(CoreError::Break(hit))` with
+//! the instruction at the breakpoint not yet executed, the same way an
+//! interrupt handle stops a frame early. Calling `step` / `run_frame` again
+//! resumes: the instruction the core stopped at runs without re-checking.
+//!
+//! Every time PC reaches a breakpoint and its condition holds, its hit count
+//! goes up; it only stops the core once `hits > ignore`, so "stop on the
+//! fifth pass" is `ignore = 4`. With no breakpoints set the check is one
+//! `is_empty` per instruction.
+
+use crate::Registers;
+use std::fmt;
+
+/// Register a condition compares (8-bit registers and 16-bit pairs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakReg { A, F, B, C, D, E, H, L, AF, BC, DE, HL, SP }
+
+impl BreakReg {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BreakReg::A => "a", BreakReg::F => "f", BreakReg::B => "b", BreakReg::C => "c",
+            BreakReg::D => "d", BreakReg::E => "e", BreakReg::H => "h", BreakReg::L => "l",
+            BreakReg::AF => "af", BreakReg::BC => "bc", BreakReg::DE => "de", BreakReg::HL => "hl",
+            BreakReg::SP => "sp",
+        }
+    }
+    pub fn parse(s: &str) -> Option<BreakReg> {
+        Some(match s.to_ascii_lowercase().as_str() {
+            "a" => BreakReg::A, "f" => BreakReg::F, "b" => BreakReg::B, "c" => BreakReg::C,
+            "d" => BreakReg::D, "e" => BreakReg::E, "h" => BreakReg::H, "l" => BreakReg::L,
+            "af" => BreakReg::AF, "bc" => BreakReg::BC, "de" => BreakReg::DE, "hl" => BreakReg::HL,
+            "sp" => BreakReg::SP,
+            _ => return None,
+        })
+    }
+    pub fn read(self, r: &Registers) -> u16 {
+        match self {
+            BreakReg::A => r.a as u16, BreakReg::F => r.f as u16, BreakReg::B => r.b as u16,
+            BreakReg::C => r.c as u16, BreakReg::D => r.d as u16, BreakReg::E => r.e as u16,
+            BreakReg::H => r.h as u16, BreakReg::L => r.l as u16,
+            BreakReg::AF => r.af(), BreakReg::BC => r.bc(), BreakReg::DE => r.de(), BreakReg::HL => r.hl(),
+            BreakReg::SP => r.sp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakCmp { Eq, Ne, Lt, Le, Gt, Ge }
+
+impl BreakCmp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BreakCmp::Eq => "==", BreakCmp::Ne => "!=", BreakCmp::Lt => "<",
+            BreakCmp::Le => "<=", BreakCmp::Gt => ">", BreakCmp::Ge => ">=",
+        }
+    }
+}
+
+/// `reg <cmp> value`, e.g. `a == 0x42` or `hl >= 0xC000`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakCondition {
+    pub reg: BreakReg,
+    pub cmp: BreakCmp,
+    pub value: u16,
+}
+
+impl BreakCondition {
+    pub fn new(reg: BreakReg, cmp: BreakCmp, value: u16) -> Self { BreakCondition { reg, cmp, value } }
+
+    /// Parse `reg op value`; value is decimal, `0x` / `$` hex
+    pub fn parse(s: &str) -> Option<BreakCondition> {
+        // Two-character operators first so `<=` is not read as `<`
+        const OPS: [(&str, BreakCmp); 6] = [
+            ("==", BreakCmp::Eq), ("!=", BreakCmp::Ne), ("<=", BreakCmp::Le),
+            (">=", BreakCmp::Ge), ("<", BreakCmp::Lt), (">", BreakCmp::Gt),
+        ];
+        let (at, op, cmp) = OPS.iter().filter_map(|&(op, cmp)| s.find(op).map(|at| (at, op, cmp))).min_by_key(|&(at, _, _)| at)?;
+        let reg = BreakReg::parse(s[..at].trim())?;
+        let v = s[at + op.len()..].trim();
+        let value = match v.strip_prefix("0x").or_else(|| v.strip_prefix("0X")).or_else(|| v.strip_prefix('$')) {
+            Some(hex) => u16::from_str_radix(hex, 16).ok()?,
+            None => v.parse().ok()?,
+        };
+        Some(BreakCondition { reg, cmp, value })
+    }
+
+    pub fn holds(&self, regs: &Registers) -> bool {
+        let v = self.reg.read(regs);
+        match self.cmp {
+            BreakCmp::Eq => v == self.value, BreakCmp::Ne => v != self.value,
+            BreakCmp::Lt => v < self.value, BreakCmp::Le => v <= self.value,
+            BreakCmp::Gt => v > self.value, BreakCmp::Ge => v >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for BreakCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} 0x{:X}", self.reg.as_str(), self.cmp.as_str(), self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub id: u32,
+    pub pc: u16,
+    /// All must hold for the breakpoint to count a hit
+    pub conditions: Vec<BreakCondition>,
+    pub enabled: bool,
+    /// Times PC reached `pc` with the conditions holding
+    pub hits: u64,
+    /// Hits to pass over before stopping
+    pub ignore: u64,
+}
+
+/// Why `step` / `run_frame` stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakHit {
+    pub id: u32,
+    pub pc: u16,
+    /// The breakpoint's hit count, including this hit
+    pub hits: u64,
+    pub t_cycles: u64,
+}
+
+impl fmt::Display for BreakHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "breakpoint {} at {:04X} (hit {})", self.id, self.pc, self.hits)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    list: Vec<Breakpoint>,
+    next_id: u32,
+    /// PC the core last stopped at; the next check there lets it run
+    resume_pc: Option<u16>,
+}
+
+impl Breakpoints {
+    /// Stop whenever PC reaches `pc`; returns the breakpoint's id
+    pub fn add(&mut self, pc: u16) -> u32 { self.add_if(pc, vec![]) }
+    /// Stop when PC reaches `pc` and every condition holds
+    pub fn add_if(&mut self, pc: u16, conditions: Vec<BreakCondition>) -> u32 {
+        self.next_id += 1;
+        self.list.push(Breakpoint { id: self.next_id, pc, conditions, enabled: true, hits: 0, ignore: 0 });
+        self.next_id
+    }
+    pub fn remove(&mut self, id: u32) -> bool {
+        let before = self.list.len();
+        self.list.retain(|b| b.id != id);
+        self.list.len() != before
+    }
+    pub fn get(&self, id: u32) -> Option<&Breakpoint> { self.list.iter().find(|b| b.id == id) }
+    /// Change `enabled`, `ignore` or the conditions of a breakpoint
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Breakpoint> { self.list.iter_mut().find(|b| b.id == id) }
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> { self.list.iter() }
+    pub fn len(&self) -> usize { self.list.len() }
+    pub fn is_empty(&self) -> bool { self.list.is_empty() }
+    pub fn clear(&mut self) { self.list.clear(); self.resume_pc = None; }
+
+    /// Called before the instruction at `regs.pc` is fetched
+    pub(crate) fn check(&mut self, regs: &Registers, t_cycles: u64) -> Option<BreakHit> {
+        if self.resume_pc.take() == Some(regs.pc) { return None; }
+        let mut stop = None;
+        for b in self.list.iter_mut().filter(|b| b.enabled && b.pc == regs.pc) {
+            if !b.conditions.iter().all(|c| c.holds(regs)) { continue; }
+            b.hits += 1;
+            if stop.is_none() && b.hits > b.ignore {
+                stop = Some(BreakHit { id: b.id, pc: b.pc, hits: b.hits, t_cycles });
+            }
+        }
+        if stop.is_some() { self.resume_pc = Some(regs.pc); }
+        stop
+    }
+
+    /// The first enabled breakpoint at `regs.pc` whose conditions hold, without
+    /// counting a hit (`reverse_continue` looks for these in the past)
+    pub(crate) fn matching(&self, regs: &Registers) -> Option<&Breakpoint> {
+        self.list.iter().find(|b| b.enabled && b.pc == regs.pc && b.conditions.iter().all(|c| c.holds(regs)))
+    }
+
+    /// Let the next check at `pc` pass, as after a stop there
+    pub(crate) fn resume_at(&mut self, pc: u16) { self.resume_pc = Some(pc); }
+}
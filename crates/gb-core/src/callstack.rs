@@ -0,0 +1,75 @@
+//! callstack — shadow call stack for crash triage and frame labelling
+//!
+//! The SM83 has no frame pointer, so the real stack cannot be walked
+//! reliably. Instead the core records every CALL, RST and interrupt dispatch
+//! as it happens and drops frames again on RET / RETI. `GbCore::call_stack()`
+//! returns the result, outermost first.
+//!
+//! Frames are kept by the SP their return address was pushed at. A return
+//! drops every frame at or below the SP it popped from, and a new push drops
+//! stale frames whose slot it reuses, so code that discards return addresses
+//! (POP, LD SP) or resets the stack cannot grow the shadow stack without
+//! bound. Savestates do not carry it; `load_state` starts an empty one.
+
+/// How a frame was entered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind { Call, Rst, Interrupt }
+
+impl CallKind {
+    pub fn as_str(self) -> &'static str {
+        match self { CallKind::Call => "call", CallKind::Rst => "rst", CallKind::Interrupt => "interrupt" }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackFrame {
+    /// Address of the CALL / RST, or the interrupted PC
+    pub caller: u16,
+    /// Subroutine entry (call target, RST or interrupt vector)
+    pub target: u16,
+    /// SP after the return address was pushed
+    pub sp: u16,
+    pub kind: CallKind,
+}
+
+impl StackFrame {
+    pub fn to_json(&self) -> String {
+        format!("{{\"caller\":{},\"target\":{},\"sp\":{},\"kind\":\"{}\"}}", self.caller, self.target, self.sp, self.kind.as_str())
+    }
+}
+
+/// Frames deeper than this are dropped from the bottom (SP wrapped around)
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, Default)]
+pub struct ShadowStack {
+    frames: Vec<StackFrame>,
+}
+
+impl ShadowStack {
+    pub fn frames(&self) -> &[StackFrame] { &self.frames }
+    /// Innermost subroutine's entry point
+    pub fn current(&self) -> Option<u16> { self.frames.last().map(|f| f.target) }
+    pub fn clear(&mut self) { self.frames.clear(); }
+
+    pub(crate) fn push(&mut self, frame: StackFrame) {
+        // Slots at or below the new return address were popped some other way
+        while self.frames.last().is_some_and(|f| f.sp <= frame.sp) { self.frames.pop(); }
+        if self.frames.len() == MAX_DEPTH { self.frames.remove(0); }
+        self.frames.push(frame);
+    }
+
+    /// After the instruction `op` at `pc` ran with SP `sp_before`; `pc_after`
+    /// and `sp_after` are the registers it left
+    pub(crate) fn track(&mut self, op: u8, pc: u16, sp_before: u16, pc_after: u16, sp_after: u16) {
+        let pushed = sp_after == sp_before.wrapping_sub(2);
+        match op {
+            0xCD | 0xC4 | 0xCC | 0xD4 | 0xDC if pushed => self.push(StackFrame { caller: pc, target: pc_after, sp: sp_after, kind: CallKind::Call }),
+            0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => self.push(StackFrame { caller: pc, target: pc_after, sp: sp_after, kind: CallKind::Rst }),
+            0xC9 | 0xD9 | 0xC0 | 0xC8 | 0xD0 | 0xD8 if sp_after == sp_before.wrapping_add(2) => {
+                while self.frames.last().is_some_and(|f| f.sp <= sp_before) { self.frames.pop(); }
+            }
+            _ => {}
+        }
+    }
+}
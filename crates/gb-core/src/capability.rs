@@ -0,0 +1,62 @@
+//! capability — gb-core described as a UCF capability graph
+//!
+//! `capability_profile()` is a `CapabilityGraph` document (ucf-planner's
+//! `capability_version` 0.1) for the emulator itself: what a game running
+//! on gb-core sees (SM83, DMG / CGB PPU, joypad and cartridge sensors, a
+//! 59.73 Hz LCD, T-cycle timing) plus what the core offers its host. The
+//! planner can then treat the core as a platform layer — plan a Game Boy
+//! artifact against it, or stack it on a host graph — instead of knowing
+//! only the host hardware. Core-specific facts the schema has no field for
+//! (models, determinism, save states, audio format) sit in `cpu.features`,
+//! the video format in `gpu.features`.
+
+use crate::{APU_SAMPLE_RATE, CPU_HZ, CYCLES_PER_FRAME, LCD_HEIGHT, LCD_WIDTH};
+
+/// `platform_id` of the profile (also gb-core's ecore `core_id`)
+pub const CAPABILITY_PLATFORM_ID: &str = "gb_core";
+/// `profiles.source` of the profile
+pub const CAPABILITY_SOURCE: &str = "gb_core_self_description";
+
+/// gb-core as a `CapabilityGraph` JSON document
+pub fn capability_profile() -> String {
+    let frame_hz = CPU_HZ as f64 / CYCLES_PER_FRAME as f64;
+    let t_cycle_ns = 1e9 / CPU_HZ as f64;
+    format!(
+        r#"{{
+  "capability_version": "0.1",
+  "platform_id": "{CAPABILITY_PLATFORM_ID}",
+  "label": "MetaROM gb-core {version} (DMG / MGB / SGB / CGB)",
+  "class": "emulator_core",
+  "host_os": {{"family": "gb_bare_metal", "version": "{version}", "abi": [], "syscalls": []}},
+  "cpu": {{
+    "isas": ["sm83"], "cores": 1, "threads": 1, "clock_mhz": {double_mhz}, "simd": [],
+    "features": {{
+      "models": ["dmg", "mgb", "sgb", "cgb"],
+      "double_speed": true,
+      "deterministic": true,
+      "t_cycle_ns": {t_cycle_ns:.3},
+      "cycles_per_frame": {CYCLES_PER_FRAME},
+      "save_states": {{"format": "mrom.sav.v1", "save_points": ["instruction", "frame"], "imports": ["gambatte", "vba-m"]}},
+      "audio": {{"sample_rate_hz": {APU_SAMPLE_RATE}, "channels": 2, "format": "pcm_s16"}}
+    }}
+  }},
+  "gpu": {{
+    "apis": ["dmg_ppu", "cgb_ppu"], "shader_models": [], "vram_mb": 0, "throughput_hint": {{}},
+    "features": {{"framebuffer": "rgb888", "width": {LCD_WIDTH}, "height": {LCD_HEIGHT}}}
+  }},
+  "memory": {{"ram_mb": 0, "bandwidth_gbps": 0.0, "storage": {{"internal_mb": 0, "streaming_read_mbps": 0.0, "seek_latency_ms": 0.0}}}},
+  "io": {{
+    "inputs": ["gb_joypad", "ir_port", "link_cable"],
+    "audio_out": true,
+    "video_out": ["lcd_160x144_2bpp", "lcd_160x144_15bit"],
+    "network": {{"available": false, "bandwidth_mbps": null, "rtt_ms": null, "jitter_ms": null}}
+  }},
+  "timing": {{"display_modes_hz": [{frame_hz:.4}], "timer_resolution_us": 1, "interrupt_model": "vectored_ime"}},
+  "security": {{"unsigned_code_allowed": true, "external_coprocessor_support": "no"}},
+  "legal": {{"firmware_required": false, "redistributable_firmware": false}},
+  "profiles": {{"measured": false, "source": "{CAPABILITY_SOURCE}"}}
+}}"#,
+        version = env!("CARGO_PKG_VERSION"),
+        double_mhz = CPU_HZ as f64 * 2.0 / 1e6,
+    )
+}
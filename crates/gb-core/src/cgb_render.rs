@@ -0,0 +1,73 @@
+//! cgb_render — CGB-mode BG attributes, sprite attributes and colour output
+//!
+//! A CGB running a CGB cartridge (`HardwareModel::cgb_mode`) draws with
+//! `Ppu::cgb` set. Each BG / window map entry then has an attribute byte at
+//! the same address in VRAM bank 1:
+//!
+//! - bits 0-2: BG colour palette (BCPS / BCPD, FF68 / FF69)
+//! - bit 3: tile data from VRAM bank 1
+//! - bit 5 / bit 6: horizontal / vertical flip
+//! - bit 7: BG-to-OAM priority; colours 1-3 of the tile cover sprites
+//!
+//! LCDC bit 0 changes meaning too: the BG and window are always drawn, and
+//! clearing the bit turns off every BG-over-sprite priority (the attribute
+//! bit 7 and OAM bit 7 alike) instead of blanking the BG as on a DMG.
+//!
+//! BGP no longer applies. `Ppu::framebuffer` holds the raw colour number
+//! (0-3) and `Ppu::pixel_source` the CGB palette it is drawn with: 0-7 for
+//! the BG palettes, `CGB_OBJ_SOURCE` + 0-7 for the OBJ palettes. Sprites
+//! take their OBJ palette (OCPS / OCPD, FF6A / FF6B) from OAM attribute
+//! bits 0-2 and their tile from the VRAM bank in bit 3; the DMG palette bit
+//! 4 is ignored, and the lower OAM index wins between overlapping sprites
+//! whatever their X. `GbCore::framebuffer_rgb` maps each pixel through
+//! palette RAM, RGB555 to RGB888 (`Bus::cgb_color`), so DMG colour schemes
+//! and palette packs do not apply. Both renderers (line and pixel FIFO)
+//! follow these rules.
+
+use crate::{bg_tile_planes, Bus};
+
+/// `Ppu::pixel_source` of the first OBJ palette in CGB mode
+pub const CGB_OBJ_SOURCE: u8 = 8;
+
+/// A BG / window map entry's VRAM bank 1 attribute byte
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BgAttr(pub u8);
+
+impl BgAttr {
+    pub fn palette(self) -> u8 { self.0 & 0x07 }
+    pub fn bank(self) -> usize { (self.0 >> 3 & 1) as usize }
+    pub fn x_flip(self) -> bool { self.0 & 0x20 != 0 }
+    pub fn y_flip(self) -> bool { self.0 & 0x40 != 0 }
+    /// Colours 1-3 of this tile are drawn over sprites
+    pub fn priority(self) -> bool { self.0 & 0x80 != 0 }
+}
+
+/// LCDC bit 0 lets the BG layer draw: always in CGB mode
+pub(crate) fn bg_enabled(lcdc: u8, cgb: bool) -> bool { lcdc & 0x01 != 0 || cgb }
+
+/// BG colours 1-3 may cover sprites: in CGB mode only while LCDC bit 0 is
+/// set (master priority)
+pub(crate) fn bg_priority_enabled(lcdc: u8, cgb: bool) -> bool { lcdc & 0x01 != 0 || !cgb }
+
+/// Bit planes and attributes of row `prow` of the tile at map address
+/// `map` (0x1800-0x1FFF); attributes are all clear outside CGB mode
+pub(crate) fn bg_tile(vram: [&[u8; 0x2000]; 2], lcdc: u8, cgb: bool, map: usize, prow: usize) -> ([u8; 2], BgAttr) {
+    let attr = if cgb { BgAttr(vram[1][map]) } else { BgAttr(0) };
+    (tile_planes(vram, lcdc, vram[0][map], attr, prow), attr)
+}
+
+/// Bit planes of row `prow` of tile `idx` drawn with `attr`: bank and flips
+/// applied, so the leftmost pixel is still bit 7
+pub(crate) fn tile_planes(vram: [&[u8; 0x2000]; 2], lcdc: u8, idx: u8, attr: BgAttr, prow: usize) -> [u8; 2] {
+    let row = if attr.y_flip() { 7 - prow } else { prow };
+    let planes = bg_tile_planes(vram[attr.bank()], lcdc, idx, row);
+    if attr.x_flip() { planes.map(u8::reverse_bits) } else { planes }
+}
+
+/// RGB888 for each CGB `pixel_source` and colour number, from palette RAM
+pub fn cgb_colors(bus: &Bus) -> [[(u8, u8, u8); 4]; 16] {
+    let mut out = [[(0, 0, 0); 4]; 16];
+    out[..8].copy_from_slice(&bus.bg_palette_rgb());
+    out[CGB_OBJ_SOURCE as usize..].copy_from_slice(&bus.obj_palette_rgb());
+    out
+}
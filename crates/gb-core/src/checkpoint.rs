@@ -0,0 +1,259 @@
+//! checkpoint — verification checkpoints with frame-perfect state export
+//!
+//! A verification harness states what a correct run must show, one
+//! checkpoint per line, each tagged with the UCF equivalence level it is
+//! evidence for:
+//!
+//! ```text
+//! # id: condition within N [-> LEVEL]
+//! boot:   pc 0150 within 10 -> L0_BOOT
+//! video:  ly cycles within 2 -> L1_STABLE
+//! title:  $C0A0 == 3 within 300 -> L2_INTERACTIVE
+//! passed: serial "Passed" within 3600 -> L3_GAMEPLAY_EQ
+//! ```
+//!
+//! `run_checkpoints` steps the core instruction by instruction and checks
+//! every open checkpoint after each one: `pc` before the instruction there
+//! runs (the same point a breakpoint stops at), `ly cycles` once LY has
+//! taken all 154 values, memory and serial conditions as soon as they
+//! hold. The moment a checkpoint passes, the run pauses to record the
+//! frame, cycle, `state_hash()` and a `save_state()` of that exact machine
+//! state. A checkpoint still open after `within` frames fails.
+//!
+//! The report (mrom.checkpoints.v1) carries every result and the highest
+//! equivalence level whose checkpoints, and all those below it, passed;
+//! the planner's `evaluate_verification` checks it against a plan's
+//! `VerificationTarget`. The level defaults to `L0_BOOT`.
+
+use crate::settings::esc;
+use crate::{write_sealed, GbCore, CoreError, CYCLES_PER_FRAME};
+use std::path::Path;
+
+pub const CHECKPOINTS_VERSION: &str = "mrom.checkpoints.v1";
+
+/// UCF equivalence levels, weakest first
+pub const EQUIVALENCE_LEVELS: [&str; 6] =
+    ["L0_BOOT", "L1_STABLE", "L2_INTERACTIVE", "L3_GAMEPLAY_EQ", "L4_RENDER_EQ", "L5_BIT_EXACT"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckpointCondition {
+    /// PC reaches the address
+    Pc(u16),
+    /// LY takes every value 0-153
+    LyCycles,
+    /// The byte at `addr` equals `value`
+    MemEquals { addr: u16, value: u8 },
+    /// The serial transcript contains the text
+    Serial(String),
+}
+
+impl CheckpointCondition {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CheckpointCondition::Pc(_) => "pc",
+            CheckpointCondition::LyCycles => "ly_cycles",
+            CheckpointCondition::MemEquals { .. } => "mem_equals",
+            CheckpointCondition::Serial(_) => "serial",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub id: String,
+    pub condition: CheckpointCondition,
+    /// Frames from the start of the run the condition has to hold within
+    pub within_frames: u64,
+    /// Index into `EQUIVALENCE_LEVELS`
+    pub level: usize,
+}
+
+fn parse_hex(s: &str) -> Option<u16> {
+    u16::from_str_radix(s.trim_start_matches("0x").trim_start_matches('$'), 16).ok()
+}
+
+impl Checkpoint {
+    /// Parse one `id: condition within N [-> LEVEL]` line
+    pub fn parse(line: &str) -> Result<Checkpoint, String> {
+        let (id, rest) = line.split_once(':').ok_or("expected `id: condition within N`")?;
+        let id = id.trim();
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("bad id {id:?}"));
+        }
+        let (expr, level) = rest.split_once("->").unwrap_or((rest, "L0_BOOT"));
+        let level = EQUIVALENCE_LEVELS.iter().position(|l| l.eq_ignore_ascii_case(level.trim()))
+            .ok_or(format!("unknown equivalence level {:?}", level.trim()))?;
+        let (cond, within) = expr.rsplit_once(" within ").ok_or("expected `within N` frames")?;
+        let within_frames = within.trim().parse().map_err(|_| format!("bad frame count {:?}", within.trim()))?;
+        let cond = cond.trim();
+        let condition = if let Some(text) = cond.strip_prefix("serial ") {
+            let text = text.trim();
+            let text = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).ok_or("serial text must be quoted")?;
+            CheckpointCondition::Serial(text.to_string())
+        } else if cond.eq_ignore_ascii_case("ly cycles") {
+            CheckpointCondition::LyCycles
+        } else if let Some(pc) = cond.strip_prefix("pc ") {
+            CheckpointCondition::Pc(parse_hex(pc.trim()).ok_or(format!("bad address {:?}", pc.trim()))?)
+        } else if let Some((addr, value)) = cond.split_once("==") {
+            let addr = parse_hex(addr.trim()).ok_or(format!("bad address {:?}", addr.trim()))?;
+            let v = value.trim();
+            let value = match v.strip_prefix("0x").or_else(|| v.strip_prefix('$')) {
+                Some(hex) => u8::from_str_radix(hex, 16).ok(),
+                None => v.parse().ok(),
+            }.ok_or(format!("bad value {v:?}"))?;
+            CheckpointCondition::MemEquals { addr, value }
+        } else {
+            return Err(format!("unknown condition {cond:?}"));
+        };
+        Ok(Checkpoint { id: id.to_string(), condition, within_frames, level })
+    }
+
+    pub fn level_name(&self) -> &'static str { EQUIVALENCE_LEVELS[self.level] }
+}
+
+/// One checkpoint per line; `#` starts a comment
+pub fn parse_checkpoints(text: &str) -> Result<Vec<Checkpoint>, String> {
+    let mut checkpoints: Vec<Checkpoint> = vec![];
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() { continue; }
+        let cp = Checkpoint::parse(line).map_err(|e| format!("line {}: {e}", n + 1))?;
+        if checkpoints.iter().any(|c| c.id == cp.id) { return Err(format!("line {}: duplicate id {:?}", n + 1, cp.id)); }
+        checkpoints.push(cp);
+    }
+    Ok(checkpoints)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointResult {
+    pub checkpoint: Checkpoint,
+    pub passed: bool,
+    /// Frame and T-cycle (from the start of the run) it passed at
+    pub frame: Option<u64>,
+    pub t_cycles: Option<u64>,
+    pub detail: String,
+    pub state_hash: Option<u64>,
+    /// `save_state()` bytes at the moment it passed
+    pub state: Option<Vec<u8>>,
+}
+
+impl CheckpointResult {
+    /// File name `write_states` gives the exported state
+    pub fn state_file(&self) -> String { format!("{}.mrom.sav", self.checkpoint.id) }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointReport {
+    pub frames_run: u64,
+    pub results: Vec<CheckpointResult>,
+}
+
+impl CheckpointReport {
+    pub fn passed(&self) -> usize { self.results.iter().filter(|r| r.passed).count() }
+
+    /// Highest level with checkpoints whose checkpoints, and those of every
+    /// level below it, all passed
+    pub fn achieved_level(&self) -> Option<&'static str> {
+        let mut achieved = None;
+        for (level, name) in EQUIVALENCE_LEVELS.iter().enumerate() {
+            let mut at = self.results.iter().filter(|r| r.checkpoint.level == level).peekable();
+            if at.peek().is_none() { continue; }
+            if !at.all(|r| r.passed) { break; }
+            achieved = Some(*name);
+        }
+        achieved
+    }
+
+    pub fn to_json(&self) -> String {
+        let opt = |v: Option<u64>| v.map_or("null".into(), |v| v.to_string());
+        let results: Vec<String> = self.results.iter().map(|r| {
+            let c = &r.checkpoint;
+            let hash = r.state_hash.map_or("null".into(), |h| format!("\"{h:016x}\""));
+            let state = if r.state.is_some() { format!("\"{}\"", r.state_file()) } else { "null".into() };
+            format!(
+                "{{\"id\":\"{}\",\"kind\":\"{}\",\"level\":\"{}\",\"within_frames\":{},\"passed\":{},\"frame\":{},\"t_cycles\":{},\"detail\":\"{}\",\"state_hash\":{hash},\"state\":{state}}}",
+                esc(&c.id), c.condition.kind(), c.level_name(), c.within_frames, r.passed,
+                opt(r.frame), opt(r.t_cycles), esc(&r.detail)
+            )
+        }).collect();
+        let achieved = self.achieved_level().map_or("null".into(), |l| format!("\"{l}\""));
+        format!(
+            "{{\"version\":\"{CHECKPOINTS_VERSION}\",\"frames_run\":{},\"passed\":{},\"total\":{},\"achieved_level\":{achieved},\"results\":[{}]}}",
+            self.frames_run, self.passed(), self.results.len(), results.join(",")
+        )
+    }
+
+    /// Write each exported state as `<id>.mrom.sav` and the report as
+    /// `checkpoints.json` into `dir`
+    pub fn write(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        for r in &self.results {
+            if let Some(st) = &r.state { write_sealed(&dir.join(r.state_file()), st)?; }
+        }
+        std::fs::write(dir.join("checkpoints.json"), self.to_json())
+    }
+}
+
+/// Run `core` until every checkpoint has passed or failed, pausing at each
+/// pass to export the machine state
+pub fn run_checkpoints(core: &mut GbCore, checkpoints: &[Checkpoint]) -> Result<CheckpointReport, CoreError> {
+    let start = core.clock.t_cycles;
+    let mut results: Vec<Option<CheckpointResult>> = vec![None; checkpoints.len()];
+    let mut ly_seen = [false; 154];
+    let mut serial_len = usize::MAX;
+    let horizon = checkpoints.iter().map(|c| c.within_frames).max().unwrap_or(0) * CYCLES_PER_FRAME;
+
+    while results.iter().any(Option::is_none) {
+        let mut hit = None;
+        let budget = horizon.saturating_sub(core.clock.t_cycles - start);
+        core.run_until(|core| {
+            if let Some(seen) = ly_seen.get_mut(core.bus.ppu.ly as usize) { *seen = true; }
+            let serial_grew = core.bus.console.serial().len() != serial_len;
+            serial_len = core.bus.console.serial().len();
+            hit = checkpoints.iter().zip(&results).position(|(c, r)| r.is_none() && match &c.condition {
+                CheckpointCondition::Pc(pc) => core.regs.pc == *pc && !core.halted,
+                CheckpointCondition::LyCycles => ly_seen.iter().all(|&s| s),
+                CheckpointCondition::MemEquals { addr, value } => core.bus.peek(*addr) == *value,
+                CheckpointCondition::Serial(text) => serial_grew && core.bus.console.serial().contains(text.as_str()),
+            });
+            hit.is_some() || (core.clock.t_cycles - start) / CYCLES_PER_FRAME >= next_deadline(checkpoints, &results)
+        }, budget)?;
+
+        let elapsed = core.clock.t_cycles - start;
+        let frame = elapsed / CYCLES_PER_FRAME;
+        match hit {
+            Some(i) if frame < checkpoints[i].within_frames => {
+                let detail = match &checkpoints[i].condition {
+                    CheckpointCondition::Pc(pc) => format!("PC reached {pc:04X} at frame {frame}"),
+                    CheckpointCondition::LyCycles => format!("LY took all 154 values by frame {frame}"),
+                    CheckpointCondition::MemEquals { addr, value } => format!("[{addr:04X}] == {value} at frame {frame}"),
+                    CheckpointCondition::Serial(text) => format!("serial printed {text:?} by frame {frame}"),
+                };
+                results[i] = Some(CheckpointResult {
+                    checkpoint: checkpoints[i].clone(), passed: true, frame: Some(frame), t_cycles: Some(elapsed),
+                    detail, state_hash: Some(core.state_hash()), state: Some(core.save_state()),
+                });
+            }
+            _ => {
+                // Everything open whose window has closed fails
+                for (c, r) in checkpoints.iter().zip(results.iter_mut()) {
+                    if r.is_none() && (frame >= c.within_frames || elapsed >= horizon) {
+                        *r = Some(CheckpointResult {
+                            checkpoint: c.clone(), passed: false, frame: None, t_cycles: None,
+                            detail: format!("not reached within {} frames", c.within_frames), state_hash: None, state: None,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    Ok(CheckpointReport {
+        frames_run: (core.clock.t_cycles - start).div_ceil(CYCLES_PER_FRAME),
+        results: results.into_iter().flatten().collect(),
+    })
+}
+
+/// Earliest `within_frames` among the checkpoints still open
+fn next_deadline(checkpoints: &[Checkpoint], results: &[Option<CheckpointResult>]) -> u64 {
+    checkpoints.iter().zip(results).filter(|(_, r)| r.is_none()).map(|(c, _)| c.within_frames).min().unwrap_or(0)
+}
@@ -0,0 +1,75 @@
+//! console — text console capture (serial out + RAM ring buffer)
+//!
+//! Test ROMs (blargg, mooneye) and a lot of homebrew print through the serial
+//! port; others keep a text ring buffer in RAM. `ConsoleCapture` collects both
+//! into one byte log that hosts read back as UTF-8 for grading and debugging.
+//! Serial bytes also go to a serial-only transcript (`serial()`), so a test
+//! runner can match blargg's "Passed" / "Failed" without RAM console noise.
+//! Console bytes are host-side output and are not part of save states.
+
+/// A text ring buffer kept by the ROM: `len` bytes at `base`, with the ROM's
+/// write index (0..len) stored in the byte at `head`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RamConsole { pub base: u16, pub len: u16, pub head: u16 }
+
+impl RamConsole {
+    /// Parse `BASE:LEN:HEAD` (hex, optional `0x`), e.g. `c000:100:c100`
+    pub fn parse(s: &str) -> Option<RamConsole> {
+        let mut it = s.split(':').map(|p| u16::from_str_radix(p.trim_start_matches("0x"), 16).ok());
+        let (base, len, head) = (it.next()??, it.next()??, it.next()??);
+        if it.next().is_some() || len == 0 { return None; }
+        Some(RamConsole { base, len, head })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConsoleCapture {
+    bytes: Vec<u8>,
+    /// Serial bytes alone, ASCII kept and other bytes as U+FFFD
+    serial: String,
+    /// Optional RAM console polled once per frame
+    pub ram: Option<RamConsole>,
+    ram_cursor: u16,
+}
+
+impl ConsoleCapture {
+    pub fn new() -> Self { Self::default() }
+
+    /// Record one byte shifted out of SB (FF01)
+    pub fn push_serial(&mut self, b: u8) {
+        self.bytes.push(b);
+        self.serial.push(if b.is_ascii() { b as char } else { char::REPLACEMENT_CHARACTER });
+    }
+
+    /// Copy bytes the ROM appended to the RAM console since the last poll.
+    /// `read` is a side-effect-free bus read.
+    pub fn poll_ram(&mut self, read: impl Fn(u16) -> u8) {
+        let Some(rc) = self.ram else { return };
+        let head = read(rc.head) as u16 % rc.len;
+        while self.ram_cursor != head {
+            self.bytes.push(read(rc.base.wrapping_add(self.ram_cursor)));
+            self.ram_cursor = (self.ram_cursor + 1) % rc.len;
+        }
+    }
+
+    /// Point the RAM console somewhere else (or turn it off); restarts the cursor
+    pub fn set_ram(&mut self, ram: Option<RamConsole>) {
+        self.ram = ram;
+        self.ram_cursor = 0;
+    }
+
+    pub fn bytes(&self) -> &[u8] { &self.bytes }
+    pub fn is_empty(&self) -> bool { self.bytes.is_empty() }
+    /// Log so far as UTF-8 (invalid sequences replaced)
+    pub fn text(&self) -> String { String::from_utf8_lossy(&self.bytes).into_owned() }
+    /// Return the log and clear it
+    pub fn take_text(&mut self) -> String {
+        let t = self.text();
+        self.bytes.clear();
+        t
+    }
+    /// Everything shifted out of SB so far (the RAM console is not included)
+    pub fn serial(&self) -> &str { &self.serial }
+    /// Return the serial transcript and clear it
+    pub fn take_serial(&mut self) -> String { std::mem::take(&mut self.serial) }
+}
@@ -0,0 +1,125 @@
+//! corpus — coverage-guided ROM corpus reduction
+//!
+//! A training corpus of thousands of ROMs is mostly repetition: the same
+//! engines exercising the same instructions and hardware registers. Each ROM
+//! is run briefly with `Bus::coverage` enabled, giving a vector of executed
+//! opcodes (base and CB-prefixed) and IO registers written; `select_corpus`
+//! then greedily picks the ROMs that add the most uncovered bits until
+//! nothing new is gained.
+
+use crate::{Cartridge, CoreError, GbCore};
+
+/// Bitmaps of what a run touched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoverageVector {
+    pub opcodes: [u64; 4],
+    pub cb_opcodes: [u64; 4],
+    /// IO registers FF00-FF7F written at least once
+    pub io_writes: [u64; 2],
+}
+
+fn set(bits: &mut [u64], i: u8) { bits[i as usize / 64] |= 1 << (i % 64); }
+fn ones(bits: &[u64]) -> usize { bits.iter().map(|w| w.count_ones() as usize).sum() }
+fn hex(bits: &[u64]) -> String { bits.iter().map(|w| format!("{:016x}", w)).collect() }
+
+impl CoverageVector {
+    /// Record an executed opcode (`cb` = the byte after a 0xCB prefix)
+    pub fn record_op(&mut self, op: u8, cb: Option<u8>) {
+        set(&mut self.opcodes, op);
+        if let Some(cb) = cb { set(&mut self.cb_opcodes, cb); }
+    }
+    /// Record a write to FF00 + `reg`
+    pub fn record_io(&mut self, reg: u8) { set(&mut self.io_writes, reg & 0x7F); }
+
+    fn words(&self) -> [u64; 10] {
+        let mut w = [0u64; 10];
+        w[..4].copy_from_slice(&self.opcodes);
+        w[4..8].copy_from_slice(&self.cb_opcodes);
+        w[8..].copy_from_slice(&self.io_writes);
+        w
+    }
+
+    /// Total bits set
+    pub fn count(&self) -> usize { ones(&self.words()) }
+
+    /// Bits set here but not in `covered`
+    pub fn gain_over(&self, covered: &CoverageVector) -> usize {
+        self.words().iter().zip(covered.words()).map(|(a, b)| (a & !b).count_ones() as usize).sum()
+    }
+
+    pub fn union_with(&mut self, other: &CoverageVector) {
+        for (a, b) in self.opcodes.iter_mut().zip(other.opcodes) { *a |= b; }
+        for (a, b) in self.cb_opcodes.iter_mut().zip(other.cb_opcodes) { *a |= b; }
+        for (a, b) in self.io_writes.iter_mut().zip(other.io_writes) { *a |= b; }
+    }
+
+    /// Counts plus hex bitmaps (bit n of word n/64 = opcode / register n)
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"opcodes\":{},\"cb_opcodes\":{},\"io_writes\":{},\"opcode_bits\":\"{}\",\"cb_bits\":\"{}\",\"io_bits\":\"{}\"}}",
+            ones(&self.opcodes), ones(&self.cb_opcodes), ones(&self.io_writes),
+            hex(&self.opcodes), hex(&self.cb_opcodes), hex(&self.io_writes)
+        )
+    }
+}
+
+/// Run `rom` for `frames` frames and return what it covered. A run that
+/// errors part-way still reports the coverage reached.
+pub fn coverage_of(rom: Vec<u8>, frames: u64) -> Result<CoverageVector, CoreError> {
+    let mut core = GbCore::new(Cartridge::from_bytes(rom)?);
+    core.bus.coverage = Some(Box::default());
+    for _ in 0..frames {
+        if core.run_frame().is_err() { break; }
+    }
+    Ok(core.bus.coverage.map(|c| *c).unwrap_or_default())
+}
+
+/// One greedy pick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pick {
+    /// Index into the candidate list
+    pub index: usize,
+    /// New bits this ROM added
+    pub gain: usize,
+    /// Bits covered after adding it
+    pub covered: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorpusSelection {
+    pub picks: Vec<Pick>,
+    /// Union of the selected ROMs
+    pub covered: CoverageVector,
+    /// Union of every candidate (what an unreduced corpus covers)
+    pub total: CoverageVector,
+}
+
+impl CorpusSelection {
+    /// Fraction of the full corpus' coverage the selection keeps
+    pub fn retained(&self) -> f64 {
+        let total = self.total.count();
+        if total == 0 { 1.0 } else { self.covered.count() as f64 / total as f64 }
+    }
+}
+
+/// Greedy max-coverage: repeatedly take the candidate with the largest gain
+/// (ties → lower index) until no candidate adds anything or `max` are picked
+pub fn select_corpus(candidates: &[CoverageVector], max: Option<usize>) -> CorpusSelection {
+    let mut sel = CorpusSelection::default();
+    for c in candidates { sel.total.union_with(c); }
+    let mut taken = vec![false; candidates.len()];
+    while max.is_none_or(|m| sel.picks.len() < m) {
+        let best = candidates.iter().enumerate()
+            .filter(|(i, _)| !taken[*i])
+            .map(|(i, c)| (i, c.gain_over(&sel.covered)))
+            .fold(None, |best: Option<(usize, usize)>, (i, g)| match best {
+                Some((_, bg)) if bg >= g => best,
+                _ => Some((i, g)),
+            });
+        let Some((index, gain)) = best.filter(|(_, g)| *g > 0) else { break };
+        taken[index] = true;
+        sel.covered.union_with(&candidates[index]);
+        sel.picks.push(Pick { index, gain, covered: sel.covered.count() });
+    }
+    sel
+}
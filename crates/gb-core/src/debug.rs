@@ -0,0 +1,54 @@
+//! debug — structured single-step events for external debuggers
+//!
+//! `GbCore::debug_step` advances the core by one `step` and says what that
+//! step was as a `DebugEvent`, so a debugger front end can drive the core
+//! deterministically without parsing `state_summary`. A breakpoint or
+//! watchpoint that stops `step` comes back as an event rather than an error.
+//!
+//! A step that ends a frame reports its instruction as usual; the following
+//! `debug_step` then returns `FrameCompleted` without advancing the core, so
+//! every call yields exactly one event and no event is folded into another.
+
+use crate::{BreakHit, WatchHit};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugEvent {
+    /// The instruction at `pc` ran
+    InstructionExecuted { pc: u16, opcode: u8, cycles: u8 },
+    /// An interrupt was serviced: `return_pc` pushed, PC now at `vector`
+    /// (0x0000 when the push cancelled it)
+    InterruptDispatched { vector: u16, return_pc: u16, cycles: u8 },
+    /// The CPU idled one M-cycle in HALT
+    Halted { cycles: u8 },
+    /// Stopped before the instruction at the breakpoint; nothing ran
+    BreakpointHit(BreakHit),
+    /// A stopping watchpoint fired; the accessing instruction ran
+    WatchpointHit(WatchHit),
+    /// The previous step reached VBlank; `frame` is `Clock::frame_count()`
+    FrameCompleted { frame: u64 },
+}
+
+impl DebugEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DebugEvent::InstructionExecuted { .. } => "instruction_executed",
+            DebugEvent::InterruptDispatched { .. } => "interrupt_dispatched",
+            DebugEvent::Halted { .. } => "halted",
+            DebugEvent::BreakpointHit(_) => "breakpoint_hit",
+            DebugEvent::WatchpointHit(_) => "watchpoint_hit",
+            DebugEvent::FrameCompleted { .. } => "frame_completed",
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let body = match self {
+            DebugEvent::InstructionExecuted { pc, opcode, cycles } => format!("\"pc\":{pc},\"opcode\":{opcode},\"cycles\":{cycles}"),
+            DebugEvent::InterruptDispatched { vector, return_pc, cycles } => format!("\"vector\":{vector},\"return_pc\":{return_pc},\"cycles\":{cycles}"),
+            DebugEvent::Halted { cycles } => format!("\"cycles\":{cycles}"),
+            DebugEvent::BreakpointHit(h) => format!("\"id\":{},\"pc\":{},\"hits\":{},\"t_cycles\":{}", h.id, h.pc, h.hits, h.t_cycles),
+            DebugEvent::WatchpointHit(h) => format!("\"id\":{},\"pc\":{},\"addr\":{},\"value\":{},\"access\":\"{}\"", h.id, h.pc, h.addr, h.value, h.access.as_str()),
+            DebugEvent::FrameCompleted { frame } => format!("\"frame\":{frame}"),
+        };
+        format!("{{\"event\":\"{}\",{body}}}", self.as_str())
+    }
+}
@@ -0,0 +1,147 @@
+//! determinism — audit a ROM run for hidden nondeterminism
+//!
+//! Netplay, TAS playback and ML datasets all assume that the same ROM and
+//! the same inputs give the same machine state, frame after frame. The audit
+//! runs a ROM twice in-process — the second run on its own thread, with the
+//! heap churned to a different layout and timing jitter between frames — and
+//! compares per-subsystem state hashes every frame. Any mismatch names the
+//! subsystems that diverged first. `audio` hashes each frame's mixed sample
+//! output (`audio_hash`), so the per-frame hashes double as golden data for
+//! audio regressions that leave the APU registers and the picture unchanged.
+//!
+//! Both runs use a `FixedClock`: host time is an explicit input, not hidden state.
+
+use crate::{audio_hash, Cartridge, CoreError, FixedClock, GbCore};
+
+/// FNV-1a state hash of each subsystem after one frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SubsystemHashes {
+    pub cpu: u64,
+    pub ppu: u64,
+    pub vram: u64,
+    pub wram: u64,
+    pub oam: u64,
+    pub hram: u64,
+    pub io: u64,
+    pub timer: u64,
+    pub apu: u64,
+    /// The frame's audio output
+    pub audio: u64,
+    pub cart: u64,
+}
+
+fn fnv64(parts: &[&[u8]]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for p in parts {
+        for &b in *p { h ^= b as u64; h = h.wrapping_mul(0x0000_0100_0000_01b3); }
+    }
+    h
+}
+
+impl SubsystemHashes {
+    pub const NAMES: [&'static str; 11] = ["cpu", "ppu", "vram", "wram", "oam", "hram", "io", "timer", "apu", "audio", "cart"];
+
+    /// Hashes after a frame; `audio` covers the samples not yet drained
+    pub fn capture(core: &GbCore) -> Self {
+        let r = &core.regs;
+        let b = &core.bus;
+        let p = &b.ppu;
+        let cpu = [r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, (r.sp >> 8) as u8, r.sp as u8, (r.pc >> 8) as u8, r.pc as u8,
+                   core.halted as u8, core.ime as u8, core.ime_pending as u8, core.halt_bug as u8];
+        let ppu_regs = [p.mode as u8, p.ly, p.lyc, p.lcdc, p.stat, p.scy, p.scx, p.wy, p.wx, p.wlc, p.pal_bg, p.pal_obj0, p.pal_obj1];
+        SubsystemHashes {
+            cpu: fnv64(&[&cpu, &core.clock.t_cycles.to_le_bytes()]),
+            ppu: fnv64(&[&ppu_regs, &p.dot.to_le_bytes(), &p.framebuffer]),
+            vram: fnv64(&[b.vram[0].as_slice(), b.vram[1].as_slice(), &[b.vram_bank]]),
+            wram: fnv64(&[b.wram.as_flattened(), &[b.wram_bank]]),
+            oam: fnv64(&[&b.oam]),
+            hram: fnv64(&[&b.hram]),
+            io: fnv64(&[&b.io, &[b.ie, b.if_reg, b.joypad, b.buttons, b.double_speed as u8, b.speed_switch_armed as u8], &b.bg_cpal, &b.obj_cpal]),
+            timer: fnv64(&[format!("{:?}", b.timer).as_bytes()]),
+            apu: fnv64(&[format!("{:?}", b.apu).as_bytes()]),
+            audio: audio_hash(&b.apu.sample_buffer),
+            cart: fnv64(&[&b.ram, format!("{:?}", b.mbc).as_bytes()]),
+        }
+    }
+
+    pub fn values(&self) -> [u64; 11] {
+        [self.cpu, self.ppu, self.vram, self.wram, self.oam, self.hram, self.io, self.timer, self.apu, self.audio, self.cart]
+    }
+
+    /// Names of subsystems whose hashes differ
+    pub fn diff(&self, other: &SubsystemHashes) -> Vec<&'static str> {
+        Self::NAMES.iter().zip(self.values().iter().zip(other.values()))
+            .filter(|(_, (a, b))| *a != b).map(|(n, _)| *n).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub frame: u64,
+    pub subsystems: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeterminismReport {
+    pub frames_compared: u64,
+    /// Divergent frames, first ones only (see MAX_REPORTED_DIVERGENCES)
+    pub divergences: Vec<Divergence>,
+}
+
+/// Divergent frames kept in a report; later frames only repeat the cascade
+pub const MAX_REPORTED_DIVERGENCES: usize = 16;
+
+impl DeterminismReport {
+    pub fn is_deterministic(&self) -> bool { self.divergences.is_empty() }
+    /// Subsystems that diverged on the first divergent frame
+    pub fn culprits(&self) -> &[&'static str] {
+        self.divergences.first().map_or(&[], |d| d.subsystems.as_slice())
+    }
+}
+
+/// Per-frame hashes for one run. `inputs[frame]` (if present) is the pressed
+/// button mask for that frame; `perturb` runs between frames.
+pub fn frame_hashes(rom: Vec<u8>, inputs: &[u8], frames: u64, mut perturb: impl FnMut(u64)) -> Result<Vec<SubsystemHashes>, CoreError> {
+    let mut core = GbCore::new(Cartridge::from_bytes(rom)?);
+    core.set_host_clock(Box::new(FixedClock::new(0)));
+    let mut out = Vec::with_capacity(frames as usize);
+    for frame in 0..frames {
+        if let Some(&j) = inputs.get(frame as usize) { core.set_buttons(j); }
+        core.run_frame()?;
+        out.push(SubsystemHashes::capture(&core));
+        core.bus.apu.sample_buffer.clear();
+        perturb(frame);
+    }
+    Ok(out)
+}
+
+/// Run `rom` twice under different allocator and thread-timing conditions
+/// and compare every frame
+pub fn audit_determinism(rom: &[u8], inputs: &[u8], frames: u64) -> Result<DeterminismReport, CoreError> {
+    let reference = frame_hashes(rom.to_vec(), inputs, frames, |_| {})?;
+
+    let (rom_b, inputs_b) = (rom.to_vec(), inputs.to_vec());
+    let perturbed = std::thread::spawn(move || {
+        // Different heap layout: keep odd-sized blocks alive across the run
+        let mut ballast: Vec<Vec<u8>> = (1..64).map(|i| vec![i as u8; i * 37 % 4093 + 1]).collect();
+        frame_hashes(rom_b, &inputs_b, frames, |frame| {
+            ballast.push(vec![0xA5; (frame as usize * 131) % 8191 + 1]);
+            if ballast.len() > 96 { ballast.swap_remove((frame as usize * 7) % ballast.len()); }
+            match frame % 3 {
+                0 => std::thread::yield_now(),
+                1 => std::thread::sleep(std::time::Duration::from_micros(frame % 200)),
+                _ => {}
+            }
+        })
+    }).join().map_err(|_| CoreError::InvalidState("perturbed audit run panicked".into()))??;
+
+    let mut report = DeterminismReport { frames_compared: reference.len().min(perturbed.len()) as u64, divergences: vec![] };
+    for (frame, (a, b)) in reference.iter().zip(&perturbed).enumerate() {
+        let subsystems = a.diff(b);
+        if !subsystems.is_empty() {
+            report.divergences.push(Divergence { frame: frame as u64, subsystems });
+            if report.divergences.len() >= MAX_REPORTED_DIVERGENCES { break; }
+        }
+    }
+    Ok(report)
+}
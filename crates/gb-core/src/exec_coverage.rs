@@ -0,0 +1,141 @@
+//! exec_coverage — per-address executed-code bitmap
+//!
+//! With `GbCore::exec_coverage` set, every instruction marks the bytes it
+//! was fetched from (opcode and operands). ROM is tracked by physical
+//! offset, so code in every bank is told apart; everything above 0x7FFF
+//! (WRAM / HRAM code, OAM DMA routines) is tracked by CPU address.
+//!
+//! `ranges()` turns the bitmap into the runs of touched code, the input for
+//! ROM coverage analysis in `letsplay_batch --exec-coverage`. The frame at
+//! which code was last seen for the first time (`last_new_frame`) tells a
+//! stuck loop: a game that keeps running without reaching new code.
+
+use crate::{Bus, CYCLES_PER_FRAME};
+
+/// Where a run of executed bytes lies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecRegion {
+    /// ROM bank (bank 0 at 0x0000-0x3FFF, others at 0x4000-0x7FFF)
+    Rom(u16),
+    /// CPU address space above 0x7FFF
+    Ram,
+}
+
+/// Inclusive run of executed bytes, in CPU addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecRange {
+    pub region: ExecRegion,
+    pub start: u16,
+    pub end: u16,
+}
+
+impl ExecRange {
+    pub fn to_json(&self) -> String {
+        let (region, bank) = match self.region {
+            ExecRegion::Rom(b) => ("rom", b.to_string()),
+            ExecRegion::Ram => ("ram", "null".to_string()),
+        };
+        format!("{{\"region\":\"{region}\",\"bank\":{bank},\"start\":{},\"end\":{}}}", self.start, self.end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecCoverage {
+    /// One bit per ROM byte (physical offset)
+    rom: Vec<u64>,
+    rom_len: usize,
+    /// One bit per address 0x8000-0xFFFF
+    ram: Vec<u64>,
+    bytes: usize,
+    last_new_frame: u64,
+}
+
+fn test_and_set(bits: &mut [u64], i: usize) -> bool {
+    let (w, m) = (i / 64, 1u64 << (i % 64));
+    let new = bits[w] & m == 0;
+    bits[w] |= m;
+    new
+}
+
+fn is_set(bits: &[u64], i: usize) -> bool { bits[i / 64] & (1 << (i % 64)) != 0 }
+
+impl ExecCoverage {
+    /// Bitmap sized for `rom_len` bytes of ROM
+    pub fn new(rom_len: usize) -> Self {
+        ExecCoverage { rom: vec![0; rom_len.div_ceil(64)], rom_len, ram: vec![0; 0x8000 / 64], bytes: 0, last_new_frame: 0 }
+    }
+    pub fn for_bus(bus: &Bus) -> Self { Self::new(bus.rom.len()) }
+
+    /// Distinct bytes executed
+    pub fn bytes(&self) -> usize { self.bytes }
+    /// Share of the ROM image executed
+    pub fn rom_fraction(&self) -> f64 {
+        if self.rom_len == 0 { return 0.0; }
+        self.rom.iter().map(|w| w.count_ones() as usize).sum::<usize>() as f64 / self.rom_len as f64
+    }
+    /// Frame in which a byte was executed for the first time, most recently
+    pub fn last_new_frame(&self) -> u64 { self.last_new_frame }
+
+    /// Whether the byte at `addr` (through the current bank mapping) has run
+    pub fn executed(&self, bus: &Bus, addr: u16) -> bool {
+        match addr {
+            0x0000..=0x7FFF => {
+                let off = bus.mbc.rom_addr(addr);
+                off < self.rom_len && is_set(&self.rom, off)
+            }
+            _ => is_set(&self.ram, addr as usize - 0x8000),
+        }
+    }
+
+    /// Mark the `len` bytes of the instruction at `pc` under the current mapping
+    pub(crate) fn mark(&mut self, bus: &Bus, pc: u16, len: u16, t_cycles: u64) {
+        let mut new = 0;
+        for i in 0..len {
+            let addr = pc.wrapping_add(i);
+            let fresh = match addr {
+                0x0000..=0x7FFF => {
+                    let off = bus.mbc.rom_addr(addr);
+                    off < self.rom_len && test_and_set(&mut self.rom, off)
+                }
+                _ => test_and_set(&mut self.ram, addr as usize - 0x8000),
+            };
+            new += fresh as usize;
+        }
+        if new > 0 {
+            self.bytes += new;
+            self.last_new_frame = t_cycles / CYCLES_PER_FRAME;
+        }
+    }
+
+    /// Runs of executed bytes: ROM by bank, then RAM
+    pub fn ranges(&self) -> Vec<ExecRange> {
+        let mut out = vec![];
+        let mut run = |region: ExecRegion, bits: &[u64], base_off: usize, len: usize, base_addr: u16| {
+            let mut start = None;
+            for i in 0..=len {
+                let set = i < len && is_set(bits, base_off + i);
+                match (set, start) {
+                    (true, None) => start = Some(i),
+                    (false, Some(s)) => {
+                        out.push(ExecRange { region, start: base_addr + s as u16, end: base_addr + (i - 1) as u16 });
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        };
+        for bank in 0..self.rom_len.div_ceil(0x4000) {
+            let off = bank * 0x4000;
+            let base = if bank == 0 { 0x0000 } else { 0x4000 };
+            run(ExecRegion::Rom(bank as u16), &self.rom, off, (self.rom_len - off).min(0x4000), base);
+        }
+        run(ExecRegion::Ram, &self.ram, 0, 0x8000, 0x8000);
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let ranges: Vec<String> = self.ranges().iter().map(ExecRange::to_json).collect();
+        format!("{{\"bytes\":{},\"rom_fraction\":{:.4},\"last_new_frame\":{},\"ranges\":[{}]}}",
+            self.bytes, self.rom_fraction(), self.last_new_frame, ranges.join(","))
+    }
+}
@@ -0,0 +1,201 @@
+//! game_adapters — per-game knowledge, looked up from the cartridge header
+//!
+//! A `GameAdapter` knows one game (or one family sharing a layout) well
+//! enough to read its battery save. `GameAdapters::builtin()` is the
+//! registry the tools consult (`metarom savedump <rom> <sav>`); embedders
+//! can `register` their own adapters, which are tried before the built-in
+//! ones. Decoded saves are JSON objects with a `"game"` key naming the
+//! adapter; everything else is game specific:
+//!
+//! - `pokemon_gen1` (Pokémon Red / Blue / Yellow): trainer, money, badges,
+//!   Pokédex counts, play time, party and current box, and whether the main
+//!   checksum matches. Species are the game's internal index numbers.
+//! - `zelda_la` (Link's Awakening, DMG): the three save slots with equipped
+//!   and carried items, hearts, rupees, seashells and instruments.
+//!
+//! Decoders only read; they never check that the save came from the ROM.
+
+use crate::settings::esc;
+use crate::text::pokemon_rb;
+use crate::RomHeader;
+
+pub trait GameAdapter: Send + Sync {
+    /// Stable id, also the `"game"` key of decoded saves
+    fn id(&self) -> &'static str;
+    fn matches(&self, header: &RomHeader) -> bool;
+    /// Battery RAM (`.sav`) to a JSON object
+    fn decode_save(&self, sram: &[u8]) -> Result<String, String>;
+}
+
+/// Adapters in lookup order: the first one matching a header wins
+pub struct GameAdapters {
+    adapters: Vec<Box<dyn GameAdapter>>,
+}
+
+impl GameAdapters {
+    pub fn empty() -> Self { GameAdapters { adapters: vec![] } }
+
+    /// The adapters shipped with the core
+    pub fn builtin() -> Self {
+        GameAdapters { adapters: vec![Box::new(PokemonGen1), Box::new(ZeldaLa)] }
+    }
+
+    /// Add an adapter ahead of the ones already registered
+    pub fn register(&mut self, adapter: Box<dyn GameAdapter>) { self.adapters.insert(0, adapter); }
+
+    pub fn ids(&self) -> Vec<&'static str> { self.adapters.iter().map(|a| a.id()).collect() }
+
+    pub fn for_header(&self, header: &RomHeader) -> Option<&dyn GameAdapter> {
+        self.adapters.iter().find(|a| a.matches(header)).map(|a| a.as_ref())
+    }
+    pub fn for_rom(&self, rom: &[u8]) -> Option<&dyn GameAdapter> {
+        self.for_header(&RomHeader::parse(rom).ok()?)
+    }
+}
+
+fn need(sram: &[u8], len: usize) -> Result<(), String> {
+    if sram.len() < len { Err(format!("save is {} bytes, expected at least {len}", sram.len())) } else { Ok(()) }
+}
+
+fn u16_be(b: &[u8], at: usize) -> u16 { u16::from_be_bytes([b[at], b[at + 1]]) }
+
+/// Packed BCD, most significant byte first
+fn bcd(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |n, b| n * 100 + (b >> 4) as u32 * 10 + (b & 0x0F) as u32)
+}
+
+fn json_list<T>(items: &[T], f: impl Fn(&T) -> String) -> String {
+    format!("[{}]", items.iter().map(f).collect::<Vec<_>>().join(","))
+}
+
+// ── Pokémon Red / Blue / Yellow ──────────────────────────────────────────────
+
+/// Offsets into the 32 KiB save (bank 1 holds the main data)
+mod gen1 {
+    pub const PLAYER_NAME: usize = 0x2598;
+    pub const DEX_OWNED: usize = 0x25A3;
+    pub const DEX_SEEN: usize = 0x25B6;
+    pub const DEX_BYTES: usize = 19;
+    pub const MONEY: usize = 0x25F3;
+    pub const RIVAL_NAME: usize = 0x25F6;
+    pub const BADGES: usize = 0x2602;
+    pub const PLAYER_ID: usize = 0x2605;
+    pub const BOX_NUMBER: usize = 0x284C;
+    pub const PLAY_TIME: usize = 0x2CED;
+    pub const PARTY: usize = 0x2F2C;
+    pub const CURRENT_BOX: usize = 0x30C0;
+    /// Sum of 0x2598..CHECKSUM, complemented
+    pub const CHECKSUM: usize = 0x3523;
+    pub const NAME_LEN: usize = 11;
+    pub const PARTY_MON: usize = 44;
+    pub const BOX_MON: usize = 33;
+    pub const SAVE_SIZE: usize = 0x8000;
+}
+
+const GEN1_BADGES: [&str; 8] = ["boulder", "cascade", "thunder", "rainbow", "soul", "marsh", "volcano", "earth"];
+
+struct PokemonGen1;
+
+impl PokemonGen1 {
+    /// 0x50-terminated string in the game's character set
+    fn text(bytes: &[u8]) -> String {
+        let glyphs = pokemon_rb();
+        bytes.iter().take_while(|&&b| b != 0x50).map(|&b| glyphs.get(b).unwrap_or('?')).collect()
+    }
+
+    /// A party / box list: count, species list, mons, OT names, nicknames
+    fn mons(sram: &[u8], at: usize, capacity: usize, mon_size: usize) -> String {
+        let count = (sram[at] as usize).min(capacity);
+        let mons_at = at + 1 + capacity + 1;
+        let ot_at = mons_at + capacity * mon_size;
+        let nick_at = ot_at + capacity * gen1::NAME_LEN;
+        let list: Vec<String> = (0..count).map(|i| {
+            let m = &sram[mons_at + i * mon_size..][..mon_size];
+            let ot = Self::text(&sram[ot_at + i * gen1::NAME_LEN..][..gen1::NAME_LEN]);
+            let nick = Self::text(&sram[nick_at + i * gen1::NAME_LEN..][..gen1::NAME_LEN]);
+            // Party mons carry their level and stats after the box fields
+            let (level, stats) = if mon_size == gen1::PARTY_MON {
+                (m[33], format!(",\"max_hp\":{},\"attack\":{},\"defense\":{},\"speed\":{},\"special\":{}",
+                    u16_be(m, 34), u16_be(m, 36), u16_be(m, 38), u16_be(m, 40), u16_be(m, 42)))
+            } else { (m[3], String::new()) };
+            format!("{{\"species\":{},\"nickname\":\"{}\",\"ot\":\"{}\",\"ot_id\":{},\"level\":{},\"hp\":{},\"exp\":{},\"moves\":{}{}}}",
+                m[0], esc(&nick), esc(&ot), u16_be(m, 12), level, u16_be(m, 1),
+                (m[14] as u32) << 16 | (m[15] as u32) << 8 | m[16] as u32,
+                json_list(&m[8..12].iter().filter(|&&mv| mv != 0).collect::<Vec<_>>(), |mv| mv.to_string()), stats)
+        }).collect();
+        format!("[{}]", list.join(","))
+    }
+}
+
+impl GameAdapter for PokemonGen1 {
+    fn id(&self) -> &'static str { "pokemon_gen1" }
+    fn matches(&self, header: &RomHeader) -> bool {
+        matches!(header.title.as_str(), "POKEMON RED" | "POKEMON BLUE" | "POKEMON YELLOW")
+    }
+    fn decode_save(&self, sram: &[u8]) -> Result<String, String> {
+        need(sram, gen1::SAVE_SIZE)?;
+        let sum = sram[gen1::PLAYER_NAME..gen1::CHECKSUM].iter().fold(0u8, |s, &b| s.wrapping_add(b));
+        let dex = |at: usize| sram[at..at + gen1::DEX_BYTES].iter().map(|b| b.count_ones()).sum::<u32>();
+        let badges: Vec<&str> = (0..8).filter(|i| sram[gen1::BADGES] >> i & 1 != 0).map(|i| GEN1_BADGES[i]).collect();
+        let t = gen1::PLAY_TIME;
+        Ok(format!(concat!(
+            "{{\"game\":\"{}\",\"checksum_ok\":{},",
+            "\"player\":{{\"name\":\"{}\",\"id\":{},\"money\":{},\"badges\":{}}},\"rival\":\"{}\",",
+            "\"pokedex\":{{\"owned\":{},\"seen\":{}}},",
+            "\"play_time\":{{\"hours\":{},\"minutes\":{},\"seconds\":{}}},",
+            "\"party\":{},\"box\":{{\"number\":{},\"mons\":{}}}}}"),
+            self.id(), !sum == sram[gen1::CHECKSUM],
+            esc(&Self::text(&sram[gen1::PLAYER_NAME..][..gen1::NAME_LEN])), u16_be(sram, gen1::PLAYER_ID),
+            bcd(&sram[gen1::MONEY..gen1::MONEY + 3]), json_list(&badges, |b| format!("\"{b}\"")),
+            esc(&Self::text(&sram[gen1::RIVAL_NAME..][..gen1::NAME_LEN])),
+            dex(gen1::DEX_OWNED), dex(gen1::DEX_SEEN),
+            sram[t], sram[t + 2], sram[t + 3],
+            Self::mons(sram, gen1::PARTY, 6, gen1::PARTY_MON),
+            (sram[gen1::BOX_NUMBER] & 0x7F) + 1, Self::mons(sram, gen1::CURRENT_BOX, 20, gen1::BOX_MON)))
+    }
+}
+
+// ── The Legend of Zelda: Link's Awakening ────────────────────────────────────
+
+/// Offsets into the 8 KiB save; each slot is a copy of WRAM from 0xDB00
+mod la {
+    pub const SLOTS: [usize; 3] = [0x0105, 0x0490, 0x081B];
+    pub const SLOT_SIZE: usize = 0x38B;
+    /// B button, A button, then ten inventory cells
+    pub const ITEMS: usize = 0x00;
+    pub const SEASHELLS: usize = 0x0F;
+    pub const HEALTH: usize = 0x5A;
+    pub const MAX_HEARTS: usize = 0x5B;
+    pub const RUPEES: usize = 0x5D;
+    pub const INSTRUMENTS: usize = 0x65;
+}
+
+const LA_ITEMS: [&str; 14] = [
+    "", "sword", "bombs", "power_bracelet", "shield", "bow", "hookshot", "magic_rod",
+    "pegasus_boots", "ocarina", "rocs_feather", "shovel", "magic_powder", "boomerang",
+];
+
+struct ZeldaLa;
+
+impl GameAdapter for ZeldaLa {
+    fn id(&self) -> &'static str { "zelda_la" }
+    fn matches(&self, header: &RomHeader) -> bool { header.title == "ZELDA" && header.cgb_flag & 0x80 == 0 }
+    fn decode_save(&self, sram: &[u8]) -> Result<String, String> {
+        need(sram, la::SLOTS[2] + la::SLOT_SIZE)?;
+        let item = |id: u8| match LA_ITEMS.get(id as usize) {
+            Some(&"") => "null".to_string(),
+            Some(name) => format!("\"{name}\""),
+            None => format!("\"0x{id:02X}\""),
+        };
+        let slots: Vec<String> = la::SLOTS.iter().map(|&at| {
+            let s = &sram[at..at + la::SLOT_SIZE];
+            if s[la::MAX_HEARTS] == 0 { return "null".to_string(); }
+            let carried: Vec<u8> = s[la::ITEMS + 2..la::ITEMS + 12].iter().copied().filter(|&i| i != 0).collect();
+            format!("{{\"b\":{},\"a\":{},\"inventory\":{},\"hearts\":{},\"max_hearts\":{},\"rupees\":{},\"seashells\":{},\"instruments\":{}}}",
+                item(s[la::ITEMS]), item(s[la::ITEMS + 1]), json_list(&carried, |&i| item(i)),
+                s[la::HEALTH] as f64 / 8.0, s[la::MAX_HEARTS], bcd(&s[la::RUPEES..la::RUPEES + 2]), s[la::SEASHELLS],
+                s[la::INSTRUMENTS..la::INSTRUMENTS + 8].iter().filter(|&&b| b != 0).count())
+        }).collect();
+        Ok(format!("{{\"game\":\"{}\",\"slots\":[{}]}}", self.id(), slots.join(",")))
+    }
+}
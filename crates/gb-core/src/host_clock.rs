@@ -0,0 +1,65 @@
+//! host_clock — injectable host time source
+//!
+//! Every consumer of wall-clock time inside the core (MBC3 RTC, replay
+//! timestamps, pacing) reads it through a `HostClock` instead of calling
+//! `std::time` directly. `FixedClock` keeps runs bit-reproducible and works on
+//! hosts without a system clock (WASM); `ScaledClock` fast-forwards or slows
+//! an inner clock.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Monotonic host time in microseconds since a clock-specific epoch.
+pub trait HostClock: Send {
+    fn now_us(&self) -> u64;
+}
+
+// ── RealClock ─────────────────────────────────────────────────────────────────
+/// Wall clock backed by `std::time::Instant`; epoch is construction time.
+#[derive(Debug, Clone)]
+pub struct RealClock { start: Instant }
+
+impl RealClock {
+    pub fn new() -> Self { RealClock { start: Instant::now() } }
+}
+impl Default for RealClock {
+    fn default() -> Self { Self::new() }
+}
+impl HostClock for RealClock {
+    fn now_us(&self) -> u64 { self.start.elapsed().as_micros() as u64 }
+}
+
+// ── FixedClock ────────────────────────────────────────────────────────────────
+/// Manually advanced clock. Clones share the same time, so a host can keep
+/// one handle and inject another into `GbCore`.
+#[derive(Debug, Clone, Default)]
+pub struct FixedClock { now: Arc<AtomicU64> }
+
+impl FixedClock {
+    pub fn new(start_us: u64) -> Self { FixedClock { now: Arc::new(AtomicU64::new(start_us)) } }
+    pub fn set(&self, us: u64) { self.now.store(us, Ordering::SeqCst); }
+    pub fn advance(&self, us: u64) { self.now.fetch_add(us, Ordering::SeqCst); }
+}
+impl HostClock for FixedClock {
+    fn now_us(&self) -> u64 { self.now.load(Ordering::SeqCst) }
+}
+
+// ── ScaledClock ───────────────────────────────────────────────────────────────
+/// Wraps another clock and multiplies elapsed time by `factor`
+/// (2.0 = time passes twice as fast). Starts at the inner clock's current time.
+pub struct ScaledClock<C: HostClock> { inner: C, factor: f64, origin: u64 }
+
+impl<C: HostClock> ScaledClock<C> {
+    pub fn new(inner: C, factor: f64) -> Self {
+        let origin = inner.now_us();
+        ScaledClock { inner, factor: factor.max(0.0), origin }
+    }
+    pub fn factor(&self) -> f64 { self.factor }
+}
+impl<C: HostClock> HostClock for ScaledClock<C> {
+    fn now_us(&self) -> u64 {
+        let elapsed = self.inner.now_us().saturating_sub(self.origin);
+        self.origin + (elapsed as f64 * self.factor) as u64
+    }
+}
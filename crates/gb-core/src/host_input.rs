@@ -0,0 +1,211 @@
+//! host_input — interactive keyboard / gamepad backends (feature-gated)
+//!
+//! `keyboard` reads the terminal through crossterm; `gamepad` uses gilrs and
+//! follows hot-plug events. Both resolve host controls through an
+//! `InputMapping` and report a BTN_* mask once per frame for
+//! `GbCore::set_buttons()`. Without either feature only the `InputBackend`
+//! plumbing is built and gb-core keeps zero dependencies.
+//!
+//! Backends do not log: a backend that cannot open is returned by
+//! `open_backends()`, and pad hot-plugs arrive as `InputEvent`s in the poll,
+//! for the binary to report as it sees fit.
+
+use crate::joypad::InputMapping;
+
+/// Something the host's input changed besides the buttons
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    /// A gamepad is usable: present at start-up or plugged in since
+    PadConnected(String),
+    /// A gamepad went away; whatever it held is released
+    PadDisconnected(String),
+}
+
+/// A backend that could not be opened, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendError {
+    pub backend: &'static str,
+    pub error: String,
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} unavailable: {}", self.backend, self.error)
+    }
+}
+
+/// One poll of a backend
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputPoll {
+    pub buttons: u8,
+    /// The user asked to leave (Esc / Ctrl-C on the keyboard)
+    pub quit: bool,
+    /// Since the last poll, in order
+    pub events: Vec<InputEvent>,
+}
+
+pub trait InputBackend {
+    /// Drain pending host events and return the current button state
+    fn poll(&mut self) -> InputPoll;
+}
+
+/// All backends OR-ed together
+pub struct CombinedInput(pub Vec<Box<dyn InputBackend>>);
+
+impl InputBackend for CombinedInput {
+    fn poll(&mut self) -> InputPoll {
+        self.0.iter_mut().map(|b| b.poll()).fold(InputPoll::default(), |mut a, p| {
+            a.buttons |= p.buttons;
+            a.quit |= p.quit;
+            a.events.extend(p.events);
+            a
+        })
+    }
+}
+
+/// Every backend compiled in and available on this host, and the ones that
+/// are compiled in but failed to open
+pub fn open_backends(mapping: &InputMapping) -> (CombinedInput, Vec<BackendError>) {
+    #[allow(unused_mut)]
+    let (mut backends, mut errors): (Vec<Box<dyn InputBackend>>, Vec<BackendError>) = (vec![], vec![]);
+    #[cfg(feature = "keyboard")]
+    match KeyboardInput::new(mapping.clone()) {
+        Ok(k) => backends.push(Box::new(k)),
+        Err(e) => errors.push(BackendError { backend: "keyboard", error: e.to_string() }),
+    }
+    #[cfg(feature = "gamepad")]
+    match GamepadInput::new(mapping.clone()) {
+        Ok(g) => backends.push(Box::new(g)),
+        Err(e) => errors.push(BackendError { backend: "gamepad", error: e.to_string() }),
+    }
+    let _ = mapping;
+    (CombinedInput(backends), errors)
+}
+
+// ── Keyboard (crossterm) ──────────────────────────────────────────────────────
+
+/// Most terminals report key presses and repeats but no releases, so a key
+/// counts as held for this many frames after its last press/repeat event.
+#[cfg(feature = "keyboard")]
+pub const KEY_HOLD_FRAMES: u8 = 8;
+
+#[cfg(feature = "keyboard")]
+pub struct KeyboardInput {
+    mapping: InputMapping,
+    /// (control, frames left)
+    held: Vec<(String, u8)>,
+}
+
+#[cfg(feature = "keyboard")]
+impl KeyboardInput {
+    /// Puts the terminal into raw mode until dropped
+    pub fn new(mapping: InputMapping) -> std::io::Result<Self> {
+        crossterm::terminal::enable_raw_mode()?;
+        Ok(KeyboardInput { mapping, held: vec![] })
+    }
+
+    fn control_name(code: crossterm::event::KeyCode) -> Option<String> {
+        use crossterm::event::KeyCode::*;
+        Some(match code {
+            Char(' ') => "key.space".into(),
+            Char(c) => format!("key.{}", c.to_ascii_lowercase()),
+            Enter => "key.enter".into(), Backspace => "key.backspace".into(), Tab => "key.tab".into(),
+            Up => "key.up".into(), Down => "key.down".into(), Left => "key.left".into(), Right => "key.right".into(),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(feature = "keyboard")]
+impl Drop for KeyboardInput {
+    fn drop(&mut self) { let _ = crossterm::terminal::disable_raw_mode(); }
+}
+
+#[cfg(feature = "keyboard")]
+impl InputBackend for KeyboardInput {
+    fn poll(&mut self) -> InputPoll {
+        use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+        let mut quit = false;
+        for h in &mut self.held { h.1 = h.1.saturating_sub(1); }
+        while event::poll(std::time::Duration::ZERO).unwrap_or(false) {
+            let Ok(Event::Key(k)) = event::read() else { continue };
+            if k.code == KeyCode::Esc || (k.code == KeyCode::Char('c') && k.modifiers.contains(KeyModifiers::CONTROL)) {
+                quit = true;
+                continue;
+            }
+            let Some(name) = Self::control_name(k.code) else { continue };
+            self.held.retain(|(c, _)| *c != name);
+            if k.kind != KeyEventKind::Release { self.held.push((name, KEY_HOLD_FRAMES)); }
+        }
+        self.held.retain(|(_, left)| *left > 0);
+        let buttons = self.held.iter().fold(0, |m, (c, _)| m | self.mapping.buttons_for(c));
+        InputPoll { buttons, quit, events: vec![] }
+    }
+}
+
+// ── Gamepad (gilrs) ───────────────────────────────────────────────────────────
+
+#[cfg(feature = "gamepad")]
+pub struct GamepadInput {
+    gilrs: gilrs::Gilrs,
+    mapping: InputMapping,
+    /// Pressed controls per connected pad
+    pressed: Vec<(gilrs::GamepadId, Vec<String>)>,
+    /// Not yet returned by `poll`; starts with the pads already plugged in
+    events: Vec<InputEvent>,
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadInput {
+    pub fn new(mapping: InputMapping) -> Result<Self, gilrs::Error> {
+        let gilrs = gilrs::Gilrs::new()?;
+        let events = gilrs.gamepads().map(|(_, pad)| InputEvent::PadConnected(pad.name().to_string())).collect();
+        Ok(GamepadInput { gilrs, mapping, pressed: vec![], events })
+    }
+
+    fn control_name(b: gilrs::Button) -> Option<&'static str> {
+        use gilrs::Button::*;
+        Some(match b {
+            South => "pad.south", East => "pad.east", North => "pad.north", West => "pad.west",
+            Start => "pad.start", Select => "pad.select",
+            LeftTrigger => "pad.l1", RightTrigger => "pad.r1", LeftTrigger2 => "pad.l2", RightTrigger2 => "pad.r2",
+            DPadUp => "pad.dpad_up", DPadDown => "pad.dpad_down", DPadLeft => "pad.dpad_left", DPadRight => "pad.dpad_right",
+            _ => return None,
+        })
+    }
+
+    fn pad(&mut self, id: gilrs::GamepadId) -> &mut Vec<String> {
+        let i = match self.pressed.iter().position(|(p, _)| *p == id) {
+            Some(i) => i,
+            None => { self.pressed.push((id, vec![])); self.pressed.len() - 1 }
+        };
+        &mut self.pressed[i].1
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl InputBackend for GamepadInput {
+    fn poll(&mut self) -> InputPoll {
+        use gilrs::EventType;
+        while let Some(ev) = self.gilrs.next_event() {
+            match ev.event {
+                EventType::ButtonPressed(b, _) => if let Some(c) = Self::control_name(b) {
+                    let pad = self.pad(ev.id);
+                    if !pad.iter().any(|p| p == c) { pad.push(c.into()); }
+                },
+                EventType::ButtonReleased(b, _) => if let Some(c) = Self::control_name(b) {
+                    self.pad(ev.id).retain(|p| p != c);
+                },
+                EventType::Connected => self.events.push(InputEvent::PadConnected(self.gilrs.gamepad(ev.id).name().to_string())),
+                EventType::Disconnected => {
+                    // Drop whatever the pad was holding so buttons do not stick
+                    self.pressed.retain(|(p, _)| *p != ev.id);
+                    self.events.push(InputEvent::PadDisconnected(self.gilrs.gamepad(ev.id).name().to_string()));
+                }
+                _ => {}
+            }
+        }
+        let buttons = self.pressed.iter().flat_map(|(_, c)| c).fold(0, |m, c| m | self.mapping.buttons_for(c));
+        InputPoll { buttons, quit: false, events: std::mem::take(&mut self.events) }
+    }
+}
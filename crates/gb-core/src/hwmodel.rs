@@ -0,0 +1,105 @@
+//! hwmodel — hardware model selection and post-boot CPU / IO state
+//!
+//! Each model's boot ROM leaves different values behind when it jumps to
+//! 0x0100, and games use them: A is the usual model check (0x01 DMG / SGB,
+//! 0xFF MGB, 0x11 CGB), and DIV, P1 and SC differ too. `CoreConfig::model`
+//! picks the model. `GbCore::with_config` loads its registers, and
+//! `Bus::with_config` its IO defaults. Values follow Pan Docs' "Power Up
+//! Sequence" table. Where it leaves a value open (DIV on SGB / CGB, the
+//! title-dependent B / H of a CGB running a DMG cartridge), zero is used so
+//! runs stay reproducible.
+//!
+//! Beyond that, the model only decides whether a CGB-flagged cartridge runs
+//! in CGB mode (colour rendering, `cgb_render.rs`). CGB registers (VRAM /
+//! WRAM banks, palettes, KEY1) stay reachable on every model.
+
+use crate::{Bus, Registers};
+
+/// Console the core pretends to be
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HardwareModel {
+    /// Original Game Boy (the historical default)
+    #[default]
+    Dmg,
+    /// Game Boy Pocket / Light
+    Mgb,
+    /// Super Game Boy
+    Sgb,
+    /// Game Boy Color
+    Cgb,
+}
+
+impl HardwareModel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HardwareModel::Dmg => "dmg",
+            HardwareModel::Mgb => "mgb",
+            HardwareModel::Sgb => "sgb",
+            HardwareModel::Cgb => "cgb",
+        }
+    }
+    pub fn parse(s: &str) -> Option<HardwareModel> {
+        match s {
+            "dmg" => Some(HardwareModel::Dmg),
+            "mgb" => Some(HardwareModel::Mgb),
+            "sgb" => Some(HardwareModel::Sgb),
+            "cgb" => Some(HardwareModel::Cgb),
+            _ => None,
+        }
+    }
+    /// CGB for cartridges flagged CGB-compatible in the header, DMG otherwise
+    pub fn for_rom(rom: &[u8]) -> HardwareModel {
+        if matches!(rom.get(0x143), Some(0x80 | 0xC0)) { HardwareModel::Cgb } else { HardwareModel::Dmg }
+    }
+    /// A CGB running a CGB-flagged cartridge draws in CGB mode (`Ppu::cgb`)
+    pub fn cgb_mode(self, rom: &[u8]) -> bool {
+        self == HardwareModel::Cgb && Self::for_rom(rom) == HardwareModel::Cgb
+    }
+    /// AF, BC, DE, HL at 0x0100 for this model and cartridge `rom`
+    pub fn post_boot_regs(self, rom: &[u8]) -> [u16; 4] {
+        // DMG-family boot ROMs leave H and C set unless the header checksum is zero
+        let hc = if rom.get(0x14D).copied().unwrap_or(0) != 0 { 0x30 } else { 0x00 };
+        match self {
+            HardwareModel::Dmg => [0x0180 | hc, 0x0013, 0x00D8, 0x014D],
+            HardwareModel::Mgb => [0xFF80 | hc, 0x0013, 0x00D8, 0x014D],
+            HardwareModel::Sgb => [0x0100, 0x0014, 0x0000, 0xC060],
+            HardwareModel::Cgb if Self::for_rom(rom) == HardwareModel::Cgb => [0x1180, 0x0000, 0xFF56, 0x000D],
+            // DMG cartridge in compatibility mode
+            HardwareModel::Cgb => [0x1180, 0x0000, 0x0008, 0x007C],
+        }
+    }
+    /// Internal 16-bit divider at 0x0100 (DIV is its upper byte)
+    pub fn post_boot_div(self) -> u16 {
+        match self {
+            HardwareModel::Dmg | HardwareModel::Mgb => 0xABCC,
+            HardwareModel::Sgb | HardwareModel::Cgb => 0x0000,
+        }
+    }
+    /// SC (FF02): unused bits read 1; the CGB boot ROM leaves the internal-clock bit set
+    pub fn post_boot_sc(self) -> u8 {
+        if self == HardwareModel::Cgb { 0x7F } else { 0x7E }
+    }
+}
+
+/// Load the model's post-boot registers (PC 0x0100, SP 0xFFFE)
+pub fn apply_post_boot_regs(regs: &mut Registers, model: HardwareModel, rom: &[u8]) {
+    let [af, bc, de, hl] = model.post_boot_regs(rom);
+    regs.set_af(af); regs.set_bc(bc); regs.set_de(de); regs.set_hl(hl);
+    regs.sp = 0xFFFE; regs.pc = 0x0100;
+}
+
+/// Load the model's post-boot IO registers
+pub fn apply_post_boot_io(bus: &mut Bus, model: HardwareModel) {
+    // Both P1 select lines low (reads 0xCF with nothing pressed)
+    bus.joypad = 0x00;
+    bus.io[0x02] = model.post_boot_sc();
+    bus.timer.set_div_counter(model.post_boot_div());
+    // The boot ROM's last frame leaves VBlank requested
+    bus.if_reg = 0x01;
+    bus.ppu.cgb = model.cgb_mode(&bus.rom);
+    let ppu = &mut bus.ppu;
+    ppu.lcdc = 0x91;
+    ppu.pal_bg = 0xFC;
+    // LY = LYC = 0; the mode bits come from where the PPU model starts
+    ppu.stat = 0x04 | ppu.mode as u8;
+}
@@ -0,0 +1,98 @@
+//! input_latency — measured press-to-screen latency of the running game
+//!
+//! `measure_input_latency(core, n)` runs twins of the core from its current
+//! state: one untouched, and one per button with that button pressed at the
+//! start of the first frame. A button's latency is the number of frames until
+//! its twin's framebuffer first differs from the untouched one, i.e. until
+//! the press is visible on screen. That covers the game's own polling and
+//! double buffering as well as anything `CoreConfig` adds (a lite mode that
+//! skips frames shows up here). The core itself is left as it was.
+//!
+//! The result is kept on the core and reported by `diagnostics_json` under
+//! `"input_latency"`, which hosts forward to ucf-planner as telemetry
+//! (`TelemetrySample::input_latency_ms`) so streaming / split-execution plans
+//! are scored against measured latency.
+
+use crate::{fnv1a, Cartridge, CoreError, FixedClock, GbCore, CYCLES_PER_FRAME};
+
+/// Emulated frame period: 70224 T-cycles at 4.194304 MHz
+pub const FRAME_MS: f64 = CYCLES_PER_FRAME as f64 * 1000.0 / 4_194_304.0;
+
+const BUTTONS: [(u8, &str); 8] = [
+    (crate::BTN_RIGHT, "right"), (crate::BTN_LEFT, "left"), (crate::BTN_UP, "up"), (crate::BTN_DOWN, "down"),
+    (crate::BTN_A, "a"), (crate::BTN_B, "b"), (crate::BTN_SELECT, "select"), (crate::BTN_START, "start"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputLatency {
+    /// Frames until the fastest-reacting button showed on screen; None if
+    /// nothing reacted within `window_frames`
+    pub frames: Option<u32>,
+    pub ms: Option<f64>,
+    /// Button behind `frames`
+    pub button: Option<&'static str>,
+    /// Per button (not already held): frames until visible, None = no reaction
+    pub per_button: Vec<(&'static str, Option<u32>)>,
+    pub window_frames: u32,
+    /// `GbCore::clock.frame_count()` the measurement started from
+    pub at_frame: u64,
+}
+
+impl InputLatency {
+    pub fn to_json(&self) -> String {
+        let opt = |v: Option<String>| v.unwrap_or_else(|| "null".into());
+        let per: Vec<String> = self.per_button.iter()
+            .map(|(b, f)| format!("\"{b}\":{}", opt(f.map(|f| f.to_string()))))
+            .collect();
+        format!("{{\"frames\":{},\"ms\":{},\"button\":{},\"per_button\":{{{}}},\"window_frames\":{},\"at_frame\":{}}}",
+            opt(self.frames.map(|f| f.to_string())), opt(self.ms.map(|ms| format!("{ms:.3}"))),
+            opt(self.button.map(|b| format!("\"{b}\""))), per.join(","), self.window_frames, self.at_frame)
+    }
+}
+
+/// Measure press-to-screen latency from `core`'s current state over at most
+/// `window_frames` frames per button. The result is also stored in
+/// `core.input_latency` for `diagnostics_json`.
+pub fn measure_input_latency(core: &mut GbCore, window_frames: u32) -> Result<InputLatency, CoreError> {
+    let state = core.save_state();
+    let twin = |src: &GbCore| -> Result<GbCore, CoreError> {
+        let mut t = GbCore::with_config(Cartridge::from_bytes(src.bus.rom.clone())?, src.config);
+        t.set_host_clock(Box::new(FixedClock::new(0)));
+        t.load_state(&state)?;
+        // Held buttons and the lite-mode frame-skip phase are host-side,
+        // not part of the savestate
+        t.bus.buttons = src.bus.buttons;
+        t.bus.ppu.odd_frame = src.bus.ppu.odd_frame;
+        Ok(t)
+    };
+
+    let mut base = twin(core)?;
+    let mut baseline = Vec::with_capacity(window_frames as usize);
+    for _ in 0..window_frames {
+        base.run_frame()?;
+        baseline.push(fnv1a(&base.bus.ppu.framebuffer));
+    }
+
+    let held = core.bus.buttons;
+    let mut per_button = vec![];
+    for (mask, name) in BUTTONS.into_iter().filter(|(m, _)| held & m == 0) {
+        let mut probe = twin(core)?;
+        probe.set_buttons(held | mask);
+        let mut reacted = None;
+        for (n, expected) in baseline.iter().enumerate() {
+            probe.run_frame()?;
+            if fnv1a(&probe.bus.ppu.framebuffer) != *expected { reacted = Some(n as u32 + 1); break; }
+        }
+        per_button.push((name, reacted));
+    }
+
+    let fastest = per_button.iter().filter_map(|(b, f)| f.map(|f| (f, *b))).min_by_key(|(f, _)| *f);
+    let latency = InputLatency {
+        frames: fastest.map(|(f, _)| f),
+        ms: fastest.map(|(f, _)| f as f64 * FRAME_MS),
+        button: fastest.map(|(_, b)| b),
+        per_button, window_frames, at_frame: core.clock.frame_count(),
+    };
+    core.input_latency = Some(latency.clone());
+    Ok(latency)
+}
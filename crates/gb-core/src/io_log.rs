@@ -0,0 +1,114 @@
+//! io_log — cycle-stamped IO register write log
+//!
+//! With `Bus::io_log` set, every write to FF00-FF7F and to IE (FFFF) is
+//! recorded with the T-cycle and PC of the instruction that made it, so the
+//! way a game programs the PPU, APU and timers can be reconstructed without
+//! stepping a debugger. Writes the host makes between steps carry the
+//! context of the last instruction.
+//!
+//! The log is saved as a compact binary file (`io_writes.mriolog` in the
+//! artifact directory) and exported with `letsplay_iolog` or `to_csv`:
+//!
+//! ```text
+//! "MRIOLOG1", then per write: t_cycles u64, pc u16, register u8, value u8 (little-endian)
+//! ```
+
+use crate::CYCLES_PER_FRAME;
+use std::io;
+use std::path::Path;
+
+pub const IO_LOG_MAGIC: &[u8; 8] = b"MRIOLOG1";
+const RECORD_BYTES: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoWrite {
+    /// T-cycle the writing instruction started at
+    pub t_cycles: u64,
+    pub pc: u16,
+    /// Low byte of the address: 0x40 is LCDC (FF40), 0xFF is IE
+    pub reg: u8,
+    pub value: u8,
+}
+
+/// Conventional name of an IO register (`reg` as in `IoWrite`), "" if unnamed
+pub fn io_reg_name(reg: u8) -> &'static str {
+    match reg {
+        0x00 => "P1", 0x01 => "SB", 0x02 => "SC", 0x04 => "DIV", 0x05 => "TIMA", 0x06 => "TMA", 0x07 => "TAC",
+        0x0F => "IF",
+        0x10 => "NR10", 0x11 => "NR11", 0x12 => "NR12", 0x13 => "NR13", 0x14 => "NR14",
+        0x16 => "NR21", 0x17 => "NR22", 0x18 => "NR23", 0x19 => "NR24",
+        0x1A => "NR30", 0x1B => "NR31", 0x1C => "NR32", 0x1D => "NR33", 0x1E => "NR34",
+        0x20 => "NR41", 0x21 => "NR42", 0x22 => "NR43", 0x23 => "NR44",
+        0x24 => "NR50", 0x25 => "NR51", 0x26 => "NR52", 0x30..=0x3F => "WAVE",
+        0x40 => "LCDC", 0x41 => "STAT", 0x42 => "SCY", 0x43 => "SCX", 0x44 => "LY", 0x45 => "LYC",
+        0x46 => "DMA", 0x47 => "BGP", 0x48 => "OBP0", 0x49 => "OBP1", 0x4A => "WY", 0x4B => "WX",
+        0x4D => "KEY1", 0x4F => "VBK", 0x50 => "BOOT",
+        0x51 => "HDMA1", 0x52 => "HDMA2", 0x53 => "HDMA3", 0x54 => "HDMA4", 0x55 => "HDMA5",
+        0x56 => "RP", 0x68 => "BCPS", 0x69 => "BCPD", 0x6A => "OCPS", 0x6B => "OCPD", 0x70 => "SVBK",
+        0xFF => "IE",
+        _ => "",
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IoWriteLog {
+    entries: Vec<IoWrite>,
+    /// PC / T-cycle of the executing instruction
+    pc: u16,
+    t_cycles: u64,
+}
+
+impl IoWriteLog {
+    pub fn new() -> Self { Self::default() }
+    pub fn entries(&self) -> &[IoWrite] { &self.entries }
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+    pub fn clear(&mut self) { self.entries.clear(); }
+    /// Keep only the writes `keep` accepts
+    pub fn retain(&mut self, keep: impl FnMut(&IoWrite) -> bool) { self.entries.retain(keep); }
+
+    pub(crate) fn set_context(&mut self, pc: u16, t_cycles: u64) { self.pc = pc; self.t_cycles = t_cycles; }
+    pub(crate) fn record(&mut self, addr: u16, value: u8) {
+        self.entries.push(IoWrite { t_cycles: self.t_cycles, pc: self.pc, reg: addr as u8, value });
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(IO_LOG_MAGIC.len() + self.entries.len() * RECORD_BYTES);
+        out.extend_from_slice(IO_LOG_MAGIC);
+        for w in &self.entries {
+            out.extend_from_slice(&w.t_cycles.to_le_bytes());
+            out.extend_from_slice(&w.pc.to_le_bytes());
+            out.push(w.reg);
+            out.push(w.value);
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<IoWriteLog, String> {
+        let body = data.strip_prefix(IO_LOG_MAGIC.as_slice()).ok_or("not an mrom IO write log")?;
+        if body.len() % RECORD_BYTES != 0 { return Err(format!("truncated record at byte {}", data.len() - body.len() % RECORD_BYTES)); }
+        let entries = body.chunks_exact(RECORD_BYTES).map(|r| IoWrite {
+            t_cycles: u64::from_le_bytes(r[0..8].try_into().unwrap_or_default()),
+            pc: u16::from_le_bytes([r[8], r[9]]),
+            reg: r[10],
+            value: r[11],
+        }).collect();
+        Ok(IoWriteLog { entries, ..Default::default() })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> { std::fs::write(path, self.to_bytes()) }
+    pub fn load(path: &Path) -> io::Result<IoWriteLog> {
+        Self::from_bytes(&std::fs::read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// `t_cycles,frame,pc,address,register,value`; addresses and values hex
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("t_cycles,frame,pc,address,register,value\n");
+        for w in &self.entries {
+            let addr = if w.reg == 0xFF { 0xFFFF } else { 0xFF00 | w.reg as u16 };
+            out.push_str(&format!("{},{},{:04X},{addr:04X},{},{:02X}\n",
+                w.t_cycles, w.t_cycles / CYCLES_PER_FRAME, w.pc, io_reg_name(w.reg), w.value));
+        }
+        out
+    }
+}
@@ -0,0 +1,89 @@
+//! joypad — button state and host input mapping
+//!
+//! The P1 register (FF00) is a 2×4 matrix: the game selects the d-pad row
+//! (bit 4 low) and/or the button row (bit 5 low) and reads the pressed keys
+//! of the selected rows as 0 bits. Hosts never touch FF00; they set the
+//! pressed-button mask with `GbCore::set_buttons()`. `InputMapping` turns host
+//! key / gamepad names into buttons for the interactive backends.
+
+/// Pressed-button mask bits (1 = pressed)
+pub const BTN_RIGHT: u8 = 0x01;
+pub const BTN_LEFT: u8 = 0x02;
+pub const BTN_UP: u8 = 0x04;
+pub const BTN_DOWN: u8 = 0x08;
+pub const BTN_A: u8 = 0x10;
+pub const BTN_B: u8 = 0x20;
+pub const BTN_SELECT: u8 = 0x40;
+pub const BTN_START: u8 = 0x80;
+
+/// P1 read value for the select bits last written and the pressed mask
+pub fn p1_read(select: u8, buttons: u8) -> u8 {
+    let mut low = 0x0F;
+    if select & 0x10 == 0 { low &= !(buttons & 0x0F); }
+    if select & 0x20 == 0 { low &= !(buttons >> 4); }
+    0xC0 | (select & 0x30) | low
+}
+
+/// Button mask for a name: a, b, start, select, up, down, left, right
+pub fn button_from_name(name: &str) -> Option<u8> {
+    Some(match name.trim().to_ascii_lowercase().as_str() {
+        "right" => BTN_RIGHT, "left" => BTN_LEFT, "up" => BTN_UP, "down" => BTN_DOWN,
+        "a" => BTN_A, "b" => BTN_B, "select" => BTN_SELECT, "start" => BTN_START,
+        _ => return None,
+    })
+}
+
+/// Host control → button bindings. Controls are backend-qualified names:
+/// `key.<name>` (keyboard, e.g. `key.z`, `key.enter`, `key.up`) and
+/// `pad.<name>` (gamepad, e.g. `pad.south`, `pad.dpad_up`, `pad.start`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMapping {
+    pub bindings: Vec<(String, u8)>,
+}
+
+pub const DEFAULT_INPUT_MAPPING: &str = "\
+# control = button
+key.right = right
+key.left = left
+key.up = up
+key.down = down
+key.z = a
+key.x = b
+key.backspace = select
+key.enter = start
+pad.dpad_right = right
+pad.dpad_left = left
+pad.dpad_up = up
+pad.dpad_down = down
+pad.south = a
+pad.east = b
+pad.west = b
+pad.select = select
+pad.start = start
+";
+
+impl Default for InputMapping {
+    fn default() -> Self {
+        Self::parse(DEFAULT_INPUT_MAPPING).unwrap_or(InputMapping { bindings: vec![] })
+    }
+}
+
+impl InputMapping {
+    /// Parse `control = button` lines; `#` starts a comment
+    pub fn parse(text: &str) -> Result<InputMapping, String> {
+        let mut bindings = vec![];
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+            let (control, button) = line.split_once('=').ok_or(format!("line {}: expected `control = button`", n + 1))?;
+            let mask = button_from_name(button).ok_or(format!("line {}: unknown button {:?}", n + 1, button.trim()))?;
+            bindings.push((control.trim().to_ascii_lowercase(), mask));
+        }
+        Ok(InputMapping { bindings })
+    }
+
+    /// Buttons bound to a control (several bindings may share a control)
+    pub fn buttons_for(&self, control: &str) -> u8 {
+        self.bindings.iter().filter(|(c, _)| c.eq_ignore_ascii_case(control)).fold(0, |m, (_, b)| m | b)
+    }
+}
@@ -0,0 +1,149 @@
+//! json — minimal JSON reader for the manifests this crate writes
+//!
+//! gb-core stays dependency-free, so post-processing tools (scene segmentation,
+//! evidence ingestion) parse mrom.*.json with this small recursive-descent
+//! reader instead of serde. Numbers are kept as f64; object key order is kept.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError { pub offset: usize, pub msg: &'static str }
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "json error at byte {}: {}", self.offset, self.msg)
+    }
+}
+impl std::error::Error for JsonError {}
+
+impl Json {
+    pub fn parse(s: &str) -> Result<Json, JsonError> {
+        let mut p = Parser { b: s.as_bytes(), i: 0 };
+        let v = p.value()?;
+        p.ws();
+        if p.i != p.b.len() { return Err(p.err("trailing characters")); }
+        Ok(v)
+    }
+    /// Object member lookup (None for non-objects / missing keys)
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self { Json::Obj(m) => m.iter().find(|(k, _)| k == key).map(|(_, v)| v), _ => None }
+    }
+    pub fn as_f64(&self) -> Option<f64> { if let Json::Num(n) = self { Some(*n) } else { None } }
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_f64().filter(|n| *n >= 0.0 && n.fract() == 0.0).map(|n| n as u64)
+    }
+    pub fn as_bool(&self) -> Option<bool> { if let Json::Bool(b) = self { Some(*b) } else { None } }
+    pub fn as_str(&self) -> Option<&str> { if let Json::Str(s) = self { Some(s) } else { None } }
+    pub fn as_array(&self) -> Option<&[Json]> { if let Json::Arr(a) = self { Some(a) } else { None } }
+}
+
+struct Parser<'a> { b: &'a [u8], i: usize }
+
+impl Parser<'_> {
+    fn err(&self, msg: &'static str) -> JsonError { JsonError { offset: self.i, msg } }
+    fn ws(&mut self) {
+        while self.i < self.b.len() && self.b[self.i].is_ascii_whitespace() { self.i += 1; }
+    }
+    fn eat(&mut self, c: u8) -> Result<(), JsonError> {
+        self.ws();
+        if self.b.get(self.i) == Some(&c) { self.i += 1; Ok(()) } else { Err(self.err("unexpected character")) }
+    }
+    fn lit(&mut self, word: &str, v: Json) -> Result<Json, JsonError> {
+        if self.b[self.i..].starts_with(word.as_bytes()) { self.i += word.len(); Ok(v) } else { Err(self.err("bad literal")) }
+    }
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.ws();
+        match self.b.get(self.i) {
+            None => Err(self.err("unexpected end")),
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Json::Str),
+            Some(b't') => self.lit("true", Json::Bool(true)),
+            Some(b'f') => self.lit("false", Json::Bool(false)),
+            Some(b'n') => self.lit("null", Json::Null),
+            Some(_) => self.number(),
+        }
+    }
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.eat(b'{')?;
+        let mut m = Vec::new();
+        self.ws();
+        if self.b.get(self.i) == Some(&b'}') { self.i += 1; return Ok(Json::Obj(m)); }
+        loop {
+            self.ws();
+            let k = self.string()?;
+            self.eat(b':')?;
+            m.push((k, self.value()?));
+            self.ws();
+            match self.b.get(self.i) {
+                Some(b',') => self.i += 1,
+                Some(b'}') => { self.i += 1; return Ok(Json::Obj(m)); }
+                _ => return Err(self.err("expected ',' or '}'")),
+            }
+        }
+    }
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.eat(b'[')?;
+        let mut a = Vec::new();
+        self.ws();
+        if self.b.get(self.i) == Some(&b']') { self.i += 1; return Ok(Json::Arr(a)); }
+        loop {
+            a.push(self.value()?);
+            self.ws();
+            match self.b.get(self.i) {
+                Some(b',') => self.i += 1,
+                Some(b']') => { self.i += 1; return Ok(Json::Arr(a)); }
+                _ => return Err(self.err("expected ',' or ']'")),
+            }
+        }
+    }
+    fn string(&mut self) -> Result<String, JsonError> {
+        if self.b.get(self.i) != Some(&b'"') { return Err(self.err("expected string")); }
+        self.i += 1;
+        let mut out = String::new();
+        loop {
+            let start = self.i;
+            while self.i < self.b.len() && self.b[self.i] != b'"' && self.b[self.i] != b'\\' { self.i += 1; }
+            out.push_str(std::str::from_utf8(&self.b[start..self.i]).map_err(|_| self.err("invalid utf8"))?);
+            match self.b.get(self.i) {
+                None => return Err(self.err("unterminated string")),
+                Some(b'"') => { self.i += 1; return Ok(out); }
+                Some(_) => {
+                    let esc = *self.b.get(self.i + 1).ok_or_else(|| self.err("bad escape"))?;
+                    self.i += 2;
+                    match esc {
+                        b'"' => out.push('"'), b'\\' => out.push('\\'), b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'), b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'), b'r' => out.push('\r'), b't' => out.push('\t'),
+                        b'u' => {
+                            let hex = self.b.get(self.i..self.i + 4).ok_or_else(|| self.err("bad \\u escape"))?;
+                            let code = std::str::from_utf8(hex).ok()
+                                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                                .ok_or_else(|| self.err("bad \\u escape"))?;
+                            out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                            self.i += 4;
+                        }
+                        _ => return Err(self.err("bad escape")),
+                    }
+                }
+            }
+        }
+    }
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.i;
+        while self.i < self.b.len() && matches!(self.b[self.i], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') { self.i += 1; }
+        std::str::from_utf8(&self.b[start..self.i]).ok()
+            .and_then(|t| t.parse().ok())
+            .map(Json::Num)
+            .ok_or(JsonError { offset: start, msg: "bad number" })
+    }
+}
@@ -3,7 +3,157 @@
 //! Phase 3: PPU modes 0-3 + STAT, DIV/TIMA timer, MBC1/3/5 banking,
 //!          CB-prefix full decode, APU channel stubs, framebuffer + letsplay.
 
+pub mod accuracy;
+pub mod asm;
+pub mod artifacts;
+pub mod audio_features;
+pub mod blip;
+pub mod block_cache;
+pub mod breakpoints;
+pub mod callstack;
+pub mod capability;
+pub mod cgb_render;
+pub mod checkpoint;
+pub mod console;
+pub mod corpus;
+pub mod debug;
+pub mod determinism;
+pub mod exec_coverage;
+pub mod game_adapters;
+pub mod host_clock;
+pub mod host_input;
+pub mod hwmodel;
+pub mod input_latency;
+pub mod io_log;
+pub mod joypad;
+pub mod json;
+pub mod link;
+pub mod lite;
+pub mod meminit;
+pub mod microtest;
+pub mod metrics;
+pub mod motion;
+pub mod oam_dma;
+pub mod oam_scan;
+pub mod open_bus;
+pub mod opcodes;
+pub mod overlay;
+pub mod palette_pack;
+pub mod phash;
+pub mod pixel_fifo;
+pub mod png;
+pub mod ppu_timeline;
+#[cfg(feature = "profile")]
+pub mod profile;
+pub mod raster;
+pub mod reg_diff;
+pub mod rombuild;
+pub mod recover;
+pub mod replay_delta;
+pub mod scenes;
+pub mod scheduler;
+pub mod scorecard;
+pub mod seal;
+#[cfg(feature = "http")]
+pub mod serve;
+pub mod session;
+pub mod settings;
+pub mod sha256;
+pub mod simd;
+pub mod sprites;
+pub mod sram_autosave;
+pub mod sram_journal;
+pub mod state_hash;
+pub mod state_import;
+pub mod state_index;
+pub mod stimulus;
+pub mod test_rom;
+pub mod testsuite;
+pub mod text;
+pub mod throttle;
+pub mod time_travel;
+pub mod trace;
+pub mod triggers;
+pub mod vin;
+pub mod watch;
+
+pub use crate::accuracy::*;
+pub use crate::asm::*;
+pub use crate::artifacts::*;
+pub use crate::audio_features::*;
+pub use crate::blip::*;
+pub use crate::block_cache::*;
+pub use crate::breakpoints::*;
+pub use crate::callstack::*;
+pub use crate::capability::*;
+pub use crate::cgb_render::*;
+pub use crate::checkpoint::*;
+pub use crate::console::*;
+pub use crate::corpus::*;
+pub use crate::debug::*;
+pub use crate::determinism::*;
+pub use crate::exec_coverage::*;
+pub use crate::game_adapters::*;
+pub use crate::host_clock::*;
+pub use crate::host_input::*;
+pub use crate::hwmodel::*;
+pub use crate::input_latency::*;
+pub use crate::io_log::*;
+pub use crate::joypad::*;
+pub use crate::json::*;
+pub use crate::link::*;
+pub use crate::lite::*;
+pub use crate::meminit::*;
+pub use crate::microtest::*;
+pub use crate::metrics::*;
+pub use crate::motion::*;
+pub use crate::oam_dma::*;
+pub use crate::oam_scan::*;
+pub use crate::open_bus::*;
+pub use crate::opcodes::*;
+pub use crate::overlay::*;
+pub use crate::palette_pack::*;
+pub use crate::phash::*;
+pub use crate::pixel_fifo::*;
+pub use crate::png::*;
+pub use crate::ppu_timeline::*;
+#[cfg(feature = "profile")]
+pub use crate::profile::*;
+pub use crate::raster::*;
+pub use crate::recover::*;
+pub use crate::reg_diff::*;
+pub use crate::replay_delta::*;
+pub use crate::rombuild::*;
+pub use crate::scenes::*;
+pub use crate::scheduler::*;
+pub use crate::scorecard::*;
+pub use crate::seal::*;
+#[cfg(feature = "http")]
+pub use crate::serve::*;
+pub use crate::session::*;
+pub use crate::settings::*;
+pub use crate::sha256::*;
+pub use crate::simd::*;
+pub use crate::sprites::*;
+pub use crate::sram_autosave::*;
+pub use crate::sram_journal::*;
+pub use crate::state_import::*;
+pub use crate::state_index::*;
+pub use crate::stimulus::*;
+pub use crate::test_rom::*;
+pub use crate::testsuite::*;
+pub use crate::text::*;
+pub use crate::throttle::*;
+pub use crate::time_travel::*;
+pub use crate::trace::*;
+pub use crate::triggers::*;
+pub use crate::vin::*;
+pub use crate::watch::*;
+
+use std::cell::Cell;
 use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 // ── Hardware constants ──────────────────────────────────────────────────────
 pub const CPU_HZ: u64 = 4_194_304;
@@ -16,6 +166,8 @@ pub const PPU_MODE2_CYCLES: u32 = 80;
 pub const PPU_MODE3_CYCLES: u32 = 172;
 pub const PPU_MODE0_CYCLES: u32 = 204;
 pub const PPU_VBLANK_LINE: u32 = 144;
+pub const APU_SAMPLE_RATE: u32 = 44_100;
+pub const APU_SAMPLES_PER_FRAME: usize = 736;
 
 // ── CartridgeKind ────────────────────────────────────────────────────────────
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -113,6 +265,22 @@ impl Mbc {
             _ => false,
         }
     }
+    /// Advance the MBC3 real-time clock by `secs` host seconds.
+    /// Honors the halt flag (DH bit 6) and sets the day-carry flag (DH bit 7) on overflow.
+    pub fn rtc_advance(&mut self, secs: u64) {
+        if !matches!(self.kind, CartridgeKind::Mbc3) || secs == 0 { return; }
+        let r = &mut self.rtc_reg;
+        if r[4] & 0x40 != 0 { return; }
+        let days = (((r[4] & 0x01) as u64) << 8) | r[3] as u64;
+        let total = days * 86_400 + (r[2] as u64 % 24) * 3_600 + (r[1] as u64 % 60) * 60 + (r[0] as u64 % 60) + secs;
+        let new_days = total / 86_400;
+        r[0] = (total % 60) as u8;
+        r[1] = ((total / 60) % 60) as u8;
+        r[2] = ((total / 3_600) % 24) as u8;
+        r[3] = (new_days & 0xFF) as u8;
+        r[4] = (r[4] & 0xFE) | ((new_days >> 8) & 0x01) as u8;
+        if new_days > 0x1FF { r[4] |= 0x80; }
+    }
     pub fn rom_addr(&self, addr: u16) -> usize {
         match addr {
             0x0000..=0x3FFF => addr as usize,
@@ -132,19 +300,36 @@ pub struct Cartridge {
 
 impl Cartridge {
     pub fn from_bytes(rom: Vec<u8>) -> Result<Self, CoreError> {
-        if rom.len() < 0x150 { return Err(CoreError::InvalidRom("ROM too short".into())); }
+        if rom.len() < 0x150 {
+            return Err(CoreError::invalid_rom(format!("ROM too short ({} bytes, the header ends at 0x150)", rom.len()), None));
+        }
         let kind = CartridgeKind::from_header_byte(rom[0x147]);
         let title = String::from_utf8_lossy(&rom[0x134..0x143]).trim_matches('\0').to_string();
         let is_cgb = rom[0x143] == 0x80 || rom[0x143] == 0xC0;
-        let rom_size_kb = 32 * (1 << rom[0x148]);
+        // Past 8 MiB the code is bogus; go by the image itself
+        let rom_size_kb = if rom[0x148] <= 0x08 { 32 << rom[0x148] } else { rom.len().div_ceil(1024) as u32 };
         let ram_size_kb = match rom[0x149] { 0x02=>8, 0x03=>32, 0x04=>128, 0x05=>64, _=>0 };
         let ram = vec![0u8; (ram_size_kb as usize) * 1024];
         Ok(Cartridge { rom, ram, kind, title, is_cgb, rom_size_kb, ram_size_kb })
     }
+    /// Opt-in header checks for hosts that would rather refuse a cartridge
+    /// than run it loosely: `from_bytes` accepts mapper types gb-core does
+    /// not emulate (MBC7, camera, HuC1 / HuC3, MMM01, TAMA5 run as ROM-only)
+    /// and ROM size codes past 8 MiB
+    pub fn validate(&self) -> Result<(), CoreError> {
+        // 0x08 / 0x09 are ROM+RAM without a mapper chip
+        if let CartridgeKind::Unknown(b) = self.kind {
+            if !matches!(b, 0x08 | 0x09) { return Err(CoreError::UnsupportedMapper(b)); }
+        }
+        if self.rom[0x148] > 0x08 {
+            return Err(CoreError::invalid_rom(format!("ROM size code {:#04x}", self.rom[0x148]), Some(0x148)));
+        }
+        Ok(())
+    }
 }
 
 // ── Registers ────────────────────────────────────────────────────────────────
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Registers {
     pub a: u8, pub f: u8, pub b: u8, pub c: u8,
     pub d: u8, pub e: u8, pub h: u8, pub l: u8,
@@ -169,107 +354,6 @@ impl Registers {
     pub fn set_flag_c(&mut self, v: bool) { if v { self.f |= 0x10 } else { self.f &= !0x10 } }
 }
 
-// ── PPU ──────────────────────────────────────────────────────────────────────
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PpuMode { HBlank = 0, VBlank = 1, OamScan = 2, Drawing = 3 }
-
-#[derive(Debug, Clone)]
-pub struct Ppu {
-    pub mode: PpuMode, pub dot: u32, pub ly: u8, pub lyc: u8,
-    pub lcdc: u8, pub stat: u8, pub scy: u8, pub scx: u8,
-    pub framebuffer: Vec<u8>,
-    pub frame_ready: bool, pub stat_irq: bool, pub vblank_irq: bool,
-}
-impl Ppu {
-    pub fn new() -> Self {
-        Ppu { mode: PpuMode::OamScan, dot: 0, ly: 0, lyc: 0,
-              lcdc: 0x91, stat: 0, scy: 0, scx: 0,
-              framebuffer: vec![0u8; LCD_WIDTH * LCD_HEIGHT],
-              frame_ready: false, stat_irq: false, vblank_irq: false }
-    }
-    pub fn step(&mut self, cycles: u8, vram: &[u8; 0x2000], _oam: &[u8; 0xA0]) {
-        if self.lcdc & 0x80 == 0 { return; }
-        self.stat_irq = false; self.vblank_irq = false;
-        self.dot += cycles as u32;
-        match self.mode {
-            PpuMode::OamScan => {
-                if self.dot >= PPU_MODE2_CYCLES { self.dot -= PPU_MODE2_CYCLES; self.mode = PpuMode::Drawing; }
-            }
-            PpuMode::Drawing => {
-                if self.dot >= PPU_MODE3_CYCLES {
-                    self.dot -= PPU_MODE3_CYCLES;
-                    self.render_scanline(vram);
-                    self.mode = PpuMode::HBlank;
-                    if self.stat & 0x08 != 0 { self.stat_irq = true; }
-                }
-            }
-            PpuMode::HBlank => {
-                if self.dot >= PPU_MODE0_CYCLES {
-                    self.dot -= PPU_MODE0_CYCLES; self.ly += 1; self.check_lyc();
-                    if self.ly >= PPU_VBLANK_LINE as u8 {
-                        self.mode = PpuMode::VBlank; self.vblank_irq = true; self.frame_ready = true;
-                        if self.stat & 0x10 != 0 { self.stat_irq = true; }
-                    } else {
-                        self.mode = PpuMode::OamScan;
-                        if self.stat & 0x20 != 0 { self.stat_irq = true; }
-                    }
-                }
-            }
-            PpuMode::VBlank => {
-                if self.dot >= DOTS_PER_LINE {
-                    self.dot -= DOTS_PER_LINE; self.ly += 1; self.check_lyc();
-                    if self.ly > 153 {
-                        self.ly = 0; self.mode = PpuMode::OamScan; self.frame_ready = false;
-                        if self.stat & 0x20 != 0 { self.stat_irq = true; }
-                    }
-                }
-            }
-        }
-        self.stat = (self.stat & 0xFC) | (self.mode as u8);
-    }
-    fn check_lyc(&mut self) {
-        if self.ly == self.lyc { self.stat |= 0x04; if self.stat & 0x40 != 0 { self.stat_irq = true; } }
-        else { self.stat &= !0x04; }
-    }
-    fn render_scanline(&mut self, vram: &[u8; 0x2000]) {
-        let ly = self.ly as usize;
-        if ly >= LCD_HEIGHT { return; }
-        let lcdc = self.lcdc;
-        if lcdc & 0x01 == 0 {
-            for x in 0..LCD_WIDTH { self.framebuffer[ly * LCD_WIDTH + x] = 0; }
-            return;
-        }
-        let tile_map_base: usize = if lcdc & 0x08 != 0 { 0x1C00 } else { 0x1800 };
-        let tile_data_base: usize = if lcdc & 0x10 != 0 { 0x0000 } else { 0x0800 };
-        let signed_addressing = lcdc & 0x10 == 0;
-        let map_y = (ly + self.scy as usize) & 0xFF;
-        let tile_row = map_y / 8; let pixel_row = map_y % 8;
-        for x in 0..LCD_WIDTH {
-            let map_x = (x + self.scx as usize) & 0xFF;
-            let tile_col = map_x / 8; let pixel_col = map_x % 8;
-            let tile_idx = vram.get(tile_map_base + tile_row * 32 + tile_col).copied().unwrap_or(0);
-            let tile_addr = if signed_addressing {
-                ((tile_data_base as i32) + (tile_idx as i8 as i32) * 16 + pixel_row as i32 * 2) as usize
-            } else {
-                tile_data_base + (tile_idx as usize) * 16 + pixel_row * 2
-            };
-            let lo = vram.get(tile_addr).copied().unwrap_or(0);
-            let hi = vram.get(tile_addr + 1).copied().unwrap_or(0);
-            let bit = 7 - pixel_col;
-            let color = ((hi >> bit) & 1) << 1 | ((lo >> bit) & 1);
-            self.framebuffer[ly * LCD_WIDTH + x] = color;
-        }
-    }
-    pub fn read_reg(&self, r: u8) -> u8 {
-        match r { 0x40=>self.lcdc, 0x41=>self.stat|0x80, 0x42=>self.scy,
-                  0x43=>self.scx, 0x44=>self.ly, 0x45=>self.lyc, _=>0xFF }
-    }
-    pub fn write_reg(&mut self, r: u8, v: u8) {
-        match r { 0x40=>self.lcdc=v, 0x41=>self.stat=(self.stat&0x87)|(v&0x78),
-                  0x42=>self.scy=v, 0x43=>self.scx=v, 0x44=>{}, 0x45=>self.lyc=v, _=>{} }
-    }
-}
-
 // ── APU defined below (Phase 4/5) ──────────────────────────────────────────
 
 // ── Timer ─────────────────────────────────────────────────────────────────────
@@ -295,6 +379,16 @@ impl Timer {
     pub fn write(&mut self, r: u8, v: u8) {
         match r { 0x04=>{self.div_counter=0;self.div=0;} 0x05=>self.tima=v, 0x06=>self.tma=v, 0x07=>self.tac=v&0x07, _=>{} }
     }
+    /// Full 16-bit divider; DIV is its upper byte
+    pub fn div_counter(&self) -> u16 { self.div_counter }
+    /// T-cycles until TIMA next overflows (u64::MAX while stopped)
+    pub fn cycles_to_overflow(&self) -> u64 {
+        if self.tac & 0x04 == 0 { return u64::MAX; }
+        let period: u64 = match self.tac & 0x03 { 0=>1024, 1=>16, 2=>64, 3=>256, _=>1024 };
+        ((256 - self.tima as u64) * period).saturating_sub(self.tima_counter as u64)
+    }
+    /// Preset the divider (post-boot state); DIV follows its upper byte
+    pub fn set_div_counter(&mut self, v: u16) { self.div_counter = v; self.div = (v >> 8) as u8; }
     pub fn read(&self, r: u8) -> u8 {
         match r { 0x04=>self.div, 0x05=>self.tima, 0x06=>self.tma, 0x07=>self.tac, _=>0xFF }
     }
@@ -308,51 +402,109 @@ pub struct Bus {
     pub hram: [u8; 0x7F], pub oam: [u8; 0xA0],
     pub io: [u8; 0x80], pub ie: u8, pub if_reg: u8,
     pub mbc: Mbc, pub ppu: Ppu, pub apu: Apu, pub timer: Timer,
+    /// P1 select bits (4-5) as last written by the game
     pub joypad: u8,
+    /// Pressed buttons (BTN_* mask), set by the host via `GbCore::set_buttons`
+    pub buttons: u8,
     pub double_speed: bool, pub speed_switch_armed: bool,
     // CGB color palettes: [palette_idx][color_idx*2 | byte_offset] = 64 bytes each
     pub bg_cpal:  [u8; 64], pub bg_cps:  u8,  // BCPS index register
     pub obj_cpal: [u8; 64], pub obj_cps: u8,  // OCPS index register
+    /// Serial-out / RAM console text (see `console.rs`)
+    pub console: ConsoleCapture,
+    /// External sensor inputs, refreshed by GbCore's StimulusProvider
+    pub stimulus: StimulusInputs,
+    /// Opcode / IO-write coverage, recorded when Some (see `corpus.rs`)
+    pub coverage: Option<Box<CoverageVector>>,
+    /// Watched address ranges, reported while an instruction runs (see `watch.rs`)
+    pub watchpoints: Watchpoints,
+    /// IO register writes, recorded when Some (see `io_log.rs`)
+    pub io_log: Option<Box<IoWriteLog>>,
+    /// A link transport is attached: serial transfers wait for `GbCore` (see `link.rs`)
+    pub link_attached: bool,
+    /// PPU mode changes, recorded when Some (see `ppu_timeline.rs`)
+    pub ppu_timeline: Option<Box<PpuTimeline>>,
+    /// Per-line scroll and window registers, recorded when Some (see `raster.rs`)
+    pub raster: Option<Box<RasterCapture>>,
+    /// FF46 transfer in flight (see `oam_dma.rs`)
+    pub dma: OamDma,
+    /// Pre-decoded basic blocks, used by `step` when Some (see `block_cache.rs`)
+    pub block_cache: Option<Box<BlockCache>>,
+    /// Cartridge RAM was written since the autosave last looked (see `sram_autosave.rs`)
+    pub sram_dirty: bool,
+    /// The game disabled cartridge RAM since the autosave last looked
+    pub sram_closed: bool,
+    /// What FEA0-FEFF and unmapped IO read as (see `open_bus.rs`)
+    pub open_bus: OpenBusPolicy,
+    /// OAM DMA cuts the CPU off from memory below FF00 (see `oam_dma.rs`);
+    /// off in `AccuracyProfile::Fast`
+    pub dma_bus_lock: bool,
+    /// Last byte the CPU read or wrote, for `OpenBusPolicy::LastValue`
+    data_bus: Cell<u8>,
 }
 impl Bus {
-    pub fn new(cart: Cartridge) -> Self {
+    pub fn new(cart: Cartridge) -> Self { Self::with_config(cart, &CoreConfig::default()) }
+    /// Build a bus with RAM filled per `config.mem_init` (see `meminit.rs`)
+    /// and `config.lite` reductions applied
+    pub fn with_config(cart: Cartridge, config: &CoreConfig) -> Self {
         let mbc = Mbc::new(cart.kind.clone());
-        Bus { rom: cart.rom, ram: cart.ram, vram: [[0u8;0x2000]; 2], vram_bank: 0,
+        let mut bus = Bus { rom: cart.rom, ram: cart.ram, vram: [[0u8;0x2000]; 2], vram_bank: 0,
               wram: [[0u8;0x1000]; 8], wram_bank: 1,
               hram: [0u8;0x7F], oam: [0u8;0xA0], io: [0u8;0x80], ie: 0, if_reg: 0,
-              mbc, ppu: Ppu::new(), apu: Apu::default(), timer: Timer::default(), joypad: 0xFF,
+              mbc, ppu: Ppu::new(), apu: Apu::default(), timer: Timer::default(), joypad: 0x30, buttons: 0,
               double_speed: false, speed_switch_armed: false,
               bg_cpal: [0xFFu8; 64], bg_cps: 0,
-              obj_cpal: [0u8; 64],   obj_cps: 0 }
-    }
+              obj_cpal: [0u8; 64],   obj_cps: 0,
+              console: ConsoleCapture::new(), stimulus: StimulusInputs::default(), coverage: None,
+              watchpoints: Watchpoints::default(), io_log: None, link_attached: false, ppu_timeline: None, raster: None,
+              dma: OamDma::new(), block_cache: None, sram_dirty: false, sram_closed: false,
+              open_bus: OpenBusPolicy::AllFF, dma_bus_lock: true, data_bus: Cell::new(0xFF) };
+        apply_mem_init(&mut bus, config);
+        apply_post_boot_io(&mut bus, config.model);
+        bus.ppu.render_skip = config.lite.render;
+        bus.apu.samples_off = config.lite.skip_audio;
+        bus
+    }
+    /// CPU read: 0xFF below FF00 while OAM DMA has the bus
     pub fn read(&self, addr: u16) -> u8 {
+        let v = if self.dma_bus_lock && self.dma.blocks(addr) { 0xFF } else { self.peek(addr) };
+        if self.watchpoints.is_armed() { self.watchpoints.access(addr, v, WatchAccess::Read); }
+        self.data_bus.set(v);
+        v
+    }
+    /// `read` without reporting to watchpoints
+    pub fn peek(&self, addr: u16) -> u8 {
         match addr {
             0x0000..=0x7FFF => { let m=self.mbc.rom_addr(addr); self.rom.get(m).copied().unwrap_or(0xFF) }
             0x8000..=0x9FFF => self.vram[self.vram_bank as usize][(addr-0x8000) as usize],
-            0xA000..=0xBFFF => {
-                if self.mbc.ram_enable {
-                    if matches!(self.mbc.kind, CartridgeKind::Mbc3) && self.mbc.rtc_sel != 0xFF {
-                        self.mbc.rtc_latch[self.mbc.rtc_sel as usize]
-                    } else {
-                        let off = self.mbc.ram_bank as usize * 0x2000 + (addr-0xA000) as usize;
-                        self.ram.get(off).copied().unwrap_or(0xFF)
-                    }
-                } else { 0xFF }
+            0xA000..=0xBFFF if self.mbc.ram_enable => {
+                if matches!(self.mbc.kind, CartridgeKind::Mbc3) && self.mbc.rtc_sel != 0xFF {
+                    self.mbc.rtc_latch[self.mbc.rtc_sel as usize]
+                } else {
+                    let off = self.mbc.ram_bank as usize * 0x2000 + (addr-0xA000) as usize;
+                    self.ram.get(off).copied().unwrap_or(0xFF)
+                }
             }
             0xC000..=0xCFFF => self.wram[0][(addr-0xC000) as usize],
             0xD000..=0xDFFF => self.wram[self.wram_bank as usize][(addr-0xD000) as usize],
             0xE000..=0xEFFF => self.wram[0][(addr-0xE000) as usize],
             0xF000..=0xFDFF => self.wram[self.wram_bank as usize][(addr-0xF000) as usize],
             0xFE00..=0xFE9F => self.oam[(addr-0xFE00) as usize],
-            0xFF00 => self.joypad,
+            0xFEA0..=0xFEFF => self.open_bus_read(addr),
+            0xFF00 => p1_read(self.joypad, self.buttons),
             0xFF01..=0xFF03 => self.io[(addr-0xFF00) as usize],
             0xFF04..=0xFF07 => self.timer.read((addr-0xFF00) as u8),
             0xFF0F => self.if_reg,
             0xFF10..=0xFF3F => 0xFF,
             0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.ppu.read_reg((addr-0xFF00) as u8),
-            0xFF46 => 0xFF,
+            0xFF46 => self.dma.reg,
             0xFF4D => (if self.double_speed {0x80} else {0}) | (if self.speed_switch_armed {0x01} else {0}),
             0xFF4F => 0xFE | self.vram_bank,
+            // CGB IR port: bit 0 LED, bits 6-7 read enable; bit 1 reads 0 while light is received
+            0xFF56 => {
+                let rp = self.io[0x56];
+                0x3C | rp | if rp & 0xC0 == 0xC0 && self.stimulus.ir_light { 0 } else { 0x02 }
+            }
             0xFF68 => self.bg_cps,
             0xFF69 => self.bg_cpal[(self.bg_cps & 0x3F) as usize],
             0xFF6A => self.obj_cps,
@@ -360,33 +512,61 @@ impl Bus {
             0xFF70 => 0xF8 | self.wram_bank,
             0xFF80..=0xFFFE => self.hram[(addr-0xFF80) as usize],
             0xFFFF => self.ie,
-            _ => 0xFF,
+            _ => self.open_bus_read(addr),
         }
     }
+    /// A read nothing answers, per `open_bus`
+    fn open_bus_read(&self, addr: u16) -> u8 {
+        let oam_blocked = self.ppu.lcdc & 0x80 != 0 && matches!(self.ppu.mode, PpuMode::OamScan | PpuMode::Drawing);
+        self.open_bus.read(addr, self.data_bus.get(), oam_blocked)
+    }
+    /// CPU write: dropped below FF00 while OAM DMA has the bus, before
+    /// anything observes it
     pub fn write(&mut self, addr: u16, val: u8) {
-        if self.mbc.write(addr, val) { return; }
+        if self.dma_bus_lock && self.dma.blocks(addr) { return; }
+        self.data_bus.set(val);
+        if self.watchpoints.is_armed() { self.watchpoints.access(addr, val, WatchAccess::Write); }
+        if let (0xFF00..=0xFF7F, Some(c)) = (addr, self.coverage.as_mut()) { c.record_io(addr as u8); }
+        if let (0xFF00..=0xFF7F | 0xFFFF, Some(l)) = (addr, self.io_log.as_mut()) { l.record(addr, val); }
+        if let Some(c) = self.block_cache.as_mut() { c.on_write(addr); }
+        let ram_was_enabled = self.mbc.ram_enable;
+        if self.mbc.write(addr, val) {
+            if ram_was_enabled && !self.mbc.ram_enable { self.sram_closed = true; }
+            return;
+        }
         match addr {
             0x8000..=0x9FFF => self.vram[self.vram_bank as usize][(addr-0x8000) as usize] = val,
-            0xA000..=0xBFFF => {
-                if self.mbc.ram_enable {
-                    if matches!(self.mbc.kind, CartridgeKind::Mbc3) && self.mbc.rtc_sel != 0xFF {
-                        self.mbc.rtc_reg[self.mbc.rtc_sel as usize] = val;
-                    } else {
-                        let off = self.mbc.ram_bank as usize * 0x2000 + (addr-0xA000) as usize;
-                        if off < self.ram.len() { self.ram[off] = val; }
-                    }
+            0xA000..=0xBFFF if self.mbc.ram_enable => {
+                if matches!(self.mbc.kind, CartridgeKind::Mbc3) && self.mbc.rtc_sel != 0xFF {
+                    self.mbc.rtc_reg[self.mbc.rtc_sel as usize] = val;
+                } else {
+                    let off = self.mbc.ram_bank as usize * 0x2000 + (addr-0xA000) as usize;
+                    if off < self.ram.len() { self.ram[off] = val; self.sram_dirty = true; }
                 }
             }
             0xC000..=0xCFFF => self.wram[0][(addr-0xC000) as usize] = val,
             0xD000..=0xDFFF => self.wram[self.wram_bank as usize][(addr-0xD000) as usize] = val,
             0xFE00..=0xFE9F => self.oam[(addr-0xFE00) as usize] = val,
-            0xFF00 => self.joypad = val,
-            0xFF04..=0xFF07 => self.timer.write((addr-0xFF00) as u8, val),
+            0xFF00 => self.joypad = val & 0x30,
+            0xFF01 => self.io[0x01] = val,
+            0xFF02 => {
+                self.io[0x02] = val;
+                // Internal-clock transfer: shift SB out at once; no link partner, so 0xFF shifts in
+                if val & 0x81 == 0x81 && !self.link_attached {
+                    self.console.push_serial(self.io[0x01]);
+                    self.io[0x01] = 0xFF;
+                    self.io[0x02] &= 0x7F;
+                    self.if_reg |= 0x08;
+                }
+            }
+            0xFF04 => self.reset_div(),
+            0xFF05..=0xFF07 => self.timer.write((addr-0xFF00) as u8, val),
             0xFF0F => self.if_reg = val,
             0xFF10..=0xFF3F => self.apu.write_reg((addr-0xFF00) as u8, val),
             0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.ppu.write_reg((addr-0xFF00) as u8, val),
             0xFF4D => self.speed_switch_armed = val & 0x01 != 0,
             0xFF4F => self.vram_bank = val & 0x01,
+            0xFF56 => self.io[0x56] = val & 0xC1,
             0xFF68 => self.bg_cps = val & 0xBF,  // bit 6 reserved
             0xFF69 => {
                 let idx = (self.bg_cps & 0x3F) as usize;
@@ -400,13 +580,32 @@ impl Bus {
                 if self.obj_cps & 0x80 != 0 { self.obj_cps = (self.obj_cps & 0x80) | ((idx as u8 + 1) & 0x3F); }
             }
             0xFF70 => self.wram_bank = if val & 0x07 == 0 { 1 } else { val & 0x07 },
-            0xFF46 => { let src=(val as u16)<<8; for i in 0..0xA0u16 { let b=self.read(src+i); self.oam[i as usize]=b; } }
+            0xFF46 => self.dma.start(val),
             0xFF80..=0xFFFE => self.hram[(addr-0xFF80) as usize] = val,
             0xFFFF => self.ie = val,
             _ => {}
         }
     }
 
+    /// Pull new RAM-console bytes into `console` (no-op without a RamConsole)
+    pub fn poll_console(&mut self) {
+        let mut console = std::mem::take(&mut self.console);
+        console.poll_ram(|a| self.read(a));
+        self.console = console;
+    }
+
+    /// The block cache's instruction at `pc`, when the cache is on and the
+    /// fetch has no bus side effects to model (OAM DMA, watchpoints, the
+    /// open-bus data latch, the halt bug's re-fetch)
+    fn fetch_cached(&mut self, pc: u16, halt_bug: bool) -> Option<CachedOp> {
+        if self.block_cache.is_none() || halt_bug || self.dma.active() || !self.watchpoints.is_empty()
+            || self.open_bus == OpenBusPolicy::LastValue { return None; }
+        let mut cache = self.block_cache.take();
+        let op = cache.as_mut().and_then(|c| c.fetch(self, pc));
+        self.block_cache = cache;
+        op
+    }
+
     /// Decode a CGB palette entry (2-byte little-endian RGB555) to (r8,g8,b8)
     pub fn cgb_color(cpal: &[u8; 64], palette: u8, color: u8) -> (u8, u8, u8) {
         let idx = (palette as usize) * 8 + (color as usize) * 2;
@@ -422,9 +621,9 @@ impl Bus {
     /// Get full BG color palette as RGB888 array [palette][color] = (r,g,b)
     pub fn bg_palette_rgb(&self) -> [[(u8,u8,u8); 4]; 8] {
         let mut out = [[(0u8,0u8,0u8); 4]; 8];
-        for p in 0..8 {
-            for c in 0..4 {
-                out[p][c] = Self::cgb_color(&self.bg_cpal, p as u8, c as u8);
+        for (p, row) in out.iter_mut().enumerate() {
+            for (c, rgb) in row.iter_mut().enumerate() {
+                *rgb = Self::cgb_color(&self.bg_cpal, p as u8, c as u8);
             }
         }
         out
@@ -433,24 +632,51 @@ impl Bus {
     /// Get full OBJ color palette as RGB888 array [palette][color] = (r,g,b)
     pub fn obj_palette_rgb(&self) -> [[(u8,u8,u8); 4]; 8] {
         let mut out = [[(0u8,0u8,0u8); 4]; 8];
-        for p in 0..8 {
-            for c in 0..4 {
-                out[p][c] = Self::cgb_color(&self.obj_cpal, p as u8, c as u8);
+        for (p, row) in out.iter_mut().enumerate() {
+            for (c, rgb) in row.iter_mut().enumerate() {
+                *rgb = Self::cgb_color(&self.obj_cpal, p as u8, c as u8);
             }
         }
         out
     }
 
+    /// Zero DIV (a write to FF04, or STOP)
+    pub fn reset_div(&mut self) {
+        // Resetting DIV drops the sequencer's input bit: early clock if it was set
+        let div = self.timer.div_counter();
+        self.timer.write(0x04, 0);
+        self.apu.clock_from_div(div, 0, self.double_speed);
+    }
+    /// A selected joypad line is low (a selected button is held)
+    pub fn joypad_line_low(&self) -> bool { p1_read(self.joypad, self.buttons) & 0x0F != 0x0F }
+    /// T-cycles until the next PPU mode change, TIMA overflow or APU
+    /// frame-sequencer clock: a `step_subsystems` call no longer than this
+    /// raises no interrupt and moves no state machine before its last cycle
+    pub fn cycles_to_next_event(&self) -> u64 {
+        let speed = if self.double_speed { 2 } else { 1 };
+        let ppu = self.ppu.dots_to_next_mode().map_or(u64::MAX, |d| d as u64 * speed);
+        // The sequencer is clocked when DIV bit 12 (13 at double speed) falls
+        let period = 1u64 << if self.double_speed { 14 } else { 13 };
+        let sequencer = period - (self.timer.div_counter() as u64 & (period - 1));
+        ppu.min(self.timer.cycles_to_overflow()).min(sequencer)
+    }
     pub fn step_subsystems(&mut self, cycles: u8) {
-        // In double-speed mode CPU runs 2x; PPU/APU/Timer stay at 1x speed
-        let sub_cycles = if self.double_speed { (cycles + 1) / 2 } else { cycles };
-        let vram = self.vram[self.vram_bank as usize]; let oam = self.oam;
-        self.ppu.step(sub_cycles, &vram, &oam);
+        // In double-speed mode CPU and DIV/timer run 2x; PPU/APU stay at 1x speed
+        let sub_cycles = if self.double_speed { cycles.div_ceil(2) } else { cycles };
+        let mut dma = std::mem::take(&mut self.dma);
+        dma.step(cycles, |src, i| self.oam[i as usize] = self.peek(src));
+        self.dma = dma;
+        let mode = self.ppu.mode;
+        self.ppu.step_banks(sub_cycles, [&self.vram[0], &self.vram[1]], &self.oam);
+        if let Some(t) = self.ppu_timeline.as_mut() { t.record(mode, &self.ppu, sub_cycles as u32); }
+        if let Some(r) = self.raster.as_mut() { r.record(mode, &self.ppu); }
         if self.ppu.vblank_irq { self.if_reg |= 0x01; }
         if self.ppu.stat_irq   { self.if_reg |= 0x02; }
-        self.timer.step(sub_cycles);
+        let div = self.timer.div_counter();
+        self.timer.step(cycles);
         if self.timer.overflow_irq { self.if_reg |= 0x04; }
-        self.apu.step_with_fs(sub_cycles);
+        self.apu.step(sub_cycles);
+        self.apu.clock_from_div(div, self.timer.div_counter(), self.double_speed);
     }
 }
 
@@ -464,7 +690,19 @@ pub struct ReplayFrame {
     pub t_cycles:  u64,
     pub pc:        u16,
     pub ly:        u8,
-    pub snapshot:  String, // mrom.snap.v1 JSON
+    pub host_us:   u64,    // HostClock timestamp at capture
+    pub phash:     Option<u64>, // perceptual frame hash, when enabled
+    pub audio_hash: Option<u64>, // hash of the frame's audio output, when enabled
+    pub routine:   Option<u16>, // innermost subroutine entry (shadow call stack)
+    pub sprites:   Option<Vec<VisibleSprite>>, // on-screen sprites, when enabled
+    pub raster:    Option<Vec<ScanlineRegs>>, // per-line scroll / window registers, when `Bus::raster` is set
+    pub text:      Option<Vec<ScreenText>>, // on-screen text, when a glyph table is set
+    pub regs:      Registers,
+    pub lcdc:      u8,
+    pub rom_bank:  u16,
+    pub memory:    Option<MemoryDelta>, // v2 change list (see `replay_delta.rs`)
+    pub events:    Vec<String>, // trigger labels that fired this frame (see `triggers.rs`)
+    pub snapshot:  String, // mrom.snap.v1 JSON; v2 keyframes only
 }
 
 #[derive(Debug, Default)]
@@ -472,40 +710,115 @@ pub struct ReplayCapture {
     pub frames:      Vec<ReplayFrame>,
     pub max_frames:  usize,
     pub rom_title:   String,
+    /// Record a perceptual hash of each captured frame
+    pub phash:       bool,
+    /// Record a hash of each captured frame's audio output
+    pub audio_hash:  bool,
+    /// Record each captured frame's visible sprites
+    pub sprites:     bool,
+    /// Read each captured frame's on-screen text through this table
+    pub glyphs:      Option<GlyphTable>,
+    /// Write mrom.replay.v2 with a keyframe every this many frames
+    pub keyframe_interval: Option<u32>,
+    last_memory:     Option<Box<ReplayMemory>>,
 }
 
 impl ReplayCapture {
     pub fn new(max_frames: usize, rom_title: &str) -> Self {
-        ReplayCapture { frames: Vec::with_capacity(max_frames), max_frames, rom_title: rom_title.to_string() }
-    }
+        ReplayCapture { frames: Vec::with_capacity(max_frames), max_frames, rom_title: rom_title.to_string(), phash: false, audio_hash: false, sprites: false, glyphs: None, keyframe_interval: None, last_memory: None }
+    }
+
+    /// Enable per-frame perceptual hashes (`"ph"` in the manifest)
+    pub fn with_phash(mut self, enabled: bool) -> Self { self.phash = enabled; self }
+    /// Enable per-frame audio hashes (`"ah"`); drain the APU samples after
+    /// every capture so each hash covers one frame
+    pub fn with_audio_hash(mut self, enabled: bool) -> Self { self.audio_hash = enabled; self }
+    /// Enable per-frame sprite lists (`"spr"`, see `sprites.rs`)
+    pub fn with_sprites(mut self, enabled: bool) -> Self { self.sprites = enabled; self }
+    /// Enable per-frame on-screen text (`"txt"`, see `text.rs`)
+    pub fn with_text(mut self, glyphs: Option<GlyphTable>) -> Self { self.glyphs = glyphs; self }
+    /// Write mrom.replay.v2: full snapshots every `interval` frames, memory
+    /// change lists in between (see `replay_delta.rs`)
+    pub fn with_keyframes(mut self, interval: Option<u32>) -> Self { self.keyframe_interval = interval.map(|n| n.max(1)); self }
 
     /// Record one frame from a live GbCore. Call after run_frame().
     pub fn capture(&mut self, core: &GbCore) {
         if self.frames.len() >= self.max_frames { return; }
+        let (memory, snapshot) = match self.keyframe_interval {
+            None => (None, core.state_json()),
+            Some(n) => {
+                let mem = Box::new(ReplayMemory::of(&core.bus));
+                let key = self.frames.len().is_multiple_of(n as usize);
+                let delta = match (&self.last_memory, key) {
+                    (Some(last), false) => MemoryDelta::between(last, &mem),
+                    _ => MemoryDelta::full(&mem),
+                };
+                self.last_memory = Some(mem);
+                (Some(delta), if key { core.state_json() } else { String::new() })
+            }
+        };
         self.frames.push(ReplayFrame {
             frame_idx: core.clock.frame_count(),
             t_cycles:  core.clock.t_cycles,
             pc:        core.regs.pc,
             ly:        core.bus.ppu.ly,
-            snapshot:  core.state_json(),
+            host_us:   core.host_clock.now_us(),
+            phash:     self.phash.then(|| phash(&core.bus.ppu.framebuffer)),
+            audio_hash: self.audio_hash.then(|| audio_hash(&core.bus.apu.sample_buffer)),
+            routine:   core.shadow_stack.current(),
+            sprites:   self.sprites.then(|| visible_sprites(&core.bus)),
+            raster:    core.bus.raster.as_ref().map(|r| r.last_frame().to_vec()),
+            text:      self.glyphs.as_ref().map(|g| screen_text(&core.bus, g)),
+            regs:      core.regs.clone(),
+            lcdc:      core.bus.ppu.lcdc,
+            rom_bank:  core.bus.mbc.rom_bank,
+            memory,
+            events:    vec![],
+            snapshot,
         });
     }
 
-    /// Export all captured frames as a replay manifest JSON (mrom.replay.v1)
+    /// Tag the last captured frame with an event label (`"ev"`), e.g. the
+    /// labels `WatchTriggers::evaluate` returns
+    pub fn mark(&mut self, label: &str) {
+        if let Some(f) = self.frames.last_mut() { f.events.push(label.to_string()); }
+    }
+
+    /// Export all captured frames as a replay manifest JSON (mrom.replay.v1,
+    /// or mrom.replay.v2 with keyframes enabled)
     pub fn to_json(&self) -> String {
-        let frames: Vec<String> = self.frames.iter().map(|f|
-            format!("{{"fi":{},"tc":{},"pc":{},"snap":{}}}", 
-                    f.frame_idx, f.t_cycles, f.pc, f.snapshot)
-        ).collect();
+        let v2 = self.keyframe_interval.is_some();
+        let frames: Vec<String> = self.frames.iter().map(|f| {
+            let ph = f.phash.map(|h| format!("\"ph\":\"{h:016x}\",")).unwrap_or_default();
+            let ah = f.audio_hash.map(|h| format!("\"ah\":\"{h:016x}\",")).unwrap_or_default();
+            let rt = f.routine.map(|r| format!("\"rt\":{r},")).unwrap_or_default();
+            let spr = f.sprites.as_ref().map(|s| format!("\"spr\":{},", sprites_json(s))).unwrap_or_default();
+            let rs = f.raster.as_ref().map(|r| format!("\"rs\":{},", raster_json(r))).unwrap_or_default();
+            let txt = f.text.as_ref().map(|t| format!("\"txt\":{},", screen_text_json(t))).unwrap_or_default();
+            let ev = if f.events.is_empty() { String::new() } else {
+                format!("\"ev\":[{}],", f.events.iter().map(|e| format!("\"{e}\"")).collect::<Vec<_>>().join(","))
+            };
+            let head = format!("{{\"fi\":{},\"tc\":{},\"pc\":{},\"ts\":{},{}{}{}{}{}{}{}",
+                    f.frame_idx, f.t_cycles, f.pc, f.host_us, ph, ah, rt, spr, rs, txt, ev);
+            match (&f.memory, v2) {
+                (Some(mem), true) => {
+                    let r = &f.regs;
+                    let key = if f.snapshot.is_empty() { String::new() } else { format!("\"key\":true,\"snap\":{},", f.snapshot) };
+                    format!("{head}\"cpu\":{{\"sp\":{},\"a\":{},\"f\":{},\"b\":{},\"c\":{},\"d\":{},\"e\":{},\"h\":{},\"l\":{}}},\"ly\":{},\"lcdc\":{},\"rb\":{},{key}\"mem\":{}}}",
+                        r.sp, r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, f.ly, f.lcdc, f.rom_bank, mem.to_json())
+                }
+                _ => format!("{head}\"snap\":{}}}", f.snapshot),
+            }
+        }).collect();
         format!(
-            "{{"version":"mrom.replay.v1","rom":"{}","frame_count":{},"frames":[{}]}}",
-            self.rom_title, self.frames.len(), frames.join(",")
+            "{{\"version\":\"{}\",\"rom\":\"{}\",\"frame_count\":{},\"frames\":[{}]}}",
+            if v2 { REPLAY_V2_VERSION } else { "mrom.replay.v1" }, self.rom_title, self.frames.len(), frames.join(",")
         )
     }
 
-    /// Write replay manifest to file
+    /// Write replay manifest to file (sealed when a key is set, see `seal.rs`)
     pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
-        std::fs::write(path, self.to_json())
+        write_sealed(path, self.to_json().as_bytes())
     }
 }
 
@@ -520,16 +833,78 @@ impl Clock {
 
 // ── Error ─────────────────────────────────────────────────────────────────────
 #[derive(Debug)]
-pub enum CoreError { InvalidRom(String), Unimplemented(String) }
+pub enum CoreError {
+    /// The ROM image is unusable; `offset` is the header byte at fault, if any
+    InvalidRom { reason: String, offset: Option<usize> },
+    /// Cartridge type byte (0x0147) of a mapper gb-core does not emulate
+    UnsupportedMapper(u8),
+    /// A savestate of another format version than this core writes
+    StateVersionMismatch { found: String, expected: &'static str },
+    Unimplemented(String), InvalidState(String),
+    /// Reading or writing a file failed (`source()` is the `std::io::Error`)
+    IoError(std::io::Error),
+    /// run_frame left early because the interrupt handle was raised
+    Interrupted,
+    /// A breakpoint fired before the instruction at its PC ran (see `breakpoints.rs`)
+    Break(BreakHit),
+    /// A stopping watchpoint fired; the accessing instruction has run (see `watch.rs`)
+    Watch(WatchHit),
+    /// The CPU hit an illegal opcode and hung; reported once, on the step
+    /// that locked it (`GbCore::locked`)
+    CpuLocked { pc: u16, opcode: u8 },
+    /// `LD B,B` ran with `GbCore::ld_b_b_break` set (the mooneye / BGB
+    /// software breakpoint); the instruction has run
+    SoftwareBreak { pc: u16 },
+}
 impl fmt::Display for CoreError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            CoreError::InvalidRom(s) => write!(f, "InvalidRom: {s}"),
+            CoreError::InvalidRom { reason, offset: Some(o) } => write!(f, "InvalidRom: {reason} (at {o:#06x})"),
+            CoreError::InvalidRom { reason, offset: None } => write!(f, "InvalidRom: {reason}"),
+            CoreError::UnsupportedMapper(b) => write!(f, "UnsupportedMapper: cartridge type {b:#04x}"),
+            CoreError::StateVersionMismatch { found, expected } => write!(f, "StateVersionMismatch: {found:?}, expected {expected:?}"),
+            CoreError::IoError(_) => write!(f, "IoError"),
             CoreError::Unimplemented(s) => write!(f, "Unimplemented: {s}"),
+            CoreError::InvalidState(s) => write!(f, "InvalidState: {s}"),
+            CoreError::Interrupted => write!(f, "Interrupted"),
+            CoreError::Break(hit) => write!(f, "Break: {hit}"),
+            CoreError::Watch(hit) => write!(f, "Watch: {hit}"),
+            CoreError::CpuLocked { pc, opcode } => write!(f, "CpuLocked: illegal opcode {opcode:02X} at {pc:04X}"),
+            CoreError::SoftwareBreak { pc } => write!(f, "SoftwareBreak: LD B,B at {pc:04X}"),
         }
     }
 }
-impl std::error::Error for CoreError {}
+impl std::error::Error for CoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self { CoreError::IoError(e) => Some(e), _ => None }
+    }
+}
+impl From<std::io::Error> for CoreError {
+    fn from(e: std::io::Error) -> Self { CoreError::IoError(e) }
+}
+impl CoreError {
+    pub fn invalid_rom(reason: impl Into<String>, offset: Option<usize>) -> Self {
+        CoreError::InvalidRom { reason: reason.into(), offset }
+    }
+}
+
+// ── Save points ───────────────────────────────────────────────────────────────
+/// `version` of the savestates `save_state` writes and `load_state` accepts
+pub const SAVE_STATE_VERSION: &str = "mrom.sav.v1";
+
+/// Where a savestate was taken. `Instruction` states resume mid-frame exactly
+/// where the CPU stopped; `Frame` states are taken on the step that enters
+/// VBlank, so every resumed frame starts from a clean boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SavePoint { Instruction, Frame }
+impl SavePoint {
+    pub fn as_str(&self) -> &'static str {
+        match self { SavePoint::Instruction => "instruction", SavePoint::Frame => "frame" }
+    }
+    pub fn parse(s: &str) -> Option<Self> {
+        match s { "instruction" => Some(SavePoint::Instruction), "frame" => Some(SavePoint::Frame), _ => None }
+    }
+}
 
 // ── CB-prefix (full 256-op) ───────────────────────────────────────────────────
 fn exec_cb(regs: &mut Registers, bus: &mut Bus) -> u8 {
@@ -538,7 +913,7 @@ fn exec_cb(regs: &mut Registers, bus: &mut Bus) -> u8 {
     let r = op & 0x07;
     let kind = op >> 6;
     let bit_n = (op >> 3) & 0x07;
-    let cycles = if r == 6 { 16 } else { 8 };
+    let cycles = cb_cycles(op);
     let val = match r {
         0=>regs.b, 1=>regs.c, 2=>regs.d, 3=>regs.e,
         4=>regs.h, 5=>regs.l, 6=>bus.read(regs.hl()), 7=>regs.a, _=>unreachable!(),
@@ -551,7 +926,7 @@ fn exec_cb(regs: &mut Registers, bus: &mut Bus) -> u8 {
             3 => { let oc=if regs.flag_c(){0x80}else{0}; let nc=val&1; let r=(val>>1)|oc; regs.set_flag_c(nc!=0); regs.set_flag_z(r==0); regs.set_flag_n(false); regs.set_flag_h(false); r }
             4 => { let c=val>>7; let r=val<<1; regs.set_flag_c(c!=0); regs.set_flag_z(r==0); regs.set_flag_n(false); regs.set_flag_h(false); r }
             5 => { let c=val&1; let r=(val>>1)|(val&0x80); regs.set_flag_c(c!=0); regs.set_flag_z(r==0); regs.set_flag_n(false); regs.set_flag_h(false); r }
-            6 => { let r=(val<<4)|(val>>4); regs.set_flag_z(r==0); regs.set_flag_c(false); regs.set_flag_n(false); regs.set_flag_h(false); r }
+            6 => { let r=val.rotate_right(4); regs.set_flag_z(r==0); regs.set_flag_c(false); regs.set_flag_n(false); regs.set_flag_h(false); r }
             7 => { let c=val&1; let r=val>>1; regs.set_flag_c(c!=0); regs.set_flag_z(r==0); regs.set_flag_n(false); regs.set_flag_h(false); r }
             _ => unreachable!()
         }),
@@ -569,8 +944,6 @@ fn exec_cb(regs: &mut Registers, bus: &mut Bus) -> u8 {
     cycles
 }
 
-// ── SM83 decode table ─────────────────────────────────────────────────────────
-
 // ── Sprite ────────────────────────────────────────────────────────────────────
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Sprite {
@@ -587,10 +960,24 @@ impl Sprite {
     pub fn y_flip(&self)    -> bool { self.flags & 0x40 != 0 }
     pub fn x_flip(&self)    -> bool { self.flags & 0x20 != 0 }
     pub fn palette(&self)   -> u8   { (self.flags >> 4) & 0x01 }
+    /// CGB mode: OBJ colour palette (bits 0-2) and tile VRAM bank (bit 3)
+    pub fn cgb_palette(&self) -> u8  { self.flags & 0x07 }
+    pub fn bank(&self)      -> usize { (self.flags >> 3 & 0x01) as usize }
 }
 
+/// Opcodes with no instruction behind them; executing one hangs the CPU
+pub const ILLEGAL_OPCODES: [u8; 11] = [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+
 fn apply_palette(pal: u8, c: u8) -> u8 { (pal >> (c * 2)) & 0x03 }
 
+/// Bit planes of row `prow` of BG / window tile `idx`, addressed per LCDC bit 4
+fn bg_tile_planes(vram: &[u8; 0x2000], lcdc: u8, idx: u8, prow: usize) -> [u8; 2] {
+    let ta = if lcdc & 0x10 == 0 {
+        (0x0800 + idx as i8 as i32 * 16 + prow as i32 * 2) as usize
+    } else { idx as usize * 16 + prow * 2 };
+    [*vram.get(ta).unwrap_or(&0), *vram.get(ta + 1).unwrap_or(&0)]
+}
+
 // ── PPU (Phase 4) ─────────────────────────────────────────────────────────────
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PpuMode { HBlank = 0, VBlank = 1, OamScan = 2, Drawing = 3 }
@@ -602,8 +989,29 @@ pub struct Ppu {
     pub wy: u8, pub wx: u8, pub wlc: u8,
     pub pal_bg: u8, pub pal_obj0: u8, pub pal_obj1: u8,
     pub framebuffer: Vec<u8>,
+    /// Per framebuffer pixel: 0 BG / window, 1 sprite with OBP0, 2 sprite with OBP1
+    pub pixel_source: Vec<u8>,
     pub frame_ready: bool, pub stat_irq: bool, pub vblank_irq: bool,
+    /// Lite-mode line / frame skipping (see `lite.rs`)
+    pub render_skip: RenderSkip,
+    /// Wide paths for line decoding (see `simd.rs`); output is identical at every level
+    pub simd: SimdLevel,
+    /// This line's sprite selection (see `oam_scan.rs`)
+    pub oam_scan: OamScan,
+    /// Dot-by-dot mode 3 when Some (see `pixel_fifo.rs`); None draws whole lines
+    pub fifo: Option<Box<PixelFifo>>,
+    /// Length of the last mode 3; always 172 without the pixel FIFO
+    pub mode3_dots: u32,
+    /// CGB mode: BG map attributes and colour palettes (see `cgb_render.rs`)
+    pub cgb: bool,
+    odd_frame: bool,
 }
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Ppu {
     pub fn new() -> Self {
         Ppu { mode: PpuMode::OamScan, dot: 0, ly: 0, lyc: 0,
@@ -611,32 +1019,55 @@ impl Ppu {
                wy: 0, wx: 0, wlc: 0,
                pal_bg: 0xFC, pal_obj0: 0xFF, pal_obj1: 0xFF,
                framebuffer: vec![0u8; LCD_WIDTH * LCD_HEIGHT],
-               frame_ready: false, stat_irq: false, vblank_irq: false }
+               pixel_source: vec![0u8; LCD_WIDTH * LCD_HEIGHT],
+               frame_ready: false, stat_irq: false, vblank_irq: false,
+               render_skip: RenderSkip::Full, simd: SimdLevel::detect(), oam_scan: OamScan::new(),
+               fifo: None, mode3_dots: PPU_MODE3_CYCLES, cgb: false, odd_frame: false }
     }
+    /// Advance `cycles` dots with a single VRAM bank (DMG)
     pub fn step(&mut self, cycles: u8, vram: &[u8; 0x2000], oam: &[u8; 0xA0]) {
+        self.step_banks(cycles, [vram, vram], oam);
+    }
+    /// Advance `cycles` dots; bank 1 holds the CGB-mode map attributes and tiles
+    pub fn step_banks(&mut self, cycles: u8, vram: [&[u8; 0x2000]; 2], oam: &[u8; 0xA0]) {
         if self.lcdc & 0x80 == 0 { return; }
         self.stat_irq = false; self.vblank_irq = false;
         self.dot += cycles as u32;
         match self.mode {
             PpuMode::OamScan => {
-                if self.dot >= PPU_MODE2_CYCLES { self.dot -= PPU_MODE2_CYCLES; self.mode = PpuMode::Drawing; }
+                self.oam_scan.run(oam, self.ly, self.lcdc & 0x04 != 0, self.dot.min(PPU_MODE2_CYCLES));
+                if self.dot >= PPU_MODE2_CYCLES {
+                    self.dot -= PPU_MODE2_CYCLES; self.mode = PpuMode::Drawing;
+                    if let Some(f) = self.fifo.as_deref_mut() { f.start(self.scx, self.oam_scan.sprites()); }
+                }
             }
             PpuMode::Drawing => {
-                if self.dot >= PPU_MODE3_CYCLES {
-                    self.dot -= PPU_MODE3_CYCLES;
-                    self.render_scanline(vram, oam);
+                // The FIFO runs on a copy taken out of `self` so it can update the registers
+                let mut fifo = self.fifo.take();
+                let end = match fifo.as_deref_mut() {
+                    Some(f) if f.active() => f.run(self, vram, oam, self.dot),
+                    _ => (self.dot >= PPU_MODE3_CYCLES).then(|| {
+                        if self.draws_line() { self.render_scanline(vram, oam); } else { self.skip_scanline(); }
+                        PPU_MODE3_CYCLES
+                    }),
+                };
+                self.fifo = fifo;
+                if let Some(len) = end {
+                    self.dot -= len; self.mode3_dots = len;
                     self.mode = PpuMode::HBlank;
                     if self.stat & 0x08 != 0 { self.stat_irq = true; }
                 }
             }
             PpuMode::HBlank => {
-                if self.dot >= PPU_MODE0_CYCLES {
-                    self.dot -= PPU_MODE0_CYCLES; self.ly += 1; self.check_lyc();
+                let hblank = self.hblank_dots();
+                if self.dot >= hblank {
+                    self.dot -= hblank; self.ly += 1; self.check_lyc();
                     if self.ly >= PPU_VBLANK_LINE as u8 {
                         self.mode = PpuMode::VBlank; self.vblank_irq = true; self.frame_ready = true;
+                        self.odd_frame = !self.odd_frame;
                         if self.stat & 0x10 != 0 { self.stat_irq = true; }
                     } else {
-                        self.mode = PpuMode::OamScan;
+                        self.mode = PpuMode::OamScan; self.oam_scan.restart();
                         if self.stat & 0x20 != 0 { self.stat_irq = true; }
                     }
                 }
@@ -646,6 +1077,7 @@ impl Ppu {
                     self.dot -= DOTS_PER_LINE; self.ly += 1; self.check_lyc();
                     if self.ly > 153 {
                         self.ly = 0; self.wlc = 0; self.mode = PpuMode::OamScan; self.frame_ready = false;
+                        self.oam_scan.restart();
                         if self.stat & 0x20 != 0 { self.stat_irq = true; }
                     }
                 }
@@ -653,86 +1085,137 @@ impl Ppu {
         }
         self.stat = (self.stat & 0xFC) | (self.mode as u8);
     }
+    /// Dots until the next mode change (None with the LCD off)
+    pub fn dots_to_next_mode(&self) -> Option<u32> {
+        if self.lcdc & 0x80 == 0 { return None; }
+        let end = match self.mode {
+            // The FIFO's mode 3 is at least 172 dots; ask again after that
+            PpuMode::Drawing if self.fifo.as_ref().is_some_and(|f| f.active()) => return Some(PPU_MODE3_CYCLES.saturating_sub(self.dot).max(1)),
+            PpuMode::OamScan => PPU_MODE2_CYCLES, PpuMode::Drawing => PPU_MODE3_CYCLES,
+            PpuMode::HBlank => self.hblank_dots(), PpuMode::VBlank => DOTS_PER_LINE,
+        };
+        Some(end.saturating_sub(self.dot))
+    }
+    /// HBlank takes what mode 3 left of the line's 376 dots after mode 2
+    fn hblank_dots(&self) -> u32 { (PPU_MODE3_CYCLES + PPU_MODE0_CYCLES).saturating_sub(self.mode3_dots) }
     fn check_lyc(&mut self) {
         if self.ly == self.lyc { self.stat |= 0x04; if self.stat & 0x40 != 0 { self.stat_irq = true; } }
         else { self.stat &= !0x04; }
     }
-    fn render_scanline(&mut self, vram: &[u8; 0x2000], oam: &[u8; 0xA0]) {
+    fn draws_line(&self) -> bool {
+        match self.render_skip {
+            RenderSkip::Full => true,
+            RenderSkip::AlternateScanlines => self.ly & 1 == 0,
+            RenderSkip::AlternateFrames => !self.odd_frame,
+        }
+    }
+    /// Lite-mode stand-in for `render_scanline`: keeps the window line
+    /// counter in step and, for alternate scanlines, repeats the line above
+    fn skip_scanline(&mut self) {
+        let ly = self.ly as usize;
+        if ly >= LCD_HEIGHT { return; }
+        if self.lcdc & 0x20 != 0 && ly >= self.wy as usize && (self.wx.saturating_sub(7) as usize) < LCD_WIDTH {
+            self.wlc = self.wlc.wrapping_add(1);
+        }
+        if self.render_skip == RenderSkip::AlternateScanlines && ly > 0 {
+            let row = ly * LCD_WIDTH;
+            self.framebuffer.copy_within(row - LCD_WIDTH..row, row);
+            self.pixel_source.copy_within(row - LCD_WIDTH..row, row);
+        }
+    }
+    fn render_scanline(&mut self, vram: [&[u8; 0x2000]; 2], oam: &[u8; 0xA0]) {
         let ly = self.ly as usize;
         if ly >= LCD_HEIGHT { return; }
         let lcdc = self.lcdc;
         let row_base = ly * LCD_WIDTH;
+        // BG / window colour numbers, one tile fetch per 8 pixels
+        let mut bg_raw = [0u8; LCD_WIDTH];
         let mut bg_col = [0u8; LCD_WIDTH];
-        let mut bg_opaque = [false; LCD_WIDTH];
-
-        // BG layer
-        if lcdc & 0x01 != 0 {
+        let mut source = [0u8; LCD_WIDTH];
+        let mut planes = [[0u8; 2]; LCD_WIDTH / 8 + 1];
+        let mut decoded = [0u8; LCD_WIDTH + 8];
+        // CGB mode: each pixel's map attributes (see `cgb_render.rs`)
+        let mut tile_attrs = [BgAttr(0); LCD_WIDTH / 8 + 1];
+        let mut bg_attr = [BgAttr(0); LCD_WIDTH];
+        // Pixels left of this stay colour 0 of no palette (BG off, window not reached)
+        let mut drawn_from = LCD_WIDTH;
+
+        // BG layer; in CGB mode LCDC bit 0 is the sprite priority master instead
+        if bg_enabled(lcdc, self.cgb) {
             let map_base: usize  = if lcdc & 0x08 != 0 { 0x1C00 } else { 0x1800 };
-            let data_base: usize = if lcdc & 0x10 != 0 { 0x0000 } else { 0x0800 };
-            let signed = lcdc & 0x10 == 0;
             let map_y = (ly.wrapping_add(self.scy as usize)) & 0xFF;
             let tile_row = map_y >> 3; let prow = map_y & 7;
-            for x in 0..LCD_WIDTH {
-                let map_x = (x.wrapping_add(self.scx as usize)) & 0xFF;
-                let tc = map_x >> 3; let pc = map_x & 7;
-                let idx = vram[map_base + tile_row * 32 + tc];
-                let ta = if signed {
-                    (data_base as i32 + idx as i8 as i32 * 16 + prow as i32 * 2) as usize
-                } else { data_base + idx as usize * 16 + prow * 2 };
-                let lo = *vram.get(ta).unwrap_or(&0);
-                let hi = *vram.get(ta+1).unwrap_or(&0);
-                let bit = 7 - pc;
-                let c = ((hi>>bit)&1)<<1 | ((lo>>bit)&1);
-                bg_col[x] = apply_palette(self.pal_bg, c);
-                bg_opaque[x] = c != 0;
+            let first = self.scx as usize >> 3;
+            for (i, (p, a)) in planes.iter_mut().zip(&mut tile_attrs).enumerate() {
+                let tc = (first + i) & 31;
+                (*p, *a) = bg_tile(vram, lcdc, self.cgb, map_base + tile_row * 32 + tc, prow);
+            }
+            decode_tile_rows(self.simd, &planes, &mut decoded);
+            let fine = self.scx as usize & 7;
+            bg_raw.copy_from_slice(&decoded[fine..fine + LCD_WIDTH]);
+            if self.cgb {
+                for (x, a) in bg_attr.iter_mut().enumerate() { *a = tile_attrs[(fine + x) >> 3]; }
             }
+            drawn_from = 0;
         }
 
-        // Window layer
+        // Window layer; on DMG, LCDC bit 0 blanks it along with the BG, but
+        // the window still runs and counts its lines
         let wx7 = self.wx.saturating_sub(7) as usize;
         if lcdc & 0x20 != 0 && ly >= self.wy as usize && wx7 < LCD_WIDTH {
-            let wmap: usize  = if lcdc & 0x40 != 0 { 0x1C00 } else { 0x1800 };
-            let data_base: usize = if lcdc & 0x10 != 0 { 0x0000 } else { 0x0800 };
-            let signed = lcdc & 0x10 == 0;
-            let wly = self.wlc as usize;
-            let tile_row = wly >> 3; let prow = wly & 7;
-            for x in wx7..LCD_WIDTH {
-                let tc = (x - wx7) >> 3; let pc = (x - wx7) & 7;
-                let idx = *vram.get(wmap + tile_row * 32 + tc).unwrap_or(&0);
-                let ta = if signed {
-                    (data_base as i32 + idx as i8 as i32 * 16 + prow as i32 * 2) as usize
-                } else { data_base + idx as usize * 16 + prow * 2 };
-                let lo = *vram.get(ta).unwrap_or(&0);
-                let hi = *vram.get(ta+1).unwrap_or(&0);
-                let bit = 7 - pc;
-                let c = ((hi>>bit)&1)<<1 | ((lo>>bit)&1);
-                bg_col[x] = apply_palette(self.pal_bg, c);
-                bg_opaque[x] = c != 0;
+            if bg_enabled(lcdc, self.cgb) {
+                let wmap: usize  = if lcdc & 0x40 != 0 { 0x1C00 } else { 0x1800 };
+                let wly = self.wlc as usize;
+                let tile_row = wly >> 3; let prow = wly & 7;
+                let width = LCD_WIDTH - wx7;
+                let tiles = width.div_ceil(8);
+                for (tc, (p, a)) in planes[..tiles].iter_mut().zip(&mut tile_attrs).enumerate() {
+                    (*p, *a) = bg_tile(vram, lcdc, self.cgb, wmap + tile_row * 32 + tc, prow);
+                }
+                decode_tile_rows(self.simd, &planes[..tiles], &mut decoded);
+                bg_raw[wx7..].copy_from_slice(&decoded[..width]);
+                if self.cgb {
+                    for (x, a) in bg_attr[wx7..].iter_mut().enumerate() { *a = tile_attrs[x >> 3]; }
+                }
+                drawn_from = drawn_from.min(wx7);
             }
             self.wlc = self.wlc.wrapping_add(1);
         }
+        if self.cgb {
+            // Colour numbers go out unmapped; the palette travels in `pixel_source`
+            bg_col[drawn_from..].copy_from_slice(&bg_raw[drawn_from..]);
+            for (s, a) in source[drawn_from..].iter_mut().zip(&bg_attr[drawn_from..]) { *s = a.palette(); }
+        } else {
+            shade_row(self.simd, self.pal_bg, &bg_raw[drawn_from..], &mut bg_col[drawn_from..]);
+        }
 
         // OAM sprites
         if lcdc & 0x02 != 0 {
             let sh: i32 = if lcdc & 0x04 != 0 { 16 } else { 8 };
-            let mut visible: Vec<(i32, Sprite)> = Vec::with_capacity(10);
-            for i in 0..40 {
-                let s = Sprite::from_oam(oam, i);
-                let sy = s.screen_y();
-                if (ly as i32) >= sy && (ly as i32) < sy + sh {
-                    visible.push((s.screen_x(), s));
-                    if visible.len() == 10 { break; }
-                }
+            // The sprites mode 2 selected (a state loaded mid-line may not
+            // have finished scanning); tile and attributes are read now.
+            // A fixed array keeps the scanline allocation-free
+            self.oam_scan.run(oam, self.ly, sh == 16, PPU_MODE2_CYCLES);
+            let mut visible = [Sprite::default(); SPRITES_PER_LINE];
+            let n = self.oam_scan.sprites().len();
+            for (v, s) in visible.iter_mut().zip(self.oam_scan.sprites()) {
+                *v = Sprite { y: s.y, x: s.x, ..Sprite::from_oam(oam, s.index as usize) };
             }
-            visible.sort_by_key(|&(x,_)| x);
-            for (_,s) in visible.iter().rev() {
+            let visible = &mut visible[..n];
+            let bg_priority = bg_priority_enabled(lcdc, self.cgb);
+            // DMG: lower X wins, then lower OAM index; CGB: OAM index alone
+            if !self.cgb { visible.sort_by_key(|s| s.x); }
+            for s in visible.iter().rev() {
                 let sy = s.screen_y();
+                // LCDC bit 2 may have changed since the scan picked it
+                if !(sy..sy + sh).contains(&(ly as i32)) { continue; }
                 let mut row = (ly as i32 - sy) as usize;
                 if s.y_flip() { row = (sh as usize) - 1 - row; }
                 let tile = if sh == 16 { if row < 8 { s.tile & 0xFE } else { s.tile | 0x01 } } else { s.tile };
                 let ta = tile as usize * 16 + (row & 7) * 2;
-                let lo = *vram.get(ta).unwrap_or(&0);
-                let hi = *vram.get(ta+1).unwrap_or(&0);
+                let bank = if self.cgb { s.bank() } else { 0 };
+                let lo = *vram[bank].get(ta).unwrap_or(&0);
+                let hi = *vram[bank].get(ta+1).unwrap_or(&0);
                 let pal = if s.palette() == 0 { self.pal_obj0 } else { self.pal_obj1 };
                 for bi in 0..8usize {
                     let sx = s.screen_x() + bi as i32;
@@ -741,13 +1224,20 @@ impl Ppu {
                     let c = ((hi>>bit)&1)<<1 | ((lo>>bit)&1);
                     if c == 0 { continue; }
                     let px = sx as usize;
-                    if s.bg_priority() && bg_opaque[px] { continue; }
-                    bg_col[px] = apply_palette(pal, c);
+                    if bg_priority && (s.bg_priority() || bg_attr[px].priority()) && bg_raw[px] != 0 { continue; }
+                    if self.cgb {
+                        bg_col[px] = c;
+                        source[px] = CGB_OBJ_SOURCE + s.cgb_palette();
+                    } else {
+                        bg_col[px] = apply_palette(pal, c);
+                        source[px] = 1 + s.palette();
+                    }
                 }
             }
         }
 
-        for x in 0..LCD_WIDTH { self.framebuffer[row_base + x] = bg_col[x]; }
+        self.framebuffer[row_base..row_base + LCD_WIDTH].copy_from_slice(&bg_col);
+        self.pixel_source[row_base..row_base + LCD_WIDTH].copy_from_slice(&source);
     }
     pub fn read_reg(&self, r: u8) -> u8 {
         match r {
@@ -786,6 +1276,17 @@ impl Square {
         if self.freq_timer == 0 { self.freq_timer = self.period(); self.duty_pos = (self.duty_pos+1)&7; }
         else { self.freq_timer -= 1; }
     }
+    /// `cycles` ticks at once; `edge(t, sample)` after each duty step, t cycles in
+    pub fn run(&mut self, cycles: u32, mut edge: impl FnMut(u32, i16)) {
+        let mut t = 0;
+        while t + self.freq_timer < cycles {
+            t += self.freq_timer;
+            self.freq_timer = self.period(); self.duty_pos = (self.duty_pos+1)&7;
+            edge(t, self.sample());
+            t += 1;
+        }
+        self.freq_timer -= cycles - t;
+    }
     pub fn sample(&self) -> i16 {
         if !self.enabled { return 0; }
         if (self.duty_hi() >> (7-self.duty_pos)) & 1 != 0 { self.volume as i16 * 256 } else { 0 }
@@ -809,6 +1310,18 @@ impl WaveChannel {
             self.freq_timer = (2048 - freq) * 2; self.pos = (self.pos+1) & 31;
         } else { self.freq_timer -= 1; }
     }
+    /// `cycles` ticks at once; `edge(t, sample)` after each table step, t cycles in
+    pub fn run(&mut self, cycles: u32, mut edge: impl FnMut(u32, i16)) {
+        let mut t = 0;
+        while t + self.freq_timer < cycles {
+            t += self.freq_timer;
+            let freq = ((self.nr4 as u32 & 0x07) << 8) | self.nr3 as u32;
+            self.freq_timer = (2048 - freq) * 2; self.pos = (self.pos+1) & 31;
+            edge(t, self.sample());
+            t += 1;
+        }
+        self.freq_timer -= cycles - t;
+    }
     pub fn sample(&self) -> i16 {
         if !self.enabled || self.nr0 & 0x80 == 0 { return 0; }
         let byte = self.wave_ram[(self.pos >> 1) as usize];
@@ -834,6 +1347,18 @@ impl NoiseChannel {
             if self.nr3 & 0x08 != 0 { self.lfsr = (self.lfsr & !0x40) | (xor << 6); }
         } else { self.freq_timer -= 1; }
     }
+    /// `cycles` ticks at once; `edge(t, sample)` after each LFSR shift, t cycles in
+    pub fn run(&mut self, cycles: u32, mut edge: impl FnMut(u32, i16)) {
+        let mut t = 0;
+        while t + self.freq_timer < cycles {
+            t += self.freq_timer;
+            self.freq_timer = 0;
+            self.tick();
+            edge(t, self.sample());
+            t += 1;
+        }
+        self.freq_timer -= cycles - t;
+    }
     pub fn sample(&self) -> i16 {
         if !self.enabled { return 0; }
         if self.lfsr & 1 == 0 { self.volume as i16 * 256 } else { 0 }
@@ -845,57 +1370,114 @@ impl NoiseChannel {
 pub struct Apu {
     pub power: bool, pub master_vol: u8, pub nr51: u8,
     pub sq1: Square, pub sq2: Square, pub wave: WaveChannel, pub noise: NoiseChannel,
+    /// Interleaved stereo output, at most two frames' worth until drained
     pub sample_buffer: Vec<i16>,
-    sample_timer: u32,
-    pub fs_counter: u8, pub wave_len: u16, pub noise_len: u16, pub fs_div: u32,
+    /// Channels triggered since the last `take_triggers()` (TRIG_* bits)
+    pub triggers: u8,
+    /// Band-limited mono mix (see `blip.rs`) and each channel's level in it
+    blip: BlipBuffer,
+    levels: [i16; 4],
+    pub fs_counter: u8, pub wave_len: u16, pub noise_len: u16,
+    /// Cartridge audio-in, routed by NR50 bits 7 / 3
+    pub vin: VinInput,
+    /// Lite mode: no channel ticking or sample output (see `lite.rs`)
+    pub samples_off: bool,
 }
 impl Default for Apu {
     fn default() -> Self {
         Apu { power:false, master_vol:0, sq1:Square::default(), sq2:Square::default(),
                wave:WaveChannel::default(), noise:NoiseChannel::default(),
-               sample_buffer: Vec::with_capacity(APU_SAMPLES_PER_FRAME * 2),
-               sample_timer: (CPU_HZ / APU_SAMPLE_RATE as u64) as u32,
-               fs_counter: 0, wave_len: 256, noise_len: 64, fs_div: 0, nr51: 0xFF }
+               sample_buffer: Vec::with_capacity(APU_SAMPLES_PER_FRAME * 4), triggers: 0,
+               blip: BlipBuffer::new(CPU_HZ, APU_SAMPLE_RATE), levels: [0; 4],
+               fs_counter: 0, wave_len: 256, noise_len: 64, nr51: 0xFF, vin: VinInput::default(),
+               samples_off: false }
     }
 }
 impl Apu {
     pub fn step(&mut self, cycles: u8) {
-        for _ in 0..cycles {
-            self.sq1.tick(); self.sq2.tick(); self.wave.tick(); self.noise.tick();
-            if self.sample_timer == 0 {
-                self.sample_timer = (CPU_HZ / APU_SAMPLE_RATE as u64) as u32;
-                let mix = (self.sq1.sample() + self.sq2.sample() + self.wave.sample() + self.noise.sample()) / 4;
-                if self.sample_buffer.len() < APU_SAMPLES_PER_FRAME * 2 {
-                    self.sample_buffer.push(mix); self.sample_buffer.push(mix);
+        if self.samples_off { return; }
+        let cycles = cycles as u32;
+        let Apu { sq1, sq2, wave, noise, blip, levels, .. } = self;
+        // Level changes since the last step (writes, envelopes, length) land at its start
+        let now = [sq1.sample(), sq2.sample(), wave.sample(), noise.sample()];
+        for (level, now) in levels.iter_mut().zip(now) { mix_level(blip, level, 0, now); }
+        sq1.run(cycles, |t, s| mix_level(blip, &mut levels[0], t, s));
+        sq2.run(cycles, |t, s| mix_level(blip, &mut levels[1], t, s));
+        wave.run(cycles, |t, s| mix_level(blip, &mut levels[2], t, s));
+        noise.run(cycles, |t, s| mix_level(blip, &mut levels[3], t, s));
+        blip.end_frame(cycles as u64);
+        let mut mono = [0i16; 32];
+        loop {
+            let n = self.blip.read_samples(&mut mono);
+            if n == 0 { break; }
+            for &mix in &mono[..n] {
+                let (left, right) = vin_mix(mix, self.vin.next_sample(), self.master_vol);
+                if self.sample_buffer.len() < self.sample_cap() {
+                    self.sample_buffer.push(left); self.sample_buffer.push(right);
                 }
-            } else { self.sample_timer -= 1; }
+            }
+        }
+    }
+    /// Output rate in Hz (default `APU_SAMPLE_RATE`)
+    pub fn sample_rate(&self) -> u32 { self.blip.sample_rate() }
+    /// Resample output to `hz`; pending output is dropped. VIN input is
+    /// consumed at the same rate.
+    pub fn set_sample_rate(&mut self, hz: u32) {
+        self.blip.set_sample_rate(hz);
+        self.levels = [0; 4];
+        self.sample_buffer.clear();
+    }
+    /// Sub-sample output position (for state hashing)
+    pub fn blip_position(&self) -> u64 { self.blip.position() }
+    /// Output samples per channel in one frame at the current rate, rounded up
+    pub fn samples_per_frame(&self) -> usize {
+        (self.blip.sample_rate() as u64 * CYCLES_PER_FRAME).div_ceil(CPU_HZ) as usize
+    }
+    /// Two frames of stereo output at the current rate
+    fn sample_cap(&self) -> usize { self.samples_per_frame() * 4 }
+    pub fn take_triggers(&mut self) -> u8 { std::mem::take(&mut self.triggers) }
+    /// Value last written to FF00+`r` (the bus reads APU registers as 0xFF)
+    pub fn written_reg(&self, r: u8) -> u8 {
+        match r {
+            0x10=>self.sq1.nr0, 0x11=>self.sq1.nr1, 0x12=>self.sq1.nr2, 0x13=>self.sq1.nr3, 0x14=>self.sq1.nr4,
+            0x16=>self.sq2.nr1, 0x17=>self.sq2.nr2, 0x18=>self.sq2.nr3, 0x19=>self.sq2.nr4,
+            0x1A=>self.wave.nr0, 0x1B=>self.wave.nr1, 0x1C=>self.wave.nr2, 0x1D=>self.wave.nr3, 0x1E=>self.wave.nr4,
+            0x20=>self.noise.nr1, 0x21=>self.noise.nr2, 0x22=>self.noise.nr3, 0x23=>self.noise.nr4,
+            0x24=>self.master_vol, 0x25=>self.nr51, 0x26=>(self.power as u8) << 7,
+            0x30..=0x3F=>self.wave.wave_ram[(r-0x30) as usize],
+            _=>0xFF,
         }
     }
     pub fn drain_samples(&mut self) -> Vec<i16> {
         let out = self.sample_buffer.clone(); self.sample_buffer.clear(); out
     }
+    /// Move pending samples onto the end of `out`; `sample_buffer` keeps its capacity
+    pub fn drain_samples_into(&mut self, out: &mut Vec<i16>) {
+        out.extend_from_slice(&self.sample_buffer); self.sample_buffer.clear();
+    }
+    /// Drop pending samples without copying them
+    pub fn clear_samples(&mut self) { self.sample_buffer.clear(); }
     pub fn write_reg(&mut self, r: u8, v: u8) {
         match r {
             0x10=>self.sq1.nr0=v, 0x11=>self.sq1.nr1=v, 0x12=>self.sq1.nr2=v,
-            0x13=>self.sq1.nr3=v, 0x14=>{ self.sq1.nr4=v; if v&0x80!=0 {self.sq1.trigger();} }
+            0x13=>self.sq1.nr3=v, 0x14=>{ self.sq1.nr4=v; if v&0x80!=0 {self.sq1.trigger(); self.triggers|=TRIG_SQ1;} }
             0x16=>self.sq2.nr1=v, 0x17=>self.sq2.nr2=v, 0x18=>self.sq2.nr3=v,
-            0x19=>{ self.sq2.nr4=v; if v&0x80!=0 {self.sq2.trigger();} }
+            0x19=>{ self.sq2.nr4=v; if v&0x80!=0 {self.sq2.trigger(); self.triggers|=TRIG_SQ2;} }
             0x1A=>self.wave.nr0=v, 0x1B=>self.wave.nr1=v, 0x1C=>self.wave.nr2=v,
-            0x1D=>self.wave.nr3=v, 0x1E=>{ self.wave.nr4=v; if v&0x80!=0 {self.wave.enabled=true;} }
+            0x1D=>self.wave.nr3=v, 0x1E=>{ self.wave.nr4=v; if v&0x80!=0 {self.wave.enabled=true; self.triggers|=TRIG_WAVE;} }
             0x20=>self.noise.nr1=v, 0x21=>self.noise.nr2=v, 0x22=>self.noise.nr3=v,
-            0x23=>{ self.noise.nr4=v; if v&0x80!=0 {self.noise.trigger();} }
+            0x23=>{ self.noise.nr4=v; if v&0x80!=0 {self.noise.trigger(); self.triggers|=TRIG_NOISE;} }
             0x24=>self.master_vol=v, 0x25=>self.nr51=v, 0x26=>self.power=v&0x80!=0,
             0x30..=0x3F=>self.wave.wave_ram[(r-0x30) as usize]=v,
             _=>{}
         }
     }
-    pub fn step_with_fs(&mut self, cycles: u8) {
-        self.step(cycles);
-        self.fs_div += cycles as u32;
-        while self.fs_div >= 8192 {
-            self.fs_div -= 8192;
-            self.frame_seq_step();
-        }
+    /// Frame sequencer input: one step per falling edge of DIV bit 4 (bit 5
+    /// in double speed), 512 Hz either way. `old`/`new` are the timer's
+    /// 16-bit divider before and after a step or a DIV write.
+    pub fn clock_from_div(&mut self, old: u16, new: u16, double_speed: bool) {
+        let bit = if double_speed { 13 } else { 12 };
+        if old >> bit & 1 == 1 && new >> bit & 1 == 0 { self.frame_seq_step(); }
     }
     pub fn frame_seq_step(&mut self) {
         self.fs_counter = (self.fs_counter + 1) & 7;
@@ -925,238 +1507,24 @@ impl Apu {
         }
     }
     fn clock_env(&mut self) {
-        fn tick(v:&mut u8,t:&mut u8,nr2:u8){if *t>0{*t-=1;}if *t==0{let p=nr2&7;*t=if p!=0{p}else{8};if nr2&8!=0{if *v<15{*v+=1;}}else{if *v>0{*v-=1;}}}}
+        fn tick(v:&mut u8,t:&mut u8,nr2:u8){if *t>0{*t-=1;}
+            if *t==0{let p=nr2&7;*t=if p!=0{p}else{8};if nr2&8!=0{if *v<15{*v+=1;}}else{if *v>0{*v-=1;}}}}
         tick(&mut self.sq1.volume,&mut self.sq1.env_timer,self.sq1.nr2);
         tick(&mut self.sq2.volume,&mut self.sq2.env_timer,self.sq2.nr2);
         tick(&mut self.noise.volume,&mut self.noise.env_timer,self.noise.nr2);
     }
 }
 
-// ── Timer ─────────────────────────────────────────────────────────────────────
-
-// ── Timer ─────────────────────────────────────────────────────────────────────
-#[derive(Debug, Clone, Default)]
-pub struct Timer {
-    pub div: u8, pub tima: u8, pub tma: u8, pub tac: u8,
-    div_counter: u16, tima_counter: u32, pub overflow_irq: bool,
-}
-impl Timer {
-    pub fn step(&mut self, cycles: u8) {
-        self.overflow_irq = false;
-        self.div_counter = self.div_counter.wrapping_add(cycles as u16);
-        self.div = (self.div_counter >> 8) as u8;
-        if self.tac & 0x04 == 0 { return; }
-        let period: u32 = match self.tac & 0x03 { 0=>1024, 1=>16, 2=>64, 3=>256, _=>1024 };
-        self.tima_counter += cycles as u32;
-        while self.tima_counter >= period {
-            self.tima_counter -= period;
-            let (t, ov) = self.tima.overflowing_add(1);
-            if ov { self.tima = self.tma; self.overflow_irq = true; } else { self.tima = t; }
-        }
-    }
-    pub fn write(&mut self, r: u8, v: u8) {
-        match r { 0x04=>{self.div_counter=0;self.div=0;} 0x05=>self.tima=v, 0x06=>self.tma=v, 0x07=>self.tac=v&0x07, _=>{} }
-    }
-    pub fn read(&self, r: u8) -> u8 {
-        match r { 0x04=>self.div, 0x05=>self.tima, 0x06=>self.tma, 0x07=>self.tac, _=>0xFF }
+/// Feed one channel's new output level into the mix (channels are mixed at 1/4)
+fn mix_level(blip: &mut BlipBuffer, level: &mut i16, t: u32, now: i16) {
+    if now != *level {
+        blip.add_delta(t as u64, (now as i32 - *level as i32) / 4);
+        *level = now;
     }
 }
 
-// ── Bus ───────────────────────────────────────────────────────────────────────
-
-// ── Bus (Phase 4) ─────────────────────────────────────────────────────────────
-pub struct Bus {
-    pub rom: Vec<u8>, pub ram: Vec<u8>,
-    pub vram: [[u8; 0x2000]; 2], pub vram_bank: u8,
-    pub wram: [[u8; 0x1000]; 8], pub wram_bank: u8,
-    pub hram: [u8; 0x7F], pub oam: [u8; 0xA0],
-    pub io: [u8; 0x80], pub ie: u8, pub if_reg: u8,
-    pub mbc: Mbc, pub ppu: Ppu, pub apu: Apu, pub timer: Timer,
-    pub joypad: u8,
-    pub double_speed: bool, pub speed_switch_armed: bool,
-    // CGB color palettes: [palette_idx][color_idx*2 | byte_offset] = 64 bytes each
-    pub bg_cpal:  [u8; 64], pub bg_cps:  u8,  // BCPS index register
-    pub obj_cpal: [u8; 64], pub obj_cps: u8,  // OCPS index register
-}
-impl Bus {
-    pub fn new(cart: Cartridge) -> Self {
-        let mbc = Mbc::new(cart.kind.clone());
-        Bus { rom: cart.rom, ram: cart.ram, vram: [[0u8;0x2000]; 2], vram_bank: 0,
-              wram: [[0u8;0x1000]; 8], wram_bank: 1,
-              hram: [0u8;0x7F], oam: [0u8;0xA0], io: [0u8;0x80], ie: 0, if_reg: 0,
-              mbc, ppu: Ppu::new(), apu: Apu::default(), timer: Timer::default(), joypad: 0xFF,
-              double_speed: false, speed_switch_armed: false,
-              bg_cpal: [0xFFu8; 64], bg_cps: 0,
-              obj_cpal: [0u8; 64],   obj_cps: 0 }
-    }
-    pub fn read(&self, addr: u16) -> u8 {
-        match addr {
-            0x0000..=0x7FFF => { let m=self.mbc.rom_addr(addr); self.rom.get(m).copied().unwrap_or(0xFF) }
-            0x8000..=0x9FFF => self.vram[self.vram_bank as usize][(addr-0x8000) as usize],
-            0xA000..=0xBFFF => {
-                if self.mbc.ram_enable {
-                    let off = self.mbc.ram_bank as usize * 0x2000 + (addr-0xA000) as usize;
-                    self.ram.get(off).copied().unwrap_or(0xFF)
-                } else { 0xFF }
-            }
-            0xC000..=0xCFFF => self.wram[0][(addr-0xC000) as usize],
-            0xD000..=0xDFFF => self.wram[self.wram_bank as usize][(addr-0xD000) as usize],
-            0xE000..=0xEFFF => self.wram[0][(addr-0xE000) as usize],
-            0xF000..=0xFDFF => self.wram[self.wram_bank as usize][(addr-0xF000) as usize],
-            0xFE00..=0xFE9F => self.oam[(addr-0xFE00) as usize],
-            0xFF00 => self.joypad,
-            0xFF01..=0xFF03 => self.io[(addr-0xFF00) as usize],
-            0xFF04..=0xFF07 => self.timer.read((addr-0xFF00) as u8),
-            0xFF0F => self.if_reg,
-            0xFF10..=0xFF3F => 0xFF,
-            0xFF40..=0xFF4B => self.ppu.read_reg((addr-0xFF00) as u8),
-            0xFF46 => 0xFF,
-            0xFF80..=0xFFFE => self.hram[(addr-0xFF80) as usize],
-            0xFFFF => self.ie,
-            _ => 0xFF,
-        }
-    }
-    pub fn write(&mut self, addr: u16, val: u8) {
-        if self.mbc.write(addr, val) { return; }
-        match addr {
-            0x8000..=0x9FFF => self.vram[self.vram_bank as usize][(addr-0x8000) as usize] = val,
-            0xA000..=0xBFFF => {
-                if self.mbc.ram_enable {
-                    if matches!(self.mbc.kind, CartridgeKind::Mbc3) && self.mbc.rtc_sel != 0xFF {
-                        self.mbc.rtc_reg[self.mbc.rtc_sel as usize] = val;
-                    } else {
-                        let off = self.mbc.ram_bank as usize * 0x2000 + (addr-0xA000) as usize;
-                        if off < self.ram.len() { self.ram[off] = val; }
-                    }
-                }
-            }
-            0xC000..=0xCFFF => self.wram[0][(addr-0xC000) as usize] = val,
-            0xD000..=0xDFFF => self.wram[self.wram_bank as usize][(addr-0xD000) as usize] = val,
-            0xFE00..=0xFE9F => self.oam[(addr-0xFE00) as usize] = val,
-            0xFF00 => self.joypad = val,
-            0xFF04..=0xFF07 => self.timer.write((addr-0xFF00) as u8, val),
-            0xFF0F => self.if_reg = val,
-            0xFF10..=0xFF3F => self.apu.write_reg((addr-0xFF00) as u8, val),
-            0xFF40..=0xFF4B => self.ppu.write_reg((addr-0xFF00) as u8, val),
-            0xFF46 => { let src=(val as u16)<<8; for i in 0..0xA0u16 { let b=self.read(src+i); self.oam[i as usize]=b; } }
-            0xFF80..=0xFFFE => self.hram[(addr-0xFF80) as usize] = val,
-            0xFFFF => self.ie = val,
-            _ => {}
-        }
-    }
-
-    /// Decode a CGB palette entry (2-byte little-endian RGB555) to (r8,g8,b8)
-    pub fn cgb_color(cpal: &[u8; 64], palette: u8, color: u8) -> (u8, u8, u8) {
-        let idx = (palette as usize) * 8 + (color as usize) * 2;
-        let lo = cpal[idx] as u16;
-        let hi = cpal[idx + 1] as u16;
-        let rgb = lo | (hi << 8);
-        let r = ((rgb & 0x001F) as u8) << 3;
-        let g = (((rgb >> 5) & 0x001F) as u8) << 3;
-        let b = (((rgb >> 10) & 0x001F) as u8) << 3;
-        (r, g, b)
-    }
-
-    /// Get full BG color palette as RGB888 array [palette][color] = (r,g,b)
-    pub fn bg_palette_rgb(&self) -> [[(u8,u8,u8); 4]; 8] {
-        let mut out = [[(0u8,0u8,0u8); 4]; 8];
-        for p in 0..8 {
-            for c in 0..4 {
-                out[p][c] = Self::cgb_color(&self.bg_cpal, p as u8, c as u8);
-            }
-        }
-        out
-    }
-
-    /// Get full OBJ color palette as RGB888 array [palette][color] = (r,g,b)
-    pub fn obj_palette_rgb(&self) -> [[(u8,u8,u8); 4]; 8] {
-        let mut out = [[(0u8,0u8,0u8); 4]; 8];
-        for p in 0..8 {
-            for c in 0..4 {
-                out[p][c] = Self::cgb_color(&self.obj_cpal, p as u8, c as u8);
-            }
-        }
-        out
-    }
-
-    pub fn step_subsystems(&mut self, cycles: u8) {
-        // In double-speed mode CPU runs 2x; PPU/APU/Timer stay at 1x speed
-        let sub_cycles = if self.double_speed { (cycles + 1) / 2 } else { cycles };
-        let vram = self.vram[self.vram_bank as usize]; let oam = self.oam;
-        self.ppu.step(sub_cycles, &vram, &oam);
-        if self.ppu.vblank_irq { self.if_reg |= 0x01; }
-        if self.ppu.stat_irq   { self.if_reg |= 0x02; }
-        self.timer.step(cycles);
-        if self.timer.overflow_irq { self.if_reg |= 0x04; }
-    }
-}
-
-
-// ── ReplayCapture ──────────────────────────────────────────────────────────────
-/// Captures live replay frames from a running GbCore.
-/// Feeds both live streaming (state_json) and training record generation.
-#[derive(Debug, Default, Clone)]
-pub struct ReplayFrame {
-    pub frame_idx: u64,
-    pub t_cycles:  u64,
-    pub pc:        u16,
-    pub ly:        u8,
-    pub snapshot:  String, // mrom.snap.v1 JSON
-}
-
-#[derive(Debug, Default)]
-pub struct ReplayCapture {
-    pub frames:      Vec<ReplayFrame>,
-    pub max_frames:  usize,
-    pub rom_title:   String,
-}
-
-impl ReplayCapture {
-    pub fn new(max_frames: usize, rom_title: &str) -> Self {
-        ReplayCapture { frames: Vec::with_capacity(max_frames), max_frames, rom_title: rom_title.to_string() }
-    }
-
-    /// Record one frame from a live GbCore. Call after run_frame().
-    pub fn capture(&mut self, core: &GbCore) {
-        if self.frames.len() >= self.max_frames { return; }
-        self.frames.push(ReplayFrame {
-            frame_idx: core.clock.frame_count(),
-            t_cycles:  core.clock.t_cycles,
-            pc:        core.regs.pc,
-            ly:        core.bus.ppu.ly,
-            snapshot:  core.state_json(),
-        });
-    }
-
-    /// Export all captured frames as a replay manifest JSON (mrom.replay.v1)
-    pub fn to_json(&self) -> String {
-        let frames: Vec<String> = self.frames.iter().map(|f|
-            format!("{{"fi":{},"tc":{},"pc":{},"snap":{}}}", 
-                    f.frame_idx, f.t_cycles, f.pc, f.snapshot)
-        ).collect();
-        format!(
-            "{{"version":"mrom.replay.v1","rom":"{}","frame_count":{},"frames":[{}]}}",
-            self.rom_title, self.frames.len(), frames.join(",")
-        )
-    }
-
-    /// Write replay manifest to file
-    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
-        std::fs::write(path, self.to_json())
-    }
-}
-
-// ── Clock ─────────────────────────────────────────────────────────────────────
-#[derive(Debug, Default, Clone)]
-pub struct Clock { pub t_cycles: u64 }
-impl Clock {
-    pub fn tick(&mut self, c: u8) { self.t_cycles = self.t_cycles.wrapping_add(c as u64); }
-    pub fn frame_count(&self) -> u64 { self.t_cycles / CYCLES_PER_FRAME }
-    pub fn current_scanline(&self) -> u32 { ((self.t_cycles % CYCLES_PER_FRAME) / DOTS_PER_LINE as u64) as u32 }
-}
-
 // ── Training data ─────────────────────────────────────────────────────────────
-fn fnv1a(data: &[u8]) -> u32 {
+pub fn fnv1a(data: &[u8]) -> u32 {
     let mut h: u32 = 0x811c9dc5;
     for &b in data { h ^= b as u32; h = h.wrapping_mul(0x01000193); }
     h
@@ -1172,64 +1540,29 @@ pub struct FrameRecord {
     pub vblank_count: u64,
     pub framebuffer: Vec<u8>,
     pub sq1_on: bool, pub sq2_on: bool, pub wave_on: bool, pub noise_on: bool,
+    pub audio: AudioFeatures,
+    pub phash: Option<u64>,
     pub rom_bank: u16, pub ram_bank: u8,
     pub wram_hash: u32, pub vram_hash: u32, pub oam_hash: u32,
     pub rom_title: String,
 }
 
-// ── CPU decode ────────────────────────────────────────────────────────────────
-fn decode(op: u8, bus: &Bus, pc: u16) -> (u8, i16) {
-    match op {
-        0x00=>(4,1), 0x01|0x11|0x21|0x31=>(12,3), 0x02|0x12|0x0A|0x1A=>(8,1),
-        0x03|0x13|0x23|0x33|0x0B|0x1B|0x2B|0x3B=>(8,1),
-        0x04|0x05|0x0C|0x0D|0x14|0x15|0x1C|0x1D|0x24|0x25|0x2C|0x2D|0x3C|0x3D=>(4,1),
-        0x06|0x0E|0x16|0x1E|0x26|0x2E|0x3E=>(8,2), 0x07|0x0F|0x17|0x1F=>(4,1),
-        0x08=>(20,3), 0x09|0x19|0x29|0x39=>(8,1), 0x18=>(12,2),
-        0x20|0x28|0x30|0x38=>(8,2), 0x22|0x2A|0x32|0x3A=>(8,1),
-        0x34|0x35=>(12,1), 0x36=>(12,2), 0x27|0x2F|0x37|0x3F=>(4,1),
-        0x46|0x4E|0x56|0x5E|0x66|0x6E|0x7E=>(8,1), 0x70|0x71|0x72|0x73|0x74|0x75|0x77=>(8,1),
-        0x76=>(4,1), 0x40..=0x7F=>(4,1), 0x80..=0xBF=>(4,1),
-        0xC6|0xCE|0xD6|0xDE|0xE6|0xEE|0xF6|0xFE=>(8,2),
-        0xC0|0xC8|0xD0|0xD8=>(8,1), 0xC1|0xD1|0xE1|0xF1=>(12,1),
-        0xC2|0xCA|0xD2|0xDA=>(12,3), 0xC3=>(16,3),
-        0xC4|0xCC|0xD4|0xDC=>(12,3), 0xC5|0xD5|0xE5|0xF5=>(16,1),
-        0xC7|0xCF|0xD7|0xDF|0xE7|0xEF|0xF7|0xFF=>(16,1),
-        0xC9=>(16,1), 0xCB=>{ let _=bus.read(pc.wrapping_add(1)); (8,2) }
-        0xCD=>(24,3), 0xD9=>(16,1), 0xE0|0xF0=>(12,2), 0xE2|0xF2=>(8,1),
-        0xE8=>(16,2), 0xE9=>(4,1), 0xEA|0xFA=>(16,3), 0xF8=>(12,2),
-        0xF9=>(8,1), 0xF3|0xFB=>(4,1), _=>(4,1),
-    }
-}
-
-
 // ── SM83 full instruction set (Phase 5) ──────────────────────────────────────
 // Called from GbCore::step() in the match op { ... } block.
-// Returns cycle count (u8). PC has already been advanced by delta from decode().
+// Returns cycle count (u8). PC has already been advanced past the instruction
+// (`OpInfo::len`); `default_cyc` is `OpInfo::cycles` (see `opcodes.rs`).
 
-fn exec_op(op: u8, regs: &mut Registers, bus: &mut Bus, default_cyc: u8) -> u8 {
-    // Helper: read immediate byte after opcode
-    macro_rules! imm8 {
-        () => {{ let v = bus.read(regs.pc.wrapping_sub(1)); v }}
-    }
+fn exec_op(op: u8, regs: &mut Registers, bus: &mut Bus, default_cyc: u8, imm8_val: u8, imm16_val: u16) -> u8 {
     // We need the PC *before* decode advanced it. Caller passes pre-exec pc.
     // Instead, use the decode-table delta. Easier: regs.pc was already advanced,
     // so imm8 is at pc-1 (1-byte imm after 1-byte opcode), imm16 lo at pc-2, hi at pc-1.
 
     let cyc = default_cyc;
 
-    // Read immediates from pre-instruction positions
-    // After decode() advanced pc, pc points PAST the instruction.
+    // Immediates come from the caller (`step_instruction`): read from the
+    // bytes before the advanced PC, or taken from the block cache.
     // For a 2-byte instr (opcode + imm8): imm8 = bus.read(pc - 1)
     // For a 3-byte instr (opcode + imm16): lo = bus.read(pc - 2), hi = bus.read(pc - 1)
-    let pre_pc = regs.pc;   // PC *after* delta advance (next instr)
-
-    // Macros using pre-calc offsets
-    let imm8_val: u8 = bus.read(pre_pc.wrapping_sub(1));
-    let imm16_val: u16 = {
-        let lo = bus.read(pre_pc.wrapping_sub(2)) as u16;
-        let hi = bus.read(pre_pc.wrapping_sub(1)) as u16;
-        (hi << 8) | lo
-    };
 
     // ── Helper closures ──────────────────────────────────────────────────────
     // ADD A, r
@@ -1377,10 +1710,10 @@ fn exec_op(op: u8, regs: &mut Registers, bus: &mut Bus, default_cyc: u8) -> u8 {
 
         // ── JR cc, e8 ──────────────────────────────────────────────────────
         // decode gave cyc=8 (not-taken); taken = 12 (handled by returning 12)
-        0x20 => { if !regs.flag_z() { let e=imm8_val as i8 as i16; regs.pc=regs.pc.wrapping_add_signed(e); return 12; } }
-        0x28 => { if  regs.flag_z() { let e=imm8_val as i8 as i16; regs.pc=regs.pc.wrapping_add_signed(e); return 12; } }
-        0x30 => { if !regs.flag_c() { let e=imm8_val as i8 as i16; regs.pc=regs.pc.wrapping_add_signed(e); return 12; } }
-        0x38 => { if  regs.flag_c() { let e=imm8_val as i8 as i16; regs.pc=regs.pc.wrapping_add_signed(e); return 12; } }
+        0x20 if !regs.flag_z() => { let e=imm8_val as i8 as i16; regs.pc=regs.pc.wrapping_add_signed(e); return 12; }
+        0x28 if  regs.flag_z() => { let e=imm8_val as i8 as i16; regs.pc=regs.pc.wrapping_add_signed(e); return 12; }
+        0x30 if !regs.flag_c() => { let e=imm8_val as i8 as i16; regs.pc=regs.pc.wrapping_add_signed(e); return 12; }
+        0x38 if  regs.flag_c() => { let e=imm8_val as i8 as i16; regs.pc=regs.pc.wrapping_add_signed(e); return 12; }
 
         // ── LDI / LDD (HL+/-), A and A, (HL+/-) ──────────────────────────
         0x22 => { bus.write(regs.hl(), regs.a); regs.set_hl(regs.hl().wrapping_add(1)); }
@@ -1532,10 +1865,10 @@ fn exec_op(op: u8, regs: &mut Registers, bus: &mut Bus, default_cyc: u8) -> u8 {
         0xFE => cp_a(regs, imm8_val),
 
         // ── RET cc ─────────────────────────────────────────────────────────
-        0xC0 => { if !regs.flag_z() { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.pc=(hi<<8)|lo; return 20; } }
-        0xC8 => { if  regs.flag_z() { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.pc=(hi<<8)|lo; return 20; } }
-        0xD0 => { if !regs.flag_c() { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.pc=(hi<<8)|lo; return 20; } }
-        0xD8 => { if  regs.flag_c() { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.pc=(hi<<8)|lo; return 20; } }
+        0xC0 if !regs.flag_z() => { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.pc=(hi<<8)|lo; return 20; }
+        0xC8 if  regs.flag_z() => { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.pc=(hi<<8)|lo; return 20; }
+        0xD0 if !regs.flag_c() => { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.pc=(hi<<8)|lo; return 20; }
+        0xD8 if  regs.flag_c() => { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.pc=(hi<<8)|lo; return 20; }
 
         // ── POP r16 ────────────────────────────────────────────────────────
         0xC1 => { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.set_bc((hi<<8)|lo); }
@@ -1544,10 +1877,10 @@ fn exec_op(op: u8, regs: &mut Registers, bus: &mut Bus, default_cyc: u8) -> u8 {
         0xF1 => { let lo=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); let hi=bus.read(regs.sp) as u16; regs.sp=regs.sp.wrapping_add(1); regs.set_af((hi<<8)|(lo&0xF0)); }
 
         // ── JP cc, a16 ─────────────────────────────────────────────────────
-        0xC2 => { if !regs.flag_z() { regs.pc=imm16_val; return 16; } }
-        0xCA => { if  regs.flag_z() { regs.pc=imm16_val; return 16; } }
-        0xD2 => { if !regs.flag_c() { regs.pc=imm16_val; return 16; } }
-        0xDA => { if  regs.flag_c() { regs.pc=imm16_val; return 16; } }
+        0xC2 if !regs.flag_z() => { regs.pc=imm16_val; return 16; }
+        0xCA if  regs.flag_z() => { regs.pc=imm16_val; return 16; }
+        0xD2 if !regs.flag_c() => { regs.pc=imm16_val; return 16; }
+        0xDA if  regs.flag_c() => { regs.pc=imm16_val; return 16; }
 
         // ── JP a16 / JP HL already handled in caller ───────────────────────
         0xC3 | 0xE9 => {} // handled by step()
@@ -1560,10 +1893,10 @@ fn exec_op(op: u8, regs: &mut Registers, bus: &mut Bus, default_cyc: u8) -> u8 {
         0xF5 => { let v=regs.af(); regs.sp=regs.sp.wrapping_sub(1); bus.write(regs.sp,(v>>8)as u8); regs.sp=regs.sp.wrapping_sub(1); bus.write(regs.sp,(v&0xF0) as u8); }
 
         // ── CALL cc, a16 ───────────────────────────────────────────────────
-        0xC4 => { if !regs.flag_z() { push_call(regs,bus,imm16_val); return 24; } }
-        0xCC => { if  regs.flag_z() { push_call(regs,bus,imm16_val); return 24; } }
-        0xD4 => { if !regs.flag_c() { push_call(regs,bus,imm16_val); return 24; } }
-        0xDC => { if  regs.flag_c() { push_call(regs,bus,imm16_val); return 24; } }
+        0xC4 if !regs.flag_z() => { push_call(regs,bus,imm16_val); return 24; }
+        0xCC if  regs.flag_z() => { push_call(regs,bus,imm16_val); return 24; }
+        0xD4 if !regs.flag_c() => { push_call(regs,bus,imm16_val); return 24; }
+        0xDC if  regs.flag_c() => { push_call(regs,bus,imm16_val); return 24; }
 
         // ── RST nn ─────────────────────────────────────────────────────────
         0xC7 => push_call(regs,bus,0x0000), 0xCF => push_call(regs,bus,0x0008),
@@ -1631,73 +1964,591 @@ fn push_call(regs: &mut Registers, bus: &mut Bus, target: u16) {
 pub struct GbCore {
     pub regs: Registers, pub bus: Bus, pub clock: Clock,
     pub halted: bool, pub ime: bool, pub ime_pending: bool,
+    /// In STOP mode: CPU, LCD and timers halted until a joypad line goes low
+    pub stopped: bool,
+    /// Hung on an illegal opcode: only the PPU, APU and timers still run, and
+    /// interrupts are not serviced until a state load or power cycle
+    pub locked: bool,
+    /// The lock not yet reported by `step` (pc, opcode)
+    lock_hit: Option<(u16, u8)>,
+    /// HALT hit the halt bug: the next opcode is fetched without advancing PC
+    pub halt_bug: bool,
+    /// Options the core was built with; recorded in savestates
+    pub config: CoreConfig,
+    /// Last executed instructions, recorded when Some (see `trace.rs`)
+    pub trace: Option<TraceRing>,
+    /// Keyframes for `reverse_step` / `reverse_continue`, taken when Some (see `time_travel.rs`)
+    pub time_travel: Option<Box<TimeTravel>>,
+    /// Executed-code bitmap, marked when Some (see `exec_coverage.rs`)
+    pub exec_coverage: Option<Box<ExecCoverage>>,
+    /// Per-opcode / per-address profile, recorded when Some (see `profile.rs`)
+    #[cfg(feature = "profile")]
+    pub profiler: Option<Box<Profiler>>,
+    /// Checked before every instruction; a hit stops `step` / `run_frame`
+    pub breakpoints: Breakpoints,
+    /// Stop `step` / `run_frame` with `CoreError::SoftwareBreak` after each
+    /// `LD B,B` (test ROMs use it to say they are done, see `test_rom.rs`)
+    pub ld_b_b_break: bool,
+    /// The `LD B,B` not yet reported by `step`
+    soft_break_hit: Option<u16>,
+    /// While halted, `step` jumps to the next timer / PPU / APU event (up to
+    /// 252 T-cycles) instead of running 4 at a time. Same results, far fewer
+    /// steps for ROMs that idle in HALT; `debug_step` reports the longer
+    /// `Halted` spans.
+    pub fast_halt: bool,
+    /// End of the current `run_frame` / `run_cycles`, which a halted jump
+    /// does not cross
+    run_target: u64,
+    /// CALL / RST / interrupt frames, see `call_stack()`
+    pub shadow_stack: ShadowStack,
+    /// Last `measure_input_latency` result, reported in `diagnostics_json`
+    pub input_latency: Option<InputLatency>,
+    /// Scroll / window / sprite motion, sampled at each VBlank when Some (see `motion.rs`)
+    pub motion: Option<MotionTracker>,
+    /// DMG shade colours for BG and each OBJ palette in `framebuffer_rgb`
+    /// (see `palette_pack.rs`)
+    pub dmg_colors: DmgColors,
+    /// Host time source for RTC, replay timestamps and pacing (RealClock by default)
+    pub host_clock: Box<dyn HostClock>,
+    /// Battery save file kept in step with cartridge RAM, when Some (see `sram_autosave.rs`)
+    pub autosave: Option<Box<SramAutosave>>,
+    /// Annotation layer for `framebuffer_rgb_annotated`, when Some (see `overlay.rs`)
+    pub overlay: Option<Box<Overlay>>,
+    rtc_synced_us: u64,
+    at_frame_boundary: bool,
+    /// `debug_step` owes a FrameCompleted event
+    debug_frame_pending: bool,
+    vblank_save_requested: bool,
+    vblank_state: Option<Vec<u8>>,
+    interrupt: Arc<AtomicBool>,
+    stimulus_provider: Option<Box<dyn StimulusProvider>>,
+    stimulus_due: u64,
+    vin_source: Option<Box<dyn VinSource>>,
+    link: Option<Box<dyn LinkTransport>>,
+    /// Next T-cycle an armed slave transfer asks the link again
+    link_poll_due: u64,
 }
 impl GbCore {
-    pub fn new(cart: Cartridge) -> Self {
+    pub fn new(cart: Cartridge) -> Self { Self::with_config(cart, CoreConfig::default()) }
+    pub fn with_config(cart: Cartridge, config: CoreConfig) -> Self {
+        let bus = Bus::with_config(cart, &config);
         let mut regs = Registers::default();
-        regs.set_af(0x01B0); regs.set_bc(0x0013); regs.set_de(0x00D8); regs.set_hl(0x014D);
-        regs.sp = 0xFFFE; regs.pc = 0x0100;
-        GbCore { regs, bus: Bus::new(cart), clock: Clock::default(), halted: false, ime: false, ime_pending: false }
+        apply_post_boot_regs(&mut regs, config.model, &bus.rom);
+        let host_clock: Box<dyn HostClock> = Box::new(RealClock::new());
+        let rtc_synced_us = host_clock.now_us();
+        GbCore { regs, bus, clock: Clock::default(), halted: false, ime: false, ime_pending: false, stopped: false, locked: false, lock_hit: None,
+                 halt_bug: false, config, trace: None, time_travel: None, exec_coverage: None,
+                 #[cfg(feature = "profile")] profiler: None,
+                 breakpoints: Breakpoints::default(), ld_b_b_break: false, soft_break_hit: None, fast_halt: false, run_target: 0, shadow_stack: ShadowStack::default(), input_latency: None, motion: None, dmg_colors: DmgColors::uniform(DMG_GREYSCALE), host_clock, autosave: None, overlay: None, rtc_synced_us,
+                 at_frame_boundary: false, debug_frame_pending: false, vblank_save_requested: false, vblank_state: None,
+                 interrupt: Arc::new(AtomicBool::new(false)),
+                 stimulus_provider: None, stimulus_due: 0, vin_source: None,
+                 link: None, link_poll_due: 0 }
+    }
+    /// Set fast halt, the block cache, the OAM DMA bus lock, the open-bus
+    /// policy and the pixel FIFO from one profile (see `accuracy.rs`)
+    pub fn set_accuracy(&mut self, profile: AccuracyProfile) {
+        self.fast_halt = profile.fast_halt();
+        if profile.block_cache() != self.bus.block_cache.is_some() {
+            self.bus.block_cache = profile.block_cache().then(Box::default);
+        }
+        self.bus.dma_bus_lock = profile.dma_bus_lock();
+        self.bus.open_bus = profile.open_bus(self.config.model);
+        if profile.pixel_fifo() != self.bus.ppu.fifo.is_some() {
+            self.bus.ppu.fifo = profile.pixel_fifo().then(Box::default);
+        }
+    }
+    /// Replace the host time source (e.g. FixedClock for deterministic runs).
+    /// RTC elapsed-time tracking restarts from the new clock's current time.
+    pub fn set_host_clock(&mut self, clock: Box<dyn HostClock>) {
+        self.rtc_synced_us = clock.now_us();
+        self.host_clock = clock;
+    }
+    /// Soft reset: the loaded game restarts from the post-boot state, as after
+    /// a power cycle, keeping cartridge RAM and the RTC. Host attachments
+    /// (clock, stimulus, VIN, link, breakpoints, watchpoints, recorders,
+    /// palette, interrupt handle) stay, and `clock` keeps counting so frame
+    /// numbers stay monotonic across the reset.
+    pub fn reset(&mut self) {
+        let (rtc_reg, rtc_latch) = (self.bus.mbc.rtc_reg, self.bus.mbc.rtc_latch);
+        let cart = Cartridge {
+            rom: std::mem::take(&mut self.bus.rom), ram: std::mem::take(&mut self.bus.ram),
+            kind: self.bus.mbc.kind.clone(), title: String::new(), is_cgb: false, rom_size_kb: 0, ram_size_kb: 0,
+        };
+        self.power_on(cart);
+        self.bus.mbc.rtc_reg = rtc_reg;
+        self.bus.mbc.rtc_latch = rtc_latch;
+    }
+    /// Hot swap: boot `cart` (with its own `ram`) in place of the loaded game,
+    /// keeping host attachments as `reset` does. Per-ROM recordings (executed
+    /// code, profile, measured input latency, RAM console) start over; the
+    /// hardware model stays `config.model`.
+    pub fn swap_rom(&mut self, cart: Cartridge) {
+        // The autosave file belongs to the outgoing game
+        let _ = self.flush_sram();
+        self.autosave = None;
+        self.power_on(cart);
+        if let Some(cov) = self.exec_coverage.as_mut() { **cov = ExecCoverage::for_bus(&self.bus); }
+        #[cfg(feature = "profile")]
+        if let Some(p) = self.profiler.as_mut() { p.clear(); }
+        self.input_latency = None;
+        self.bus.console = ConsoleCapture::new();
+    }
+    /// Fresh bus and CPU for `cart`, carrying over the host-side state
+    fn power_on(&mut self, cart: Cartridge) {
+        let mut bus = Bus::with_config(cart, &self.config);
+        let old = &mut self.bus;
+        bus.console = std::mem::take(&mut old.console);
+        bus.stimulus = std::mem::take(&mut old.stimulus);
+        bus.coverage = old.coverage.take();
+        bus.watchpoints = std::mem::take(&mut old.watchpoints);
+        bus.io_log = old.io_log.take();
+        bus.ppu_timeline = old.ppu_timeline.take();
+        bus.raster = old.raster.take().map(|_| Box::default());
+        bus.block_cache = old.block_cache.take().map(|_| Box::default());
+        bus.open_bus = old.open_bus;
+        bus.dma_bus_lock = old.dma_bus_lock;
+        bus.ppu.simd = old.ppu.simd;
+        bus.ppu.fifo = old.ppu.fifo.take().map(|_| Box::default());
+        bus.sram_dirty = old.sram_dirty;
+        bus.link_attached = old.link_attached;
+        bus.buttons = old.buttons;
+        self.bus = bus;
+        self.regs = Registers::default();
+        apply_post_boot_regs(&mut self.regs, self.config.model, &self.bus.rom);
+        self.halted = false;
+        self.ime = false;
+        self.ime_pending = false;
+        self.stopped = false;
+        self.locked = false;
+        self.lock_hit = None;
+        self.soft_break_hit = None;
+        self.halt_bug = false;
+        self.shadow_stack.clear();
+        if let Some(tt) = self.time_travel.as_deref_mut() { tt.clear(); }
+        self.at_frame_boundary = false;
+        self.debug_frame_pending = false;
+        self.vblank_save_requested = false;
+        self.vblank_state = None;
+    }
+    /// Advance the cartridge RTC by whole host seconds elapsed since the last sync
+    pub fn sync_rtc(&mut self) {
+        let now = self.host_clock.now_us();
+        let secs = now.saturating_sub(self.rtc_synced_us) / 1_000_000;
+        if secs > 0 {
+            self.bus.mbc.rtc_advance(secs);
+            self.rtc_synced_us += secs * 1_000_000;
+        }
+    }
+    /// Attach (or detach) the source of external sensor input
+    pub fn set_stimulus_provider(&mut self, provider: Option<Box<dyn StimulusProvider>>) {
+        self.stimulus_provider = provider;
+        self.stimulus_due = self.clock.t_cycles;
+    }
+    /// Attach (or detach) a per-frame source of cartridge audio-in (VIN)
+    pub fn set_vin_source(&mut self, source: Option<Box<dyn VinSource>>) {
+        self.vin_source = source;
+    }
+    /// Plug in (or pull out) a serial link cable partner (see `link.rs`)
+    pub fn set_link(&mut self, link: Option<Box<dyn LinkTransport>>) {
+        self.bus.link_attached = link.is_some();
+        self.link = link;
+        self.link_poll_due = self.clock.t_cycles;
+    }
+    /// Queue external audio for VIN directly (mono PCM-16 at APU_SAMPLE_RATE)
+    pub fn push_vin(&mut self, samples: &[i16]) { self.bus.apu.vin.push(samples); }
+    fn update_stimulus(&mut self) {
+        if let Some(p) = self.stimulus_provider.as_mut() {
+            let ctx = StimulusContext {
+                t_cycles: self.clock.t_cycles, frame: self.clock.frame_count(), ir_led: self.bus.io[0x56] & 0x01 != 0,
+            };
+            p.update(&ctx, &mut self.bus.stimulus);
+        }
     }
     pub fn step(&mut self) -> Result<u8, CoreError> {
-        if self.halted {
+        let Some(mut tt) = self.time_travel.take() else { return self.step_once() };
+        tt.before_step(self);
+        let t = self.clock.t_cycles;
+        let result = self.step_once();
+        tt.after_step(self.clock.t_cycles != t);
+        self.time_travel = Some(tt);
+        result
+    }
+    fn step_once(&mut self) -> Result<u8, CoreError> {
+        if let Some(StimulusRate::Cycles(n)) = self.stimulus_provider.as_ref().map(|p| p.rate()) {
+            if self.clock.t_cycles >= self.stimulus_due {
+                self.update_stimulus();
+                self.stimulus_due = self.clock.t_cycles + n as u64;
+            }
+        }
+        let cycles = self.step_instruction()?;
+        if self.link.is_some() && self.bus.io[0x02] & 0x80 != 0 { self.service_link(); }
+        self.at_frame_boundary = self.bus.ppu.vblank_irq;
+        if self.at_frame_boundary {
+            if let Some(m) = self.motion.as_mut() { m.sample(&self.bus, self.clock.frame_count()); }
+        }
+        if self.at_frame_boundary && self.vblank_save_requested {
+            self.vblank_save_requested = false;
+            self.vblank_state = Some(self.save_state_kind(SavePoint::Frame));
+        }
+        if let Some(hit) = self.bus.watchpoints.take_stop() { return Err(CoreError::Watch(hit)); }
+        if let Some((pc, opcode)) = self.lock_hit.take() { return Err(CoreError::CpuLocked { pc, opcode }); }
+        if let Some(pc) = self.soft_break_hit.take() { return Err(CoreError::SoftwareBreak { pc }); }
+        Ok(cycles)
+    }
+    /// Run an armed serial transfer through the link
+    fn service_link(&mut self) {
+        let Some(link) = self.link.as_mut() else { return };
+        let (sb, sc, t) = (self.bus.io[0x01], self.bus.io[0x02], self.clock.t_cycles);
+        let received = if sc & 0x01 != 0 {
+            Some(link.transfer(sb, sc, t))
+        } else if t >= self.link_poll_due {
+            self.link_poll_due = t + 512;
+            link.poll_slave(sb, t)
+        } else {
+            None
+        };
+        if let Some(b) = received {
+            self.bus.console.push_serial(sb);
+            self.bus.io[0x01] = b;
+            self.bus.io[0x02] &= 0x7F;
+            self.bus.if_reg |= 0x08;
+        }
+    }
+    /// One `step`, described as a `DebugEvent` (see `debug.rs`). Breakpoint
+    /// and watchpoint stops are events; other errors are returned as is.
+    pub fn debug_step(&mut self) -> Result<DebugEvent, CoreError> {
+        if std::mem::take(&mut self.debug_frame_pending) {
+            return Ok(DebugEvent::FrameCompleted { frame: self.clock.frame_count() });
+        }
+        let (pc, opcode) = (self.regs.pc, self.bus.peek(self.regs.pc));
+        let halted = self.halted || self.stopped || self.locked;
+        let dispatch = !halted && self.ime && self.bus.if_reg & self.bus.ie & 0x1F != 0;
+        let event = match self.step() {
+            Ok(cycles) if halted => DebugEvent::Halted { cycles },
+            Ok(cycles) if dispatch => {
+                let sp = self.regs.sp;
+                let return_pc = u16::from_le_bytes([self.bus.peek(sp), self.bus.peek(sp.wrapping_add(1))]);
+                DebugEvent::InterruptDispatched { vector: self.regs.pc, return_pc, cycles }
+            }
+            Ok(cycles) => DebugEvent::InstructionExecuted { pc, opcode, cycles },
+            Err(CoreError::Break(hit)) => return Ok(DebugEvent::BreakpointHit(hit)),
+            Err(CoreError::Watch(hit)) => DebugEvent::WatchpointHit(hit),
+            Err(e) => return Err(e),
+        };
+        self.debug_frame_pending = self.at_frame_boundary;
+        Ok(event)
+    }
+    /// Go back one `step` by replaying from the nearest keyframe (see
+    /// `time_travel.rs`); false when `time_travel` is off or its history
+    /// does not reach that far
+    pub fn reverse_step(&mut self) -> bool { time_travel::reverse_step(self) }
+    /// Go back to the last time PC reached an enabled breakpoint with its
+    /// conditions holding, before that instruction ran; with none in the
+    /// history, go back to its start and return None
+    pub fn reverse_continue(&mut self) -> Option<BreakHit> { time_travel::reverse_continue(self) }
+    /// How far a halted `step` may jump: whole M-cycles up to the next
+    /// subsystem event (`Bus::cycles_to_next_event`), per-cycle stimulus
+    /// update or run target, so every interrupt is raised and seen on the
+    /// same cycle as with 4-cycle steps. OAM DMA and a link partner keep
+    /// the 4-cycle pace.
+    fn halt_skip_cycles(&self) -> u8 {
+        if self.bus.dma.active() || self.link.is_some() { return 4; }
+        let t = self.clock.t_cycles;
+        let mut n = self.bus.cycles_to_next_event();
+        if self.run_target > t { n = n.min(self.run_target - t); }
+        if let Some(StimulusRate::Cycles(_)) = self.stimulus_provider.as_ref().map(|p| p.rate()) {
+            n = n.min(self.stimulus_due.saturating_sub(t));
+        }
+        (n.div_ceil(4) * 4).clamp(4, 252) as u8
+    }
+    fn step_instruction(&mut self) -> Result<u8, CoreError> {
+        if self.locked {
             self.bus.step_subsystems(4); self.clock.tick(4);
-            if self.bus.if_reg & self.bus.ie & 0x1F != 0 { self.halted = false; }
             return Ok(4);
         }
-        if self.ime_pending { self.ime = true; self.ime_pending = false; }
-        if self.ime {
-            let pending = self.bus.if_reg & self.bus.ie & 0x1F;
-            if pending != 0 {
-                self.ime = false;
-                let bit = pending.trailing_zeros() as u8;
-                self.bus.if_reg &= !(1<<bit);
-                let vec: u16 = 0x0040 + (bit as u16) * 8;
-                self.regs.sp = self.regs.sp.wrapping_sub(1);
-                self.bus.write(self.regs.sp, (self.regs.pc>>8) as u8);
-                self.regs.sp = self.regs.sp.wrapping_sub(1);
-                self.bus.write(self.regs.sp, self.regs.pc as u8);
-                self.regs.pc = vec;
-                self.bus.step_subsystems(20); self.clock.tick(20);
-                return Ok(20);
+        if self.stopped {
+            // Only time passes (for the host's frame pacing) until a button
+            // on a selected line is pressed; the LCD and DIV stay frozen
+            if self.bus.joypad_line_low() { self.stopped = false; }
+            self.clock.tick(4);
+            return Ok(4);
+        }
+        if self.halted {
+            let cycles = if self.fast_halt { self.halt_skip_cycles() } else { 4 };
+            self.bus.step_subsystems(cycles); self.clock.tick(cycles);
+            if self.bus.if_reg & self.bus.ie & 0x1F != 0 { self.halted = false; }
+            return Ok(cycles);
+        }
+        if self.ime && self.bus.if_reg & self.bus.ie & 0x1F != 0 {
+            return Ok(self.dispatch_interrupt());
+        }
+        if !self.breakpoints.is_empty() {
+            if let Some(hit) = self.breakpoints.check(&self.regs, self.clock.t_cycles) {
+                return Err(CoreError::Break(hit));
             }
         }
-        let op = self.bus.read(self.regs.pc);
+        // EI takes effect once the instruction after it has run (unless a DI
+        // in between cancelled it); a second EI ends the first one's delay
+        let ei_delay_done = self.ime_pending;
+        if !self.bus.watchpoints.is_empty() { self.bus.watchpoints.arm(self.regs.pc); }
+        if let Some(l) = self.bus.io_log.as_mut() { l.set_context(self.regs.pc, self.clock.t_cycles); }
+        let cached = self.bus.fetch_cached(self.regs.pc, self.halt_bug);
+        let op = match cached { Some(c) => c.op, None => self.bus.read(self.regs.pc) };
+        if self.bus.coverage.is_some() {
+            let cb = (op == 0xCB).then(|| self.bus.peek(self.regs.pc.wrapping_add(1)));
+            if let Some(c) = self.bus.coverage.as_mut() { c.record_op(op, cb); }
+        }
+        if self.trace.is_some() {
+            let cb = (op == 0xCB).then(|| self.bus.peek(self.regs.pc.wrapping_add(1)));
+            let operands = [self.bus.peek(self.regs.pc.wrapping_add(1)), self.bus.peek(self.regs.pc.wrapping_add(2))];
+            let entry = TraceEntry { pc: self.regs.pc, opcode: op, cb, operands, regs: self.regs.clone(), t_cycles: self.clock.t_cycles };
+            if let Some(t) = self.trace.as_mut() { t.push(entry); }
+        }
+        // After the halt bug, PC was not incremented past this opcode, so its
+        // byte is read again as the next byte (immediate, CB operand or opcode)
+        let refetch = std::mem::take(&mut self.halt_bug) as u16;
+        let (op_pc, op_sp) = (self.regs.pc, self.regs.sp);
+        #[cfg(feature = "profile")]
+        let profile_start = self.profiler.is_some().then(|| {
+            let bank = if op_pc < 0x8000 { (self.bus.mbc.rom_addr(op_pc) / 0x4000) as u16 } else { 0 };
+            let cb = (op == 0xCB).then(|| self.bus.peek(op_pc.wrapping_add(1)));
+            (bank, cb, std::time::Instant::now())
+        });
         // Phase 5: full SM83 instruction set via exec_op
         let cycles = if op == 0xCB {
+            if let Some(m) = self.exec_coverage.as_mut() { m.mark(&self.bus, op_pc, 2, self.clock.t_cycles); }
+            self.regs.pc = self.regs.pc.wrapping_sub(refetch);
             exec_cb(&mut self.regs, &mut self.bus)
+        } else if OPCODES[op as usize].is_illegal() {
+            // The SM83 hangs on these; PC stays on the opcode
+            if let Some(m) = self.exec_coverage.as_mut() { m.mark(&self.bus, op_pc, 1, self.clock.t_cycles); }
+            self.locked = true;
+            self.lock_hit = Some((op_pc, op));
+            4
         } else {
-            // Decode: get cycle count + PC delta, advance PC
-            let (cyc, delta) = decode(op, &self.bus, self.regs.pc);
-            self.regs.pc = self.regs.pc.wrapping_add(delta as u16);
-            // Execute instruction (exec_op reads immediates relative to advanced PC)
-            let actual_cyc = exec_op(op, &mut self.regs, &mut self.bus, cyc as u8);
+            // Base cycle count and length from the opcode table, advance PC
+            let info = &OPCODES[op as usize];
+            if let Some(m) = self.exec_coverage.as_mut() { m.mark(&self.bus, op_pc, info.len as u16, self.clock.t_cycles); }
+            self.regs.pc = self.regs.pc.wrapping_add(info.len as u16).wrapping_sub(refetch);
+            // Immediates sit just before the advanced PC
+            let (imm8, imm16) = match cached {
+                Some(c) => (c.imm8(), c.imm16()),
+                None => {
+                    let pc = self.regs.pc;
+                    let imm8 = self.bus.read(pc.wrapping_sub(1));
+                    (imm8, u16::from_le_bytes([self.bus.read(pc.wrapping_sub(2)), self.bus.read(pc.wrapping_sub(1))]))
+                }
+            };
+            let actual_cyc = exec_op(op, &mut self.regs, &mut self.bus, info.cycles, imm8, imm16);
             // Handle ops that exec_op defers back to step()
             match op {
-                0x76 => { self.regs.pc = self.regs.pc.wrapping_sub(delta as u16); self.halted = true; }
-                0x10 => {
-                    // STOP: execute CGB double-speed switch if armed
-                    if self.bus.speed_switch_armed {
-                        self.bus.double_speed = !self.bus.double_speed;
-                        self.bus.speed_switch_armed = false;
-                    }
-                    // Consume STOP's second byte (always 0x00)
+                // HALT with IME=0 and an interrupt already pending does not
+                // halt; it triggers the halt bug instead
+                0x76 => {
+                    if !self.ime && self.bus.if_reg & self.bus.ie & 0x1F != 0 { self.halt_bug = true; }
+                    else { self.halted = true; }
                 }
-                0xF3 => { self.ime = false; }
+                0x10 => self.stop(),
+                0x40 if self.ld_b_b_break => self.soft_break_hit = Some(op_pc),
+                0xF3 => { self.ime = false; self.ime_pending = false; }
                 0xFB => { self.ime_pending = true; }
+                // JP a16 / CALL a16: PC is already past the immediate
+                0xC3 | 0xCD => {
+                    let pc = self.regs.pc;
+                    let nn = match cached {
+                        Some(c) => c.imm16(),
+                        None => u16::from_le_bytes([self.bus.read(pc.wrapping_sub(2)), self.bus.read(pc.wrapping_sub(1))]),
+                    };
+                    if op == 0xCD { push_call(&mut self.regs, &mut self.bus, nn); } else { self.regs.pc = nn; }
+                }
+                0xE9 => { self.regs.pc = self.regs.hl(); }
+                // RET / RETI
+                0xC9 | 0xD9 => {
+                    let lo = self.bus.read(self.regs.sp); self.regs.sp = self.regs.sp.wrapping_add(1);
+                    let hi = self.bus.read(self.regs.sp); self.regs.sp = self.regs.sp.wrapping_add(1);
+                    self.regs.pc = u16::from_le_bytes([lo, hi]);
+                    if op == 0xD9 { self.ime = true; }
+                }
                 _ => {}
             }
             actual_cyc
         };
+        self.shadow_stack.track(op, op_pc, op_sp, self.regs.pc, self.regs.sp);
+        if self.bus.watchpoints.is_armed() { self.bus.watchpoints.finish(); }
         self.bus.step_subsystems(cycles);
         self.clock.tick(cycles);
+        #[cfg(feature = "profile")]
+        if let (Some(p), Some((bank, cb, t0))) = (self.profiler.as_mut(), profile_start) {
+            p.record(bank, op_pc, cb.unwrap_or(op), cb.is_some(), cycles, t0.elapsed().as_nanos() as u64);
+        }
+        if ei_delay_done && self.ime_pending { self.ime = true; self.ime_pending = false; }
         Ok(cycles)
     }
+    /// STOP (PC already past both bytes). With a button held it does not
+    /// stop: it halts, or with an interrupt pending does nothing at all.
+    /// Otherwise DIV is reset and the CPU either switches speed (CGB, KEY1
+    /// armed) or enters STOP mode. The byte after STOP is skipped unless an
+    /// interrupt is pending.
+    fn stop(&mut self) {
+        let pending = self.bus.if_reg & self.bus.ie & 0x1F != 0;
+        if pending { self.regs.pc = self.regs.pc.wrapping_sub(1); }
+        if self.bus.joypad_line_low() {
+            if !pending { self.halted = true; }
+            return;
+        }
+        self.bus.reset_div();
+        if self.bus.speed_switch_armed {
+            self.bus.double_speed = !self.bus.double_speed;
+            self.bus.speed_switch_armed = false;
+        } else {
+            self.stopped = true;
+        }
+    }
+    /// The 5 M-cycle interrupt dispatch: two internal cycles, PC pushed high
+    /// byte first, then the jump. The vector is picked from IE & IF after the
+    /// high byte push, so an interrupt raised during the first three cycles
+    /// can still win on priority. With SP at 0x0000 that push lands on IE:
+    /// if the new IE leaves nothing to service the dispatch is cancelled and
+    /// jumps to 0x0000, the request staying in IF. Returns the T-cycles taken (20).
+    fn dispatch_interrupt(&mut self) -> u8 {
+        self.ime = false;
+        self.ime_pending = false;
+        self.tick_m(2);
+        // After the halt bug the PC was never advanced past the opcode it
+        // re-reads, so the handler returns to that opcode (e.g. `ei; halt`)
+        let ret = self.regs.pc.wrapping_sub(std::mem::take(&mut self.halt_bug) as u16);
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.bus.write(self.regs.sp, (ret >> 8) as u8);
+        self.tick_m(1);
+        let pending = self.bus.if_reg & self.bus.ie & 0x1F;
+        self.regs.sp = self.regs.sp.wrapping_sub(1);
+        self.bus.write(self.regs.sp, ret as u8);
+        self.tick_m(1);
+        self.regs.pc = match pending.trailing_zeros() {
+            bit @ 0..=4 => { self.bus.if_reg &= !(1 << bit); 0x0040 + bit as u16 * 8 }
+            // Nothing left to service (the push overwrote IE): jump to 0x0000
+            _ => 0x0000,
+        };
+        self.shadow_stack.push(StackFrame { caller: ret, target: self.regs.pc, sp: self.regs.sp, kind: CallKind::Interrupt });
+        self.tick_m(1);
+        20
+    }
+    fn tick_m(&mut self, m_cycles: u8) {
+        self.bus.step_subsystems(m_cycles * 4);
+        self.clock.tick(m_cycles * 4);
+    }
     pub fn run_frame(&mut self) -> Result<(), CoreError> {
         let target = self.clock.t_cycles + CYCLES_PER_FRAME;
-        while self.clock.t_cycles < target { self.step()?; }
+        self.run_target = target;
+        self.begin_frame();
+        while self.clock.t_cycles < target {
+            if self.interrupt.swap(false, Ordering::AcqRel) { return Err(CoreError::Interrupted); }
+            self.step()?;
+        }
+        self.end_frame();
         Ok(())
     }
+    /// Host inputs for the frame about to run: per-frame stimulus, VIN audio
+    fn begin_frame(&mut self) {
+        if self.stimulus_provider.as_ref().is_some_and(|p| p.rate() == StimulusRate::Frame) {
+            self.update_stimulus();
+        }
+        if let Some(src) = self.vin_source.as_mut() {
+            let samples = src.samples(self.clock.frame_count(), self.bus.apu.samples_per_frame());
+            self.bus.apu.vin.push(&samples);
+        }
+    }
+    /// Host work after a frame: RTC sync, RAM console polling, link sync,
+    /// SRAM autosave
+    fn end_frame(&mut self) {
+        self.sync_rtc();
+        self.bus.poll_console();
+        if self.bus.io[0x02] & 0x81 != 0x80 {
+            if let Some(link) = self.link.as_mut() { link.sync(self.clock.t_cycles); }
+        }
+        // A failed flush is kept in `last_error` and retried
+        if let Some(a) = self.autosave.as_mut() { let _ = a.poll(&mut self.bus, self.clock.frame_count()); }
+    }
+    /// Write cartridge RAM to the autosave file now, folding its journal
+    /// into it (no-op without one, or when the file already holds it);
+    /// returns whether it was written
+    pub fn flush_sram(&mut self) -> std::io::Result<bool> {
+        match self.autosave.as_mut() {
+            Some(a) => a.compact(&self.bus),
+            None => Ok(false),
+        }
+    }
+    /// One `step` for the sub-frame runners. Crossing a frame boundary
+    /// (`clock.frame_count()`) does `run_frame`'s per-frame host work there,
+    /// so a frontend pacing with `run_cycles` / `run_scanline` alone still
+    /// gets RTC, console, link and stimulus updates.
+    fn step_paced(&mut self) -> Result<u8, CoreError> {
+        if self.interrupt.swap(false, Ordering::AcqRel) { return Err(CoreError::Interrupted); }
+        let frame = self.clock.frame_count();
+        let cycles = self.step()?;
+        if self.clock.frame_count() != frame {
+            self.end_frame();
+            self.begin_frame();
+        }
+        Ok(cycles)
+    }
+    /// Run at least `n` T-cycles (whole instructions, so up to one
+    /// instruction more). Returns the T-cycles run.
+    pub fn run_cycles(&mut self, n: u64) -> Result<u64, CoreError> {
+        let start = self.clock.t_cycles;
+        self.run_target = start.saturating_add(n);
+        while self.clock.t_cycles - start < n { self.step_paced()?; }
+        Ok(self.clock.t_cycles - start)
+    }
+    /// Run until LY moves to the next line (or wraps / resets to 0); with
+    /// the LCD off or frozen, one scanline's worth of cycles. Returns the
+    /// T-cycles run.
+    pub fn run_scanline(&mut self) -> Result<u64, CoreError> {
+        let (start, ly) = (self.clock.t_cycles, self.bus.ppu.ly);
+        let line = DOTS_PER_LINE as u64 * if self.bus.double_speed { 2 } else { 1 };
+        while self.bus.ppu.ly == ly && self.clock.t_cycles - start < line { self.step_paced()?; }
+        Ok(self.clock.t_cycles - start)
+    }
+    /// Step until `until(self)` holds, checked before every instruction, or
+    /// until `max_cycles` T-cycles have run. Ok(true) when the condition was
+    /// met, Ok(false) when the budget ran out. Step errors and the interrupt
+    /// handle end the run as in `run_frame`; frame boundaries get the
+    /// per-frame host work as in `run_cycles`.
+    pub fn run_until(&mut self, mut until: impl FnMut(&GbCore) -> bool, max_cycles: u64) -> Result<bool, CoreError> {
+        let limit = self.clock.t_cycles.saturating_add(max_cycles);
+        loop {
+            if until(self) { return Ok(true); }
+            if self.clock.t_cycles >= limit { return Ok(false); }
+            self.step_paced()?;
+        }
+    }
+    /// Set the pressed buttons (BTN_* mask). A newly pressed button requests
+    /// the joypad interrupt.
+    pub fn set_buttons(&mut self, buttons: u8) {
+        if buttons & !self.bus.buttons != 0 { self.bus.if_reg |= 0x10; }
+        self.bus.buttons = buttons;
+    }
+    /// Shared flag another thread can raise to make `run_frame` return
+    /// `Err(CoreError::Interrupted)` at the next instruction boundary. The
+    /// machine state stays consistent; the next `run_frame` resumes from there.
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> { Arc::clone(&self.interrupt) }
+    /// Console text (serial out + RAM console) captured so far, as UTF-8
+    pub fn console_text(&self) -> String { self.bus.console.text() }
+    /// Serial output alone, e.g. blargg test ROMs' results ("Passed" /
+    /// "Failed"); link transfers count too
+    pub fn serial_output(&self) -> &str { self.bus.console.serial() }
+    /// Return the serial output and clear it
+    pub fn take_serial_output(&mut self) -> String { self.bus.console.take_serial() }
+    /// Active subroutine frames, outermost first (see `callstack.rs`)
+    pub fn call_stack(&self) -> Vec<StackFrame> { self.shadow_stack.frames().to_vec() }
+    /// The profiler's report (see `profile.rs`), None while it is off
+    #[cfg(feature = "profile")]
+    pub fn profile_json(&self) -> Option<String> { self.profiler.as_ref().map(|p| p.to_json()) }
+    /// Return the console text and clear the log
+    pub fn take_console_text(&mut self) -> String { self.bus.console.take_text() }
+    /// Also capture a RAM ring-buffer console (polled after every frame)
+    pub fn set_ram_console(&mut self, ram: Option<RamConsole>) { self.bus.console.set_ram(ram); }
     pub fn frame_to_ascii(&self) -> String {
         let palette = ['.', '+', '#', '@'];
         let fb = &self.bus.ppu.framebuffer;
@@ -1709,48 +2560,92 @@ impl GbCore {
         out
     }
 
-    /// Serialize current emulator state to .mrom.sav JSON bytes
+    /// Serialize current emulator state to .mrom.sav JSON bytes.
+    /// Always taken at an instruction boundary (`SavePoint::Instruction`).
     pub fn save_state(&self) -> Vec<u8> {
+        self.save_state_kind(SavePoint::Instruction)
+    }
+
+    /// Serialize state at the requested save point. `SavePoint::Frame` is only
+    /// valid right after the step that entered VBlank (see `at_frame_boundary()`).
+    pub fn save_state_at(&self, point: SavePoint) -> Result<Vec<u8>, CoreError> {
+        if point == SavePoint::Frame && !self.at_frame_boundary {
+            return Err(CoreError::InvalidState("not at a frame boundary; use save_state_at_next_vblank()".into()));
+        }
+        Ok(self.save_state_kind(point))
+    }
+
+    /// Arm a frame-boundary save: the state is captured by the step that enters
+    /// the next VBlank and can be collected with `take_vblank_state()`.
+    pub fn save_state_at_next_vblank(&mut self) {
+        self.vblank_save_requested = true;
+    }
+
+    /// Collect a state captured by `save_state_at_next_vblank()`, if it is ready
+    pub fn take_vblank_state(&mut self) -> Option<Vec<u8>> {
+        self.vblank_state.take()
+    }
+
+    /// True when the last step entered VBlank (a `SavePoint::Frame` boundary)
+    pub fn at_frame_boundary(&self) -> bool { self.at_frame_boundary }
+
+    fn save_state_kind(&self, point: SavePoint) -> Vec<u8> {
         let cpu = format!(
-            "{{"pc":{},"sp":{},"a":{},"f":{},"b":{},"c":{},"d":{},"e":{},"h":{},"l":{},"halted":{},"ime":{}}}",
+            "{{\"pc\":{},\"sp\":{},\"a\":{},\"f\":{},\"b\":{},\"c\":{},\"d\":{},\"e\":{},\"h\":{},\"l\":{},\"halted\":{},\"ime\":{},\"ime_pending\":{},\"halt_bug\":{},\"stopped\":{},\"locked\":{}}}",
             self.regs.pc, self.regs.sp, self.regs.a, self.regs.f,
             self.regs.b, self.regs.c, self.regs.d, self.regs.e, self.regs.h, self.regs.l,
-            self.halted, self.ime
+            self.halted, self.ime, self.ime_pending, self.halt_bug, self.stopped, self.locked
+        );
+        let p = &self.bus.ppu;
+        let ppu = format!(
+            "{{\"mode\":{},\"dot\":{},\"ly\":{},\"lyc\":{},\"lcdc\":{},\"stat\":{},\"scy\":{},\"scx\":{},\"wy\":{},\"wx\":{},\"wlc\":{},\"bgp\":{},\"obp0\":{},\"obp1\":{},\"oam_scan\":\"{}\",\"mode3\":{}}}",
+            p.mode as u8, p.dot, p.ly, p.lyc, p.lcdc, p.stat, p.scy, p.scx, p.wy, p.wx, p.wlc,
+            p.pal_bg, p.pal_obj0, p.pal_obj1,
+            p.oam_scan.state_bytes().iter().map(|b| format!("{b:02x}")).collect::<String>(), p.mode3_dots
         );
+        let tm = &self.bus.timer;
+        let timer = format!(
+            "{{\"div\":{},\"div_counter\":{},\"tima\":{},\"tma\":{},\"tac\":{},\"tima_counter\":{}}}",
+            tm.div, tm.div_counter, tm.tima, tm.tma, tm.tac, tm.tima_counter
+        );
+        let d = &self.bus.dma;
+        let dma = format!("{{\"reg\":{},\"src\":{},\"next\":{},\"delay\":{}}}", d.reg, d.src, d.next, d.delay);
         let t = self.clock.t_cycles;
         // Compact hex dump helpers
         let wram_hex: String = self.bus.wram.iter().flat_map(|bank| bank.iter()).map(|b| format!("{:02x}",b)).collect();
         let hram_hex: String = self.bus.hram.iter().map(|b| format!("{:02x}",b)).collect();
         let oam_hex:  String = self.bus.oam.iter().map(|b| format!("{:02x}",b)).collect();
+        let io_hex:   String = self.bus.io.iter().map(|b| format!("{:02x}",b)).collect();
         let v0_hex:   String = self.bus.vram[0].iter().map(|b| format!("{:02x}",b)).collect();
         let v1_hex:   String = self.bus.vram[1].iter().map(|b| format!("{:02x}",b)).collect();
         let json = format!(
             concat!(
-                "{{"version":"mrom.sav.v1",",
-                ""t_cycles":{t},",
-                ""cpu":{cpu},",
-                ""rom_bank":{rom_bank},"ram_bank":{ram_bank},",
-                ""vram_bank":{vb},"double_speed":{ds},",
-                ""wram":"{wram}","hram":"{hram}","oam":"{oam}",",
-                ""vram0":"{v0}","vram1":"{v1}"}}"
+                "{{\"version\":\"{version}\",\"save_point\":\"{sp}\",\"meta\":{meta},\"config\":{config},",
+                "\"t_cycles\":{t},",
+                "\"cpu\":{cpu},\"ppu\":{ppu},\"timer\":{timer},\"dma\":{dma},",
+                "\"ie\":{ie},\"if\":{if_reg},",
+                "\"rom_bank\":{rom_bank},\"ram_bank\":{ram_bank},\"ram_enable\":{ram_en},",
+                "\"vram_bank\":{vb},\"wram_bank\":{wb},\"double_speed\":{ds},",
+                "\"wram\":\"{wram}\",\"hram\":\"{hram}\",\"oam\":\"{oam}\",\"io\":\"{io}\",",
+                "\"vram0\":\"{v0}\",\"vram1\":\"{v1}\"}}"
             ),
-            t=t, cpu=cpu,
-            rom_bank=self.bus.mbc.rom_bank, ram_bank=self.bus.mbc.ram_bank,
-            vb=self.bus.vram_bank, ds=self.bus.double_speed,
-            wram=wram_hex, hram=hram_hex, oam=oam_hex, v0=v0_hex, v1=v1_hex
+            version=SAVE_STATE_VERSION, sp=point.as_str(), meta=self.state_meta(point).to_json(), config=self.config.to_json(), t=t, cpu=cpu, ppu=ppu, timer=timer, dma=dma,
+            ie=self.bus.ie, if_reg=self.bus.if_reg,
+            rom_bank=self.bus.mbc.rom_bank, ram_bank=self.bus.mbc.ram_bank, ram_en=self.bus.mbc.ram_enable,
+            vb=self.bus.vram_bank, wb=self.bus.wram_bank, ds=self.bus.double_speed,
+            wram=wram_hex, hram=hram_hex, oam=oam_hex, io=io_hex, v0=v0_hex, v1=v1_hex
         );
         json.into_bytes()
     }
 
 
-    /// Load emulator state from mrom.sav.v1 JSON bytes (from save_state())
+    /// Load emulator state from mrom.sav.v1 JSON bytes (from save_state());
+    /// sealed files go through `load_state_from_file` instead
     pub fn load_state(&mut self, data: &[u8]) -> Result<(), CoreError> {
         let s = std::str::from_utf8(data)
-            .map_err(|e| CoreError::InvalidRom(format!("load_state: utf8 error: {e}")))?;
+            .map_err(|e| CoreError::InvalidState(format!("load_state: utf8 error: {e}")))?;
 
         fn parse_u64(s: &str, key: &str) -> Option<u64> {
-            let k = format!("\"{}\": ", key); // "key": 
-            let k2 = format!("\"{}\",", key); // tolerate both
             let pos = s.find(&format!("\"{}\":", key))?;
             let rest = &s[pos + key.len() + 3..];
             let end = rest.find(|c: char| !c.is_ascii_digit())?;
@@ -1772,12 +2667,46 @@ impl GbCore {
             bytes
         }
 
+        fn sub_object<'a>(s: &'a str, key: &str) -> Option<&'a str> {
+            let start = s.find(&format!("\"{}\":{{", key))? + key.len() + 3;
+            let end = s[start..].find('}').map(|i| start + i + 1).unwrap_or(s.len());
+            Some(&s[start..end])
+        }
+
+        if let Some(pos) = s.find("\"version\":\"") {
+            let rest = &s[pos + 11..];
+            let found = &rest[..rest.find('"').unwrap_or(rest.len())];
+            if found != SAVE_STATE_VERSION {
+                return Err(CoreError::StateVersionMismatch { found: found.into(), expected: SAVE_STATE_VERSION });
+            }
+        }
+
+        // Unknown save points are rejected rather than resumed inconsistently;
+        // states written before save points existed are instruction-boundary.
+        if let Some(pos) = s.find("\"save_point\":\"") {
+            let rest = &s[pos + 14..];
+            let name = &rest[..rest.find('"').unwrap_or(rest.len())];
+            if SavePoint::parse(name).is_none() {
+                return Err(CoreError::InvalidState(format!("load_state: unknown save_point {name:?}")));
+            }
+        }
+
+        // Older states have no config and keep the current one
+        if let Some(c) = sub_object(s, "config") {
+            if let Some(pos) = c.find("\"mem_init\":\"") {
+                let rest = &c[pos + 12..];
+                if let Some(m) = MemInit::parse(&rest[..rest.find('"').unwrap_or(rest.len())]) { self.config.mem_init = m; }
+            }
+            if let Some(seed) = parse_u64(c, "mem_seed") { self.config.mem_seed = seed; }
+            if let Some(pos) = c.find("\"model\":\"") {
+                let rest = &c[pos + 9..];
+                if let Some(m) = HardwareModel::parse(&rest[..rest.find('"').unwrap_or(rest.len())]) { self.config.model = m; }
+            }
+        }
+        self.bus.ppu.cgb = self.config.model.cgb_mode(&self.bus.rom);
+
         // CPU registers from "cpu" sub-object
-        let cpu_start = s.find("\"cpu\":{").map(|i| i + 7).unwrap_or(0);
-        let cpu_str = if cpu_start > 0 {
-            let end = s[cpu_start..].find('}').map(|i| cpu_start + i + 1).unwrap_or(s.len());
-            &s[cpu_start..end]
-        } else { s };
+        let cpu_str = sub_object(s, "cpu").unwrap_or(s);
 
         macro_rules! pu8 {
             ($k:expr) => { parse_u64(cpu_str, $k).unwrap_or(0) as u8 }
@@ -1798,6 +2727,51 @@ impl GbCore {
         self.regs.pc = pu16!("pc");
         self.halted  = parse_bool(cpu_str, "halted").unwrap_or(false);
         self.ime     = parse_bool(cpu_str, "ime").unwrap_or(false);
+        self.ime_pending = parse_bool(cpu_str, "ime_pending").unwrap_or(false);
+        self.halt_bug = parse_bool(cpu_str, "halt_bug").unwrap_or(false);
+        self.stopped = parse_bool(cpu_str, "stopped").unwrap_or(false);
+        self.locked = parse_bool(cpu_str, "locked").unwrap_or(false);
+        self.lock_hit = None;
+        self.soft_break_hit = None;
+        if let Some(tt) = self.time_travel.as_deref_mut() { tt.clear(); }
+
+        if let Some(p) = sub_object(s, "ppu") {
+            let ppu = &mut self.bus.ppu;
+            macro_rules! set {
+                ($f:ident, $k:expr, $t:ty) => { if let Some(v) = parse_u64(p, $k) { ppu.$f = v as $t; } }
+            }
+            if let Some(m) = parse_u64(p, "mode") {
+                ppu.mode = match m { 0 => PpuMode::HBlank, 1 => PpuMode::VBlank, 2 => PpuMode::OamScan, _ => PpuMode::Drawing };
+            }
+            set!(dot, "dot", u32); set!(ly, "ly", u8); set!(lyc, "lyc", u8);
+            set!(lcdc, "lcdc", u8); set!(stat, "stat", u8); set!(scy, "scy", u8); set!(scx, "scx", u8);
+            set!(wy, "wy", u8); set!(wx, "wx", u8); set!(wlc, "wlc", u8);
+            set!(pal_bg, "bgp", u8); set!(pal_obj0, "obp0", u8); set!(pal_obj1, "obp1", u8);
+            // Older states: the line's scan is redone from OAM
+            ppu.oam_scan = parse_hex(p, "oam_scan").and_then(|b| OamScan::from_state_bytes(&b)).unwrap_or_default();
+            ppu.mode3_dots = parse_u64(p, "mode3").map_or(PPU_MODE3_CYCLES, |v| v as u32);
+            // A line the FIFO was part way through finishes with the line renderer
+            if let Some(f) = ppu.fifo.as_deref_mut() { f.stop(); }
+        }
+        if let Some(t) = sub_object(s, "timer") {
+            let tm = &mut self.bus.timer;
+            if let Some(v) = parse_u64(t, "div") { tm.div = v as u8; }
+            if let Some(v) = parse_u64(t, "div_counter") { tm.div_counter = v as u16; }
+            if let Some(v) = parse_u64(t, "tima") { tm.tima = v as u8; }
+            if let Some(v) = parse_u64(t, "tma") { tm.tma = v as u8; }
+            if let Some(v) = parse_u64(t, "tac") { tm.tac = v as u8; }
+            if let Some(v) = parse_u64(t, "tima_counter") { tm.tima_counter = v as u32; }
+        }
+        if let Some(c) = self.bus.block_cache.as_mut() { c.clear(); }
+        // States from before OAM DMA took time have no transfer in flight
+        self.bus.dma = OamDma::new();
+        if let Some(d) = sub_object(s, "dma") {
+            let dma = &mut self.bus.dma;
+            if let Some(v) = parse_u64(d, "reg") { dma.reg = v as u8; }
+            if let Some(v) = parse_u64(d, "src") { dma.src = v as u8; }
+            if let Some(v) = parse_u64(d, "next") { dma.next = (v as u8).min(OAM_DMA_LEN); }
+            if let Some(v) = parse_u64(d, "delay") { dma.delay = v as u8; }
+        }
 
         // Top-level fields
         if let Some(t) = parse_u64(s, "t_cycles") { self.clock.t_cycles = t; }
@@ -1805,6 +2779,10 @@ impl GbCore {
         if let Some(rb) = parse_u64(s, "ram_bank") { self.bus.mbc.ram_bank = rb as u8; }
         if let Some(vb) = parse_u64(s, "vram_bank") { self.bus.vram_bank = vb as u8; }
         if let Some(ds) = parse_bool(s, "double_speed") { self.bus.double_speed = ds; }
+        if let Some(wb) = parse_u64(s, "wram_bank") { self.bus.wram_bank = wb as u8; }
+        if let Some(re) = parse_bool(s, "ram_enable") { self.bus.mbc.ram_enable = re; }
+        if let Some(ie) = parse_u64(s, "ie") { self.bus.ie = ie as u8; }
+        if let Some(fl) = parse_u64(s, "if") { self.bus.if_reg = fl as u8; }
 
         // Memory banks
         if let Some(wram_bytes) = parse_hex(s, "wram") {
@@ -1824,6 +2802,11 @@ impl GbCore {
                 if i < self.bus.oam.len() { self.bus.oam[i] = *b; }
             }
         }
+        if let Some(io_bytes) = parse_hex(s, "io") {
+            for (i, b) in io_bytes.iter().enumerate() {
+                if i < self.bus.io.len() { self.bus.io[i] = *b; }
+            }
+        }
         if let Some(v0) = parse_hex(s, "vram0") {
             for (i, b) in v0.iter().enumerate() {
                 if i < 0x2000 { self.bus.vram[0][i] = *b; }
@@ -1834,42 +2817,80 @@ impl GbCore {
                 if i < 0x2000 { self.bus.vram[1][i] = *b; }
             }
         }
+        self.shadow_stack.clear();
 
         Ok(())
     }
 
-    /// Load save state from file
-    pub fn load_state_from_file(&mut self, path: &std::path::Path) -> std::io::Result<()> {
+    /// Load save state from file, opening it under the sealing policy
+    /// (`open_stored` in `seal.rs`)
+    pub fn load_state_from_file(&mut self, path: &std::path::Path) -> Result<(), CoreError> {
         let data = std::fs::read(path)?;
-        self.load_state(&data).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+        self.load_state(&open_stored(&data)?)
+    }
+
+    /// Metadata embedded in every savestate (see `state_index.rs`)
+    pub fn state_meta(&self, point: SavePoint) -> StateMeta {
+        let title = self.bus.rom.get(0x134..0x143).unwrap_or(&[]);
+        StateMeta {
+            rom_title: String::from_utf8_lossy(title).trim_matches('\0').to_string(),
+            rom_hash: rom_hash(&self.bus.rom),
+            frame: self.clock.frame_count(),
+            play_time_ms: self.clock.t_cycles * 1000 / CPU_HZ,
+            save_point: point.as_str().into(),
+            thumbnail: thumbnail_rgb(&self.framebuffer_rgb()),
+        }
     }
 
-    /// Write save state to file at `path`
+    /// Write save state to file at `path` (sealed when a key is set, see `seal.rs`)
     pub fn save_state_to_file(&self, path: &std::path::Path) -> std::io::Result<()> {
-        std::fs::write(path, self.save_state())
+        write_sealed(path, &self.save_state())
     }
 
     /// Get framebuffer as RGB888 bytes [r,g,b, r,g,b, ...] — 160×144×3 = 69,120 bytes
-    /// For DMG (non-CGB): maps 2-bit palette values to greyscale
-    /// For CGB: uses bg_cpal with direct palette index from tile attributes
-    /// (Phase 7 approximation: maps 2-bit value through BG palette 0)
+    /// For DMG (non-CGB): maps 2-bit palette values through `dmg_colors`, by
+    /// the layer each pixel came from
+    /// In CGB mode: maps each colour number through the CGB palette in
+    /// `pixel_source` (see `cgb_render.rs`)
     pub fn framebuffer_rgb(&self) -> Vec<u8> {
-        let fb = &self.bus.ppu.framebuffer;
-        let is_cgb = self.bus.bg_cpal != [0xFFu8; 64];
-        let mut out = Vec::with_capacity(LCD_WIDTH * LCD_HEIGHT * 3);
+        let mut out = vec![0u8; LCD_WIDTH * LCD_HEIGHT * 3];
+        self.framebuffer_rgb_into(&mut out);
+        out
+    }
 
-        for &px in fb.iter() {
-            let (r, g, b) = if is_cgb {
-                // Use CGB BG palette 0, color index = pixel value
-                Bus::cgb_color(&self.bus.bg_cpal, 0, px.min(3))
-            } else {
-                // DMG greyscale
-                let shade = match px.min(3) { 0 => 255, 1 => 170, 2 => 85, _ => 0 };
-                (shade, shade, shade)
-            };
-            out.push(r); out.push(g); out.push(b);
+    /// `framebuffer_rgb()` into `out` (at least 160×144×3 bytes)
+    pub fn framebuffer_rgb_into(&self, out: &mut [u8]) {
+        let ppu = &self.bus.ppu;
+        if ppu.cgb {
+            let colors = cgb_colors(&self.bus);
+            for ((&c, &src), rgb) in ppu.framebuffer.iter().zip(&ppu.pixel_source).zip(out.chunks_exact_mut(3)) {
+                let (r, g, b) = colors[src as usize & 15][c as usize & 3];
+                rgb.copy_from_slice(&[r, g, b]);
+            }
+            return;
         }
-        out
+        let is_cgb = self.bus.bg_cpal != [0xFFu8; 64];
+        let lut = if is_cgb {
+            // Use CGB BG palette 0, color index = pixel value
+            RgbLut::uniform([0, 1, 2, 3].map(|c| Bus::cgb_color(&self.bus.bg_cpal, 0, c)))
+        } else {
+            // DMG shades by layer (greyscale unless a palette is set)
+            RgbLut::new([0, 1, 2, 3].map(|s| *self.dmg_colors.for_source(s)))
+        };
+        rgb_row(ppu.simd, &ppu.framebuffer, &ppu.pixel_source, &lut, out);
+    }
+
+    /// `framebuffer_rgb()` with `overlay` blended over it; the plain
+    /// framebuffer when there is no overlay
+    pub fn framebuffer_rgb_annotated(&self) -> Vec<u8> {
+        let mut rgb = self.framebuffer_rgb();
+        if let Some(overlay) = self.overlay.as_ref() { overlay.composite(&mut rgb); }
+        rgb
+    }
+
+    /// Encode the current framebuffer (`framebuffer_rgb()`) as a PNG file
+    pub fn framebuffer_png(&self) -> Vec<u8> {
+        encode_png_rgb(LCD_WIDTH as u32, LCD_HEIGHT as u32, &self.framebuffer_rgb())
     }
 
     /// Encode framebuffer as compact base64-like hex string for JSON embedding
@@ -1877,27 +2898,33 @@ impl GbCore {
         self.framebuffer_rgb().iter().map(|b| format!("{:02x}", b)).collect()
     }
 
+    /// Stable 64-bit digest of the emulated machine state: equal for two
+    /// cores that will run identically (see `state_hash.rs`)
+    pub fn state_hash(&self) -> u64 { state_hash::hash_core(self) }
+
     /// Full JSON snapshot for network broadcast / live replay
     /// Format: mrom.snap.v1 — lightweight, designed for WebSocket streaming
     pub fn state_json(&self) -> String {
         let fb_hex = self.framebuffer_hex();
         let cpu = format!(
-            "{{"pc":{},"sp":{},"a":{},"f":{}}}",
+            "{{\"pc\":{},\"sp\":{},\"a\":{},\"f\":{}}}",
             self.regs.pc, self.regs.sp, self.regs.a, self.regs.f
         );
         let bg_pal: String = self.bus.bg_cpal.iter().map(|b| format!("{:02x}",b)).collect();
         format!(
             concat!(
-                "{{"v":"mrom.snap.v1",",
-                ""f":{frame},"ly":{ly},"mode":{mode},",
-                ""cpu":{cpu},",
-                ""ds":{ds},"wb":{wb},"vb":{vb},",
-                ""bg_pal":"{bg_pal}",",
-                ""fb":"{fb}"}}"
+                "{{\"v\":\"mrom.snap.v1\",",
+                "\"f\":{frame},\"ly\":{ly},\"mode\":{mode},\"lcdc\":{lcdc},\"rb\":{rb},",
+                "\"cpu\":{cpu},",
+                "\"ds\":{ds},\"wb\":{wb},\"vb\":{vb},",
+                "\"bg_pal\":\"{bg_pal}\",",
+                "\"fb\":\"{fb}\"}}"
             ),
             frame = self.clock.frame_count(),
             ly = self.bus.ppu.ly,
             mode = self.bus.ppu.mode as u8,
+            lcdc = self.bus.ppu.lcdc,
+            rb = self.bus.mbc.rom_bank,
             cpu = cpu,
             ds = self.bus.double_speed,
             wb = self.bus.wram_bank,
@@ -1916,4 +2943,26 @@ impl GbCore {
             self.clock.t_cycles,
         )
     }
+
+    /// JSON for the ABI `diagnostics` callback: frame / cycle position, the
+    /// active lite-mode degradations (empty when running at full quality),
+    /// the measured input latency and the last frame's motion (null when off)
+    pub fn diagnostics_json(&self) -> String {
+        let degradations: Vec<String> = self.config.lite.degradations().iter().map(|d| d.to_json()).collect();
+        let input_latency = self.input_latency.as_ref().map_or("null".to_string(), InputLatency::to_json);
+        let motion = self.motion.as_ref().and_then(MotionTracker::last).map_or("null".to_string(), FrameMotion::to_json);
+        format!("{{\"frame\":{},\"t_cycles\":{},\"lite\":{},\"degradations\":[{}],\"input_latency\":{},\"motion\":{}}}",
+            self.clock.frame_count(), self.clock.t_cycles, self.config.lite.is_active(), degradations.join(","), input_latency, motion)
+    }
+    /// Apply the JSON options blob of the ABI `configure` callback. Known
+    /// keys: `motion_metadata` (bool) turns `motion` on / off. Unknown keys
+    /// are ignored, as the ABI allows.
+    pub fn configure(&mut self, json: &str) -> Result<(), String> {
+        let cfg = Json::parse(json).map_err(|e| e.to_string())?;
+        if let Some(v) = cfg.get("motion_metadata") {
+            let on = v.as_bool().ok_or("motion_metadata: expected a bool")?;
+            if on != self.motion.is_some() { self.motion = on.then(MotionTracker::new); }
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,220 @@
+//! link — serial link cable transports, including BGB's TCP link protocol
+//!
+//! Without a transport the serial port acts as if no cable were plugged in:
+//! an internal-clock transfer completes at once and shifts in 0xFF. With
+//! `GbCore::set_link(Some(transport))` transfers go through the transport:
+//!
+//! - internal clock (SC = 0x81): this core is master. `transfer` sends SB and
+//!   returns the partner's byte; the transfer completes within the same step,
+//!   as the unlinked port does.
+//! - external clock (SC = 0x80): this core waits as slave. `poll_slave` is
+//!   asked once per bit time (512 T-cycles) whether the master clocked a
+//!   byte in, and the transfer completes when it has.
+//!
+//! `sync` runs at the end of every `run_frame` while no slave transfer is
+//! armed, for timestamps and for answering a master this core is not ready for.
+//!
+//! `BgbLink` (feature `link`, std sockets only) speaks the link protocol of
+//! the BGB emulator (version 1.4), which other emulators implement as well,
+//! so a core can trade or battle with a BGB / SameBoy instance over TCP. Each
+//! packet is 8 bytes: command, three data bytes and a little-endian u32
+//! timestamp in 2 MiHz units (T-cycles / 2, lower 31 bits).
+
+/// Serial link partner (`GbCore::set_link`)
+pub trait LinkTransport: Send {
+    /// Master transfer: send `out` (SB) with `control` (SC) and return the
+    /// byte shifted in; 0xFF when no partner answers
+    fn transfer(&mut self, out: u8, control: u8, t_cycles: u64) -> u8;
+    /// Slave side with a transfer armed: if the master clocked a byte in,
+    /// answer with `out` and return the byte received
+    fn poll_slave(&mut self, out: u8, t_cycles: u64) -> Option<u8>;
+    /// Once per frame while no slave transfer is armed
+    fn sync(&mut self, _t_cycles: u64) {}
+}
+
+pub const BGB_VERSION: BgbPacket = BgbPacket { cmd: BgbPacket::VERSION, b2: 1, b3: 4, b4: 0, i1: 0 };
+
+/// One BGB link packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BgbPacket {
+    pub cmd: u8,
+    pub b2: u8,
+    pub b3: u8,
+    pub b4: u8,
+    pub i1: u32,
+}
+
+impl BgbPacket {
+    pub const VERSION: u8 = 1;
+    pub const JOYPAD: u8 = 101;
+    /// Master starts a transfer: b2 = data, b3 = control
+    pub const SYNC1: u8 = 104;
+    /// Slave answers sync1: b2 = data, b3 = 0x80, b4 = 1
+    pub const SYNC2: u8 = 105;
+    /// b2 = 0: timestamp update; b2 = 1: answer to sync1 with no transfer armed
+    pub const SYNC3: u8 = 106;
+    pub const STATUS: u8 = 108;
+    pub const WANT_DISCONNECT: u8 = 109;
+
+    pub fn new(cmd: u8, b2: u8, b3: u8, b4: u8, i1: u32) -> Self { BgbPacket { cmd, b2, b3, b4, i1 } }
+    pub fn encode(&self) -> [u8; 8] {
+        let t = self.i1.to_le_bytes();
+        [self.cmd, self.b2, self.b3, self.b4, t[0], t[1], t[2], t[3]]
+    }
+    pub fn decode(b: [u8; 8]) -> Self {
+        BgbPacket { cmd: b[0], b2: b[1], b3: b[2], b4: b[3], i1: u32::from_le_bytes([b[4], b[5], b[6], b[7]]) }
+    }
+}
+
+/// T-cycles as a BGB timestamp
+pub fn bgb_timestamp(t_cycles: u64) -> u32 { (t_cycles / 2) as u32 & 0x7FFF_FFFF }
+
+#[cfg(feature = "link")]
+pub use self::tcp::BgbLink;
+
+#[cfg(feature = "link")]
+mod tcp {
+    use super::*;
+    use std::io::{self, Read, Write};
+    use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+    use std::time::{Duration, Instant};
+
+    /// Status flags: running, and reconnects not supported
+    const BGB_STATUS_RUNNING: u8 = 0x01;
+
+    /// BGB link protocol over TCP
+    pub struct BgbLink {
+        stream: TcpStream,
+        rx: Vec<u8>,
+        connected: bool,
+        remote_timestamp: u32,
+        remote_status: u8,
+        /// How long a master transfer waits for the partner's answer
+        pub timeout: Duration,
+    }
+
+    impl BgbLink {
+        /// Connect to a partner listening at `addr` (BGB's default port is 8765)
+        pub fn connect(addr: impl ToSocketAddrs) -> io::Result<BgbLink> { Self::handshake(TcpStream::connect(addr)?) }
+        /// Wait for one partner to connect
+        pub fn accept(listener: &TcpListener) -> io::Result<BgbLink> { Self::handshake(listener.accept()?.0) }
+
+        /// Exchange version packets (both must be 1.4) and report running
+        pub fn handshake(stream: TcpStream) -> io::Result<BgbLink> {
+            stream.set_nodelay(true)?;
+            stream.set_nonblocking(true)?;
+            let mut link = BgbLink {
+                stream, rx: vec![], connected: true, remote_timestamp: 0, remote_status: 0,
+                timeout: Duration::from_secs(1),
+            };
+            link.send(BGB_VERSION)?;
+            match link.recv_until(|p| p.cmd == BgbPacket::VERSION) {
+                Some(v) if v == BGB_VERSION => {}
+                Some(v) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("BGB link version {}.{} (want 1.4)", v.b2, v.b3))),
+                None => return Err(io::Error::new(io::ErrorKind::TimedOut, "no BGB version packet")),
+            }
+            link.send(BgbPacket::new(BgbPacket::STATUS, BGB_STATUS_RUNNING, 0, 0, 0))?;
+            Ok(link)
+        }
+
+        pub fn is_connected(&self) -> bool { self.connected }
+        /// Partner's last reported time (2 MiHz units)
+        pub fn remote_timestamp(&self) -> u32 { self.remote_timestamp }
+        /// Partner's last status flags (bit 0 running, bit 1 paused)
+        pub fn remote_status(&self) -> u8 { self.remote_status }
+
+        fn send(&mut self, p: BgbPacket) -> io::Result<()> {
+            let bytes = p.encode();
+            let mut sent = 0;
+            while sent < bytes.len() {
+                match self.stream.write(&bytes[sent..]) {
+                    Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                    Ok(n) => sent += n,
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => std::thread::yield_now(),
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+
+        fn send_or_drop(&mut self, p: BgbPacket) {
+            if self.connected && self.send(p).is_err() { self.connected = false; }
+        }
+
+        /// Next whole packet, without waiting
+        fn try_recv(&mut self) -> Option<BgbPacket> {
+            let mut buf = [0u8; 256];
+            while self.connected {
+                match self.stream.read(&mut buf) {
+                    Ok(0) => self.connected = false,
+                    Ok(n) => self.rx.extend_from_slice(&buf[..n]),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(_) => self.connected = false,
+                }
+            }
+            if self.rx.len() < 8 { return None; }
+            let mut b = [0u8; 8];
+            b.copy_from_slice(&self.rx[..8]);
+            self.rx.drain(..8);
+            Some(BgbPacket::decode(b))
+        }
+
+        /// Handle housekeeping packets; returns the packet if the caller must act on it
+        fn housekeep(&mut self, p: BgbPacket) -> Option<BgbPacket> {
+            match p.cmd {
+                BgbPacket::SYNC3 if p.b2 == 0 => { self.remote_timestamp = p.i1; None }
+                BgbPacket::STATUS => { self.remote_status = p.b2; None }
+                BgbPacket::WANT_DISCONNECT => { self.connected = false; None }
+                BgbPacket::JOYPAD => None,
+                _ => Some(p),
+            }
+        }
+
+        /// Wait up to `timeout` for a packet `want` accepts, handling others
+        fn recv_until(&mut self, want: impl Fn(&BgbPacket) -> bool) -> Option<BgbPacket> {
+            let deadline = Instant::now() + self.timeout;
+            while self.connected && Instant::now() < deadline {
+                match self.try_recv() {
+                    Some(p) if want(&p) => return Some(p),
+                    Some(p) => { self.housekeep(p); }
+                    None => std::thread::sleep(Duration::from_micros(100)),
+                }
+            }
+            None
+        }
+    }
+
+    impl LinkTransport for BgbLink {
+        fn transfer(&mut self, out: u8, control: u8, t_cycles: u64) -> u8 {
+            self.send_or_drop(BgbPacket::new(BgbPacket::SYNC1, out, control, 0, bgb_timestamp(t_cycles)));
+            let answer = |p: &BgbPacket| p.cmd == BgbPacket::SYNC2 || (p.cmd == BgbPacket::SYNC3 && p.b2 == 1);
+            match self.recv_until(answer) {
+                Some(p) if p.cmd == BgbPacket::SYNC2 => p.b2,
+                _ => 0xFF,
+            }
+        }
+
+        fn poll_slave(&mut self, out: u8, _t_cycles: u64) -> Option<u8> {
+            while let Some(p) = self.try_recv() {
+                let Some(p) = self.housekeep(p) else { continue };
+                if p.cmd == BgbPacket::SYNC1 {
+                    self.remote_timestamp = p.i1;
+                    self.send_or_drop(BgbPacket::new(BgbPacket::SYNC2, out, 0x80, 1, 0));
+                    return Some(p.b2);
+                }
+            }
+            None
+        }
+
+        fn sync(&mut self, t_cycles: u64) {
+            // No transfer armed: a master waiting on us gets "no answer"
+            while let Some(p) = self.try_recv() {
+                if let Some(p) = self.housekeep(p) {
+                    if p.cmd == BgbPacket::SYNC1 { self.send_or_drop(BgbPacket::new(BgbPacket::SYNC3, 1, 0, 0, 0)); }
+                }
+            }
+            self.send_or_drop(BgbPacket::new(BgbPacket::SYNC3, 0, 0, 0, bgb_timestamp(t_cycles)));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! lite — graceful degradation for hosts too slow for full-speed emulation
+//!
+//! `CoreConfig::lite` trades output quality for speed. The CPU, timer, DMA
+//! and interrupt timing stay exact, so game logic runs the same as in a full
+//! build. Only what the player sees and hears is reduced:
+//!
+//! - `skip_audio` — the APU channels are not ticked and no samples are
+//!   produced. Register reads and DIV-clocked length counters still work.
+//! - `render` — PPU modes, STAT/LY and interrupts run unchanged, but only
+//!   every other scanline (skipped rows repeat the one above) or every other
+//!   frame (the previous frame stays on screen) is drawn.
+//!
+//! The active degradations are reported by `GbCore::diagnostics_json()`. That
+//! is the JSON an ecore returns from the ABI `diagnostics` callback, and its
+//! entries have the same shape as the planner's `Degradation`.
+
+/// Which scanlines the PPU draws
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderSkip {
+    #[default]
+    Full,
+    /// Draw even lines; odd lines repeat the line above
+    AlternateScanlines,
+    /// Draw even frames; odd frames keep the previous picture
+    AlternateFrames,
+}
+
+impl RenderSkip {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RenderSkip::Full => "full",
+            RenderSkip::AlternateScanlines => "alternate_scanlines",
+            RenderSkip::AlternateFrames => "alternate_frames",
+        }
+    }
+    pub fn parse(s: &str) -> Option<RenderSkip> {
+        match s {
+            "full" => Some(RenderSkip::Full),
+            "alternate_scanlines" => Some(RenderSkip::AlternateScanlines),
+            "alternate_frames" => Some(RenderSkip::AlternateFrames),
+            _ => None,
+        }
+    }
+}
+
+/// Output reductions for weak hosts; the default is a full-quality core
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LiteMode {
+    pub skip_audio: bool,
+    pub render: RenderSkip,
+}
+
+/// One active reduction, described for the host / planner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoreDegradation {
+    pub subsystem: &'static str,
+    pub description: &'static str,
+    pub equivalence_impact: &'static str,
+}
+
+impl CoreDegradation {
+    pub fn to_json(&self) -> String {
+        format!("{{\"subsystem\":\"{}\",\"description\":\"{}\",\"equivalence_impact\":\"{}\"}}",
+            self.subsystem, self.description, self.equivalence_impact)
+    }
+}
+
+impl LiteMode {
+    /// Everything reduced: no audio, alternate scanlines
+    pub const LITE: LiteMode = LiteMode { skip_audio: true, render: RenderSkip::AlternateScanlines };
+
+    pub fn is_active(&self) -> bool { *self != LiteMode::default() }
+
+    pub fn degradations(&self) -> Vec<CoreDegradation> {
+        let mut out = Vec::new();
+        if self.skip_audio {
+            out.push(CoreDegradation {
+                subsystem: "apu",
+                description: "APU sample generation disabled",
+                equivalence_impact: "no audio output",
+            });
+        }
+        match self.render {
+            RenderSkip::Full => {}
+            RenderSkip::AlternateScanlines => out.push(CoreDegradation {
+                subsystem: "ppu",
+                description: "alternate_scanlines: odd scanlines repeat the line above",
+                equivalence_impact: "half vertical resolution",
+            }),
+            RenderSkip::AlternateFrames => out.push(CoreDegradation {
+                subsystem: "ppu",
+                description: "alternate_frames: odd frames are not drawn",
+                equivalence_impact: "half frame rate on screen",
+            }),
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        format!("{{\"skip_audio\":{},\"render\":\"{}\"}}", self.skip_audio, self.render.as_str())
+    }
+}
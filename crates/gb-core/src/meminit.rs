@@ -0,0 +1,89 @@
+//! meminit — power-on RAM contents per hardware model
+//!
+//! Real units do not boot with zeroed RAM. What WRAM, HRAM and OAM hold at
+//! power-on is model-specific and only partly random, and some games (and
+//! anti-emulator checks) read it before writing. `CoreConfig::mem_init` picks
+//! a pattern. `mem_seed` makes the random part reproducible, and both are
+//! written into savestates. VRAM stays zero for every model, because both
+//! boot ROMs clear it before handing over to the cartridge.
+
+use crate::{Bus, HardwareModel, LiteMode};
+
+/// Initial RAM pattern
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemInit {
+    /// Everything zero (deterministic; the historical behavior)
+    #[default]
+    Zero,
+    /// DMG: uniformly noisy WRAM, HRAM and OAM
+    Dmg,
+    /// CGB: WRAM in 0x00/0xFF stripes with sparse flipped bits, noisy HRAM,
+    /// zeroed OAM
+    Cgb,
+}
+
+impl MemInit {
+    pub fn as_str(self) -> &'static str {
+        match self { MemInit::Zero => "zero", MemInit::Dmg => "dmg", MemInit::Cgb => "cgb" }
+    }
+    pub fn parse(s: &str) -> Option<MemInit> {
+        match s { "zero" => Some(MemInit::Zero), "dmg" => Some(MemInit::Dmg), "cgb" => Some(MemInit::Cgb), _ => None }
+    }
+}
+
+/// Construction-time options for `GbCore::with_config` / `Bus::with_config`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoreConfig {
+    /// Console whose post-boot registers the core starts from (see `hwmodel.rs`)
+    pub model: HardwareModel,
+    pub mem_init: MemInit,
+    /// Seed for the random component of `mem_init`
+    pub mem_seed: u64,
+    /// Output reductions for slow hosts (see `lite.rs`). Recorded in
+    /// savestates, but loading one keeps the current host's setting
+    pub lite: LiteMode,
+}
+
+impl CoreConfig {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"model\":\"{}\",\"mem_init\":\"{}\",\"mem_seed\":{},\"lite\":{}}}",
+            self.model.as_str(), self.mem_init.as_str(), self.mem_seed, self.lite.to_json()
+        )
+    }
+}
+
+/// xorshift64*; a zero seed is remapped so it still produces noise
+pub(crate) struct Noise(u64);
+impl Noise {
+    pub(crate) fn new(seed: u64) -> Self { Noise(if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed }) }
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12; self.0 ^= self.0 << 25; self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+    fn byte(&mut self) -> u8 { (self.next() >> 56) as u8 }
+}
+
+/// Fill WRAM, HRAM and OAM with the power-on pattern for `config`
+pub fn apply_mem_init(bus: &mut Bus, config: &CoreConfig) {
+    let mut noise = Noise::new(config.mem_seed);
+    match config.mem_init {
+        MemInit::Zero => {}
+        MemInit::Dmg => {
+            for b in bus.wram.iter_mut().flat_map(|bank| bank.iter_mut()) { *b = noise.byte(); }
+            for b in bus.hram.iter_mut() { *b = noise.byte(); }
+            for b in bus.oam.iter_mut() { *b = noise.byte(); }
+        }
+        MemInit::Cgb => {
+            for bank in bus.wram.iter_mut() {
+                for (i, b) in bank.iter_mut().enumerate() {
+                    let stripe = if (i / 8) % 2 == 0 { 0x00 } else { 0xFF };
+                    // roughly one byte in 64 has a single bit flipped
+                    let r = noise.next();
+                    *b = if r & 0x3F == 0 { stripe ^ (1 << ((r >> 8) & 7)) } else { stripe };
+                }
+            }
+            for b in bus.hram.iter_mut() { *b = noise.byte(); }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! metrics — counters / gauges for capture runs, Prometheus text export
+//!
+//! One `Metrics` registry per process, shared by reference (interior
+//! mutability). The `METRIC_*` names below are pre-registered; other names
+//! can be `describe`d, or are registered on first use. Export options:
+//!
+//! - scrape: `encode()` behind an HTTP endpoint (`letsplay_serve /metrics`)
+//!   or `write_textfile()` for node_exporter's textfile collector
+//! - push: `push()` PUTs to a Prometheus Pushgateway
+//!
+//! Std sockets only, so the default build stays dependency-free.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const METRIC_FPS: &str = "mrom_fps";
+pub const METRIC_FRAMES: &str = "mrom_frames_total";
+pub const METRIC_DESYNCS: &str = "mrom_desyncs_total";
+pub const METRIC_WATCHDOG_TRIPS: &str = "mrom_watchdog_trips_total";
+pub const METRIC_BYTES_WRITTEN: &str = "mrom_bytes_written_total";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind { Counter, Gauge }
+
+impl MetricKind {
+    pub fn as_str(self) -> &'static str {
+        match self { MetricKind::Counter => "counter", MetricKind::Gauge => "gauge" }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Series { kind: MetricKind, help: String, value: f64 }
+
+#[derive(Debug)]
+pub struct Metrics {
+    /// Constant labels attached to every series (`rom`, `job`, ...)
+    labels: Vec<(String, String)>,
+    series: Mutex<BTreeMap<String, Series>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self { Self::new() }
+}
+
+impl Metrics {
+    /// Registry with the standard capture metrics at zero
+    pub fn new() -> Self {
+        let m = Metrics { labels: vec![], series: Mutex::new(BTreeMap::new()) };
+        m.describe(METRIC_FPS, MetricKind::Gauge, "Emulated frames per host second");
+        m.describe(METRIC_FRAMES, MetricKind::Counter, "Frames emitted");
+        m.describe(METRIC_DESYNCS, MetricKind::Counter, "Determinism / replay desyncs detected");
+        m.describe(METRIC_WATCHDOG_TRIPS, MetricKind::Counter, "Runs stopped by the wall-clock watchdog");
+        m.describe(METRIC_BYTES_WRITTEN, MetricKind::Counter, "Bytes of artifacts written");
+        m
+    }
+
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        self.labels.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Series>> {
+        self.series.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    /// Register `name` (keeps the current value if it already exists)
+    pub fn describe(&self, name: &str, kind: MetricKind, help: &str) {
+        let mut s = self.lock();
+        let e = s.entry(name.to_string()).or_insert(Series { kind, help: String::new(), value: 0.0 });
+        e.kind = kind;
+        e.help = help.to_string();
+    }
+
+    /// Add to a counter (registered as one on first use)
+    pub fn add(&self, name: &str, by: f64) {
+        self.lock().entry(name.to_string())
+            .or_insert(Series { kind: MetricKind::Counter, help: String::new(), value: 0.0 }).value += by;
+    }
+    pub fn inc(&self, name: &str) { self.add(name, 1.0); }
+
+    /// Set a gauge (registered as one on first use)
+    pub fn set(&self, name: &str, value: f64) {
+        self.lock().entry(name.to_string())
+            .or_insert(Series { kind: MetricKind::Gauge, help: String::new(), value: 0.0 }).value = value;
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> { self.lock().get(name).map(|s| s.value) }
+
+    /// Prometheus text exposition format (0.0.4)
+    pub fn encode(&self) -> String {
+        let esc = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        let labels = if self.labels.is_empty() { String::new() } else {
+            let l: Vec<String> = self.labels.iter().map(|(k, v)| format!("{k}=\"{}\"", esc(v))).collect();
+            format!("{{{}}}", l.join(","))
+        };
+        let mut out = String::new();
+        for (name, s) in self.lock().iter() {
+            if !s.help.is_empty() { out += &format!("# HELP {name} {}\n", s.help.replace('\n', " ")); }
+            out += &format!("# TYPE {name} {}\n{name}{labels} {}\n", s.kind.as_str(), format_value(s.value));
+        }
+        out
+    }
+
+    /// Write `encode()` to `path` for a textfile collector (atomic rename)
+    pub fn write_textfile(&self, path: &Path) -> io::Result<()> {
+        let tmp = path.with_extension("prom.tmp");
+        std::fs::write(&tmp, self.encode())?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// PUT `encode()` to a Pushgateway at `gateway` (`host:port`, optionally
+    /// `http://`-prefixed) under `/metrics/job/<job>`
+    pub fn push(&self, gateway: &str, job: &str) -> io::Result<()> {
+        let host = gateway.trim_start_matches("http://").trim_end_matches('/');
+        let body = self.encode();
+        let mut stream = TcpStream::connect(host)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+        write!(stream, "PUT /metrics/job/{job} HTTP/1.0\r\nHost: {host}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{body}", body.len())?;
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp)?;
+        let status = resp.split_whitespace().nth(1).unwrap_or("");
+        if status.starts_with('2') { Ok(()) } else {
+            Err(io::Error::other(format!("pushgateway {host}: {}", resp.lines().next().unwrap_or("no response"))))
+        }
+    }
+}
+
+/// Integers without a fraction, everything else as Rust prints it
+fn format_value(v: f64) -> String {
+    if v.fract() == 0.0 && v.abs() < 1e15 { format!("{}", v as i64) } else { format!("{v}") }
+}
@@ -0,0 +1,266 @@
+//! microtest — single-instruction CPU tests for differential comparison
+//!
+//! `generate_microtests()` builds randomized CPU states for one opcode,
+//! executes exactly that instruction on a fresh core and records the
+//! result. Records use the field names of the widely shared SM83
+//! single-step test files (`name`, `initial` / `final` with `pc`, `sp`,
+//! `a`..`l`, `ime`, `ie`, `ram: [[addr, value], ...]`), plus `ei` (EI
+//! still pending) and `t_cycles`, so another emulator's harness can load
+//! them, and `run_microtest()` checks records from elsewhere against
+//! gb-core.
+//!
+//! Unlike a flat 64 KiB test bus, gb-core has a real memory map, so states
+//! are constrained to it: the instruction sits in WRAM, every pointer it
+//! dereferences (BC / DE / HL, SP for stack ops, `[a16]`) points into WRAM
+//! and every `[a8]` / `[c]` into HRAM. Registers that are not dereferenced
+//! stay fully random. IE is 0, so no interrupt is dispatched. HALT, STOP
+//! and the illegal opcodes are not generated.
+//!
+//! Opcodes are `u16`s: 0x00-0xFF for the base table, 0xCB00-0xCBFF for the
+//! CB-prefixed ones.
+
+use crate::opcodes::{cb_mnemonic, OPCODES};
+use crate::meminit::Noise;
+use crate::{Cartridge, GbCore, Json, Registers, RomBuilder};
+
+/// Records `generate_microtests` writes per opcode by default
+pub const DEFAULT_MICROTESTS_PER_OP: usize = 1000;
+
+const WRAM: std::ops::RangeInclusive<u16> = 0xC000..=0xDFFF;
+const HRAM: std::ops::RangeInclusive<u16> = 0xFF80..=0xFFFE;
+
+/// CPU registers, interrupt flags and the memory bytes a test touches
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CpuState {
+    pub regs: Registers,
+    pub ime: bool,
+    /// EI executed, IME not yet set
+    pub ei: bool,
+    /// (address, value), ascending by address; WRAM and HRAM only
+    pub ram: Vec<(u16, u8)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MicroTest {
+    /// `"<opcode> <index>"`, e.g. `"3e 0007"` or `"cb 11 0042"`
+    pub name: String,
+    pub initial: CpuState,
+    pub final_state: CpuState,
+    pub t_cycles: u8,
+}
+
+/// Every opcode `generate_microtests` accepts: the base table minus HALT,
+/// STOP, the CB prefix and the illegal opcodes, then all 256 CB opcodes
+pub fn microtest_opcodes() -> Vec<u16> {
+    (0u16..0x100)
+        .filter(|&op| !matches!(op, 0x10 | 0x76 | 0xCB) && !OPCODES[op as usize].is_illegal())
+        .chain(0xCB00..=0xCBFF)
+        .collect()
+}
+
+/// Lowercase opcode bytes as used in test names and file names: `"3e"`,
+/// `"cb 11"`
+pub fn microtest_op_name(opcode: u16) -> String {
+    if opcode >= 0xCB00 { format!("cb {:02x}", opcode & 0xFF) } else { format!("{opcode:02x}") }
+}
+
+fn mnemonic(opcode: u16) -> String {
+    if opcode >= 0xCB00 { cb_mnemonic(opcode as u8) } else { OPCODES[opcode as usize].mnemonic.to_string() }
+}
+
+fn in_ram(addr: u16) -> bool { WRAM.contains(&addr) || HRAM.contains(&addr) }
+
+/// `count` random states for `opcode`, each run for one instruction.
+/// The same seed gives the same records.
+pub fn generate_microtests(opcode: u16, count: usize, seed: u64) -> Result<Vec<MicroTest>, String> {
+    if !microtest_opcodes().contains(&opcode) {
+        return Err(format!("opcode {} cannot be microtested", microtest_op_name(opcode)));
+    }
+    let m = mnemonic(opcode);
+    let stack = ["push", "pop", "call", "ret", "rst"].iter().any(|s| m.starts_with(s));
+    let mut noise = Noise::new(seed ^ (opcode as u64).wrapping_mul(0x9E37_79B9));
+    let mut core = microtest_core()?;
+    let mut tests = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut word = || noise.next() as u16;
+        let ram_word = |w: u16| 0xC000 + w % 0x1FFE;
+        let hram_low = |w: u16| 0x80 + (w % 0x7F) as u8;
+        let mut regs = Registers {
+            a: word() as u8, f: word() as u8 & 0xF0, b: word() as u8, c: word() as u8,
+            d: word() as u8, e: word() as u8, h: word() as u8, l: word() as u8,
+            sp: word(), pc: 0xC000 + word() % 0x1FFD,
+        };
+        if m.contains("[bc]") { regs.set_bc(ram_word(word())); }
+        if m.contains("[de]") { regs.set_de(ram_word(word())); }
+        if m.contains("[hl") { regs.set_hl(ram_word(word())); }
+        if m.contains("[c]") { regs.c = hram_low(word()); }
+        if stack { regs.sp = 0xC002 + word() % 0x1FFC; }
+
+        // Instruction bytes first: a pointer onto them reads the instruction
+        let mut bytes: Vec<u8> = match opcode {
+            0xCB00..=0xFFFF => vec![0xCB, opcode as u8],
+            op => {
+                let mut b = vec![op as u8];
+                b.extend((1..OPCODES[op as usize].len).map(|_| word() as u8));
+                b
+            }
+        };
+        if m.contains("[a16]") { bytes[1..3].copy_from_slice(&ram_word(word()).to_le_bytes()); }
+        if m.contains("[a8]") { bytes[1] = hram_low(word()); }
+        let mut ram: Vec<(u16, u8)> = bytes.iter().enumerate().map(|(k, &b)| (regs.pc.wrapping_add(k as u16), b)).collect();
+        let mut data = |addr: u16, ram: &mut Vec<(u16, u8)>| {
+            if !ram.iter().any(|&(a, _)| a == addr) { ram.push((addr, noise.next() as u8)); }
+        };
+        if m.contains("[bc]") { data(regs.bc(), &mut ram); }
+        if m.contains("[de]") { data(regs.de(), &mut ram); }
+        if m.contains("[hl") { data(regs.hl(), &mut ram); }
+        if m.contains("[c]") { data(0xFF00 | regs.c as u16, &mut ram); }
+        if m.contains("[a8]") { data(0xFF00 | bytes[1] as u16, &mut ram); }
+        if m.contains("[a16]") {
+            let addr = u16::from_le_bytes([bytes[1], bytes[2]]);
+            data(addr, &mut ram);
+            data(addr.wrapping_add(1), &mut ram);
+        }
+        if stack {
+            for addr in [regs.sp.wrapping_sub(2), regs.sp.wrapping_sub(1), regs.sp, regs.sp.wrapping_add(1)] { data(addr, &mut ram); }
+        }
+        ram.sort_unstable();
+        let ime = noise.next() & 1 != 0;
+        let initial = CpuState { regs, ime, ei: false, ram };
+        let (final_state, t_cycles) = execute(&mut core, &initial, &[])?;
+        tests.push(MicroTest { name: format!("{} {i:04}", microtest_op_name(opcode)), initial, final_state, t_cycles });
+    }
+    Ok(tests)
+}
+
+fn microtest_core() -> Result<GbCore, String> {
+    let cart = Cartridge::from_bytes(RomBuilder::new().title("MICROTEST").build()).map_err(|e| e.to_string())?;
+    Ok(GbCore::new(cart))
+}
+
+/// Run one instruction from `initial`: the final state (every initial and
+/// `report` address plus every WRAM / HRAM byte that changed) and its
+/// T-cycles. WRAM and HRAM are put back afterwards, so `core` can run the
+/// next state; with IE 0 the rest of its hardware never reaches the CPU.
+fn execute(core: &mut GbCore, initial: &CpuState, report: &[u16]) -> Result<(CpuState, u8), String> {
+    if let Some(&(addr, _)) = initial.ram.iter().find(|(a, _)| !in_ram(*a)) {
+        return Err(format!("address {addr:04X} is outside WRAM / HRAM"));
+    }
+    // WRAM.chain(HRAM) order
+    let snapshot = |core: &GbCore| -> Vec<u8> {
+        let bus = &core.bus;
+        [&bus.wram[0][..], &bus.wram[bus.wram_bank as usize][..], &bus.hram[..0x7F]].concat()
+    };
+    let pristine = snapshot(core);
+    core.regs = initial.regs.clone();
+    core.regs.f &= 0xF0;
+    (core.ime, core.ime_pending, core.halted, core.bus.ie) = (initial.ime, initial.ei, false, 0);
+    for &(addr, value) in &initial.ram { core.bus.write(addr, value); }
+    let before = snapshot(core);
+    let stepped = core.step().map_err(|e| e.to_string());
+    let after = snapshot(core);
+    for ((addr, &was), &now) in WRAM.chain(HRAM).zip(&pristine).zip(&after) {
+        if was != now { core.bus.write(addr, was); }
+    }
+    let t_cycles = stepped?;
+
+    let mut ram: Vec<(u16, u8)> = WRAM.chain(HRAM).zip(before.iter().zip(&after))
+        .filter(|(addr, (b, a))| b != a || report.contains(addr) || initial.ram.iter().any(|(x, _)| x == addr))
+        .map(|(addr, (_, &a))| (addr, a))
+        .collect();
+    ram.sort_unstable();
+    Ok((CpuState { regs: core.regs.clone(), ime: core.ime, ei: core.ime_pending, ram }, t_cycles))
+}
+
+/// Run `test` on gb-core; Err lists every field that differs from the
+/// recorded final state
+pub fn run_microtest(test: &MicroTest) -> Result<(), String> {
+    check(&mut microtest_core()?, test)
+}
+
+/// `run_microtest` for each of `tests`, in order, on one core
+pub fn run_microtests(tests: &[MicroTest]) -> Result<Vec<Result<(), String>>, String> {
+    let mut core = microtest_core()?;
+    Ok(tests.iter().map(|t| check(&mut core, t)).collect())
+}
+
+fn check(core: &mut GbCore, test: &MicroTest) -> Result<(), String> {
+    let report: Vec<u16> = test.final_state.ram.iter().map(|&(a, _)| a).collect();
+    let (got, t_cycles) = execute(core, &test.initial, &report).map_err(|e| format!("{}: {e}", test.name))?;
+    let want = &test.final_state;
+    let mut diffs = vec![];
+    let (g, w) = (&got.regs, &want.regs);
+    for (name, g, w) in [
+        ("pc", g.pc, w.pc), ("sp", g.sp, w.sp), ("a", g.a as u16, w.a as u16), ("f", g.f as u16, w.f as u16),
+        ("b", g.b as u16, w.b as u16), ("c", g.c as u16, w.c as u16), ("d", g.d as u16, w.d as u16),
+        ("e", g.e as u16, w.e as u16), ("h", g.h as u16, w.h as u16), ("l", g.l as u16, w.l as u16),
+        ("ime", got.ime as u16, want.ime as u16), ("t_cycles", t_cycles as u16, test.t_cycles as u16),
+    ] {
+        if g != w { diffs.push(format!("{name} {g:#x}, expected {w:#x}")); }
+    }
+    for &(addr, value) in &want.ram {
+        let g = got.ram.iter().find(|(a, _)| *a == addr).map(|&(_, v)| v);
+        if g != Some(value) { diffs.push(format!("[{addr:04X}] {g:02X?}, expected {value:02X}")); }
+    }
+    if diffs.is_empty() { Ok(()) } else { Err(format!("{}: {}", test.name, diffs.join(", "))) }
+}
+
+impl CpuState {
+    pub fn to_json(&self) -> String {
+        let r = &self.regs;
+        let ram: Vec<String> = self.ram.iter().map(|(a, v)| format!("[{a},{v}]")).collect();
+        format!(
+            "{{\"pc\":{},\"sp\":{},\"a\":{},\"b\":{},\"c\":{},\"d\":{},\"e\":{},\"f\":{},\"h\":{},\"l\":{},\"ime\":{},\"ei\":{},\"ie\":0,\"ram\":[{}]}}",
+            r.pc, r.sp, r.a, r.b, r.c, r.d, r.e, r.f, r.h, r.l, self.ime as u8, self.ei as u8, ram.join(",")
+        )
+    }
+
+    pub fn from_json(doc: &Json) -> Result<CpuState, String> {
+        let num = |k: &str| doc.get(k).and_then(Json::as_u64).ok_or_else(|| format!("missing {k}"));
+        let byte = |k: &str| num(k).and_then(|v| u8::try_from(v).map_err(|_| format!("{k} out of range")));
+        let word = |k: &str| num(k).and_then(|v| u16::try_from(v).map_err(|_| format!("{k} out of range")));
+        let regs = Registers {
+            a: byte("a")?, f: byte("f")?, b: byte("b")?, c: byte("c")?, d: byte("d")?,
+            e: byte("e")?, h: byte("h")?, l: byte("l")?, sp: word("sp")?, pc: word("pc")?,
+        };
+        if doc.get("ie").and_then(Json::as_u64).unwrap_or(0) != 0 { return Err("ie must be 0".into()); }
+        let flag = |k: &str| doc.get(k).and_then(Json::as_u64).unwrap_or(0) != 0;
+        let mut ram = vec![];
+        for pair in doc.get("ram").and_then(Json::as_array).ok_or("missing ram")? {
+            let (addr, value) = match pair.as_array().map(|p| p.iter().filter_map(Json::as_u64).collect::<Vec<_>>()).as_deref() {
+                Some(&[a, v]) if a <= 0xFFFF && v <= 0xFF => (a as u16, v as u8),
+                _ => return Err("ram entries are [address, value]".into()),
+            };
+            ram.push((addr, value));
+        }
+        ram.sort_unstable();
+        Ok(CpuState { regs, ime: flag("ime"), ei: flag("ei"), ram })
+    }
+}
+
+impl MicroTest {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"name\":\"{}\",\"initial\":{},\"final\":{},\"t_cycles\":{}}}",
+            self.name, self.initial.to_json(), self.final_state.to_json(), self.t_cycles
+        )
+    }
+
+    pub fn from_json(doc: &Json) -> Result<MicroTest, String> {
+        let name = doc.get("name").and_then(Json::as_str).ok_or("missing name")?.to_string();
+        let state = |k: &str| doc.get(k).ok_or(format!("{name}: missing {k}")).and_then(|s| CpuState::from_json(s).map_err(|e| format!("{name}: {e}")));
+        let t_cycles = doc.get("t_cycles").and_then(Json::as_u64).and_then(|v| u8::try_from(v).ok())
+            .ok_or(format!("{name}: missing t_cycles"))?;
+        Ok(MicroTest { initial: state("initial")?, final_state: state("final")?, t_cycles, name })
+    }
+}
+
+/// A JSON array of records, one per line
+pub fn microtests_json(tests: &[MicroTest]) -> String {
+    let lines: Vec<String> = tests.iter().map(MicroTest::to_json).collect();
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
+pub fn parse_microtests(doc: &Json) -> Result<Vec<MicroTest>, String> {
+    doc.as_array().ok_or("expected an array of microtests")?.iter().map(MicroTest::from_json).collect()
+}
@@ -0,0 +1,105 @@
+//! motion — per-frame motion metadata for frame interpolation
+//!
+//! A host presenting at 120 Hz or more shows each Game Boy frame at least
+//! twice. To synthesise the in-between pictures it needs to know what moved:
+//! the background scroll (SCX / SCY), the window position (WX / WY) and each
+//! sprite's OAM position. With `GbCore::motion` set, the core samples those at
+//! every VBlank and `FrameMotion` holds the deltas against the previous
+//! VBlank. Raster effects that change scroll mid-frame are not broken down;
+//! the values in effect when VBlank starts are what is compared.
+//!
+//! Hosts switch it on through the ABI `configure` options
+//! (`{"motion_metadata": true}`, see `GbCore::configure`) and read the last
+//! frame's motion from `diagnostics_json` under `"motion"`.
+
+use crate::Bus;
+
+/// One OAM entry that moved between frames (visible in both)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteMotion {
+    /// OAM index (0-39)
+    pub index: u8,
+    pub dx: i16,
+    pub dy: i16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameMotion {
+    /// `Clock::frame_count()` at the VBlank the frame ended on
+    pub frame: u64,
+    /// SCX / SCY change, wrapped to -128..=127. Positive is the view moving
+    /// right / down, i.e. the background sliding left / up.
+    pub scroll_dx: i8,
+    pub scroll_dy: i8,
+    pub window_dx: i16,
+    pub window_dy: i16,
+    /// Window enabled (LCDC bit 5) at this VBlank
+    pub window_visible: bool,
+    pub sprites: Vec<SpriteMotion>,
+}
+
+impl FrameMotion {
+    pub fn to_json(&self) -> String {
+        let sprites: Vec<String> = self.sprites.iter()
+            .map(|s| format!("{{\"index\":{},\"dx\":{},\"dy\":{}}}", s.index, s.dx, s.dy))
+            .collect();
+        format!("{{\"frame\":{},\"scroll_dx\":{},\"scroll_dy\":{},\"window_dx\":{},\"window_dy\":{},\"window_visible\":{},\"sprites\":[{}]}}",
+            self.frame, self.scroll_dx, self.scroll_dy, self.window_dx, self.window_dy, self.window_visible, sprites.join(","))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Snapshot {
+    scx: u8, scy: u8, wx: u8, wy: u8,
+    /// OAM (Y, X) per entry
+    oam: [(u8, u8); 40],
+}
+
+impl Snapshot {
+    fn take(bus: &Bus) -> Self {
+        let mut oam = [(0, 0); 40];
+        for (i, e) in oam.iter_mut().enumerate() { *e = (bus.oam[i * 4], bus.oam[i * 4 + 1]); }
+        let p = &bus.ppu;
+        Snapshot { scx: p.scx, scy: p.scy, wx: p.wx, wy: p.wy, oam }
+    }
+}
+
+/// An OAM (Y, X) pair that puts the sprite at least partly on screen
+fn on_screen((y, x): (u8, u8)) -> bool { (1..160).contains(&y) && (1..168).contains(&x) }
+
+/// Samples scroll, window and OAM positions at each VBlank
+#[derive(Debug, Clone, Default)]
+pub struct MotionTracker {
+    prev: Option<Snapshot>,
+    last: Option<FrameMotion>,
+}
+
+impl MotionTracker {
+    pub fn new() -> Self { Self::default() }
+    /// Motion of the most recent frame; None until two VBlanks were sampled
+    pub fn last(&self) -> Option<&FrameMotion> { self.last.as_ref() }
+
+    pub(crate) fn sample(&mut self, bus: &Bus, frame: u64) {
+        let now = Snapshot::take(bus);
+        if let Some(prev) = self.prev {
+            let sprites = (0..40)
+                .filter(|&i| on_screen(prev.oam[i]) && on_screen(now.oam[i]) && prev.oam[i] != now.oam[i])
+                .map(|i| SpriteMotion {
+                    index: i as u8,
+                    dx: now.oam[i].1 as i16 - prev.oam[i].1 as i16,
+                    dy: now.oam[i].0 as i16 - prev.oam[i].0 as i16,
+                })
+                .collect();
+            self.last = Some(FrameMotion {
+                frame,
+                scroll_dx: now.scx.wrapping_sub(prev.scx) as i8,
+                scroll_dy: now.scy.wrapping_sub(prev.scy) as i8,
+                window_dx: now.wx as i16 - prev.wx as i16,
+                window_dy: now.wy as i16 - prev.wy as i16,
+                window_visible: bus.ppu.lcdc & 0x20 != 0,
+                sprites,
+            });
+        }
+        self.prev = Some(now);
+    }
+}
@@ -0,0 +1,76 @@
+//! oam_dma — FF46 OAM DMA as a 160 M-cycle background transfer
+//!
+//! Writing FF46 starts a copy of XX00-XX9F into OAM, one byte per M-cycle
+//! after a 1 M-cycle start-up delay. While bytes are being copied the CPU
+//! is cut off from everything below FF00: reads return 0xFF and writes are
+//! dropped, which is why games run their DMA wait loop from HRAM. The IO
+//! registers, HRAM and IE stay reachable (a second FF46 write restarts the
+//! transfer; the old one keeps the bus through the new one's delay).
+//!
+//! The transfer runs at CPU speed, so it takes half as long in CGB double
+//! speed. Sources at E000 and above read the WRAM echo (FE00 copies DE00).
+
+/// Bytes copied per transfer
+pub const OAM_DMA_LEN: u8 = 0xA0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OamDma {
+    /// Last value written to FF46 (reads back)
+    pub reg: u8,
+    /// Source page of the running transfer
+    pub src: u8,
+    /// Next byte to copy; `OAM_DMA_LEN` when idle
+    pub next: u8,
+    /// M-cycles until a requested transfer starts (0: none requested)
+    pub delay: u8,
+    /// FF46 was written by the instruction now finishing; its delay starts
+    /// with the next M-cycle
+    pending: bool,
+    /// T-cycles short of a whole M-cycle
+    carry: u8,
+}
+
+impl Default for OamDma {
+    fn default() -> Self { OamDma { reg: 0xFF, src: 0, next: OAM_DMA_LEN, delay: 0, pending: false, carry: 0 } }
+}
+
+impl OamDma {
+    pub fn new() -> Self { Self::default() }
+
+    /// Bytes are being copied and the CPU is cut off from the bus
+    pub fn active(&self) -> bool { self.next < OAM_DMA_LEN }
+
+    /// The CPU cannot reach `addr` right now
+    pub fn blocks(&self, addr: u16) -> bool { self.active() && addr < 0xFF00 }
+
+    /// Every field, private ones included, for `state_hash`
+    pub(crate) fn state_bytes(&self) -> [u8; 6] {
+        [self.reg, self.src, self.next, self.delay, self.pending as u8, self.carry]
+    }
+
+    pub(crate) fn start(&mut self, val: u8) {
+        self.reg = val;
+        self.pending = true;
+    }
+
+    /// Advance by `cycles` CPU T-cycles, calling `copy(source address, OAM
+    /// index)` for each byte due
+    pub(crate) fn step(&mut self, cycles: u8, mut copy: impl FnMut(u16, u8)) {
+        if !self.active() && self.delay == 0 && !self.pending { return; }
+        let total = self.carry + cycles;
+        self.carry = total % 4;
+        for _ in 0..total / 4 {
+            if self.active() {
+                let base = (self.src as u16) << 8;
+                let base = if base >= 0xE000 { base - 0x2000 } else { base };
+                copy(base + self.next as u16, self.next);
+                self.next += 1;
+            }
+            if self.delay > 0 {
+                self.delay -= 1;
+                if self.delay == 0 { self.src = self.reg; self.next = 0; }
+            }
+        }
+        if std::mem::take(&mut self.pending) { self.delay = 1; }
+    }
+}
@@ -0,0 +1,96 @@
+//! oam_scan — mode 2 sprite selection for the line being drawn
+//!
+//! For the first 80 dots of a visible line the PPU walks OAM, two dots per
+//! entry, in index order, and copies the first 10 entries whose Y range
+//! covers the line into its sprite buffer; mode 3 draws those and no others.
+//! X plays no part in the selection, so a sprite parked off screen (X 0 or
+//! X ≥ 168) still uses up one of the line's 10 slots. The sprite height
+//! (LCDC bit 2) is read as each entry is checked.
+//!
+//! `Ppu::step` runs the scan up to the current dot, so OAM changed while it
+//! is under way (an OAM DMA started late in HBlank, say) is seen by the
+//! entries checked after the change, as on hardware. Mode 3 reads tile and
+//! attributes from OAM when it draws; Y and X come from the buffer. Among
+//! the selected sprites the one with the lower X wins, then the lower OAM
+//! index (DMG priority); in CGB mode the lower OAM index alone.
+
+/// Sprites one line can show
+pub const SPRITES_PER_LINE: usize = 10;
+/// Dots the scan spends on each OAM entry
+pub const OAM_SCAN_DOTS_PER_ENTRY: u32 = 2;
+
+const OAM_ENTRIES: u8 = 40;
+
+/// One sprite buffer slot: OAM index with the Y and X it was selected with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScannedSprite {
+    pub index: u8,
+    pub y: u8,
+    pub x: u8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OamScan {
+    /// Next OAM entry to check; 40 once the scan is complete
+    pub next: u8,
+    buf: [ScannedSprite; SPRITES_PER_LINE],
+    len: u8,
+}
+
+impl OamScan {
+    pub fn new() -> Self { Self::default() }
+
+    /// Scan all of OAM for line `ly` at once
+    pub fn line(oam: &[u8; 0xA0], ly: u8, tall: bool) -> Self {
+        let mut scan = OamScan::new();
+        scan.run(oam, ly, tall, OAM_ENTRIES as u32 * OAM_SCAN_DOTS_PER_ENTRY);
+        scan
+    }
+
+    /// Empty the buffer for a new line (start of mode 2)
+    pub fn restart(&mut self) { *self = OamScan::new(); }
+
+    /// Check every entry the scan has reached `dot` dots into mode 2, for
+    /// line `ly` with 8x16 sprites when `tall`
+    pub fn run(&mut self, oam: &[u8; 0xA0], ly: u8, tall: bool, dot: u32) {
+        let reached = (dot / OAM_SCAN_DOTS_PER_ENTRY).min(OAM_ENTRIES as u32) as u8;
+        let height = if tall { 16 } else { 8 };
+        while self.next < reached {
+            let entry = &oam[self.next as usize * 4..self.next as usize * 4 + 2];
+            let (y, x) = (entry[0], entry[1]);
+            // Screen row 0 is OAM Y 16
+            let line = ly as u16 + 16;
+            if (self.len as usize) < SPRITES_PER_LINE && line >= y as u16 && line < y as u16 + height {
+                self.buf[self.len as usize] = ScannedSprite { index: self.next, y, x };
+                self.len += 1;
+            }
+            self.next += 1;
+        }
+    }
+
+    pub fn done(&self) -> bool { self.next >= OAM_ENTRIES }
+
+    /// Selected sprites, in OAM order
+    pub fn sprites(&self) -> &[ScannedSprite] { &self.buf[..self.len as usize] }
+
+    /// Every field, for `state_hash` and save states
+    pub(crate) fn state_bytes(&self) -> [u8; 2 + SPRITES_PER_LINE * 3] {
+        let mut out = [0u8; 2 + SPRITES_PER_LINE * 3];
+        out[0] = self.next;
+        out[1] = self.len;
+        for (slot, s) in out[2..].chunks_exact_mut(3).zip(&self.buf) {
+            slot.copy_from_slice(&[s.index, s.y, s.x]);
+        }
+        out
+    }
+
+    /// Inverse of `state_bytes`; None for a malformed buffer
+    pub(crate) fn from_state_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 2 + SPRITES_PER_LINE * 3 || bytes[0] > OAM_ENTRIES || bytes[1] as usize > SPRITES_PER_LINE { return None; }
+        let mut scan = OamScan { next: bytes[0], len: bytes[1], ..OamScan::new() };
+        for (s, slot) in scan.buf.iter_mut().zip(bytes[2..].chunks_exact(3)) {
+            *s = ScannedSprite { index: slot[0], y: slot[1], x: slot[2] };
+        }
+        Some(scan)
+    }
+}
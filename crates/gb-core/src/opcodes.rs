@@ -0,0 +1,378 @@
+//! opcodes — the SM83 opcode table
+//!
+//! One entry per opcode: mnemonic, length, cycles (taken and not-taken for
+//! conditional branches) and the kind of immediate operand. The executor
+//! takes lengths and base cycles from it, and the disassembler and the
+//! instruction trace take mnemonics from it. CB-prefixed opcodes are
+//! regular enough to decode from their bits (`cb_mnemonic`).
+//!
+//! Mnemonics are RGBDS-flavoured, as `asm.rs` reads them: `disassemble`
+//! output assembles back to the same bytes.
+
+/// The immediate operand following an opcode, as it appears in the mnemonic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    None,
+    /// `n8`: 8-bit value
+    N8,
+    /// `n16`: 16-bit value
+    N16,
+    /// `a8`: FF00 + 8-bit offset (LDH)
+    A8,
+    /// `a16`: 16-bit address
+    A16,
+    /// `e8`: signed offset from the address after the instruction (JR)
+    Rel,
+    /// `e8`: signed offset added to SP
+    E8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpInfo {
+    /// Empty for the illegal opcodes (`ILLEGAL_OPCODES`)
+    pub mnemonic: &'static str,
+    /// Bytes including the opcode (STOP's second byte included)
+    pub len: u8,
+    /// T-cycles; for conditional branches, when not taken
+    pub cycles: u8,
+    /// T-cycles when a conditional branch is taken (`cycles` otherwise)
+    pub cycles_taken: u8,
+    pub operand: OperandKind,
+}
+
+impl OpInfo {
+    pub fn is_illegal(&self) -> bool { self.mnemonic.is_empty() }
+    pub fn is_conditional(&self) -> bool { self.cycles_taken != self.cycles }
+}
+
+const fn op(mnemonic: &'static str, len: u8, cycles: u8, operand: OperandKind) -> OpInfo {
+    OpInfo { mnemonic, len, cycles, cycles_taken: cycles, operand }
+}
+const fn branch(mnemonic: &'static str, len: u8, cycles: u8, cycles_taken: u8, operand: OperandKind) -> OpInfo {
+    OpInfo { mnemonic, len, cycles, cycles_taken, operand }
+}
+/// Hangs the CPU (see `GbCore::locked`)
+const ILLEGAL: OpInfo = op("", 1, 4, OperandKind::None);
+
+use OperandKind::*;
+
+pub static OPCODES: [OpInfo; 256] = [
+    op("nop", 1, 4, None),                    // 00
+    op("ld bc, n16", 3, 12, N16),             // 01
+    op("ld [bc], a", 1, 8, None),             // 02
+    op("inc bc", 1, 8, None),                 // 03
+    op("inc b", 1, 4, None),                  // 04
+    op("dec b", 1, 4, None),                  // 05
+    op("ld b, n8", 2, 8, N8),                 // 06
+    op("rlca", 1, 4, None),                   // 07
+    op("ld [a16], sp", 3, 20, A16),           // 08
+    op("add hl, bc", 1, 8, None),             // 09
+    op("ld a, [bc]", 1, 8, None),             // 0A
+    op("dec bc", 1, 8, None),                 // 0B
+    op("inc c", 1, 4, None),                  // 0C
+    op("dec c", 1, 4, None),                  // 0D
+    op("ld c, n8", 2, 8, N8),                 // 0E
+    op("rrca", 1, 4, None),                   // 0F
+    op("stop", 2, 4, None),                   // 10
+    op("ld de, n16", 3, 12, N16),             // 11
+    op("ld [de], a", 1, 8, None),             // 12
+    op("inc de", 1, 8, None),                 // 13
+    op("inc d", 1, 4, None),                  // 14
+    op("dec d", 1, 4, None),                  // 15
+    op("ld d, n8", 2, 8, N8),                 // 16
+    op("rla", 1, 4, None),                    // 17
+    op("jr e8", 2, 12, Rel),                  // 18
+    op("add hl, de", 1, 8, None),             // 19
+    op("ld a, [de]", 1, 8, None),             // 1A
+    op("dec de", 1, 8, None),                 // 1B
+    op("inc e", 1, 4, None),                  // 1C
+    op("dec e", 1, 4, None),                  // 1D
+    op("ld e, n8", 2, 8, N8),                 // 1E
+    op("rra", 1, 4, None),                    // 1F
+    branch("jr nz, e8", 2, 8, 12, Rel),       // 20
+    op("ld hl, n16", 3, 12, N16),             // 21
+    op("ld [hl+], a", 1, 8, None),            // 22
+    op("inc hl", 1, 8, None),                 // 23
+    op("inc h", 1, 4, None),                  // 24
+    op("dec h", 1, 4, None),                  // 25
+    op("ld h, n8", 2, 8, N8),                 // 26
+    op("daa", 1, 4, None),                    // 27
+    branch("jr z, e8", 2, 8, 12, Rel),        // 28
+    op("add hl, hl", 1, 8, None),             // 29
+    op("ld a, [hl+]", 1, 8, None),            // 2A
+    op("dec hl", 1, 8, None),                 // 2B
+    op("inc l", 1, 4, None),                  // 2C
+    op("dec l", 1, 4, None),                  // 2D
+    op("ld l, n8", 2, 8, N8),                 // 2E
+    op("cpl", 1, 4, None),                    // 2F
+    branch("jr nc, e8", 2, 8, 12, Rel),       // 30
+    op("ld sp, n16", 3, 12, N16),             // 31
+    op("ld [hl-], a", 1, 8, None),            // 32
+    op("inc sp", 1, 8, None),                 // 33
+    op("inc [hl]", 1, 12, None),              // 34
+    op("dec [hl]", 1, 12, None),              // 35
+    op("ld [hl], n8", 2, 12, N8),             // 36
+    op("scf", 1, 4, None),                    // 37
+    branch("jr c, e8", 2, 8, 12, Rel),        // 38
+    op("add hl, sp", 1, 8, None),             // 39
+    op("ld a, [hl-]", 1, 8, None),            // 3A
+    op("dec sp", 1, 8, None),                 // 3B
+    op("inc a", 1, 4, None),                  // 3C
+    op("dec a", 1, 4, None),                  // 3D
+    op("ld a, n8", 2, 8, N8),                 // 3E
+    op("ccf", 1, 4, None),                    // 3F
+    op("ld b, b", 1, 4, None),                // 40
+    op("ld b, c", 1, 4, None),                // 41
+    op("ld b, d", 1, 4, None),                // 42
+    op("ld b, e", 1, 4, None),                // 43
+    op("ld b, h", 1, 4, None),                // 44
+    op("ld b, l", 1, 4, None),                // 45
+    op("ld b, [hl]", 1, 8, None),             // 46
+    op("ld b, a", 1, 4, None),                // 47
+    op("ld c, b", 1, 4, None),                // 48
+    op("ld c, c", 1, 4, None),                // 49
+    op("ld c, d", 1, 4, None),                // 4A
+    op("ld c, e", 1, 4, None),                // 4B
+    op("ld c, h", 1, 4, None),                // 4C
+    op("ld c, l", 1, 4, None),                // 4D
+    op("ld c, [hl]", 1, 8, None),             // 4E
+    op("ld c, a", 1, 4, None),                // 4F
+    op("ld d, b", 1, 4, None),                // 50
+    op("ld d, c", 1, 4, None),                // 51
+    op("ld d, d", 1, 4, None),                // 52
+    op("ld d, e", 1, 4, None),                // 53
+    op("ld d, h", 1, 4, None),                // 54
+    op("ld d, l", 1, 4, None),                // 55
+    op("ld d, [hl]", 1, 8, None),             // 56
+    op("ld d, a", 1, 4, None),                // 57
+    op("ld e, b", 1, 4, None),                // 58
+    op("ld e, c", 1, 4, None),                // 59
+    op("ld e, d", 1, 4, None),                // 5A
+    op("ld e, e", 1, 4, None),                // 5B
+    op("ld e, h", 1, 4, None),                // 5C
+    op("ld e, l", 1, 4, None),                // 5D
+    op("ld e, [hl]", 1, 8, None),             // 5E
+    op("ld e, a", 1, 4, None),                // 5F
+    op("ld h, b", 1, 4, None),                // 60
+    op("ld h, c", 1, 4, None),                // 61
+    op("ld h, d", 1, 4, None),                // 62
+    op("ld h, e", 1, 4, None),                // 63
+    op("ld h, h", 1, 4, None),                // 64
+    op("ld h, l", 1, 4, None),                // 65
+    op("ld h, [hl]", 1, 8, None),             // 66
+    op("ld h, a", 1, 4, None),                // 67
+    op("ld l, b", 1, 4, None),                // 68
+    op("ld l, c", 1, 4, None),                // 69
+    op("ld l, d", 1, 4, None),                // 6A
+    op("ld l, e", 1, 4, None),                // 6B
+    op("ld l, h", 1, 4, None),                // 6C
+    op("ld l, l", 1, 4, None),                // 6D
+    op("ld l, [hl]", 1, 8, None),             // 6E
+    op("ld l, a", 1, 4, None),                // 6F
+    op("ld [hl], b", 1, 8, None),             // 70
+    op("ld [hl], c", 1, 8, None),             // 71
+    op("ld [hl], d", 1, 8, None),             // 72
+    op("ld [hl], e", 1, 8, None),             // 73
+    op("ld [hl], h", 1, 8, None),             // 74
+    op("ld [hl], l", 1, 8, None),             // 75
+    op("halt", 1, 4, None),                   // 76
+    op("ld [hl], a", 1, 8, None),             // 77
+    op("ld a, b", 1, 4, None),                // 78
+    op("ld a, c", 1, 4, None),                // 79
+    op("ld a, d", 1, 4, None),                // 7A
+    op("ld a, e", 1, 4, None),                // 7B
+    op("ld a, h", 1, 4, None),                // 7C
+    op("ld a, l", 1, 4, None),                // 7D
+    op("ld a, [hl]", 1, 8, None),             // 7E
+    op("ld a, a", 1, 4, None),                // 7F
+    op("add a, b", 1, 4, None),               // 80
+    op("add a, c", 1, 4, None),               // 81
+    op("add a, d", 1, 4, None),               // 82
+    op("add a, e", 1, 4, None),               // 83
+    op("add a, h", 1, 4, None),               // 84
+    op("add a, l", 1, 4, None),               // 85
+    op("add a, [hl]", 1, 8, None),            // 86
+    op("add a, a", 1, 4, None),               // 87
+    op("adc a, b", 1, 4, None),               // 88
+    op("adc a, c", 1, 4, None),               // 89
+    op("adc a, d", 1, 4, None),               // 8A
+    op("adc a, e", 1, 4, None),               // 8B
+    op("adc a, h", 1, 4, None),               // 8C
+    op("adc a, l", 1, 4, None),               // 8D
+    op("adc a, [hl]", 1, 8, None),            // 8E
+    op("adc a, a", 1, 4, None),               // 8F
+    op("sub b", 1, 4, None),                  // 90
+    op("sub c", 1, 4, None),                  // 91
+    op("sub d", 1, 4, None),                  // 92
+    op("sub e", 1, 4, None),                  // 93
+    op("sub h", 1, 4, None),                  // 94
+    op("sub l", 1, 4, None),                  // 95
+    op("sub [hl]", 1, 8, None),               // 96
+    op("sub a", 1, 4, None),                  // 97
+    op("sbc a, b", 1, 4, None),               // 98
+    op("sbc a, c", 1, 4, None),               // 99
+    op("sbc a, d", 1, 4, None),               // 9A
+    op("sbc a, e", 1, 4, None),               // 9B
+    op("sbc a, h", 1, 4, None),               // 9C
+    op("sbc a, l", 1, 4, None),               // 9D
+    op("sbc a, [hl]", 1, 8, None),            // 9E
+    op("sbc a, a", 1, 4, None),               // 9F
+    op("and b", 1, 4, None),                  // A0
+    op("and c", 1, 4, None),                  // A1
+    op("and d", 1, 4, None),                  // A2
+    op("and e", 1, 4, None),                  // A3
+    op("and h", 1, 4, None),                  // A4
+    op("and l", 1, 4, None),                  // A5
+    op("and [hl]", 1, 8, None),               // A6
+    op("and a", 1, 4, None),                  // A7
+    op("xor b", 1, 4, None),                  // A8
+    op("xor c", 1, 4, None),                  // A9
+    op("xor d", 1, 4, None),                  // AA
+    op("xor e", 1, 4, None),                  // AB
+    op("xor h", 1, 4, None),                  // AC
+    op("xor l", 1, 4, None),                  // AD
+    op("xor [hl]", 1, 8, None),               // AE
+    op("xor a", 1, 4, None),                  // AF
+    op("or b", 1, 4, None),                   // B0
+    op("or c", 1, 4, None),                   // B1
+    op("or d", 1, 4, None),                   // B2
+    op("or e", 1, 4, None),                   // B3
+    op("or h", 1, 4, None),                   // B4
+    op("or l", 1, 4, None),                   // B5
+    op("or [hl]", 1, 8, None),                // B6
+    op("or a", 1, 4, None),                   // B7
+    op("cp b", 1, 4, None),                   // B8
+    op("cp c", 1, 4, None),                   // B9
+    op("cp d", 1, 4, None),                   // BA
+    op("cp e", 1, 4, None),                   // BB
+    op("cp h", 1, 4, None),                   // BC
+    op("cp l", 1, 4, None),                   // BD
+    op("cp [hl]", 1, 8, None),                // BE
+    op("cp a", 1, 4, None),                   // BF
+    branch("ret nz", 1, 8, 20, None),         // C0
+    op("pop bc", 1, 12, None),                // C1
+    branch("jp nz, a16", 3, 12, 16, A16),     // C2
+    op("jp a16", 3, 16, A16),                 // C3
+    branch("call nz, a16", 3, 12, 24, A16),   // C4
+    op("push bc", 1, 16, None),               // C5
+    op("add a, n8", 2, 8, N8),                // C6
+    op("rst $00", 1, 16, None),               // C7
+    branch("ret z", 1, 8, 20, None),          // C8
+    op("ret", 1, 16, None),                   // C9
+    branch("jp z, a16", 3, 12, 16, A16),      // CA
+    op("prefix cb", 2, 8, None),              // CB
+    branch("call z, a16", 3, 12, 24, A16),    // CC
+    op("call a16", 3, 24, A16),               // CD
+    op("adc a, n8", 2, 8, N8),                // CE
+    op("rst $08", 1, 16, None),               // CF
+    branch("ret nc", 1, 8, 20, None),         // D0
+    op("pop de", 1, 12, None),                // D1
+    branch("jp nc, a16", 3, 12, 16, A16),     // D2
+    ILLEGAL,                                  // D3
+    branch("call nc, a16", 3, 12, 24, A16),   // D4
+    op("push de", 1, 16, None),               // D5
+    op("sub n8", 2, 8, N8),                   // D6
+    op("rst $10", 1, 16, None),               // D7
+    branch("ret c", 1, 8, 20, None),          // D8
+    op("reti", 1, 16, None),                  // D9
+    branch("jp c, a16", 3, 12, 16, A16),      // DA
+    ILLEGAL,                                  // DB
+    branch("call c, a16", 3, 12, 24, A16),    // DC
+    ILLEGAL,                                  // DD
+    op("sbc a, n8", 2, 8, N8),                // DE
+    op("rst $18", 1, 16, None),               // DF
+    op("ldh [a8], a", 2, 12, A8),             // E0
+    op("pop hl", 1, 12, None),                // E1
+    op("ldh [c], a", 1, 8, None),             // E2
+    ILLEGAL,                                  // E3
+    ILLEGAL,                                  // E4
+    op("push hl", 1, 16, None),               // E5
+    op("and n8", 2, 8, N8),                   // E6
+    op("rst $20", 1, 16, None),               // E7
+    op("add sp, e8", 2, 16, E8),              // E8
+    op("jp hl", 1, 4, None),                  // E9
+    op("ld [a16], a", 3, 16, A16),            // EA
+    ILLEGAL,                                  // EB
+    ILLEGAL,                                  // EC
+    ILLEGAL,                                  // ED
+    op("xor n8", 2, 8, N8),                   // EE
+    op("rst $28", 1, 16, None),               // EF
+    op("ldh a, [a8]", 2, 12, A8),             // F0
+    op("pop af", 1, 12, None),                // F1
+    op("ldh a, [c]", 1, 8, None),             // F2
+    op("di", 1, 4, None),                     // F3
+    ILLEGAL,                                  // F4
+    op("push af", 1, 16, None),               // F5
+    op("or n8", 2, 8, N8),                    // F6
+    op("rst $30", 1, 16, None),               // F7
+    op("ld hl, sp+e8", 2, 12, E8),            // F8
+    op("ld sp, hl", 1, 8, None),              // F9
+    op("ld a, [a16]", 3, 16, A16),            // FA
+    op("ei", 1, 4, None),                     // FB
+    ILLEGAL,                                  // FC
+    ILLEGAL,                                  // FD
+    op("cp n8", 2, 8, N8),                    // FE
+    op("rst $38", 1, 16, None),               // FF
+
+];
+
+const R8: [&str; 8] = ["b", "c", "d", "e", "h", "l", "[hl]", "a"];
+
+/// Mnemonic of the CB-prefixed opcode `cb`
+pub fn cb_mnemonic(cb: u8) -> String {
+    const SHIFTS: [&str; 8] = ["rlc", "rrc", "rl", "rr", "sla", "sra", "swap", "srl"];
+    let (r, n) = (R8[(cb & 7) as usize], (cb >> 3) & 7);
+    match cb >> 6 {
+        0 => format!("{} {r}", SHIFTS[n as usize]),
+        1 => format!("bit {n}, {r}"),
+        2 => format!("res {n}, {r}"),
+        _ => format!("set {n}, {r}"),
+    }
+}
+
+/// T-cycles of the CB-prefixed opcode `cb`, prefix included
+pub fn cb_cycles(cb: u8) -> u8 {
+    match (cb & 7, cb >> 6) { (6, 1) => 12, (6, _) => 16, _ => 8 }
+}
+
+/// Disassemble the instruction whose bytes start `bytes` (missing bytes
+/// read as 0) at address `pc`; returns the text and the length. Illegal
+/// opcodes come out as `db $XX`.
+pub fn disassemble(bytes: &[u8], pc: u16) -> (String, u8) {
+    let byte = |i: usize| bytes.get(i).copied().unwrap_or(0);
+    let info = &OPCODES[byte(0) as usize];
+    if info.is_illegal() { return (format!("db ${:02x}", byte(0)), 1); }
+    if byte(0) == 0xCB { return (cb_mnemonic(byte(1)), 2); }
+    let n16 = u16::from_le_bytes([byte(1), byte(2)]);
+    let text = match info.operand {
+        None => info.mnemonic.to_string(),
+        N8 => info.mnemonic.replace("n8", &format!("${:02x}", byte(1))),
+        N16 => info.mnemonic.replace("n16", &format!("${n16:04x}")),
+        A8 => info.mnemonic.replace("a8", &format!("$ff{:02x}", byte(1))),
+        A16 => info.mnemonic.replace("a16", &format!("${n16:04x}")),
+        Rel => {
+            let target = pc.wrapping_add(2).wrapping_add(byte(1) as i8 as u16);
+            info.mnemonic.replace("e8", &format!("${target:04x}"))
+        }
+        E8 => {
+            let e = byte(1) as i8;
+            let text = format!("{}{}", if e < 0 { "-" } else { "+" }, e.unsigned_abs());
+            info.mnemonic.replace("+e8", &text).replace("e8", &e.to_string())
+        }
+    };
+    (text, info.len)
+}
+
+/// Disassemble `count` instructions from `pc`, reading memory through
+/// `read`: (address, bytes, text) per instruction
+pub fn disassemble_range(read: impl Fn(u16) -> u8, pc: u16, count: usize) -> Vec<(u16, Vec<u8>, String)> {
+    let mut out = Vec::with_capacity(count);
+    let mut at = pc;
+    for _ in 0..count {
+        let bytes: Vec<u8> = (0..3).map(|i| read(at.wrapping_add(i))).collect();
+        let (text, len) = disassemble(&bytes, at);
+        out.push((at, bytes[..len as usize].to_vec(), text));
+        at = at.wrapping_add(len as u16);
+    }
+    out
+}
@@ -0,0 +1,55 @@
+//! open_bus — what reads of the prohibited region and unmapped IO return
+//!
+//! Nothing answers reads of FEA0-FEFF or of IO addresses without a register
+//! (FF08-FF0E, FF4C, FF71-FF7F, ...). gb-core has always read them as 0xFF;
+//! `Bus::open_bus` picks the behaviour instead:
+//!
+//! - `AllFF` (default): 0xFF everywhere, what a DMG returns for unmapped IO.
+//! - `LastValue`: the last byte the CPU moved over the data bus, read or
+//!   written, as a floating bus would hold it. Code that leans on open-bus
+//!   values then sees whatever it last touched, which is useful for fuzzing.
+//!   The block cache is bypassed in this mode, since its fetches skip the bus.
+//! - `CgbBehavior`: FEA0-FEFF as CGB revision E and AGB return it, the high
+//!   nibble of the address's low byte twice (FEA5 → 0xAA), but 0xFF while
+//!   the PPU has OAM (modes 2 and 3). Unmapped IO reads 0xFF.
+//!
+//! Writes to both ranges are dropped under every policy.
+
+/// Open-bus read behaviour
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpenBusPolicy {
+    #[default]
+    AllFF,
+    LastValue,
+    CgbBehavior,
+}
+
+impl OpenBusPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self { OpenBusPolicy::AllFF => "ff", OpenBusPolicy::LastValue => "last", OpenBusPolicy::CgbBehavior => "cgb" }
+    }
+    pub fn parse(s: &str) -> Option<OpenBusPolicy> {
+        match s {
+            "ff" => Some(OpenBusPolicy::AllFF),
+            "last" => Some(OpenBusPolicy::LastValue),
+            "cgb" => Some(OpenBusPolicy::CgbBehavior),
+            _ => None,
+        }
+    }
+
+    /// Value read at `addr` (FEA0-FEFF or unmapped IO) given the last data
+    /// bus value and whether the PPU currently holds OAM
+    pub fn read(self, addr: u16, last: u8, oam_blocked: bool) -> u8 {
+        match self {
+            OpenBusPolicy::AllFF => 0xFF,
+            OpenBusPolicy::LastValue => last,
+            OpenBusPolicy::CgbBehavior => match addr {
+                0xFEA0..=0xFEFF if !oam_blocked => {
+                    let nibble = (addr as u8) >> 4;
+                    nibble << 4 | nibble
+                }
+                _ => 0xFF,
+            },
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! overlay — RGBA annotation layer composited over the RGB framebuffer
+//!
+//! Scripts, the debugger and training hosts draw diagnostics onto an
+//! `Overlay` (text, boxes around sprites, watched values) and set it as
+//! `GbCore::overlay`; `framebuffer_rgb_annotated()` blends it over
+//! `framebuffer_rgb()` on demand, so captures and live streams (the serve
+//! `/screenshot.png`) carry the annotations without an external compositor.
+//!
+//! The overlay is host state: it never touches the emulated framebuffer,
+//! savestates, replays or `state_hash`, and stays drawn across frames until
+//! `clear()` (a host that annotates live state clears and redraws after each
+//! `run_frame`). Colours are straight (not premultiplied) RGBA; alpha 255
+//! covers the pixel, 0 leaves it alone. Text uses a built-in 3×5 font on a
+//! 4×6 cell; letters are drawn upper case, and characters it lacks draw as
+//! `?`. Everything is clipped to the 160×144 screen, so coordinates may be
+//! negative or run past the edges.
+
+use crate::{VisibleSprite, LCD_HEIGHT, LCD_WIDTH};
+
+/// Straight-alpha colour, `[r, g, b, a]`
+pub type Rgba = [u8; 4];
+
+/// Horizontal advance of one character
+pub const OVERLAY_CHAR_W: i32 = 4;
+/// Line height of one row of text
+pub const OVERLAY_LINE_H: i32 = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlay {
+    /// 160×144 RGBA, row-major
+    pixels: Vec<u8>,
+    /// Some pixel is not fully transparent
+    drawn: bool,
+}
+
+impl Default for Overlay {
+    fn default() -> Self { Overlay { pixels: vec![0; LCD_WIDTH * LCD_HEIGHT * 4], drawn: false } }
+}
+
+impl Overlay {
+    pub fn new() -> Self { Self::default() }
+
+    /// Make every pixel transparent again
+    pub fn clear(&mut self) {
+        if self.drawn { self.pixels.fill(0); }
+        self.drawn = false;
+    }
+    /// Nothing drawn since the last `clear`
+    pub fn is_empty(&self) -> bool { !self.drawn }
+    /// The RGBA buffer, 160×144×4 bytes
+    pub fn rgba(&self) -> &[u8] { &self.pixels }
+
+    pub fn pixel(&self, x: i32, y: i32) -> Option<Rgba> {
+        let i = Self::index(x, y)?;
+        self.pixels[i..i + 4].try_into().ok()
+    }
+    /// Set one pixel (replacing what the overlay held there)
+    pub fn set(&mut self, x: i32, y: i32, color: Rgba) {
+        let Some(i) = Self::index(x, y) else { return };
+        self.pixels[i..i + 4].copy_from_slice(&color);
+        self.drawn |= color[3] != 0;
+    }
+    fn index(x: i32, y: i32) -> Option<usize> {
+        let inside = (0..LCD_WIDTH as i32).contains(&x) && (0..LCD_HEIGHT as i32).contains(&y);
+        inside.then(|| (y as usize * LCD_WIDTH + x as usize) * 4)
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: Rgba) {
+        for py in y.max(0)..(y + h).min(LCD_HEIGHT as i32) {
+            for px in x.max(0)..(x + w).min(LCD_WIDTH as i32) { self.set(px, py, color); }
+        }
+    }
+    /// One-pixel outline of the `w`×`h` box at (`x`, `y`)
+    pub fn rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: Rgba) {
+        if w <= 0 || h <= 0 { return; }
+        self.fill_rect(x, y, w, 1, color);
+        self.fill_rect(x, y + h - 1, w, 1, color);
+        self.fill_rect(x, y, 1, h, color);
+        self.fill_rect(x + w - 1, y, 1, h, color);
+    }
+
+    /// Draw `text` with its top-left corner at (`x`, `y`); `\n` starts a new
+    /// line. Returns the width of the longest line in pixels.
+    pub fn text(&mut self, x: i32, y: i32, text: &str, color: Rgba) -> i32 {
+        let mut widest = 0;
+        for (row, line) in text.split('\n').enumerate() {
+            let top = y + row as i32 * OVERLAY_LINE_H;
+            let mut left = x;
+            for c in line.chars() {
+                for (dy, bits) in glyph(c).into_iter().enumerate() {
+                    for dx in 0..3 {
+                        if bits & (4 >> dx) != 0 { self.set(left + dx, top + dy as i32, color); }
+                    }
+                }
+                left += OVERLAY_CHAR_W;
+            }
+            widest = widest.max(left - x);
+        }
+        widest
+    }
+    /// `text` on a filled box with a one-pixel margin, readable over any
+    /// picture
+    pub fn label(&mut self, x: i32, y: i32, text: &str, fg: Rgba, bg: Rgba) {
+        let lines = text.split('\n').count() as i32;
+        let w = text.split('\n').map(|l| l.chars().count() as i32).max().unwrap_or(0) * OVERLAY_CHAR_W;
+        self.fill_rect(x, y, w + 1, lines * OVERLAY_LINE_H + 1, bg);
+        self.text(x + 1, y + 1, text, fg);
+    }
+    /// Outline each sprite's box (`visible_sprites`), tagged with its OAM
+    /// index above the top-left corner
+    pub fn sprite_boxes(&mut self, sprites: &[VisibleSprite], color: Rgba) {
+        for s in sprites {
+            let (x, y) = (s.x as i32, s.y as i32);
+            self.rect(x, y, 8, s.height as i32, color);
+            self.text(x, y - OVERLAY_LINE_H, &s.index.to_string(), color);
+        }
+    }
+    /// `name=value` lines for watched values, one per row, as a label in
+    /// the top-left corner
+    pub fn watch_values(&mut self, values: &[(&str, u16)], fg: Rgba, bg: Rgba) {
+        if values.is_empty() { return; }
+        let lines: Vec<String> = values.iter().map(|(name, v)| format!("{name}={v:02X}")).collect();
+        self.label(0, 0, &lines.join("\n"), fg, bg);
+    }
+
+    /// Blend the overlay over a 160×144 RGB888 frame in place
+    pub fn composite(&self, rgb: &mut [u8]) {
+        if !self.drawn { return; }
+        for (dst, src) in rgb.chunks_exact_mut(3).zip(self.pixels.chunks_exact(4)) {
+            let a = src[3] as u32;
+            if a == 0 { continue; }
+            for c in 0..3 {
+                dst[c] = ((src[c] as u32 * a + dst[c] as u32 * (255 - a) + 127) / 255) as u8;
+            }
+        }
+    }
+}
+
+/// Rows of a 3×5 glyph, top first; bit 2 is the left column
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '$' => [0b011, 0b110, 0b010, 0b011, 0b110],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
@@ -0,0 +1,193 @@
+//! palette_pack — per-game DMG colour schemes
+//!
+//! A DMG picture is four shades; a palette pack gives each game its own
+//! colours so captures of different games (and of sprites against the
+//! background) stay visually distinct in training data. Schemes have 4
+//! colours (BG and sprites alike) or 12 (BG, OBP0, OBP1), lightest first:
+//!
+//! ```text
+//! {"version":"mrom.palettes.v1",
+//!  "games":{"<rom_hash>":{"title":"...","colors":["#RRGGBB", ...]}},
+//!  "titles":{"TETRIS":["#RRGGBB", ...]}}
+//! ```
+//!
+//! A ROM hash entry wins over a header title entry. `PalettePack::builtin()`
+//! covers popular titles by header title; user packs are laid over it with
+//! `merge`. Packs only change `framebuffer_rgb` (PNG frames, replay
+//! snapshots), never emulation, and CGB games keep their own palettes.
+
+use crate::settings::{esc, parse_colour};
+use crate::{rom_hash, Cartridge, DmgPalette, GbCore, Json, DMG_GREYSCALE};
+use std::io;
+use std::path::Path;
+
+pub const PALETTE_PACK_VERSION: &str = "mrom.palettes.v1";
+
+/// Shade colours for the background and the two sprite palettes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DmgColors {
+    /// BG and window
+    pub bg: DmgPalette,
+    pub obj0: DmgPalette,
+    pub obj1: DmgPalette,
+}
+
+impl Default for DmgColors {
+    fn default() -> Self { DmgColors::uniform(DMG_GREYSCALE) }
+}
+
+impl DmgColors {
+    /// One palette for every layer
+    pub const fn uniform(p: DmgPalette) -> Self { DmgColors { bg: p, obj0: p, obj1: p } }
+
+    /// Palette for a `Ppu::pixel_source` value
+    pub fn for_source(&self, source: u8) -> &DmgPalette {
+        match source { 1 => &self.obj0, 2 => &self.obj1, _ => &self.bg }
+    }
+
+    /// 4 colours when uniform, else 12
+    pub fn to_json(&self) -> String {
+        let layers: &[&DmgPalette] = if *self == DmgColors::uniform(self.bg) { &[&self.bg] } else { &[&self.bg, &self.obj0, &self.obj1] };
+        let colours: Vec<String> = layers.iter().flat_map(|p| p.iter())
+            .map(|(r, g, b)| format!("\"#{r:02x}{g:02x}{b:02x}\""))
+            .collect();
+        format!("[{}]", colours.join(","))
+    }
+
+    pub fn from_json(doc: &Json) -> Result<DmgColors, String> {
+        let colours = doc.as_array().filter(|a| a.len() == 4 || a.len() == 12).ok_or("expected 4 or 12 colours")?;
+        let mut rgb = vec![];
+        for c in colours { rgb.push(c.as_str().and_then(parse_colour).ok_or("expected \"#RRGGBB\"")?); }
+        let palette = |i: usize| -> DmgPalette { [rgb[i], rgb[i + 1], rgb[i + 2], rgb[i + 3]] };
+        Ok(if rgb.len() == 4 { DmgColors::uniform(palette(0)) } else { DmgColors { bg: palette(0), obj0: palette(4), obj1: palette(8) } })
+    }
+}
+
+const fn rgb(v: u32) -> (u8, u8, u8) { ((v >> 16) as u8, (v >> 8) as u8, v as u8) }
+const fn pal(c: [u32; 4]) -> DmgPalette { [rgb(c[0]), rgb(c[1]), rgb(c[2]), rgb(c[3])] }
+
+/// Built-in schemes, by header title
+const BUILTIN: &[(&str, DmgColors)] = &[
+    ("TETRIS", DmgColors {
+        bg: pal([0xfff6d3, 0xf9a875, 0xeb6b6f, 0x7c3f58]),
+        obj0: pal([0xffffff, 0x8bd3e6, 0x3a7bd5, 0x0f1f4b]),
+        obj1: pal([0xffffff, 0xa8e890, 0x3c9d4e, 0x133b1f]),
+    }),
+    ("DR.MARIO", DmgColors {
+        bg: pal([0xf4f4f4, 0xa8c8e8, 0x5878a8, 0x182840]),
+        obj0: pal([0xffffff, 0xffe060, 0xe83828, 0x381008]),
+        obj1: pal([0xffffff, 0xffe060, 0x3878e8, 0x081838]),
+    }),
+    ("SUPER MARIOLAND", DmgColors {
+        bg: pal([0xfcf8e8, 0xd8b070, 0x8c5830, 0x2c1808]),
+        obj0: pal([0xffffff, 0xf8a888, 0xd03020, 0x401010]),
+        obj1: pal([0xffffff, 0x98d8f8, 0x3070c0, 0x102040]),
+    }),
+    ("MARIOLAND2", DmgColors {
+        bg: pal([0xf8f8d8, 0xb8d890, 0x689050, 0x203818]),
+        obj0: pal([0xffffff, 0xf8b090, 0xc83838, 0x401010]),
+        obj1: pal([0xffffff, 0xe0c0f8, 0x8050c0, 0x281040]),
+    }),
+    ("ZELDA", DmgColors {
+        bg: pal([0xf8f8c8, 0xa8d078, 0x508838, 0x183010]),
+        obj0: pal([0xffffff, 0xf8d098, 0x30a048, 0x102810]),
+        obj1: pal([0xffffff, 0xf8b8b8, 0xc04040, 0x401010]),
+    }),
+    ("KIRBY DREAM LAND", DmgColors {
+        bg: pal([0xfff0f8, 0xf8b8d8, 0xc06090, 0x401830]),
+        obj0: pal([0xffffff, 0xffc8e0, 0xf06098, 0x501028]),
+        obj1: pal([0xffffff, 0xfff0a0, 0xe0a020, 0x403008]),
+    }),
+    ("METROID2", DmgColors {
+        bg: pal([0xd8e8f0, 0x7898b0, 0x384e68, 0x0c1420]),
+        obj0: pal([0xffffff, 0xf8c070, 0xd86020, 0x401808]),
+        obj1: pal([0xffffff, 0xb8f8a0, 0x48b040, 0x103010]),
+    }),
+    ("POKEMON RED", DmgColors::uniform(pal([0xfff0e8, 0xf8a080, 0xc03828, 0x300808]))),
+    ("POKEMON BLUE", DmgColors::uniform(pal([0xe8f0ff, 0x88b0f8, 0x2858c0, 0x081030]))),
+    ("POKEMON GREEN", DmgColors::uniform(pal([0xe8ffe8, 0x90e090, 0x2c9040, 0x083010]))),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PalettePack {
+    /// (rom_hash, title, colours)
+    games: Vec<(String, String, DmgColors)>,
+    /// (header title, colours)
+    titles: Vec<(String, DmgColors)>,
+}
+
+impl PalettePack {
+    /// Schemes shipped with the core for popular titles
+    pub fn builtin() -> Self {
+        PalettePack { games: vec![], titles: BUILTIN.iter().map(|(t, c)| (t.to_string(), *c)).collect() }
+    }
+
+    pub fn is_empty(&self) -> bool { self.games.is_empty() && self.titles.is_empty() }
+
+    /// Colours for a ROM: by hash, else by header title
+    pub fn lookup(&self, rom_hash: &str, title: &str) -> Option<DmgColors> {
+        self.games.iter().find(|g| g.0 == rom_hash).map(|g| g.2)
+            .or_else(|| self.titles.iter().find(|t| t.0 == title).map(|t| t.1))
+    }
+    pub fn for_cartridge(&self, cart: &Cartridge) -> Option<DmgColors> { self.lookup(&rom_hash(&cart.rom), &cart.title) }
+
+    /// Set `core.dmg_colors` from the pack; false if the game has no entry
+    pub fn apply(&self, core: &mut GbCore) -> bool {
+        let title = String::from_utf8_lossy(&core.bus.rom[0x134..0x143]).trim_matches('\0').to_string();
+        match self.lookup(&rom_hash(&core.bus.rom), &title) {
+            Some(c) => { core.dmg_colors = c; true }
+            None => false,
+        }
+    }
+
+    pub fn set_game(&mut self, rom_hash: &str, title: &str, colors: DmgColors) {
+        self.games.retain(|g| g.0 != rom_hash);
+        self.games.push((rom_hash.to_string(), title.to_string(), colors));
+    }
+    pub fn set_title(&mut self, title: &str, colors: DmgColors) {
+        self.titles.retain(|t| t.0 != title);
+        self.titles.push((title.to_string(), colors));
+    }
+
+    /// Lay `over` on top: its entries replace ours
+    pub fn merge(&mut self, over: &PalettePack) {
+        for (h, t, c) in &over.games { self.set_game(h, t, *c); }
+        for (t, c) in &over.titles { self.set_title(t, *c); }
+    }
+
+    pub fn from_json(text: &str) -> Result<PalettePack, String> {
+        let doc = Json::parse(text).map_err(|e| e.to_string())?;
+        match doc.get("version").and_then(Json::as_str) {
+            Some(PALETTE_PACK_VERSION) => {}
+            v => return Err(format!("expected version {PALETTE_PACK_VERSION}, got {v:?}")),
+        }
+        let mut pack = PalettePack::default();
+        if let Some(Json::Obj(games)) = doc.get("games") {
+            for (hash, g) in games {
+                let colors = g.get("colors").ok_or_else(|| format!("{hash}: missing colors"))?;
+                let colors = DmgColors::from_json(colors).map_err(|e| format!("{hash}: {e}"))?;
+                pack.set_game(hash, g.get("title").and_then(Json::as_str).unwrap_or(""), colors);
+            }
+        }
+        if let Some(Json::Obj(titles)) = doc.get("titles") {
+            for (title, c) in titles {
+                pack.set_title(title, DmgColors::from_json(c).map_err(|e| format!("{title}: {e}"))?);
+            }
+        }
+        Ok(pack)
+    }
+
+    pub fn load(path: &Path) -> io::Result<PalettePack> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_json(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {e}", path.display())))
+    }
+
+    pub fn to_json(&self) -> String {
+        let games: Vec<String> = self.games.iter()
+            .map(|(h, t, c)| format!("\"{}\": {{\"title\":\"{}\",\"colors\":{}}}", esc(h), esc(t), c.to_json()))
+            .collect();
+        let titles: Vec<String> = self.titles.iter().map(|(t, c)| format!("\"{}\": {}", esc(t), c.to_json())).collect();
+        format!("{{\n  \"version\": \"{}\",\n  \"games\": {{\n    {}\n  }},\n  \"titles\": {{\n    {}\n  }}\n}}\n",
+            PALETTE_PACK_VERSION, games.join(",\n    "), titles.join(",\n    "))
+    }
+}
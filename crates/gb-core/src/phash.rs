@@ -0,0 +1,60 @@
+//! phash — 64-bit perceptual hash of a framebuffer
+//!
+//! DCT-based pHash: the 160x144 shade buffer is box-downscaled to 32x32, a 2D
+//! DCT-II is taken, and each of the 8x8 lowest-frequency coefficients (DC
+//! excluded from the median) becomes one bit: set when above the median.
+//! Visually similar frames land a small Hamming distance apart, so manifests
+//! can carry near-duplicate / scene-change signal without storing images.
+
+use crate::{LCD_HEIGHT, LCD_WIDTH};
+
+const N: usize = 32;
+
+/// Perceptual hash of a `LCD_WIDTH * LCD_HEIGHT` shade framebuffer (values 0-3)
+pub fn phash(framebuffer: &[u8]) -> u64 {
+    phash_image(framebuffer, LCD_WIDTH, LCD_HEIGHT)
+}
+
+/// Perceptual hash of an arbitrary `width * height` single-channel image
+pub fn phash_image(pixels: &[u8], width: usize, height: usize) -> u64 {
+    if width == 0 || height == 0 || pixels.len() < width * height { return 0; }
+
+    // Box-downscale to N x N
+    let mut small = [[0f64; N]; N];
+    for (sy, row) in small.iter_mut().enumerate() {
+        let (y0, y1) = (sy * height / N, ((sy + 1) * height / N).max(sy * height / N + 1));
+        for (sx, cell) in row.iter_mut().enumerate() {
+            let (x0, x1) = (sx * width / N, ((sx + 1) * width / N).max(sx * width / N + 1));
+            let mut sum = 0u32;
+            for y in y0..y1 { for x in x0..x1 { sum += pixels[y * width + x] as u32; } }
+            *cell = sum as f64 / ((y1 - y0) * (x1 - x0)) as f64;
+        }
+    }
+
+    // Separable DCT-II, keeping only the 8x8 low-frequency block
+    let cos = |k: usize, n: usize| ((2 * n + 1) as f64 * k as f64 * std::f64::consts::PI / (2 * N) as f64).cos();
+    let mut rows = [[0f64; 8]; N];
+    for (y, out) in rows.iter_mut().enumerate() {
+        for (u, o) in out.iter_mut().enumerate() {
+            *o = (0..N).map(|x| small[y][x] * cos(u, x)).sum();
+        }
+    }
+    let mut coeffs = [0f64; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            coeffs[v * 8 + u] = (0..N).map(|y| rows[y][u] * cos(v, y)).sum();
+        }
+    }
+
+    let mut sorted = [0f64; 63];
+    sorted.copy_from_slice(&coeffs[1..]);
+    sorted.sort_unstable_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+    coeffs.iter().enumerate()
+        .fold(0u64, |h, (i, &c)| if c > median { h | (1 << i) } else { h })
+}
+
+/// Number of differing bits between two hashes (0 = identical, 64 = inverted)
+pub fn phash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,227 @@
+//! pixel_fifo — dot-by-dot mode 3: background fetcher and pixel FIFOs
+//!
+//! The default renderer draws a whole line when mode 3 ends and gives every
+//! line 172 dots of mode 3. With `Ppu::fifo` set, mode 3 runs one dot at a
+//! time the way the DMG PPU does:
+//!
+//! - The fetcher reads a tile number, its low and high bit planes (2 dots
+//!   each) and pushes 8 pixels into the BG FIFO once it is empty. The first
+//!   fetch of a line is thrown away, so pixels start at dot 12.
+//! - One pixel leaves the FIFO per dot; the first `SCX & 7` are dropped.
+//! - When the next pixel reaches WX - 7 on a line at or below WY, the FIFO is
+//!   cleared and the fetcher restarts on the window's tile map (6 dots).
+//! - When the next pixel reaches a sprite the mode 2 scan selected, output
+//!   stalls until the fetcher is on its last step, then for 6 dots while
+//!   the sprite's row is fetched into the sprite FIFO (6 to 11 dots in all). Pixels already in
+//!   the sprite FIFO win over later sprites, which gives DMG priority; in
+//!   CGB mode a later sprite with a lower OAM index takes them over.
+//!
+//! Mode 3 thus lasts 172 dots plus the fine-scroll, window and sprite
+//! penalties (`Ppu::mode3_dots`), and HBlank is shorter by the same amount.
+//! SCX/SCY are read at each fetch, LCDC and the palettes at each pixel, so
+//! writes the CPU makes during mode 3 take effect mid-line. A line with no
+//! such writes comes out as the line renderer draws it, except that a
+//! behind-BG sprite now hides sprites under it, as on hardware. Lines are
+//! always drawn (`RenderSkip` does not apply).
+
+use crate::{apply_palette, bg_enabled, bg_priority_enabled, tile_planes, BgAttr, Ppu, ScannedSprite, Sprite, CGB_OBJ_SOURCE, LCD_WIDTH, SPRITES_PER_LINE};
+
+/// Dots of the discarded first fetch
+const STARTUP_DOTS: u32 = 6;
+/// Fetcher dots before a tile is ready to push
+const FETCH_DOTS: u8 = 6;
+/// Fetcher dot from which a due sprite fetch can start (its last step)
+const SPRITE_FETCH_FROM: u8 = 5;
+/// Dots a sprite fetch holds the pixel output once it starts
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ObjPixel {
+    /// Colour number; 0 is transparent
+    color: u8,
+    /// OBP0 / OBP1, or the CGB OBJ palette
+    palette: u8,
+    behind_bg: bool,
+    oam_index: u8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PixelFifo {
+    /// Dots into the current mode 3
+    dot: u32,
+    /// Pixels sent to the LCD
+    lx: u8,
+    /// Drawing a line (false between lines, or after a state load mid-line)
+    active: bool,
+    bg: [u8; 8],
+    bg_len: u8,
+    /// CGB attributes of the tile in the BG FIFO
+    bg_attr: BgAttr,
+    obj: [ObjPixel; 8],
+    /// Fetcher: dots into the current fetch, tile column, window or BG map
+    fetch_dot: u8,
+    fetch_x: u8,
+    tile: u8,
+    attr: BgAttr,
+    planes: [u8; 2],
+    /// Pixels still to drop for SCX fine scroll
+    discard: u8,
+    window: bool,
+    /// The mode 2 scan's sprites ordered by X (OAM order among equal X)
+    sprites: [ScannedSprite; SPRITES_PER_LINE],
+    sprite_count: u8,
+    next_sprite: u8,
+    /// Dots left of the sprite fetch under way
+    sprite_fetch: Option<u8>,
+}
+
+impl PixelFifo {
+    pub fn new() -> Self { Self::default() }
+
+    /// Dots into the current mode 3
+    pub fn dot(&self) -> u32 { self.dot }
+    /// Pixels drawn on the current line
+    pub fn lx(&self) -> u8 { self.lx }
+    pub fn active(&self) -> bool { self.active }
+
+    /// Start mode 3 with the line's scanned sprites
+    pub fn start(&mut self, scx: u8, scanned: &[ScannedSprite]) {
+        *self = PixelFifo { active: true, discard: scx & 7, sprite_count: scanned.len() as u8, ..PixelFifo::new() };
+        self.sprites[..scanned.len()].copy_from_slice(scanned);
+        self.sprites[..scanned.len()].sort_by_key(|s| s.x);
+    }
+
+    /// Forget a line in progress; `Ppu` draws it with the line renderer
+    pub(crate) fn stop(&mut self) { self.active = false; }
+
+    /// Run mode 3 up to `until` dots; returns its length once the line is done
+    pub fn run(&mut self, ppu: &mut Ppu, vram: [&[u8; 0x2000]; 2], oam: &[u8; 0xA0], until: u32) -> Option<u32> {
+        while self.dot < until {
+            self.tick(ppu, vram, oam);
+            self.dot += 1;
+            if self.lx as usize >= LCD_WIDTH {
+                self.active = false;
+                if self.window { ppu.wlc = ppu.wlc.wrapping_add(1); }
+                return Some(self.dot);
+            }
+        }
+        None
+    }
+
+    fn tick(&mut self, ppu: &mut Ppu, vram: [&[u8; 0x2000]; 2], oam: &[u8; 0xA0]) {
+        if self.dot < STARTUP_DOTS { return; }
+        let lx = self.lx as usize;
+        // Window start: clear the FIFO and refetch from the window map
+        if !self.window && ppu.lcdc & 0x20 != 0 && ppu.ly >= ppu.wy && lx >= ppu.wx.saturating_sub(7) as usize {
+            self.window = true;
+            self.bg_len = 0;
+            self.fetch_dot = 0;
+            self.fetch_x = 0;
+            self.discard = 0;
+        }
+        if let Some(left) = self.sprite_fetch {
+            if left > 1 { self.sprite_fetch = Some(left - 1); return; }
+            self.sprite_fetch = None;
+            self.merge_sprite(ppu, vram, oam);
+            self.next_sprite += 1;
+            return;
+        }
+        let sprite_due = self.discard == 0 && ppu.lcdc & 0x02 != 0
+            && self.next_sprite < self.sprite_count && self.sprites[self.next_sprite as usize].x as usize <= lx + 8;
+        self.fetch(ppu, vram);
+        if sprite_due {
+            // Output holds until the fetcher is on its last step, then for the sprite fetch
+            if self.fetch_dot >= SPRITE_FETCH_FROM && self.bg_len > 0 { self.sprite_fetch = Some(SPRITE_FETCH_DOTS - 1); }
+            return;
+        }
+        if self.bg_len == 0 { return; }
+
+        // Shift one pixel out
+        let color = self.bg[8 - self.bg_len as usize];
+        self.bg_len -= 1;
+        if self.discard > 0 { self.discard -= 1; return; }
+        let obj = self.obj[0];
+        self.obj.copy_within(1.., 0);
+        self.obj[7] = ObjPixel::default();
+        let bg_on = bg_enabled(ppu.lcdc, ppu.cgb);
+        let bg_color = if bg_on { color } else { 0 };
+        let behind = (obj.behind_bg || self.bg_attr.priority()) && bg_priority_enabled(ppu.lcdc, ppu.cgb);
+        let (shade, source) = if obj.color != 0 && ppu.lcdc & 0x02 != 0 && !(behind && bg_color != 0) {
+            if ppu.cgb { (obj.color, CGB_OBJ_SOURCE + obj.palette) } else {
+                let pal = if obj.palette == 0 { ppu.pal_obj0 } else { ppu.pal_obj1 };
+                (apply_palette(pal, obj.color), 1 + obj.palette)
+            }
+        } else if bg_on {
+            if ppu.cgb { (bg_color, self.bg_attr.palette()) } else { (apply_palette(ppu.pal_bg, bg_color), 0) }
+        } else {
+            (0, 0)
+        };
+        let i = ppu.ly as usize * LCD_WIDTH + lx;
+        if let (Some(px), Some(src)) = (ppu.framebuffer.get_mut(i), ppu.pixel_source.get_mut(i)) {
+            *px = shade;
+            *src = source;
+        }
+        self.lx += 1;
+    }
+
+    /// One fetcher dot: tile number (and CGB attributes), low plane, high
+    /// plane, then push
+    fn fetch(&mut self, ppu: &Ppu, vram: [&[u8; 0x2000]; 2]) {
+        if self.fetch_dot < FETCH_DOTS {
+            self.fetch_dot += 1;
+            let (map, col, row) = if self.window {
+                (if ppu.lcdc & 0x40 != 0 { 0x1C00 } else { 0x1800 }, self.fetch_x as usize, ppu.wlc as usize)
+            } else {
+                let y = ppu.ly.wrapping_add(ppu.scy) as usize;
+                (if ppu.lcdc & 0x08 != 0 { 0x1C00 } else { 0x1800 }, ((ppu.scx >> 3) as usize + self.fetch_x as usize) & 31, y)
+            };
+            match self.fetch_dot {
+                2 => {
+                    let addr = map + (row >> 3 & 31) * 32 + col;
+                    self.tile = vram[0][addr];
+                    self.attr = if ppu.cgb { BgAttr(vram[1][addr]) } else { BgAttr(0) };
+                }
+                4 => self.planes[0] = tile_planes(vram, ppu.lcdc, self.tile, self.attr, row & 7)[0],
+                6 => self.planes[1] = tile_planes(vram, ppu.lcdc, self.tile, self.attr, row & 7)[1],
+                _ => {}
+            }
+        } else if self.bg_len == 0 {
+            let [lo, hi] = self.planes;
+            for (bit, px) in (0..8).rev().zip(self.bg.iter_mut()) {
+                *px = (hi >> bit & 1) << 1 | (lo >> bit & 1);
+            }
+            self.bg_len = 8;
+            self.bg_attr = self.attr;
+            self.fetch_dot = 0;
+            self.fetch_x = self.fetch_x.wrapping_add(1);
+        }
+    }
+
+    /// Fetch the due sprite's row into the sprite FIFO's transparent slots
+    fn merge_sprite(&mut self, ppu: &Ppu, vram: [&[u8; 0x2000]; 2], oam: &[u8; 0xA0]) {
+        let scanned = self.sprites[self.next_sprite as usize];
+        let s = Sprite { y: scanned.y, x: scanned.x, ..Sprite::from_oam(oam, scanned.index as usize) };
+        let sh: i32 = if ppu.lcdc & 0x04 != 0 { 16 } else { 8 };
+        let sy = s.screen_y();
+        // LCDC bit 2 may have changed since the scan picked it
+        if !(sy..sy + sh).contains(&(ppu.ly as i32)) { return; }
+        let mut row = (ppu.ly as i32 - sy) as usize;
+        if s.y_flip() { row = sh as usize - 1 - row; }
+        let tile = if sh == 16 { if row < 8 { s.tile & 0xFE } else { s.tile | 0x01 } } else { s.tile };
+        let ta = tile as usize * 16 + (row & 7) * 2;
+        let bank = if ppu.cgb { s.bank() } else { 0 };
+        let (lo, hi) = (vram[bank][ta], vram[bank][ta + 1]);
+        let palette = if ppu.cgb { s.cgb_palette() } else { s.palette() };
+        for i in 0..8 {
+            // Sprite pixel i lands on screen x s.screen_x() + i
+            let Ok(slot) = usize::try_from(s.screen_x() + i - self.lx as i32) else { continue };
+            let Some(px) = self.obj.get_mut(slot) else { continue };
+            let bit = if s.x_flip() { i } else { 7 - i };
+            let color = (hi >> bit & 1) << 1 | (lo >> bit & 1);
+            let wins = px.color == 0 || (ppu.cgb && scanned.index < px.oam_index);
+            if color != 0 && wins {
+                *px = ObjPixel { color, palette, behind_bg: s.bg_priority(), oam_index: scanned.index };
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! png — dependency-free PNG encoder for framebuffer exports
+//!
+//! Writes 8-bit RGB images using zlib "stored" (uncompressed) deflate blocks.
+//! Files are larger than a real compressor would produce but any PNG reader
+//! accepts them, which is all keyframe / screenshot export needs.
+
+/// Encode `rgb` (`width * height * 3` bytes, row-major) as a PNG file
+pub fn encode_png_rgb(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    let row = width as usize * 3;
+    assert_eq!(rgb.len(), row * height as usize, "encode_png_rgb: buffer size mismatch");
+
+    // Raw scanlines, each prefixed with filter type 0 (None)
+    let mut raw = Vec::with_capacity((row + 1) * height as usize);
+    for line in rgb.chunks(row) { raw.push(0); raw.extend_from_slice(line); }
+
+    let mut z = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() { z.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]); }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        z.push(blocks.peek().is_none() as u8);
+        z.extend_from_slice(&len.to_le_bytes());
+        z.extend_from_slice(&(!len).to_le_bytes());
+        z.extend_from_slice(block);
+    }
+    z.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit depth, RGB, deflate, no filter, no interlace
+
+    let mut out = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    chunk(&mut out, b"IHDR", &ihdr);
+    chunk(&mut out, b"IDAT", &z);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 { crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 }; }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &x in data { a = (a + x as u32) % 65521; b = (b + a) % 65521; }
+    (b << 16) | a
+}
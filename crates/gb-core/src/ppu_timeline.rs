@@ -0,0 +1,165 @@
+//! ppu_timeline — per-scanline PPU mode timing for one frame
+//!
+//! With `Bus::ppu_timeline` set, every PPU mode change is stamped with the
+//! dot (PPU cycle) since the frame started, and each completed frame is
+//! reduced to per-line mode 2 / 3 / 0 durations. `last_frame()` holds the
+//! most recent one; it exports as JSON (`mrom.ppu_timeline.v1`) for
+//! comparison against hardware captures in the same format, or as an SVG
+//! strip chart (one row per line, one colour per mode) for eyeballing mode
+//! length changes.
+//!
+//! Recording starts at the next frame start (LY 0, mode 2) and drops the
+//! frame in progress when the LCD is switched off.
+
+use crate::{Json, Ppu, PpuMode, DOTS_PER_LINE, PPU_VBLANK_LINE};
+
+pub const PPU_TIMELINE_VERSION: &str = "mrom.ppu_timeline.v1";
+
+/// One visible line: when it started and how long each mode lasted, in dots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LineTiming {
+    pub ly: u8,
+    /// Dots from the start of the frame
+    pub start: u32,
+    pub oam: u32,
+    pub draw: u32,
+    pub hblank: u32,
+}
+
+impl LineTiming {
+    pub fn to_json(&self) -> String {
+        format!("{{\"ly\":{},\"start\":{},\"oam\":{},\"draw\":{},\"hblank\":{}}}",
+            self.ly, self.start, self.oam, self.draw, self.hblank)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FrameTimeline {
+    /// Frames completed since recording started, counting from 0
+    pub frame: u64,
+    pub lines: Vec<LineTiming>,
+    /// Length of the VBlank period
+    pub vblank: u32,
+}
+
+impl FrameTimeline {
+    pub fn dots(&self) -> u32 {
+        self.lines.first().map_or(0, |l| l.start) + self.lines.iter().map(|l| l.oam + l.draw + l.hblank).sum::<u32>() + self.vblank
+    }
+
+    /// Lines whose mode durations differ from `reference`, including lines
+    /// only one of the two has
+    pub fn mismatches(&self, reference: &FrameTimeline) -> Vec<u8> {
+        let find = |t: &FrameTimeline, ly: u8| t.lines.iter().find(|l| l.ly == ly).map(|l| (l.oam, l.draw, l.hblank));
+        let mut lys: Vec<u8> = self.lines.iter().chain(&reference.lines).map(|l| l.ly).collect();
+        lys.sort_unstable();
+        lys.dedup();
+        lys.retain(|&ly| find(self, ly) != find(reference, ly));
+        lys
+    }
+
+    pub fn to_json(&self) -> String {
+        let lines: Vec<String> = self.lines.iter().map(LineTiming::to_json).collect();
+        format!("{{\"version\":\"{PPU_TIMELINE_VERSION}\",\"frame\":{},\"dots\":{},\"vblank\":{},\"lines\":[{}]}}",
+            self.frame, self.dots(), self.vblank, lines.join(","))
+    }
+
+    pub fn from_json(s: &str) -> Result<FrameTimeline, String> {
+        let doc = Json::parse(s).map_err(|e| e.to_string())?;
+        let version = doc.get("version").and_then(Json::as_str).unwrap_or("");
+        if version != PPU_TIMELINE_VERSION { return Err(format!("unsupported version {version:?}")); }
+        let num = |j: &Json, key: &str| j.get(key).and_then(Json::as_u64).ok_or_else(|| format!("missing \"{key}\""));
+        let lines = doc.get("lines").and_then(Json::as_array).ok_or("missing \"lines\"")?.iter().map(|l| Ok(LineTiming {
+            ly: num(l, "ly")? as u8,
+            start: num(l, "start")? as u32,
+            oam: num(l, "oam")? as u32,
+            draw: num(l, "draw")? as u32,
+            hblank: num(l, "hblank")? as u32,
+        })).collect::<Result<_, String>>()?;
+        Ok(FrameTimeline { frame: doc.get("frame").and_then(Json::as_u64).unwrap_or(0), lines, vblank: num(&doc, "vblank")? as u32 })
+    }
+
+    /// Strip chart: one 2-px row per line over a dot axis, mode 2 yellow,
+    /// mode 3 red, mode 0 blue and VBlank grey
+    pub fn to_svg(&self) -> String {
+        const ROW: u32 = 2;
+        let rows = self.lines.len() as u32 + self.vblank.div_ceil(DOTS_PER_LINE);
+        let mut out = format!(concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+            "<title>PPU modes, frame {f}</title>"), w = DOTS_PER_LINE, h = rows * ROW, f = self.frame);
+        for (row, l) in self.lines.iter().enumerate() {
+            let y = row as u32 * ROW;
+            let mut x = 0;
+            for (len, colour) in [(l.oam, "#e8c547"), (l.draw, "#d1495b"), (l.hblank, "#00798c")] {
+                out.push_str(&format!("<rect x=\"{x}\" y=\"{y}\" width=\"{len}\" height=\"{ROW}\" fill=\"{colour}\"/>"));
+                x += len;
+            }
+        }
+        if self.vblank > 0 {
+            out.push_str(&format!("<rect x=\"0\" y=\"{}\" width=\"{DOTS_PER_LINE}\" height=\"{}\" fill=\"#9a9a9a\"/>",
+                self.lines.len() as u32 * ROW, rows * ROW - self.lines.len() as u32 * ROW));
+        }
+        out.push_str("</svg>");
+        out
+    }
+}
+
+/// Records mode changes as the PPU steps; see the module docs
+#[derive(Debug, Clone, Default)]
+pub struct PpuTimeline {
+    /// Dots since the current frame started
+    dot: u32,
+    /// Saw a frame start since recording began (or the LCD came back on)
+    synced: bool,
+    /// Mode changes of the current frame: (ly, new mode, dot)
+    changes: Vec<(u8, PpuMode, u32)>,
+    frames: u64,
+    last: Option<FrameTimeline>,
+}
+
+impl PpuTimeline {
+    pub fn new() -> Self { Self::default() }
+    pub fn last_frame(&self) -> Option<&FrameTimeline> { self.last.as_ref() }
+
+    /// After `Ppu::step(cycles)`; `before` is the mode going in
+    pub(crate) fn record(&mut self, before: PpuMode, ppu: &Ppu, cycles: u32) {
+        if ppu.lcdc & 0x80 == 0 {
+            self.synced = false;
+            self.changes.clear();
+            return;
+        }
+        self.dot += cycles;
+        if ppu.mode == before { return; }
+        // `ppu.dot` is what the step carried past the change
+        let at = self.dot - ppu.dot.min(self.dot);
+        if ppu.mode == PpuMode::OamScan && ppu.ly == 0 {
+            if self.synced {
+                self.changes.push((PPU_VBLANK_LINE as u8, PpuMode::OamScan, at));
+                self.last = Some(self.finish());
+                self.frames += 1;
+            }
+            self.synced = true;
+            self.changes.clear();
+            self.dot = ppu.dot;
+            self.changes.push((0, PpuMode::OamScan, 0));
+        } else if self.synced {
+            self.changes.push((ppu.ly, ppu.mode, at));
+        }
+    }
+
+    fn finish(&self) -> FrameTimeline {
+        let mut lines: Vec<LineTiming> = vec![];
+        let mut vblank = 0;
+        for (i, &(ly, mode, at)) in self.changes.iter().enumerate() {
+            let Some(&(_, _, next)) = self.changes.get(i + 1) else { break };
+            let len = next - at;
+            match mode {
+                PpuMode::OamScan => lines.push(LineTiming { ly, start: at, oam: len, ..Default::default() }),
+                PpuMode::Drawing => if let Some(l) = lines.last_mut() { l.draw = len },
+                PpuMode::HBlank => if let Some(l) = lines.last_mut() { l.hblank = len },
+                PpuMode::VBlank => vblank += len,
+            }
+        }
+        FrameTimeline { frame: self.frames, lines, vblank }
+    }
+}
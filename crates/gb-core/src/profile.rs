@@ -0,0 +1,95 @@
+//! profile — per-opcode and per-address execution profiler (feature `profile`)
+//!
+//! With `GbCore::profiler` set, every instruction adds to two tables: one
+//! per opcode (CB-prefixed opcodes separately) and one per `bank:address` of
+//! the opcode. Each counts executions, emulated T-cycles and host time spent
+//! stepping the instruction (execution plus the PPU / APU / timer catch-up
+//! it causes), so the same report shows a ROM's hot loops and where the
+//! interpreter itself is slow.
+//!
+//! Banks are ROM banks (0 for 0x0000-0x3FFF, the mapped bank above); code
+//! outside ROM reports bank 0. Interrupt dispatch and HALT cycles are not
+//! instructions and are not counted. `GbCore::profile_json()` exports it:
+//!
+//! ```text
+//! {"instructions":N,"cycles":N,"host_ns":N,
+//!  "opcodes":[{"op":"3E","count":N,"cycles":N,"host_ns":N}, ...],   // "CB 37" for CB ops
+//!  "hot":[{"bank":1,"addr":16384,"count":N,"cycles":N,"host_ns":N}, ...]}
+//! ```
+
+use std::collections::HashMap;
+
+/// Sites listed under "hot" in `to_json`
+pub const PROFILE_HOT_SITES: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProfileStats {
+    pub count: u64,
+    /// Emulated T-cycles
+    pub cycles: u64,
+    /// Host nanoseconds
+    pub host_ns: u64,
+}
+
+impl ProfileStats {
+    fn add(&mut self, cycles: u8, host_ns: u64) {
+        self.count += 1;
+        self.cycles += cycles as u64;
+        self.host_ns += host_ns;
+    }
+    fn json_fields(&self) -> String {
+        format!("\"count\":{},\"cycles\":{},\"host_ns\":{}", self.count, self.cycles, self.host_ns)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    /// 0x000-0x0FF opcodes, 0x100-0x1FF CB opcodes
+    ops: Vec<ProfileStats>,
+    sites: HashMap<(u16, u16), ProfileStats>,
+    total: ProfileStats,
+}
+
+impl Default for Profiler {
+    fn default() -> Self { Profiler { ops: vec![ProfileStats::default(); 0x200], sites: HashMap::new(), total: ProfileStats::default() } }
+}
+
+impl Profiler {
+    pub fn new() -> Self { Self::default() }
+    pub fn clear(&mut self) { *self = Self::default(); }
+
+    pub fn total(&self) -> ProfileStats { self.total }
+    /// Stats of an opcode; `cb` selects the CB-prefixed table
+    pub fn opcode(&self, op: u8, cb: bool) -> ProfileStats { self.ops[op as usize | (cb as usize) << 8] }
+    pub fn site(&self, bank: u16, addr: u16) -> ProfileStats { self.sites.get(&(bank, addr)).copied().unwrap_or_default() }
+
+    /// The `n` costliest sites by T-cycles: ((bank, addr), stats)
+    pub fn hot_sites(&self, n: usize) -> Vec<((u16, u16), ProfileStats)> {
+        let mut sites: Vec<_> = self.sites.iter().map(|(k, v)| (*k, *v)).collect();
+        sites.sort_by(|a, b| b.1.cycles.cmp(&a.1.cycles).then(a.0.cmp(&b.0)));
+        sites.truncate(n);
+        sites
+    }
+
+    /// `op` is the CB operand for CB-prefixed instructions when `cb` is set
+    pub(crate) fn record(&mut self, bank: u16, addr: u16, op: u8, cb: bool, cycles: u8, host_ns: u64) {
+        self.ops[op as usize | (cb as usize) << 8].add(cycles, host_ns);
+        self.sites.entry((bank, addr)).or_default().add(cycles, host_ns);
+        self.total.add(cycles, host_ns);
+    }
+
+    /// Opcodes by execution count, then the `PROFILE_HOT_SITES` hottest sites
+    pub fn to_json(&self) -> String {
+        let mut ops: Vec<(usize, &ProfileStats)> = self.ops.iter().enumerate().filter(|(_, s)| s.count > 0).collect();
+        ops.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(&b.0)));
+        let ops: Vec<String> = ops.iter().map(|(i, s)| {
+            let name = if *i >= 0x100 { format!("CB {:02X}", i & 0xFF) } else { format!("{i:02X}") };
+            format!("{{\"op\":\"{name}\",{}}}", s.json_fields())
+        }).collect();
+        let hot: Vec<String> = self.hot_sites(PROFILE_HOT_SITES).iter()
+            .map(|((bank, addr), s)| format!("{{\"bank\":{bank},\"addr\":{addr},{}}}", s.json_fields()))
+            .collect();
+        format!("{{\"instructions\":{},\"cycles\":{},\"host_ns\":{},\"opcodes\":[{}],\"hot\":[{}]}}",
+            self.total.count, self.total.cycles, self.total.host_ns, ops.join(","), hot.join(","))
+    }
+}
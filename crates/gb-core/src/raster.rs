@@ -0,0 +1,137 @@
+//! raster — per-scanline scroll and window registers of each frame
+//!
+//! Parallax layers, status bars and wavy water are drawn by rewriting SCX /
+//! SCY / WX / WY between lines (STAT or LYC interrupts, HBlank loops), which
+//! a per-frame register dump misses. With `Bus::raster` set, every visible
+//! line records the registers it was drawn with, read when mode 3 ends as
+//! the line renderer does, whether the window showed on it, and whether any
+//! of them changed during mode 3. On those `mid_line` lines the pixel FIFO
+//! (`pixel_fifo.rs`) picks the new values up part way across, so they are
+//! where its output may differ from the line renderer's.
+//!
+//! `last_frame()` holds the 144 lines of the most recent complete frame
+//! (recording starts at the next LY 0 and drops the frame in progress when
+//! the LCD is switched off). In replay and training data a frame is a list
+//! of runs, one per change, so a static screen is a single entry:
+//!
+//! ```text
+//! [[ly, scx, scy, wx, wy, window, mid_line], ...]   window / mid_line: 0 or 1
+//! ```
+//!
+//! Each run covers the lines from its `ly` to the next run's; `raster_from_json`
+//! expands it back to one `ScanlineRegs` per line.
+
+use crate::{Json, Ppu, PpuMode, LCD_HEIGHT, LCD_WIDTH};
+use std::fmt::Write;
+
+/// The scroll and window registers one line was drawn with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanlineRegs {
+    pub ly: u8,
+    pub scx: u8,
+    pub scy: u8,
+    pub wx: u8,
+    pub wy: u8,
+    /// The window covered part of the line (LCDC bit 5, LY >= WY, WX < 167)
+    pub window: bool,
+    /// SCX, SCY, WX or WY was written during the line's mode 3
+    pub mid_line: bool,
+}
+
+impl ScanlineRegs {
+    fn of(ppu: &Ppu, mid_line: bool) -> ScanlineRegs {
+        ScanlineRegs {
+            ly: ppu.ly, scx: ppu.scx, scy: ppu.scy, wx: ppu.wx, wy: ppu.wy,
+            window: ppu.lcdc & 0x20 != 0 && ppu.ly >= ppu.wy && (ppu.wx.saturating_sub(7) as usize) < LCD_WIDTH,
+            mid_line,
+        }
+    }
+    /// Same registers and flags, whatever the line
+    fn same_run(&self, other: &ScanlineRegs) -> bool { ScanlineRegs { ly: other.ly, ..*self } == *other }
+}
+
+/// Records the registers of each line as the PPU steps; see the module docs
+#[derive(Debug, Clone, Default)]
+pub struct RasterCapture {
+    /// Saw a frame start since recording began (or the LCD came back on)
+    synced: bool,
+    /// SCX, SCY, WX, WY when the current line's mode 3 began
+    at_draw: [u8; 4],
+    lines: Vec<ScanlineRegs>,
+    last: Vec<ScanlineRegs>,
+}
+
+impl RasterCapture {
+    pub fn new() -> Self { Self::default() }
+    /// Lines of the last complete frame; empty until one was recorded
+    pub fn last_frame(&self) -> &[ScanlineRegs] { &self.last }
+
+    /// After `Ppu::step(cycles)`; `before` is the mode going in
+    pub(crate) fn record(&mut self, before: PpuMode, ppu: &Ppu) {
+        if ppu.lcdc & 0x80 == 0 {
+            self.synced = false;
+            self.lines.clear();
+            return;
+        }
+        if ppu.mode == before { return; }
+        let regs = [ppu.scx, ppu.scy, ppu.wx, ppu.wy];
+        match ppu.mode {
+            PpuMode::OamScan if ppu.ly == 0 => {
+                self.synced = true;
+                self.lines.clear();
+            }
+            PpuMode::Drawing => self.at_draw = regs,
+            PpuMode::HBlank if self.synced => self.lines.push(ScanlineRegs::of(ppu, regs != self.at_draw)),
+            PpuMode::VBlank if self.synced && self.lines.len() == LCD_HEIGHT => {
+                std::mem::swap(&mut self.last, &mut self.lines);
+                self.lines.clear();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Append `lines` as runs (see the module docs)
+pub fn write_raster_json(out: &mut String, lines: &[ScanlineRegs]) {
+    out.push('[');
+    let mut prev: Option<&ScanlineRegs> = None;
+    for l in lines {
+        if prev.is_some_and(|p| p.same_run(l)) { continue; }
+        if prev.is_some() { out.push(','); }
+        let _ = write!(out, "[{},{},{},{},{},{},{}]", l.ly, l.scx, l.scy, l.wx, l.wy, l.window as u8, l.mid_line as u8);
+        prev = Some(l);
+    }
+    out.push(']');
+}
+
+pub fn raster_json(lines: &[ScanlineRegs]) -> String {
+    let mut out = String::new();
+    write_raster_json(&mut out, lines);
+    out
+}
+
+/// One `ScanlineRegs` per line of a frame's runs, up to line 143
+pub fn raster_from_json(runs: &Json) -> Result<Vec<ScanlineRegs>, String> {
+    let runs = runs.as_array().ok_or("raster: expected an array of runs")?;
+    let mut lines: Vec<ScanlineRegs> = Vec::with_capacity(LCD_HEIGHT);
+    for (i, run) in runs.iter().enumerate() {
+        let v: Vec<u8> = run.as_array().unwrap_or_default().iter()
+            .map(|n| n.as_u64().filter(|&n| n <= 0xFF).map(|n| n as u8))
+            .collect::<Option<_>>().filter(|v: &Vec<u8>| v.len() == 7)
+            .ok_or_else(|| format!("raster run {i}: expected 7 numbers of 0-255"))?;
+        let from = v[0] as usize;
+        if from < lines.len() || from >= LCD_HEIGHT { return Err(format!("raster run {i}: line {from} out of order")); }
+        let regs = ScanlineRegs { ly: v[0], scx: v[1], scy: v[2], wx: v[3], wy: v[4], window: v[5] != 0, mid_line: v[6] != 0 };
+        // The previous run lasts until this one starts
+        if let Some(&last) = lines.last() {
+            lines.extend((lines.len()..from).map(|ly| ScanlineRegs { ly: ly as u8, ..last }));
+        } else if from != 0 {
+            return Err("raster: the first run must start at line 0".into());
+        }
+        lines.push(regs);
+    }
+    if let Some(&last) = lines.last() {
+        lines.extend((lines.len()..LCD_HEIGHT).map(|ly| ScanlineRegs { ly: ly as u8, ..last }));
+    }
+    Ok(lines)
+}
@@ -0,0 +1,104 @@
+//! recover — keep batch runs alive through panicking or wedged ROMs
+//!
+//! `catch_run` runs one job under `catch_unwind` and turns a panic into a
+//! `RunPanic` record (message, location, backtrace hash) instead of killing
+//! the process. The hash covers only the symbol names of the backtrace, so
+//! the same bug hit by many ROMs groups under one value across runs.
+//! `RunDeadline` is the wall-clock watchdog: it raises a core's interrupt
+//! flag when a job overruns, making `run_frame` return `Interrupted`.
+
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Once};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunPanic {
+    pub message: String,
+    /// `file:line:col` of the panic, when known
+    pub location: String,
+    /// FNV-1a of the backtrace's symbol names, hex
+    pub backtrace_hash: String,
+}
+
+impl RunPanic {
+    pub fn to_json(&self) -> String {
+        let esc = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        format!("{{\"message\":\"{}\",\"location\":\"{}\",\"backtrace_hash\":\"{}\"}}",
+                esc(&self.message), esc(&self.location), self.backtrace_hash)
+    }
+}
+
+thread_local! {
+    static GUARDED: Cell<bool> = const { Cell::new(false) };
+    static LAST_PANIC: RefCell<Option<RunPanic>> = const { RefCell::new(None) };
+}
+
+static HOOK: Once = Once::new();
+
+/// Hash of the symbol lines of a rendered backtrace (addresses and frame
+/// numbers dropped, so it is stable across runs of the same binary)
+pub fn backtrace_hash(rendered: &str) -> String {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for line in rendered.lines().map(str::trim) {
+        let Some((n, sym)) = line.split_once(": ") else { continue };
+        if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) { continue; }
+        for b in sym.bytes().chain([b'\n']) { h ^= b as u64; h = h.wrapping_mul(0x0000_0100_0000_01b3); }
+    }
+    format!("{:016x}", h)
+}
+
+fn install_hook() {
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !GUARDED.with(Cell::get) { return previous(info); }
+            let message = info.payload().downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| info.payload().downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "non-string panic payload".into());
+            let location = info.location().map(|l| l.to_string()).unwrap_or_default();
+            let backtrace_hash = backtrace_hash(&Backtrace::force_capture().to_string());
+            LAST_PANIC.with(|p| *p.borrow_mut() = Some(RunPanic { message, location, backtrace_hash }));
+        }));
+    });
+}
+
+/// Run `f`, returning its value or the panic it raised. Panics inside are
+/// recorded silently; panics elsewhere still reach the previous hook.
+pub fn catch_run<T>(f: impl FnOnce() -> T) -> Result<T, RunPanic> {
+    install_hook();
+    let was_guarded = GUARDED.with(|g| g.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    GUARDED.with(|g| g.set(was_guarded));
+    result.map_err(|_| LAST_PANIC.with(|p| p.borrow_mut().take()).unwrap_or_else(|| RunPanic {
+        message: "panic".into(), location: String::new(), backtrace_hash: backtrace_hash(""),
+    }))
+}
+
+/// Raises `flag` (a `GbCore::interrupt_handle()`) once `budget` elapses,
+/// unless dropped first
+pub struct RunDeadline {
+    _cancel: mpsc::Sender<()>,
+    fired: Arc<AtomicBool>,
+}
+
+impl RunDeadline {
+    pub fn arm(flag: Arc<AtomicBool>, budget: Duration) -> RunDeadline {
+        let (cancel, rx) = mpsc::channel::<()>();
+        let fired = Arc::new(AtomicBool::new(false));
+        let fired_t = Arc::clone(&fired);
+        std::thread::spawn(move || {
+            if rx.recv_timeout(budget) == Err(mpsc::RecvTimeoutError::Timeout) {
+                fired_t.store(true, Ordering::Release);
+                flag.store(true, Ordering::Release);
+            }
+        });
+        RunDeadline { _cancel: cancel, fired }
+    }
+
+    /// The budget ran out and the interrupt was raised
+    pub fn fired(&self) -> bool { self.fired.load(Ordering::Acquire) }
+}
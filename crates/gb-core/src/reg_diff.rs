@@ -0,0 +1,103 @@
+//! reg_diff — per-frame IO register and HRAM diffs for training files
+//!
+//! Full memory dumps per frame are too big for a training corpus, but the
+//! way a game programs its hardware is exactly what register-level models
+//! need to see. `RegDiffTracker` snapshots FF00-FF7F and HRAM after every
+//! frame and reports only the bytes that changed, as `[address, value]`
+//! pairs (`io_diff` / `hram_diff` in `mrom.train.v2`).
+
+use crate::Bus;
+use std::fmt::Write;
+
+/// IO registers FF00-FF7F as the game sees them, plus HRAM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegSnapshot {
+    pub io: [u8; 0x80],
+    pub hram: [u8; 0x7F],
+}
+
+impl RegSnapshot {
+    /// CPU-visible register values; APU registers report the value last
+    /// written, since they read back as 0xFF on this bus
+    pub fn capture(bus: &Bus) -> RegSnapshot {
+        let mut io = [0u8; 0x80];
+        for (r, v) in io.iter_mut().enumerate() {
+            *v = match r as u8 {
+                r @ 0x10..=0x3F => bus.apu.written_reg(r),
+                r => bus.read(0xFF00 | r as u16),
+            };
+        }
+        RegSnapshot { io, hram: bus.hram }
+    }
+
+    /// Bytes of `self` that differ from `prev`
+    pub fn diff(&self, prev: &RegSnapshot) -> RegDiff {
+        let mut diff = RegDiff::default();
+        self.diff_into(prev, &mut diff);
+        diff
+    }
+
+    /// `diff` into `out`, reusing its buffers
+    pub fn diff_into(&self, prev: &RegSnapshot, out: &mut RegDiff) {
+        let changed = |new: &[u8], old: &[u8], base: u16, out: &mut Vec<(u16, u8)>| {
+            out.clear();
+            out.extend(new.iter().zip(old).enumerate().filter(|(_, (n, o))| n != o).map(|(i, (n, _))| (base + i as u16, *n)));
+        };
+        changed(&self.io, &prev.io, 0xFF00, &mut out.io);
+        changed(&self.hram, &prev.hram, 0xFF80, &mut out.hram);
+    }
+}
+
+/// Changed (address, new value) pairs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegDiff {
+    pub io: Vec<(u16, u8)>,
+    pub hram: Vec<(u16, u8)>,
+}
+
+impl RegDiff {
+    pub fn is_empty(&self) -> bool { self.io.is_empty() && self.hram.is_empty() }
+
+    /// `"io_diff":[[a,v],...],"hram_diff":[[a,v],...],` — trailing comma, for
+    /// splicing into a frame record
+    pub fn to_json_fields(&self) -> String {
+        let mut out = String::new();
+        self.write_json_fields(&mut out);
+        out
+    }
+
+    /// Append `to_json_fields()` to `out`
+    pub fn write_json_fields(&self, out: &mut String) {
+        for (name, pairs) in [("io_diff", &self.io), ("hram_diff", &self.hram)] {
+            let _ = write!(out, "\"{name}\":[");
+            for (i, (a, v)) in pairs.iter().enumerate() {
+                let _ = write!(out, "{}[{},{}]", if i > 0 { "," } else { "" }, a, v);
+            }
+            out.push_str("],");
+        }
+    }
+}
+
+/// Diffs each frame against the previous one (the first against power-on)
+#[derive(Debug, Clone)]
+pub struct RegDiffTracker {
+    prev: RegSnapshot,
+}
+
+impl RegDiffTracker {
+    pub fn new(bus: &Bus) -> Self { RegDiffTracker { prev: RegSnapshot::capture(bus) } }
+
+    /// Diff since the last call; call once per frame after `run_frame`
+    pub fn frame_diff(&mut self, bus: &Bus) -> RegDiff {
+        let mut diff = RegDiff::default();
+        self.frame_diff_into(bus, &mut diff);
+        diff
+    }
+
+    /// `frame_diff` into `out`, reusing its buffers
+    pub fn frame_diff_into(&mut self, bus: &Bus, out: &mut RegDiff) {
+        let now = RegSnapshot::capture(bus);
+        now.diff_into(&self.prev, out);
+        self.prev = now;
+    }
+}
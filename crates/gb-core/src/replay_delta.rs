@@ -0,0 +1,213 @@
+//! replay_delta — memory change lists for mrom.replay.v2
+//!
+//! A v1 replay stores a full `mrom.snap.v1` snapshot (framebuffer included)
+//! for every frame. With `ReplayCapture::with_keyframes(n)` the manifest is
+//! `mrom.replay.v2` instead: every `n`th captured frame is a keyframe
+//! carrying the snapshot and all of memory, and the frames in between carry
+//! only the memory units that changed since the previous capture:
+//!
+//! | key   | region                         | unit      |
+//! |-------|--------------------------------|-----------|
+//! | `w`   | WRAM, banks 0-7                | 256-byte page |
+//! | `v`   | VRAM, banks 0-1                | 16-byte tile |
+//! | `o`   | OAM                            | 4-byte entry |
+//! | `io`  | FF00-FF7F as the CPU reads it  | whole     |
+//! | `h`   | HRAM and IE (FF80-FFFF)        | whole     |
+//! | `pal` | CGB BG then OBJ palette RAM    | whole     |
+//! | `s`   | cartridge RAM                  | 256-byte page |
+//!
+//! Each region is a list of `[unit, "hex"]` pairs; unchanged regions are
+//! left out. Every frame also records the register file, LY, LCDC and ROM
+//! bank. `ReplayReader` rebuilds any frame's memory from the keyframe before
+//! it; the picture is only stored on keyframes (use `"ph"` hashes for
+//! per-frame scene work).
+
+use crate::{Bus, Json, Registers};
+
+pub const REPLAY_V2_VERSION: &str = "mrom.replay.v2";
+/// Five seconds of frames between keyframes
+pub const DEFAULT_KEYFRAME_INTERVAL: u32 = 300;
+
+/// Region key and unit size, in manifest order
+const REGIONS: [(&str, usize); 7] = [("w", 0x100), ("v", 0x10), ("o", 4), ("io", 0x80), ("h", 0x80), ("pal", 0x80), ("s", 0x100)];
+
+/// The memory a v2 replay tracks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayMemory {
+    pub wram: Vec<u8>,
+    pub vram: Vec<u8>,
+    pub oam: Vec<u8>,
+    pub io: Vec<u8>,
+    pub hram: Vec<u8>,
+    pub palettes: Vec<u8>,
+    pub sram: Vec<u8>,
+}
+
+impl Default for ReplayMemory {
+    fn default() -> Self {
+        ReplayMemory {
+            wram: vec![0; 0x8000], vram: vec![0; 0x4000], oam: vec![0; 0xA0], io: vec![0; 0x80],
+            hram: vec![0; 0x80], palettes: vec![0; 0x80], sram: vec![],
+        }
+    }
+}
+
+impl ReplayMemory {
+    pub fn of(bus: &Bus) -> Self {
+        ReplayMemory {
+            wram: bus.wram.concat(),
+            vram: bus.vram.concat(),
+            oam: bus.oam.to_vec(),
+            io: (0xFF00..0xFF80).map(|a| bus.peek(a)).collect(),
+            hram: (0xFF80..=0xFFFF).map(|a| bus.peek(a)).collect(),
+            palettes: [bus.bg_cpal, bus.obj_cpal].concat(),
+            sram: bus.ram.clone(),
+        }
+    }
+
+    fn region(&self, key: &str) -> &[u8] {
+        match key {
+            "w" => &self.wram, "v" => &self.vram, "o" => &self.oam, "io" => &self.io,
+            "h" => &self.hram, "pal" => &self.palettes, _ => &self.sram,
+        }
+    }
+    fn region_mut(&mut self, key: &str) -> &mut Vec<u8> {
+        match key {
+            "w" => &mut self.wram, "v" => &mut self.vram, "o" => &mut self.oam, "io" => &mut self.io,
+            "h" => &mut self.hram, "pal" => &mut self.palettes, _ => &mut self.sram,
+        }
+    }
+}
+
+/// Changed memory units: (region key, unit index, new bytes)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryDelta {
+    pub changes: Vec<(&'static str, u32, Vec<u8>)>,
+}
+
+impl MemoryDelta {
+    /// Every unit of `mem` (a keyframe)
+    pub fn full(mem: &ReplayMemory) -> Self {
+        let changes = REGIONS.iter().flat_map(|&(key, unit)| {
+            mem.region(key).chunks(unit).enumerate().map(move |(i, c)| (key, i as u32, c.to_vec()))
+        }).collect();
+        MemoryDelta { changes }
+    }
+
+    /// The units of `new` that differ from `old`
+    pub fn between(old: &ReplayMemory, new: &ReplayMemory) -> Self {
+        let changes = REGIONS.iter().flat_map(|&(key, unit)| {
+            let (a, b) = (old.region(key), new.region(key));
+            b.chunks(unit).enumerate()
+                .filter(move |&(i, c)| a.get(i * unit..i * unit + c.len()) != Some(c))
+                .map(move |(i, c)| (key, i as u32, c.to_vec()))
+        }).collect();
+        MemoryDelta { changes }
+    }
+
+    pub fn is_empty(&self) -> bool { self.changes.is_empty() }
+
+    pub fn apply(&self, mem: &mut ReplayMemory) {
+        for (key, i, bytes) in &self.changes {
+            let unit = REGIONS.iter().find(|r| r.0 == *key).map_or(0, |r| r.1);
+            let region = mem.region_mut(key);
+            let at = *i as usize * unit;
+            if region.len() < at + bytes.len() { region.resize(at + bytes.len(), 0); }
+            region[at..at + bytes.len()].copy_from_slice(bytes);
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let regions: Vec<String> = REGIONS.iter().filter_map(|&(key, _)| {
+            let units: Vec<String> = self.changes.iter().filter(|c| c.0 == key)
+                .map(|(_, i, b)| format!("[{i},\"{}\"]", b.iter().map(|x| format!("{x:02x}")).collect::<String>()))
+                .collect();
+            (!units.is_empty()).then(|| format!("\"{key}\":[{}]", units.join(",")))
+        }).collect();
+        format!("{{{}}}", regions.join(","))
+    }
+
+    pub fn from_json(doc: &Json) -> Result<Self, String> {
+        let mut changes = vec![];
+        for &(key, unit) in &REGIONS {
+            let Some(units) = doc.get(key) else { continue };
+            for u in units.as_array().ok_or_else(|| format!("\"{key}\" is not a list"))? {
+                let pair = u.as_array().filter(|p| p.len() == 2).ok_or_else(|| format!("bad \"{key}\" entry"))?;
+                let i = pair[0].as_u64().ok_or_else(|| format!("bad \"{key}\" unit"))?;
+                let hex = pair[1].as_str().ok_or_else(|| format!("bad \"{key}\" bytes"))?;
+                let bytes = (0..hex.len() / 2).map(|j| u8::from_str_radix(hex.get(j * 2..j * 2 + 2)?, 16).ok())
+                    .collect::<Option<Vec<u8>>>().filter(|b| b.len() <= unit && hex.len() % 2 == 0)
+                    .ok_or_else(|| format!("bad \"{key}\" bytes at unit {i}"))?;
+                changes.push((key, i as u32, bytes));
+            }
+        }
+        Ok(MemoryDelta { changes })
+    }
+}
+
+/// One frame rebuilt by `ReplayReader`
+#[derive(Debug, Clone)]
+pub struct ReplayState {
+    pub frame_idx: u64,
+    pub regs: Registers,
+    pub ly: u8,
+    pub lcdc: u8,
+    pub rom_bank: u16,
+    pub memory: ReplayMemory,
+}
+
+#[derive(Debug)]
+struct ReaderFrame {
+    frame_idx: u64,
+    regs: Registers,
+    ly: u8,
+    lcdc: u8,
+    rom_bank: u16,
+    keyframe: bool,
+    delta: MemoryDelta,
+}
+
+/// Random access to the frames of an mrom.replay.v2 manifest
+#[derive(Debug)]
+pub struct ReplayReader {
+    frames: Vec<ReaderFrame>,
+}
+
+impl ReplayReader {
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let doc = Json::parse(text).map_err(|e| e.to_string())?;
+        let version = doc.get("version").and_then(Json::as_str).unwrap_or("");
+        if version != REPLAY_V2_VERSION { return Err(format!("unsupported replay version {version:?}")); }
+        let frames = doc.get("frames").and_then(Json::as_array).ok_or("replay has no frames array")?;
+        let frames = frames.iter().enumerate().map(|(n, f)| {
+            let num = |j: Option<&Json>, k: &str| j.and_then(|j| j.get(k)).and_then(Json::as_u64).unwrap_or(0);
+            let cpu = f.get("cpu");
+            let regs = Registers {
+                pc: num(Some(f), "pc") as u16, sp: num(cpu, "sp") as u16,
+                a: num(cpu, "a") as u8, f: num(cpu, "f") as u8, b: num(cpu, "b") as u8, c: num(cpu, "c") as u8,
+                d: num(cpu, "d") as u8, e: num(cpu, "e") as u8, h: num(cpu, "h") as u8, l: num(cpu, "l") as u8,
+            };
+            let mem = f.get("mem").ok_or_else(|| format!("frame {n} has no \"mem\""))?;
+            Ok(ReaderFrame {
+                frame_idx: num(Some(f), "fi"), regs,
+                ly: num(Some(f), "ly") as u8, lcdc: num(Some(f), "lcdc") as u8, rom_bank: num(Some(f), "rb") as u16,
+                keyframe: f.get("key").and_then(Json::as_bool).unwrap_or(false),
+                delta: MemoryDelta::from_json(mem).map_err(|e| format!("frame {n}: {e}"))?,
+            })
+        }).collect::<Result<Vec<_>, String>>()?;
+        if frames.first().is_some_and(|f| !f.keyframe) { return Err("replay does not start with a keyframe".into()); }
+        Ok(ReplayReader { frames })
+    }
+
+    pub fn len(&self) -> usize { self.frames.len() }
+    pub fn is_empty(&self) -> bool { self.frames.is_empty() }
+
+    /// The `n`th captured frame: its keyframe plus every change list since
+    pub fn state(&self, n: usize) -> Option<ReplayState> {
+        let f = self.frames.get(n)?;
+        let key = self.frames[..=n].iter().rposition(|f| f.keyframe)?;
+        let mut memory = ReplayMemory { sram: vec![], ..Default::default() };
+        for g in &self.frames[key..=n] { g.delta.apply(&mut memory); }
+        Some(ReplayState { frame_idx: f.frame_idx, regs: f.regs.clone(), ly: f.ly, lcdc: f.lcdc, rom_bank: f.rom_bank, memory })
+    }
+}
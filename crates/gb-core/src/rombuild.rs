@@ -0,0 +1,225 @@
+//! rombuild — construct and edit cartridge images programmatically
+//!
+//! `RomBuilder` produces a valid image from a few fluent calls: header fields,
+//! entry point, code placed at 0x0150 (or any `org`), Nintendo logo and both
+//! checksums fixed up on `build()`. `RomHeader` reads and rewrites the header
+//! of an existing image. `Code` is a small SM83 emitter for the handful of
+//! instructions synthetic ROMs and tests keep hand-assembling.
+
+use crate::{Cartridge, CartridgeKind, CoreError};
+
+/// Boot ROM logo check bytes (0x0104-0x0133)
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+/// Where code starts after the header
+pub const CODE_START: u16 = 0x0150;
+
+/// Header checksum over 0x0134-0x014C (checked by the boot ROM)
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[0x134..=0x14C].iter().fold(0u8, |x, &b| x.wrapping_sub(b).wrapping_sub(1))
+}
+
+/// Sum of every byte except the global checksum itself
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter().enumerate().filter(|(i, _)| *i != 0x14E && *i != 0x14F).fold(0u16, |s, (_, &b)| s.wrapping_add(b as u16))
+}
+
+/// Rewrite 0x014D and 0x014E-0x014F to match the image
+pub fn fix_checksums(rom: &mut [u8]) {
+    rom[0x14D] = header_checksum(rom);
+    let g = global_checksum(rom);
+    rom[0x14E..0x150].copy_from_slice(&g.to_be_bytes());
+}
+
+/// Editable cartridge header fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomHeader {
+    /// Up to 15 ASCII characters; longer titles are cut
+    pub title: String,
+    /// 0x0143: 0x00 DMG, 0x80 CGB-enhanced, 0xC0 CGB-only
+    pub cgb_flag: u8,
+    /// 0x0147 cartridge type
+    pub cart_type: u8,
+    /// 0x0148: ROM is 32 KiB << code
+    pub rom_size_code: u8,
+    /// 0x0149: 0 none, 2 8 KiB, 3 32 KiB, 4 128 KiB, 5 64 KiB
+    pub ram_size_code: u8,
+}
+
+impl RomHeader {
+    pub fn parse(rom: &[u8]) -> Result<RomHeader, CoreError> {
+        if rom.len() < 0x150 {
+            return Err(CoreError::invalid_rom(format!("ROM too short ({} bytes, the header ends at 0x150)", rom.len()), None));
+        }
+        Ok(RomHeader {
+            title: String::from_utf8_lossy(&rom[0x134..0x143]).trim_matches('\0').to_string(),
+            cgb_flag: rom[0x143],
+            cart_type: rom[0x147],
+            rom_size_code: rom[0x148],
+            ram_size_code: rom[0x149],
+        })
+    }
+
+    /// Write the fields into `rom` and fix both checksums
+    pub fn apply(&self, rom: &mut [u8]) {
+        let title = self.title.as_bytes();
+        let n = title.len().min(15);
+        rom[0x134..0x143].fill(0);
+        rom[0x134..0x134 + n].copy_from_slice(&title[..n]);
+        rom[0x143] = self.cgb_flag;
+        rom[0x147] = self.cart_type;
+        rom[0x148] = self.rom_size_code;
+        rom[0x149] = self.ram_size_code;
+        fix_checksums(rom);
+    }
+
+    pub fn logo_ok(rom: &[u8]) -> bool { rom.get(0x104..0x134) == Some(&NINTENDO_LOGO[..]) }
+    pub fn checksums_ok(rom: &[u8]) -> bool {
+        rom.len() >= 0x150 && rom[0x14D] == header_checksum(rom)
+            && u16::from_be_bytes([rom[0x14E], rom[0x14F]]) == global_checksum(rom)
+    }
+}
+
+/// 0x0149 code for a RAM size in KiB (unsupported sizes → no RAM)
+pub fn ram_size_code(kb: u32) -> u8 {
+    match kb { 8 => 2, 32 => 3, 128 => 4, 64 => 5, _ => 0 }
+}
+
+/// Cartridge type byte for a mapper; `with_ram` picks the RAM+BATTERY variant
+pub fn cart_type_for(kind: &CartridgeKind, with_ram: bool) -> u8 {
+    match (kind, with_ram) {
+        (CartridgeKind::RomOnly, _) => 0x00,
+        (CartridgeKind::Mbc1, false) => 0x01, (CartridgeKind::Mbc1, true) => 0x03,
+        (CartridgeKind::Mbc2, false) => 0x05, (CartridgeKind::Mbc2, true) => 0x06,
+        (CartridgeKind::Mbc3, false) => 0x11, (CartridgeKind::Mbc3, true) => 0x13,
+        (CartridgeKind::Mbc5, false) => 0x19, (CartridgeKind::Mbc5, true) => 0x1B,
+        (CartridgeKind::Unknown(b), _) => *b,
+    }
+}
+
+/// Fluent cartridge image builder
+#[derive(Debug, Clone)]
+pub struct RomBuilder {
+    header: RomHeader,
+    mapper: Option<CartridgeKind>,
+    rom_kb: u32,
+    ram_kb: u32,
+    logo: bool,
+    entry: Vec<u8>,
+    /// (ROM offset, bytes), applied in order
+    chunks: Vec<(usize, Vec<u8>)>,
+}
+
+impl Default for RomBuilder {
+    fn default() -> Self {
+        RomBuilder {
+            header: RomHeader { title: String::new(), cgb_flag: 0, cart_type: 0, rom_size_code: 0, ram_size_code: 0 },
+            mapper: None, rom_kb: 32, ram_kb: 0, logo: true,
+            entry: Code::new(0x100).nop().jp(CODE_START).into_bytes(),
+            chunks: vec![],
+        }
+    }
+}
+
+impl RomBuilder {
+    pub fn new() -> Self { Self::default() }
+    pub fn title(mut self, title: &str) -> Self { self.header.title = title.to_string(); self }
+    /// Mapper chip; the type byte also reflects whether RAM was requested
+    pub fn mapper(mut self, kind: CartridgeKind) -> Self { self.mapper = Some(kind); self }
+    /// Raw 0x0147 type byte (overrides `mapper`)
+    pub fn cart_type(mut self, b: u8) -> Self { self.mapper = None; self.header.cart_type = b; self }
+    /// ROM size in KiB: 32 × 2^n, rounded up
+    pub fn rom_kb(mut self, kb: u32) -> Self { self.rom_kb = kb.max(32).next_power_of_two(); self }
+    pub fn ram_kb(mut self, kb: u32) -> Self { self.ram_kb = kb; self }
+    pub fn cgb(mut self, flag: u8) -> Self { self.header.cgb_flag = flag; self }
+    /// Leave the logo area blank (a real boot ROM would refuse the cart)
+    pub fn without_logo(mut self) -> Self { self.logo = false; self }
+    /// Bytes at 0x0100 (4 bytes fit before the logo); default `NOP; JP 0x0150`
+    pub fn entry(mut self, bytes: &[u8]) -> Self { self.entry = bytes.to_vec(); self }
+    /// Code at CODE_START
+    pub fn code(self, bytes: &[u8]) -> Self { self.org(CODE_START as usize, bytes) }
+    /// Bytes at any ROM offset (bank n starts at n × 0x4000)
+    pub fn org(mut self, offset: usize, bytes: &[u8]) -> Self { self.chunks.push((offset, bytes.to_vec())); self }
+    /// Assemble `src` (see `asm`) and place every chunk at its address;
+    /// code before the first `org` lands at CODE_START
+    pub fn asm(mut self, src: &str) -> Result<Self, String> {
+        for (addr, bytes) in crate::asm::assemble(src)?.chunks { self.chunks.push((addr as usize, bytes)); }
+        Ok(self)
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let end = self.chunks.iter().map(|(o, b)| o + b.len()).max().unwrap_or(0);
+        let kb = (self.rom_kb as usize).max(end.div_ceil(1024).next_power_of_two()).max(32);
+        let mut rom = vec![0u8; kb * 1024];
+        rom[0x100..0x100 + self.entry.len().min(4)].copy_from_slice(&self.entry[..self.entry.len().min(4)]);
+        if self.logo { rom[0x104..0x134].copy_from_slice(&NINTENDO_LOGO); }
+        for (offset, bytes) in &self.chunks { rom[*offset..offset + bytes.len()].copy_from_slice(bytes); }
+        let mut header = self.header.clone();
+        header.rom_size_code = (kb / 32).trailing_zeros() as u8;
+        header.ram_size_code = ram_size_code(self.ram_kb);
+        if let Some(kind) = &self.mapper { header.cart_type = cart_type_for(kind, header.ram_size_code != 0); }
+        header.apply(&mut rom);
+        rom
+    }
+
+    pub fn cartridge(&self) -> Result<Cartridge, CoreError> { Cartridge::from_bytes(self.build()) }
+}
+
+/// Tiny SM83 emitter. `origin` is the address of the first byte, so `here()`
+/// gives jump targets without counting bytes by hand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Code {
+    origin: u16,
+    bytes: Vec<u8>,
+}
+
+impl Code {
+    pub fn new(origin: u16) -> Self { Code { origin, bytes: vec![] } }
+    /// Address of the next byte
+    pub fn here(&self) -> u16 { self.origin.wrapping_add(self.bytes.len() as u16) }
+    pub fn bytes(&self) -> &[u8] { &self.bytes }
+    pub fn into_bytes(self) -> Vec<u8> { self.bytes }
+
+    pub fn raw(mut self, b: &[u8]) -> Self { self.bytes.extend_from_slice(b); self }
+    fn op16(self, op: u8, nn: u16) -> Self { let [lo, hi] = nn.to_le_bytes(); self.raw(&[op, lo, hi]) }
+
+    pub fn nop(self) -> Self { self.raw(&[0x00]) }
+    pub fn di(self) -> Self { self.raw(&[0xF3]) }
+    pub fn ei(self) -> Self { self.raw(&[0xFB]) }
+    pub fn halt(self) -> Self { self.raw(&[0x76]) }
+    pub fn ret(self) -> Self { self.raw(&[0xC9]) }
+    pub fn reti(self) -> Self { self.raw(&[0xD9]) }
+    /// LD A,n
+    pub fn ld_a(self, n: u8) -> Self { self.raw(&[0x3E, n]) }
+    pub fn ld_bc(self, nn: u16) -> Self { self.op16(0x01, nn) }
+    pub fn ld_de(self, nn: u16) -> Self { self.op16(0x11, nn) }
+    pub fn ld_hl(self, nn: u16) -> Self { self.op16(0x21, nn) }
+    pub fn ld_sp(self, nn: u16) -> Self { self.op16(0x31, nn) }
+    /// LD (nn),A
+    pub fn st_a(self, nn: u16) -> Self { self.op16(0xEA, nn) }
+    /// LD A,(nn)
+    pub fn ld_a_from(self, nn: u16) -> Self { self.op16(0xFA, nn) }
+    /// LDH (FF00+n),A
+    pub fn ldh_st(self, n: u8) -> Self { self.raw(&[0xE0, n]) }
+    /// LDH A,(FF00+n)
+    pub fn ldh_ld(self, n: u8) -> Self { self.raw(&[0xF0, n]) }
+    pub fn jp(self, nn: u16) -> Self { self.op16(0xC3, nn) }
+    pub fn call(self, nn: u16) -> Self { self.op16(0xCD, nn) }
+    /// JR to an absolute address (must be within -128..=127 of the next instruction)
+    pub fn jr(self, target: u16) -> Self {
+        let off = target.wrapping_sub(self.here().wrapping_add(2)) as i16;
+        self.raw(&[0x18, off as i8 as u8])
+    }
+    /// JR -2: spin forever
+    pub fn spin(self) -> Self { self.raw(&[0x18, 0xFE]) }
+    /// Write `reg` = `val` through A (LD A,val / LDH (reg),A)
+    pub fn set_io(self, reg: u8, val: u8) -> Self { self.ld_a(val).ldh_st(reg) }
+    /// Send `text` out of the serial port, one internal-clock transfer per byte
+    pub fn serial_print(self, text: &str) -> Self {
+        text.bytes().fold(self, |c, b| c.set_io(0x01, b).set_io(0x02, 0x81))
+    }
+}
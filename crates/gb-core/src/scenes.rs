@@ -0,0 +1,162 @@
+//! scenes — scene segmentation over replay / training manifests
+//!
+//! A scene boundary is cut where the picture changes abruptly (pHash distance
+//! to the previous frame above a threshold), where the LCD is switched off or
+//! back on, or where the game switches ROM bank. Each scene gets a keyframe
+//! (its middle frame) for dataset browsing; see the `letsplay_scenes` tool.
+
+use crate::{phash_distance, phash_image, Json, LCD_HEIGHT, LCD_WIDTH, REPLAY_V2_VERSION};
+
+/// Per-frame signals used for segmentation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneFrame {
+    pub frame: u64,
+    pub phash: Option<u64>,
+    pub lcd_on: bool,
+    pub rom_bank: u16,
+}
+
+/// Why a scene started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneCut {
+    Start,
+    Visual { distance: u32 },
+    LcdOff,
+    LcdOn,
+    BankSwitch { from: u16, to: u16 },
+}
+impl SceneCut {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SceneCut::Start => "start", SceneCut::Visual { .. } => "visual",
+            SceneCut::LcdOff => "lcd_off", SceneCut::LcdOn => "lcd_on",
+            SceneCut::BankSwitch { .. } => "bank_switch",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scene {
+    pub index: usize,
+    pub start_frame: u64,
+    pub end_frame: u64,
+    pub keyframe: u64,
+    pub cut: SceneCut,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SceneConfig {
+    /// pHash Hamming distance (0-64) that counts as a visual cut
+    pub phash_threshold: u32,
+    /// Visual and bank-switch cuts are ignored until a scene is this long;
+    /// LCD transitions always cut
+    pub min_scene_frames: u64,
+}
+impl Default for SceneConfig {
+    fn default() -> Self { SceneConfig { phash_threshold: 16, min_scene_frames: 10 } }
+}
+
+/// Split `frames` (in frame order) into scenes
+pub fn detect_scenes(frames: &[SceneFrame], cfg: &SceneConfig) -> Vec<Scene> {
+    let mut scenes: Vec<Scene> = Vec::new();
+    let Some(first) = frames.first() else { return scenes };
+    let mut start = first.frame;
+    let mut cut = SceneCut::Start;
+    let close = |scenes: &mut Vec<Scene>, start: u64, end: u64, cut: SceneCut| {
+        scenes.push(Scene { index: scenes.len(), start_frame: start, end_frame: end, keyframe: start + end.saturating_sub(start) / 2, cut });
+    };
+
+    for w in frames.windows(2) {
+        let (prev, cur) = (&w[0], &w[1]);
+        // Frame numbers should rise, but a spliced or hand-edited manifest may
+        // go back; that only makes the scene look short
+        let long_enough = cur.frame.saturating_sub(start) >= cfg.min_scene_frames;
+        let next = if prev.lcd_on && !cur.lcd_on {
+            Some(SceneCut::LcdOff)
+        } else if !prev.lcd_on && cur.lcd_on {
+            Some(SceneCut::LcdOn)
+        } else if long_enough && prev.rom_bank != cur.rom_bank {
+            Some(SceneCut::BankSwitch { from: prev.rom_bank, to: cur.rom_bank })
+        } else {
+            match (prev.phash, cur.phash) {
+                (Some(a), Some(b)) if long_enough && cur.lcd_on && phash_distance(a, b) > cfg.phash_threshold =>
+                    Some(SceneCut::Visual { distance: phash_distance(a, b) }),
+                _ => None,
+            }
+        };
+        if let Some(next) = next {
+            close(&mut scenes, start, prev.frame, cut);
+            start = cur.frame;
+            cut = next;
+        }
+    }
+    close(&mut scenes, start, frames[frames.len() - 1].frame, cut);
+    scenes
+}
+
+/// Read segmentation signals from an mrom.train.v1/v2 or mrom.replay.v1/v2 manifest.
+/// Replay frames without a stored `ph` are hashed from the snapshot framebuffer;
+/// v2 only has one on keyframes, so record v2 replays with `ph` for scene work.
+pub fn scene_frames_from_manifest(doc: &Json) -> Result<Vec<SceneFrame>, String> {
+    let version = doc.get("version").and_then(Json::as_str).unwrap_or("");
+    let frames = doc.get("frames").and_then(Json::as_array).ok_or("manifest has no frames array")?;
+    let hex_hash = |v: Option<&Json>| v.and_then(Json::as_str).and_then(|h| u64::from_str_radix(h, 16).ok());
+    match version {
+        "mrom.train.v1" | "mrom.train.v2" => Ok(frames.iter().map(|f| SceneFrame {
+            frame: f.get("frame").and_then(Json::as_u64).unwrap_or(0),
+            phash: hex_hash(f.get("phash")),
+            lcd_on: f.get("lcdc").and_then(Json::as_u64).unwrap_or(0x80) & 0x80 != 0,
+            rom_bank: f.get("rom_bank").and_then(Json::as_u64).unwrap_or(1) as u16,
+        }).collect()),
+        "mrom.replay.v1" | REPLAY_V2_VERSION => Ok(frames.iter().map(|f| {
+            let snap = f.get("snap");
+            // v2 keeps LCDC and the ROM bank on every frame, outside the snapshot
+            let field = |k: &str| f.get(k).or_else(|| snap.and_then(|s| s.get(k))).and_then(Json::as_u64);
+            let phash = hex_hash(f.get("ph")).or_else(|| {
+                snap.and_then(|s| s.get("fb")).and_then(Json::as_str)
+                    .and_then(decode_rgb_hex)
+                    .map(|rgb| phash_image(&rgb_to_luma(&rgb), LCD_WIDTH, LCD_HEIGHT))
+            });
+            SceneFrame {
+                frame: f.get("fi").and_then(Json::as_u64).unwrap_or(0),
+                phash,
+                lcd_on: field("lcdc").unwrap_or(0x80) & 0x80 != 0,
+                rom_bank: field("rb").unwrap_or(1) as u16,
+            }
+        }).collect()),
+        other => Err(format!("unsupported manifest version {other:?}")),
+    }
+}
+
+/// Decode a snapshot `fb` hex string into RGB888 bytes
+pub fn decode_rgb_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() != LCD_WIDTH * LCD_HEIGHT * 6 { return None; }
+    (0..hex.len() / 2).map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()).collect()
+}
+
+/// RGB888 to 8-bit luma (for hashing colour frames)
+pub fn rgb_to_luma(rgb: &[u8]) -> Vec<u8> {
+    rgb.chunks_exact(3)
+        .map(|p| ((p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000) as u8)
+        .collect()
+}
+
+/// Scene index manifest (mrom.scenes.v1); `keyframe_png` names each scene's PNG, if exported
+pub fn scenes_to_json(source: &str, scenes: &[Scene], keyframe_png: &dyn Fn(&Scene) -> Option<String>) -> String {
+    let entries: Vec<String> = scenes.iter().map(|s| {
+        let detail = match s.cut {
+            SceneCut::Visual { distance } => format!(",\"distance\":{distance}"),
+            SceneCut::BankSwitch { from, to } => format!(",\"from_bank\":{from},\"to_bank\":{to}"),
+            _ => String::new(),
+        };
+        let png = keyframe_png(s).map(|p| format!(",\"png\":\"{p}\"")).unwrap_or_default();
+        format!(
+            "    {{\"index\":{},\"start\":{},\"end\":{},\"keyframe\":{},\"cut\":\"{}\"{}{}}}",
+            s.index, s.start_frame, s.end_frame, s.keyframe, s.cut.as_str(), detail, png
+        )
+    }).collect();
+    format!(
+        "{{\n  \"version\": \"mrom.scenes.v1\",\n  \"source\": \"{}\",\n  \"scene_count\": {},\n  \"scenes\": [\n{}\n  ]\n}}",
+        source.replace('\\', "\\\\").replace('"', "\\\""), scenes.len(), entries.join(",\n")
+    )
+}
@@ -0,0 +1,317 @@
+//! scheduler — persistent multi-ROM job queue for long-running batch captures
+//!
+//! `letsplay_batch --queue=FILE` takes its ROMs from a queue file instead of
+//! a single directory scan, so a capture farm can add and remove ROMs while a
+//! run is in progress:
+//!
+//! ```text
+//! {"version":"mrom.queue.v1","jobs":[
+//!   {"rom":"roms/a.gb","priority":10,"frames":3600,"retries":2,"retry":"transient",
+//!    "attempts":0,"status":"pending","error":null}, ...]}
+//! ```
+//!
+//! The next job is the pending one with the highest priority, oldest first
+//! among equals. `frames` overrides the run's frames per ROM (null: the
+//! run's default). A failed job is attempted again up to `retries` more
+//! times, at the back of its priority, when the `retry` policy covers the
+//! failure: `never`, `transient` (watchdog trips and I/O errors; the default)
+//! or `always` (also panics and bad cartridges). Only `rom` is required when
+//! writing jobs by hand.
+//!
+//! Every change is a read-modify-write of the file under `<file>.lock`
+//! (`JobQueue::update`), and the runner re-reads the file before each job, so
+//! jobs added, re-prioritised or removed by another process take effect at
+//! the next pick. A lock older than `LOCK_STALE` was left by a killed process
+//! and is broken. Jobs still `running` when a runner starts were interrupted
+//! by a crash and go back to pending (`requeue_running`): one runner per
+//! queue file.
+
+use crate::settings::esc;
+use crate::Json;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const QUEUE_VERSION: &str = "mrom.queue.v1";
+/// A lock file this old is left over from a killed process
+pub const LOCK_STALE: Duration = Duration::from_secs(10);
+const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus { Pending, Running, Done, Failed }
+
+impl JobStatus {
+    pub fn as_str(self) -> &'static str {
+        match self { JobStatus::Pending => "pending", JobStatus::Running => "running", JobStatus::Done => "done", JobStatus::Failed => "failed" }
+    }
+    pub fn parse(s: &str) -> Option<JobStatus> {
+        match s {
+            "pending" => Some(JobStatus::Pending),
+            "running" => Some(JobStatus::Running),
+            "done" => Some(JobStatus::Done),
+            "failed" => Some(JobStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Which failures a job is retried after
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RetryPolicy {
+    Never,
+    /// Watchdog trips and I/O errors
+    #[default]
+    Transient,
+    /// Any failure, panics included
+    Always,
+}
+
+impl RetryPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self { RetryPolicy::Never => "never", RetryPolicy::Transient => "transient", RetryPolicy::Always => "always" }
+    }
+    pub fn parse(s: &str) -> Option<RetryPolicy> {
+        match s {
+            "never" => Some(RetryPolicy::Never),
+            "transient" => Some(RetryPolicy::Transient),
+            "always" => Some(RetryPolicy::Always),
+            _ => None,
+        }
+    }
+    fn covers(self, outcome: &JobOutcome) -> bool {
+        match outcome {
+            JobOutcome::Done => false,
+            JobOutcome::Transient(_) => self != RetryPolicy::Never,
+            JobOutcome::Failed(_) => self == RetryPolicy::Always,
+        }
+    }
+}
+
+/// How an attempt at a job ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobOutcome {
+    Done,
+    /// A failure that may not happen again (watchdog, I/O)
+    Transient(String),
+    /// A failure the same ROM will repeat (panic, bad cartridge)
+    Failed(String),
+}
+
+/// One ROM in the queue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueJob {
+    /// ROM file, as given (relative paths resolve against the runner's directory)
+    pub rom: String,
+    /// Higher runs first
+    pub priority: i32,
+    /// Frames to capture; None = the run's default
+    pub frames: Option<u64>,
+    /// Attempts allowed after the first failure
+    pub retries: u32,
+    pub retry: RetryPolicy,
+    /// Attempts started so far
+    pub attempts: u32,
+    pub status: JobStatus,
+    /// Error of the last failed attempt
+    pub error: Option<String>,
+}
+
+impl QueueJob {
+    /// A pending job with priority 0, the default frames and 2 transient retries
+    pub fn new(rom: &str) -> QueueJob {
+        QueueJob {
+            rom: rom.to_string(), priority: 0, frames: None, retries: 2, retry: RetryPolicy::default(),
+            attempts: 0, status: JobStatus::Pending, error: None,
+        }
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"rom\":\"{}\",\"priority\":{},\"frames\":{},\"retries\":{},\"retry\":\"{}\",\"attempts\":{},\"status\":\"{}\",\"error\":{}}}",
+            esc(&self.rom), self.priority, self.frames.map_or("null".into(), |f| f.to_string()), self.retries,
+            self.retry.as_str(), self.attempts, self.status.as_str(),
+            self.error.as_ref().map_or("null".into(), |e| format!("\"{}\"", esc(e)))
+        )
+    }
+
+    fn from_json(j: &Json) -> Result<QueueJob, String> {
+        let rom = j.get("rom").and_then(Json::as_str).ok_or("job without \"rom\"")?;
+        let mut job = QueueJob::new(rom);
+        let field = |key: &str| j.get(key).filter(|v| **v != Json::Null);
+        if let Some(p) = field("priority") {
+            job.priority = p.as_f64().filter(|p| p.fract() == 0.0 && p.abs() <= i32::MAX as f64)
+                .ok_or_else(|| format!("{rom}: bad priority"))? as i32;
+        }
+        if let Some(f) = field("frames") { job.frames = Some(f.as_u64().ok_or_else(|| format!("{rom}: bad frames"))?); }
+        if let Some(r) = field("retries") {
+            job.retries = r.as_u64().and_then(|r| u32::try_from(r).ok()).ok_or_else(|| format!("{rom}: bad retries"))?;
+        }
+        if let Some(r) = field("retry") {
+            job.retry = r.as_str().and_then(RetryPolicy::parse).ok_or_else(|| format!("{rom}: retry is never, transient or always"))?;
+        }
+        if let Some(a) = field("attempts") {
+            job.attempts = a.as_u64().and_then(|a| u32::try_from(a).ok()).ok_or_else(|| format!("{rom}: bad attempts"))?;
+        }
+        if let Some(s) = field("status") {
+            job.status = s.as_str().and_then(JobStatus::parse).ok_or_else(|| format!("{rom}: bad status"))?;
+        }
+        job.error = field("error").and_then(Json::as_str).map(str::to_string);
+        Ok(job)
+    }
+}
+
+/// The jobs of one queue file, in queue order
+#[derive(Debug, Clone)]
+pub struct JobQueue {
+    path: PathBuf,
+    jobs: Vec<QueueJob>,
+}
+
+impl JobQueue {
+    /// Read the queue at `path`; a missing file is an empty queue
+    pub fn open(path: &Path) -> io::Result<JobQueue> {
+        let mut queue = JobQueue { path: path.to_path_buf(), jobs: vec![] };
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(queue),
+            Err(e) => return Err(e),
+        };
+        let bad = |msg: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {msg}", path.display()));
+        let doc = Json::parse(&text).map_err(|e| bad(e.to_string()))?;
+        match doc.get("version").and_then(Json::as_str) {
+            Some(QUEUE_VERSION) => {}
+            v => return Err(bad(format!("expected version {QUEUE_VERSION}, got {v:?}"))),
+        }
+        for j in doc.get("jobs").and_then(Json::as_array).unwrap_or_default() {
+            queue.jobs.push(QueueJob::from_json(j).map_err(bad)?);
+        }
+        Ok(queue)
+    }
+
+    /// Apply `f` to the queue at `path` and write it back, holding the queue
+    /// lock so concurrent updates from other processes are not lost
+    pub fn update<R>(path: &Path, f: impl FnOnce(&mut JobQueue) -> R) -> io::Result<R> {
+        let _lock = QueueLock::acquire(path)?;
+        let mut queue = JobQueue::open(path)?;
+        let r = f(&mut queue);
+        queue.save()?;
+        Ok(r)
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+    pub fn jobs(&self) -> &[QueueJob] { &self.jobs }
+    pub fn job(&self, rom: &str) -> Option<&QueueJob> { self.jobs.iter().find(|j| j.rom == rom) }
+    pub fn pending(&self) -> usize { self.jobs.iter().filter(|j| j.status == JobStatus::Pending).count() }
+
+    /// Queue `job`, replacing the entry for the same ROM. A running job keeps
+    /// its status and attempts and only takes the new settings
+    pub fn add(&mut self, job: QueueJob) {
+        match self.jobs.iter_mut().find(|j| j.rom == job.rom) {
+            Some(j) if j.status == JobStatus::Running => {
+                (j.priority, j.frames, j.retries, j.retry) = (job.priority, job.frames, job.retries, job.retry);
+            }
+            Some(j) => *j = job,
+            None => self.jobs.push(job),
+        }
+    }
+
+    /// Drop a ROM's job; a running attempt finishes but is not recorded
+    pub fn remove(&mut self, rom: &str) -> bool {
+        let before = self.jobs.len();
+        self.jobs.retain(|j| j.rom != rom);
+        self.jobs.len() != before
+    }
+
+    /// Mark the next job running and return it: highest priority first,
+    /// then queue order
+    pub fn claim_next(&mut self) -> Option<QueueJob> {
+        let (_, job) = self.jobs.iter_mut().enumerate()
+            .filter(|(_, j)| j.status == JobStatus::Pending)
+            .min_by_key(|(i, j)| (std::cmp::Reverse(j.priority), *i))?;
+        job.status = JobStatus::Running;
+        job.attempts += 1;
+        Some(job.clone())
+    }
+
+    /// Record how an attempt at `rom` ended and return the job's new status
+    /// (None if it was removed meanwhile). A retried job moves to the back
+    /// of the queue
+    pub fn finish(&mut self, rom: &str, outcome: JobOutcome) -> Option<JobStatus> {
+        let i = self.jobs.iter().position(|j| j.rom == rom)?;
+        let job = &mut self.jobs[i];
+        let retry = job.retry.covers(&outcome) && job.attempts <= job.retries;
+        job.error = match outcome { JobOutcome::Done => None, JobOutcome::Transient(e) | JobOutcome::Failed(e) => Some(e) };
+        job.status = match (&job.error, retry) {
+            (None, _) => JobStatus::Done,
+            (Some(_), true) => JobStatus::Pending,
+            (Some(_), false) => JobStatus::Failed,
+        };
+        let status = job.status;
+        if retry {
+            let job = self.jobs.remove(i);
+            self.jobs.push(job);
+        }
+        Some(status)
+    }
+
+    /// Put jobs left running by an interrupted run back to pending
+    pub fn requeue_running(&mut self) -> usize {
+        let mut n = 0;
+        for j in self.jobs.iter_mut().filter(|j| j.status == JobStatus::Running) {
+            j.status = JobStatus::Pending;
+            n += 1;
+        }
+        n
+    }
+
+    pub fn to_json(&self) -> String {
+        let jobs: Vec<String> = self.jobs.iter().map(QueueJob::to_json).collect();
+        format!("{{\n  \"version\": \"{}\",\n  \"jobs\": [\n    {}\n  ]\n}}\n", QUEUE_VERSION, jobs.join(",\n    "))
+    }
+
+    /// Write the queue back atomically (see `SettingsStore::save`); use
+    /// `update` when another process may write the file too
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, self.to_json())?;
+        std::fs::rename(&tmp, &self.path)
+    }
+}
+
+/// `<queue>.lock`, held while one process rewrites the queue
+struct QueueLock(PathBuf);
+
+impl QueueLock {
+    fn acquire(queue: &Path) -> io::Result<QueueLock> {
+        let mut name = queue.as_os_str().to_owned();
+        name.push(".lock");
+        let path = PathBuf::from(name);
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let start = Instant::now();
+        loop {
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(QueueLock(path)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let age = std::fs::metadata(&path).and_then(|m| m.modified()).ok().and_then(|t| t.elapsed().ok());
+                    if age.is_some_and(|a| a > LOCK_STALE) {
+                        let _ = std::fs::remove_file(&path);
+                    } else if start.elapsed() > LOCK_TIMEOUT {
+                        return Err(io::Error::new(io::ErrorKind::WouldBlock, format!("{} is held by another process", path.display())));
+                    } else {
+                        std::thread::sleep(Duration::from_millis(10));
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for QueueLock {
+    fn drop(&mut self) { let _ = std::fs::remove_file(&self.0); }
+}
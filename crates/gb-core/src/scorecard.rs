@@ -0,0 +1,152 @@
+//! scorecard — accuracy scorecards over suites of test ROMs
+//!
+//! A suite is a directory of test ROMs graded against one subsystem (cpu,
+//! timer, ppu, ...). `letsplay_scorecard` runs every suite through the
+//! `test_rom` harness and writes an `mrom.scorecard.v1` document; comparing it
+//! with the previous scorecard yields the regressions that fail a nightly run.
+//! The top-level passed/total/pass_rate/core/frames fields are what the
+//! planner reads as evidence; `artifact_id` / `rom_sha` name the game the
+//! run vouches for, and the planner ignores a scorecard that names neither.
+
+use crate::json::Json;
+use crate::test_rom::TestRun;
+
+/// Frame budget for suites that do not set one (~60 s of emulated time)
+pub const DEFAULT_SUITE_FRAMES: u64 = 3600;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suite {
+    pub name: String,
+    pub subsystem: String,
+    /// ROM directory, relative to the test-ROM root
+    pub dir: String,
+    pub frames: u64,
+}
+
+/// Parse a suites file: `{"suites":[{"name","subsystem","dir","frames"?}]}`.
+/// `subsystem` defaults to the name and `dir` to the name.
+pub fn parse_suites(doc: &Json) -> Result<Vec<Suite>, String> {
+    let list = doc.get("suites").and_then(Json::as_array).ok_or("missing \"suites\" array")?;
+    list.iter().enumerate().map(|(i, s)| {
+        let name = s.get("name").and_then(Json::as_str).ok_or(format!("suites[{i}]: missing \"name\""))?;
+        let field = |k: &str| s.get(k).and_then(Json::as_str).unwrap_or(name).to_string();
+        Ok(Suite {
+            name: name.to_string(),
+            subsystem: field("subsystem"),
+            dir: field("dir"),
+            frames: s.get("frames").and_then(Json::as_u64).unwrap_or(DEFAULT_SUITE_FRAMES),
+        })
+    }).collect()
+}
+
+/// One graded ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreEntry {
+    pub suite: String,
+    pub subsystem: String,
+    /// ROM file name within the suite directory
+    pub rom: String,
+    /// "pass" / "fail" / "timeout"
+    pub outcome: String,
+    pub frames: u64,
+    pub detail: String,
+}
+
+impl ScoreEntry {
+    pub fn from_run(suite: &Suite, rom: &str, run: &TestRun) -> ScoreEntry {
+        let detail = match &run.outcome { crate::test_rom::TestOutcome::Fail(d) => d.clone(), _ => String::new() };
+        ScoreEntry {
+            suite: suite.name.clone(), subsystem: suite.subsystem.clone(), rom: rom.to_string(),
+            outcome: run.outcome.as_str().to_string(), frames: run.frames, detail,
+        }
+    }
+    pub fn passed(&self) -> bool { self.outcome == "pass" }
+    fn key(&self) -> (&str, &str) { (&self.suite, &self.rom) }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scorecard {
+    pub core: String,
+    /// Planner artifact this scorecard is evidence for
+    pub artifact_id: Option<String>,
+    /// SHA-256 of that artifact's ROM, lower-case hex (`sha256_hex`)
+    pub rom_sha: Option<String>,
+    pub entries: Vec<ScoreEntry>,
+}
+
+fn esc(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl Scorecard {
+    pub fn passed(&self) -> usize { self.entries.iter().filter(|e| e.passed()).count() }
+    pub fn total(&self) -> usize { self.entries.len() }
+    pub fn pass_rate(&self) -> f64 {
+        if self.entries.is_empty() { 0.0 } else { self.passed() as f64 / self.total() as f64 }
+    }
+
+    /// (subsystem, passed, total) in first-seen order
+    pub fn by_subsystem(&self) -> Vec<(String, usize, usize)> {
+        let mut out: Vec<(String, usize, usize)> = vec![];
+        for e in &self.entries {
+            let i = match out.iter().position(|(s, _, _)| *s == e.subsystem) {
+                Some(i) => i,
+                None => { out.push((e.subsystem.clone(), 0, 0)); out.len() - 1 }
+            };
+            out[i].1 += e.passed() as usize;
+            out[i].2 += 1;
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let subs: Vec<String> = self.by_subsystem().iter().map(|(s, p, t)| {
+            format!("    \"{}\": {{\"passed\":{p},\"total\":{t}}}", esc(s))
+        }).collect();
+        let results: Vec<String> = self.entries.iter().map(|e| format!(
+            "    {{\"suite\":\"{}\",\"subsystem\":\"{}\",\"rom\":\"{}\",\"outcome\":\"{}\",\"frames\":{},\"detail\":\"{}\"}}",
+            esc(&e.suite), esc(&e.subsystem), esc(&e.rom), e.outcome, e.frames, esc(&e.detail)
+        )).collect();
+        let binding: String = [("artifact_id", &self.artifact_id), ("rom_sha", &self.rom_sha)].iter()
+            .filter_map(|(k, v)| v.as_ref().map(|v| format!("  \"{k}\": \"{}\",\n", esc(v))))
+            .collect();
+        format!(
+            "{{\n  \"version\": \"mrom.scorecard.v1\",\n  \"core\": \"{}\",\n{}  \"passed\": {},\n  \"total\": {},\n  \"pass_rate\": {:.4},\n  \"frames\": {},\n  \"subsystems\": {{\n{}\n  }},\n  \"results\": [\n{}\n  ]\n}}",
+            esc(&self.core), binding, self.passed(), self.total(), self.pass_rate(),
+            self.entries.iter().map(|e| e.frames).sum::<u64>(),
+            subs.join(",\n"), results.join(",\n")
+        )
+    }
+
+    /// Read back an `mrom.scorecard.v1` document
+    pub fn from_json(doc: &Json) -> Result<Scorecard, String> {
+        let version = doc.get("version").and_then(Json::as_str).unwrap_or("");
+        if version != "mrom.scorecard.v1" { return Err(format!("unsupported scorecard version {version:?}")); }
+        let s = |v: &Json, k: &str| v.get(k).and_then(Json::as_str).unwrap_or("").to_string();
+        let entries = doc.get("results").and_then(Json::as_array).unwrap_or(&[]).iter().map(|r| ScoreEntry {
+            suite: s(r, "suite"), subsystem: s(r, "subsystem"), rom: s(r, "rom"), outcome: s(r, "outcome"),
+            frames: r.get("frames").and_then(Json::as_u64).unwrap_or(0), detail: s(r, "detail"),
+        }).collect();
+        let opt = |k: &str| doc.get(k).and_then(Json::as_str).map(str::to_string);
+        Ok(Scorecard { core: s(doc, "core"), artifact_id: opt("artifact_id"), rom_sha: opt("rom_sha"), entries })
+    }
+
+    /// ROMs that passed in `previous` but not now, and subsystems whose pass count dropped
+    pub fn regressions(&self, previous: &Scorecard) -> Vec<String> {
+        let mut out = vec![];
+        for p in previous.entries.iter().filter(|e| e.passed()) {
+            match self.entries.iter().find(|e| e.key() == p.key()) {
+                Some(e) if e.passed() => {}
+                Some(e) => out.push(format!("{}/{}: pass → {}{}", p.suite, p.rom, e.outcome,
+                    if e.detail.is_empty() { String::new() } else { format!(" ({})", e.detail) })),
+                None => out.push(format!("{}/{}: passed previously, not run", p.suite, p.rom)),
+            }
+        }
+        let now = self.by_subsystem();
+        for (sub, was, _) in previous.by_subsystem() {
+            let is = now.iter().find(|(s, _, _)| *s == sub).map_or(0, |(_, p, _)| *p);
+            if is < was { out.push(format!("subsystem {sub}: {was} → {is} passing")); }
+        }
+        out
+    }
+}
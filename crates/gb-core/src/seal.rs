@@ -0,0 +1,167 @@
+//! seal — optional authenticated encryption of savestate and replay files
+//!
+//! Savestates and replays hold cartridge RAM, VRAM and framebuffers, which
+//! can count as licensed content once they sit on shared capture storage.
+//! With the `encrypt` feature and `METAROM_STATE_KEY` set (64 hex digits, a
+//! 256-bit key), every savestate and replay file gb-core writes is sealed
+//! with ChaCha20-Poly1305:
+//!
+//! ```text
+//! "MROMSEAL" | version u8 (1) | nonce (12 bytes, random) | ciphertext | tag (16 bytes)
+//! ```
+//!
+//! The 9 header bytes are authenticated as associated data, so a file that
+//! was cut, altered or sealed with another key fails to open rather than
+//! loading garbage. Loading files is transparent: `GbCore::load_state_from_file`
+//! and `read_sealed` open sealed data with the key from the environment, so
+//! fleets can switch encryption on without touching readers. In-memory
+//! `save_state` / `load_state` round trips never see the key. With a key
+//! set, unsealed files are refused (someone could swap in a forged
+//! plaintext state) unless `METAROM_ALLOW_PLAINTEXT_STATES=1` lets them
+//! through while an existing store is being migrated.
+//!
+//! A key set in a build without the feature is an error on write (nothing
+//! is stored in the clear by mistake), as is a sealed file without a key or
+//! the feature on read. `StateIndex::scan` lists sealed states under
+//! `skipped`: their metadata is encrypted too.
+
+use crate::CoreError;
+use std::borrow::Cow;
+use std::io;
+use std::path::Path;
+
+pub const SEAL_MAGIC: &[u8; 8] = b"MROMSEAL";
+pub const SEAL_VERSION: u8 = 1;
+/// Environment variable holding the key (64 hex digits)
+pub const STATE_KEY_ENV: &str = "METAROM_STATE_KEY";
+/// Set to `1` to accept unsealed files while a key is set (migration only)
+pub const ALLOW_PLAINTEXT_ENV: &str = "METAROM_ALLOW_PLAINTEXT_STATES";
+
+#[cfg(feature = "encrypt")]
+const HEADER_LEN: usize = 9;
+#[cfg(feature = "encrypt")]
+const NONCE_LEN: usize = 12;
+
+/// 256-bit ChaCha20-Poly1305 key
+#[derive(Clone, PartialEq, Eq)]
+pub struct StateKey([u8; 32]);
+
+impl StateKey {
+    pub fn new(bytes: [u8; 32]) -> Self { StateKey(bytes) }
+
+    /// Parse 64 hex digits (surrounding whitespace allowed)
+    pub fn from_hex(hex: &str) -> Result<StateKey, CoreError> {
+        let hex = hex.trim();
+        let bad = || CoreError::InvalidState(format!("{STATE_KEY_ENV}: expected 64 hex digits"));
+        if hex.len() != 64 || !hex.is_ascii() { return Err(bad()); }
+        let mut key = [0u8; 32];
+        for (b, pair) in key.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+            *b = u8::from_str_radix(std::str::from_utf8(pair).map_err(|_| bad())?, 16).map_err(|_| bad())?;
+        }
+        Ok(StateKey(key))
+    }
+
+    /// The key in `METAROM_STATE_KEY`; None when it is unset or empty
+    pub fn from_env() -> Result<Option<StateKey>, CoreError> {
+        match std::env::var(STATE_KEY_ENV) {
+            Ok(hex) if !hex.trim().is_empty() => StateKey::from_hex(&hex).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl std::fmt::Debug for StateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str("StateKey(..)") }
+}
+
+#[cfg(feature = "encrypt")]
+fn header() -> [u8; HEADER_LEN] {
+    let mut h = [0u8; HEADER_LEN];
+    h[..8].copy_from_slice(SEAL_MAGIC);
+    h[8] = SEAL_VERSION;
+    h
+}
+
+/// Data starts with the sealed-file header
+pub fn is_sealed(data: &[u8]) -> bool { data.starts_with(SEAL_MAGIC) }
+
+/// Encrypt `data` under `key` with a fresh random nonce
+#[cfg(feature = "encrypt")]
+pub fn seal(key: &StateKey, data: &[u8]) -> Vec<u8> {
+    use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+    use chacha20poly1305::{ChaCha20Poly1305, Key};
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key.0));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let aad = header();
+    let sealed = cipher.encrypt(&nonce, Payload { msg: data, aad: &aad }).expect("ChaCha20-Poly1305 accepts any length we write");
+    [&aad[..], &nonce[..], &sealed].concat()
+}
+
+/// Decrypt and authenticate data written by `seal`
+#[cfg(feature = "encrypt")]
+pub fn unseal(key: &StateKey, data: &[u8]) -> Result<Vec<u8>, CoreError> {
+    use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+    if !is_sealed(data) { return Err(CoreError::InvalidState("unseal: not a sealed file".into())); }
+    if data.len() < HEADER_LEN + NONCE_LEN + 16 { return Err(CoreError::InvalidState("unseal: sealed file is truncated".into())); }
+    if data[8] != SEAL_VERSION {
+        return Err(CoreError::InvalidState(format!("unseal: unknown sealed-file version {}", data[8])));
+    }
+    let (aad, rest) = data.split_at(HEADER_LEN);
+    let (nonce, msg) = rest.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(Key::from_slice(&key.0))
+        .decrypt(Nonce::from_slice(nonce), Payload { msg, aad })
+        .map_err(|_| CoreError::InvalidState("unseal: wrong key or damaged file".into()))
+}
+
+/// `data` as it should be stored: sealed when `METAROM_STATE_KEY` is set
+pub fn seal_for_storage(data: &[u8]) -> Result<Cow<'_, [u8]>, CoreError> {
+    let Some(key) = StateKey::from_env()? else { return Ok(Cow::Borrowed(data)) };
+    #[cfg(feature = "encrypt")]
+    { Ok(Cow::Owned(seal(&key, data))) }
+    #[cfg(not(feature = "encrypt"))]
+    {
+        let _ = key;
+        Err(CoreError::InvalidState(format!("{STATE_KEY_ENV} is set but gb-core was built without the encrypt feature")))
+    }
+}
+
+/// Plain contents of stored `data`: sealed data is opened with the key in
+/// `METAROM_STATE_KEY`; unsealed data passes through only when no key is set
+/// or `METAROM_ALLOW_PLAINTEXT_STATES=1`
+pub fn open_stored(data: &[u8]) -> Result<Cow<'_, [u8]>, CoreError> {
+    let key = StateKey::from_env()?;
+    if !is_sealed(data) {
+        if key.is_some() && std::env::var(ALLOW_PLAINTEXT_ENV).as_deref() != Ok("1") {
+            return Err(CoreError::InvalidState(format!(
+                "unsealed file refused while {STATE_KEY_ENV} is set (set {ALLOW_PLAINTEXT_ENV}=1 to migrate plain files)"
+            )));
+        }
+        return Ok(Cow::Borrowed(data));
+    }
+    let Some(key) = key else {
+        return Err(CoreError::InvalidState(format!("sealed file: set {STATE_KEY_ENV} to open it")));
+    };
+    #[cfg(feature = "encrypt")]
+    { unseal(&key, data).map(Cow::Owned) }
+    #[cfg(not(feature = "encrypt"))]
+    {
+        let _ = key;
+        Err(CoreError::InvalidState("sealed file: gb-core was built without the encrypt feature".into()))
+    }
+}
+
+fn io_error(e: CoreError) -> io::Error {
+    match e { CoreError::IoError(e) => e, e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()) }
+}
+
+/// Write a savestate / replay file, sealed when a key is set
+pub fn write_sealed(path: &Path, data: &[u8]) -> io::Result<()> {
+    std::fs::write(path, seal_for_storage(data).map_err(io_error)?)
+}
+
+/// Read a file `write_sealed` wrote (plain files as `open_stored` allows)
+pub fn read_sealed(path: &Path) -> io::Result<Vec<u8>> {
+    let data = std::fs::read(path)?;
+    Ok(open_stored(&data).map_err(io_error)?.into_owned())
+}
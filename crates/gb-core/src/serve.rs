@@ -0,0 +1,267 @@
+//! serve — read-only HTTP endpoints for monitoring a running core (feature `http`)
+//!
+//! The emulation thread publishes a `ServeSnapshot` after each frame; the
+//! server answers from the latest one, so requests never stall or perturb
+//! emulation. Plain HTTP/1.0 on std sockets, one request per connection,
+//! GET only. Each connection gets its own thread (at most `MAX_CONNECTIONS`
+//! at once) and `CONNECTION_DEADLINE` in all to send a request head of at
+//! most `MAX_REQUEST_HEAD` bytes and take the answer:
+//!
+//! - `/state` — mrom.snap.v1 JSON (`GbCore::state_json`)
+//! - `/memory/wram?offset=N&len=N` — WRAM as seen at 0xC000-0xDFFF (bank 0 +
+//!   the selected bank), hex in JSON; `len` defaults to 256
+//! - `/screenshot.png` — current framebuffer, with `GbCore::overlay`
+//!   annotations blended in
+//! - `/metrics` — the shared `Metrics` registry (Prometheus text), with
+//!   frame / cycle / fps gauges refreshed from the snapshot
+
+use crate::{encode_png_rgb, GbCore, MetricKind, Metrics, LCD_HEIGHT, LCD_WIDTH, METRIC_FPS, METRIC_FRAMES};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// WRAM visible at 0xC000-0xDFFF
+pub const WRAM_VIEW_LEN: usize = 0x2000;
+/// Request line plus headers; longer heads are answered with 400
+pub const MAX_REQUEST_HEAD: usize = 8192;
+/// Time a connection has for its whole request and response
+pub const CONNECTION_DEADLINE: Duration = Duration::from_secs(5);
+/// Connections served at once; further ones are answered with 503
+pub const MAX_CONNECTIONS: usize = 16;
+
+/// What the server can answer from, refreshed by the emulation thread
+#[derive(Debug, Clone)]
+pub struct ServeSnapshot {
+    pub rom_title: String,
+    pub frame: u64,
+    pub t_cycles: u64,
+    /// Emulated frames per host second, as measured by the publisher
+    pub fps: f64,
+    pub state_json: String,
+    pub wram: Vec<u8>,
+    pub framebuffer_rgb: Vec<u8>,
+}
+
+impl ServeSnapshot {
+    pub fn capture(core: &GbCore, rom_title: &str, fps: f64) -> Self {
+        let mut wram = Vec::with_capacity(WRAM_VIEW_LEN);
+        wram.extend_from_slice(&core.bus.wram[0]);
+        wram.extend_from_slice(&core.bus.wram[(core.bus.wram_bank as usize).clamp(1, 7)]);
+        ServeSnapshot {
+            rom_title: rom_title.to_string(),
+            frame: core.clock.frame_count(),
+            t_cycles: core.clock.t_cycles,
+            fps,
+            state_json: core.state_json(),
+            wram,
+            framebuffer_rgb: core.framebuffer_rgb_annotated(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn new(status: u16, content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        HttpResponse { status, content_type, body: body.into() }
+    }
+    fn error(status: u16, msg: &str) -> Self { Self::new(status, "text/plain", format!("{msg}\n")) }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let reason = match self.status { 200 => "OK", 400 => "Bad Request", 404 => "Not Found", 405 => "Method Not Allowed", 503 => "Service Unavailable", _ => "Error" };
+        let mut out = format!(
+            "HTTP/1.0 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+            self.status, reason, self.content_type, self.body.len()
+        ).into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+pub const METRIC_CYCLES: &str = "mrom_cycles_total";
+pub const METRIC_UPTIME: &str = "mrom_uptime_seconds";
+pub const METRIC_HTTP_REQUESTS: &str = "mrom_http_requests_total";
+pub const METRIC_HTTP_ERRORS: &str = "mrom_http_errors_total";
+
+fn describe_serve_metrics(m: &Metrics) {
+    m.describe(METRIC_CYCLES, MetricKind::Counter, "T-cycles emulated");
+    m.describe(METRIC_UPTIME, MetricKind::Gauge, "Seconds since the server started");
+    m.describe(METRIC_HTTP_REQUESTS, MetricKind::Counter, "HTTP requests served");
+    m.describe(METRIC_HTTP_ERRORS, MetricKind::Counter, "HTTP requests answered with 4xx");
+}
+
+/// Answer one request line (`GET /path?query HTTP/1.1`), counting it in `metrics`
+pub fn route(request_line: &str, snap: &ServeSnapshot, metrics: &Metrics, uptime: Duration) -> HttpResponse {
+    describe_serve_metrics(metrics);
+    metrics.inc(METRIC_HTTP_REQUESTS);
+    let resp = route_inner(request_line, snap, metrics, uptime);
+    if resp.status >= 400 { metrics.inc(METRIC_HTTP_ERRORS); }
+    resp
+}
+
+fn route_inner(request_line: &str, snap: &ServeSnapshot, metrics: &Metrics, uptime: Duration) -> HttpResponse {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return HttpResponse::error(400, "malformed request line");
+    };
+    if method != "GET" { return HttpResponse::error(405, "read-only: GET only"); }
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let param = |k: &str| query.split('&').find_map(|kv| kv.strip_prefix(k)?.strip_prefix('='));
+    match path {
+        "/state" => HttpResponse::new(200, "application/json", snap.state_json.clone()),
+        "/screenshot.png" => HttpResponse::new(200, "image/png",
+            encode_png_rgb(LCD_WIDTH as u32, LCD_HEIGHT as u32, &snap.framebuffer_rgb)),
+        "/metrics" => {
+            metrics.set(METRIC_FRAMES, snap.frame as f64);
+            metrics.set(METRIC_CYCLES, snap.t_cycles as f64);
+            metrics.set(METRIC_FPS, snap.fps);
+            metrics.set(METRIC_UPTIME, uptime.as_secs_f64());
+            HttpResponse::new(200, "text/plain; version=0.0.4", metrics.encode())
+        }
+        "/memory/wram" => {
+            let num = |k, default| param(k).map_or(Ok(default), |v| parse_num(v).ok_or(k));
+            let (offset, len) = match (num("offset", 0), num("len", 256)) {
+                (Ok(o), Ok(l)) => (o, l),
+                (Err(k), _) | (_, Err(k)) => return HttpResponse::error(400, &format!("bad {k}")),
+            };
+            if offset >= WRAM_VIEW_LEN { return HttpResponse::error(400, "offset past end of WRAM"); }
+            let end = offset.saturating_add(len).min(WRAM_VIEW_LEN);
+            let hex: String = snap.wram[offset..end].iter().map(|b| format!("{:02x}", b)).collect();
+            HttpResponse::new(200, "application/json", format!(
+                "{{\"frame\":{},\"address\":{},\"offset\":{},\"len\":{},\"hex\":\"{}\"}}",
+                snap.frame, 0xC000 + offset, offset, end - offset, hex))
+        }
+        _ => HttpResponse::error(404, "endpoints: /state /memory/wram /screenshot.png /metrics"),
+    }
+}
+
+/// Decimal or 0x-prefixed hex
+fn parse_num(s: &str) -> Option<usize> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(h) => usize::from_str_radix(h, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Background server; stops (and joins) on drop
+pub struct HttpServer {
+    pub addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    /// Bind `addr` (port 0 picks a free one) and serve from `snapshot`;
+    /// /metrics renders `metrics`, which the caller may also update
+    pub fn spawn(addr: &str, snapshot: Arc<Mutex<ServeSnapshot>>, metrics: Arc<Metrics>) -> io::Result<HttpServer> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_t = Arc::clone(&stop);
+        let thread = std::thread::spawn(move || {
+            let started = Instant::now();
+            let open = Arc::new(AtomicUsize::new(0));
+            while !stop_t.load(Ordering::Acquire) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let deadline = Instant::now() + CONNECTION_DEADLINE;
+                        if open.fetch_add(1, Ordering::AcqRel) >= MAX_CONNECTIONS {
+                            open.fetch_sub(1, Ordering::AcqRel);
+                            let _ = respond(&stream, &HttpResponse::error(503, "too many connections"), deadline);
+                            continue;
+                        }
+                        let (snapshot, metrics, open) = (Arc::clone(&snapshot), Arc::clone(&metrics), Arc::clone(&open));
+                        std::thread::spawn(move || {
+                            let _ = handle(stream, &snapshot, &metrics, started, deadline);
+                            open.fetch_sub(1, Ordering::AcqRel);
+                        });
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(10)),
+                    Err(_) => {}
+                }
+            }
+        });
+        Ok(HttpServer { addr, stop, thread: Some(thread) })
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(t) = self.thread.take() { let _ = t.join(); }
+    }
+}
+
+fn handle(stream: TcpStream, snapshot: &Mutex<ServeSnapshot>, metrics: &Metrics, started: Instant, deadline: Instant) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    let Some(request_line) = read_head(&stream, deadline)? else {
+        describe_serve_metrics(metrics);
+        metrics.inc(METRIC_HTTP_REQUESTS);
+        metrics.inc(METRIC_HTTP_ERRORS);
+        respond(&stream, &HttpResponse::error(400, "request head too large"), deadline)?;
+        // Closing with unread input would reset the connection under the answer
+        stream.shutdown(Shutdown::Write)?;
+        io::copy(&mut DeadlineReader { stream: &stream, deadline }.take(1 << 16), &mut io::sink())?;
+        return Ok(());
+    };
+    let resp = {
+        let snap = snapshot.lock().unwrap_or_else(|p| p.into_inner());
+        route(request_line.trim_end(), &snap, metrics, started.elapsed())
+    };
+    respond(&stream, &resp, deadline)
+}
+
+/// The request line, after draining the headers (bodies are never
+/// expected); None if the head runs past `MAX_REQUEST_HEAD` bytes
+fn read_head(stream: &TcpStream, deadline: Instant) -> io::Result<Option<String>> {
+    let mut reader = BufReader::new(DeadlineReader { stream, deadline }.take(MAX_REQUEST_HEAD as u64));
+    let (mut request_line, mut line) = (Vec::new(), Vec::new());
+    loop {
+        line.clear();
+        reader.read_until(b'\n', &mut line)?;
+        if !line.ends_with(b"\n") {
+            if reader.get_ref().limit() == 0 { return Ok(None); }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if request_line.is_empty() { request_line = std::mem::take(&mut line); }
+        else if line.len() <= 2 { break; }
+    }
+    Ok(Some(String::from_utf8_lossy(&request_line).into_owned()))
+}
+
+fn respond(mut stream: &TcpStream, resp: &HttpResponse, deadline: Instant) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(remaining(deadline)?))?;
+    stream.write_all(&resp.to_bytes())?;
+    stream.flush()
+}
+
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    match deadline.checked_duration_since(Instant::now()) {
+        Some(d) if !d.is_zero() => Ok(d),
+        _ => Err(io::ErrorKind::TimedOut.into()),
+    }
+}
+
+/// Reads that fail once `deadline` has passed, however slowly bytes trickle in
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(remaining(self.deadline)?))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
@@ -0,0 +1,156 @@
+//! session — mrom.session.v1: one provenance document per run
+//!
+//! `manifest.json` (mrom.artifacts.v1) only lists what is in a ROM's artifact
+//! directory. The session manifest records how a run produced it: the ROM
+//! hash, the `CoreConfig` it ran under, the CompatibilityPlan that chose this
+//! core (if the run was planned), and every input and output file with its
+//! size and FNV-1a checksum, so a later stage can tell which replay, states
+//! and training data belong together and whether any of them changed since.
+//!
+//! ```text
+//! <out>/<rom_hash>/session.json
+//! ```
+//!
+//! Paths under the artifact directory are stored relative to it; files
+//! elsewhere (the plan, an input movie) keep the path they were given.
+
+use crate::{fnv1a, CoreConfig, Json, RomArtifacts};
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const SESSION_VERSION: &str = "mrom.session.v1";
+
+/// What a file is to the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionRole {
+    /// CompatibilityPlan (ucf-planner) the run was made under
+    Plan,
+    /// Recorded input driving the run
+    InputMovie,
+    Replay,
+    State,
+    /// mrom.train.v1 / v2
+    Training,
+    Console,
+    Audio,
+    /// Cycle-stamped IO register writes
+    IoLog,
+    /// Executed-code ranges
+    ExecCoverage,
+    /// Per-opcode / per-address execution profile
+    Profile,
+}
+
+impl SessionRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionRole::Plan => "plan",
+            SessionRole::InputMovie => "input_movie",
+            SessionRole::Replay => "replay",
+            SessionRole::State => "state",
+            SessionRole::Training => "training",
+            SessionRole::Console => "console",
+            SessionRole::Audio => "audio",
+            SessionRole::IoLog => "io_log",
+            SessionRole::ExecCoverage => "exec_coverage",
+            SessionRole::Profile => "profile",
+        }
+    }
+}
+
+/// One file with its checksum at the time the session was written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionFile {
+    pub role: SessionRole,
+    pub path: String,
+    pub bytes: u64,
+    /// FNV-1a of the contents, hex (the same hash as `rom_hash`)
+    pub fnv1a: String,
+}
+
+impl SessionFile {
+    fn to_json(&self) -> String {
+        format!("{{\"role\":\"{}\",\"path\":\"{}\",\"bytes\":{},\"fnv1a\":\"{}\"}}",
+            self.role.as_str(), esc(&self.path), self.bytes, self.fnv1a)
+    }
+}
+
+/// Identity of the plan, read from the plan file when it parses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionPlan {
+    pub file: SessionFile,
+    pub plan_id: Option<String>,
+    pub strategy: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionManifest {
+    pub rom_hash: String,
+    pub rom_title: String,
+    /// ROM path as given on the command line
+    pub source: String,
+    pub config: CoreConfig,
+    pub frames: u64,
+    pub plan: Option<SessionPlan>,
+    pub files: Vec<SessionFile>,
+    /// Artifact directory relative paths are taken against
+    artifacts: RomArtifacts,
+}
+
+impl SessionManifest {
+    pub fn new(artifacts: &RomArtifacts, rom_title: &str, source: &str, config: &CoreConfig) -> Self {
+        SessionManifest {
+            rom_hash: artifacts.rom_hash.clone(), rom_title: rom_title.to_string(), source: source.to_string(),
+            config: *config, frames: 0, plan: None, files: vec![], artifacts: artifacts.clone(),
+        }
+    }
+
+    /// Record a file as it is now (read it and checksum it)
+    pub fn add(&mut self, role: SessionRole, path: &Path) -> io::Result<()> {
+        let file = self.file(role, path)?;
+        self.files.push(file);
+        Ok(())
+    }
+
+    /// Record the CompatibilityPlan JSON the run was made under
+    pub fn set_plan(&mut self, path: &Path) -> io::Result<()> {
+        let file = self.file(SessionRole::Plan, path)?;
+        let doc = std::fs::read_to_string(path).ok().and_then(|t| Json::parse(&t).ok());
+        let field = |k: &str| doc.as_ref().and_then(|d| d.get(k)).and_then(Json::as_str).map(str::to_string);
+        self.plan = Some(SessionPlan { plan_id: field("plan_id"), strategy: field("strategy"), file });
+        Ok(())
+    }
+
+    fn file(&self, role: SessionRole, path: &Path) -> io::Result<SessionFile> {
+        let data = std::fs::read(path)?;
+        let shown = path.strip_prefix(&self.artifacts.dir).unwrap_or(path);
+        Ok(SessionFile {
+            role, path: shown.to_string_lossy().replace('\\', "/"),
+            bytes: data.len() as u64, fnv1a: format!("{:08x}", fnv1a(&data)),
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let plan = match &self.plan {
+            None => "null".to_string(),
+            Some(p) => {
+                let opt = |v: &Option<String>| v.as_ref().map_or("null".to_string(), |s| format!("\"{}\"", esc(s)));
+                format!("{{\"plan_id\":{},\"strategy\":{},\"file\":{}}}", opt(&p.plan_id), opt(&p.strategy), p.file.to_json())
+            }
+        };
+        let files: Vec<String> = self.files.iter().map(SessionFile::to_json).collect();
+        format!(
+            "{{\n  \"version\": \"{}\",\n  \"rom_hash\": \"{}\",\n  \"rom_title\": \"{}\",\n  \"source\": \"{}\",\n  \"config\": {},\n  \"frames\": {},\n  \"plan\": {},\n  \"files\": [\n    {}\n  ]\n}}",
+            SESSION_VERSION, self.rom_hash, esc(&self.rom_title), esc(&self.source),
+            self.config.to_json(), self.frames, plan, files.join(",\n    ")
+        )
+    }
+
+    /// Write `session.json` into the artifact directory
+    pub fn write(&self) -> io::Result<PathBuf> {
+        std::fs::write(self.artifacts.session(), self.to_json())?;
+        Ok(self.artifacts.session())
+    }
+}
+
+fn esc(s: &str) -> String { s.replace('\\', "\\\\").replace('"', "\\\"") }
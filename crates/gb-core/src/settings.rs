@@ -0,0 +1,258 @@
+//! settings — persistent user settings with per-game overrides
+//!
+//! One JSON file per user (mrom.settings.v1) holds defaults and overrides
+//! keyed by ROM hash, so preferences made in one frontend or binary carry over
+//! to the next session and to the others:
+//!
+//! ```text
+//! $METAROM_SETTINGS, else <config dir>/metarom/settings.json
+//! {"version":"mrom.settings.v1","defaults":{...},"games":{"<rom_hash>":{"title":"...",...}}}
+//! ```
+//!
+//! `<config dir>` is `$XDG_CONFIG_HOME`, `%APPDATA%` or `~/.config`. Each
+//! entry may set the DMG palette, the accuracy profile (console model and
+//! lite mode), cheat codes and an input map; a game's fields win over the
+//! defaults. `SettingsStore::core_for` applies them when a cartridge is
+//! loaded. Cheat codes are stored for frontends as entered; the core does
+//! not interpret them. Frontends change entries with `set_game` /
+//! `defaults` and write the file back with `save`.
+
+use crate::{button_from_name, rom_hash, Cartridge, CoreConfig, DmgColors, GbCore, HardwareModel, InputMapping, Json, LiteMode, RenderSkip};
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const SETTINGS_VERSION: &str = "mrom.settings.v1";
+/// Environment variable naming the settings file, overriding the default location
+pub const SETTINGS_ENV: &str = "METAROM_SETTINGS";
+
+/// RGB colours of the four DMG shades, lightest first
+pub type DmgPalette = [(u8, u8, u8); 4];
+/// The greyscale `framebuffer_rgb` uses unless a palette is set
+pub const DMG_GREYSCALE: DmgPalette = [(255, 255, 255), (170, 170, 170), (85, 85, 85), (0, 0, 0)];
+
+/// One settings entry; unset fields fall through to the defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameSettings {
+    pub palette: Option<DmgPalette>,
+    /// Accuracy profile: console the core starts as
+    pub model: Option<HardwareModel>,
+    /// Accuracy profile: output reductions (see `lite.rs`)
+    pub lite: Option<LiteMode>,
+    /// Game Genie / GameShark codes as entered
+    pub cheats: Vec<String>,
+    pub input_map: Option<InputMapping>,
+}
+
+impl GameSettings {
+    pub fn is_empty(&self) -> bool { *self == GameSettings::default() }
+
+    /// `self` with every field `over` sets replaced
+    pub fn overlay(&self, over: &GameSettings) -> GameSettings {
+        GameSettings {
+            palette: over.palette.or(self.palette),
+            model: over.model.or(self.model),
+            lite: over.lite.or(self.lite),
+            cheats: if over.cheats.is_empty() { self.cheats.clone() } else { over.cheats.clone() },
+            input_map: over.input_map.clone().or_else(|| self.input_map.clone()),
+        }
+    }
+
+    /// Construction-time part: model and lite mode
+    pub fn apply_config(&self, config: &mut CoreConfig) {
+        if let Some(m) = self.model { config.model = m; }
+        if let Some(l) = self.lite { config.lite = l; }
+    }
+
+    /// Runtime part: the DMG palette, for BG and sprites alike
+    pub fn apply(&self, core: &mut GbCore) {
+        if let Some(p) = self.palette { core.dmg_colors = DmgColors::uniform(p); }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut fields = vec![];
+        if let Some(p) = self.palette {
+            let colours: Vec<String> = p.iter().map(|(r, g, b)| format!("\"#{r:02x}{g:02x}{b:02x}\"")).collect();
+            fields.push(format!("\"palette\":[{}]", colours.join(",")));
+        }
+        if let Some(m) = self.model { fields.push(format!("\"model\":\"{}\"", m.as_str())); }
+        if let Some(l) = self.lite { fields.push(format!("\"lite\":{}", l.to_json())); }
+        if !self.cheats.is_empty() {
+            let codes: Vec<String> = self.cheats.iter().map(|c| format!("\"{}\"", esc(c))).collect();
+            fields.push(format!("\"cheats\":[{}]", codes.join(",")));
+        }
+        if let Some(map) = &self.input_map {
+            let lines: Vec<String> = map.bindings.iter()
+                .map(|(c, b)| format!("\"{} = {}\"", esc(c), button_name(*b)))
+                .collect();
+            fields.push(format!("\"input_map\":[{}]", lines.join(",")));
+        }
+        format!("{{{}}}", fields.join(","))
+    }
+
+    pub fn from_json(doc: &Json) -> Result<GameSettings, String> {
+        let mut s = GameSettings::default();
+        if let Some(p) = doc.get("palette") {
+            let colours = p.as_array().filter(|a| a.len() == 4).ok_or("palette: expected 4 colours")?;
+            let mut palette = DMG_GREYSCALE;
+            for (slot, c) in palette.iter_mut().zip(colours) {
+                *slot = c.as_str().and_then(parse_colour).ok_or("palette: expected \"#RRGGBB\"")?;
+            }
+            s.palette = Some(palette);
+        }
+        if let Some(m) = doc.get("model") {
+            s.model = Some(m.as_str().and_then(HardwareModel::parse).ok_or("model: expected dmg / mgb / sgb / cgb")?);
+        }
+        if let Some(l) = doc.get("lite") {
+            let render = match l.get("render") {
+                None => RenderSkip::Full,
+                Some(r) => r.as_str().and_then(RenderSkip::parse).ok_or("lite.render: unknown mode")?,
+            };
+            s.lite = Some(LiteMode { skip_audio: l.get("skip_audio").and_then(Json::as_bool).unwrap_or(false), render });
+        }
+        if let Some(c) = doc.get("cheats") {
+            let codes = c.as_array().ok_or("cheats: expected an array")?;
+            s.cheats = codes.iter().map(|c| c.as_str().map(str::to_string).ok_or("cheats: expected strings"))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(m) = doc.get("input_map") {
+            let lines = m.as_array().ok_or("input_map: expected an array")?;
+            let text: Vec<&str> = lines.iter().map(|l| l.as_str().ok_or("input_map: expected strings"))
+                .collect::<Result<_, _>>()?;
+            s.input_map = Some(InputMapping::parse(&text.join("\n")).map_err(|e| format!("input_map: {e}"))?);
+        }
+        Ok(s)
+    }
+}
+
+/// One game's overrides
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameEntry {
+    pub rom_hash: String,
+    /// Cartridge title, for people reading the file
+    pub title: String,
+    pub settings: GameSettings,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsStore {
+    path: PathBuf,
+    pub defaults: GameSettings,
+    games: Vec<GameEntry>,
+}
+
+/// `$METAROM_SETTINGS`, else `settings.json` under the user's config directory
+pub fn default_settings_path() -> Option<PathBuf> {
+    if let Some(p) = std::env::var_os(SETTINGS_ENV) { return Some(p.into()); }
+    let dir = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(dir.join("metarom").join("settings.json"))
+}
+
+impl SettingsStore {
+    /// Read the store at `path`; a missing file is an empty store
+    pub fn open(path: &Path) -> io::Result<SettingsStore> {
+        let mut store = SettingsStore { path: path.to_path_buf(), defaults: GameSettings::default(), games: vec![] };
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(store),
+            Err(e) => return Err(e),
+        };
+        let bad = |msg: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {msg}", path.display()));
+        let doc = Json::parse(&text).map_err(|e| bad(e.to_string()))?;
+        match doc.get("version").and_then(Json::as_str) {
+            Some(SETTINGS_VERSION) => {}
+            v => return Err(bad(format!("expected version {SETTINGS_VERSION}, got {v:?}"))),
+        }
+        if let Some(d) = doc.get("defaults") { store.defaults = GameSettings::from_json(d).map_err(|e| bad(format!("defaults: {e}")))?; }
+        if let Some(Json::Obj(games)) = doc.get("games") {
+            for (hash, g) in games {
+                let settings = GameSettings::from_json(g).map_err(|e| bad(format!("{hash}: {e}")))?;
+                let title = g.get("title").and_then(Json::as_str).unwrap_or("").to_string();
+                store.games.push(GameEntry { rom_hash: hash.clone(), title, settings });
+            }
+        }
+        Ok(store)
+    }
+
+    /// `open` at `default_settings_path()`
+    pub fn open_default() -> io::Result<SettingsStore> {
+        let path = default_settings_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no settings location (set METAROM_SETTINGS or HOME)"))?;
+        Self::open(&path)
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+    pub fn games(&self) -> impl Iterator<Item = &GameEntry> { self.games.iter() }
+    /// A game's own overrides, without the defaults
+    pub fn game(&self, rom_hash: &str) -> Option<&GameSettings> {
+        self.games.iter().find(|g| g.rom_hash == rom_hash).map(|g| &g.settings)
+    }
+
+    /// Defaults with the cartridge's overrides applied
+    pub fn for_cartridge(&self, cart: &Cartridge) -> GameSettings {
+        match self.game(&rom_hash(&cart.rom)) {
+            Some(g) => self.defaults.overlay(g),
+            None => self.defaults.clone(),
+        }
+    }
+
+    /// Build a core for `cart` from `config` with the stored settings applied
+    pub fn core_for(&self, cart: Cartridge, mut config: CoreConfig) -> GbCore {
+        let settings = self.for_cartridge(&cart);
+        settings.apply_config(&mut config);
+        let mut core = GbCore::with_config(cart, config);
+        settings.apply(&mut core);
+        core
+    }
+
+    /// Replace a game's overrides; empty settings remove the entry
+    pub fn set_game(&mut self, rom_hash: &str, title: &str, settings: GameSettings) {
+        self.games.retain(|g| g.rom_hash != rom_hash);
+        if !settings.is_empty() {
+            self.games.push(GameEntry { rom_hash: rom_hash.to_string(), title: title.to_string(), settings });
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let games: Vec<String> = self.games.iter().map(|g| {
+            let fields = g.settings.to_json();
+            let sep = if fields.len() > 2 { "," } else { "" };
+            format!("\"{}\": {{\"title\":\"{}\"{sep}{}", esc(&g.rom_hash), esc(&g.title), &fields[1..])
+        }).collect();
+        format!("{{\n  \"version\": \"{}\",\n  \"defaults\": {},\n  \"games\": {{\n    {}\n  }}\n}}\n",
+            SETTINGS_VERSION, self.defaults.to_json(), games.join(",\n    "))
+    }
+
+    /// Write the store back, creating its directory; the file is replaced
+    /// atomically so another process never reads half of it
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, self.to_json())?;
+        std::fs::rename(&tmp, &self.path)
+    }
+}
+
+pub(crate) fn parse_colour(s: &str) -> Option<(u8, u8, u8)> {
+    let hex = s.strip_prefix('#').filter(|h| h.len() == 6)?;
+    let v = u32::from_str_radix(hex, 16).ok()?;
+    Some(((v >> 16) as u8, (v >> 8) as u8, v as u8))
+}
+
+fn button_name(mask: u8) -> &'static str {
+    ["right", "left", "up", "down", "a", "b", "select", "start"].into_iter()
+        .find(|n| button_from_name(n) == Some(mask))
+        .unwrap_or("none")
+}
+
+pub(crate) fn esc(s: &str) -> String {
+    s.chars().map(|c| match c {
+        '"' => "\\\"".to_string(),
+        '\\' => "\\\\".to_string(),
+        c if (c as u32) < 0x20 => format!("\\u{:04x}", c as u32),
+        c => c.to_string(),
+    }).collect()
+}
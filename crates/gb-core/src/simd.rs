@@ -0,0 +1,334 @@
+//! simd — wide paths for scanline decoding and RGB conversion
+//!
+//! The PPU fetches BG and window tiles once per 8 pixels and turns each
+//! tile row's two bit planes into colour numbers 16 at a time
+//! (`decode_tile_rows`), maps colour numbers through BGP a line at a time
+//! (`shade_row`), and `framebuffer_rgb` expands shades to RGB888 through a
+//! 16-entry lookup table (`rgb_row`). Each kernel has a scalar version and
+//! SSE2 / SSSE3 (x86_64) or NEON (aarch64) versions; `SimdLevel::detect()`
+//! picks the best the CPU supports at runtime, and a level the CPU lacks
+//! falls back to scalar. Every level produces identical bytes, so replays,
+//! hashes and training data do not depend on the host.
+//!
+//! `cargo bench -p gb-core --bench render` compares the levels.
+
+/// Instruction set the wide paths use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdLevel {
+    Scalar,
+    /// x86_64: tile decoding and palette mapping
+    Sse2,
+    /// x86_64: SSE2 plus table lookups for palette mapping and RGB conversion
+    Ssse3,
+    /// aarch64: every kernel
+    Neon,
+}
+
+impl SimdLevel {
+    /// Best level this CPU supports
+    pub fn detect() -> SimdLevel {
+        Self::supported().last().copied().unwrap_or(SimdLevel::Scalar)
+    }
+
+    /// Every level this CPU supports, scalar first
+    pub fn supported() -> Vec<SimdLevel> {
+        [SimdLevel::Scalar, SimdLevel::Sse2, SimdLevel::Ssse3, SimdLevel::Neon]
+            .into_iter().filter(|l| l.available()).collect()
+    }
+
+    pub fn available(self) -> bool {
+        match self {
+            SimdLevel::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse2 => is_x86_feature_detected!("sse2"),
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Ssse3 => is_x86_feature_detected!("ssse3"),
+            #[cfg(target_arch = "aarch64")]
+            SimdLevel::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self { SimdLevel::Scalar => "scalar", SimdLevel::Sse2 => "sse2", SimdLevel::Ssse3 => "ssse3", SimdLevel::Neon => "neon" }
+    }
+}
+
+impl Default for SimdLevel {
+    fn default() -> Self { SimdLevel::detect() }
+}
+
+/// RGB888 colour for each (layer source, shade) pair, indexed `source * 4 + shade`
+/// (sources as in `Ppu::pixel_source`; 3 and above share the last row)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbLut {
+    r: [u8; 16],
+    g: [u8; 16],
+    b: [u8; 16],
+}
+
+impl RgbLut {
+    /// `colors[source][shade]`
+    pub fn new(colors: [[(u8, u8, u8); 4]; 4]) -> Self {
+        let mut lut = RgbLut { r: [0; 16], g: [0; 16], b: [0; 16] };
+        for (i, &(r, g, b)) in colors.iter().flatten().enumerate() {
+            (lut.r[i], lut.g[i], lut.b[i]) = (r, g, b);
+        }
+        lut
+    }
+    /// The same four colours for every source
+    pub fn uniform(colors: [(u8, u8, u8); 4]) -> Self { Self::new([colors; 4]) }
+
+    fn get(&self, shade: u8, source: u8) -> (u8, u8, u8) {
+        let i = (source.min(3) << 2 | shade.min(3)) as usize;
+        (self.r[i], self.g[i], self.b[i])
+    }
+}
+
+/// Colour numbers (0-3, leftmost pixel first) of tile rows given as
+/// `[low plane, high plane]` byte pairs; `out` holds 8 per row
+pub fn decode_tile_rows(level: SimdLevel, planes: &[[u8; 2]], out: &mut [u8]) {
+    assert!(out.len() >= planes.len() * 8, "decode_tile_rows: output too short");
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Sse2 | SimdLevel::Ssse3 if level.available() => {
+            // SAFETY: SSE2 presence was just checked
+            unsafe { x86::decode_tile_rows(planes, out) }
+        }
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon if level.available() => {
+            // SAFETY: NEON presence was just checked
+            unsafe { neon::decode_tile_rows(planes, out) }
+        }
+        _ => scalar::decode_tile_rows(planes, out),
+    }
+}
+
+/// Map colour numbers through a DMG palette register (BGP / OBP0 / OBP1)
+pub fn shade_row(level: SimdLevel, palette: u8, colors: &[u8], out: &mut [u8]) {
+    assert!(out.len() >= colors.len(), "shade_row: output too short");
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Ssse3 if level.available() => {
+            // SAFETY: SSSE3 presence was just checked
+            unsafe { x86::shade_row_ssse3(palette, colors, out) }
+        }
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Sse2 if level.available() => {
+            // SAFETY: SSE2 presence was just checked
+            unsafe { x86::shade_row_sse2(palette, colors, out) }
+        }
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon if level.available() => {
+            // SAFETY: NEON presence was just checked
+            unsafe { neon::shade_row(palette, colors, out) }
+        }
+        _ => scalar::shade_row(palette, colors, out),
+    }
+}
+
+/// RGB888 bytes for each (shade, source) pair; `out` holds 3 per pixel
+pub fn rgb_row(level: SimdLevel, shades: &[u8], sources: &[u8], lut: &RgbLut, out: &mut [u8]) {
+    assert!(sources.len() >= shades.len() && out.len() >= shades.len() * 3, "rgb_row: input / output too short");
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Ssse3 if level.available() => {
+            // SAFETY: SSSE3 presence was just checked
+            unsafe { x86::rgb_row(shades, sources, lut, out) }
+        }
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon if level.available() => {
+            // SAFETY: NEON presence was just checked
+            unsafe { neon::rgb_row(shades, sources, lut, out) }
+        }
+        _ => scalar::rgb_row(shades, sources, lut, out),
+    }
+}
+
+/// Bit masks testing pixels 0-7 of a tile row (bit 7 is the leftmost pixel)
+const PIXEL_BITS: [u8; 16] = [0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01,
+                              0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01];
+
+fn shade_lut(palette: u8) -> [u8; 16] {
+    let mut lut = [0u8; 16];
+    for (c, s) in lut.iter_mut().take(4).enumerate() { *s = (palette >> (c * 2)) & 0x03; }
+    lut
+}
+
+mod scalar {
+    use super::RgbLut;
+
+    pub fn decode_tile_rows(planes: &[[u8; 2]], out: &mut [u8]) {
+        for (&[lo, hi], px) in planes.iter().zip(out.chunks_exact_mut(8)) {
+            for (i, c) in px.iter_mut().enumerate() {
+                let bit = 7 - i;
+                *c = ((hi >> bit) & 1) << 1 | ((lo >> bit) & 1);
+            }
+        }
+    }
+
+    pub fn shade_row(palette: u8, colors: &[u8], out: &mut [u8]) {
+        for (s, &c) in out.iter_mut().zip(colors) { *s = (palette >> ((c & 3) * 2)) & 0x03; }
+    }
+
+    pub fn rgb_row(shades: &[u8], sources: &[u8], lut: &RgbLut, out: &mut [u8]) {
+        for ((&shade, &source), rgb) in shades.iter().zip(sources).zip(out.chunks_exact_mut(3)) {
+            let (r, g, b) = lut.get(shade, source);
+            rgb.copy_from_slice(&[r, g, b]);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{scalar, shade_lut, RgbLut, PIXEL_BITS};
+    use std::arch::x86_64::*;
+
+    /// pshufb masks spreading R, G and B vectors over three 16-byte output
+    /// blocks: `[block][channel]`, 0x80 zeroes a byte
+    const INTERLEAVE: [[[u8; 16]; 3]; 3] = {
+        let mut m = [[[0x80u8; 16]; 3]; 3];
+        let mut j = 0;
+        while j < 48 {
+            m[j / 16][j % 3][j % 16] = (j / 3) as u8;
+            j += 1;
+        }
+        m
+    };
+
+    fn load(bytes: &[u8]) -> __m128i {
+        assert!(bytes.len() >= 16);
+        // SAFETY: 16 readable bytes; loadu has no alignment requirement
+        unsafe { _mm_loadu_si128(bytes.as_ptr().cast()) }
+    }
+
+    fn store(bytes: &mut [u8], v: __m128i) {
+        assert!(bytes.len() >= 16);
+        // SAFETY: 16 writable bytes; storeu has no alignment requirement
+        unsafe { _mm_storeu_si128(bytes.as_mut_ptr().cast(), v) }
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub fn decode_tile_rows(planes: &[[u8; 2]], out: &mut [u8]) {
+        const SPREAD: u64 = 0x0101_0101_0101_0101;
+        let bits = load(&PIXEL_BITS);
+        let (one, two) = (_mm_set1_epi8(1), _mm_set1_epi8(2));
+        let pairs = planes.chunks_exact(2);
+        let done = planes.len() - pairs.remainder().len();
+        for (pair, dst) in pairs.zip(out.chunks_exact_mut(16)) {
+            let lo = _mm_set_epi64x((pair[1][0] as u64 * SPREAD) as i64, (pair[0][0] as u64 * SPREAD) as i64);
+            let hi = _mm_set_epi64x((pair[1][1] as u64 * SPREAD) as i64, (pair[0][1] as u64 * SPREAD) as i64);
+            let lo = _mm_and_si128(_mm_cmpeq_epi8(_mm_and_si128(lo, bits), bits), one);
+            let hi = _mm_and_si128(_mm_cmpeq_epi8(_mm_and_si128(hi, bits), bits), two);
+            store(dst, _mm_or_si128(lo, hi));
+        }
+        scalar::decode_tile_rows(&planes[done..], &mut out[done * 8..]);
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub fn shade_row_sse2(palette: u8, colors: &[u8], out: &mut [u8]) {
+        let lut = shade_lut(palette);
+        let chunks = colors.chunks_exact(16);
+        let done = colors.len() - chunks.remainder().len();
+        for (src, dst) in chunks.zip(out.chunks_exact_mut(16)) {
+            let c = _mm_and_si128(load(src), _mm_set1_epi8(3));
+            let mut shade = _mm_setzero_si128();
+            for (n, &s) in lut.iter().take(4).enumerate() {
+                let hit = _mm_cmpeq_epi8(c, _mm_set1_epi8(n as i8));
+                shade = _mm_or_si128(shade, _mm_and_si128(hit, _mm_set1_epi8(s as i8)));
+            }
+            store(dst, shade);
+        }
+        scalar::shade_row(palette, &colors[done..], &mut out[done..]);
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub fn shade_row_ssse3(palette: u8, colors: &[u8], out: &mut [u8]) {
+        let lut = load(&shade_lut(palette));
+        let chunks = colors.chunks_exact(16);
+        let done = colors.len() - chunks.remainder().len();
+        for (src, dst) in chunks.zip(out.chunks_exact_mut(16)) {
+            store(dst, _mm_shuffle_epi8(lut, _mm_and_si128(load(src), _mm_set1_epi8(3))));
+        }
+        scalar::shade_row(palette, &colors[done..], &mut out[done..]);
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub fn rgb_row(shades: &[u8], sources: &[u8], lut: &RgbLut, out: &mut [u8]) {
+        let tables = [load(&lut.r), load(&lut.g), load(&lut.b)];
+        let masks = INTERLEAVE.map(|block| block.map(|m| load(&m)));
+        let three = _mm_set1_epi8(3);
+        let chunks = shades.chunks_exact(16);
+        let done = shades.len() - chunks.remainder().len();
+        for ((s, src), dst) in chunks.zip(sources.chunks_exact(16)).zip(out.chunks_exact_mut(48)) {
+            let shade = _mm_min_epu8(load(s), three);
+            let source = _mm_min_epu8(load(src), three);
+            // Sources are at most 3, so the 16-bit shift cannot carry between bytes
+            let idx = _mm_or_si128(_mm_slli_epi16::<2>(source), shade);
+            let rgb = tables.map(|t| _mm_shuffle_epi8(t, idx));
+            for (block, m) in dst.chunks_exact_mut(16).zip(&masks) {
+                let v = _mm_or_si128(_mm_or_si128(_mm_shuffle_epi8(rgb[0], m[0]), _mm_shuffle_epi8(rgb[1], m[1])),
+                                     _mm_shuffle_epi8(rgb[2], m[2]));
+                store(block, v);
+            }
+        }
+        scalar::rgb_row(&shades[done..], &sources[done..], lut, &mut out[done * 3..]);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::{scalar, shade_lut, RgbLut, PIXEL_BITS};
+    use std::arch::aarch64::*;
+
+    fn load(bytes: &[u8]) -> uint8x16_t {
+        assert!(bytes.len() >= 16);
+        // SAFETY: 16 readable bytes
+        unsafe { vld1q_u8(bytes.as_ptr()) }
+    }
+
+    #[target_feature(enable = "neon")]
+    pub fn decode_tile_rows(planes: &[[u8; 2]], out: &mut [u8]) {
+        let bits = load(&PIXEL_BITS);
+        let (one, two) = (vdupq_n_u8(1), vdupq_n_u8(2));
+        let pairs = planes.chunks_exact(2);
+        let done = planes.len() - pairs.remainder().len();
+        for (pair, dst) in pairs.zip(out.chunks_exact_mut(16)) {
+            let lo = vcombine_u8(vdup_n_u8(pair[0][0]), vdup_n_u8(pair[1][0]));
+            let hi = vcombine_u8(vdup_n_u8(pair[0][1]), vdup_n_u8(pair[1][1]));
+            let v = vorrq_u8(vandq_u8(vtstq_u8(lo, bits), one), vandq_u8(vtstq_u8(hi, bits), two));
+            // SAFETY: dst is 16 writable bytes
+            unsafe { vst1q_u8(dst.as_mut_ptr(), v) }
+        }
+        scalar::decode_tile_rows(&planes[done..], &mut out[done * 8..]);
+    }
+
+    #[target_feature(enable = "neon")]
+    pub fn shade_row(palette: u8, colors: &[u8], out: &mut [u8]) {
+        let lut = load(&shade_lut(palette));
+        let chunks = colors.chunks_exact(16);
+        let done = colors.len() - chunks.remainder().len();
+        for (src, dst) in chunks.zip(out.chunks_exact_mut(16)) {
+            let v = vqtbl1q_u8(lut, vandq_u8(load(src), vdupq_n_u8(3)));
+            // SAFETY: dst is 16 writable bytes
+            unsafe { vst1q_u8(dst.as_mut_ptr(), v) }
+        }
+        scalar::shade_row(palette, &colors[done..], &mut out[done..]);
+    }
+
+    #[target_feature(enable = "neon")]
+    pub fn rgb_row(shades: &[u8], sources: &[u8], lut: &RgbLut, out: &mut [u8]) {
+        let (r, g, b) = (load(&lut.r), load(&lut.g), load(&lut.b));
+        let three = vdupq_n_u8(3);
+        let chunks = shades.chunks_exact(16);
+        let done = shades.len() - chunks.remainder().len();
+        for ((s, src), dst) in chunks.zip(sources.chunks_exact(16)).zip(out.chunks_exact_mut(48)) {
+            let idx = vorrq_u8(vshlq_n_u8::<2>(vminq_u8(load(src), three)), vminq_u8(load(s), three));
+            let rgb = uint8x16x3_t(vqtbl1q_u8(r, idx), vqtbl1q_u8(g, idx), vqtbl1q_u8(b, idx));
+            // SAFETY: dst is 48 writable bytes; vst3q interleaves them as RGB
+            unsafe { vst3q_u8(dst.as_mut_ptr(), rgb) }
+        }
+        scalar::rgb_row(&shades[done..], &sources[done..], lut, &mut out[done * 3..]);
+    }
+}
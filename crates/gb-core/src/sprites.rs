@@ -0,0 +1,104 @@
+//! sprites — per-frame visible sprite list (object-detection labels)
+//!
+//! `visible_sprites` reads OAM the way the PPU does and lists the sprites
+//! that show up on screen, with their bounding boxes, tile and attributes.
+//! Next to a framebuffer they are free object labels for vision models:
+//! `letsplay_batch --sprites` / `letsplay_train --sprites` add them to each
+//! training record as `"sprites"`, `ReplayCapture::with_sprites` to each
+//! replay frame as `"spr"`.
+//!
+//! Called after `run_frame` (VBlank start) the list matches the picture just
+//! drawn, unless the game rewrote OAM mid-frame. A sprite counts as visible
+//! when it overlaps the screen and survives the 10-per-line limit on at least
+//! one line; with the LCD or sprites (LCDC bits 7 / 1) off the list is empty.
+//! Boxes are in screen pixels and may extend past the edges.
+//!
+//! `visible_sprites_into` and `write_sprites_json` reuse the caller's buffers,
+//! so per-frame capture does not allocate once they have grown.
+
+use crate::{Bus, OamScan, Sprite, LCD_HEIGHT, LCD_WIDTH};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisibleSprite {
+    /// OAM index (0-39)
+    pub index: u8,
+    /// Top-left corner on screen
+    pub x: i16,
+    pub y: i16,
+    /// 8 or 16 (LCDC bit 2)
+    pub height: u8,
+    /// Tile drawn at the top (8x16 sprites use it with bit 0 cleared)
+    pub tile: u8,
+    /// DMG object palette (0 = OBP0, 1 = OBP1); the OBJ palette (0-7) in CGB mode
+    pub palette: u8,
+    pub x_flip: bool,
+    pub y_flip: bool,
+    /// Drawn behind non-zero BG colours
+    pub behind_bg: bool,
+}
+
+impl VisibleSprite {
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+
+    /// Append `to_json()` to `out`
+    pub fn write_json(&self, out: &mut String) {
+        let _ = write!(out, "{{\"i\":{},\"x\":{},\"y\":{},\"w\":8,\"h\":{},\"tile\":{},\"pal\":{},\"xflip\":{},\"yflip\":{},\"behind\":{}}}",
+            self.index, self.x, self.y, self.height, self.tile, self.palette, self.x_flip, self.y_flip, self.behind_bg);
+    }
+}
+
+/// The sprites the PPU shows with the current OAM and LCDC, in OAM order
+pub fn visible_sprites(bus: &Bus) -> Vec<VisibleSprite> {
+    let mut out = Vec::new();
+    visible_sprites_into(bus, &mut out);
+    out
+}
+
+/// `visible_sprites` into `out`, replacing its contents
+pub fn visible_sprites_into(bus: &Bus, out: &mut Vec<VisibleSprite>) {
+    out.clear();
+    let lcdc = bus.ppu.lcdc;
+    if lcdc & 0x82 != 0x82 { return; }
+    let height: i32 = if lcdc & 0x04 != 0 { 16 } else { 8 };
+    let sprites: [Sprite; 40] = std::array::from_fn(|i| Sprite::from_oam(&bus.oam, i));
+    // Sprites the mode 2 scan selects on some line, after the 10-per-line limit
+    let mut shown = [false; 40];
+    for ly in 0..LCD_HEIGHT as u8 {
+        for s in OamScan::line(&bus.oam, ly, height == 16).sprites() { shown[s.index as usize] = true; }
+    }
+    out.extend(sprites.iter().enumerate()
+        .filter(|&(i, s)| shown[i] && s.screen_x() > -8 && s.screen_x() < LCD_WIDTH as i32)
+        .map(|(i, s)| VisibleSprite {
+            index: i as u8,
+            x: s.screen_x() as i16,
+            y: s.screen_y() as i16,
+            height: height as u8,
+            tile: if height == 16 { s.tile & 0xFE } else { s.tile },
+            palette: if bus.ppu.cgb { s.cgb_palette() } else { s.palette() },
+            x_flip: s.x_flip(),
+            y_flip: s.y_flip(),
+            behind_bg: s.bg_priority(),
+        }));
+}
+
+/// `[{"i":..,"x":..,...}, ...]`
+pub fn sprites_json(sprites: &[VisibleSprite]) -> String {
+    let mut out = String::new();
+    write_sprites_json(&mut out, sprites);
+    out
+}
+
+/// Append `sprites_json(sprites)` to `out`
+pub fn write_sprites_json(out: &mut String, sprites: &[VisibleSprite]) {
+    out.push('[');
+    for (i, s) in sprites.iter().enumerate() {
+        if i > 0 { out.push(','); }
+        s.write_json(out);
+    }
+    out.push(']');
+}
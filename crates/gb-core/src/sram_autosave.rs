@@ -0,0 +1,161 @@
+//! sram_autosave — keep a battery save file in step with cartridge RAM
+//!
+//! Real carts keep whatever the game wrote to battery RAM; games disable the
+//! RAM (a 0x00 write to 0000-1FFF) once a save is complete. With
+//! `GbCore::autosave` set, the core flushes cartridge RAM to `path`:
+//!
+//! - at the end of the frame in which the game disabled RAM, and
+//! - every `interval` frames while RAM has been written since the last flush
+//!   (catches games that leave RAM enabled).
+//!
+//! Flushes coalesce: any number of writes and disables within a frame cost
+//! one check, and a flush whose bytes match the file is skipped. The file is
+//! written beside the target, synced, then renamed over it, so a host crash
+//! leaves either the old save or the new one, never half of each.
+//! `GbCore::flush_sram` forces a flush (on quit, say).
+//!
+//! `with_journal` makes flushes append the changed bytes to a journal beside
+//! the file instead (`sram_journal.rs`); `load_into` replays it and
+//! `compact`, which `flush_sram` calls, folds it back into the plain file.
+
+use crate::{journal_path, save_hash, sram_diff, Bus, SramJournal, JOURNAL_COMPACT_FACTOR};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Five seconds of frames between periodic flushes
+pub const DEFAULT_AUTOSAVE_INTERVAL: u64 = 300;
+
+/// Cartridge types (header byte 0x147) with battery-backed RAM
+pub fn has_battery(rom: &[u8]) -> bool {
+    matches!(rom.get(0x147), Some(0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0xFF))
+}
+
+#[derive(Debug, Clone)]
+pub struct SramAutosave {
+    pub path: PathBuf,
+    /// Frames between periodic flushes of written RAM; None flushes only on
+    /// RAM disable (and `flush_sram`)
+    pub interval: Option<u64>,
+    /// Flush when the game disables RAM
+    pub on_disable: bool,
+    /// RAM as last written to `path`
+    saved: Vec<u8>,
+    /// Frame of the last periodic check that flushed or found RAM clean
+    last_flush_frame: u64,
+    /// RAM written since the last flush
+    dirty: bool,
+    /// Flushes that wrote the file
+    pub flushes: u64,
+    /// Last failed flush, kept so a host can report it; the next flush retries
+    pub last_error: Option<String>,
+    /// Flushes append to this journal, when Some
+    pub journal: Option<SramJournal>,
+}
+
+impl SramAutosave {
+    pub fn new(path: &Path) -> Self {
+        SramAutosave {
+            path: path.to_path_buf(), interval: Some(DEFAULT_AUTOSAVE_INTERVAL), on_disable: true,
+            saved: vec![], last_flush_frame: 0, dirty: false, flushes: 0, last_error: None, journal: None,
+        }
+    }
+    pub fn with_interval(mut self, frames: Option<u64>) -> Self { self.interval = frames.map(|n| n.max(1)); self }
+    pub fn with_on_disable(mut self, enabled: bool) -> Self { self.on_disable = enabled; self }
+    /// Journal flushes to `<path>.journal` instead of rewriting the file
+    pub fn with_journal(mut self, enabled: bool) -> Self {
+        self.journal = enabled.then(|| SramJournal::new(&journal_path(&self.path)));
+        self
+    }
+
+    /// Load the save file into cartridge RAM, if there is one, and replay
+    /// the journal over it. A short file fills the start of RAM; extra bytes
+    /// are ignored. Returns whether a file was read.
+    pub fn load_into(&mut self, bus: &mut Bus) -> io::Result<bool> {
+        let data = match std::fs::read(&self.path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let n = data.len().min(bus.ram.len());
+        bus.ram[..n].copy_from_slice(&data[..n]);
+        if let Some(j) = self.journal.as_mut() { j.replay(save_hash(&data), &mut bus.ram)?; }
+        self.saved = bus.ram.clone();
+        Ok(true)
+    }
+
+    /// End-of-frame check (`GbCore` calls it from `end_frame`); returns
+    /// whether the file was written
+    pub fn poll(&mut self, bus: &mut Bus, frame: u64) -> io::Result<bool> {
+        self.dirty |= std::mem::take(&mut bus.sram_dirty);
+        let closed = std::mem::take(&mut bus.sram_closed) && self.on_disable;
+        let due = self.interval.is_some_and(|n| frame >= self.last_flush_frame + n);
+        if !(closed || (due && self.dirty)) {
+            if due { self.last_flush_frame = frame; }
+            return Ok(false);
+        }
+        self.last_flush_frame = frame;
+        self.flush(bus)
+    }
+
+    /// Write cartridge RAM to `path` unless the file already holds it. With
+    /// a journal, only the changed bytes are appended to it, once the file
+    /// exists and until the journal is due for compaction.
+    pub fn flush(&mut self, bus: &Bus) -> io::Result<bool> {
+        if bus.ram.is_empty() || bus.ram == self.saved { self.dirty = false; return Ok(false); }
+        let result = match self.journal.as_mut() {
+            Some(j) if self.saved.len() == bus.ram.len() && j.len() < bus.ram.len() as u64 * JOURNAL_COMPACT_FACTOR => {
+                j.append(&sram_diff(&self.saved, &bus.ram))
+            }
+            _ => self.write_file(&bus.ram),
+        };
+        self.finish(bus, result)
+    }
+
+    /// Write cartridge RAM to `path` and drop the journal (clean shutdown);
+    /// without a journal, the same as `flush`
+    pub fn compact(&mut self, bus: &Bus) -> io::Result<bool> {
+        let journaled = self.journal.as_ref().is_some_and(|j| !j.is_empty());
+        if bus.ram.is_empty() || (bus.ram == self.saved && !journaled) { self.dirty = false; return Ok(false); }
+        let result = self.write_file(&bus.ram);
+        self.finish(bus, result)
+    }
+
+    /// The file is replaced before the journal goes: a crash between the two
+    /// leaves a journal naming the old file, which `load_into` ignores
+    fn write_file(&mut self, ram: &[u8]) -> io::Result<()> {
+        write_atomic(&self.path, ram)?;
+        match self.journal.as_mut() {
+            Some(j) => j.reset(save_hash(ram)),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(&mut self, bus: &Bus, result: io::Result<()>) -> io::Result<bool> {
+        match result {
+            Ok(()) => {
+                self.saved.clone_from(&bus.ram);
+                self.dirty = false;
+                self.flushes += 1;
+                self.last_error = None;
+                Ok(true)
+            }
+            Err(e) => { self.last_error = Some(e.to_string()); Err(e) }
+        }
+    }
+}
+
+/// Write `data` to a temporary file beside `path`, sync it and rename it over
+/// `path`
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut f = std::fs::File::create(&tmp)?;
+    f.write_all(data)?;
+    f.sync_all()?;
+    drop(f);
+    std::fs::rename(&tmp, path)
+}
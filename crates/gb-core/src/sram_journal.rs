@@ -0,0 +1,142 @@
+//! sram_journal — append-only journal of battery RAM changes
+//!
+//! Rewriting the whole save on every flush costs a full write, a sync and a
+//! rename each time. With `SramAutosave::with_journal` a flush instead
+//! appends the bytes that changed since the last one to `<save>.journal`
+//! and syncs just that; the plain save file is only rewritten (compacted)
+//! on `GbCore::flush_sram`, at clean shutdown, or once the journal outgrows
+//! `JOURNAL_COMPACT_FACTOR` times the RAM size.
+//!
+//! ```text
+//! header  "MRSJ" | version u32 | FNV-1a 64 of the save file it applies to
+//! record  count u32 | count × (offset u32, value u8) | FNV-1a 32 of count and entries
+//! ```
+//!
+//! All integers are little-endian. Loading replays records over the save
+//! file in order and stops at the first short or corrupt one, so a crash in
+//! the middle of an append loses that flush and nothing else; the torn tail
+//! is cut off before the next append. A journal whose header names a
+//! different save file (left over from a compaction interrupted between
+//! writing the save and deleting the journal) is ignored.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const JOURNAL_MAGIC: &[u8; 4] = b"MRSJ";
+pub const JOURNAL_VERSION: u32 = 1;
+/// Compact once the journal is this many times the size of cartridge RAM
+pub const JOURNAL_COMPACT_FACTOR: u64 = 4;
+
+const HEADER_LEN: u64 = 16;
+const ENTRY_LEN: usize = 5;
+
+/// `battery.sav` → `battery.sav.journal`
+pub fn journal_path(save: &Path) -> PathBuf {
+    let mut p = save.as_os_str().to_owned();
+    p.push(".journal");
+    PathBuf::from(p)
+}
+
+/// FNV-1a 64 of a save file's bytes, naming it in journal headers
+pub fn save_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// (offset, new value) of every byte that differs between `old` and `new`
+pub fn sram_diff(old: &[u8], new: &[u8]) -> Vec<(u32, u8)> {
+    new.iter().zip(old).enumerate().filter(|(_, (n, o))| n != o).map(|(i, (n, _))| (i as u32, *n)).collect()
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c_9dc5, |h, &b| (h ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+#[derive(Debug, Clone)]
+pub struct SramJournal {
+    pub path: PathBuf,
+    /// `save_hash` of the save file the journal applies to
+    base: u64,
+    /// Bytes of the file that hold a header and whole records; 0 when there
+    /// is no usable journal
+    len: u64,
+}
+
+impl SramJournal {
+    pub fn new(path: &Path) -> Self { SramJournal { path: path.to_path_buf(), base: 0, len: 0 } }
+
+    /// Size of the usable journal in bytes
+    pub fn len(&self) -> u64 { self.len }
+    pub fn is_empty(&self) -> bool { self.len <= HEADER_LEN }
+
+    /// Apply the journal's records for the save file `base` (its `save_hash`)
+    /// to `ram`, in order; returns how many records were applied
+    pub fn replay(&mut self, base: u64, ram: &mut [u8]) -> io::Result<usize> {
+        self.base = base;
+        self.len = 0;
+        let data = match std::fs::read(&self.path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let header_ok = data.len() >= HEADER_LEN as usize && &data[..4] == JOURNAL_MAGIC
+            && data[4..8] == JOURNAL_VERSION.to_le_bytes() && data[8..16] == base.to_le_bytes();
+        if !header_ok { return Ok(0); }
+        let mut pos = HEADER_LEN as usize;
+        let mut records = 0;
+        while let Some(count) = data.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize) {
+            let end = pos + 4 + count * ENTRY_LEN;
+            let Some(sum) = data.get(end..end + 4) else { break };
+            if checksum(&data[pos..end]) != u32::from_le_bytes([sum[0], sum[1], sum[2], sum[3]]) { break; }
+            for e in data[pos + 4..end].chunks_exact(ENTRY_LEN) {
+                let offset = u32::from_le_bytes([e[0], e[1], e[2], e[3]]) as usize;
+                if let Some(b) = ram.get_mut(offset) { *b = e[4]; }
+            }
+            pos = end + 4;
+            records += 1;
+        }
+        self.len = pos as u64;
+        Ok(records)
+    }
+
+    /// Append one record and sync it; starts a new journal for the current
+    /// base when there is none
+    pub fn append(&mut self, entries: &[(u32, u8)]) -> io::Result<()> {
+        if entries.is_empty() { return Ok(()); }
+        let mut record = Vec::with_capacity(8 + entries.len() * ENTRY_LEN);
+        record.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for &(offset, value) in entries {
+            record.extend_from_slice(&offset.to_le_bytes());
+            record.push(value);
+        }
+        record.extend_from_slice(&checksum(&record).to_le_bytes());
+        let mut f = if self.len == 0 {
+            let mut f = File::create(&self.path)?;
+            f.write_all(JOURNAL_MAGIC)?;
+            f.write_all(&JOURNAL_VERSION.to_le_bytes())?;
+            f.write_all(&self.base.to_le_bytes())?;
+            self.len = HEADER_LEN;
+            f
+        } else {
+            let mut f = OpenOptions::new().write(true).open(&self.path)?;
+            // Drop a torn record left by a crash
+            f.set_len(self.len)?;
+            f.seek(SeekFrom::End(0))?;
+            f
+        };
+        f.write_all(&record)?;
+        f.sync_data()?;
+        self.len += record.len() as u64;
+        Ok(())
+    }
+
+    /// Delete the journal once its changes are in the save file `base`
+    pub fn reset(&mut self, base: u64) -> io::Result<()> {
+        self.base = base;
+        self.len = 0;
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
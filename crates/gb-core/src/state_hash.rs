@@ -0,0 +1,126 @@
+//! state_hash — one 64-bit digest of the whole machine state
+//!
+//! `GbCore::state_hash()` feeds every piece of emulated state through
+//! FNV-1a in a fixed order, multi-byte values little-endian: CPU registers
+//! and flags, the cycle counter, VRAM / WRAM / HRAM / OAM / cartridge RAM,
+//! IO registers and CGB palettes, PPU, APU (all channels, wave RAM, frame
+//! sequencer), timer, OAM DMA and MBC / RTC registers (the ROM itself is
+//! not hashed; compare `rom_hash` for that). Two cores with equal
+//! hashes run identically from there on, so the digest serves replay
+//! verification, netplay desync checks and golden-state tests without
+//! building `save_state` JSON.
+//!
+//! Host-side outputs and attachments are left out: the framebuffer, the
+//! sample buffer, VIN audio, debugger and recording state, and the host
+//! clock. A run that skips rendering (`RenderSkip`) or drains audio
+//! differently hashes the same as a full one. `SubsystemHashes` (`determinism.rs`) splits the state
+//! per subsystem when you need to know what diverged.
+
+use crate::{Apu, Bus, GbCore, Mbc, NoiseChannel, Ppu, Square, Timer, WaveChannel};
+
+/// FNV-1a 64 over a stream of fixed-width fields
+struct StateHasher(u64);
+
+impl StateHasher {
+    fn new() -> Self { StateHasher(0xcbf2_9ce4_8422_2325) }
+    fn bytes(&mut self, data: &[u8]) {
+        for &b in data { self.0 ^= b as u64; self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3); }
+    }
+    fn u8s<const N: usize>(&mut self, vals: [u8; N]) { self.bytes(&vals); }
+    fn u16(&mut self, v: u16) { self.bytes(&v.to_le_bytes()); }
+    fn u32(&mut self, v: u32) { self.bytes(&v.to_le_bytes()); }
+    fn u64(&mut self, v: u64) { self.bytes(&v.to_le_bytes()); }
+    /// Variable-length data is prefixed with its length, so adjacent regions
+    /// cannot trade bytes
+    fn slice(&mut self, data: &[u8]) { self.u64(data.len() as u64); self.bytes(data); }
+}
+
+fn cpu(h: &mut StateHasher, core: &GbCore) {
+    let r = &core.regs;
+    h.u8s([r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l]);
+    h.u16(r.sp);
+    h.u16(r.pc);
+    h.u8s([core.halted as u8, core.stopped as u8, core.locked as u8, core.halt_bug as u8, core.ime as u8, core.ime_pending as u8]);
+    h.u64(core.clock.t_cycles);
+}
+
+fn memory(h: &mut StateHasher, b: &Bus) {
+    h.bytes(b.vram.as_flattened());
+    h.bytes(b.wram.as_flattened());
+    h.u8s([b.vram_bank, b.wram_bank]);
+    h.bytes(&b.hram);
+    h.bytes(&b.oam);
+    h.slice(&b.ram);
+}
+
+fn io(h: &mut StateHasher, b: &Bus) {
+    h.bytes(&b.io);
+    h.u8s([b.ie, b.if_reg, b.joypad, b.buttons, b.double_speed as u8, b.speed_switch_armed as u8, b.bg_cps, b.obj_cps]);
+    h.bytes(&b.bg_cpal);
+    h.bytes(&b.obj_cpal);
+    h.bytes(&b.dma.state_bytes());
+}
+
+fn ppu(h: &mut StateHasher, p: &Ppu) {
+    h.u8s([p.mode as u8, p.ly, p.lyc, p.lcdc, p.stat, p.scy, p.scx, p.wy, p.wx, p.wlc, p.pal_bg, p.pal_obj0, p.pal_obj1]);
+    h.u32(p.dot);
+    h.u8s([p.frame_ready as u8, p.stat_irq as u8, p.vblank_irq as u8, p.odd_frame as u8]);
+    h.bytes(&p.oam_scan.state_bytes());
+    h.u32(p.mode3_dots);
+}
+
+fn square(h: &mut StateHasher, s: &Square) {
+    h.u8s([s.nr0, s.nr1, s.nr2, s.nr3, s.nr4, s.enabled as u8, s.duty_pos, s.volume, s.env_timer, s.sweep_timer, s.sweep_enabled as u8]);
+    h.u32(s.freq_timer);
+    h.u16(s.len_timer);
+    h.u16(s.sweep_shadow);
+}
+
+fn wave(h: &mut StateHasher, w: &WaveChannel) {
+    h.u8s([w.enabled as u8, w.nr0, w.nr1, w.nr2, w.nr3, w.nr4, w.pos]);
+    h.bytes(&w.wave_ram);
+    h.u32(w.freq_timer);
+}
+
+fn noise(h: &mut StateHasher, n: &NoiseChannel) {
+    h.u8s([n.enabled as u8, n.nr1, n.nr2, n.nr3, n.nr4, n.volume, n.env_timer]);
+    h.u16(n.lfsr);
+    h.u32(n.freq_timer);
+}
+
+fn apu(h: &mut StateHasher, a: &Apu) {
+    h.u8s([a.power as u8, a.master_vol, a.nr51, a.triggers, a.fs_counter]);
+    square(h, &a.sq1);
+    square(h, &a.sq2);
+    wave(h, &a.wave);
+    noise(h, &a.noise);
+    h.u16(a.wave_len);
+    h.u16(a.noise_len);
+    h.u64(a.blip_position());
+}
+
+fn timer(h: &mut StateHasher, t: &Timer) {
+    h.u8s([t.div, t.tima, t.tma, t.tac, t.overflow_irq as u8]);
+    h.u16(t.div_counter);
+    h.u32(t.tima_counter);
+}
+
+fn mbc(h: &mut StateHasher, m: &Mbc) {
+    h.u16(m.rom_bank);
+    h.u8s([m.ram_bank, m.ram_enable as u8, m.mode, m.upper_bits, m.rtc_latch_state, m.rtc_sel]);
+    h.bytes(&m.rtc_reg);
+    h.bytes(&m.rtc_latch);
+}
+
+/// See the module docs; `GbCore::state_hash` calls this
+pub(crate) fn hash_core(core: &GbCore) -> u64 {
+    let mut h = StateHasher::new();
+    cpu(&mut h, core);
+    memory(&mut h, &core.bus);
+    io(&mut h, &core.bus);
+    ppu(&mut h, &core.bus.ppu);
+    apu(&mut h, &core.bus.apu);
+    timer(&mut h, &core.bus.timer);
+    mbc(&mut h, &core.bus.mbc);
+    h.0
+}
@@ -0,0 +1,374 @@
+//! state_import — best-effort savestate import from other emulators
+//!
+//! `import_state(core, data)` maps what is documented of a foreign
+//! savestate onto a core that already has the matching ROM loaded, and
+//! reports what it could not carry over. The result is a starting point
+//! for migrating save collections, not a cycle-exact resume: sub-instruction
+//! timing is never imported, so the PPU restarts at the top of its line.
+//!
+//! - Gambatte (`.gqs`): the labelled format (`"pc"`, `"vram"`, ...). CPU
+//!   registers, IME / halt, the MBC bank registers, VRAM, WRAM, cart RAM,
+//!   OAM / IO / HRAM / IE and CGB palettes are imported; every other label
+//!   (timing counters, DMA progress, RTC) is reported as unconverted.
+//!   The format carries no header title, so the state is checked against
+//!   the loaded cartridge by shape instead: cart RAM size and CGB VRAM.
+//! - VBA-M (`.sgm`, gzip-compressed or not): only the CPU registers and
+//!   IME / halt at the head of the state are imported; the rest of the
+//!   layout changes between VBA-M versions and is left alone.
+
+use crate::{CoreError, GbCore, PpuMode, PPU_VBLANK_LINE};
+
+/// Gambatte's framebuffer thumbnail, 160×144 32-bit pixels
+const GAMBATTE_SNAPSHOT_BYTES: usize = 160 * 144 * 4;
+/// VBA-M versions from this one store a use-BIOS flag after the ROM name
+const VBAM_BIOS_FLAG_VERSION: u32 = 7;
+const VBAM_MAX_VERSION: u32 = 64;
+/// Inflate limit: a VBA-M GB state is registers plus at most 32 KiB WRAM,
+/// 16 KiB VRAM and 128 KiB cart RAM, so anything larger is not one
+const VBAM_MAX_STATE_BYTES: usize = 512 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignState { Gambatte, VbaM }
+
+impl ForeignState {
+    pub fn as_str(&self) -> &'static str {
+        match self { ForeignState::Gambatte => "gambatte", ForeignState::VbaM => "vba-m" }
+    }
+
+    /// Recognise a Gambatte or VBA-M state from its contents
+    pub fn detect(data: &[u8]) -> Option<ForeignState> {
+        if gambatte_entries(data).is_some() { return Some(ForeignState::Gambatte); }
+        if data.starts_with(&GZIP_MAGIC) || vbam_version(data).is_some() { return Some(ForeignState::VbaM); }
+        None
+    }
+}
+
+/// What `import_state` carried over and what it left behind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateImport {
+    pub format: ForeignState,
+    pub converted: Vec<String>,
+    pub unconverted: Vec<String>,
+}
+
+impl StateImport {
+    pub fn to_json(&self) -> String {
+        let list = |v: &[String]| v.iter().map(|s| format!("\"{}\"", crate::settings::esc(s))).collect::<Vec<_>>().join(",");
+        format!("{{\"format\":\"{}\",\"converted\":[{}],\"unconverted\":[{}]}}",
+            self.format.as_str(), list(&self.converted), list(&self.unconverted))
+    }
+}
+
+/// Load a Gambatte or VBA-M savestate into `core`, which must be running
+/// the ROM the state was made with
+pub fn import_state(core: &mut GbCore, data: &[u8]) -> Result<StateImport, CoreError> {
+    let bad = |e: String| CoreError::InvalidState(format!("import_state: {e}"));
+    let report = match ForeignState::detect(data) {
+        Some(ForeignState::Gambatte) => import_gambatte(core, data),
+        Some(ForeignState::VbaM) if data.starts_with(&GZIP_MAGIC) => import_vbam(core, &gunzip(data).map_err(bad)?),
+        Some(ForeignState::VbaM) => import_vbam(core, data),
+        None => Err("not a Gambatte or VBA-M savestate".to_string()),
+    }.map_err(bad)?;
+    core.ime_pending = false;
+    core.stopped = false;
+    core.locked = false;
+    core.lock_hit = None;
+    core.soft_break_hit = None;
+    core.shadow_stack.clear();
+    // Keyframes from before the import would rewind into the old state
+    if let Some(tt) = core.time_travel.as_deref_mut() { tt.clear(); }
+    // Memory was replaced behind the bus; blocks decoded from the old code are stale
+    if let Some(c) = core.bus.block_cache.as_mut() { c.clear(); }
+    Ok(report)
+}
+
+// ── Gambatte ─────────────────────────────────────────────────────────────────
+
+/// Version bytes, a 24-bit-length snapshot, then NUL-terminated labels each
+/// followed by a 24-bit big-endian length and the value (integers big-endian)
+fn gambatte_entries(data: &[u8]) -> Option<Vec<(&str, &[u8])>> {
+    let be24 = |at: usize| data.get(at..at + 3).map(|b| (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize);
+    if !data.starts_with(&[0x00, 0x01]) { return None; }
+    let snapshot = be24(2)?;
+    if snapshot != 0 && snapshot != GAMBATTE_SNAPSHOT_BYTES { return None; }
+    let mut at = 5 + snapshot;
+    let mut out = vec![];
+    while at < data.len() {
+        let end = at + data[at..].iter().position(|&b| b == 0)?;
+        let label = std::str::from_utf8(&data[at..end]).ok()
+            .filter(|l| (1..=16).contains(&l.len()) && l.bytes().all(|b| b.is_ascii_alphanumeric()))?;
+        let len = be24(end + 1)?;
+        out.push((label, data.get(end + 4..end + 4 + len)?));
+        at = end + 4 + len;
+    }
+    out.iter().any(|(l, _)| *l == "pc").then_some(out)
+}
+
+fn be(v: &[u8]) -> u64 { v.iter().take(8).fold(0, |n, &b| n << 8 | b as u64) }
+
+fn import_gambatte(core: &mut GbCore, data: &[u8]) -> Result<StateImport, String> {
+    let entries = gambatte_entries(data).ok_or("malformed Gambatte state")?;
+    let mut report = StateImport { format: ForeignState::Gambatte, converted: vec![], unconverted: vec![] };
+    let cgb = entries.iter().any(|&(l, v)| l == "vram" && v.len() == 0x4000);
+    check_gambatte_cart(core, &entries, cgb)?;
+    for &(label, v) in &entries {
+        let n = be(v);
+        let (regs, bus) = (&mut core.regs, &mut core.bus);
+        let known = match label {
+            "pc" => { regs.pc = n as u16; true }
+            "sp" => { regs.sp = n as u16; true }
+            "a" => { regs.a = n as u8; true }
+            "b" => { regs.b = n as u8; true }
+            "c" => { regs.c = n as u8; true }
+            "d" => { regs.d = n as u8; true }
+            "e" => { regs.e = n as u8; true }
+            "f" => { regs.f = n as u8 & 0xF0; true }
+            "h" => { regs.h = n as u8; true }
+            "l" => { regs.l = n as u8; true }
+            "skip" => { core.halt_bug = n != 0; true }
+            "halt" => { core.halted = n != 0; true }
+            "ime" => { core.ime = n != 0; true }
+            "rombank" => { bus.mbc.rom_bank = n as u16; true }
+            "rambank" => { bus.mbc.ram_bank = n as u8; true }
+            "sramon" => { bus.mbc.ram_enable = n != 0; true }
+            "rambmod" => { bus.mbc.mode = n as u8; true }
+            "vram" => {
+                for (bank, chunk) in v.chunks(0x2000).take(2).enumerate() { bus.vram[bank][..chunk.len()].copy_from_slice(chunk); }
+                true
+            }
+            "wram" => {
+                for (bank, chunk) in v.chunks(0x1000).take(8).enumerate() { bus.wram[bank][..chunk.len()].copy_from_slice(chunk); }
+                true
+            }
+            "sram" => { let n = v.len().min(bus.ram.len()); bus.ram[..n].copy_from_slice(&v[..n]); true }
+            "hram" if v.len() == 0x200 => { apply_ioamhram(core, v, cgb); true }
+            "bgp" if v.len() == 64 => { bus.bg_cpal.copy_from_slice(v); true }
+            "objp" if v.len() == 64 => { bus.obj_cpal.copy_from_slice(v); true }
+            _ => false,
+        };
+        if known { report.converted.push(label.to_string()); } else { report.unconverted.push(label.to_string()); }
+    }
+    Ok(report)
+}
+
+/// Refuse a state made with another cartridge before anything is written:
+/// its `sram` must be the loaded cart's RAM size, and two VRAM banks need a
+/// core running in CGB mode
+fn check_gambatte_cart(core: &GbCore, entries: &[(&str, &[u8])], cgb: bool) -> Result<(), String> {
+    let title = String::from_utf8_lossy(core.bus.rom.get(0x134..0x143).unwrap_or(&[])).trim_end_matches('\0').to_string();
+    if let Some(&(_, sram)) = entries.iter().find(|&&(l, _)| l == "sram") {
+        if !sram.is_empty() && sram.len() != core.bus.ram.len() {
+            return Err(format!("state has {} bytes of cart RAM, {title:?} has {}", sram.len(), core.bus.ram.len()));
+        }
+    }
+    if cgb && !core.bus.ppu.cgb { return Err(format!("state is from a CGB game, {title:?} runs in DMG mode")); }
+    Ok(())
+}
+
+/// Gambatte's FE00-FFFF block: OAM, then IO at 0x100, HRAM at 0x180, IE last
+fn apply_ioamhram(core: &mut GbCore, block: &[u8], cgb: bool) {
+    let bus = &mut core.bus;
+    let io = &block[0x100..0x180];
+    bus.oam.copy_from_slice(&block[..0xA0]);
+    bus.hram.copy_from_slice(&block[0x180..0x1FF]);
+    bus.ie = block[0x1FF];
+    bus.io.copy_from_slice(io);
+    bus.joypad = io[0x00] & 0x30;
+    bus.if_reg = io[0x0F];
+    for reg in 0x05..=0x07 { bus.timer.write(reg, io[reg as usize]); }
+    // Power first; no channel is retriggered
+    bus.apu.write_reg(0x26, io[0x26]);
+    for reg in 0x10..=0x3Fu8 {
+        if reg == 0x26 { continue; }
+        let v = io[reg as usize];
+        bus.apu.write_reg(reg, if matches!(reg, 0x14 | 0x19 | 0x1E | 0x23) { v & 0x7F } else { v });
+    }
+    for reg in (0x40..=0x4Bu8).filter(|r| !matches!(r, 0x44 | 0x46)) { bus.ppu.write_reg(reg, io[reg as usize]); }
+    let ppu = &mut bus.ppu;
+    ppu.ly = io[0x44];
+    ppu.dot = 0;
+    ppu.mode = if ppu.ly as u32 >= PPU_VBLANK_LINE { PpuMode::VBlank } else { PpuMode::OamScan };
+    ppu.stat = (ppu.stat & 0xFC) | ppu.mode as u8;
+    if cgb {
+        bus.vram_bank = io[0x4F] & 0x01;
+        bus.wram_bank = (io[0x70] & 0x07).max(1);
+        bus.double_speed = io[0x4D] & 0x80 != 0;
+        bus.bg_cps = io[0x68] & 0xBF;
+        bus.obj_cps = io[0x6A] & 0xBF;
+    }
+}
+
+// ── VBA-M ────────────────────────────────────────────────────────────────────
+
+/// Little-endian version, then the 15-byte header title
+fn vbam_version(data: &[u8]) -> Option<u32> {
+    let version = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+    let title = data.get(4..19)?;
+    ((1..=VBAM_MAX_VERSION).contains(&version) && title.iter().all(|&b| b == 0 || b.is_ascii_graphic() || b == b' '))
+        .then_some(version)
+}
+
+fn import_vbam(core: &mut GbCore, data: &[u8]) -> Result<StateImport, String> {
+    let version = vbam_version(data).ok_or("malformed VBA-M state")?;
+    let title = &data[4..19];
+    if core.bus.rom.get(0x134..0x143) != Some(title) {
+        return Err(format!("state is for {:?}", String::from_utf8_lossy(title).trim_end_matches('\0')));
+    }
+    // PC, SP, AF, BC, DE, HL (little-endian words), then IFF
+    let at = if version >= VBAM_BIOS_FLAG_VERSION { 23 } else { 19 };
+    let head = data.get(at..at + 13).ok_or("truncated VBA-M state")?;
+    let word = |i: usize| u16::from_le_bytes([head[i * 2], head[i * 2 + 1]]);
+    let r = &mut core.regs;
+    r.pc = word(0);
+    r.sp = word(1);
+    [r.a, r.f] = [(word(2) >> 8) as u8, word(2) as u8 & 0xF0];
+    [r.b, r.c] = word(3).to_be_bytes();
+    [r.d, r.e] = word(4).to_be_bytes();
+    [r.h, r.l] = word(5).to_be_bytes();
+    core.ime = head[12] & 0x01 != 0;
+    core.halted = head[12] & 0x80 != 0;
+    core.halt_bug = false;
+    Ok(StateImport {
+        format: ForeignState::VbaM,
+        converted: ["pc", "sp", "af", "bc", "de", "hl", "ime", "halt"].map(String::from).to_vec(),
+        unconverted: ["timing", "io", "memory", "mbc", "apu"].map(String::from).to_vec(),
+    })
+}
+
+// ── gzip ─────────────────────────────────────────────────────────────────────
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// The first member of a gzip file; the CRC is not checked
+fn gunzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    let flags = *data.get(3).ok_or("truncated gzip header")?;
+    if data[2] != 8 { return Err("gzip: unknown compression method".into()); }
+    let mut at = 10;
+    if flags & 0x04 != 0 {
+        let xlen = data.get(at..at + 2).ok_or("truncated gzip header")?;
+        at += 2 + u16::from_le_bytes([xlen[0], xlen[1]]) as usize;
+    }
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 { at += 1 + data.get(at..).and_then(|d| d.iter().position(|&b| b == 0)).ok_or("truncated gzip header")?; }
+    }
+    if flags & 0x02 != 0 { at += 2; }
+    inflate(data.get(at..).ok_or("truncated gzip header")?, VBAM_MAX_STATE_BYTES)
+}
+
+struct Bits<'a> { data: &'a [u8], at: usize, bit: u32 }
+
+impl Bits<'_> {
+    fn bits(&mut self, n: u32) -> Result<u32, String> {
+        let mut v = 0;
+        for i in 0..n {
+            let byte = *self.data.get(self.at).ok_or("deflate: unexpected end of data")?;
+            v |= ((byte >> self.bit) as u32 & 1) << i;
+            self.bit += 1;
+            if self.bit == 8 { self.bit = 0; self.at += 1; }
+        }
+        Ok(v)
+    }
+
+    /// Canonical Huffman decode, one bit at a time
+    fn decode(&mut self, h: &Huffman) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= self.bits(1)? as i32;
+            let count = h.counts[len] as i32;
+            if code - first < count { return Ok(h.symbols[(index + code - first) as usize]); }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("deflate: bad Huffman code".into())
+    }
+}
+
+struct Huffman { counts: [u16; 16], symbols: Vec<u16> }
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &l in lengths { counts[l as usize] += 1; }
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|&s| lengths[s as usize] != 0).collect();
+        symbols.sort_by_key(|&s| lengths[s as usize]);
+        Huffman { counts, symbols }
+    }
+}
+
+const LEN_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LEN_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Raw deflate (RFC 1951), failing once the output would pass `limit` bytes
+fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let mut b = Bits { data, at: 0, bit: 0 };
+    let mut out = vec![];
+    loop {
+        let last = b.bits(1)? == 1;
+        match b.bits(2)? {
+            0 => {
+                if b.bit != 0 { b.bit = 0; b.at += 1; }
+                let head = data.get(b.at..b.at + 4).ok_or("deflate: truncated stored block")?;
+                let len = u16::from_le_bytes([head[0], head[1]]) as usize;
+                if out.len() + len > limit { return Err(too_large(limit)); }
+                out.extend_from_slice(data.get(b.at + 4..b.at + 4 + len).ok_or("deflate: truncated stored block")?);
+                b.at += 4 + len;
+            }
+            1 => {
+                let mut lengths = [8u8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                inflate_block(&mut b, &mut out, limit, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            }
+            2 => {
+                let (nlen, ndist, ncode) = (b.bits(5)? as usize + 257, b.bits(5)? as usize + 1, b.bits(4)? as usize + 4);
+                let mut code_lengths = [0u8; 19];
+                for &i in &CODE_LENGTH_ORDER[..ncode] { code_lengths[i] = b.bits(3)? as u8; }
+                let codes = Huffman::new(&code_lengths);
+                let mut lengths = vec![];
+                while lengths.len() < nlen + ndist {
+                    let (value, repeat) = match b.decode(&codes)? {
+                        sym @ 0..=15 => (sym as u8, 1),
+                        16 => (*lengths.last().ok_or("deflate: repeat with no length")?, 3 + b.bits(2)?),
+                        17 => (0, 3 + b.bits(3)?),
+                        _ => (0, 11 + b.bits(7)?),
+                    };
+                    lengths.extend(std::iter::repeat_n(value, repeat as usize));
+                }
+                if lengths.len() > nlen + ndist { return Err("deflate: code lengths overrun".into()); }
+                inflate_block(&mut b, &mut out, limit, &Huffman::new(&lengths[..nlen]), &Huffman::new(&lengths[nlen..]))?;
+            }
+            _ => return Err("deflate: reserved block type".into()),
+        }
+        if last { return Ok(out); }
+    }
+}
+
+fn too_large(limit: usize) -> String { format!("deflate: output larger than {limit} bytes") }
+
+fn inflate_block(b: &mut Bits, out: &mut Vec<u8>, limit: usize, lit: &Huffman, dist: &Huffman) -> Result<(), String> {
+    loop {
+        let sym = b.decode(lit)? as usize;
+        match sym {
+            0..=255 if out.len() == limit => return Err(too_large(limit)),
+            0..=255 => out.push(sym as u8),
+            256 => return Ok(()),
+            _ => {
+                let i = sym - 257;
+                if i >= LEN_BASE.len() { return Err("deflate: bad length code".into()); }
+                let len = LEN_BASE[i] as usize + b.bits(LEN_EXTRA[i] as u32)? as usize;
+                let d = b.decode(dist)? as usize;
+                if d >= DIST_BASE.len() { return Err("deflate: bad distance code".into()); }
+                let back = DIST_BASE[d] as usize + b.bits(DIST_EXTRA[d] as u32)? as usize;
+                if back > out.len() { return Err("deflate: distance before start of output".into()); }
+                if out.len() + len > limit { return Err(too_large(limit)); }
+                let start = out.len() - back;
+                for k in 0..len { out.push(out[start + k]); }
+            }
+        }
+    }
+}
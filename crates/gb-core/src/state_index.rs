@@ -0,0 +1,165 @@
+//! state_index — savestate metadata and a browsable slot index
+//!
+//! Every `mrom.sav.v1` state carries a `"meta"` object right after its
+//! header: ROM title and hash, frame index, emulated play time and a
+//! 40×36 RGB thumbnail. `StateIndex::scan(dir)` reads only that head of each
+//! `*.mrom.sav` file, so a frontend can list slots with pictures without
+//! restoring (or even fully reading) any state.
+
+use crate::json::Json;
+use crate::png::encode_png_rgb;
+use crate::{LCD_HEIGHT, LCD_WIDTH};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub const THUMB_WIDTH: usize = 40;
+pub const THUMB_HEIGHT: usize = 36;
+
+/// Bytes of a state file read by `StateIndex::scan`; meta always fits
+const META_HEAD_BYTES: u64 = 32 * 1024;
+
+/// Box-filter a 160×144 RGB888 frame down to THUMB_WIDTH×THUMB_HEIGHT
+pub fn thumbnail_rgb(rgb: &[u8]) -> Vec<u8> {
+    let (sx, sy) = (LCD_WIDTH / THUMB_WIDTH, LCD_HEIGHT / THUMB_HEIGHT);
+    let mut out = Vec::with_capacity(THUMB_WIDTH * THUMB_HEIGHT * 3);
+    for ty in 0..THUMB_HEIGHT {
+        for tx in 0..THUMB_WIDTH {
+            for c in 0..3 {
+                let mut sum = 0u32;
+                for y in ty * sy..(ty + 1) * sy {
+                    for x in tx * sx..(tx + 1) * sx {
+                        sum += rgb.get((y * LCD_WIDTH + x) * 3 + c).copied().unwrap_or(0) as u32;
+                    }
+                }
+                out.push((sum / (sx * sy) as u32) as u8);
+            }
+        }
+    }
+    out
+}
+
+/// The `"meta"` object of a savestate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateMeta {
+    pub rom_title: String,
+    /// FNV-1a of the ROM image, hex (same as `rom_hash` in training files)
+    pub rom_hash: String,
+    pub frame: u64,
+    /// Emulated time since power-on
+    pub play_time_ms: u64,
+    pub save_point: String,
+    /// THUMB_WIDTH×THUMB_HEIGHT RGB888
+    pub thumbnail: Vec<u8>,
+}
+
+impl StateMeta {
+    /// JSON for the `"meta"` member (save_point is recorded at top level)
+    pub fn to_json(&self) -> String {
+        let thumb: String = self.thumbnail.iter().map(|b| format!("{:02x}", b)).collect();
+        format!(
+            "{{\"rom_title\":\"{}\",\"rom_hash\":\"{}\",\"frame\":{},\"play_time_ms\":{},\"thumb_w\":{},\"thumb_h\":{},\"thumb\":\"{}\"}}",
+            self.rom_title.replace('\\', "\\\\").replace('"', "\\\""), self.rom_hash,
+            self.frame, self.play_time_ms, THUMB_WIDTH, THUMB_HEIGHT, thumb
+        )
+    }
+
+    /// Read the meta from the start of a state (the whole state or just its head)
+    pub fn from_state(head: &[u8]) -> Option<StateMeta> {
+        // A head cut mid-character is fine: meta sits well before the cut
+        let s = match std::str::from_utf8(head) {
+            Ok(s) => s,
+            Err(e) => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        };
+        let save_point = s.find("\"save_point\":\"").map(|i| {
+            let rest = &s[i + 14..];
+            rest[..rest.find('"').unwrap_or(0)].to_string()
+        }).unwrap_or_else(|| "instruction".into());
+        let start = s.find("\"meta\":{")? + 7;
+        let end = start + object_len(&s[start..])?;
+        let meta = Json::parse(&s[start..end]).ok()?;
+        let str_of = |k: &str| meta.get(k).and_then(Json::as_str).unwrap_or("").to_string();
+        let thumb = str_of("thumb");
+        let thumbnail = (0..thumb.len() / 2).map(|i| u8::from_str_radix(&thumb[i * 2..i * 2 + 2], 16).ok()).collect::<Option<Vec<u8>>>()?;
+        Some(StateMeta {
+            rom_title: str_of("rom_title"),
+            rom_hash: str_of("rom_hash"),
+            frame: meta.get("frame").and_then(Json::as_u64).unwrap_or(0),
+            play_time_ms: meta.get("play_time_ms").and_then(Json::as_u64).unwrap_or(0),
+            save_point,
+            thumbnail,
+        })
+    }
+
+    pub fn thumbnail_png(&self) -> Vec<u8> {
+        encode_png_rgb(THUMB_WIDTH as u32, THUMB_HEIGHT as u32, &self.thumbnail)
+    }
+}
+
+/// Length of the flat JSON object at the start of `s` (braces inside strings skipped)
+fn object_len(s: &str) -> Option<usize> {
+    let (mut in_str, mut esc) = (false, false);
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if esc => esc = false,
+            '\\' if in_str => esc = true,
+            '"' => in_str = !in_str,
+            '}' if !in_str => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateSlot {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub meta: StateMeta,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateIndex {
+    /// Sorted by file name
+    pub slots: Vec<StateSlot>,
+    /// `*.mrom.sav` files without readable meta (older or damaged states)
+    pub skipped: Vec<PathBuf>,
+}
+
+impl StateIndex {
+    /// Index every `*.mrom.sav` in `dir` (not recursive)
+    pub fn scan(dir: &Path) -> std::io::Result<StateIndex> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.to_string_lossy().ends_with(".mrom.sav"))
+            .collect();
+        paths.sort();
+        let mut index = StateIndex::default();
+        for path in paths {
+            let file = std::fs::File::open(&path)?;
+            let size_bytes = file.metadata()?.len();
+            let mut head = Vec::new();
+            file.take(META_HEAD_BYTES).read_to_end(&mut head)?;
+            match StateMeta::from_state(&head) {
+                Some(meta) => index.slots.push(StateSlot { path, size_bytes, meta }),
+                None => index.skipped.push(path),
+            }
+        }
+        Ok(index)
+    }
+
+    /// Slots saved from the ROM with this hash
+    pub fn for_rom<'a>(&'a self, rom_hash: &'a str) -> impl Iterator<Item = &'a StateSlot> + 'a {
+        self.slots.iter().filter(move |s| s.meta.rom_hash == rom_hash)
+    }
+
+    /// `mrom.state_index.v1` listing (thumbnails as RGB hex)
+    pub fn to_json(&self) -> String {
+        let slots: Vec<String> = self.slots.iter().map(|s| {
+            let path = s.path.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+            format!("    {{\"path\":\"{}\",\"size_bytes\":{},\"save_point\":\"{}\",\"meta\":{}}}",
+                    path, s.size_bytes, s.meta.save_point, s.meta.to_json())
+        }).collect();
+        format!("{{\n  \"version\": \"mrom.state_index.v1\",\n  \"slots\": [\n{}\n  ]\n}}", slots.join(",\n"))
+    }
+}
@@ -0,0 +1,50 @@
+//! stimulus — external sensor input (the CGB IR port)
+//!
+//! A `StimulusProvider` refreshes one `StimulusInputs` block that the bus
+//! reads from: once per frame, or every N T-cycles for inputs that change
+//! faster than that (an IR peer toggling its LED). The CGB IR port (FF56)
+//! reads `ir_light`; it is the only sensor the core emulates.
+
+/// How often `GbCore` calls `StimulusProvider::update`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StimulusRate {
+    /// At the start of every `run_frame`
+    Frame,
+    /// Every N T-cycles (checked at instruction boundaries)
+    Cycles(u32),
+}
+
+/// Sensor values the emulated hardware sees
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StimulusInputs {
+    /// IR receiver (FF56 bit 1) sees light
+    pub ir_light: bool,
+}
+
+/// What the emulated machine is doing when the provider is asked for input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StimulusContext {
+    pub t_cycles: u64,
+    pub frame: u64,
+    /// CGB IR LED is on (FF56 bit 0) — lets an IR peer answer the game
+    pub ir_led: bool,
+}
+
+pub trait StimulusProvider: Send {
+    fn rate(&self) -> StimulusRate { StimulusRate::Frame }
+    /// Refresh `inputs` for the upcoming frame / cycle window
+    fn update(&mut self, ctx: &StimulusContext, inputs: &mut StimulusInputs);
+}
+
+/// Any `FnMut(&StimulusContext, &mut StimulusInputs)` is a per-frame provider
+impl<F: FnMut(&StimulusContext, &mut StimulusInputs) + Send> StimulusProvider for F {
+    fn update(&mut self, ctx: &StimulusContext, inputs: &mut StimulusInputs) { self(ctx, inputs) }
+}
+
+/// Wraps a provider to run it every `n` T-cycles instead of per frame
+pub struct EveryCycles<P> { pub n: u32, pub inner: P }
+
+impl<P: StimulusProvider> StimulusProvider for EveryCycles<P> {
+    fn rate(&self) -> StimulusRate { StimulusRate::Cycles(self.n.max(1)) }
+    fn update(&mut self, ctx: &StimulusContext, inputs: &mut StimulusInputs) { self.inner.update(ctx, inputs) }
+}
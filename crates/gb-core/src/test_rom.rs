@@ -0,0 +1,181 @@
+//! test_rom — run a test ROM to a pass/fail verdict
+//!
+//! Two reporting conventions are recognised, checked after every frame:
+//! - serial console text containing "Passed" / "Failed" (blargg style)
+//! - the mooneye register signature: B,C,D,E,H,L = 3,5,8,13,21,34 on pass,
+//!   all 0x42 on fail
+//!
+//! A ROM that reports neither within the frame budget is a `Timeout`.
+//!
+//! Mooneye ROMs finish with `LD B,B`, which the harness turns into a stop
+//! (`GbCore::ld_b_b_break`), so the signature is read the moment the test
+//! ends. `run_mooneye` trusts the registers only there and returns a
+//! `TestResult` with the break address and register fingerprint; a break
+//! with neither signature is a failure.
+
+use crate::settings::esc;
+use crate::{Cartridge, CoreConfig, CoreError, GbCore, HardwareModel};
+
+/// B, C, D, E, H, L of a passing mooneye test (Fibonacci)
+pub const MOONEYE_PASS: [u8; 6] = [3, 5, 8, 13, 21, 34];
+/// B, C, D, E, H, L of a failing mooneye test
+pub const MOONEYE_FAIL: [u8; 6] = [0x42; 6];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestOutcome { Pass, Fail(String), Timeout }
+
+impl TestOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self { TestOutcome::Pass => "pass", TestOutcome::Fail(_) => "fail", TestOutcome::Timeout => "timeout" }
+    }
+    pub fn passed(&self) -> bool { *self == TestOutcome::Pass }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestRun {
+    pub outcome: TestOutcome,
+    /// Frames run before the verdict (or the whole budget on timeout)
+    pub frames: u64,
+    /// Console text the ROM printed
+    pub console: String,
+}
+
+/// B, C, D, E, H, L
+pub fn mooneye_registers(core: &GbCore) -> [u8; 6] {
+    let r = &core.regs;
+    [r.b, r.c, r.d, r.e, r.h, r.l]
+}
+
+/// Verdict of a mooneye register fingerprint, if it is either signature
+pub fn mooneye_verdict(registers: [u8; 6]) -> Option<TestOutcome> {
+    match registers {
+        MOONEYE_PASS => Some(TestOutcome::Pass),
+        MOONEYE_FAIL => Some(TestOutcome::Fail("mooneye failure signature".into())),
+        _ => None,
+    }
+}
+
+/// Verdict from the core's current state, if the ROM has reported one
+pub fn test_verdict(core: &GbCore) -> Option<TestOutcome> {
+    if let Some(outcome) = mooneye_verdict(mooneye_registers(core)) { return Some(outcome); }
+    serial_verdict(&core.console_text())
+}
+
+/// Verdict of blargg-style console text, if it has reported one
+pub fn serial_verdict(text: &str) -> Option<TestOutcome> {
+    if text.contains("Passed") { return Some(TestOutcome::Pass); }
+    if text.contains("Failed") {
+        let detail = text.lines().rev().find(|l| !l.trim().is_empty() && !l.contains("Failed"))
+            .unwrap_or("Failed").trim().to_string();
+        return Some(TestOutcome::Fail(detail));
+    }
+    None
+}
+
+/// Run `core` frame by frame until it reports a verdict or `max_frames` pass.
+/// An `LD B,B` ends the test early only when it carries a mooneye signature.
+pub fn run_test(core: &mut GbCore, max_frames: u64) -> TestRun {
+    core.ld_b_b_break = true;
+    for frame in 1..=max_frames {
+        match core.run_frame() {
+            Ok(()) | Err(CoreError::SoftwareBreak { .. }) => {}
+            Err(e) => return TestRun { outcome: TestOutcome::Fail(e.to_string()), frames: frame, console: core.console_text() },
+        }
+        if let Some(outcome) = test_verdict(core) {
+            return TestRun { outcome, frames: frame, console: core.console_text() };
+        }
+    }
+    TestRun { outcome: TestOutcome::Timeout, frames: max_frames, console: core.console_text() }
+}
+
+/// Load and run a test ROM image; an unloadable ROM is a failure
+pub fn run_test_rom(rom: Vec<u8>, max_frames: u64) -> TestRun {
+    match Cartridge::from_bytes(rom) {
+        Ok(cart) => run_test(&mut GbCore::new(cart), max_frames),
+        Err(e) => TestRun { outcome: TestOutcome::Fail(e.to_string()), frames: 0, console: String::new() },
+    }
+}
+
+// ── Mooneye ──────────────────────────────────────────────────────────────────
+
+/// How a mooneye test ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub outcome: TestOutcome,
+    /// Address of the `LD B,B` that ended the test; None on timeout or crash
+    pub break_pc: Option<u16>,
+    /// B, C, D, E, H, L when the test ended
+    pub registers: [u8; 6],
+    /// Frames run, counting the one the test ended in
+    pub frames: u64,
+    pub t_cycles: u64,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool { self.outcome.passed() }
+    /// One line for CI logs: outcome and break address, plus the
+    /// fingerprint on failure
+    pub fn summary(&self) -> String {
+        let regs: Vec<String> = self.registers.iter().map(|r| format!("{r:02X}")).collect();
+        let at = self.break_pc.map(|pc| format!(" at {pc:04X}")).unwrap_or_default();
+        match &self.outcome {
+            TestOutcome::Fail(detail) => format!("fail{at}: {detail} (B,C,D,E,H,L = {})", regs.join(" ")),
+            outcome => format!("{}{at} after {} frames", outcome.as_str(), self.frames),
+        }
+    }
+    pub fn to_json(&self) -> String {
+        let detail = match &self.outcome { TestOutcome::Fail(d) => d.as_str(), _ => "" };
+        format!(
+            "{{\"outcome\":\"{}\",\"detail\":\"{}\",\"break_pc\":{},\"registers\":[{}],\"frames\":{},\"t_cycles\":{}}}",
+            self.outcome.as_str(),
+            esc(detail),
+            self.break_pc.map_or("null".to_string(), |pc| pc.to_string()),
+            self.registers.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(","),
+            self.frames, self.t_cycles,
+        )
+    }
+}
+
+/// Run a mooneye test until its `LD B,B` or `max_frames` pass. Only the
+/// registers at the break count; other stops (a CPU lock, a breakpoint) fail.
+pub fn run_mooneye(core: &mut GbCore, max_frames: u64) -> TestResult {
+    core.ld_b_b_break = true;
+    let result = |core: &GbCore, outcome, break_pc, frames| TestResult {
+        outcome, break_pc, registers: mooneye_registers(core), frames, t_cycles: core.clock.t_cycles,
+    };
+    for frame in 1..=max_frames {
+        match core.run_frame() {
+            Ok(()) => {}
+            Err(CoreError::SoftwareBreak { pc }) => {
+                let outcome = mooneye_verdict(mooneye_registers(core))
+                    .unwrap_or_else(|| TestOutcome::Fail("LD B,B without a result signature".into()));
+                return result(core, outcome, Some(pc), frame);
+            }
+            Err(e) => return result(core, TestOutcome::Fail(e.to_string()), None, frame),
+        }
+    }
+    result(core, TestOutcome::Timeout, None, max_frames)
+}
+
+/// The model a mooneye ROM targets, from the hardware tag after the last
+/// `-` in its file name: `dmg…` / `G…` DMG, `mgb…` MGB, `sgb…` / `S…` SGB,
+/// `cgb…` / `C…` / `A…` CGB. Untagged ROMs run on the DMG.
+pub fn mooneye_model(file_name: &str) -> HardwareModel {
+    let stem = file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem);
+    let tag = stem.rsplit_once('-').map_or("", |(_, tag)| tag);
+    if tag.starts_with("mgb") { HardwareModel::Mgb }
+    else if tag.starts_with("sgb") || tag.starts_with('S') { HardwareModel::Sgb }
+    else if tag.starts_with("cgb") || tag.starts_with('C') || tag.starts_with('A') { HardwareModel::Cgb }
+    else { HardwareModel::Dmg }
+}
+
+/// Load and run a mooneye ROM on the model its `file_name` asks for
+pub fn run_mooneye_rom(file_name: &str, rom: Vec<u8>, max_frames: u64) -> TestResult {
+    let config = CoreConfig { model: mooneye_model(file_name), ..CoreConfig::default() };
+    match Cartridge::from_bytes(rom) {
+        Ok(cart) => run_mooneye(&mut GbCore::with_config(cart, config), max_frames),
+        Err(e) => TestResult {
+            outcome: TestOutcome::Fail(e.to_string()), break_pc: None, registers: [0; 6], frames: 0, t_cycles: 0,
+        },
+    }
+}
@@ -0,0 +1,181 @@
+//! testsuite — one accuracy report over a directory of mixed test ROMs
+//!
+//! `mrom-testsuite` runs every ROM under a directory (blargg, mooneye,
+//! acid2, ...) and classifies each by how it reports:
+//! - `frame_hash` — the ROM has a reference hash (acid2-style picture
+//!   tests); the picture at its `LD B,B`, or at the end of the budget, must
+//!   match it
+//! - `mooneye` — `LD B,B` with the pass / fail register signature
+//! - `serial` — "Passed" / "Failed" on the serial console
+//!
+//! A ROM with none of these within the budget is a `Timeout`. The report
+//! (`mrom.testsuite.v1`) carries every ROM's frame hash, so a run on a
+//! known-good build doubles as the reference list for the next.
+
+use crate::json::Json;
+use crate::settings::esc;
+use crate::test_rom::{mooneye_model, mooneye_registers, mooneye_verdict, serial_verdict, TestOutcome};
+use crate::{Cartridge, CoreConfig, CoreError, GbCore, HardwareModel};
+use std::path::{Path, PathBuf};
+
+/// How a ROM's verdict was reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestMethod { FrameHash, Mooneye, Serial, None }
+
+impl TestMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TestMethod::FrameHash => "frame_hash", TestMethod::Mooneye => "mooneye",
+            TestMethod::Serial => "serial", TestMethod::None => "none",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteResult {
+    /// Path relative to the suite directory
+    pub rom: String,
+    pub method: TestMethod,
+    pub outcome: TestOutcome,
+    pub frames: u64,
+    /// `frame_hash` of the picture when the run ended
+    pub frame_hash: u64,
+}
+
+/// FNV-1a 64 of `framebuffer_rgb`
+pub fn frame_hash(core: &GbCore) -> u64 {
+    core.framebuffer_rgb().iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// Reference frame hashes: `{"<rom file name>": "<16 hex digits>", ...}`
+pub fn parse_frame_hashes(doc: &Json) -> Result<Vec<(String, u64)>, String> {
+    let Json::Obj(members) = doc else { return Err("frame hashes must be an object".into()) };
+    members.iter().map(|(name, v)| {
+        let hex = v.as_str().ok_or(format!("{name}: hash must be a hex string"))?;
+        u64::from_str_radix(hex, 16).map(|h| (name.clone(), h)).map_err(|e| format!("{name}: {e}"))
+    }).collect()
+}
+
+/// Every .gb / .gbc under `dir`, recursively, sorted
+pub fn test_rom_files(dir: &Path) -> Vec<PathBuf> {
+    fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+        for path in std::fs::read_dir(dir).into_iter().flatten().filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.is_dir() {
+                walk(&path, out);
+            } else if matches!(path.extension().and_then(|s| s.to_str()).map(str::to_lowercase).as_deref(), Some("gb" | "gbc")) {
+                out.push(path);
+            }
+        }
+    }
+    let mut out = vec![];
+    walk(dir, &mut out);
+    out.sort();
+    out
+}
+
+/// Run one ROM and classify it. `expected_hash` makes it a picture test.
+/// CGB-flagged ROMs run on the CGB, others on the model their name tags.
+pub fn run_suite_rom(rom_name: &str, rom: Vec<u8>, expected_hash: Option<u64>, max_frames: u64) -> SuiteResult {
+    let file_name = rom_name.rsplit(['/', '\\']).next().unwrap_or(rom_name);
+    let model = match HardwareModel::for_rom(&rom) {
+        HardwareModel::Cgb => HardwareModel::Cgb,
+        _ => mooneye_model(file_name),
+    };
+    let result = |method, outcome, frames, hash| SuiteResult { rom: rom_name.to_string(), method, outcome, frames, frame_hash: hash };
+    let mut core = match Cartridge::from_bytes(rom) {
+        Ok(cart) => GbCore::with_config(cart, CoreConfig { model, ..CoreConfig::default() }),
+        Err(e) => return result(TestMethod::None, TestOutcome::Fail(e.to_string()), 0, 0),
+    };
+    core.ld_b_b_break = true;
+    let compare = |core: &GbCore, expected: u64, frames| {
+        let hash = frame_hash(core);
+        let outcome = if hash == expected { TestOutcome::Pass }
+            else { TestOutcome::Fail(format!("frame hash {hash:016x}, expected {expected:016x}")) };
+        result(TestMethod::FrameHash, outcome, frames, hash)
+    };
+    for frame in 1..=max_frames {
+        let broke = match core.run_frame() {
+            Ok(()) => false,
+            Err(CoreError::SoftwareBreak { .. }) => true,
+            Err(e) => return result(TestMethod::None, TestOutcome::Fail(e.to_string()), frame, frame_hash(&core)),
+        };
+        match expected_hash {
+            Some(expected) if broke => return compare(&core, expected, frame),
+            Some(_) => {}
+            None => {
+                if let Some(outcome) = mooneye_verdict(mooneye_registers(&core)) {
+                    return result(TestMethod::Mooneye, outcome, frame, frame_hash(&core));
+                }
+                if let Some(outcome) = serial_verdict(&core.console_text()) {
+                    return result(TestMethod::Serial, outcome, frame, frame_hash(&core));
+                }
+            }
+        }
+    }
+    match expected_hash {
+        Some(expected) => compare(&core, expected, max_frames),
+        None => result(TestMethod::None, TestOutcome::Timeout, max_frames, frame_hash(&core)),
+    }
+}
+
+/// Every ROM under `dir`, run with `hashes` as the picture references;
+/// `progress` sees each result as it comes in
+pub fn run_suite_dir(dir: &Path, hashes: &[(String, u64)], max_frames: u64, mut progress: impl FnMut(&SuiteResult)) -> std::io::Result<SuiteReport> {
+    let mut results = vec![];
+    for path in test_rom_files(dir) {
+        let rom = std::fs::read(&path)?;
+        let rel = path.strip_prefix(dir).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let expected = hashes.iter().find(|(name, _)| *name == file_name || *name == rel).map(|(_, h)| *h);
+        let result = run_suite_rom(&rel, rom, expected, max_frames);
+        progress(&result);
+        results.push(result);
+    }
+    Ok(SuiteReport { core: "gb-core".into(), results })
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SuiteReport {
+    pub core: String,
+    pub results: Vec<SuiteResult>,
+}
+
+impl SuiteReport {
+    pub fn passed(&self) -> usize { self.results.iter().filter(|r| r.outcome.passed()).count() }
+    pub fn total(&self) -> usize { self.results.len() }
+    pub fn pass_rate(&self) -> f64 {
+        if self.results.is_empty() { 0.0 } else { self.passed() as f64 / self.total() as f64 }
+    }
+    /// (method, passed, total) for each method that classified a ROM
+    pub fn by_method(&self) -> Vec<(TestMethod, usize, usize)> {
+        [TestMethod::FrameHash, TestMethod::Mooneye, TestMethod::Serial, TestMethod::None].into_iter().filter_map(|m| {
+            let of: Vec<&SuiteResult> = self.results.iter().filter(|r| r.method == m).collect();
+            (!of.is_empty()).then(|| (m, of.iter().filter(|r| r.outcome.passed()).count(), of.len()))
+        }).collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        let methods: Vec<String> = self.by_method().iter().map(|(m, p, t)| {
+            format!("    \"{}\": {{\"passed\":{p},\"total\":{t}}}", m.as_str())
+        }).collect();
+        let results: Vec<String> = self.results.iter().map(|r| {
+            let detail = match &r.outcome { TestOutcome::Fail(d) => d.as_str(), _ => "" };
+            format!(
+                "    {{\"rom\":\"{}\",\"method\":\"{}\",\"outcome\":\"{}\",\"frames\":{},\"frame_hash\":\"{:016x}\",\"detail\":\"{}\"}}",
+                esc(&r.rom), r.method.as_str(), r.outcome.as_str(), r.frames, r.frame_hash, esc(detail)
+            )
+        }).collect();
+        format!(
+            "{{\n  \"version\": \"mrom.testsuite.v1\",\n  \"core\": \"{}\",\n  \"passed\": {},\n  \"total\": {},\n  \"pass_rate\": {:.4},\n  \"methods\": {{\n{}\n  }},\n  \"results\": [\n{}\n  ]\n}}",
+            esc(&self.core), self.passed(), self.total(), self.pass_rate(), methods.join(",\n"), results.join(",\n")
+        )
+    }
+
+    /// `{"<rom>": "<hash>"}` for every ROM, in `parse_frame_hashes` form:
+    /// a run on a known-good build seeds the picture references
+    pub fn frame_hashes_json(&self) -> String {
+        let lines: Vec<String> = self.results.iter()
+            .map(|r| format!("  \"{}\": \"{:016x}\"", esc(&r.rom), r.frame_hash)).collect();
+        format!("{{\n{}\n}}", lines.join(",\n"))
+    }
+}
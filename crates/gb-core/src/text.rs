@@ -0,0 +1,264 @@
+//! text — on-screen text extraction via per-game tile→glyph tables
+//!
+//! Games draw text as tiles, and each game numbers its font tiles its own
+//! way. A `GlyphTable` maps tilemap bytes to characters; `screen_text` reads
+//! the visible part of the BG and window tilemaps through it and returns the
+//! runs of known glyphs as strings. Dialogue then lands in the data next to
+//! the frames: `letsplay_batch --text` / `letsplay_train --text` add it to
+//! each training record as `"text"`, `ReplayCapture::with_text` to each
+//! replay frame as `"txt"`, and RPG progress can be followed by grepping
+//! for known lines.
+//!
+//! Tables are registered per game in a `GlyphTables` set, by ROM hash or
+//! header title (a hash entry wins). Keys are hex tilemap bytes, values the
+//! characters of consecutive tiles starting there:
+//!
+//! ```text
+//! {"version":"mrom.glyphs.v1",
+//!  "games":{"<rom_hash>":{"title":"...","glyphs":{"80":"ABCDEFGHIJKLMNOPQRSTUVWXYZ","7F":" "}}},
+//!  "titles":{"POKEMON RED":{"80":"ABC..."}}}
+//! ```
+//!
+//! Extraction works on whole tiles: BG cells are picked from SCX / SCY
+//! rounded down to the tile, and cells under the window are left to the
+//! window. CGB tile attributes (VRAM bank 1) are ignored, so a game that
+//! keeps its font in bank 1 needs its own indices in the table.
+
+use crate::settings::esc;
+use crate::{rom_hash, Bus, Cartridge, GbCore, Json};
+use std::io;
+use std::path::Path;
+
+pub const GLYPH_TABLES_VERSION: &str = "mrom.glyphs.v1";
+/// Runs with fewer non-space characters are dropped as noise
+pub const MIN_TEXT_CHARS: usize = 2;
+
+/// Tilemap byte → character
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphTable {
+    glyphs: [Option<char>; 256],
+}
+
+impl Default for GlyphTable {
+    fn default() -> Self { GlyphTable { glyphs: [None; 256] } }
+}
+
+impl GlyphTable {
+    pub fn get(&self, tile: u8) -> Option<char> { self.glyphs[tile as usize] }
+    pub fn set(&mut self, tile: u8, c: char) { self.glyphs[tile as usize] = Some(c); }
+    /// Map `chars` to the tiles from `first` on (stops at tile 0xFF)
+    pub fn set_run(&mut self, first: u8, chars: &str) {
+        for (tile, c) in (first..=0xFF).zip(chars.chars()) { self.set(tile, c); }
+    }
+    pub fn is_empty(&self) -> bool { self.glyphs.iter().all(Option::is_none) }
+
+    /// `{"80":"ABC...","7F":" "}`: a key per run of consecutive mapped tiles
+    pub fn to_json(&self) -> String {
+        let mut runs: Vec<(usize, String)> = vec![];
+        for (tile, c) in self.glyphs.iter().enumerate() {
+            let Some(c) = c else { continue };
+            match runs.last_mut() {
+                Some((first, s)) if *first + s.chars().count() == tile => s.push(*c),
+                _ => runs.push((tile, c.to_string())),
+            }
+        }
+        let runs: Vec<String> = runs.iter().map(|(t, s)| format!("\"{t:02X}\":\"{}\"", esc(s))).collect();
+        format!("{{{}}}", runs.join(","))
+    }
+
+    pub fn from_json(doc: &Json) -> Result<GlyphTable, String> {
+        let Json::Obj(runs) = doc else { return Err("expected an object of tile runs".into()) };
+        let mut table = GlyphTable::default();
+        for (key, chars) in runs {
+            let first = u8::from_str_radix(key, 16).map_err(|_| format!("{key}: expected a hex tile index"))?;
+            let chars = chars.as_str().ok_or_else(|| format!("{key}: expected a string"))?;
+            if first as usize + chars.chars().count() > 256 { return Err(format!("{key}: run goes past tile FF")); }
+            table.set_run(first, chars);
+        }
+        Ok(table)
+    }
+}
+
+/// Which tilemap a line of text was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextLayer { Bg, Window }
+
+impl TextLayer {
+    pub fn as_str(self) -> &'static str {
+        match self { TextLayer::Bg => "bg", TextLayer::Window => "win" }
+    }
+}
+
+/// One run of recognised glyphs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenText {
+    pub layer: TextLayer,
+    /// Screen tile cell of the first character (0-19, 0-17)
+    pub col: u8,
+    pub row: u8,
+    pub text: String,
+}
+
+impl ScreenText {
+    pub fn to_json(&self) -> String {
+        format!("{{\"layer\":\"{}\",\"col\":{},\"row\":{},\"text\":\"{}\"}}", self.layer.as_str(), self.col, self.row, esc(&self.text))
+    }
+}
+
+/// Text on screen, row by row, BG first then window
+pub fn screen_text(bus: &Bus, glyphs: &GlyphTable) -> Vec<ScreenText> {
+    let ppu = &bus.ppu;
+    if ppu.lcdc & 0x80 == 0 { return vec![]; }
+    let (wx, wy) = (ppu.wx as usize, ppu.wy as usize);
+    let window = ppu.lcdc & 0x20 != 0 && wy < 144 && wx < 167;
+    // First screen cell fully covered by the window
+    let (win_col, win_row) = (wx.saturating_sub(7).div_ceil(8), wy.div_ceil(8));
+    let mut out = vec![];
+    if ppu.lcdc & 0x01 != 0 {
+        let map = if ppu.lcdc & 0x08 != 0 { 0x1C00 } else { 0x1800 };
+        let (x0, y0) = (ppu.scx as usize / 8, ppu.scy as usize / 8);
+        for row in 0..18 {
+            let end = if window && row >= win_row { win_col.min(20) } else { 20 };
+            let tiles = (0..end).map(|col| bus.vram[0][map + (y0 + row) % 32 * 32 + (x0 + col) % 32]);
+            scan_row(&mut out, glyphs, TextLayer::Bg, row, 0, tiles);
+        }
+    }
+    if window && win_col < 20 {
+        let map = if ppu.lcdc & 0x40 != 0 { 0x1C00 } else { 0x1800 };
+        for row in win_row..18 {
+            let tiles = (win_col..20).map(|col| bus.vram[0][map + (row - win_row) * 32 + col - win_col]);
+            scan_row(&mut out, glyphs, TextLayer::Window, row, win_col, tiles);
+        }
+    }
+    out
+}
+
+/// Push the runs of mapped glyphs in one row of tiles starting at `col`
+fn scan_row(out: &mut Vec<ScreenText>, glyphs: &GlyphTable, layer: TextLayer, row: usize, col: usize, tiles: impl Iterator<Item = u8>) {
+    let mut flush = |start: usize, run: &mut String| {
+        let text = run.trim_start();
+        let start = start + run.chars().count() - text.chars().count();
+        if text.chars().filter(|c| !c.is_whitespace()).count() >= MIN_TEXT_CHARS {
+            out.push(ScreenText { layer, col: start as u8, row: row as u8, text: text.trim_end().to_string() });
+        }
+        run.clear();
+    };
+    let (mut start, mut run) = (col, String::new());
+    for (i, tile) in tiles.enumerate() {
+        match glyphs.get(tile) {
+            Some(c) => { if run.is_empty() { start = col + i; } run.push(c); }
+            None => flush(start, &mut run),
+        }
+    }
+    flush(start, &mut run);
+}
+
+/// `[{"layer":"bg","col":..,"row":..,"text":".."}, ...]`
+pub fn screen_text_json(lines: &[ScreenText]) -> String {
+    let items: Vec<String> = lines.iter().map(ScreenText::to_json).collect();
+    format!("[{}]", items.join(","))
+}
+
+/// Pokémon Red / Blue character map (pokered `charmap.asm`)
+pub(crate) fn pokemon_rb() -> GlyphTable {
+    let mut t = GlyphTable::default();
+    t.set_run(0x7F, " ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+    t.set_run(0x9A, "():;[]abcdefghijklmnopqrstuvwxyzé");
+    t.set(0xE0, '\'');
+    t.set(0xE3, '-');
+    t.set_run(0xE6, "?!.");
+    t.set_run(0xF3, "/,");
+    t.set_run(0xF6, "0123456789");
+    t
+}
+
+/// Glyph tables for many games, by ROM hash or header title
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GlyphTables {
+    /// (rom_hash, title, table)
+    games: Vec<(String, String, GlyphTable)>,
+    /// (header title, table)
+    titles: Vec<(String, GlyphTable)>,
+}
+
+impl GlyphTables {
+    /// Tables shipped with the core, by header title
+    pub fn builtin() -> Self {
+        let mut tables = GlyphTables::default();
+        tables.set_title("POKEMON RED", pokemon_rb());
+        tables.set_title("POKEMON BLUE", pokemon_rb());
+        tables
+    }
+
+    pub fn is_empty(&self) -> bool { self.games.is_empty() && self.titles.is_empty() }
+
+    /// Table for a ROM: by hash, else by header title
+    pub fn lookup(&self, rom_hash: &str, title: &str) -> Option<&GlyphTable> {
+        self.games.iter().find(|g| g.0 == rom_hash).map(|g| &g.2)
+            .or_else(|| self.titles.iter().find(|t| t.0 == title).map(|t| &t.1))
+    }
+    pub fn for_cartridge(&self, cart: &Cartridge) -> Option<&GlyphTable> { self.lookup(&rom_hash(&cart.rom), &cart.title) }
+    /// Table for the ROM loaded in `core`
+    pub fn for_core(&self, core: &GbCore) -> Option<&GlyphTable> {
+        let title = String::from_utf8_lossy(&core.bus.rom[0x134..0x143]).trim_matches('\0').to_string();
+        self.lookup(&rom_hash(&core.bus.rom), &title)
+    }
+
+    pub fn set_game(&mut self, rom_hash: &str, title: &str, table: GlyphTable) {
+        self.games.retain(|g| g.0 != rom_hash);
+        self.games.push((rom_hash.to_string(), title.to_string(), table));
+    }
+    pub fn set_title(&mut self, title: &str, table: GlyphTable) {
+        self.titles.retain(|t| t.0 != title);
+        self.titles.push((title.to_string(), table));
+    }
+
+    /// Lay `over` on top: its entries replace ours
+    pub fn merge(&mut self, over: &GlyphTables) {
+        for (h, t, g) in &over.games { self.set_game(h, t, g.clone()); }
+        for (t, g) in &over.titles { self.set_title(t, g.clone()); }
+    }
+
+    pub fn from_json(text: &str) -> Result<GlyphTables, String> {
+        let doc = Json::parse(text).map_err(|e| e.to_string())?;
+        match doc.get("version").and_then(Json::as_str) {
+            Some(GLYPH_TABLES_VERSION) => {}
+            v => return Err(format!("expected version {GLYPH_TABLES_VERSION}, got {v:?}")),
+        }
+        let mut tables = GlyphTables::default();
+        if let Some(Json::Obj(games)) = doc.get("games") {
+            for (hash, g) in games {
+                let glyphs = g.get("glyphs").ok_or_else(|| format!("{hash}: missing glyphs"))?;
+                let glyphs = GlyphTable::from_json(glyphs).map_err(|e| format!("{hash}: {e}"))?;
+                tables.set_game(hash, g.get("title").and_then(Json::as_str).unwrap_or(""), glyphs);
+            }
+        }
+        if let Some(Json::Obj(titles)) = doc.get("titles") {
+            for (title, g) in titles {
+                tables.set_title(title, GlyphTable::from_json(g).map_err(|e| format!("{title}: {e}"))?);
+            }
+        }
+        Ok(tables)
+    }
+
+    /// The built-in tables with `path`'s laid over them (`--text[=FILE]`)
+    pub fn builtin_with(path: Option<&Path>) -> io::Result<GlyphTables> {
+        let mut tables = GlyphTables::builtin();
+        if let Some(path) = path { tables.merge(&GlyphTables::load(path)?); }
+        Ok(tables)
+    }
+
+    pub fn load(path: &Path) -> io::Result<GlyphTables> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_json(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {e}", path.display())))
+    }
+
+    pub fn to_json(&self) -> String {
+        let games: Vec<String> = self.games.iter()
+            .map(|(h, t, g)| format!("\"{}\": {{\"title\":\"{}\",\"glyphs\":{}}}", esc(h), esc(t), g.to_json()))
+            .collect();
+        let titles: Vec<String> = self.titles.iter().map(|(t, g)| format!("\"{}\": {}", esc(t), g.to_json())).collect();
+        format!("{{\n  \"version\": \"{}\",\n  \"games\": {{\n    {}\n  }},\n  \"titles\": {{\n    {}\n  }}\n}}\n",
+            GLYPH_TABLES_VERSION, games.join(",\n    "), titles.join(",\n    "))
+    }
+}
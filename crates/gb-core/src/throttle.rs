@@ -0,0 +1,130 @@
+//! throttle — frame pacing for realtime hosts
+//!
+//! A `Throttle` decides how long a host should wait after each emulated
+//! frame. Clock sync schedules frame N at N frame periods after the start
+//! (so sleep overshoot never accumulates); a host that falls more than
+//! `MAX_LAG_FRAMES` behind is rebased instead of bursting to catch up.
+//! Audio sync instead waits until the host's output queue has drained to
+//! its target, so the audio device's clock drives the pace. `turbo`
+//! multiplies the target speed (0 runs unthrottled); audio sync only
+//! applies at 1×. `speed_percent()` reports emulated time over wall time,
+//! measured over the last `SPEED_WINDOW_US`.
+//!
+//! Time comes from a `HostClock`, so pacing is testable with `FixedClock`.
+
+use crate::{HostClock, CPU_HZ, CYCLES_PER_FRAME};
+
+/// Game Boy frames per second (~59.7275)
+pub const GB_FPS: f64 = CPU_HZ as f64 / CYCLES_PER_FRAME as f64;
+/// Frames a clock-synced host may fall behind before pacing restarts from now
+pub const MAX_LAG_FRAMES: u64 = 4;
+/// Wall time `speed_percent()` averages over
+pub const SPEED_WINDOW_US: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThrottleSync {
+    /// Pace against the host clock
+    Clock,
+    /// Pace against an audio output queue: wait while more than
+    /// `target_queue` samples are queued at `sample_rate_hz`
+    Audio { sample_rate_hz: u32, target_queue: u32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    /// Emulated frames per second at 1× (default `GB_FPS`)
+    pub target_fps: f64,
+    pub sync: ThrottleSync,
+    turbo: f64,
+    /// Schedule origin (host µs) and frames paced since it
+    origin: Option<u64>,
+    frames: u64,
+    /// Speed measurement window: start (host µs) and emulated frames in it
+    window: Option<(u64, u64)>,
+    speed: f64,
+}
+
+impl Default for Throttle {
+    fn default() -> Self { Self::new() }
+}
+
+impl Throttle {
+    /// Realtime, clock-synced
+    pub fn new() -> Self {
+        Throttle { target_fps: GB_FPS, sync: ThrottleSync::Clock, turbo: 1.0, origin: None, frames: 0, window: None, speed: 0.0 }
+    }
+    pub fn with_fps(mut self, fps: f64) -> Self { self.target_fps = fps.max(1.0); self }
+    pub fn with_turbo(mut self, turbo: f64) -> Self { self.set_turbo(turbo); self }
+    pub fn with_audio_sync(mut self, sample_rate_hz: u32, target_queue: u32) -> Self {
+        self.sync = ThrottleSync::Audio { sample_rate_hz, target_queue };
+        self
+    }
+
+    pub fn turbo(&self) -> f64 { self.turbo }
+
+    /// Speed multiplier (2.0 = double speed, 0 = unthrottled); pacing
+    /// restarts from the next frame
+    pub fn set_turbo(&mut self, turbo: f64) {
+        self.turbo = turbo.max(0.0);
+        self.origin = None;
+    }
+
+    /// Host µs between frames at the current target speed (0 = unthrottled)
+    pub fn frame_us(&self) -> f64 {
+        if self.turbo == 0.0 { 0.0 } else { 1e6 / (self.target_fps * self.turbo) }
+    }
+
+    /// Count one emulated frame finished at `now_us` and return how long to
+    /// wait before the next. `queued_samples` is the host's audio queue
+    /// depth (ignored under clock sync).
+    pub fn frame_delay_us(&mut self, now_us: u64, queued_samples: usize) -> u64 {
+        self.measure(now_us);
+        match self.sync {
+            ThrottleSync::Audio { sample_rate_hz, target_queue } if self.turbo == 1.0 && sample_rate_hz > 0 => {
+                self.origin = None;
+                let excess = (queued_samples as u64).saturating_sub(target_queue as u64);
+                excess * 1_000_000 / sample_rate_hz as u64
+            }
+            _ => self.clock_delay(now_us),
+        }
+    }
+
+    /// `frame_delay_us` against `clock`, then sleep for it
+    pub fn wait(&mut self, clock: &dyn HostClock, queued_samples: usize) {
+        let delay = self.frame_delay_us(clock.now_us(), queued_samples);
+        if delay > 0 { std::thread::sleep(std::time::Duration::from_micros(delay)); }
+    }
+
+    /// Emulated time over wall time, in percent of realtime (100 = full
+    /// speed); 0 until two frames have been paced
+    pub fn speed_percent(&self) -> f64 { self.speed }
+
+    fn clock_delay(&mut self, now_us: u64) -> u64 {
+        let frame_us = self.frame_us();
+        if frame_us == 0.0 { return 0; }
+        let origin = *self.origin.get_or_insert_with(|| { self.frames = 0; now_us });
+        self.frames += 1;
+        let due = origin + (self.frames as f64 * frame_us) as u64;
+        if now_us > due + (MAX_LAG_FRAMES as f64 * frame_us) as u64 {
+            // Too far behind (a stall, a debugger): don't race to catch up
+            (self.origin, self.frames) = (Some(now_us), 0);
+            return 0;
+        }
+        due.saturating_sub(now_us)
+    }
+
+    fn measure(&mut self, now_us: u64) {
+        let Some((start, frames)) = self.window.as_mut() else {
+            self.window = Some((now_us, 0));
+            return;
+        };
+        *frames += 1;
+        let elapsed = now_us.saturating_sub(*start);
+        if elapsed > 0 {
+            self.speed = *frames as f64 * 1e6 / GB_FPS / elapsed as f64 * 100.0;
+        }
+        if elapsed >= SPEED_WINDOW_US {
+            self.window = Some((now_us, 0));
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! time_travel — reverse stepping by replay from keyframes
+//!
+//! With `GbCore::time_travel` set, `step` copies the machine into a keyframe
+//! (everything `state_hash` covers, held in memory) every `interval` steps
+//! and logs the joypad state and run target whenever they change. A step is
+//! what `step` / `debug_step` advance by: one instruction, interrupt
+//! dispatch or halted span.
+//!
+//! `GbCore::reverse_step` rebuilds the machine as it was one step earlier:
+//! it restores the nearest keyframe at or before that point and replays the
+//! steps in between with the logged input (button changes go through
+//! `set_buttons`, so a press raises the joypad interrupt again), exactly
+//! as they ran the first time. `GbCore::reverse_continue` replays keyframe
+//! span after span, newest first, to find the last time PC reached an
+//! enabled breakpoint with its conditions holding, and stops there with
+//! that instruction not yet run, like gdb's `reverse-continue`; stepping
+//! forward again runs it without stopping. Hit counts are left alone.
+//!
+//! Replayed steps run with breakpoints, watchpoints and the host's
+//! recorders (trace, coverage, IO log, PPU timeline, motion, console)
+//! detached, so nothing is reported or recorded twice. The trace ring is
+//! cut back to the restored point, so it still ends with the instruction
+//! that led there, and the APU sample buffer is emptied.
+//!
+//! History reaches back `interval * capacity` steps. Stepping forward after
+//! going back records a new future in place of the old one. Input that
+//! does not come through the joypad (stimulus providers, VIN, a link
+//! partner) is not logged; detach it while debugging in reverse. Memory the
+//! host pokes directly and state loads are not steps: call `clear` after a
+//! poke (`load_state`, `reset` and `swap_rom` clear the history themselves).
+
+use crate::{
+    Apu, BreakHit, Breakpoints, Clock, ConsoleCapture, CoverageVector, ExecCoverage, GbCore, IoWriteLog, LinkTransport,
+    Mbc, MotionTracker, OamDma, Ppu, PpuTimeline, RasterCapture, Registers, ShadowStack, StimulusInputs, StimulusProvider, Timer,
+    TraceRing, Watchpoints,
+};
+#[cfg(feature = "profile")]
+use crate::Profiler;
+use std::collections::VecDeque;
+
+/// The machine as it was before step `position`
+#[derive(Debug, Clone)]
+struct Keyframe {
+    position: u64,
+    run_target: u64,
+    regs: Registers,
+    clock: Clock,
+    halted: bool, ime: bool, ime_pending: bool, stopped: bool, locked: bool, halt_bug: bool,
+    at_frame_boundary: bool,
+    shadow_stack: ShadowStack,
+    ram: Vec<u8>,
+    vram: [[u8; 0x2000]; 2], vram_bank: u8,
+    wram: [[u8; 0x1000]; 8], wram_bank: u8,
+    hram: [u8; 0x7F], oam: [u8; 0xA0], io: [u8; 0x80], ie: u8, if_reg: u8,
+    mbc: Mbc, ppu: Ppu, apu: Apu, timer: Timer, dma: OamDma,
+    joypad: u8, buttons: u8,
+    double_speed: bool, speed_switch_armed: bool,
+    bg_cpal: [u8; 64], bg_cps: u8, obj_cpal: [u8; 64], obj_cps: u8,
+    stimulus: StimulusInputs,
+    sram_dirty: bool, sram_closed: bool,
+    data_bus: u8,
+}
+
+impl Keyframe {
+    fn capture(core: &GbCore, position: u64) -> Self {
+        let b = &core.bus;
+        let mut apu = b.apu.clone();
+        apu.clear_samples();
+        Keyframe {
+            position, run_target: core.run_target, regs: core.regs.clone(), clock: core.clock.clone(),
+            halted: core.halted, ime: core.ime, ime_pending: core.ime_pending, stopped: core.stopped,
+            locked: core.locked, halt_bug: core.halt_bug, at_frame_boundary: core.at_frame_boundary,
+            shadow_stack: core.shadow_stack.clone(),
+            ram: b.ram.clone(), vram: b.vram, vram_bank: b.vram_bank, wram: b.wram, wram_bank: b.wram_bank,
+            hram: b.hram, oam: b.oam, io: b.io, ie: b.ie, if_reg: b.if_reg,
+            mbc: b.mbc.clone(), ppu: b.ppu.clone(), apu, timer: b.timer.clone(), dma: b.dma.clone(),
+            joypad: b.joypad, buttons: b.buttons, double_speed: b.double_speed, speed_switch_armed: b.speed_switch_armed,
+            bg_cpal: b.bg_cpal, bg_cps: b.bg_cps, obj_cpal: b.obj_cpal, obj_cps: b.obj_cps,
+            stimulus: b.stimulus.clone(), sram_dirty: b.sram_dirty, sram_closed: b.sram_closed, data_bus: b.data_bus.get(),
+        }
+    }
+
+    fn restore(&self, core: &mut GbCore) {
+        core.run_target = self.run_target;
+        core.regs = self.regs.clone();
+        core.clock = self.clock.clone();
+        (core.halted, core.ime, core.ime_pending, core.stopped, core.locked, core.halt_bug) =
+            (self.halted, self.ime, self.ime_pending, self.stopped, self.locked, self.halt_bug);
+        core.at_frame_boundary = self.at_frame_boundary;
+        (core.lock_hit, core.soft_break_hit, core.debug_frame_pending) = (None, None, false);
+        core.shadow_stack = self.shadow_stack.clone();
+        let b = &mut core.bus;
+        b.ram.clone_from(&self.ram);
+        (b.vram, b.vram_bank, b.wram, b.wram_bank) = (self.vram, self.vram_bank, self.wram, self.wram_bank);
+        (b.hram, b.oam, b.io, b.ie, b.if_reg) = (self.hram, self.oam, self.io, self.ie, self.if_reg);
+        b.mbc = self.mbc.clone();
+        b.ppu = self.ppu.clone();
+        b.apu = self.apu.clone();
+        b.timer = self.timer.clone();
+        b.dma = self.dma.clone();
+        (b.joypad, b.buttons, b.double_speed, b.speed_switch_armed) = (self.joypad, self.buttons, self.double_speed, self.speed_switch_armed);
+        (b.bg_cpal, b.bg_cps, b.obj_cpal, b.obj_cps) = (self.bg_cpal, self.bg_cps, self.obj_cpal, self.obj_cps);
+        b.stimulus = self.stimulus.clone();
+        (b.sram_dirty, b.sram_closed) = (self.sram_dirty, self.sram_closed);
+        b.data_bus.set(self.data_bus);
+        // Blocks decoded from WRAM / HRAM may no longer match it
+        if let Some(c) = b.block_cache.as_mut() { c.clear(); }
+    }
+}
+
+/// Host attachments a replay must not feed: taken off the core for its
+/// duration and put back after
+struct Detached {
+    trace: Option<TraceRing>,
+    exec_coverage: Option<Box<ExecCoverage>>,
+    #[cfg(feature = "profile")]
+    profiler: Option<Box<Profiler>>,
+    coverage: Option<Box<CoverageVector>>,
+    io_log: Option<Box<IoWriteLog>>,
+    ppu_timeline: Option<Box<PpuTimeline>>,
+    raster: Option<Box<RasterCapture>>,
+    watchpoints: Watchpoints,
+    motion: Option<MotionTracker>,
+    console: ConsoleCapture,
+    stimulus_provider: Option<Box<dyn StimulusProvider>>,
+    link: Option<Box<dyn LinkTransport>>,
+    breakpoints: Breakpoints,
+}
+
+impl Detached {
+    fn take(core: &mut GbCore) -> Self {
+        Detached {
+            trace: core.trace.take(),
+            exec_coverage: core.exec_coverage.take(),
+            #[cfg(feature = "profile")]
+            profiler: core.profiler.take(),
+            coverage: core.bus.coverage.take(),
+            io_log: core.bus.io_log.take(),
+            ppu_timeline: core.bus.ppu_timeline.take(),
+            raster: core.bus.raster.take(),
+            watchpoints: std::mem::take(&mut core.bus.watchpoints),
+            motion: core.motion.take(),
+            console: std::mem::take(&mut core.bus.console),
+            stimulus_provider: core.stimulus_provider.take(),
+            link: core.link.take(),
+            breakpoints: std::mem::take(&mut core.breakpoints),
+        }
+    }
+
+    fn restore(self, core: &mut GbCore) {
+        core.trace = self.trace;
+        core.exec_coverage = self.exec_coverage;
+        #[cfg(feature = "profile")]
+        { core.profiler = self.profiler; }
+        core.bus.coverage = self.coverage;
+        core.bus.io_log = self.io_log;
+        core.bus.ppu_timeline = self.ppu_timeline;
+        core.bus.raster = self.raster;
+        core.bus.watchpoints = self.watchpoints;
+        core.motion = self.motion;
+        core.bus.console = self.console;
+        core.stimulus_provider = self.stimulus_provider;
+        core.link = self.link;
+        core.breakpoints = self.breakpoints;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeTravel {
+    /// Steps between keyframes
+    pub interval: u64,
+    /// Keyframes kept; the oldest is dropped beyond this
+    pub capacity: usize,
+    keyframes: VecDeque<Box<Keyframe>>,
+    /// (position, buttons, run target) for each step before which either changed
+    inputs: Vec<(u64, u8, u64)>,
+    last_input: Option<(u8, u64)>,
+    position: u64,
+}
+
+impl TimeTravel {
+    /// A keyframe every `interval` steps, the last `capacity` of them kept
+    /// (each holds a copy of RAM, VRAM, cartridge RAM and the framebuffer)
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        TimeTravel { interval: interval.max(1), capacity: capacity.max(1), keyframes: VecDeque::new(), inputs: Vec::new(), last_input: None, position: 0 }
+    }
+
+    /// Steps taken since time travel was attached, less those stepped back
+    pub fn position(&self) -> u64 { self.position }
+    /// Earliest position reverse stepping can reach
+    pub fn earliest(&self) -> Option<u64> { self.keyframes.front().map(|k| k.position) }
+    pub fn keyframes(&self) -> usize { self.keyframes.len() }
+
+    /// Forget the history; the next step starts it again
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.inputs.clear();
+        self.last_input = None;
+    }
+
+    /// Called by `step` before the machine moves
+    pub(crate) fn before_step(&mut self, core: &GbCore) {
+        let input = (core.bus.buttons, core.run_target);
+        if self.last_input != Some(input) {
+            self.inputs.push((self.position, input.0, input.1));
+            self.last_input = Some(input);
+        }
+        if self.keyframes.back().is_none_or(|k| self.position >= k.position + self.interval) {
+            self.keyframes.push_back(Box::new(Keyframe::capture(core, self.position)));
+            if self.keyframes.len() > self.capacity {
+                self.keyframes.pop_front();
+                let oldest = self.keyframes.front().map_or(0, |k| k.position);
+                self.inputs.retain(|i| i.0 > oldest);
+            }
+        }
+    }
+
+    /// Called by `step` afterwards; a step stopped by a breakpoint ran nothing
+    pub(crate) fn after_step(&mut self, advanced: bool) {
+        if advanced { self.position += 1; }
+    }
+
+    /// Restore keyframe `k` and step to `target`, calling `each` with the
+    /// core and its position before every step
+    fn replay(&self, core: &mut GbCore, k: usize, target: u64, mut each: impl FnMut(&GbCore, u64)) {
+        let kf = &self.keyframes[k];
+        kf.restore(core);
+        let mut inputs = self.inputs.iter().filter(|i| i.0 > kf.position).peekable();
+        let mut pos = kf.position;
+        loop {
+            // Input set at a position is part of the state there
+            while let Some(&(_, buttons, run_target)) = inputs.next_if(|i| i.0 <= pos) {
+                core.set_buttons(buttons);
+                core.run_target = run_target;
+            }
+            if pos >= target { break; }
+            each(core, pos);
+            let t = core.clock.t_cycles;
+            // Watchpoint, lock and LD B,B stops come after the step ran
+            let _ = core.step_once();
+            if core.clock.t_cycles == t { break; }
+            pos += 1;
+        }
+    }
+
+    /// The core now stands at `position`: drop the history after it
+    fn rewound(&mut self, core: &mut GbCore, position: u64) {
+        self.position = position;
+        self.keyframes.retain(|k| k.position <= position);
+        self.inputs.retain(|i| i.0 <= position);
+        self.last_input = Some((core.bus.buttons, core.run_target));
+        if let Some(t) = core.trace.as_mut() { t.rewind(core.clock.t_cycles); }
+        core.bus.apu.clear_samples();
+    }
+}
+
+/// `GbCore::reverse_step`
+pub(crate) fn reverse_step(core: &mut GbCore) -> bool {
+    let Some(mut tt) = core.time_travel.take() else { return false };
+    let target = tt.position.checked_sub(1);
+    let k = target.and_then(|t| tt.keyframes.iter().rposition(|k| k.position <= t));
+    if let (Some(target), Some(k)) = (target, k) {
+        let detached = Detached::take(core);
+        tt.replay(core, k, target, |_, _| {});
+        detached.restore(core);
+        tt.rewound(core, target);
+    }
+    core.time_travel = Some(tt);
+    k.is_some()
+}
+
+/// `GbCore::reverse_continue`
+pub(crate) fn reverse_continue(core: &mut GbCore) -> Option<BreakHit> {
+    let mut tt = core.time_travel.take()?;
+    let end = tt.position;
+    let detached = Detached::take(core);
+    let mut found = None;
+    for k in (0..tt.keyframes.len()).rev() {
+        let span_end = tt.keyframes.get(k + 1).map_or(end, |n| n.position).min(end);
+        if tt.keyframes[k].position >= span_end { continue; }
+        let mut last = None;
+        tt.replay(core, k, span_end, |c, pos| {
+            if let Some(b) = breakpoint_due(c, &detached.breakpoints) { last = Some((pos, b)); }
+        });
+        if let Some((pos, id)) = last { found = Some((k, pos, id)); break; }
+    }
+    // No breakpoint in the history: stop at its start
+    let stop = found.or_else(|| (!tt.keyframes.is_empty()).then(|| (0, tt.keyframes[0].position, 0)));
+    if let Some((k, pos, _)) = stop { tt.replay(core, k, pos, |_, _| {}); }
+    detached.restore(core);
+    if let Some((_, pos, _)) = stop { tt.rewound(core, pos); }
+    core.time_travel = Some(tt);
+    let (_, _, id) = found?;
+    let pc = core.regs.pc;
+    core.breakpoints.resume_at(pc);
+    let hits = core.breakpoints.get(id).map_or(0, |b| b.hits);
+    Some(BreakHit { id, pc, hits, t_cycles: core.clock.t_cycles })
+}
+
+/// The breakpoint `step` would stop at next, by PC and conditions alone
+fn breakpoint_due(core: &GbCore, breakpoints: &Breakpoints) -> Option<u32> {
+    let dispatch = core.ime && core.bus.if_reg & core.bus.ie & 0x1F != 0;
+    if core.halted || core.stopped || core.locked || dispatch { return None; }
+    breakpoints.matching(&core.regs).map(|b| b.id)
+}
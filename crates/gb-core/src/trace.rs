@@ -0,0 +1,96 @@
+//! trace — ring buffer of the last executed instructions
+//!
+//! Opt-in: set `GbCore::trace` to `Some(TraceRing::new(n))` and every
+//! instruction `step` executes is recorded with the registers as they were
+//! before it ran. Once full, the oldest entry is overwritten, so after a crash
+//! or a bad branch the ring holds the path that led there. Interrupt dispatch
+//! and HALT idle cycles are not instructions and are not recorded.
+
+use crate::{disassemble, Registers};
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+/// One executed instruction
+#[derive(Debug, Clone)]
+pub struct TraceEntry {
+    /// Address the opcode was fetched from
+    pub pc: u16,
+    pub opcode: u8,
+    /// Second opcode byte of CB-prefixed instructions
+    pub cb: Option<u8>,
+    /// The two bytes after the opcode (whether or not the instruction uses them)
+    pub operands: [u8; 2],
+    /// Registers before the instruction ran
+    pub regs: Registers,
+    /// `Clock::t_cycles` before the instruction ran
+    pub t_cycles: u64,
+}
+
+impl TraceEntry {
+    /// The instruction as `disassemble` prints it, e.g. `swap a`
+    pub fn mnemonic(&self) -> String {
+        disassemble(&[self.opcode, self.operands[0], self.operands[1]], self.pc).0
+    }
+
+    /// `t=1234 PC=0150 op=CB 37 A=01 F=B0 B=00 C=13 D=00 E=D8 H=01 L=4D SP=FFFE  swap a`
+    pub fn to_text(&self) -> String {
+        let op = match self.cb {
+            Some(cb) => format!("CB {cb:02X}"),
+            None => format!("{:02X}", self.opcode),
+        };
+        let r = &self.regs;
+        format!("t={} PC={:04X} op={} A={:02X} F={:02X} B={:02X} C={:02X} D={:02X} E={:02X} H={:02X} L={:02X} SP={:04X}  {}",
+            self.t_cycles, self.pc, op, r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, r.sp, self.mnemonic())
+    }
+    pub fn to_json(&self) -> String {
+        let r = &self.regs;
+        let cb = self.cb.map_or("null".to_string(), |v| v.to_string());
+        format!("{{\"t_cycles\":{},\"pc\":{},\"opcode\":{},\"cb\":{},\"asm\":\"{}\",\"regs\":{{\"a\":{},\"f\":{},\"b\":{},\"c\":{},\"d\":{},\"e\":{},\"h\":{},\"l\":{},\"sp\":{}}}}}",
+            self.t_cycles, self.pc, self.opcode, cb, self.mnemonic(), r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, r.sp)
+    }
+}
+
+/// Fixed-capacity history of executed instructions, oldest first
+#[derive(Debug, Clone)]
+pub struct TraceRing {
+    entries: VecDeque<TraceEntry>,
+    capacity: usize,
+}
+
+impl TraceRing {
+    /// A ring keeping the last `capacity` instructions (at least one)
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        TraceRing { entries: VecDeque::with_capacity(capacity), capacity }
+    }
+    pub fn push(&mut self, entry: TraceEntry) {
+        if self.entries.len() == self.capacity { self.entries.pop_front(); }
+        self.entries.push_back(entry);
+    }
+    /// Recorded instructions, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &TraceEntry> { self.entries.iter() }
+    /// Most recently executed instruction
+    pub fn last(&self) -> Option<&TraceEntry> { self.entries.back() }
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+    pub fn capacity(&self) -> usize { self.capacity }
+    pub fn clear(&mut self) { self.entries.clear(); }
+    /// Drop the instructions that ran at or after `t_cycles`, once the core
+    /// has been rewound there (see `time_travel.rs`)
+    pub fn rewind(&mut self, t_cycles: u64) {
+        while self.entries.back().is_some_and(|e| e.t_cycles >= t_cycles) { self.entries.pop_back(); }
+    }
+
+    /// One line per instruction (see `TraceEntry::to_text`), oldest first
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for e in &self.entries {
+            let _ = writeln!(out, "{}", e.to_text());
+        }
+        out
+    }
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self.entries.iter().map(TraceEntry::to_json).collect();
+        format!("{{\"capacity\":{},\"entries\":[{}]}}", self.capacity, entries.join(","))
+    }
+}
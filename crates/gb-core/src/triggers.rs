@@ -0,0 +1,208 @@
+//! triggers — per-frame watch expressions that capture labelled datasets
+//!
+//! A `TriggerRule` watches one byte (or little-endian word) of memory and
+//! fires when it changes the way the rule asks; each firing captures a
+//! screenshot and / or a savestate under the rule's label. Evaluated once
+//! per frame (`WatchTriggers::evaluate`, after `run_frame`), a handful of
+//! rules turns an ordinary recording into an event-aligned dataset:
+//!
+//! ```text
+//! # label: address[.w] condition [-> actions]
+//! level_up: C0A0 increases -> screenshot savestate
+//! death:    $D022 == 0 -> screenshot cooldown=120
+//! score:    0xC0F0.w >= 1000
+//! ```
+//!
+//! Conditions are `increases`, `decreases`, `changes`, or a comparison
+//! (`== != < <= > >=`, as in breakpoint conditions) that fires on the frame
+//! it becomes true. Actions default to both captures; `cooldown=N` ignores
+//! the rule for N frames after it fires. Addresses are hex, values decimal
+//! or `0x` / `$` hex. The first evaluation only records starting values.
+
+use crate::{encode_png_rgb, write_sealed, BreakCmp, GbCore, LCD_HEIGHT, LCD_WIDTH};
+use std::path::Path;
+
+pub const TRIGGERS_VERSION: &str = "mrom.triggers.v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerWhen {
+    Increases,
+    Decreases,
+    Changes,
+    /// Fires when `value <cmp> operand` goes from false to true
+    Becomes(BreakCmp, u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerRule {
+    pub label: String,
+    pub addr: u16,
+    /// Read a little-endian word at `addr`
+    pub wide: bool,
+    pub when: TriggerWhen,
+    pub screenshot: bool,
+    pub savestate: bool,
+    /// Frames to ignore the rule after it fires
+    pub cooldown: u64,
+}
+
+fn parse_num(s: &str) -> Option<u16> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).or_else(|| s.strip_prefix('$')) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+impl TriggerRule {
+    /// Parse one `label: address[.w] condition [-> actions]` line
+    pub fn parse(line: &str) -> Result<TriggerRule, String> {
+        let (label, rest) = line.split_once(':').ok_or("expected `label: address condition`")?;
+        let label = label.trim();
+        if label.is_empty() || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("bad label {label:?}"));
+        }
+        let (expr, actions) = rest.split_once("->").unwrap_or((rest, ""));
+        let expr = expr.trim();
+        let (target, cond) = expr.split_once(char::is_whitespace).ok_or("expected a condition after the address")?;
+        let (addr, wide) = match target.strip_suffix(".w") { Some(a) => (a, true), None => (target, false) };
+        let addr = u16::from_str_radix(addr.trim_start_matches("0x").trim_start_matches('$'), 16)
+            .map_err(|_| format!("bad address {target:?}"))?;
+        const CMPS: [(&str, BreakCmp); 6] = [
+            ("==", BreakCmp::Eq), ("!=", BreakCmp::Ne), ("<=", BreakCmp::Le),
+            (">=", BreakCmp::Ge), ("<", BreakCmp::Lt), (">", BreakCmp::Gt),
+        ];
+        let cond = cond.trim();
+        let when = match cond.to_ascii_lowercase().as_str() {
+            "increases" => TriggerWhen::Increases,
+            "decreases" => TriggerWhen::Decreases,
+            "changes" => TriggerWhen::Changes,
+            _ => {
+                let (op, cmp) = CMPS.iter().find(|(op, _)| cond.starts_with(op)).ok_or(format!("unknown condition {cond:?}"))?;
+                let v = cond[op.len()..].trim();
+                TriggerWhen::Becomes(*cmp, parse_num(v).ok_or(format!("bad value {v:?}"))?)
+            }
+        };
+        let mut rule = TriggerRule { label: label.to_string(), addr, wide, when, screenshot: false, savestate: false, cooldown: 0 };
+        for a in actions.split_whitespace() {
+            match a.split_once('=') {
+                None if a == "screenshot" => rule.screenshot = true,
+                None if a == "savestate" => rule.savestate = true,
+                Some(("cooldown", n)) => rule.cooldown = n.parse().map_err(|_| format!("bad cooldown {n:?}"))?,
+                _ => return Err(format!("unknown action {a:?}")),
+            }
+        }
+        if !rule.screenshot && !rule.savestate { rule.screenshot = true; rule.savestate = true; }
+        Ok(rule)
+    }
+
+    fn read(&self, core: &GbCore) -> u16 {
+        let lo = core.bus.peek(self.addr) as u16;
+        if self.wide { lo | (core.bus.peek(self.addr.wrapping_add(1)) as u16) << 8 } else { lo }
+    }
+
+    fn fires(&self, old: u16, new: u16) -> bool {
+        let holds = |v: u16, cmp: BreakCmp, x: u16| match cmp {
+            BreakCmp::Eq => v == x, BreakCmp::Ne => v != x, BreakCmp::Lt => v < x,
+            BreakCmp::Le => v <= x, BreakCmp::Gt => v > x, BreakCmp::Ge => v >= x,
+        };
+        match self.when {
+            TriggerWhen::Increases => new > old,
+            TriggerWhen::Decreases => new < old,
+            TriggerWhen::Changes => new != old,
+            TriggerWhen::Becomes(cmp, x) => holds(new, cmp, x) && !holds(old, cmp, x),
+        }
+    }
+}
+
+/// What one firing captured
+#[derive(Debug, Clone)]
+pub struct TriggerCapture {
+    pub label: String,
+    /// `Clock::frame_count()` when it fired (the replay's `"fi"`)
+    pub frame: u64,
+    pub old: u16,
+    pub new: u16,
+    /// PNG of the frame
+    pub png: Option<Vec<u8>>,
+    /// `save_state()` bytes
+    pub state: Option<Vec<u8>>,
+}
+
+impl TriggerCapture {
+    /// File stem the dataset writes it under: `<label>_f<frame:06>`
+    pub fn name(&self) -> String { format!("{}_f{:06}", self.label, self.frame) }
+}
+
+/// A rule set and everything it has captured
+#[derive(Debug, Clone, Default)]
+pub struct WatchTriggers {
+    pub rules: Vec<TriggerRule>,
+    /// Value each rule saw last frame (None before the first evaluation)
+    last: Vec<Option<u16>>,
+    /// Frame each rule may fire again from
+    quiet_until: Vec<u64>,
+    pub captures: Vec<TriggerCapture>,
+}
+
+impl WatchTriggers {
+    pub fn new(rules: Vec<TriggerRule>) -> Self {
+        let n = rules.len();
+        WatchTriggers { rules, last: vec![None; n], quiet_until: vec![0; n], captures: vec![] }
+    }
+
+    /// One rule per line; `#` starts a comment
+    pub fn parse(text: &str) -> Result<WatchTriggers, String> {
+        let mut rules = vec![];
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+            rules.push(TriggerRule::parse(line).map_err(|e| format!("line {}: {e}", n + 1))?);
+        }
+        Ok(Self::new(rules))
+    }
+
+    /// Check every rule against the current frame; returns the labels that
+    /// fired (their captures are appended to `captures`)
+    pub fn evaluate(&mut self, core: &GbCore) -> Vec<String> {
+        let frame = core.clock.frame_count();
+        let mut fired = vec![];
+        let mut shot: Option<Vec<u8>> = None;
+        let mut state: Option<Vec<u8>> = None;
+        for (i, rule) in self.rules.iter().enumerate() {
+            let new = rule.read(core);
+            let old = self.last[i].replace(new);
+            let Some(old) = old else { continue };
+            if frame < self.quiet_until[i] || !rule.fires(old, new) { continue; }
+            self.quiet_until[i] = frame + rule.cooldown + 1;
+            // Rules firing on the same frame share one capture of each kind
+            let png = rule.screenshot.then(|| shot.get_or_insert_with(|| {
+                encode_png_rgb(LCD_WIDTH as u32, LCD_HEIGHT as u32, &core.framebuffer_rgb())
+            }).clone());
+            let st = rule.savestate.then(|| state.get_or_insert_with(|| core.save_state()).clone());
+            self.captures.push(TriggerCapture { label: rule.label.clone(), frame, old, new, png, state: st });
+            fired.push(rule.label.clone());
+        }
+        fired
+    }
+
+    /// Index of every capture (mrom.triggers.v1), naming the files
+    /// `write_dataset` writes
+    pub fn to_json(&self) -> String {
+        let caps: Vec<String> = self.captures.iter().map(|c| {
+            let png = c.png.as_ref().map(|_| format!(",\"png\":\"{}.png\"", c.name())).unwrap_or_default();
+            let st = c.state.as_ref().map(|_| format!(",\"state\":\"{}.mrom.sav\"", c.name())).unwrap_or_default();
+            format!("{{\"label\":\"{}\",\"frame\":{},\"old\":{},\"new\":{}{png}{st}}}", c.label, c.frame, c.old, c.new)
+        }).collect();
+        format!("{{\"version\":\"{TRIGGERS_VERSION}\",\"captures\":[{}]}}", caps.join(","))
+    }
+
+    /// Write every capture's PNG / savestate and `triggers.json` into `dir`
+    pub fn write_dataset(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        for c in &self.captures {
+            if let Some(png) = &c.png { std::fs::write(dir.join(format!("{}.png", c.name())), png)?; }
+            if let Some(st) = &c.state { write_sealed(&dir.join(format!("{}.mrom.sav", c.name())), st)?; }
+        }
+        std::fs::write(dir.join("triggers.json"), self.to_json())
+    }
+}
@@ -0,0 +1,87 @@
+//! vin — cartridge audio-in (VIN) and external audio mixing
+//!
+//! The cartridge edge has an analog VIN pin that NR50 can route into the left
+//! (bit 7) and/or right (bit 3) output. Carts with extra audio hardware drive
+//! it; hosts can use the same path to layer commentary or TTS into captures.
+//! The host feeds PCM at APU_SAMPLE_RATE into `VinInput` (directly, or per
+//! frame through a `VinSource`) and the APU consumes one sample per output
+//! sample, whether or not NR50 currently routes it, so the stream stays in time.
+
+use crate::APU_SAMPLE_RATE;
+use std::collections::VecDeque;
+
+/// At most one second of VIN audio is queued; older samples are dropped
+pub const VIN_MAX_QUEUED: usize = APU_SAMPLE_RATE as usize;
+
+/// Queued external audio, mono signed PCM-16
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VinInput {
+    queue: VecDeque<i16>,
+    /// Samples dropped because the queue was full
+    pub dropped: u64,
+}
+
+impl VinInput {
+    pub fn push(&mut self, samples: &[i16]) {
+        self.queue.extend(samples);
+        let over = self.queue.len().saturating_sub(VIN_MAX_QUEUED);
+        if over > 0 {
+            self.queue.drain(..over);
+            self.dropped += over as u64;
+        }
+    }
+    pub fn queued(&self) -> usize { self.queue.len() }
+    pub fn clear(&mut self) { self.queue.clear(); }
+    /// Next sample, silence when the host has not kept up
+    pub fn next_sample(&mut self) -> i16 { self.queue.pop_front().unwrap_or(0) }
+}
+
+/// Mix a VIN sample into the channel mix per NR50: bit 7 / bit 3 route VIN
+/// left / right, scaled by that side's volume (bits 6-4 / 2-0, 0-7 → 1/8-8/8).
+pub fn vin_mix(mix: i16, vin: i16, nr50: u8) -> (i16, i16) {
+    let side = |route: u8, vol: u8| {
+        if nr50 & route == 0 { return mix; }
+        mix.saturating_add((vin as i32 * (vol as i32 + 1) / 8) as i16)
+    };
+    (side(0x80, (nr50 >> 4) & 7), side(0x08, nr50 & 7))
+}
+
+/// Supplies VIN audio once per frame (`GbCore::set_vin_source`)
+pub trait VinSource: Send {
+    /// About `n` samples for `frame`; shorter returns leave silence
+    fn samples(&mut self, frame: u64, n: usize) -> Vec<i16>;
+}
+
+/// Any `FnMut(frame, n) -> Vec<i16>` is a source
+impl<F: FnMut(u64, usize) -> Vec<i16> + Send> VinSource for F {
+    fn samples(&mut self, frame: u64, n: usize) -> Vec<i16> { self(frame, n) }
+}
+
+/// Plays a PCM clip (e.g. a rendered TTS line) through VIN, once or looped
+#[derive(Debug, Clone)]
+pub struct PcmVin {
+    pub pcm: Vec<i16>,
+    pub looped: bool,
+    pos: usize,
+}
+
+impl PcmVin {
+    pub fn new(pcm: Vec<i16>, looped: bool) -> Self { PcmVin { pcm, looped, pos: 0 } }
+    pub fn finished(&self) -> bool { !self.looped && self.pos >= self.pcm.len() }
+}
+
+impl VinSource for PcmVin {
+    fn samples(&mut self, _frame: u64, n: usize) -> Vec<i16> {
+        let mut out = Vec::with_capacity(n);
+        while out.len() < n && !self.pcm.is_empty() {
+            if self.pos >= self.pcm.len() {
+                if !self.looped { break; }
+                self.pos = 0;
+            }
+            let take = (n - out.len()).min(self.pcm.len() - self.pos);
+            out.extend_from_slice(&self.pcm[self.pos..self.pos + take]);
+            self.pos += take;
+        }
+        out
+    }
+}
@@ -0,0 +1,137 @@
+//! watch — memory read / write watchpoints
+//!
+//! `Bus::watchpoints` holds address ranges to watch for reads, writes or
+//! both. While an instruction executes, `Bus::read` / `Bus::write` report
+//! every access inside a watched range; reads made outside instructions
+//! (host peeks, savestates, interrupt dispatch, DMA) are not reported.
+//! Opcode and operand fetches are reads like any other, so a watch on ROM
+//! sees the code that runs there.
+//! Each (address, access) is reported once per instruction.
+//!
+//! After the instruction, every hit is appended to the log
+//! (`Watchpoints::take_hits`). If a fired watchpoint has `stop` set (the
+//! default), `step` / `run_frame` return `Err(CoreError::Watch(hit))`; unlike
+//! a breakpoint the instruction has already run, so calling again simply
+//! continues. Clear `stop` to log accesses without interrupting the run.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAccess { Read, Write }
+
+impl WatchAccess {
+    pub fn as_str(self) -> &'static str {
+        match self { WatchAccess::Read => "read", WatchAccess::Write => "write" }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub id: u32,
+    /// Inclusive address range
+    pub start: u16,
+    pub end: u16,
+    pub on_read: bool,
+    pub on_write: bool,
+    pub enabled: bool,
+    /// Stop `step` / `run_frame` when it fires (else only log)
+    pub stop: bool,
+    /// Instructions that accessed the range
+    pub hits: u64,
+}
+
+impl Watchpoint {
+    fn matches(&self, addr: u16, access: WatchAccess) -> bool {
+        self.enabled && (self.start..=self.end).contains(&addr) && match access {
+            WatchAccess::Read => self.on_read,
+            WatchAccess::Write => self.on_write,
+        }
+    }
+}
+
+/// One watched access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub id: u32,
+    /// Address of the instruction that made the access
+    pub pc: u16,
+    pub addr: u16,
+    /// Value read, or value written
+    pub value: u8,
+    pub access: WatchAccess,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "watchpoint {}: {} {:04X} = {:02X} at PC {:04X}", self.id, self.access.as_str(), self.addr, self.value, self.pc)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    next_id: u32,
+    /// PC of the executing instruction; None outside instructions
+    armed: Cell<Option<u16>>,
+    /// Accesses of the executing instruction (reads happen through `&Bus`)
+    pending: RefCell<Vec<WatchHit>>,
+    log: Vec<WatchHit>,
+    /// Stopping hit of the last instruction, for `GbCore::step` to return
+    stop: Option<WatchHit>,
+}
+
+impl Watchpoints {
+    /// Watch `start..=end` for the given accesses; returns the id
+    pub fn add(&mut self, start: u16, end: u16, on_read: bool, on_write: bool) -> u32 {
+        self.next_id += 1;
+        let (start, end) = (start.min(end), start.max(end));
+        self.list.push(Watchpoint { id: self.next_id, start, end, on_read, on_write, enabled: true, stop: true, hits: 0 });
+        self.next_id
+    }
+    pub fn add_read(&mut self, addr: u16) -> u32 { self.add(addr, addr, true, false) }
+    pub fn add_write(&mut self, addr: u16) -> u32 { self.add(addr, addr, false, true) }
+    pub fn remove(&mut self, id: u32) -> bool {
+        let before = self.list.len();
+        self.list.retain(|w| w.id != id);
+        self.list.len() != before
+    }
+    pub fn get(&self, id: u32) -> Option<&Watchpoint> { self.list.iter().find(|w| w.id == id) }
+    /// Change the range, accesses, `enabled` or `stop` of a watchpoint
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Watchpoint> { self.list.iter_mut().find(|w| w.id == id) }
+    pub fn iter(&self) -> impl Iterator<Item = &Watchpoint> { self.list.iter() }
+    pub fn len(&self) -> usize { self.list.len() }
+    pub fn is_empty(&self) -> bool { self.list.is_empty() }
+    pub fn clear(&mut self) { self.list.clear(); }
+    /// Hits logged so far, oldest first
+    pub fn hits(&self) -> &[WatchHit] { &self.log }
+    /// Return and clear the hit log
+    pub fn take_hits(&mut self) -> Vec<WatchHit> { std::mem::take(&mut self.log) }
+
+    /// Start reporting accesses for the instruction at `pc`
+    pub(crate) fn arm(&self, pc: u16) { self.armed.set(Some(pc)); }
+    pub(crate) fn is_armed(&self) -> bool { self.armed.get().is_some() }
+
+    /// Called by `Bus::read` / `Bus::write` while armed
+    pub(crate) fn access(&self, addr: u16, value: u8, access: WatchAccess) {
+        let Some(pc) = self.armed.get() else { return };
+        let mut pending = self.pending.borrow_mut();
+        for w in self.list.iter().filter(|w| w.matches(addr, access)) {
+            if !pending.iter().any(|h| h.id == w.id && h.addr == addr && h.access == access) {
+                pending.push(WatchHit { id: w.id, pc, addr, value, access });
+            }
+        }
+    }
+
+    /// End of the instruction: log its hits and keep the first one that stops
+    pub(crate) fn finish(&mut self) {
+        self.armed.set(None);
+        let pending = std::mem::take(self.pending.get_mut());
+        for w in self.list.iter_mut() {
+            if pending.iter().any(|h| h.id == w.id) { w.hits += 1; }
+        }
+        self.stop = pending.iter().find(|h| self.list.iter().any(|w| w.id == h.id && w.stop)).copied();
+        self.log.extend(pending);
+    }
+    pub(crate) fn take_stop(&mut self) -> Option<WatchHit> { self.stop.take() }
+}
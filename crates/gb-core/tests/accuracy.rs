@@ -0,0 +1,68 @@
+//! Accuracy profiles: one switch over fast halt, block cache, DMA bus lock, open bus and pixel FIFO
+
+use gb_core::*;
+
+fn core(model: HardwareModel) -> GbCore {
+    let cart = Cartridge::from_bytes(RomBuilder::new().code(&[0x18, 0xFE]).build()).unwrap();
+    GbCore::with_config(cart, CoreConfig { model, ..Default::default() })
+}
+
+#[test]
+fn each_profile_sets_its_toggles() {
+    let mut c = core(HardwareModel::Cgb);
+    c.set_accuracy(AccuracyProfile::Fast);
+    assert!(c.fast_halt && c.bus.block_cache.is_some() && !c.bus.dma_bus_lock);
+    assert_eq!(c.bus.open_bus, OpenBusPolicy::AllFF);
+    c.set_accuracy(AccuracyProfile::Balanced);
+    assert!(c.fast_halt && c.bus.block_cache.is_none() && c.bus.dma_bus_lock);
+    assert_eq!(c.bus.open_bus, OpenBusPolicy::AllFF);
+    c.set_accuracy(AccuracyProfile::Cycle);
+    assert!(!c.fast_halt && c.bus.block_cache.is_none() && c.bus.dma_bus_lock && c.bus.ppu.fifo.is_some());
+    assert_eq!(c.bus.open_bus, OpenBusPolicy::CgbBehavior);
+    c.set_accuracy(AccuracyProfile::Balanced);
+    assert!(c.bus.ppu.fifo.is_none());
+
+    let mut dmg = core(HardwareModel::Dmg);
+    dmg.set_accuracy(AccuracyProfile::Cycle);
+    assert_eq!(dmg.bus.open_bus, OpenBusPolicy::AllFF, "a DMG reads 0xFF");
+}
+
+#[test]
+fn fast_profile_lets_the_cpu_read_memory_during_oam_dma() {
+    for (profile, expect) in [(AccuracyProfile::Balanced, 0xFF), (AccuracyProfile::Fast, 0x5A)] {
+        let mut c = core(HardwareModel::Dmg);
+        c.set_accuracy(profile);
+        c.bus.write(0xC100, 0x5A);
+        c.bus.write(0xFF46, 0xC1);
+        c.run_cycles(8).unwrap();
+        assert!(c.bus.dma.active());
+        assert_eq!(c.bus.read(0xC100), expect, "{}", profile.as_str());
+    }
+}
+
+#[test]
+fn profiles_give_the_same_frames_and_survive_reset() {
+    let run = |profile: AccuracyProfile| {
+        let mut c = core(HardwareModel::Dmg);
+        c.set_accuracy(profile);
+        for _ in 0..10 { c.run_frame().unwrap(); }
+        c.reset();
+        assert_eq!(c.fast_halt, profile.fast_halt());
+        assert_eq!(c.bus.block_cache.is_some(), profile.block_cache());
+        assert_eq!(c.bus.dma_bus_lock, profile.dma_bus_lock());
+        assert_eq!(c.bus.ppu.fifo.is_some(), profile.pixel_fifo());
+        for _ in 0..10 { c.run_frame().unwrap(); }
+        (c.regs.pc, c.bus.ppu.framebuffer.clone())
+    };
+    assert_eq!(run(AccuracyProfile::Balanced), run(AccuracyProfile::Cycle));
+    assert_eq!(run(AccuracyProfile::Balanced), run(AccuracyProfile::Fast));
+}
+
+#[test]
+fn profiles_parse_from_their_names() {
+    for p in [AccuracyProfile::Fast, AccuracyProfile::Balanced, AccuracyProfile::Cycle] {
+        assert_eq!(AccuracyProfile::parse(p.as_str()), Some(p));
+    }
+    assert_eq!(AccuracyProfile::default(), AccuracyProfile::Balanced);
+    assert_eq!(AccuracyProfile::parse("exact"), None);
+}
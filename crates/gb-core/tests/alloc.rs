@@ -0,0 +1,109 @@
+//! Allocation-free hot path: `run_frame` and the per-frame capture helpers
+//! allocate nothing once their buffers have grown (counting allocator)
+
+use gb_core::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt::Write;
+
+thread_local! {
+    static ALLOCS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// System allocator that counts allocations made on the current thread
+struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) { System.dealloc(ptr, layout) }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCS.try_with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations(f: impl FnOnce()) -> u64 {
+    let before = ALLOCS.with(Cell::get);
+    f();
+    ALLOCS.with(Cell::get) - before
+}
+
+/// Square 1 playing, one sprite on screen, SCY and NR13 rewritten in a loop
+fn busy_core() -> GbCore {
+    let code = [
+        0x3E, 0x80, 0xE0, 0x26, 0x3E, 0x77, 0xE0, 0x24, 0x3E, 0xFF, 0xE0, 0x25,
+        0x3E, 0x80, 0xE0, 0x11, 0x3E, 0xF0, 0xE0, 0x12, 0x3E, 0x87, 0xE0, 0x14,
+        0x21, 0x00, 0xFE, 0x36, 40, 0x23, 0x36, 40, 0x23, 0x36, 1, 0x23, 0x36, 0,
+        0x3E, 0x93, 0xE0, 0x40,
+        0x3C, 0xE0, 0x42, 0xE0, 0x13, 0x18, 0xF9,
+    ];
+    GbCore::new(Cartridge::from_bytes(RomBuilder::new().code(&code).build()).unwrap())
+}
+
+#[test]
+fn run_frame_does_not_allocate() {
+    for profile in [AccuracyProfile::Fast, AccuracyProfile::Balanced, AccuracyProfile::Cycle] {
+        let mut core = busy_core();
+        core.set_accuracy(profile);
+        for _ in 0..10 { core.run_frame().unwrap(); core.bus.apu.clear_samples(); }
+        let n = allocations(|| {
+            for _ in 0..60 { core.run_frame().unwrap(); core.bus.apu.clear_samples(); }
+        });
+        assert_eq!(n, 0, "{} profile", profile.as_str());
+    }
+}
+
+#[test]
+fn per_frame_capture_reuses_its_buffers() {
+    let mut core = busy_core();
+    let mut tracker = RegDiffTracker::new(&core.bus);
+    let mut diff = RegDiff::default();
+    let mut sprites = Vec::with_capacity(40);
+    let mut rgb = vec![0u8; LCD_WIDTH * LCD_HEIGHT * 3];
+    let mut audio = Vec::with_capacity(4 * 1024);
+    let mut record = String::with_capacity(64 * 1024);
+    let mut frame = |core: &mut GbCore| {
+        core.run_frame().unwrap();
+        record.clear();
+        AudioFeatures::capture(&mut core.bus.apu).write_json(&mut record);
+        let _ = write!(record, "{:016x}{:016x}", phash(&core.bus.ppu.framebuffer), audio_hash(&core.bus.apu.sample_buffer));
+        tracker.frame_diff_into(&core.bus, &mut diff);
+        diff.write_json_fields(&mut record);
+        visible_sprites_into(&core.bus, &mut sprites);
+        write_sprites_json(&mut record, &sprites);
+        core.framebuffer_rgb_into(&mut rgb);
+        audio.clear();
+        core.bus.apu.drain_samples_into(&mut audio);
+    };
+    for _ in 0..10 { frame(&mut core); }
+    assert_eq!(allocations(|| for _ in 0..60 { frame(&mut core); }), 0);
+    assert_eq!(sprites.len(), 1);
+    assert!(record.contains("[65346,"), "SCY changes every frame: {record}");
+    assert!(!audio.is_empty());
+}
+
+#[test]
+fn buffered_helpers_match_the_allocating_ones() {
+    let mut core = busy_core();
+    for _ in 0..5 { core.run_frame().unwrap(); }
+    let mut rgb = vec![0u8; LCD_WIDTH * LCD_HEIGHT * 3];
+    core.framebuffer_rgb_into(&mut rgb);
+    assert_eq!(rgb, core.framebuffer_rgb());
+    let mut sprites = vec![];
+    visible_sprites_into(&core.bus, &mut sprites);
+    assert_eq!(sprites, visible_sprites(&core.bus));
+    let mut json = String::from("x");
+    write_sprites_json(&mut json, &sprites);
+    assert_eq!(json, format!("x{}", sprites_json(&sprites)));
+    let expected = core.bus.apu.sample_buffer.clone();
+    let mut samples = vec![7];
+    core.bus.apu.drain_samples_into(&mut samples);
+    assert_eq!((samples[0], &samples[1..]), (7, &expected[..]));
+    assert!(core.bus.apu.sample_buffer.is_empty());
+}
@@ -0,0 +1,38 @@
+//! Per-ROM artifact layout
+
+use gb_core::*;
+
+#[test]
+fn layout_is_keyed_by_rom_hash() {
+    let rom = RomBuilder::new().title("ART").build();
+    let a = RomArtifacts::new(std::path::Path::new("out"), &rom);
+    assert_eq!(a.rom_hash, rom_hash(&rom));
+    assert_eq!(a.rom_hash.len(), 8);
+    assert_eq!(a.train(), std::path::Path::new("out").join(&a.rom_hash).join("train.json"));
+    assert!(a.state("slot1").ends_with("states/slot1.mrom.sav"));
+    assert!(a.frame(42).ends_with("frames/000042.png"));
+
+    // Same hash savestate meta carries
+    let core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    assert_eq!(core.state_meta(SavePoint::Instruction).rom_hash, a.rom_hash);
+}
+
+#[test]
+fn manifest_lists_written_files() {
+    let out = std::env::temp_dir().join(format!("mrom_artifacts_{}", std::process::id()));
+    let rom = RomBuilder::new().title("ART").build();
+    let a = RomArtifacts::new(&out, &rom);
+    a.create().unwrap();
+    std::fs::write(a.train(), "{}").unwrap();
+    let core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    core.save_state_to_file(&a.state("final")).unwrap();
+    a.write_manifest("ART", "roms/art.gb").unwrap();
+    assert_eq!(a.files(), ["states/final.mrom.sav", "train.json"]);
+
+    let m = std::fs::read_to_string(a.manifest()).unwrap();
+    assert!(m.starts_with("{\"version\":\"mrom.artifacts.v1\""));
+    assert!(m.contains(&format!("\"rom_hash\":\"{}\"", a.rom_hash)));
+    assert!(m.contains("\"files\":[\"states/final.mrom.sav\",\"train.json\"]"));
+    assert_eq!(StateIndex::scan(&a.states_dir()).unwrap().slots.len(), 1);
+    std::fs::remove_dir_all(&out).unwrap();
+}
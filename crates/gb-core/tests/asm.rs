@@ -0,0 +1,156 @@
+//! SM83 assembler: encodings, labels, directives, macros and errors
+
+use gb_core::*;
+
+fn bytes(src: &str) -> Vec<u8> { assemble(src).unwrap().flatten().1 }
+
+#[test]
+fn encodes_the_instruction_forms() {
+    let src = "
+        nop
+        ld b, c
+        ld [hl], a
+        ld a, $42
+        ld hl, $c000
+        ld [hl+], a
+        ld a, (hld)
+        ld [$c123], a
+        ld a, [$ff44]
+        ldh [$40], a
+        ldh a, [$ff44]
+        ld [c], a
+        ld hl, sp+4
+        ld [$c000], sp
+        add a, b
+        sub 1
+        cp [hl]
+        xor a
+        add hl, de
+        add sp, -2
+        inc bc
+        dec [hl]
+        push af
+        pop hl
+        rst $38
+        swap a
+        bit 7, h
+        set 0, [hl]
+        res 3, b
+        jp nz, $1234
+        call c, $4567
+        ret z
+        jp hl
+        stop
+    ";
+    assert_eq!(bytes(src), [
+        0x00, 0x41, 0x77, 0x3E, 0x42, 0x21, 0x00, 0xC0, 0x22, 0x3A, 0xEA, 0x23, 0xC1, 0xFA, 0x44, 0xFF,
+        0xE0, 0x40, 0xF0, 0x44, 0xE2, 0xF8, 0x04, 0x08, 0x00, 0xC0, 0x80, 0xD6, 0x01, 0xBE, 0xAF, 0x19,
+        0xE8, 0xFE, 0x03, 0x35, 0xF5, 0xE1, 0xFF, 0xCB, 0x37, 0xCB, 0x7C, 0xCB, 0xC6, 0xCB, 0x98,
+        0xC2, 0x34, 0x12, 0xDC, 0x67, 0x45, 0xC8, 0xE9, 0x10, 0x00,
+    ]);
+}
+
+#[test]
+fn matches_the_code_emitter() {
+    let code = Code::new(CODE_START).ld_sp(0xFFFE).set_io(0x80, 0x42).serial_print("A").spin();
+    let asm = bytes("
+        ld sp, $fffe
+        ld a, $42
+        ldh [$80], a
+        ld a, 'A'
+        ldh [$01], a
+        ld a, $81
+        ldh [$02], a
+        jr @
+    ");
+    assert_eq!(asm, code.bytes());
+}
+
+#[test]
+fn labels_locals_equ_and_org() {
+    let a = assemble("
+        COUNT equ 3
+    main:
+        ld b, COUNT
+    .loop:
+        dec b
+        jr nz, .loop
+        jp other
+    other:
+        jr forward
+    forward:
+        org $0200
+    table:
+        db 1, \"hi\", -1
+        dw table + 2, $1234
+        ds 3, $aa
+    ").unwrap();
+    assert_eq!(a.symbol("COUNT"), Some(3));
+    assert_eq!(a.symbol("main"), Some(0x150));
+    assert_eq!(a.symbol("main.loop"), Some(0x152));
+    assert_eq!(a.symbol("table"), Some(0x200));
+    assert_eq!(a.chunks[0], (0x150, vec![0x06, 0x03, 0x05, 0x20, 0xFD, 0xC3, 0x58, 0x01, 0x18, 0x00]));
+    assert_eq!(a.chunks[1], (0x200, vec![0x01, b'h', b'i', 0xFF, 0x02, 0x02, 0x34, 0x12, 0xAA, 0xAA, 0xAA]));
+}
+
+#[test]
+fn macros_expand_with_arguments_and_unique_labels() {
+    let src = "
+    macro setio
+        ld a, \\2
+        ldh [\\1], a
+    endm
+    macro wait
+        ld b, \\1
+    .w\\@:
+        dec b
+        jr nz, .w\\@
+    endm
+    start:
+        setio $80, $12
+        wait 2
+        wait 3 ; second expansion gets its own label
+    ";
+    assert_eq!(bytes(src), [0x3E, 0x12, 0xE0, 0x80, 0x06, 0x02, 0x05, 0x20, 0xFD, 0x06, 0x03, 0x05, 0x20, 0xFD]);
+}
+
+#[test]
+fn errors_carry_line_numbers() {
+    assert_eq!(assemble("nop\n  frob a").unwrap_err(), "line 2: unknown mnemonic \"frob\"");
+    assert_eq!(assemble("jp nowhere").unwrap_err(), "line 1: unknown symbol \"nowhere\"");
+    assert_eq!(assemble("ld a, 300").unwrap_err(), "line 1: 8-bit value out of range: 300");
+    assert!(assemble("x:\n ds 200\n jr x").unwrap_err().starts_with("line 3: jr offset out of range"));
+    assert!(assemble("ld [hl], [hl]").unwrap_err().starts_with("line 1: unsupported operands"));
+    assert!(assemble("a:\na:").unwrap_err().contains("duplicate label"));
+    assert!(assemble("macro m\n nop").unwrap_err().contains("missing endm"));
+}
+
+#[test]
+fn assembled_rom_runs() {
+    let cart = RomBuilder::new().asm("
+        ld sp, $fffe
+        ld hl, msg
+    .next:
+        ld a, [hl+]
+        or a
+        jr z, .done
+        ldh [$01], a
+        ld a, $81
+        ldh [$02], a
+        jr .next
+    .done:
+        call sub
+        jr @
+    sub:
+        ld a, $99
+        ldh [$81], a
+        ret
+        org $0300
+    msg:
+        db \"asm ok\", 0
+    ").unwrap().cartridge().unwrap();
+    let mut core = GbCore::new(cart);
+    core.run_frame().unwrap();
+    assert_eq!(core.console_text(), "asm ok");
+    assert_eq!(core.bus.hram[1], 0x99);
+}
@@ -0,0 +1,63 @@
+//! Band-limited APU output: exact step levels, arbitrary rates, no aliasing
+
+use gb_core::{Apu, BlipBuffer, BLIP_WIDTH, CPU_HZ, CYCLES_PER_FRAME};
+
+#[test]
+fn steps_settle_exactly_and_keep_their_level() {
+    let mut blip = BlipBuffer::new(CPU_HZ, 44_100);
+    blip.add_delta(1000, 960);
+    blip.add_delta(5000, -480);
+    blip.end_frame(CPU_HZ);
+    let mut out = vec![0i16; 50_000];
+    let n = blip.read_samples(&mut out);
+    assert_eq!(n, 44_100, "one second of output");
+    assert_eq!(out[0], 0, "silence before the first step");
+    assert!(out[30..50].iter().all(|&s| s == 960), "{:?}", &out[30..50]);
+    assert!(out[100..n].iter().all(|&s| s == 480), "settled at the sum of deltas");
+    assert_eq!(blip.samples_avail(), 0);
+
+    // Skipped output still counts toward the level
+    blip.add_delta(10, 20);
+    blip.end_frame(CPU_HZ / 100);
+    blip.skip_samples(BLIP_WIDTH * 2);
+    let n = blip.read_samples(&mut out);
+    assert!(out[..n].iter().all(|&s| s == 500));
+}
+
+/// One frame of mono (left) output from a square wave at `freq` (11-bit period value)
+fn square_frame(rate: u32, freq: u16) -> Vec<i16> {
+    let mut apu = Apu::default();
+    apu.set_sample_rate(rate);
+    apu.write_reg(0x11, 0x80);
+    apu.write_reg(0x12, 0xF0);
+    apu.write_reg(0x13, freq as u8);
+    apu.write_reg(0x14, 0x80 | (freq >> 8) as u8);
+    let mut t = 0;
+    while t < CYCLES_PER_FRAME { apu.step(4); t += 4; }
+    apu.drain_samples().chunks(2).map(|lr| lr[0]).collect()
+}
+
+#[test]
+fn output_follows_the_configured_rate() {
+    for rate in [22_050, 44_100, 48_000, 96_000] {
+        let expected = rate as u64 * CYCLES_PER_FRAME / CPU_HZ;
+        let got = square_frame(rate, 0x700).len() as u64;
+        assert!(got.abs_diff(expected) <= 1, "{rate} Hz: {got} samples, expected ~{expected}");
+    }
+}
+
+#[test]
+fn tones_above_nyquist_do_not_alias() {
+    // 65.5 kHz: far above 22.05 kHz, so only its average level may come through
+    let high = square_frame(44_100, 0x7FE);
+    let settled = &high[BLIP_WIDTH * 2..];
+    let (lo, hi) = (*settled.iter().min().unwrap(), *settled.iter().max().unwrap());
+    assert!(hi - lo < 960 / 8, "ripple {lo}..{hi}");
+    let mean = settled.iter().map(|&s| s as i64).sum::<i64>() / settled.len() as i64;
+    assert!((mean - 480).abs() < 16, "mean {mean}");
+
+    // An audible tone keeps its full swing
+    let tone = square_frame(44_100, 0x700);
+    let swing = tone.iter().max().unwrap() - tone.iter().min().unwrap();
+    assert!(swing >= 900, "swing {swing}");
+}
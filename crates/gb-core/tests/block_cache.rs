@@ -0,0 +1,109 @@
+//! Basic-block cache: same results as fetching through the bus
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+fn cached(mut core: GbCore) -> GbCore {
+    core.bus.block_cache = Some(Box::default());
+    core
+}
+
+fn stats(core: &GbCore) -> BlockCacheStats { core.bus.block_cache.as_ref().unwrap().stats }
+
+/// LD SP,D000 / LD HL,C000 / loop: INC (HL) / CALL 0150 / INC L / JR loop,
+/// with 0150: LD A,(HL) / SWAP A / LD (0xFF90),A / RET
+fn walker() -> Vec<u8> {
+    let mut prog = vec![0x31, 0x00, 0xD0, 0x21, 0x00, 0xC0, 0x34, 0xCD, 0x50, 0x01, 0x2C, 0x18, 0xF9];
+    prog.resize(0x50, 0x00);
+    prog.extend([0x7E, 0xCB, 0x37, 0xEA, 0x90, 0xFF, 0xC9]);
+    prog
+}
+
+#[test]
+fn runs_rom_code_exactly_like_the_bus_path() {
+    let mut plain = core_with(&walker());
+    let mut fast = cached(core_with(&walker()));
+    for _ in 0..30 {
+        plain.run_frame().unwrap();
+        fast.run_frame().unwrap();
+    }
+    assert_eq!(format!("{:?}", fast.regs), format!("{:?}", plain.regs));
+    assert_eq!(fast.clock.t_cycles, plain.clock.t_cycles);
+    assert_eq!((fast.bus.wram, fast.bus.hram), (plain.bus.wram, plain.bus.hram));
+    let s = stats(&fast);
+    assert_eq!((s.uncached, s.invalidated), (0, 0));
+    assert!(s.hits > 100_000 && s.built <= 4, "{s:?}");
+    assert!(plain.bus.block_cache.is_none());
+}
+
+#[test]
+fn writes_to_cached_wram_code_are_seen() {
+    let mut core = cached(core_with(&[
+        0x31, 0x00, 0xD0,                         // LD SP,D000
+        0x3E, 0x3E, 0xEA, 0x00, 0xC1,             // C100: LD A,1 / RET
+        0x3E, 0x01, 0xEA, 0x01, 0xC1,
+        0x3E, 0xC9, 0xEA, 0x02, 0xC1,
+        0xCD, 0x00, 0xC1, 0xEA, 0x00, 0xC0,       // CALL C100 / LD (C000),A
+        0x3E, 0x02, 0xEA, 0x01, 0xC1,             // patch: LD A,2
+        0xCD, 0x00, 0xC1, 0xEA, 0x01, 0xC0,       // CALL C100 / LD (C001),A
+        0xCD, 0x00, 0xC2,                         // CALL C200
+        0x18, 0xFE,
+    ]));
+    // C200: LD A,5 / LD (C206),A / LD B,0 / RET — patches its own next instruction
+    for (i, b) in [0x3E, 0x05, 0xEA, 0x06, 0xC2, 0x06, 0x00, 0xC9].into_iter().enumerate() {
+        core.bus.write(0xC200 + i as u16, b);
+    }
+    core.run_frame().unwrap();
+    assert_eq!((core.bus.peek(0xC000), core.bus.peek(0xC001), core.regs.b), (1, 2, 5));
+    assert!(stats(&core).invalidated >= 2, "{:?}", stats(&core));
+}
+
+/// 64K MBC1 ROM; banks 2 and 3 hold `LD A,bank / RET` at 0x4000
+fn banked_core(prog: &[u8], bank2: &[u8], bank3: &[u8]) -> GbCore {
+    let mut rom = vec![0x00u8; 64 * 1024];
+    rom[0x147] = 0x01;
+    rom[0x148] = 0x01;
+    rom[0x0100..0x0100 + prog.len()].copy_from_slice(prog);
+    rom[0x8000..0x8000 + bank2.len()].copy_from_slice(bank2);
+    rom[0xC000..0xC000 + bank3.len()].copy_from_slice(bank3);
+    cached(GbCore::new(Cartridge::from_bytes(rom).unwrap()))
+}
+
+#[test]
+fn blocks_are_keyed_by_rom_bank() {
+    let select_and_call = |bank: u8, store: u8| [0x3E, bank, 0xEA, 0x00, 0x20, 0xCD, 0x00, 0x40, 0xEA, store, 0xC0];
+    let prog: Vec<u8> = [0x31, 0x00, 0xD0].into_iter()
+        .chain(select_and_call(2, 0)).chain(select_and_call(3, 1)).chain(select_and_call(2, 2))
+        .chain([0x18, 0xFE]).collect();
+    let mut core = banked_core(&prog, &[0x3E, 0x02, 0xC9], &[0x3E, 0x03, 0xC9]);
+    core.run_frame().unwrap();
+    assert_eq!([core.bus.peek(0xC000), core.bus.peek(0xC001), core.bus.peek(0xC002)], [2, 3, 2]);
+}
+
+#[test]
+fn switching_the_running_bank_ends_the_block() {
+    // Bank 2 at 0x4000: LD A,3 / LD (2000),A / LD B,2 / JR $; bank 3 has LD B,3 there
+    let prog = [0x3E, 0x02, 0xEA, 0x00, 0x20, 0xC3, 0x00, 0x40];
+    let bank3 = [0, 0, 0, 0, 0, 0x06, 0x03, 0x18, 0xFE];
+    let mut core = banked_core(&prog, &[0x3E, 0x03, 0xEA, 0x00, 0x20, 0x06, 0x02, 0x18, 0xFE], &bank3);
+    core.run_frame().unwrap();
+    assert_eq!(core.regs.b, 3);
+}
+
+#[test]
+fn io_writes_leave_hram_code_cached() {
+    // Copy LD A,C1 / LDH (46),A / wait loop / RET to FF80 and call it
+    let mut core = cached(core_with(&[
+        0x31, 0x00, 0xD0, 0x0E, 0x80, 0x21, 0x18, 0x01,
+        0x2A, 0xE2, 0x0C, 0x79, 0xFE, 0x8A, 0x20, 0xF8, // copy loop
+        0xCD, 0x80, 0xFF, 0xEA, 0x00, 0xC0, 0x18, 0xFE,
+        0x3E, 0xC1, 0xE0, 0x46, 0x3E, 0x28, 0x3D, 0x20, 0xFD, 0xC9,
+    ]));
+    core.run_frame().unwrap();
+    assert_eq!(core.regs.pc, 0x0116, "returned from the HRAM routine");
+    assert!(core.bus.oam.iter().all(|&b| b == 0) && !core.bus.dma.active());
+    assert_eq!(stats(&core).invalidated, 0);
+    assert!(stats(&core).built >= 3);
+}
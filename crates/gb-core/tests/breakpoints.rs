@@ -0,0 +1,86 @@
+//! PC breakpoints, register conditions and hit counts
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+/// INC A / JR -3: loops over 0x0100 with A counting up
+const COUNT_LOOP: [u8; 3] = [0x3C, 0x18, 0xFD];
+
+fn expect_break(r: Result<impl std::fmt::Debug, CoreError>) -> BreakHit {
+    match r {
+        Err(CoreError::Break(hit)) => hit,
+        other => panic!("expected a break, got {other:?}"),
+    }
+}
+
+#[test]
+fn stops_before_the_instruction_and_resumes() {
+    let mut core = core_with(&[0x00, 0x3E, 0x42, 0x00]);
+    let id = core.breakpoints.add(0x0101);
+    core.step().unwrap();
+    let t = core.clock.t_cycles;
+    let hit = expect_break(core.step());
+    assert_eq!((hit.id, hit.pc, hit.hits, hit.t_cycles), (id, 0x0101, 1, t));
+    assert_eq!((core.regs.pc, core.regs.a, core.clock.t_cycles), (0x0101, 0x01, t), "LD A,n not executed yet");
+
+    core.step().unwrap();
+    assert_eq!((core.regs.pc, core.regs.a), (0x0103, 0x42));
+}
+
+#[test]
+fn run_frame_returns_the_hit() {
+    let mut core = core_with(&COUNT_LOOP);
+    core.breakpoints.add(0x0101);
+    let hit = expect_break(core.run_frame());
+    assert_eq!(hit.pc, 0x0101);
+    assert_eq!(core.regs.a, 0x02, "INC A ran once");
+    let hit = expect_break(core.run_frame());
+    assert_eq!((hit.hits, core.regs.a), (2, 0x03));
+}
+
+#[test]
+fn conditions_gate_hits() {
+    let mut core = core_with(&COUNT_LOOP);
+    let id = core.breakpoints.add_if(0x0100, vec![BreakCondition::parse("a >= 0x10").unwrap()]);
+    expect_break(core.run_frame());
+    assert_eq!(core.regs.a, 0x10);
+    assert_eq!(core.breakpoints.get(id).unwrap().hits, 1);
+
+    core.breakpoints.get_mut(id).unwrap().conditions.push(BreakCondition::new(BreakReg::A, BreakCmp::Eq, 0x14));
+    expect_break(core.run_frame());
+    assert_eq!(core.regs.a, 0x14);
+}
+
+#[test]
+fn ignore_counts_hits_without_stopping() {
+    let mut core = core_with(&COUNT_LOOP);
+    let id = core.breakpoints.add(0x0100);
+    core.breakpoints.get_mut(id).unwrap().ignore = 4;
+    let hit = expect_break(core.run_frame());
+    assert_eq!((hit.hits, core.regs.a), (5, 0x05));
+}
+
+#[test]
+fn disabled_and_removed_breakpoints_do_not_stop() {
+    let mut core = core_with(&COUNT_LOOP);
+    let a = core.breakpoints.add(0x0100);
+    let b = core.breakpoints.add(0x0101);
+    core.breakpoints.get_mut(a).unwrap().enabled = false;
+    assert!(core.breakpoints.remove(b));
+    assert!(!core.breakpoints.remove(b));
+    core.run_frame().unwrap();
+    assert_eq!(core.breakpoints.get(a).unwrap().hits, 0);
+}
+
+#[test]
+fn condition_parsing() {
+    let c = BreakCondition::parse("HL<=$C000").unwrap();
+    assert_eq!(c, BreakCondition::new(BreakReg::HL, BreakCmp::Le, 0xC000));
+    assert_eq!(c.to_string(), "hl <= 0xC000");
+    assert_eq!(BreakCondition::parse("sp != 65534").unwrap().value, 0xFFFE);
+    assert!(BreakCondition::parse("x == 1").is_none());
+    assert!(BreakCondition::parse("a = 1").is_none());
+    assert!(BreakCondition::parse("a == zz").is_none());
+}
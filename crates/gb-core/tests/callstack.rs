@@ -0,0 +1,74 @@
+//! Shadow call stack (GbCore::call_stack)
+
+use gb_core::*;
+
+/// 0x0100: CALL 0x0200 / JR -2; 0x0200: RST 0x28 / RET; 0x0028: NOP / RET
+fn core() -> GbCore {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x0100..0x0105].copy_from_slice(&[0xCD, 0x00, 0x02, 0x18, 0xFE]);
+    rom[0x0200..0x0202].copy_from_slice(&[0xEF, 0xC9]);
+    rom[0x0028..0x002A].copy_from_slice(&[0x00, 0xC9]);
+    GbCore::new(Cartridge::from_bytes(rom).unwrap())
+}
+
+fn targets(core: &GbCore) -> Vec<(u16, u16, CallKind)> {
+    core.call_stack().iter().map(|f| (f.caller, f.target, f.kind)).collect()
+}
+
+#[test]
+fn calls_and_rsts_push_and_rets_pop() {
+    let mut core = core();
+    let sp = core.regs.sp;
+    core.step().unwrap();
+    assert_eq!(targets(&core), [(0x0100, 0x0200, CallKind::Call)]);
+    assert_eq!(core.call_stack()[0].sp, sp.wrapping_sub(2));
+    core.step().unwrap();
+    assert_eq!(targets(&core), [(0x0100, 0x0200, CallKind::Call), (0x0200, 0x0028, CallKind::Rst)]);
+    assert_eq!(core.shadow_stack.current(), Some(0x0028));
+    core.step().unwrap();
+    core.step().unwrap();
+    assert_eq!(targets(&core), [(0x0100, 0x0200, CallKind::Call)]);
+    core.step().unwrap();
+    assert!(core.call_stack().is_empty());
+    assert_eq!(core.regs.pc, 0x0103);
+}
+
+#[test]
+fn interrupts_are_frames_and_reti_pops_them() {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x0040] = 0xD9;
+    rom[0x0100..0x0102].copy_from_slice(&[0x18, 0xFE]);
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    core.ime = true;
+    core.bus.ie = 0x01;
+    core.bus.if_reg = 0x01;
+    core.step().unwrap();
+    assert_eq!(targets(&core), [(0x0100, 0x0040, CallKind::Interrupt)]);
+    core.step().unwrap();
+    assert!(core.call_stack().is_empty());
+}
+
+#[test]
+fn discarded_return_addresses_do_not_pile_up() {
+    // 0x0100: CALL 0x0200 ; 0x0200: POP HL / JP 0x0100
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x0100..0x0103].copy_from_slice(&[0xCD, 0x00, 0x02]);
+    rom[0x0200..0x0204].copy_from_slice(&[0xE1, 0xC3, 0x00, 0x01]);
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    for _ in 0..300 { core.step().unwrap(); }
+    assert!(core.call_stack().len() <= 1);
+}
+
+#[test]
+fn replay_frames_are_labelled_with_the_routine() {
+    let mut core = core();
+    core.breakpoints.add(0x0029);
+    let mut replay = ReplayCapture::new(1, "CS");
+    while core.step().is_ok() {}
+    replay.capture(&core);
+    assert!(replay.to_json().contains("\"rt\":40,"), "RST 0x28 is the innermost routine");
+
+    let state = core.save_state();
+    core.load_state(&state).unwrap();
+    assert!(core.call_stack().is_empty());
+}
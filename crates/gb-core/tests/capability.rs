@@ -0,0 +1,25 @@
+//! capability_profile: gb-core described as a capability graph
+
+use gb_core::*;
+
+#[test]
+fn profile_describes_the_core_as_a_platform() {
+    let doc = Json::parse(&capability_profile()).unwrap();
+    let get = |path: &[&str]| path.iter().try_fold(&doc, |v, k| v.get(k)).unwrap_or_else(|| panic!("missing {path:?}"));
+    assert_eq!(get(&["capability_version"]).as_str(), Some("0.1"));
+    assert_eq!(get(&["platform_id"]).as_str(), Some(CAPABILITY_PLATFORM_ID));
+    assert_eq!(get(&["host_os", "family"]).as_str(), Some("gb_bare_metal"));
+    assert_eq!(get(&["cpu", "isas"]).as_array().unwrap()[0].as_str(), Some("sm83"));
+    assert_eq!(get(&["memory", "ram_mb"]).as_u64(), Some(0));
+    assert_eq!(get(&["timing", "timer_resolution_us"]).as_u64(), Some(1));
+    assert_eq!(get(&["profiles", "source"]).as_str(), Some(CAPABILITY_SOURCE));
+
+    let features = get(&["cpu", "features"]);
+    let models: Vec<&str> = features.get("models").and_then(Json::as_array).unwrap().iter().filter_map(Json::as_str).collect();
+    assert_eq!(models, [HardwareModel::Dmg, HardwareModel::Mgb, HardwareModel::Sgb, HardwareModel::Cgb].map(HardwareModel::as_str));
+    assert_eq!(features.get("cycles_per_frame").and_then(Json::as_u64), Some(CYCLES_PER_FRAME));
+    assert_eq!(features.get("audio").and_then(|a| a.get("sample_rate_hz")).and_then(Json::as_u64), Some(APU_SAMPLE_RATE as u64));
+    assert_eq!(features.get("save_states").and_then(|s| s.get("format")).and_then(Json::as_str), Some("mrom.sav.v1"));
+    assert_eq!(get(&["gpu", "features", "width"]).as_u64(), Some(LCD_WIDTH as u64));
+    assert!(get(&["io", "inputs"]).as_array().unwrap().iter().any(|i| i.as_str() == Some("gb_joypad")));
+}
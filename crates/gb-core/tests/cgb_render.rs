@@ -0,0 +1,179 @@
+//! CGB mode: BG map attributes from VRAM bank 1 and colour output
+
+use gb_core::*;
+
+/// Bank 0 tile 1 draws a diagonal of colour 1 (row r, pixel r), tile 2 is
+/// all colour 2; bank 1 tile 1 is all colour 3. The BG map is all tile 1,
+/// and column c's attributes pick palette c & 7 plus, in columns 1-3 and 5,
+/// X flip, Y flip, bank 1 and BG-to-OAM priority
+fn vram() -> [[u8; 0x2000]; 2] {
+    let mut vram = [[0u8; 0x2000]; 2];
+    for r in 0..8 {
+        vram[0][16 + r * 2] = 0x80 >> r;
+        vram[0][32 + r * 2 + 1] = 0xFF;
+        vram[1][16 + r * 2..16 + r * 2 + 2].copy_from_slice(&[0xFF, 0xFF]);
+    }
+    vram[0][0x1800..0x1C00].fill(1);
+    for i in 0..0x400 {
+        let col = i & 31;
+        let flags = match col { 1 => 0x20, 2 => 0x40, 3 => 0x08, 5 => 0x80, _ => 0 };
+        vram[1][0x1800 + i] = flags | (col & 7) as u8;
+    }
+    vram
+}
+
+fn frame(fifo: bool, vram: &[[u8; 0x2000]; 2], oam: &[u8; 0xA0]) -> Ppu { frame_lcdc(0x93, fifo, vram, oam) }
+
+fn frame_lcdc(lcdc: u8, fifo: bool, vram: &[[u8; 0x2000]; 2], oam: &[u8; 0xA0]) -> Ppu {
+    let mut ppu = Ppu::new();
+    (ppu.cgb, ppu.lcdc) = (true, lcdc);
+    if fifo { ppu.fifo = Some(Box::default()); }
+    while !ppu.frame_ready { ppu.step_banks(4, [&vram[0], &vram[1]], oam); }
+    ppu
+}
+
+#[test]
+fn attributes_pick_palette_flip_and_bank() {
+    let vram = vram();
+    let ppu = frame(false, &vram, &[0; 0xA0]);
+    let row = 2 * LCD_WIDTH;
+    let px = |x: usize| (ppu.framebuffer[row + x], ppu.pixel_source[row + x]);
+    assert_eq!((px(2), px(3)), ((1, 0), (0, 0)), "colour numbers are not mapped through BGP");
+    assert_eq!((px(8 + 5), px(8 + 2)), ((1, 1), (0, 1)), "X flip");
+    assert_eq!((px(16 + 5), px(16 + 2)), ((1, 2), (0, 2)), "Y flip");
+    assert!((24..32).all(|x| px(x) == (3, 3)), "tile data from bank 1");
+    assert_eq!(px(8 * 9 + 2), (1, 1), "palette = column & 7");
+
+    // The pixel FIFO fetches the same attributes
+    let fifo = frame(true, &vram, &[0; 0xA0]);
+    assert_eq!(fifo.framebuffer, ppu.framebuffer);
+    assert_eq!(fifo.pixel_source, ppu.pixel_source);
+
+    // Outside CGB mode bank 1 is ignored
+    let mut dmg = Ppu::new();
+    dmg.pal_bg = 0xE4;
+    while !dmg.frame_ready { dmg.step_banks(4, [&vram[0], &vram[1]], &[0; 0xA0]); }
+    assert_eq!(dmg.framebuffer[row + 8 + 2], 1);
+    assert!(dmg.pixel_source.iter().all(|&s| s == 0));
+}
+
+#[test]
+fn bg_priority_attribute_covers_sprites() {
+    let vram = vram();
+    let mut oam = [0u8; 0xA0];
+    // Colour-2 sprites over columns 5 (BG priority) and 0, lines 0-7
+    oam[..8].copy_from_slice(&[16, 48, 2, 0x00, 16, 8, 2, 0x01]);
+    for fifo in [false, true] {
+        let ppu = frame(fifo, &vram, &oam);
+        let row = 2 * LCD_WIDTH;
+        let px = |x: usize| (ppu.framebuffer[row + x], ppu.pixel_source[row + x]);
+        assert_eq!(px(40 + 2), (1, 5), "BG colour 1-3 with priority stays on top");
+        assert_eq!(px(40 + 3), (2, CGB_OBJ_SOURCE), "BG colour 0 does not");
+        assert_eq!(px(2), (2, CGB_OBJ_SOURCE + 1), "no priority: the sprite wins, OBJ palette 1");
+    }
+}
+
+#[test]
+fn lcdc_bit_0_is_the_cgb_master_priority() {
+    let vram = vram();
+    let mut oam = [0u8; 0xA0];
+    // Colour-2 sprites over column 5 (BG priority attribute) and, behind
+    // the BG by OAM bit 7, column 0
+    oam[..8].copy_from_slice(&[16, 48, 2, 0x00, 16, 8, 2, 0x80]);
+    for fifo in [false, true] {
+        let on = frame_lcdc(0x93, fifo, &vram, &oam);
+        let off = frame_lcdc(0x92, fifo, &vram, &oam);
+        let row = 2 * LCD_WIDTH;
+        let px = |ppu: &Ppu, x: usize| (ppu.framebuffer[row + x], ppu.pixel_source[row + x]);
+        assert_eq!((px(&on, 40 + 2), px(&on, 2)), ((1, 5), (1, 0)));
+        assert_eq!((px(&off, 40 + 2), px(&off, 2)), ((2, CGB_OBJ_SOURCE), (2, CGB_OBJ_SOURCE)), "bit 0 clear: sprites always on top");
+        assert_eq!(px(&off, 48 + 2), (1, 6), "the BG is still drawn");
+        assert_eq!(off.framebuffer[LCD_WIDTH * 20..], on.framebuffer[LCD_WIDTH * 20..]);
+    }
+
+    // On a DMG bit 0 blanks the BG
+    let mut dmg = Ppu::new();
+    (dmg.lcdc, dmg.pal_bg) = (0x92, 0xE4);
+    while !dmg.frame_ready { dmg.step_banks(4, [&vram[0], &vram[1]], &[0; 0xA0]); }
+    assert!(dmg.framebuffer.iter().all(|&c| c == 0));
+}
+
+#[test]
+fn dmg_lcdc_bit_0_blanks_the_window_too() {
+    // Window map at 9C00 full of a solid colour-3 tile, BG map left blank
+    let mut vram = [[0u8; 0x2000]; 2];
+    vram[0][0x10..0x20].fill(0xFF);
+    vram[0][0x1C00..0x2000].fill(1);
+    for fifo in [false, true] {
+        let frame = |lcdc: u8| {
+            let mut ppu = Ppu::new();
+            (ppu.lcdc, ppu.pal_bg, ppu.wx) = (lcdc, 0xE4, 7);
+            if fifo { ppu.fifo = Some(Box::default()); }
+            while !ppu.frame_ready { ppu.step_banks(4, [&vram[0], &vram[1]], &[0; 0xA0]); }
+            ppu
+        };
+        assert!(frame(0xF1).framebuffer.iter().all(|&c| c == 3), "fifo={fifo}: window shown");
+        assert!(frame(0xF0).framebuffer.iter().all(|&c| c == 0), "fifo={fifo}: bit 0 clear blanks BG and window");
+    }
+}
+
+#[test]
+fn sprites_use_cgb_palette_bank_and_oam_order() {
+    let mut vram = vram();
+    // Bank 1 tile 2: all colour 1
+    for r in 0..8 { vram[1][32 + r * 2] = 0xFF; }
+    let mut oam = [0u8; 0xA0];
+    // OAM 0: X 12-19, bank 1, palette 3, DMG palette bit set (ignored);
+    // OAM 1: X 8-15, bank 0, palette 6
+    oam[..8].copy_from_slice(&[16, 20, 2, 0x18 | 3, 16, 16, 2, 6]);
+    for fifo in [false, true] {
+        let ppu = frame(fifo, &vram, &oam);
+        let row = 2 * LCD_WIDTH;
+        let px = |x: usize| (ppu.framebuffer[row + x], ppu.pixel_source[row + x]);
+        assert_eq!(px(9), (2, CGB_OBJ_SOURCE + 6), "bank 0 tile, palette 6");
+        assert!((12..20).all(|x| px(x) == (1, CGB_OBJ_SOURCE + 3)), "bank 1 tile, palette 3; OAM 0 wins over the lower X");
+    }
+
+    // The same OAM on a DMG: bank 0, OBP1, lower X first
+    let mut dmg = Ppu::new();
+    (dmg.lcdc, dmg.pal_obj0, dmg.pal_obj1) = (0x93, 0xE4, 0xE4);
+    while !dmg.frame_ready { dmg.step_banks(4, [&vram[0], &vram[1]], &oam); }
+    let row = 2 * LCD_WIDTH;
+    assert_eq!((dmg.framebuffer[row + 13], dmg.pixel_source[row + 13]), (2, 1));
+    assert_eq!((dmg.framebuffer[row + 17], dmg.pixel_source[row + 17]), (2, 2));
+}
+
+#[test]
+fn cgb_cartridges_on_a_cgb_render_in_colour() {
+    let core_for = |model, flag| {
+        let cart = RomBuilder::new().cgb(flag).code(&[0x18, 0xFE]).cartridge().unwrap();
+        GbCore::with_config(cart, CoreConfig { model, ..Default::default() })
+    };
+    assert!(core_for(HardwareModel::Cgb, 0x80).bus.ppu.cgb);
+    assert!(core_for(HardwareModel::Cgb, 0xC0).bus.ppu.cgb);
+    assert!(!core_for(HardwareModel::Cgb, 0x00).bus.ppu.cgb);
+    assert!(!core_for(HardwareModel::Dmg, 0x80).bus.ppu.cgb);
+
+    let mut core = core_for(HardwareModel::Cgb, 0x80);
+    // BG palette 0 colour 0 red, OBJ palette 0 colour 2 blue (auto-increment)
+    core.bus.write(0xFF68, 0x80);
+    core.bus.write(0xFF69, 0x1F);
+    core.bus.write(0xFF69, 0x00);
+    core.bus.write(0xFF6A, 0x84);
+    core.bus.write(0xFF6B, 0x00);
+    core.bus.write(0xFF6B, 0x7C);
+    core.bus.vram[0][32..48].copy_from_slice(&[0x00, 0xFF].repeat(8));
+    core.bus.oam[..4].copy_from_slice(&[16, 8, 2, 0]);
+    core.bus.write(0xFF40, 0x93);
+    core.run_frame().unwrap();
+    core.run_frame().unwrap();
+    let rgb = core.framebuffer_rgb();
+    assert_eq!(rgb[..3], [0, 0, 248], "sprite pixel, OBJ palette 0 colour 2");
+    assert_eq!(rgb[8 * 3..9 * 3], [248, 0, 0], "BG palette 0 colour 0");
+
+    // The mode follows the model a state was saved with
+    let state = core.save_state();
+    let mut dmg = core_for(HardwareModel::Dmg, 0x80);
+    dmg.load_state(&state).unwrap();
+    assert!(dmg.bus.ppu.cgb);
+}
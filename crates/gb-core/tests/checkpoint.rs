@@ -0,0 +1,67 @@
+//! Verification checkpoints and their equivalence-grade report
+
+use gb_core::*;
+
+const CHECKPOINTS: &str = "
+# boot and video
+boot:   pc 0150 within 2 -> L0_BOOT
+video:  ly cycles within 3 -> L1_STABLE
+flag:   $C0A0 == 3 within 5 -> L2_INTERACTIVE
+passed: serial \"Passed\" within 5 -> L3_GAMEPLAY_EQ
+never:  pc 7000 within 4 -> L4_RENDER_EQ
+";
+
+fn core() -> GbCore {
+    let code = Code::new(CODE_START).ld_a(0x91).st_a(0xFF40).serial_print("Passed\n").ld_a(3).st_a(0xC0A0).spin();
+    GbCore::new(Cartridge::from_bytes(RomBuilder::new().title("CHECKS").code(code.bytes()).build()).unwrap())
+}
+
+#[test]
+fn checkpoints_parse_from_lines() {
+    let cps = parse_checkpoints(CHECKPOINTS).unwrap();
+    assert_eq!(cps.len(), 5);
+    assert_eq!(cps[0], Checkpoint { id: "boot".into(), condition: CheckpointCondition::Pc(0x150), within_frames: 2, level: 0 });
+    assert_eq!(cps[2].condition, CheckpointCondition::MemEquals { addr: 0xC0A0, value: 3 });
+    assert_eq!((cps[3].condition.kind(), cps[3].level_name()), ("serial", "L3_GAMEPLAY_EQ"));
+    assert_eq!(Checkpoint::parse("x: ly cycles within 1").unwrap().level_name(), "L0_BOOT");
+
+    assert!(parse_checkpoints("a: pc 150 within 1\na: pc 151 within 1").unwrap_err().contains("line 2: duplicate id"));
+    assert!(Checkpoint::parse("a: pc 150").unwrap_err().contains("within"));
+    assert!(Checkpoint::parse("a: pc 150 within 1 -> L9_PERFECT").unwrap_err().contains("unknown equivalence level"));
+    assert!(Checkpoint::parse("a: serial Passed within 1").unwrap_err().contains("quoted"));
+}
+
+#[test]
+fn passes_pause_on_the_exact_state_and_failures_cap_the_level() {
+    let cps = parse_checkpoints(CHECKPOINTS).unwrap();
+    let report = run_checkpoints(&mut core(), &cps).unwrap();
+    let ids: Vec<(&str, bool)> = report.results.iter().map(|r| (r.checkpoint.id.as_str(), r.passed)).collect();
+    assert_eq!(ids, [("boot", true), ("video", true), ("flag", true), ("passed", true), ("never", false)]);
+    assert_eq!(report.frames_run, 4);
+    assert_eq!(report.achieved_level(), Some("L3_GAMEPLAY_EQ"));
+
+    // The exported state is the machine at the checkpoint, before the
+    // instruction at PC has run
+    let boot = &report.results[0];
+    assert_eq!((boot.frame, boot.detail.as_str()), (Some(0), "PC reached 0150 at frame 0"));
+    let mut resumed = core();
+    resumed.load_state(boot.state.as_ref().unwrap()).unwrap();
+    assert_eq!(resumed.regs.pc, 0x150);
+    let mut direct = core();
+    assert!(direct.run_until(|c| c.regs.pc == 0x150, CYCLES_PER_FRAME).unwrap());
+    assert_eq!(Some(direct.state_hash()), boot.state_hash);
+    assert!(report.results[3].t_cycles > report.results[0].t_cycles);
+    assert_eq!(report.results[4].detail, "not reached within 4 frames");
+
+    let doc = Json::parse(&report.to_json()).unwrap();
+    assert_eq!(doc.get("version").and_then(Json::as_str), Some(CHECKPOINTS_VERSION));
+    assert_eq!(doc.get("achieved_level").and_then(Json::as_str), Some("L3_GAMEPLAY_EQ"));
+    let results = doc.get("results").and_then(Json::as_array).unwrap();
+    assert_eq!(results[1].get("kind").and_then(Json::as_str), Some("ly_cycles"));
+    assert_eq!(results[3].get("state").and_then(Json::as_str), Some("passed.mrom.sav"));
+    assert!(results[4].get("state_hash").is_some_and(|h| *h == Json::Null));
+
+    // A lower level failing caps the report below it, however much passed above
+    let lower = parse_checkpoints("boot: pc 0150 within 2\nmiss: pc 7000 within 1 -> L1_STABLE\nflag: $C0A0 == 3 within 5 -> L2_INTERACTIVE").unwrap();
+    assert_eq!(run_checkpoints(&mut core(), &lower).unwrap().achieved_level(), Some("L0_BOOT"));
+}
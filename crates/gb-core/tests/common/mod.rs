@@ -0,0 +1,16 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use gb_core::{Cartridge, GbCore};
+
+/// 32 KiB ROM-only image with `prog` at the 0x0100 entry point
+pub fn rom_with(prog: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x0100..0x0100 + prog.len()].copy_from_slice(prog);
+    rom
+}
+
+/// A DMG core that starts executing `prog` at 0x0100
+pub fn core_with(prog: &[u8]) -> GbCore {
+    GbCore::new(Cartridge::from_bytes(rom_with(prog)).unwrap())
+}
@@ -0,0 +1,63 @@
+//! Serial-out and RAM ring-buffer console capture
+
+use gb_core::{Code, GbCore, RamConsole, RomBuilder, CODE_START};
+
+mod common;
+use common::core_with;
+
+#[test]
+fn serial_bytes_become_console_text() {
+    let mut prog = vec![];
+    for &c in b"OK\n" {
+        // LD A,c / LDH (01),A / LD A,81 / LDH (02),A
+        prog.extend_from_slice(&[0x3E, c, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02]);
+    }
+    prog.extend_from_slice(&[0x18, 0xFE]); // JR -2
+    let mut core = core_with(&prog);
+    core.run_frame().unwrap();
+
+    assert_eq!(core.console_text(), "OK\n");
+    assert_eq!(core.bus.read(0xFF01), 0xFF);
+    assert_eq!(core.bus.read(0xFF02) & 0x80, 0);
+    assert_ne!(core.bus.if_reg & 0x08, 0);
+    assert_eq!(core.take_console_text(), "OK\n");
+    assert_eq!(core.console_text(), "");
+}
+
+#[test]
+fn ram_console_is_polled_per_frame() {
+    let mut core = core_with(&[0x18, 0xFE]);
+    let rc = RamConsole::parse("c000:4:c010").unwrap();
+    assert_eq!(rc, RamConsole { base: 0xC000, len: 4, head: 0xC010 });
+    core.set_ram_console(Some(rc));
+
+    for (i, &c) in b"abc".iter().enumerate() { core.bus.write(0xC000 + i as u16, c); }
+    core.bus.write(0xC010, 3);
+    core.run_frame().unwrap();
+    assert_eq!(core.console_text(), "abc");
+
+    // Wraps around the ring
+    core.bus.write(0xC003, b'd');
+    core.bus.write(0xC000, b'e');
+    core.bus.write(0xC010, 1);
+    core.run_frame().unwrap();
+    assert_eq!(core.console_text(), "abcde");
+}
+
+#[test]
+fn serial_output_holds_the_serial_port_alone() {
+    // blargg-style report over serial, plus a byte in a RAM console
+    let code = Code::new(CODE_START).serial_print("cpu_instrs\n\n01:ok\n\nPassed all tests\n")
+        .ld_a(b'x').st_a(0xC000).ld_a(1).st_a(0xC010).serial_print("\u{e9}").spin();
+    let mut core = GbCore::new(RomBuilder::new().code(code.bytes()).cartridge().unwrap());
+    core.set_ram_console(RamConsole::parse("c000:4:c010"));
+    core.run_frame().unwrap();
+
+    assert!(core.serial_output().contains("Passed all tests"));
+    assert!(core.serial_output().ends_with("tests\n\u{fffd}\u{fffd}"), "non-ASCII bytes are replaced");
+    assert!(!core.serial_output().contains('x'));
+    assert!(core.console_text().contains('x'), "the console log still has both");
+    assert_eq!(core.take_serial_output().lines().next(), Some("cpu_instrs"));
+    assert_eq!(core.serial_output(), "");
+    assert!(core.console_text().starts_with("cpu_instrs"), "clearing the transcript leaves the log");
+}
@@ -0,0 +1,55 @@
+//! Structured CoreError variants hosts can match on
+
+use gb_core::*;
+use std::error::Error;
+
+#[test]
+fn bad_headers_name_the_problem() {
+    match Cartridge::from_bytes(vec![0; 0x100]) {
+        Err(CoreError::InvalidRom { offset: None, reason }) => assert!(reason.contains("256 bytes"), "{reason}"),
+        other => panic!("{other:?}"),
+    }
+}
+
+#[test]
+fn strict_header_checks_are_opt_in() {
+    let mut rom = RomBuilder::new().code(&[0x18, 0xFE]).build();
+    rom[0x148] = 0x20;
+    let cart = Cartridge::from_bytes(rom.clone()).expect("loaded as is");
+    assert_eq!(cart.rom_size_kb, 32);
+    assert!(matches!(cart.validate(), Err(CoreError::InvalidRom { offset: Some(0x148), .. })));
+    rom[0x148] = 0x00;
+    rom[0x147] = 0xFC; // Pocket Camera
+    let cart = Cartridge::from_bytes(rom.clone()).expect("runs as ROM-only");
+    assert_eq!(cart.kind, CartridgeKind::Unknown(0xFC));
+    let err = cart.validate().unwrap_err();
+    assert!(matches!(err, CoreError::UnsupportedMapper(0xFC)));
+    assert_eq!(err.to_string(), "UnsupportedMapper: cartridge type 0xfc");
+    rom[0x147] = 0x09; // ROM+RAM+BATTERY, no mapper chip
+    Cartridge::from_bytes(rom).unwrap().validate().unwrap();
+}
+
+#[test]
+fn other_state_versions_are_rejected_by_version() {
+    let rom = RomBuilder::new().code(&[0x18, 0xFE]).build();
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    let state = String::from_utf8(core.save_state()).unwrap();
+    assert!(state.starts_with(&format!("{{\"version\":\"{SAVE_STATE_VERSION}\"")));
+    match core.load_state(state.replace(SAVE_STATE_VERSION, "mrom.sav.v9").as_bytes()) {
+        Err(CoreError::StateVersionMismatch { found, expected }) => assert_eq!((found.as_str(), expected), ("mrom.sav.v9", SAVE_STATE_VERSION)),
+        other => panic!("{other:?}"),
+    }
+    core.load_state(state.as_bytes()).unwrap();
+}
+
+#[test]
+fn io_failures_chain_their_source() {
+    let rom = RomBuilder::new().code(&[0x18, 0xFE]).build();
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    let err = core.load_state_from_file(std::path::Path::new("/nonexistent/state.mrom.sav")).unwrap_err();
+    assert!(matches!(err, CoreError::IoError(_)));
+    let source = err.source().and_then(|s| s.downcast_ref::<std::io::Error>()).expect("io source");
+    assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+    assert_eq!(err.to_string(), "IoError", "the io::Error is the source, not repeated");
+    assert!(CoreError::Interrupted.source().is_none());
+}
@@ -0,0 +1,53 @@
+//! Opcode / IO coverage vectors and greedy corpus selection
+
+use gb_core::*;
+
+mod common;
+use common::rom_with;
+
+fn cov(ops: &[u8], io: &[u8]) -> CoverageVector {
+    let mut c = CoverageVector::default();
+    for &o in ops { c.record_op(o, None); }
+    for &r in io { c.record_io(r); }
+    c
+}
+
+#[test]
+fn run_records_opcodes_and_io_writes() {
+    // LD A,0x91 / LDH (40),A / SWAP A / JR -2
+    let c = coverage_of(rom_with(&[0x3E, 0x91, 0xE0, 0x40, 0xCB, 0x37, 0x18, 0xFE]), 1).unwrap();
+    let bit = |bits: &[u64], i: u8| bits[i as usize / 64] & (1 << (i % 64)) != 0;
+    assert!([0x3E, 0xE0, 0xCB, 0x18].iter().all(|&o| bit(&c.opcodes, o)));
+    assert!(!bit(&c.opcodes, 0xC3));
+    assert!(bit(&c.cb_opcodes, 0x37));
+    assert!(bit(&c.io_writes, 0x40));
+    assert_eq!(c.count(), 4 + 1 + 1);
+}
+
+#[test]
+fn greedy_selection_drops_redundant_roms() {
+    let candidates = [
+        cov(&[1, 2], &[]),
+        cov(&[1, 2, 3, 4], &[0x40]),  // superset of 0
+        cov(&[5], &[0x26]),
+        cov(&[1, 5], &[0x40]),        // covered by 1 + 2
+    ];
+    let sel = select_corpus(&candidates, None);
+    let picked: Vec<usize> = sel.picks.iter().map(|p| p.index).collect();
+    assert_eq!(picked, vec![1, 2]);
+    assert_eq!(sel.picks[0].gain, 5);
+    assert_eq!(sel.picks[1], Pick { index: 2, gain: 2, covered: 7 });
+    assert_eq!(sel.covered, sel.total);
+    assert_eq!(sel.retained(), 1.0);
+
+    let capped = select_corpus(&candidates, Some(1));
+    assert_eq!(capped.picks.len(), 1);
+    assert!(capped.retained() < 1.0);
+}
+
+#[test]
+fn empty_corpus_selects_nothing() {
+    let sel = select_corpus(&[CoverageVector::default()], None);
+    assert!(sel.picks.is_empty());
+    assert_eq!(sel.retained(), 1.0);
+}
@@ -0,0 +1,63 @@
+//! Structured single-step events (GbCore::debug_step)
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+#[test]
+fn instruction_and_halt_events() {
+    // LD A,0x42 / HALT with nothing enabled
+    let mut core = core_with(&[0x3E, 0x42, 0x76]);
+    assert_eq!(core.debug_step().unwrap(), DebugEvent::InstructionExecuted { pc: 0x0100, opcode: 0x3E, cycles: 8 });
+    assert_eq!(core.debug_step().unwrap(), DebugEvent::InstructionExecuted { pc: 0x0102, opcode: 0x76, cycles: 4 });
+    assert_eq!(core.debug_step().unwrap(), DebugEvent::Halted { cycles: 4 });
+}
+
+#[test]
+fn interrupt_dispatch_reports_vector_and_return_address() {
+    let mut core = core_with(&[0x00]);
+    core.ime = true;
+    core.bus.ie = 0x04;
+    core.bus.if_reg = 0x04;
+    let ev = core.debug_step().unwrap();
+    assert_eq!(ev, DebugEvent::InterruptDispatched { vector: 0x0050, return_pc: 0x0100, cycles: 20 });
+    assert_eq!(ev.to_json(), r#"{"event":"interrupt_dispatched","vector":80,"return_pc":256,"cycles":20}"#);
+}
+
+#[test]
+fn breakpoints_and_watchpoints_are_events() {
+    // NOP / LD (0xC000),A
+    let mut core = core_with(&[0x00, 0xEA, 0x00, 0xC0]);
+    core.breakpoints.add(0x0101);
+    core.bus.watchpoints.add_write(0xC000);
+    core.debug_step().unwrap();
+    match core.debug_step().unwrap() {
+        DebugEvent::BreakpointHit(hit) => assert_eq!((hit.pc, core.regs.pc), (0x0101, 0x0101)),
+        other => panic!("expected a breakpoint, got {other:?}"),
+    }
+    match core.debug_step().unwrap() {
+        DebugEvent::WatchpointHit(hit) => assert_eq!((hit.pc, hit.addr, core.regs.pc), (0x0101, 0xC000, 0x0104)),
+        other => panic!("expected a watchpoint, got {other:?}"),
+    }
+}
+
+#[test]
+fn frame_completed_follows_the_step_that_reached_vblank() {
+    // JR -2
+    let mut core = core_with(&[0x18, 0xFE]);
+    let mut steps = 0;
+    loop {
+        let t = core.clock.t_cycles;
+        match core.debug_step().unwrap() {
+            DebugEvent::FrameCompleted { frame } => {
+                assert_eq!((frame, core.clock.t_cycles), (core.clock.frame_count(), t), "reported without advancing");
+                break;
+            }
+            ev => assert!(matches!(ev, DebugEvent::InstructionExecuted { pc: 0x0100, cycles: 12, .. }), "{ev:?}"),
+        }
+        steps += 1;
+        assert!(steps < 100_000, "no VBlank");
+    }
+    assert!(matches!(core.debug_step().unwrap(), DebugEvent::InstructionExecuted { .. }));
+}
@@ -0,0 +1,59 @@
+//! APU frame sequencer clocked from DIV (DIV-APU), including DIV resets
+
+use gb_core::*;
+
+fn bus() -> Bus { Bus::new(RomBuilder::new().cartridge().unwrap()) }
+
+/// Step until DIV bit 4 (divider bit 12) reads `set`
+fn step_until_bit4(bus: &mut Bus, set: bool) {
+    while (bus.timer.div_counter() >> 12 & 1 == 1) != set { bus.step_subsystems(4); }
+}
+
+#[test]
+fn sequencer_runs_at_512hz() {
+    let mut b = bus();
+    for _ in 0..8 * 8192 / 4 { b.step_subsystems(4); }
+    assert_eq!(b.apu.fs_counter, 0, "eight steps in 8 * 8192 cycles wraps the counter");
+    for _ in 0..8192 / 4 { b.step_subsystems(4); }
+    assert_eq!(b.apu.fs_counter, 1);
+}
+
+#[test]
+fn double_speed_watches_bit5() {
+    // Double speed is CGB-only; the CGB model also starts DIV from zero
+    let config = CoreConfig { model: HardwareModel::Cgb, ..Default::default() };
+    let mut b = Bus::with_config(RomBuilder::new().cartridge().unwrap(), &config);
+    b.double_speed = true;
+    // DIV runs at CPU speed, so 8192 CPU cycles are only half a sequencer period
+    for _ in 0..8192 / 4 { b.step_subsystems(4); }
+    assert_eq!(b.apu.fs_counter, 0);
+    for _ in 0..8192 / 4 { b.step_subsystems(4); }
+    assert_eq!(b.apu.fs_counter, 1);
+}
+
+#[test]
+fn div_write_clocks_early_only_when_bit_is_set() {
+    let mut b = bus();
+    step_until_bit4(&mut b, true);
+    let fs = b.apu.fs_counter;
+    b.write(0xFF04, 0x12);
+    assert_eq!(b.timer.div_counter(), 0);
+    assert_eq!(b.apu.fs_counter, (fs + 1) & 7);
+
+    b.write(0xFF04, 0);
+    assert_eq!(b.apu.fs_counter, (fs + 1) & 7, "bit already clear: no extra clock");
+}
+
+#[test]
+fn div_write_shortens_a_length_counter() {
+    let mut b = bus();
+    // Park the sequencer so the next step is a length clock (even step)
+    while b.apu.fs_counter != 1 { b.step_subsystems(4); }
+    step_until_bit4(&mut b, true);
+    b.write(0xFF16, 0x3F); // NR21: length 1
+    b.write(0xFF17, 0xF0);
+    b.write(0xFF19, 0xC0); // NR24: trigger + length enable
+    assert!(b.apu.sq2.enabled);
+    b.write(0xFF04, 0);
+    assert!(!b.apu.sq2.enabled, "DIV reset clocked the length counter to zero");
+}
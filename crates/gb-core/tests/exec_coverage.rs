@@ -0,0 +1,80 @@
+//! Executed-code coverage map
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+fn with_coverage(mut core: GbCore) -> GbCore {
+    core.exec_coverage = Some(Box::new(ExecCoverage::for_bus(&core.bus)));
+    core
+}
+
+/// 64K MBC1 ROM: select bank 2, jump into it, spin on JR -2 there
+fn banked_core() -> GbCore {
+    let mut rom = vec![0x00u8; 64 * 1024];
+    rom[0x147] = 0x01;
+    rom[0x148] = 0x01;
+    // LD A,2 / LD (0x2000),A / JP 0x4000
+    rom[0x0100..0x0108].copy_from_slice(&[0x3E, 0x02, 0xEA, 0x00, 0x20, 0xC3, 0x00, 0x40]);
+    rom[0x8000..0x8002].copy_from_slice(&[0x18, 0xFE]);
+    with_coverage(GbCore::new(Cartridge::from_bytes(rom).unwrap()))
+}
+
+#[test]
+fn off_by_default() {
+    let mut core = core_with(&[]);
+    core.step().unwrap();
+    assert!(core.exec_coverage.is_none());
+}
+
+#[test]
+fn marks_opcodes_and_operands() {
+    // LD A,0x07 / CB SWAP A / NOP
+    let mut core = with_coverage(core_with(&[0x3E, 0x07, 0xCB, 0x37, 0x00]));
+    for _ in 0..3 { core.step().unwrap(); }
+    let cov = core.exec_coverage.as_ref().unwrap();
+    assert_eq!(cov.bytes(), 5);
+    for addr in 0x0100..0x0105 { assert!(cov.executed(&core.bus, addr), "{addr:04X}"); }
+    assert!(!cov.executed(&core.bus, 0x0105));
+    assert_eq!(cov.ranges(), [ExecRange { region: ExecRegion::Rom(0), start: 0x0100, end: 0x0104 }]);
+}
+
+#[test]
+fn banks_are_told_apart() {
+    let mut core = banked_core();
+    for _ in 0..5 { core.step().unwrap(); }
+    let cov = core.exec_coverage.as_ref().unwrap();
+    assert_eq!(cov.ranges(), [
+        ExecRange { region: ExecRegion::Rom(0), start: 0x0100, end: 0x0107 },
+        ExecRange { region: ExecRegion::Rom(2), start: 0x4000, end: 0x4001 },
+    ]);
+    assert!(cov.executed(&core.bus, 0x4000));
+    core.bus.write(0x2000, 1);
+    assert!(!core.exec_coverage.as_ref().unwrap().executed(&core.bus, 0x4000), "bank 1 never ran");
+}
+
+#[test]
+fn ram_code_by_address() {
+    // JP 0xC000; WRAM holds LD A,1 / NOP
+    let mut core = with_coverage(core_with(&[0xC3, 0x00, 0xC0]));
+    core.bus.write(0xC000, 0x3E);
+    core.bus.write(0xC001, 0x01);
+    core.bus.write(0xC002, 0x00);
+    for _ in 0..3 { core.step().unwrap(); }
+    let cov = core.exec_coverage.as_ref().unwrap();
+    assert_eq!(cov.ranges()[1], ExecRange { region: ExecRegion::Ram, start: 0xC000, end: 0xC002 });
+    let json = cov.to_json();
+    assert!(json.contains("{\"region\":\"ram\",\"bank\":null,\"start\":49152,\"end\":49154}"), "{json}");
+    assert!(json.starts_with("{\"bytes\":6,"), "{json}");
+}
+
+#[test]
+fn stuck_loop_stops_reaching_new_code() {
+    let mut core = banked_core();
+    for _ in 0..10 { core.run_frame().unwrap(); }
+    let cov = core.exec_coverage.as_ref().unwrap();
+    assert_eq!(cov.last_new_frame(), 0);
+    assert_eq!(cov.bytes(), 10);
+    assert!(cov.rom_fraction() > 0.0 && cov.rom_fraction() < 0.001);
+}
@@ -0,0 +1,86 @@
+//! Fast halt: jumping to the next event matches 4-cycle halted steps
+
+use gb_core::*;
+
+/// Idles in HALT with VBlank, LYC (line 100) and timer interrupts counted
+/// in HRAM, while square 1 plays a length-limited note
+const IDLE: &str = "
+        ld a, $80
+        ldh [$26], a
+        ld a, $77
+        ldh [$24], a
+        ld a, $ff
+        ldh [$25], a
+        ld a, $f0
+        ldh [$12], a
+        ld a, $c7
+        ldh [$14], a
+        ld a, $40
+        ldh [$41], a
+        ld a, 100
+        ldh [$45], a
+        ld a, $07
+        ldh [$07], a
+        ld a, $07
+        ldh [$ff], a
+        ei
+    idle:
+        halt
+        jr idle
+        org $40
+        ldh a, [$80]
+        inc a
+        ldh [$80], a
+        reti
+        org $48
+        ldh a, [$81]
+        inc a
+        ldh [$81], a
+        reti
+        org $50
+        ldh a, [$82]
+        inc a
+        ldh [$82], a
+        reti
+";
+
+fn core(fast_halt: bool) -> GbCore {
+    let mut core = GbCore::new(RomBuilder::new().asm(IDLE).unwrap().cartridge().unwrap());
+    core.fast_halt = fast_halt;
+    core
+}
+
+#[test]
+fn halted_jumps_match_four_cycle_steps() {
+    let (mut plain, mut fast) = (core(false), core(true));
+    for frame in 0..30 {
+        plain.run_frame().unwrap();
+        fast.run_frame().unwrap();
+        assert_eq!(fast.clock.t_cycles, plain.clock.t_cycles, "frame {frame}");
+        assert_eq!(fast.bus.apu.drain_samples(), plain.bus.apu.drain_samples(), "frame {frame}");
+    }
+    assert_eq!(format!("{:?}", fast.regs), format!("{:?}", plain.regs));
+    assert_eq!(fast.bus.hram, plain.bus.hram);
+    assert_eq!((fast.bus.ppu.ly, fast.bus.ppu.dot, fast.bus.timer.tima), (plain.bus.ppu.ly, plain.bus.ppu.dot, plain.bus.timer.tima));
+    assert_eq!(fast.framebuffer_rgb(), plain.framebuffer_rgb());
+    let counts = [fast.bus.hram[0], fast.bus.hram[1], fast.bus.hram[2]];
+    assert!(counts[..2].iter().all(|n| (30..=31).contains(n)), "one VBlank and one LYC interrupt per frame: {counts:?}");
+    assert!(counts[2] > 30, "the timer interrupt woke the CPU too: {counts:?}");
+}
+
+#[test]
+fn an_idle_frame_takes_far_fewer_steps() {
+    let steps = |mut core: GbCore| {
+        core.run_frame().unwrap();
+        let end = core.clock.t_cycles + CYCLES_PER_FRAME;
+        let mut n = 0;
+        while core.clock.t_cycles < end { core.step().unwrap(); n += 1; }
+        n
+    };
+    let (plain, fast) = (steps(core(false)), steps(core(true)));
+    assert!(fast * 10 < plain, "{fast} fast steps vs {plain}");
+    let mut halted = core(true);
+    halted.run_frame().unwrap();
+    while !halted.halted { halted.step().unwrap(); }
+    assert!(matches!(halted.debug_step().unwrap(), DebugEvent::Halted { cycles } if cycles > 4 && cycles % 4 == 0));
+}
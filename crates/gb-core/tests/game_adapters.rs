@@ -0,0 +1,143 @@
+//! Game adapter registry and battery save decoders
+
+use gb_core::*;
+
+/// Pokémon Red / Blue text: 'A' is 0x80, 0x50 ends a string
+fn gen1_text(s: &str) -> Vec<u8> {
+    let mut out: Vec<u8> = s.bytes().map(|b| b - b'A' + 0x80).collect();
+    out.push(0x50);
+    out
+}
+
+fn gen1_save() -> Vec<u8> {
+    let mut sav = vec![0u8; 0x8000];
+    sav[0x2598..][..4].copy_from_slice(&gen1_text("ASH"));
+    sav[0x25F6..][..5].copy_from_slice(&gen1_text("GARY"));
+    sav[0x25A3] = 0b0000_0111;
+    sav[0x25B6..0x25B8].copy_from_slice(&[0xFF, 0x01]);
+    sav[0x25F3..0x25F6].copy_from_slice(&[0x00, 0x30, 0x00]);
+    sav[0x2602] = 0b1000_0011;
+    sav[0x2605..0x2607].copy_from_slice(&12345u16.to_be_bytes());
+    sav[0x2CED..0x2CF1].copy_from_slice(&[12, 0, 34, 56]);
+    // Party: one level 5 mon
+    sav[0x2F2C..0x2F2F].copy_from_slice(&[1, 0x99, 0xFF]);
+    let mon = &mut sav[0x2F34..0x2F34 + 44];
+    mon[0] = 0x99;
+    mon[1..3].copy_from_slice(&19u16.to_be_bytes());
+    mon[8..12].copy_from_slice(&[0x21, 0x2D, 0, 0]);
+    mon[12..14].copy_from_slice(&12345u16.to_be_bytes());
+    mon[14..17].copy_from_slice(&[0, 0, 135]);
+    mon[33] = 5;
+    mon[34..36].copy_from_slice(&20u16.to_be_bytes());
+    sav[0x303C..][..4].copy_from_slice(&gen1_text("ASH"));
+    sav[0x307E..][..10].copy_from_slice(&gen1_text("BULBASAUR"));
+    fix_gen1_checksum(&mut sav);
+    sav
+}
+
+fn fix_gen1_checksum(sav: &mut [u8]) {
+    sav[0x3523] = !sav[0x2598..0x3523].iter().fold(0u8, |s, &b| s.wrapping_add(b));
+}
+
+fn rom(title: &str) -> Vec<u8> { RomBuilder::new().title(title).code(&[0x18, 0xFE]).build() }
+
+#[test]
+fn registry_picks_adapters_by_title() {
+    let adapters = GameAdapters::builtin();
+    assert_eq!(adapters.ids(), ["pokemon_gen1", "zelda_la"]);
+    assert_eq!(adapters.for_rom(&rom("POKEMON BLUE")).map(|a| a.id()), Some("pokemon_gen1"));
+    assert_eq!(adapters.for_rom(&rom("ZELDA")).map(|a| a.id()), Some("zelda_la"));
+    assert!(adapters.for_rom(&rom("TETRIS")).is_none());
+    assert!(adapters.for_rom(&[0u8; 16]).is_none(), "no header");
+}
+
+struct Everything;
+
+impl GameAdapter for Everything {
+    fn id(&self) -> &'static str { "everything" }
+    fn matches(&self, _: &RomHeader) -> bool { true }
+    fn decode_save(&self, sram: &[u8]) -> Result<String, String> { Ok(format!("{{\"bytes\":{}}}", sram.len())) }
+}
+
+#[test]
+fn registered_adapters_come_first() {
+    let mut adapters = GameAdapters::builtin();
+    adapters.register(Box::new(Everything));
+    let adapter = adapters.for_rom(&rom("POKEMON RED")).unwrap();
+    assert_eq!(adapter.id(), "everything");
+    assert_eq!(adapter.decode_save(&[0; 3]).unwrap(), "{\"bytes\":3}");
+    assert!(GameAdapters::empty().for_rom(&rom("POKEMON RED")).is_none());
+}
+
+#[test]
+fn decodes_a_pokemon_save() {
+    let adapters = GameAdapters::builtin();
+    let gen1 = adapters.for_rom(&rom("POKEMON RED")).unwrap();
+    let doc = Json::parse(&gen1.decode_save(&gen1_save()).unwrap()).unwrap();
+    assert_eq!(doc.get("game").and_then(Json::as_str), Some("pokemon_gen1"));
+    assert_eq!(doc.get("checksum_ok").and_then(Json::as_bool), Some(true));
+    assert_eq!(doc.get("rival").and_then(Json::as_str), Some("GARY"));
+
+    let player = doc.get("player").unwrap();
+    assert_eq!(player.get("name").and_then(Json::as_str), Some("ASH"));
+    assert_eq!(player.get("id").and_then(Json::as_u64), Some(12345));
+    assert_eq!(player.get("money").and_then(Json::as_u64), Some(3000));
+    let badges: Vec<&str> = player.get("badges").and_then(Json::as_array).unwrap().iter().filter_map(Json::as_str).collect();
+    assert_eq!(badges, ["boulder", "cascade", "earth"]);
+    let dex = doc.get("pokedex").unwrap();
+    assert_eq!((dex.get("owned").and_then(Json::as_u64), dex.get("seen").and_then(Json::as_u64)), (Some(3), Some(9)));
+    assert_eq!(doc.get("play_time").and_then(|t| t.get("minutes")).and_then(Json::as_u64), Some(34));
+
+    let party = doc.get("party").and_then(Json::as_array).unwrap();
+    assert_eq!(party.len(), 1);
+    let mon = &party[0];
+    assert_eq!(mon.get("species").and_then(Json::as_u64), Some(0x99));
+    assert_eq!(mon.get("nickname").and_then(Json::as_str), Some("BULBASAUR"));
+    assert_eq!(mon.get("ot").and_then(Json::as_str), Some("ASH"));
+    assert_eq!(mon.get("level").and_then(Json::as_u64), Some(5));
+    assert_eq!((mon.get("hp").and_then(Json::as_u64), mon.get("max_hp").and_then(Json::as_u64)), (Some(19), Some(20)));
+    assert_eq!(mon.get("exp").and_then(Json::as_u64), Some(135));
+    assert_eq!(mon.get("moves").and_then(Json::as_array).map(|m| m.len()), Some(2));
+
+    let bx = doc.get("box").unwrap();
+    assert_eq!(bx.get("number").and_then(Json::as_u64), Some(1));
+    assert!(bx.get("mons").and_then(Json::as_array).unwrap().is_empty());
+}
+
+#[test]
+fn pokemon_checksum_and_size_are_checked() {
+    let gen1 = GameAdapters::builtin();
+    let gen1 = gen1.for_rom(&rom("POKEMON YELLOW")).unwrap();
+    let mut sav = gen1_save();
+    sav[0x25F3] = 0x99;
+    let doc = Json::parse(&gen1.decode_save(&sav).unwrap()).unwrap();
+    assert_eq!(doc.get("checksum_ok").and_then(Json::as_bool), Some(false));
+    assert!(gen1.decode_save(&sav[..0x2000]).unwrap_err().contains("expected at least"));
+}
+
+#[test]
+fn decodes_links_awakening_slots() {
+    let mut sav = vec![0u8; 0x2000];
+    let slot = &mut sav[0x0105..];
+    slot[0..6].copy_from_slice(&[0x01, 0x04, 0x02, 0x00, 0x0A, 0x42]);
+    slot[0x0F] = 5;
+    slot[0x5A] = 20;
+    slot[0x5B] = 3;
+    slot[0x5D..0x5F].copy_from_slice(&[0x01, 0x23]);
+    slot[0x65..0x68].copy_from_slice(&[1, 1, 0]);
+
+    let adapters = GameAdapters::builtin();
+    let doc = Json::parse(&adapters.for_rom(&rom("ZELDA")).unwrap().decode_save(&sav).unwrap()).unwrap();
+    let slots = doc.get("slots").and_then(Json::as_array).unwrap();
+    assert_eq!(slots.len(), 3);
+    assert_eq!(slots[1], Json::Null, "unused slot");
+    let s = &slots[0];
+    assert_eq!((s.get("b").and_then(Json::as_str), s.get("a").and_then(Json::as_str)), (Some("sword"), Some("shield")));
+    let inventory: Vec<&str> = s.get("inventory").and_then(Json::as_array).unwrap().iter().filter_map(Json::as_str).collect();
+    assert_eq!(inventory, ["bombs", "rocs_feather", "0x42"]);
+    assert_eq!(s.get("hearts").and_then(Json::as_f64), Some(2.5));
+    assert_eq!(s.get("max_hearts").and_then(Json::as_u64), Some(3));
+    assert_eq!(s.get("rupees").and_then(Json::as_u64), Some(123));
+    assert_eq!(s.get("seashells").and_then(Json::as_u64), Some(5));
+    assert_eq!(s.get("instruments").and_then(Json::as_u64), Some(2));
+}
@@ -0,0 +1,129 @@
+//! HALT: wake-up, interrupt return address and the halt bug
+
+use gb_core::*;
+
+fn core(src: &str) -> (GbCore, Assembly) {
+    let asm = assemble(src).unwrap();
+    let cart = RomBuilder::new().asm(src).unwrap().cartridge().unwrap();
+    (GbCore::new(cart), asm)
+}
+
+fn addr(asm: &Assembly, label: &str) -> u16 { asm.symbol(label).unwrap() as u16 }
+
+/// Step until PC reaches `label` (bounded, so a wedged CPU fails the test)
+fn run_to(core: &mut GbCore, asm: &Assembly, label: &str) {
+    let target = addr(asm, label);
+    for _ in 0..10_000 {
+        if core.regs.pc == target { return; }
+        core.step().unwrap();
+    }
+    panic!("never reached {label}, pc={:#06x}", core.regs.pc);
+}
+
+#[test]
+fn halt_bug_reads_the_next_byte_twice() {
+    // IME=0 with a pending, enabled interrupt: HALT falls through and the
+    // following opcode byte is fetched twice
+    let (mut c, asm) = core("
+        di
+        ld a, $01
+        ldh [$ff], a
+        ldh [$0f], a
+        xor a
+        halt
+        inc a
+        ld b, $04
+    done:
+        jr done
+    ");
+    run_to(&mut c, &asm, "done");
+    assert!(!c.halted);
+    assert_eq!(c.regs.a, 2, "INC A ran twice");
+    assert_eq!(c.regs.b, 4, "only one fetch repeats");
+    assert_eq!(c.bus.if_reg & 1, 1, "nothing was dispatched");
+}
+
+#[test]
+fn halt_bug_on_a_two_byte_immediate() {
+    let (mut c, asm) = core("
+        ld a, $04
+        ldh [$ff], a
+        ldh [$0f], a
+        halt
+        ld a, $14
+    done:
+        jr done
+    ");
+    run_to(&mut c, &asm, "done");
+    // `3E 14` runs as LD A,$3E followed by INC D ($14)
+    assert_eq!(c.regs.a, 0x3E);
+    assert_eq!(c.regs.d, 0x01);
+}
+
+#[test]
+fn ime0_halt_resumes_after_halt_without_dispatch() {
+    let (mut c, asm) = core("
+        ld a, $04
+        ldh [$ff], a
+        halt
+    after:
+        inc b
+    done:
+        jr done
+    ");
+    for _ in 0..50 { c.step().unwrap(); }
+    assert!(c.halted);
+    assert_eq!(c.regs.pc, addr(&asm, "after"));
+    let sp = c.regs.sp;
+    c.bus.if_reg |= 0x04;
+    run_to(&mut c, &asm, "done");
+    assert_eq!(c.regs.b, 0x01, "INC B ran once");
+    assert_eq!(c.regs.sp, sp);
+}
+
+#[test]
+fn interrupt_returns_past_halt() {
+    let (mut c, asm) = core("
+        ld a, $04
+        ldh [$ff], a
+        ei
+        halt
+    after:
+        nop
+    done:
+        jr done
+
+        org $50
+        reti
+    ");
+    for _ in 0..50 { c.step().unwrap(); }
+    assert!(c.halted);
+    c.bus.if_reg |= 0x04;
+    run_to(&mut c, &asm, "done");
+    // The timer handler's return address is the instruction after HALT
+    let ret = u16::from_le_bytes([c.bus.read(c.regs.sp.wrapping_sub(2)), c.bus.read(c.regs.sp.wrapping_sub(1))]);
+    assert_eq!(ret, addr(&asm, "after"));
+}
+
+#[test]
+fn halt_bug_survives_a_savestate() {
+    const SRC: &str = "
+        ld a, $01
+        ldh [$ff], a
+        ldh [$0f], a
+    h:
+        halt
+        inc a
+    done:
+        jr done
+    ";
+    let (mut c, asm) = core(SRC);
+    run_to(&mut c, &asm, "h");
+    c.step().unwrap();
+    assert!(c.halt_bug);
+    let (mut d, _) = core(SRC);
+    d.load_state(&c.save_state()).unwrap();
+    assert!(d.halt_bug);
+    run_to(&mut d, &asm, "done");
+    assert_eq!(d.regs.a, 3);
+}
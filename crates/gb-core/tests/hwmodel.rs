@@ -0,0 +1,66 @@
+//! Post-boot registers and IO defaults per hardware model
+
+use gb_core::*;
+
+fn core_for(model: HardwareModel, cgb_cart: bool) -> GbCore {
+    let cart = RomBuilder::new().cgb(if cgb_cart { 0x80 } else { 0 }).cartridge().unwrap();
+    GbCore::with_config(cart, CoreConfig { model, ..Default::default() })
+}
+
+#[test]
+fn a_register_identifies_the_model() {
+    assert_eq!(core_for(HardwareModel::Dmg, false).regs.a, 0x01);
+    assert_eq!(core_for(HardwareModel::Mgb, false).regs.a, 0xFF);
+    assert_eq!(core_for(HardwareModel::Sgb, false).regs.a, 0x01);
+    assert_eq!(core_for(HardwareModel::Cgb, true).regs.a, 0x11);
+}
+
+#[test]
+fn post_boot_register_pairs() {
+    let r = core_for(HardwareModel::Sgb, false).regs;
+    assert_eq!((r.af(), r.bc(), r.de(), r.hl()), (0x0100, 0x0014, 0x0000, 0xC060));
+    let r = core_for(HardwareModel::Cgb, true).regs;
+    assert_eq!((r.af(), r.bc(), r.de(), r.hl()), (0x1180, 0x0000, 0xFF56, 0x000D));
+    // A DMG cartridge on a CGB boots into compatibility mode
+    let r = core_for(HardwareModel::Cgb, false).regs;
+    assert_eq!((r.de(), r.hl()), (0x0008, 0x007C));
+    assert_eq!((r.sp, r.pc), (0xFFFE, 0x0100));
+}
+
+#[test]
+fn default_model_is_dmg() {
+    let c = GbCore::new(RomBuilder::new().cartridge().unwrap());
+    assert_eq!(c.config.model, HardwareModel::Dmg);
+    assert_eq!((c.regs.af(), c.regs.bc(), c.regs.de(), c.regs.hl()), (0x01B0, 0x0013, 0x00D8, 0x014D));
+}
+
+#[test]
+fn io_defaults() {
+    let c = core_for(HardwareModel::Dmg, false);
+    assert_eq!(c.bus.read(0xFF00), 0xCF);
+    assert_eq!(c.bus.read(0xFF02), 0x7E);
+    assert_eq!(c.bus.read(0xFF04), 0xAB);
+    assert_eq!(c.bus.read(0xFF0F) & 0x1F, 0x01);
+    assert_eq!(c.bus.read(0xFF40), 0x91);
+    assert_eq!(c.bus.read(0xFF47), 0xFC);
+    assert_eq!(c.bus.read(0xFF41) & 0x04, 0x04, "LY == LYC");
+
+    let c = core_for(HardwareModel::Cgb, true);
+    assert_eq!(c.bus.read(0xFF02), 0x7F);
+    assert_eq!(c.bus.read(0xFF04), 0x00);
+}
+
+#[test]
+fn model_follows_the_cartridge_header() {
+    assert_eq!(HardwareModel::for_rom(&RomBuilder::new().build()), HardwareModel::Dmg);
+    assert_eq!(HardwareModel::for_rom(&RomBuilder::new().cgb(0xC0).build()), HardwareModel::Cgb);
+}
+
+#[test]
+fn savestate_restores_the_model() {
+    let a = core_for(HardwareModel::Mgb, false);
+    let mut b = core_for(HardwareModel::Dmg, false);
+    b.load_state(&a.save_state()).unwrap();
+    assert_eq!(b.config.model, HardwareModel::Mgb);
+    assert_eq!(b.regs.a, 0xFF);
+}
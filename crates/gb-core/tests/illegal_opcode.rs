@@ -0,0 +1,62 @@
+//! Illegal opcodes hang the CPU
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+/// LD A,0x01 / <op> / INC A
+fn locking(op: u8) -> GbCore { core_with(&[0x3E, 0x01, op, 0x3C]) }
+
+#[test]
+fn every_illegal_opcode_locks_and_reports_once() {
+    for op in ILLEGAL_OPCODES {
+        let mut core = locking(op);
+        core.step().unwrap();
+        match core.step() {
+            Err(CoreError::CpuLocked { pc, opcode }) => assert_eq!((pc, opcode), (0x0102, op)),
+            other => panic!("{op:02X}: {other:?}"),
+        }
+        assert!(core.locked);
+        for _ in 0..8 { assert_eq!(core.step().unwrap(), 4, "{op:02X}"); }
+        assert_eq!((core.regs.pc, core.regs.a), (0x0102, 0x01), "{op:02X}: nothing after it runs");
+    }
+}
+
+#[test]
+fn locked_cpu_ignores_interrupts_but_the_ppu_runs_on() {
+    let mut core = locking(0xD3);
+    core.step().unwrap();
+    assert!(core.step().is_err());
+    core.ime = true;
+    core.bus.ie = 0x01;
+    let (frame, sp) = (core.clock.frame_count(), core.regs.sp);
+    for _ in 0..3 { core.run_frame().unwrap(); }
+    assert!(core.clock.frame_count() >= frame + 3);
+    assert_eq!((core.regs.pc, core.regs.sp), (0x0102, sp), "VBlank was not dispatched");
+    assert!(core.bus.if_reg & 0x01 != 0);
+}
+
+#[test]
+fn lock_survives_savestates() {
+    let mut core = locking(0xFD);
+    let fresh = core.save_state();
+    core.step().unwrap();
+    assert!(core.step().is_err());
+    let locked = core.save_state();
+
+    let mut other = locking(0xFD);
+    other.load_state(&locked).unwrap();
+    assert!(other.locked);
+    assert_eq!(other.step().unwrap(), 4, "the lock is not reported again");
+    other.load_state(&fresh).unwrap();
+    assert!(!other.locked);
+    other.step().unwrap();
+    assert_eq!(other.regs.a, 0x01);
+}
+
+#[test]
+fn test_runs_fail_on_a_lock() {
+    let run = run_test(&mut locking(0xE4), 10);
+    assert_eq!(run.outcome, TestOutcome::Fail("CpuLocked: illegal opcode E4 at 0102".into()));
+}
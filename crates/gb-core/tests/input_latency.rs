@@ -0,0 +1,87 @@
+//! Press-to-screen input latency measurement
+
+use gb_core::*;
+
+/// Polls the button row; while A is held BGP turns black, else stays white
+const POLL_A: &str = "
+    ld a, $fc
+    ldh [$47], a
+loop:
+    ld a, $10
+    ldh [$00], a
+    ldh a, [$00]
+    bit 0, a
+    ld a, $fc
+    jr nz, .set
+    ld a, $ff
+.set:
+    ldh [$47], a
+    jr loop
+";
+
+fn core(src: &str, config: CoreConfig) -> GbCore {
+    let cart = RomBuilder::new().asm(src).unwrap().cartridge().unwrap();
+    GbCore::with_config(cart, config)
+}
+
+#[test]
+fn reacting_button_is_found_and_core_is_untouched() {
+    let mut c = core(POLL_A, CoreConfig::default());
+    c.run_frame().unwrap();
+    let (t, pc) = (c.clock.t_cycles, c.regs.pc);
+
+    let l = measure_input_latency(&mut c, 8).unwrap();
+    assert_eq!((l.frames, l.button), (Some(1), Some("a")));
+    assert!((l.ms.unwrap() - FRAME_MS).abs() < 1e-9);
+    assert_eq!(l.per_button.len(), 8);
+    assert!(l.per_button.iter().all(|(b, f)| (*b == "a") == f.is_some()), "{:?}", l.per_button);
+    assert_eq!((c.clock.t_cycles, c.regs.pc, c.bus.buttons), (t, pc, 0));
+    assert_eq!(l.at_frame, c.clock.frame_count());
+}
+
+#[test]
+fn unresponsive_game_reports_no_latency() {
+    let mut c = core("loop:\n    jr loop\n", CoreConfig::default());
+    let l = measure_input_latency(&mut c, 4).unwrap();
+    assert_eq!((l.frames, l.ms, l.button), (None, None, None));
+    assert!(l.to_json().starts_with("{\"frames\":null,\"ms\":null,\"button\":null,"));
+}
+
+#[test]
+fn held_buttons_are_not_probed() {
+    let mut c = core(POLL_A, CoreConfig::default());
+    c.set_buttons(BTN_A);
+    let l = measure_input_latency(&mut c, 4).unwrap();
+    assert_eq!(l.per_button.len(), 7);
+    assert_eq!(l.frames, None, "A is already down");
+}
+
+#[test]
+fn frame_skipping_lite_mode_adds_latency() {
+    let lite = CoreConfig { lite: LiteMode { skip_audio: false, render: RenderSkip::AlternateFrames }, ..Default::default() };
+    let mut full = core(POLL_A, CoreConfig::default());
+    let mut skip = core(POLL_A, lite);
+    // A press landing on a skipped frame only shows on the next drawn one
+    let mut measured = vec![];
+    for _ in 0..2 {
+        full.run_frame().unwrap();
+        skip.run_frame().unwrap();
+        let f = measure_input_latency(&mut full, 8).unwrap().frames.unwrap();
+        let s = measure_input_latency(&mut skip, 8).unwrap().frames.unwrap();
+        measured.push((f, s));
+    }
+    measured.sort();
+    assert_eq!(measured, vec![(1, 1), (1, 2)]);
+}
+
+#[test]
+fn diagnostics_carry_the_measurement() {
+    let mut c = core(POLL_A, CoreConfig::default());
+    assert!(c.diagnostics_json().contains("\"input_latency\":null,"));
+    measure_input_latency(&mut c, 4).unwrap();
+    let diag = Json::parse(&c.diagnostics_json()).unwrap();
+    let l = diag.get("input_latency").unwrap();
+    assert_eq!(l.get("frames").and_then(Json::as_f64), Some(1.0));
+    assert_eq!(l.get("button").and_then(Json::as_str), Some("a"));
+    assert_eq!(l.get("per_button").and_then(|p| p.get("start")), Some(&Json::Null));
+}
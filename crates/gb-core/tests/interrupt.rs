@@ -0,0 +1,22 @@
+//! Host-raised interrupt of a wedged run_frame
+
+use gb_core::{Cartridge, CoreError, GbCore, CYCLES_PER_FRAME};
+use std::sync::atomic::Ordering;
+
+#[test]
+fn interrupt_handle_cuts_frame_short_and_resumes() {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x0100] = 0x18; rom[0x0101] = 0xFE; // JR -2: spins forever
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    let handle = core.interrupt_handle();
+
+    core.step().unwrap();
+    let t0 = core.clock.t_cycles;
+    handle.store(true, Ordering::Release);
+    assert!(matches!(core.run_frame(), Err(CoreError::Interrupted)));
+    assert_eq!(core.clock.t_cycles, t0);
+    assert!(!handle.load(Ordering::Acquire));
+
+    core.run_frame().unwrap();
+    assert!(core.clock.t_cycles >= t0 + CYCLES_PER_FRAME);
+}
@@ -0,0 +1,179 @@
+//! EI delay, DI / EI interaction and the 5 M-cycle interrupt dispatch
+
+use gb_core::*;
+
+/// Timer handler at 0x50 stores B (the count of INC B run before it) to C000
+const HANDLER: &str = "
+        org $50
+        ld a, b
+        ld [$c000], a
+        ld a, $ff
+        ld [$c001], a
+    spin:
+        jr spin
+";
+
+fn core(body: &str) -> (GbCore, Assembly) {
+    // Timer interrupt enabled and already requested before the code under test
+    let src = format!("
+        ld a, $04
+        ldh [$ff], a
+        ldh [$0f], a
+        ld b, 0
+{body}
+{HANDLER}");
+    let asm = assemble(&src).unwrap();
+    let cart = RomBuilder::new().asm(&src).unwrap().cartridge().unwrap();
+    (GbCore::new(cart), asm)
+}
+
+fn run(core: &mut GbCore, steps: usize) { for _ in 0..steps { core.step().unwrap(); } }
+fn handled(core: &GbCore) -> bool { core.bus.read(0xC001) == 0xFF }
+
+#[test]
+fn ei_takes_effect_after_the_next_instruction() {
+    let (mut c, _) = core("
+        ei
+        inc b
+        inc b
+        inc b
+    done:
+        jr done
+    ");
+    run(&mut c, 40);
+    assert!(handled(&c));
+    assert_eq!(c.bus.read(0xC000), 1, "exactly one instruction ran after EI");
+}
+
+#[test]
+fn di_right_after_ei_cancels_it() {
+    let (mut c, _) = core("
+        ei
+        di
+        inc b
+    done:
+        jr done
+    ");
+    run(&mut c, 40);
+    assert!(!handled(&c));
+    assert!(!c.ime && !c.ime_pending);
+    assert_eq!(c.regs.b, 1);
+}
+
+#[test]
+fn back_to_back_ei_enables_after_the_second() {
+    let (mut c, _) = core("
+        ei
+        ei
+        inc b
+    done:
+        jr done
+    ");
+    run(&mut c, 40);
+    assert!(handled(&c));
+    assert_eq!(c.bus.read(0xC000), 0);
+}
+
+#[test]
+fn ei_halt_with_pending_interrupt_returns_to_halt() {
+    let (mut c, asm) = core("
+        ei
+    h:
+        halt
+        inc b
+    done:
+        jr done
+    ");
+    run(&mut c, 40);
+    assert!(handled(&c));
+    // The halt bug leaves PC on the HALT, so that is where the handler returns
+    let ret = u16::from_le_bytes([c.bus.read(c.regs.sp), c.bus.read(c.regs.sp.wrapping_add(1))]);
+    assert_eq!(ret, asm.symbol("h").unwrap() as u16);
+    assert!(!c.halt_bug);
+}
+
+#[test]
+fn dispatch_takes_five_m_cycles() {
+    let (mut c, _) = core("
+    done:
+        jr done
+    ");
+    run(&mut c, 8);
+    c.ime = true;
+    let (t, sp, pc) = (c.clock.t_cycles, c.regs.sp, c.regs.pc);
+    assert_eq!(c.step().unwrap(), 20);
+    assert_eq!(c.clock.t_cycles - t, 20);
+    assert_eq!(c.regs.pc, 0x0050);
+    assert_eq!(c.regs.sp, sp - 2);
+    assert_eq!(u16::from_le_bytes([c.bus.read(sp - 2), c.bus.read(sp - 1)]), pc);
+    assert_eq!(c.bus.if_reg & 0x04, 0);
+    assert!(!c.ime);
+}
+
+#[test]
+fn interrupt_raised_during_dispatch_wins_on_priority() {
+    let (mut c, _) = core("
+    done:
+        jr done
+    ");
+    run(&mut c, 8);
+    c.bus.ie = 0x05;
+    // VBlank starts 4 T-cycles into the timer interrupt's dispatch
+    let p = &mut c.bus.ppu;
+    p.mode = PpuMode::HBlank; p.ly = 143; p.dot = PPU_MODE0_CYCLES - 4;
+    c.ime = true;
+    c.step().unwrap();
+    assert_eq!(c.regs.pc, 0x0040, "VBlank outranks the timer");
+    assert_eq!(c.bus.if_reg & 0x05, 0x04, "the timer request is still pending");
+}
+
+#[test]
+fn pushing_pc_high_into_ie_cancels_dispatch() {
+    let (mut c, _) = core("
+    done:
+        jr done
+    ");
+    run(&mut c, 8);
+    assert_eq!(c.regs.pc >> 8, 0x01);
+    // The high byte push lands on IE (FFFF): 0x01 leaves the timer disabled
+    c.regs.sp = 0x0000;
+    c.ime = true;
+    assert_eq!(c.step().unwrap(), 20);
+    assert_eq!(c.bus.ie, 0x01);
+    assert_eq!(c.regs.pc, 0x0000, "nothing left to service");
+    assert_eq!(c.bus.if_reg & 0x04, 0x04, "the timer request is not acknowledged");
+    assert_eq!(c.regs.sp, 0xFFFE);
+    assert!(!c.ime);
+}
+
+#[test]
+fn ie_written_by_the_push_can_pick_another_interrupt() {
+    let (mut c, _) = core("
+    done:
+        jr done
+    ");
+    run(&mut c, 8);
+    c.bus.if_reg |= 0x01;
+    c.bus.ie = 0x04;
+    c.regs.sp = 0x0000;
+    c.ime = true;
+    c.step().unwrap();
+    assert_eq!(c.regs.pc, 0x0040, "IE now only enables VBlank");
+    assert_eq!(c.bus.if_reg & 0x05, 0x04);
+}
+
+#[test]
+fn low_byte_push_into_ie_is_too_late_to_cancel() {
+    let (mut c, _) = core("
+    done:
+        jr done
+    ");
+    run(&mut c, 8);
+    let pc = c.regs.pc;
+    c.regs.sp = 0x0001;
+    c.ime = true;
+    c.step().unwrap();
+    assert_eq!(c.regs.pc, 0x0050, "the vector was picked before the low byte");
+    assert_eq!(c.bus.ie, pc as u8);
+    assert_eq!(c.bus.if_reg & 0x04, 0);
+}
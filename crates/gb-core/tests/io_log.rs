@@ -0,0 +1,50 @@
+//! Cycle-stamped IO register write log
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+/// LD A,0x07 / LDH (SCX),A / LD (0xFFFF),A / LD (0xC000),A
+const PROG: [u8; 10] = [0x3E, 0x07, 0xE0, 0x43, 0xEA, 0xFF, 0xFF, 0xEA, 0x00, 0xC0];
+
+#[test]
+fn records_io_and_ie_writes_with_pc_and_cycle() {
+    let mut core = core_with(&PROG);
+    core.bus.io_log = Some(Box::default());
+    let mut starts = vec![];
+    for _ in 0..4 { starts.push(core.clock.t_cycles); core.step().unwrap(); }
+    let log = core.bus.io_log.as_ref().unwrap();
+    assert_eq!(log.entries(), [
+        IoWrite { t_cycles: starts[1], pc: 0x0102, reg: 0x43, value: 0x07 },
+        IoWrite { t_cycles: starts[2], pc: 0x0104, reg: 0xFF, value: 0x07 },
+    ], "WRAM writes are not logged");
+}
+
+#[test]
+fn binary_round_trip_and_csv() {
+    let mut core = core_with(&PROG);
+    core.bus.io_log = Some(Box::default());
+    for _ in 0..3 { core.step().unwrap(); }
+    let log = core.bus.io_log.take().unwrap();
+    let bytes = log.to_bytes();
+    assert_eq!(&bytes[..8], IO_LOG_MAGIC);
+    assert_eq!(bytes.len(), 8 + 2 * 12);
+    assert_eq!(IoWriteLog::from_bytes(&bytes).unwrap().entries(), log.entries());
+    assert!(IoWriteLog::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(IoWriteLog::from_bytes(b"not a log").is_err());
+
+    let csv = log.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "t_cycles,frame,pc,address,register,value");
+    assert!(lines[1].ends_with(",0,0102,FF43,SCX,07"), "{}", lines[1]);
+    assert!(lines[2].ends_with(",0104,FFFF,IE,07"), "{}", lines[2]);
+}
+
+#[test]
+fn off_by_default() {
+    let mut core = core_with(&PROG);
+    for _ in 0..4 { core.step().unwrap(); }
+    assert!(core.bus.io_log.is_none());
+    assert_eq!(io_reg_name(0x40), "LCDC");
+}
@@ -0,0 +1,65 @@
+//! P1 matrix reads, the joypad interrupt and host input mapping
+
+use gb_core::*;
+
+fn core() -> GbCore {
+    GbCore::new(Cartridge::from_bytes(vec![0x00u8; 32 * 1024]).unwrap())
+}
+
+#[test]
+fn p1_reads_selected_rows() {
+    let held = BTN_RIGHT | BTN_A | BTN_START;
+    assert_eq!(p1_read(0x30, held), 0xFF);            // nothing selected
+    assert_eq!(p1_read(0x20, held), 0xE0 | 0x0E);     // d-pad row: right low
+    assert_eq!(p1_read(0x10, held), 0xD0 | 0x06);     // button row: A and Start low
+    assert_eq!(p1_read(0x00, held), 0xC0 | 0x06);     // both rows OR-ed
+}
+
+#[test]
+fn bus_p1_follows_set_buttons() {
+    let mut core = core();
+    core.bus.write(0xFF00, 0x20);
+    assert_eq!(core.bus.read(0xFF00) & 0x0F, 0x0F);
+    core.set_buttons(BTN_DOWN);
+    assert_eq!(core.bus.read(0xFF00) & 0x0F, 0x07);
+    core.bus.write(0xFF00, 0x10);
+    assert_eq!(core.bus.read(0xFF00) & 0x0F, 0x0F);
+}
+
+#[test]
+fn new_press_requests_joypad_interrupt() {
+    let mut core = core();
+    core.set_buttons(BTN_A);
+    assert_ne!(core.bus.if_reg & 0x10, 0);
+    core.bus.if_reg = 0;
+    core.set_buttons(BTN_A);
+    assert_eq!(core.bus.if_reg & 0x10, 0, "held button is not a new press");
+    core.set_buttons(0);
+    assert_eq!(core.bus.if_reg & 0x10, 0);
+}
+
+#[test]
+fn mapping_parses_and_combines_bindings() {
+    let m = InputMapping::parse("# custom\nkey.j = a\nkey.j = b  # both\npad.north = START\n").unwrap();
+    assert_eq!(m.buttons_for("key.j"), BTN_A | BTN_B);
+    assert_eq!(m.buttons_for("PAD.NORTH"), BTN_START);
+    assert_eq!(m.buttons_for("key.q"), 0);
+    assert!(InputMapping::parse("key.j = turbo").is_err());
+    assert!(InputMapping::parse("key.j").is_err());
+
+    let d = InputMapping::default();
+    assert_eq!(d.buttons_for("key.z"), BTN_A);
+    assert_eq!(d.buttons_for("pad.dpad_up"), BTN_UP);
+}
+
+#[test]
+fn combined_input_ors_backends() {
+    struct Fixed(InputPoll);
+    impl InputBackend for Fixed { fn poll(&mut self) -> InputPoll { self.0.clone() } }
+    let pad = |name: &str| InputEvent::PadConnected(name.into());
+    let mut c = CombinedInput(vec![
+        Box::new(Fixed(InputPoll { buttons: BTN_A, quit: false, events: vec![pad("one")] })),
+        Box::new(Fixed(InputPoll { buttons: BTN_LEFT, quit: true, events: vec![pad("two")] })),
+    ]);
+    assert_eq!(c.poll(), InputPoll { buttons: BTN_A | BTN_LEFT, quit: true, events: vec![pad("one"), pad("two")] });
+}
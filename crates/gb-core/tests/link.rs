@@ -0,0 +1,95 @@
+//! Serial link transports and the BGB link protocol
+
+use gb_core::*;
+use std::sync::{Arc, Mutex};
+
+mod common;
+use common::core_with;
+
+/// LD A,sb / LDH (SB),A / LD A,sc / LDH (SC),A / JR -2
+fn serial_prog(sb: u8, sc: u8) -> [u8; 10] { [0x3E, sb, 0xE0, 0x01, 0x3E, sc, 0xE0, 0x02, 0x18, 0xFE] }
+
+/// Answers every transfer with `reply`, logging what it was sent
+struct Partner { reply: u8, master_byte: Option<u8>, log: Arc<Mutex<Vec<u8>>> }
+
+impl LinkTransport for Partner {
+    fn transfer(&mut self, out: u8, _control: u8, _t: u64) -> u8 { self.log.lock().unwrap().push(out); self.reply }
+    fn poll_slave(&mut self, out: u8, _t: u64) -> Option<u8> {
+        let b = self.master_byte.take()?;
+        self.log.lock().unwrap().push(out);
+        Some(b)
+    }
+}
+
+fn partner(reply: u8, master_byte: Option<u8>) -> (Box<Partner>, Arc<Mutex<Vec<u8>>>) {
+    let log = Arc::new(Mutex::new(vec![]));
+    (Box::new(Partner { reply, master_byte, log: log.clone() }), log)
+}
+
+#[test]
+fn packets_round_trip_in_bgb_layout() {
+    let p = BgbPacket::new(BgbPacket::SYNC1, 0x42, 0x81, 0, 0x1234_5678);
+    assert_eq!(p.encode(), [104, 0x42, 0x81, 0, 0x78, 0x56, 0x34, 0x12]);
+    assert_eq!(BgbPacket::decode(p.encode()), p);
+    assert_eq!(BGB_VERSION.encode(), [1, 1, 4, 0, 0, 0, 0, 0]);
+    assert_eq!(bgb_timestamp(u64::MAX), 0x7FFF_FFFF);
+}
+
+#[test]
+fn master_transfer_goes_through_the_link() {
+    let mut core = core_with(&serial_prog(0x42, 0x81));
+    let (link, log) = partner(0x99, None);
+    core.set_link(Some(link));
+    for _ in 0..4 { core.step().unwrap(); }
+    assert_eq!(*log.lock().unwrap(), [0x42]);
+    assert_eq!((core.bus.read(0xFF01), core.bus.io[0x02] & 0x80, core.bus.if_reg & 0x08), (0x99, 0, 0x08));
+}
+
+#[test]
+fn slave_waits_for_the_master() {
+    let mut core = core_with(&serial_prog(0x17, 0x80));
+    let (link, log) = partner(0, Some(0x5A));
+    core.set_link(Some(link));
+    for _ in 0..4 { core.step().unwrap(); }
+    assert_eq!(core.bus.io[0x01], 0x5A, "the first poll after arming completes the transfer");
+    assert_eq!(*log.lock().unwrap(), [0x17]);
+
+    // Without a master byte the transfer stays armed
+    let mut core = core_with(&serial_prog(0x17, 0x80));
+    core.set_link(Some(partner(0, None).0));
+    core.run_frame().unwrap();
+    assert_eq!((core.bus.io[0x01], core.bus.io[0x02] & 0x80), (0x17, 0x80));
+}
+
+#[test]
+fn unlinked_port_still_shifts_in_ff() {
+    let mut core = core_with(&serial_prog(0x42, 0x81));
+    for _ in 0..4 { core.step().unwrap(); }
+    assert_eq!(core.bus.io[0x01], 0xFF);
+    assert_eq!(core.console_text(), "B");
+}
+
+#[cfg(feature = "link")]
+#[test]
+fn two_cores_trade_a_byte_over_bgb_tcp() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let slave = std::thread::spawn(move || {
+        let mut core = core_with(&serial_prog(0x99, 0x80));
+        core.set_link(Some(Box::new(BgbLink::accept(&listener).unwrap())));
+        for _ in 0..120 {
+            core.run_frame().unwrap();
+            if core.bus.io[0x02] & 0x80 == 0 { break; }
+        }
+        core.bus.io[0x01]
+    });
+    let mut core = core_with(&serial_prog(0x42, 0x81));
+    let mut link = BgbLink::connect(addr).unwrap();
+    link.timeout = std::time::Duration::from_secs(5);
+    core.set_link(Some(Box::new(link)));
+    // Let the slave arm its transfer first
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    for _ in 0..4 { core.step().unwrap(); }
+    assert_eq!(core.bus.io[0x01], 0x99);
+    assert_eq!(slave.join().unwrap(), 0x42);
+}
@@ -0,0 +1,113 @@
+//! Lite mode: reduced audio / video output with unchanged CPU timing
+
+use gb_core::*;
+
+/// Starts a square-wave note with a length counter, then counts in WRAM
+const PROGRAM: &str = "
+    ld a, $80
+    ldh [$26], a
+    ld a, $77
+    ldh [$24], a
+    ld a, $f0
+    ldh [$12], a
+    ld a, $20
+    ldh [$11], a
+    ld a, $c7
+    ldh [$14], a
+    ld hl, $c000
+loop:
+    inc [hl]
+    ldh a, [$44]
+    ld [$c001], a
+    jr loop
+";
+
+fn core(lite: LiteMode) -> GbCore {
+    let cart = RomBuilder::new().asm(PROGRAM).unwrap().cartridge().unwrap();
+    GbCore::with_config(cart, CoreConfig { lite, ..Default::default() })
+}
+
+/// Tile 1 has alternating dark / light rows; the whole BG map uses it
+fn striped(core: &mut GbCore) {
+    for row in 0..8 {
+        let v = if row % 2 == 0 { 0xFF } else { 0x00 };
+        core.bus.vram[0][0x10 + row * 2] = v;
+        core.bus.vram[0][0x11 + row * 2] = v;
+    }
+    core.bus.vram[0][0x1800..0x1C00].fill(1);
+}
+
+fn row(core: &GbCore, y: usize) -> &[u8] { &core.bus.ppu.framebuffer[y * LCD_WIDTH..(y + 1) * LCD_WIDTH] }
+
+#[test]
+fn timing_matches_a_full_core() {
+    let mut full = core(LiteMode::default());
+    let mut lite = core(LiteMode::LITE);
+    for _ in 0..30 {
+        full.run_frame().unwrap();
+        lite.run_frame().unwrap();
+    }
+    assert_eq!(lite.regs.pc, full.regs.pc);
+    assert_eq!(lite.clock.t_cycles, full.clock.t_cycles);
+    assert_eq!(lite.bus.wram, full.bus.wram);
+    assert_eq!(lite.bus.timer.div_counter(), full.bus.timer.div_counter());
+    assert_eq!(lite.bus.read(0xFF26), full.bus.read(0xFF26), "length counters still run");
+    assert!(!full.bus.apu.sample_buffer.is_empty());
+    assert!(lite.bus.apu.sample_buffer.is_empty());
+}
+
+#[test]
+fn alternate_scanlines_repeat_the_line_above() {
+    let lite = LiteMode { render: RenderSkip::AlternateScanlines, ..Default::default() };
+    let (mut full, mut half) = (core(LiteMode::default()), core(lite));
+    striped(&mut full);
+    striped(&mut half);
+    full.run_frame().unwrap();
+    half.run_frame().unwrap();
+    assert_ne!(row(&full, 0), row(&full, 1));
+    for y in (0..LCD_HEIGHT).step_by(2) {
+        assert_eq!(row(&half, y), row(&full, y));
+        assert_eq!(row(&half, y + 1), row(&half, y));
+    }
+}
+
+#[test]
+fn alternate_frames_draw_every_other_frame() {
+    let count_changes = |lite: LiteMode| {
+        let mut c = core(lite);
+        striped(&mut c);
+        // Eight distinct tile rows, so each scroll step gives a new picture
+        for row in 0..8 { c.bus.vram[0][0x10 + row * 2] = 1 << row; }
+        let mut changes = 0;
+        for _ in 0..8 {
+            c.bus.ppu.scy += 1;
+            let before = c.bus.ppu.framebuffer.clone();
+            c.run_frame().unwrap();
+            if c.bus.ppu.framebuffer != before { changes += 1; }
+        }
+        changes
+    };
+    assert_eq!(count_changes(LiteMode::default()), 8);
+    assert_eq!(count_changes(LiteMode { render: RenderSkip::AlternateFrames, ..Default::default() }), 4);
+}
+
+#[test]
+fn diagnostics_report_degradations() {
+    let full = core(LiteMode::default());
+    assert!(full.diagnostics_json().contains("\"lite\":false,\"degradations\":[]"));
+
+    let lite = core(LiteMode::LITE);
+    let diag = Json::parse(&lite.diagnostics_json()).unwrap();
+    let subsystems: Vec<&str> = diag.get("degradations").unwrap().as_array().unwrap().iter()
+        .map(|d| d.get("subsystem").unwrap().as_str().unwrap()).collect();
+    assert_eq!(subsystems, ["apu", "ppu"]);
+    assert_eq!(LiteMode::LITE.degradations().len(), 2);
+    assert_eq!(RenderSkip::parse(RenderSkip::AlternateFrames.as_str()), Some(RenderSkip::AlternateFrames));
+
+    // Recorded in savestates, but a load keeps the host's own setting
+    let state = lite.save_state();
+    assert!(String::from_utf8_lossy(&state).contains("\"lite\":{\"skip_audio\":true,\"render\":\"alternate_scanlines\"}"));
+    let mut other = core(LiteMode::default());
+    other.load_state(&state).unwrap();
+    assert_eq!(other.config.lite, LiteMode::default());
+}
@@ -0,0 +1,47 @@
+//! Power-on RAM patterns and their savestate round trip
+
+use gb_core::*;
+
+fn cart() -> Cartridge { RomBuilder::new().code(Code::new(CODE_START).spin().bytes()).cartridge().unwrap() }
+
+fn core(mem_init: MemInit, mem_seed: u64) -> GbCore { GbCore::with_config(cart(), CoreConfig { mem_init, mem_seed, ..Default::default() }) }
+
+#[test]
+fn default_config_keeps_ram_zeroed() {
+    let c = GbCore::new(cart());
+    assert_eq!(c.config, CoreConfig::default());
+    assert!(c.bus.wram.iter().flatten().all(|&b| b == 0));
+    assert!(c.bus.hram.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn dmg_noise_is_seeded() {
+    let a = core(MemInit::Dmg, 1);
+    assert_eq!(a.bus.wram, core(MemInit::Dmg, 1).bus.wram);
+    assert_ne!(a.bus.wram, core(MemInit::Dmg, 2).bus.wram);
+    let zeros = a.bus.wram.iter().flatten().filter(|&&b| b == 0).count();
+    assert!(zeros < 0x8000 / 64, "{zeros} zero bytes");
+    assert!(a.bus.vram.iter().flatten().all(|&b| b == 0), "boot ROM clears VRAM");
+}
+
+#[test]
+fn cgb_wram_is_striped() {
+    let c = core(MemInit::Cgb, 7);
+    let bank = &c.bus.wram[0];
+    let matching = bank.iter().enumerate().filter(|(i, &b)| b == if (i / 8) % 2 == 0 { 0x00 } else { 0xFF }).count();
+    assert!(matching > 0x1000 * 9 / 10 && matching < 0x1000, "{matching}");
+    assert!(c.bus.oam.iter().all(|&b| b == 0));
+    assert_eq!(c.bus.read(0xC008), bank[8]);
+}
+
+#[test]
+fn savestate_records_the_config() {
+    let a = core(MemInit::Cgb, 0xDEAD);
+    let state = a.save_state();
+    assert!(String::from_utf8_lossy(&state).contains("\"config\":{\"model\":\"dmg\",\"mem_init\":\"cgb\",\"mem_seed\":57005,"));
+
+    let mut b = GbCore::new(cart());
+    b.load_state(&state).unwrap();
+    assert_eq!(b.config, a.config);
+    assert_eq!(b.bus.wram, a.bus.wram);
+}
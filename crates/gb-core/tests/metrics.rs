@@ -0,0 +1,57 @@
+//! Metrics registry and Prometheus encoding
+
+use gb_core::*;
+use std::io::{Read, Write};
+
+#[test]
+fn standard_metrics_encode_with_labels() {
+    let m = Metrics::new().with_label("job", "batch").with_label("host", "a\"b");
+    m.add(METRIC_FRAMES, 300.0);
+    m.inc(METRIC_WATCHDOG_TRIPS);
+    m.set(METRIC_FPS, 1234.5);
+    let text = m.encode();
+    assert!(text.contains("# HELP mrom_frames_total Frames emitted\n# TYPE mrom_frames_total counter\nmrom_frames_total{job=\"batch\",host=\"a\\\"b\"} 300\n"), "{text}");
+    assert!(text.contains("mrom_fps{job=\"batch\",host=\"a\\\"b\"} 1234.5\n"));
+    assert!(text.contains("mrom_watchdog_trips_total{job=\"batch\",host=\"a\\\"b\"} 1\n"));
+    assert!(text.contains("# TYPE mrom_desyncs_total counter\n"));
+}
+
+#[test]
+fn unknown_names_register_on_first_use() {
+    let m = Metrics::new();
+    m.inc("custom_events_total");
+    m.set("custom_level", 3.0);
+    m.describe("custom_level", MetricKind::Gauge, "A level");
+    assert_eq!(m.get("custom_events_total"), Some(1.0));
+    let text = m.encode();
+    assert!(text.contains("# TYPE custom_events_total counter\ncustom_events_total 1\n"));
+    assert!(text.contains("# HELP custom_level A level\n# TYPE custom_level gauge\ncustom_level 3\n"));
+}
+
+#[test]
+fn textfile_and_push() {
+    let m = Metrics::new();
+    m.add(METRIC_BYTES_WRITTEN, 4096.0);
+    let path = std::env::temp_dir().join(format!("mrom_metrics_{}.prom", std::process::id()));
+    m.write_textfile(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), m.encode());
+    std::fs::remove_file(&path).unwrap();
+
+    // Minimal Pushgateway stand-in
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let gateway = std::thread::spawn(move || {
+        let (mut s, _) = listener.accept().unwrap();
+        let mut buf = vec![0u8; 8192];
+        let mut req = String::new();
+        while !req.contains("mrom_bytes_written_total 4096") {
+            let n = s.read(&mut buf).unwrap();
+            if n == 0 { break; }
+            req += &String::from_utf8_lossy(&buf[..n]);
+        }
+        s.write_all(b"HTTP/1.0 200 OK\r\n\r\n").unwrap();
+        req
+    });
+    m.push(&format!("http://{addr}"), "unit").unwrap();
+    assert!(gateway.join().unwrap().starts_with("PUT /metrics/job/unit HTTP/1.0\r\n"));
+}
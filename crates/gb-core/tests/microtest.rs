@@ -0,0 +1,53 @@
+//! Single-instruction CPU test records
+
+use gb_core::*;
+
+#[test]
+fn records_are_reproducible_and_round_trip() {
+    assert_eq!(microtest_opcodes().len(), 242 + 256);
+    assert!(generate_microtests(0x76, 1, 0).is_err());
+
+    let tests = generate_microtests(0x3E, 5, 7).unwrap();
+    assert_eq!(tests, generate_microtests(0x3E, 5, 7).unwrap());
+    assert_ne!(tests, generate_microtests(0x3E, 5, 8).unwrap());
+    // LD A, n8: A takes the immediate, PC moves past it
+    for t in &tests {
+        let (i, f) = (&t.initial, &t.final_state);
+        let imm = i.ram.iter().find(|(a, _)| *a == i.regs.pc.wrapping_add(1)).unwrap().1;
+        assert_eq!((f.regs.a, f.regs.pc, t.t_cycles), (imm, i.regs.pc + 2, 8), "{}", t.name);
+    }
+    assert_eq!(tests[3].name, "3e 0003");
+
+    let parsed = parse_microtests(&Json::parse(&microtests_json(&tests)).unwrap()).unwrap();
+    assert_eq!(parsed, tests);
+}
+
+#[test]
+fn every_opcode_passes_its_own_records_and_stays_in_ram() {
+    for op in microtest_opcodes() {
+        let tests = generate_microtests(op, 4, 1).unwrap();
+        for t in &tests {
+            assert!(t.initial.ram.iter().chain(&t.final_state.ram).all(|&(a, _)| (0xC000..=0xDFFF).contains(&a) || (0xFF80..=0xFFFE).contains(&a)), "{}", t.name);
+        }
+        for result in run_microtests(&tests).unwrap() { result.unwrap(); }
+    }
+}
+
+#[test]
+fn a_differing_record_is_reported_field_by_field() {
+    // PUSH BC writes B and C below SP
+    let mut t = generate_microtests(0xC5, 1, 3).unwrap().remove(0);
+    let sp = t.initial.regs.sp;
+    let pushed: Vec<u8> = [sp - 2, sp - 1].iter().map(|a| t.final_state.ram.iter().find(|(x, _)| x == a).unwrap().1).collect();
+    assert_eq!(pushed, [t.initial.regs.c, t.initial.regs.b]);
+
+    t.final_state.regs.sp ^= 0x10;
+    t.final_state.ram.retain(|&(a, _)| a != sp - 1);
+    t.final_state.ram.push((sp - 1, t.initial.regs.b ^ 0xFF));
+    let err = run_microtest(&t).unwrap_err();
+    assert!(err.starts_with("c5 0000: sp "), "{err}");
+    assert!(err.contains(&format!("[{:04X}] Some({:02X}), expected {:02X}", sp - 1, t.initial.regs.b, t.initial.regs.b ^ 0xFF)), "{err}");
+
+    let outside = CpuState { ram: vec![(0x8000, 0)], ..t.initial.clone() };
+    assert!(run_microtest(&MicroTest { initial: outside, ..t }).unwrap_err().contains("8000 is outside"));
+}
@@ -0,0 +1,75 @@
+//! Mooneye harness: LD B,B software breakpoint and TestResult
+
+use gb_core::*;
+
+const LD_B_B: u8 = 0x40;
+
+/// Load B,C,D,E,H,L, then LD B,B and spin; the ROM and the LD B,B address
+fn rom(regs: [u8; 6]) -> (Vec<u8>, u16) {
+    let code = Code::new(CODE_START)
+        .ld_bc(u16::from_be_bytes([regs[0], regs[1]]))
+        .ld_de(u16::from_be_bytes([regs[2], regs[3]]))
+        .ld_hl(u16::from_be_bytes([regs[4], regs[5]]));
+    let at = code.here();
+    let code = code.raw(&[LD_B_B]).spin();
+    (RomBuilder::new().title("MOONEYE").code(code.bytes()).build(), at)
+}
+
+#[test]
+fn ld_b_b_stops_the_core_only_when_asked() {
+    let (bytes, at) = rom([0; 6]);
+    let mut core = GbCore::new(Cartridge::from_bytes(bytes).unwrap());
+    core.run_frame().unwrap();
+
+    core.reset();
+    core.ld_b_b_break = true;
+    match core.run_frame() {
+        Err(CoreError::SoftwareBreak { pc }) => assert_eq!(pc, at),
+        other => panic!("{other:?}"),
+    }
+    assert_eq!(core.regs.pc, at + 1, "LD B,B has run");
+    core.run_frame().unwrap();
+}
+
+#[test]
+fn the_fingerprint_at_the_break_is_the_verdict() {
+    let (bytes, at) = rom(MOONEYE_PASS);
+    let pass = run_mooneye_rom("pass.gb", bytes, 10);
+    assert_eq!(pass.outcome, TestOutcome::Pass);
+    assert_eq!((pass.break_pc, pass.registers, pass.frames), (Some(at), MOONEYE_PASS, 1));
+    assert!(pass.t_cycles > 0 && pass.t_cycles < 70224);
+    assert_eq!(pass.summary(), format!("pass at {at:04X} after 1 frames"));
+
+    let fail = run_mooneye_rom("fail.gb", rom(MOONEYE_FAIL).0, 10);
+    assert_eq!(fail.outcome, TestOutcome::Fail("mooneye failure signature".into()));
+    let doc = Json::parse(&fail.to_json()).unwrap();
+    assert_eq!(doc.get("outcome").and_then(Json::as_str), Some("fail"));
+    assert_eq!(doc.get("break_pc").and_then(Json::as_u64), Some(at as u64));
+
+    let odd = run_mooneye_rom("odd.gb", rom([1, 2, 3, 4, 5, 6]).0, 10);
+    assert!(!odd.passed());
+    assert!(odd.summary().ends_with("(B,C,D,E,H,L = 01 02 03 04 05 06)"), "{}", odd.summary());
+
+    let hang = run_mooneye_rom("hang.gb", RomBuilder::new().code(Code::new(CODE_START).spin().bytes()).build(), 3);
+    assert_eq!((hang.outcome, hang.break_pc, hang.frames), (TestOutcome::Timeout, None, 3));
+}
+
+#[test]
+fn the_generic_harness_runs_past_an_unsigned_break() {
+    // LD B,B with no signature, then the pass signature without one
+    let code = Code::new(CODE_START).raw(&[LD_B_B]).ld_bc(0x0305).ld_de(0x080D).ld_hl(0x1522).spin();
+    let run = run_test_rom(RomBuilder::new().code(code.bytes()).build(), 10);
+    assert_eq!(run.outcome, TestOutcome::Pass);
+}
+
+#[test]
+fn file_name_tags_pick_the_model() {
+    assert_eq!(mooneye_model("boot_regs-dmgABC.gb"), HardwareModel::Dmg);
+    assert_eq!(mooneye_model("di_timing-GS.gb"), HardwareModel::Dmg);
+    assert_eq!(mooneye_model("boot_regs-mgb.gb"), HardwareModel::Mgb);
+    assert_eq!(mooneye_model("boot_hwio-S.gb"), HardwareModel::Sgb);
+    assert_eq!(mooneye_model("boot_regs-sgb2.gb"), HardwareModel::Sgb);
+    assert_eq!(mooneye_model("boot_div-cgbABCDE.gb"), HardwareModel::Cgb);
+    assert_eq!(mooneye_model("boot_hwio-C.gb"), HardwareModel::Cgb);
+    assert_eq!(mooneye_model("ie_push.gb"), HardwareModel::Dmg);
+}
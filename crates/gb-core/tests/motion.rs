@@ -0,0 +1,84 @@
+//! Per-frame motion metadata (scroll, window, sprites)
+
+use gb_core::*;
+
+/// Each VBlank: SCX += 1, SCY -= 1 (wrapping from 0), sprite 0 moves (+2, -1)
+const SCROLLER: &str = "
+    call wait_vblank
+    ld a, 80
+    ld [$fe00], a
+    ld a, 40
+    ld [$fe01], a
+    call leave_vblank
+loop:
+    call wait_vblank
+    ldh a, [$43]
+    inc a
+    ldh [$43], a
+    ldh a, [$42]
+    dec a
+    ldh [$42], a
+    ld a, [$fe01]
+    add a, 2
+    ld [$fe01], a
+    ld a, [$fe00]
+    dec a
+    ld [$fe00], a
+    call leave_vblank
+    jr loop
+wait_vblank:
+    ldh a, [$44]
+    cp 144
+    jr nz, wait_vblank
+    ret
+leave_vblank:
+    ldh a, [$44]
+    cp 144
+    jr z, leave_vblank
+    ret
+";
+
+fn core() -> GbCore {
+    GbCore::new(RomBuilder::new().asm(SCROLLER).unwrap().cartridge().unwrap())
+}
+
+#[test]
+fn off_by_default() {
+    let mut c = core();
+    c.run_frame().unwrap();
+    assert!(c.motion.is_none());
+    assert!(c.diagnostics_json().ends_with("\"motion\":null}"));
+}
+
+#[test]
+fn reports_scroll_and_sprite_deltas() {
+    let mut c = core();
+    c.motion = Some(MotionTracker::new());
+    for _ in 0..4 { c.run_frame().unwrap(); }
+    let m = c.motion.as_ref().unwrap().last().unwrap().clone();
+    assert_eq!((m.scroll_dx, m.scroll_dy), (1, -1), "SCY wraps below zero as -1");
+    assert_eq!((m.window_dx, m.window_dy), (0, 0));
+    assert_eq!(m.sprites, vec![SpriteMotion { index: 0, dx: 2, dy: -1 }]);
+    assert!(c.clock.frame_count() - m.frame <= 1, "sampled at the last VBlank");
+}
+
+#[test]
+fn configure_toggles_motion_and_diagnostics_report_it() {
+    let mut c = core();
+    c.configure(r#"{"motion_metadata": true, "unknown": 1}"#).unwrap();
+    assert!(c.motion.is_some());
+    for _ in 0..3 { c.run_frame().unwrap(); }
+    let diag = Json::parse(&c.diagnostics_json()).unwrap();
+    let m = diag.get("motion").unwrap();
+    assert_eq!(m.get("scroll_dx").and_then(Json::as_f64), Some(1.0));
+    let Some(Json::Arr(sprites)) = m.get("sprites") else { panic!("sprites array") };
+    assert_eq!(sprites[0].get("dx").and_then(Json::as_f64), Some(2.0));
+
+    // Turning it on again keeps the tracker; off drops it
+    c.configure(r#"{"motion_metadata": true}"#).unwrap();
+    assert!(c.motion.as_ref().unwrap().last().is_some());
+    c.configure(r#"{"motion_metadata": false}"#).unwrap();
+    assert!(c.motion.is_none());
+    assert!(c.configure(r#"{"motion_metadata": 1}"#).is_err());
+    assert!(c.configure("not json").is_err());
+}
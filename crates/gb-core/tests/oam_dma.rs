@@ -0,0 +1,114 @@
+//! OAM DMA: 160 M-cycle transfer, CPU cut off from everything below FF00
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+/// HRAM: LD A,0xC1 / LDH (0x46),A / 120 NOPs / JR $
+fn dma_from_hram() -> GbCore {
+    let mut core = core_with(&[0x18, 0xFE]);
+    for i in 0..0xA0u16 { core.bus.write(0xC100 + i, i as u8 ^ 0x5A); }
+    core.bus.oam = [0; 0xA0];
+    for (i, b) in [0x3E, 0xC1, 0xE0, 0x46].into_iter().chain([0x00; 120]).chain([0x18, 0xFE]).enumerate() {
+        core.bus.write(0xFF80 + i as u16, b);
+    }
+    core.regs.pc = 0xFF80;
+    core.step().unwrap();
+    core.step().unwrap();
+    core
+}
+
+#[test]
+fn copies_one_byte_per_m_cycle_after_a_start_up_delay() {
+    let mut core = dma_from_hram();
+    assert!(!core.bus.dma.active(), "the M-cycle after the write is the start-up delay");
+    assert_eq!(core.bus.read(0xFF46), 0xC1);
+    let mut m_cycles = 0;
+    for n in 1..=120u64 {
+        m_cycles += core.step().unwrap() as u64 / 4;
+        assert_eq!(core.bus.dma.next as u64, n - 1, "after {n} NOPs");
+    }
+    assert_eq!(core.bus.oam[118], 118 ^ 0x5A);
+    assert_eq!(core.bus.oam[119], 0);
+    while core.bus.dma.active() { m_cycles += core.step().unwrap() as u64 / 4; }
+    assert!((161..=163).contains(&m_cycles), "{m_cycles}");
+    assert!(core.bus.oam.iter().enumerate().all(|(i, &b)| b == i as u8 ^ 0x5A));
+}
+
+#[test]
+fn cpu_only_reaches_io_and_hram_during_the_transfer() {
+    let mut core = dma_from_hram();
+    core.step().unwrap();
+    assert!(core.bus.dma.active());
+    assert_eq!((core.bus.read(0xC100), core.bus.read(0x0100), core.bus.read(0xFE00)), (0xFF, 0xFF, 0xFF));
+    assert_eq!(core.bus.read(0xFF80), 0x3E);
+    core.bus.write(0xC000, 0x12);
+    core.bus.write(0xFFF0, 0x34);
+    assert_eq!((core.bus.peek(0xC000), core.bus.read(0xFFF0)), (0x00, 0x34), "the WRAM write is dropped");
+    assert_eq!(core.bus.peek(0xC100), 0x5A, "peek still sees memory");
+    core.run_cycles(160 * 4).unwrap();
+    assert!(!core.bus.dma.active());
+    assert_eq!(core.bus.read(0xC100), 0x5A);
+}
+
+#[test]
+fn a_blocked_write_is_not_seen_by_watchpoints() {
+    // HRAM: LD A,C1 / LDH (46),A / NOP / LD (C000),A / JR $
+    let mut core = core_with(&[0x18, 0xFE]);
+    for (i, b) in [0x3E, 0xC1, 0xE0, 0x46, 0x00, 0xEA, 0x00, 0xC0, 0x18, 0xFE].into_iter().enumerate() {
+        core.bus.write(0xFF80 + i as u16, b);
+    }
+    core.regs.pc = 0xFF80;
+    let id = core.bus.watchpoints.add_write(0xC000);
+    for _ in 0..3 { core.step().unwrap(); }
+    assert!(core.bus.dma.active());
+    core.step().expect("the write never reached the bus");
+    assert_eq!((core.regs.pc, core.bus.peek(0xC000)), (0xFF88, 0x00));
+    assert_eq!(core.bus.watchpoints.get(id).unwrap().hits, 0);
+}
+
+/// Fill C100-C19F, copy a DMA routine to FF80, call it with the stack in
+/// WRAM, then write 0x99 to C000. The routine waits `wait` loop passes.
+fn game_with_wait(wait: u8) -> GbCore {
+    core_with(&[
+        0x31, 0x00, 0xD0,                         // LD SP,D000
+        0x21, 0x00, 0xC1,                         // LD HL,C100
+        0x7D, 0x22, 0x7D, 0xFE, 0xA0, 0x20, 0xF9, // fill: LD A,L / LD (HL+),A / LD A,L / CP A0 / JR NZ
+        0x0E, 0x80,                               // LD C,80
+        0x21, 0x24, 0x01,                         // LD HL,routine
+        0x2A, 0xE2, 0x0C, 0x79, 0xFE, 0x8A, 0x20, 0xF8, // copy: LD A,(HL+) / LD (C),A / INC C / LD A,C / CP 8A / JR NZ
+        0xCD, 0x80, 0xFF,                         // CALL FF80
+        0x3E, 0x99, 0xEA, 0x00, 0xC0,             // LD A,99 / LD (C000),A
+        0x18, 0xFE,                               // JR $
+        // routine: LD A,C1 / LDH (46),A / LD A,wait / DEC A / JR NZ / RET
+        0x3E, 0xC1, 0xE0, 0x46, 0x3E, wait, 0x3D, 0x20, 0xFD, 0xC9,
+    ])
+}
+
+#[test]
+fn the_hram_wait_routine_returns_after_the_transfer() {
+    let mut core = game_with_wait(40);
+    core.run_frame().unwrap();
+    assert_eq!(core.bus.peek(0xC000), 0x99);
+    assert!(core.bus.oam.iter().enumerate().all(|(i, &b)| b == i as u8));
+}
+
+#[test]
+fn returning_before_the_transfer_ends_pops_garbage() {
+    let mut core = game_with_wait(10);
+    let _ = core.run_frame();
+    assert_ne!(core.bus.peek(0xC000), 0x99, "RET read 0xFFFF from the blocked stack");
+}
+
+#[test]
+fn savestates_keep_a_transfer_in_flight() {
+    let mut core = dma_from_hram();
+    for _ in 0..50 { core.step().unwrap(); }
+    let state = core.save_state();
+    let mut resumed = core_with(&[0x18, 0xFE]);
+    resumed.load_state(&state).unwrap();
+    assert_eq!(resumed.bus.dma, core.bus.dma);
+    resumed.run_cycles(120 * 4).unwrap();
+    assert!(resumed.bus.oam.iter().enumerate().all(|(i, &b)| b == i as u8 ^ 0x5A));
+}
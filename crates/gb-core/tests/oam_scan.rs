@@ -0,0 +1,85 @@
+//! Mode 2 OAM scan: per-line sprite selection and draw priority
+
+use gb_core::*;
+
+/// LCD, BG and sprites on; tile 1 solid colour 3, tile 2 solid colour 1,
+/// identity palettes, OAM cleared
+fn core() -> GbCore {
+    let mut core = GbCore::new(Cartridge::from_bytes(RomBuilder::new().code(&[0x18, 0xFE]).build()).unwrap());
+    for row in 0..8 {
+        core.bus.vram[0][16 + row * 2..16 + row * 2 + 2].copy_from_slice(&[0xFF, 0xFF]);
+        core.bus.vram[0][32 + row * 2..32 + row * 2 + 2].copy_from_slice(&[0xFF, 0x00]);
+    }
+    core.bus.oam = [0; 0xA0];
+    let ppu = &mut core.bus.ppu;
+    (ppu.lcdc, ppu.pal_bg, ppu.pal_obj0) = (0x93, 0xE4, 0xE4);
+    core
+}
+
+fn sprite(core: &mut GbCore, i: usize, y: u8, x: u8, tile: u8) {
+    core.bus.oam[i * 4..i * 4 + 4].copy_from_slice(&[y, x, tile, 0]);
+}
+
+/// Shade at screen (x, 0) after a full frame
+fn pixel(core: &mut GbCore, x: usize) -> u8 {
+    core.run_frame().unwrap();
+    core.run_frame().unwrap();
+    core.bus.ppu.framebuffer[x]
+}
+
+#[test]
+fn off_screen_sprites_use_up_the_line() {
+    let mut core = core();
+    for i in 0..10 { sprite(&mut core, i, 16, 0, 1); }
+    sprite(&mut core, 10, 16, 88, 1);
+    assert_eq!(pixel(&mut core, 80), 0, "ten sprites at X 0 fill the buffer");
+    sprite(&mut core, 0, 100, 0, 1);
+    assert_eq!(pixel(&mut core, 80), 3);
+
+    let scan = OamScan::line(&core.bus.oam, 0, false);
+    assert_eq!(scan.sprites().iter().map(|s| s.index).collect::<Vec<_>>(), [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    assert_eq!(scan.sprites()[9], ScannedSprite { index: 10, y: 16, x: 88 });
+}
+
+#[test]
+fn lower_x_then_lower_index_wins() {
+    let mut core = core();
+    sprite(&mut core, 0, 16, 12, 2);
+    sprite(&mut core, 1, 16, 8, 1);
+    assert_eq!(pixel(&mut core, 5), 3, "the sprite further left is on top");
+    sprite(&mut core, 1, 16, 12, 1);
+    assert_eq!(pixel(&mut core, 5), 1, "same X: OAM entry 0 is on top");
+}
+
+#[test]
+fn the_scan_walks_oam_two_dots_per_entry() {
+    let mut oam = [0u8; 0xA0];
+    oam[12 * 4] = 16;
+    let mut scan = OamScan::new();
+    scan.run(&oam, 0, false, 20);
+    assert_eq!((scan.next, scan.sprites().len()), (10, 0));
+    // Entries not yet reached are read as they are when the scan gets there
+    oam[12 * 4] = 0;
+    oam[30 * 4] = 9;
+    scan.run(&oam, 0, true, 80);
+    assert!(scan.done());
+    assert_eq!(scan.sprites(), [ScannedSprite { index: 30, y: 9, x: 0 }], "8x16: Y 9 covers line 0");
+    scan.restart();
+    assert!(scan.sprites().is_empty() && !scan.done());
+}
+
+#[test]
+fn the_scan_survives_a_state_saved_mid_line() {
+    let mut core = core();
+    sprite(&mut core, 3, 16, 88, 1);
+    core.run_frame().unwrap();
+    while core.bus.ppu.mode != PpuMode::Drawing { core.step().unwrap(); }
+    // The selection is made: moving the sprite now does not change this line
+    sprite(&mut core, 3, 100, 88, 1);
+    let state = core.save_state();
+    let mut loaded = self::core();
+    loaded.load_state(&state).unwrap();
+    assert_eq!(loaded.bus.ppu.oam_scan, core.bus.ppu.oam_scan);
+    while loaded.bus.ppu.mode != PpuMode::HBlank { loaded.step().unwrap(); }
+    assert_eq!(loaded.bus.ppu.framebuffer[80], 3);
+}
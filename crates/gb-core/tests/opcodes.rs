@@ -0,0 +1,73 @@
+//! The opcode table: disassembly and executed cycle counts
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+#[test]
+fn illegal_entries_match_the_illegal_opcode_list() {
+    let illegal: Vec<u8> = (0..=255u8).filter(|&op| OPCODES[op as usize].is_illegal()).collect();
+    assert_eq!(illegal, ILLEGAL_OPCODES);
+    assert_eq!(disassemble(&[0xD3], 0), ("db $d3".to_string(), 1));
+}
+
+#[test]
+fn disassembly_assembles_back_to_the_same_bytes() {
+    for operands in [[0x00, 0x00], [0xF0, 0xC1], [0x12, 0x34]] {
+        for op in (0..=255u8).filter(|&op| !OPCODES[op as usize].is_illegal()) {
+            let bytes = [op, operands[0], operands[1]];
+            let (text, len) = disassemble(&bytes, 0x0150);
+            assert_eq!(len, OPCODES[op as usize].len, "{text}");
+            let asm = assemble_at(&text, 0x0150).unwrap_or_else(|e| panic!("{op:02X} {text}: {e}"));
+            let want = if op == 0x10 { vec![0x10, 0x00] } else { bytes[..len as usize].to_vec() };
+            assert_eq!(asm.flatten().1, want, "{op:02X} {text}");
+        }
+    }
+}
+
+#[test]
+fn operands_render_by_kind() {
+    assert_eq!(disassemble(&[0x18, 0xFE], 0x0200).0, "jr $0200");
+    assert_eq!(disassemble(&[0x20, 0x05], 0x0200).0, "jr nz, $0207");
+    assert_eq!(disassemble(&[0xE0, 0x46], 0).0, "ldh [$ff46], a");
+    assert_eq!(disassemble(&[0xFA, 0x00, 0xC0], 0).0, "ld a, [$c000]");
+    assert_eq!(disassemble(&[0xF8, 0xFD], 0).0, "ld hl, sp-3");
+    assert_eq!(disassemble(&[0xE8, 0x04], 0).0, "add sp, 4");
+    assert_eq!(disassemble(&[0xCB, 0x7E], 0), ("bit 7, [hl]".to_string(), 2));
+
+    let core = core_with(&[0x3E, 0x42, 0xCB, 0x37, 0xC3, 0x00, 0x01]);
+    let listing = disassemble_range(|a| core.bus.peek(a), 0x0100, 3);
+    assert_eq!(listing[1], (0x0102, vec![0xCB, 0x37], "swap a".to_string()));
+    assert_eq!(listing[2].0, 0x0104);
+    assert_eq!(listing[2].2, "jp $0100");
+}
+
+/// Run `op` once from WRAM with flags `f`; returns the cycles taken
+fn run_once(op: u8, f: u8) -> u8 {
+    let mut core = core_with(&[]);
+    for (i, b) in [op, 0x00, 0xC1].into_iter().enumerate() { core.bus.write(0xC000 + i as u16, b); }
+    core.regs.pc = 0xC000;
+    core.regs.set_hl(0xC800);
+    core.regs.sp = 0xD000;
+    core.regs.f = f;
+    core.step().unwrap()
+}
+
+#[test]
+fn executed_cycles_match_the_table() {
+    for op in (0..=255u8).filter(|&op| !OPCODES[op as usize].is_illegal() && !matches!(op, 0x10 | 0x76 | 0xCB)) {
+        let info = OPCODES[op as usize];
+        let seen = [run_once(op, 0x00), run_once(op, 0xF0)];
+        if info.is_conditional() {
+            assert!(seen.contains(&info.cycles) && seen.contains(&info.cycles_taken), "{}: {seen:?}", info.mnemonic);
+        } else {
+            assert_eq!(seen, [info.cycles; 2], "{}", info.mnemonic);
+        }
+    }
+    for cb in 0..=255u8 {
+        let mut core = core_with(&[0xCB, cb]);
+        core.regs.set_hl(0xC800);
+        assert_eq!(core.step().unwrap(), cb_cycles(cb), "{}", cb_mnemonic(cb));
+    }
+}
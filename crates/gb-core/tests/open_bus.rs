@@ -0,0 +1,53 @@
+//! Open-bus policies for FEA0-FEFF and unmapped IO
+
+use gb_core::*;
+
+fn bus(policy: OpenBusPolicy) -> Bus {
+    let mut bus = Bus::new(Cartridge::from_bytes(RomBuilder::new().code(&[0x18, 0xFE]).build()).unwrap());
+    bus.open_bus = policy;
+    bus
+}
+
+#[test]
+fn all_ff_is_the_default() {
+    let b = bus(OpenBusPolicy::default());
+    assert_eq!(b.open_bus, OpenBusPolicy::AllFF);
+    for addr in [0xFEA0, 0xFEC7, 0xFEFF, 0xFF08, 0xFF4C, 0xFF7F] {
+        assert_eq!(b.read(addr), 0xFF, "{addr:04X}");
+    }
+}
+
+#[test]
+fn last_value_returns_what_the_bus_last_carried() {
+    let mut b = bus(OpenBusPolicy::LastValue);
+    b.write(0xC000, 0x5A);
+    assert_eq!(b.read(0xFEB0), 0x5A);
+    b.write(0xC001, 0x3C);
+    assert_eq!(b.read(0xC000), 0x5A, "a real read drives the bus");
+    assert_eq!(b.read(0xFF4C), 0x5A);
+    b.write(0xFEA0, 0x77);
+    assert_eq!(b.peek(0xFF71), 0x77, "writes are dropped but still drive the bus");
+}
+
+#[test]
+fn cgb_behavior_mirrors_the_address_nibble_unless_ppu_holds_oam() {
+    let mut b = bus(OpenBusPolicy::CgbBehavior);
+    b.ppu.lcdc = 0x00;
+    assert_eq!(b.read(0xFEA5), 0xAA);
+    assert_eq!(b.read(0xFEF0), 0xFF);
+    assert_eq!(b.read(0xFEC3), 0xCC);
+    assert_eq!(b.read(0xFF4C), 0xFF, "unmapped IO");
+    b.ppu.lcdc = 0x80;
+    b.ppu.mode = PpuMode::OamScan;
+    assert_eq!(b.read(0xFEA5), 0xFF);
+    b.ppu.mode = PpuMode::HBlank;
+    assert_eq!(b.read(0xFEA5), 0xAA);
+}
+
+#[test]
+fn policies_parse_from_their_names() {
+    for p in [OpenBusPolicy::AllFF, OpenBusPolicy::LastValue, OpenBusPolicy::CgbBehavior] {
+        assert_eq!(OpenBusPolicy::parse(p.as_str()), Some(p));
+    }
+    assert_eq!(OpenBusPolicy::parse("zero"), None);
+}
@@ -0,0 +1,99 @@
+//! Annotation overlay composited over framebuffer_rgb
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+const RED: Rgba = [255, 0, 0, 255];
+
+fn rgb_at(rgb: &[u8], x: usize, y: usize) -> [u8; 3] {
+    let i = (y * LCD_WIDTH + x) * 3;
+    [rgb[i], rgb[i + 1], rgb[i + 2]]
+}
+
+#[test]
+fn annotations_blend_over_the_frame_and_leave_the_machine_alone() {
+    let mut core = core_with(&[0x18, 0xFE]);
+    core.run_frame().unwrap();
+    let (plain, hash) = (core.framebuffer_rgb(), core.state_hash());
+    assert_eq!(core.framebuffer_rgb_annotated(), plain, "no overlay");
+
+    let mut overlay = Overlay::new();
+    overlay.fill_rect(10, 10, 2, 1, RED);
+    overlay.set(20, 20, [0, 0, 255, 128]);
+    core.overlay = Some(Box::new(overlay));
+    let shown = core.framebuffer_rgb_annotated();
+    assert_eq!(rgb_at(&shown, 10, 10), [255, 0, 0]);
+    assert_eq!(rgb_at(&shown, 11, 10), [255, 0, 0]);
+    let under = rgb_at(&plain, 20, 20);
+    let half = |c: u8, src: u32| ((src * 128 + c as u32 * 127 + 127) / 255) as u8;
+    assert_eq!(rgb_at(&shown, 20, 20), [half(under[0], 0), half(under[1], 0), half(under[2], 255)]);
+    assert_eq!(rgb_at(&shown, 12, 10), rgb_at(&plain, 12, 10));
+
+    assert_eq!(core.framebuffer_rgb(), plain);
+    assert_eq!(core.state_hash(), hash);
+    core.run_frame().unwrap();
+    assert_eq!(rgb_at(&core.framebuffer_rgb_annotated(), 10, 10), [255, 0, 0], "kept until cleared");
+    core.overlay.as_mut().unwrap().clear();
+    assert!(core.overlay.as_ref().unwrap().is_empty());
+    assert_eq!(core.framebuffer_rgb_annotated(), core.framebuffer_rgb());
+}
+
+#[test]
+fn drawing_is_clipped_to_the_screen() {
+    let mut overlay = Overlay::new();
+    overlay.rect(-4, -4, 8, 8, RED);
+    overlay.fill_rect(150, 140, 100, 100, RED);
+    overlay.text(158, 142, "WIDE TEXT", RED);
+    assert_eq!(overlay.pixel(3, 0), Some(RED));
+    assert_eq!(overlay.pixel(0, 3), Some(RED));
+    assert_eq!(overlay.pixel(2, 2), Some([0; 4]), "outline only");
+    assert_eq!(overlay.pixel(159, 143), Some(RED));
+    assert_eq!(overlay.pixel(160, 0), None);
+    assert_eq!(overlay.rgba().len(), LCD_WIDTH * LCD_HEIGHT * 4);
+}
+
+#[test]
+fn text_uses_the_built_in_font() {
+    let mut overlay = Overlay::new();
+    assert_eq!(overlay.text(0, 0, "hi\n1", RED), 2 * OVERLAY_CHAR_W);
+    let lit = |o: &Overlay, x, y| o.pixel(x, y) == Some(RED);
+    // H: both sides lit on every row, the middle only on the bar
+    assert!((0..5).all(|y| lit(&overlay, 0, y) && lit(&overlay, 2, y)));
+    assert_eq!((0..5).filter(|&y| lit(&overlay, 1, y)).count(), 1);
+    // I: full top and bottom bars
+    assert!((4..7).all(|x| lit(&overlay, x, 0) && lit(&overlay, x, 4)));
+    // 1 on the second line
+    assert!(lit(&overlay, 1, OVERLAY_LINE_H));
+    assert!(!lit(&overlay, 3, 0), "gap between characters");
+
+    let mut other = Overlay::new();
+    other.text(0, 0, "HI\n1", RED);
+    assert_eq!(other, overlay, "letters are drawn upper case");
+}
+
+#[test]
+fn sprites_and_watched_values_get_labels() {
+    let mut core = core_with(&[0x18, 0xFE]);
+    core.bus.oam[..4].copy_from_slice(&[16 + 40, 8 + 30, 0x01, 0x00]);
+    core.bus.ppu.lcdc |= 0x82;
+    let sprites = visible_sprites(&core.bus);
+    assert_eq!(sprites.len(), 1);
+
+    let mut overlay = Overlay::new();
+    overlay.sprite_boxes(&sprites, RED);
+    assert_eq!(overlay.pixel(30, 40), Some(RED));
+    assert_eq!(overlay.pixel(37, 47), Some(RED));
+    assert_eq!(overlay.pixel(33, 43), Some([0; 4]));
+    assert!((34..40).any(|y| overlay.pixel(30, y) == Some(RED)), "index above the box");
+
+    let (fg, bg) = ([255; 4], [0, 0, 0, 160]);
+    overlay.clear();
+    overlay.watch_values(&[("hp", 0x1F), ("x", 0x0123)], fg, bg);
+    assert_eq!(overlay.pixel(0, 0), Some(bg));
+    // "HP=1F" / "X=123": five characters wide, two lines high
+    assert_eq!(overlay.pixel(5 * OVERLAY_CHAR_W, 2 * OVERLAY_LINE_H), Some(bg));
+    assert_eq!(overlay.pixel(5 * OVERLAY_CHAR_W + 1, 0), Some([0; 4]));
+    assert_eq!(overlay.pixel(1, 1), Some(fg), "top-left of H");
+}
@@ -0,0 +1,84 @@
+//! Per-game DMG palette packs (mrom.palettes.v1)
+
+use gb_core::*;
+
+const WARM: DmgPalette = [(255, 240, 224), (200, 150, 100), (120, 70, 40), (30, 10, 0)];
+const COOL: DmgPalette = [(224, 240, 255), (100, 150, 200), (40, 70, 120), (0, 10, 30)];
+const LIME: DmgPalette = [(240, 255, 224), (150, 200, 100), (70, 120, 40), (10, 30, 0)];
+
+fn spinning(title: &str) -> Vec<u8> { RomBuilder::new().title(title).code(&[0x18, 0xFE]).build() }
+
+/// Blank BG with one solid 8x8 sprite (colour 3) at the top-left, using OBP1 if `obp1`
+fn core_with_sprite(rom: Vec<u8>, obp1: bool) -> GbCore {
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    core.bus.vram[0][0x10..0x20].fill(0xFF);
+    core.bus.oam[..4].copy_from_slice(&[16, 8, 1, if obp1 { 0x10 } else { 0x00 }]);
+    core.bus.ppu.lcdc |= 0x02;
+    core
+}
+
+fn pixel(rgb: &[u8], x: usize, y: usize) -> (u8, u8, u8) {
+    let i = (y * LCD_WIDTH + x) * 3;
+    (rgb[i], rgb[i + 1], rgb[i + 2])
+}
+
+#[test]
+fn split_scheme_colours_sprites_by_obj_palette() {
+    let colors = DmgColors { bg: WARM, obj0: COOL, obj1: LIME };
+    for (obp1, want) in [(false, COOL[3]), (true, LIME[3])] {
+        let mut core = core_with_sprite(spinning("SPLIT"), obp1);
+        core.dmg_colors = colors;
+        for _ in 0..2 { core.run_frame().unwrap(); }
+        let rgb = core.framebuffer_rgb();
+        assert_eq!(pixel(&rgb, 0, 0), want, "sprite pixel, obp1 = {obp1}");
+        assert_eq!(pixel(&rgb, 20, 20), WARM[0], "background pixel");
+    }
+}
+
+#[test]
+fn hash_entry_wins_over_title_and_builtin_covers_popular_titles() {
+    let rom = spinning("TETRIS");
+    let builtin = PalettePack::builtin();
+    let tetris = builtin.lookup(&rom_hash(&rom), "TETRIS").expect("built-in entry");
+    assert_ne!(tetris.bg, DMG_GREYSCALE);
+    assert_eq!(builtin.lookup("00000000", "UNKNOWN GAME"), None);
+
+    let mut user = PalettePack::default();
+    user.set_game(&rom_hash(&rom), "TETRIS", DmgColors::uniform(LIME));
+    let mut pack = PalettePack::builtin();
+    pack.merge(&user);
+    let cart = Cartridge::from_bytes(rom.clone()).unwrap();
+    assert_eq!(pack.for_cartridge(&cart), Some(DmgColors::uniform(LIME)));
+
+    let mut core = GbCore::new(cart);
+    assert!(pack.apply(&mut core));
+    assert_eq!(core.dmg_colors, DmgColors::uniform(LIME));
+    let mut other = GbCore::new(Cartridge::from_bytes(spinning("OTHER")).unwrap());
+    assert!(!pack.apply(&mut other));
+    assert_eq!(other.dmg_colors, DmgColors::default());
+}
+
+#[test]
+fn json_round_trip_with_4_and_12_colour_schemes() {
+    let mut pack = PalettePack::default();
+    pack.set_game("1234abcd", "SOME \"GAME\"", DmgColors { bg: WARM, obj0: COOL, obj1: LIME });
+    pack.set_title("OTHER", DmgColors::uniform(COOL));
+    let text = pack.to_json();
+    assert_eq!(PalettePack::from_json(&text).unwrap(), pack);
+
+    let doc = Json::parse(&text).unwrap();
+    assert_eq!(doc.get("version").and_then(Json::as_str), Some(PALETTE_PACK_VERSION));
+    let colours = |d: Option<&Json>| d.and_then(Json::as_array).map(|a| a.len());
+    assert_eq!(colours(doc.get("games").and_then(|g| g.get("1234abcd")).and_then(|g| g.get("colors"))), Some(12));
+    assert_eq!(colours(doc.get("titles").and_then(|t| t.get("OTHER"))), Some(4));
+}
+
+#[test]
+fn bad_packs_are_errors() {
+    let v = PALETTE_PACK_VERSION;
+    assert!(PalettePack::from_json(r#"{"version":"mrom.palettes.v0"}"#).is_err());
+    assert!(PalettePack::from_json(&format!(r##"{{"version":"{v}","titles":{{"X":["#000000","#111111","#222222"]}}}}"##)).is_err());
+    assert!(PalettePack::from_json(&format!(r##"{{"version":"{v}","titles":{{"X":["red","#111111","#222222","#333333"]}}}}"##)).is_err());
+    assert!(PalettePack::from_json(&format!(r#"{{"version":"{v}","games":{{"1234abcd":{{"title":"X"}}}}}}"#)).is_err());
+    assert!(PalettePack::from_json(&format!(r#"{{"version":"{v}"}}"#)).unwrap().is_empty());
+}
@@ -0,0 +1,26 @@
+//! Perceptual frame hashes
+
+use gb_core::{phash, phash_distance, LCD_HEIGHT, LCD_WIDTH};
+
+/// Blocky pseudo-random scene, 16x16-pixel tiles of shades 0-3
+fn scene() -> Vec<u8> {
+    (0..LCD_WIDTH * LCD_HEIGHT)
+        .map(|i| {
+            let (tx, ty) = ((i % LCD_WIDTH) / 16, (i / LCD_WIDTH) / 16);
+            ((tx * 7 + ty * 13 + tx * ty) % 4) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn similar_frames_hash_close_and_different_frames_far() {
+    let base = phash(&scene());
+    assert_eq!(base, phash(&scene()));
+
+    let mut speck = scene();
+    speck[LCD_WIDTH * 70 + 80] ^= 3;
+    assert!(phash_distance(base, phash(&speck)) <= 4);
+
+    let inverted: Vec<u8> = scene().iter().map(|p| 3 - p).collect();
+    assert!(phash_distance(base, phash(&inverted)) > 16);
+}
@@ -0,0 +1,123 @@
+//! Pixel FIFO: dot-by-dot mode 3, its length and mid-line register writes
+
+use gb_core::*;
+
+/// Tiles 0-7 with a different pattern on every row, a BG map and a window
+/// map mixing them, identity palettes
+fn vram() -> [u8; 0x2000] {
+    let mut vram = [0u8; 0x2000];
+    for (i, b) in vram[..8 * 16].iter_mut().enumerate() {
+        *b = (i as u8).wrapping_mul(37) ^ (i as u8 >> 3);
+    }
+    for i in 0..0x400 {
+        vram[0x1800 + i] = (i * 5 + i / 32) as u8 & 7;
+        vram[0x1C00 + i] = (i * 3 + 1) as u8 & 7;
+    }
+    vram
+}
+
+fn ppu(fifo: bool) -> Ppu {
+    let mut ppu = Ppu::new();
+    (ppu.lcdc, ppu.pal_bg, ppu.pal_obj0, ppu.pal_obj1) = (0x93, 0xE4, 0xE4, 0x1B);
+    if fifo { ppu.fifo = Some(Box::default()); }
+    ppu
+}
+
+fn sprite(oam: &mut [u8; 0xA0], i: usize, y: u8, x: u8, tile: u8, flags: u8) {
+    oam[i * 4..i * 4 + 4].copy_from_slice(&[y, x, tile, flags]);
+}
+
+/// Step a dot at a time until line `ly` enters HBlank; returns its mode 3 length
+fn run_to_hblank(ppu: &mut Ppu, vram: &[u8; 0x2000], oam: &[u8; 0xA0], ly: u8) -> u32 {
+    while !(ppu.ly == ly && ppu.mode == PpuMode::HBlank) { ppu.step(1, vram, oam); }
+    ppu.mode3_dots
+}
+
+#[test]
+fn static_frames_match_the_line_renderer() {
+    let vram = vram();
+    let mut oam = [0u8; 0xA0];
+    sprite(&mut oam, 0, 30, 20, 3, 0x00);
+    sprite(&mut oam, 1, 34, 24, 5, 0x30);
+    sprite(&mut oam, 2, 60, 3, 1, 0x80);
+    sprite(&mut oam, 3, 90, 150, 6, 0x40);
+    sprite(&mut oam, 4, 120, 80, 2, 0x10);
+    for (scx, scy, wx, wy, lcdc) in [(0, 0, 0, 144, 0x93), (5, 9, 87, 50, 0xF3), (250, 200, 3, 20, 0xB7), (0, 0, 40, 0, 0xE2)] {
+        let [mut line, mut fifo] = [ppu(false), ppu(true)];
+        for p in [&mut line, &mut fifo] {
+            (p.scx, p.scy, p.wx, p.wy, p.lcdc) = (scx, scy, wx, wy, lcdc);
+            while !p.frame_ready { p.step(4, &vram, &oam); }
+        }
+        assert_eq!(fifo.framebuffer, line.framebuffer, "SCX {scx} SCY {scy} WX {wx} WY {wy} LCDC {lcdc:02x}");
+        assert_eq!(fifo.pixel_source, line.pixel_source);
+        assert_eq!(fifo.wlc, line.wlc);
+    }
+}
+
+#[test]
+fn mode_3_grows_with_fine_scroll_sprites_and_the_window() {
+    let vram = vram();
+    let oam = [0u8; 0xA0];
+    let mode3 = |set: &dyn Fn(&mut Ppu), oam: &[u8; 0xA0]| {
+        let mut p = ppu(true);
+        set(&mut p);
+        run_to_hblank(&mut p, &vram, oam, 2)
+    };
+    assert_eq!(mode3(&|_| {}, &oam), 172);
+    assert_eq!(mode3(&|p| p.scx = 3, &oam), 175);
+    assert_eq!(mode3(&|p| p.scx = 8, &oam), 172);
+    assert_eq!(mode3(&|p| (p.lcdc, p.wx) = (0xB3, 87), &oam), 178, "window restart: 6 dots");
+
+    // A sprite costs 6 dots plus the wait for the fetcher: 11 at X 0 or 8, 6 at X 5 mod 8
+    for (x, len) in [(0, 183), (8, 183), (13, 178), (14, 178), (15, 178), (17, 182)] {
+        let mut oam = [0u8; 0xA0];
+        sprite(&mut oam, 0, 16, x, 1, 0);
+        assert_eq!(mode3(&|_| {}, &oam), len, "sprite at X {x}");
+    }
+    let mut oam = [0u8; 0xA0];
+    for i in 0..10 { sprite(&mut oam, i, 16, 50, 1, 0); }
+    assert_eq!(mode3(&|p| p.lcdc = 0x91, &oam), 172, "sprites off: no fetches");
+    assert!(mode3(&|_| {}, &oam) > 172 + 10 * 6 - 1);
+}
+
+#[test]
+fn hblank_absorbs_the_longer_mode_3() {
+    let vram = vram();
+    let mut oam = [0u8; 0xA0];
+    for i in 0..10 { sprite(&mut oam, i, 16 + i as u8 * 8, 10 + i as u8 * 15, 1, 0); }
+    let mut p = ppu(true);
+    p.scx = 7;
+    let mut dots = 0u32;
+    while !p.frame_ready { p.step(1, &vram, &oam); dots += 1; }
+    let frame_start = dots;
+    while p.frame_ready { p.step(1, &vram, &oam); dots += 1; }
+    while !p.frame_ready { p.step(1, &vram, &oam); dots += 1; }
+    assert_eq!(dots - frame_start, 70224);
+    assert_eq!(p.dots_to_next_mode(), Some(456));
+}
+
+#[test]
+fn mid_line_writes_change_the_rest_of_the_line() {
+    let vram = vram();
+    let oam = [0u8; 0xA0];
+    let mut p = ppu(true);
+    while !(p.ly == 5 && p.mode == PpuMode::Drawing && p.fifo.as_ref().unwrap().lx() == 80) { p.step(1, &vram, &oam); }
+    p.pal_bg = 0x1B;
+    run_to_hblank(&mut p, &vram, &oam, 5);
+    let mut line = ppu(false);
+    run_to_hblank(&mut line, &vram, &oam, 5);
+    let row = 5 * LCD_WIDTH;
+    let inverted: Vec<u8> = line.framebuffer[row + 80..row + 160].iter().map(|s| 3 - s).collect();
+    assert_eq!(p.framebuffer[row..row + 80], line.framebuffer[row..row + 80], "pixels before the write keep BGP 0xE4");
+    assert_eq!(p.framebuffer[row + 80..row + 160], inverted[..], "pixels after it use BGP 0x1B");
+
+    // A state loaded mid-line finishes the line with the line renderer
+    let mut core = GbCore::new(Cartridge::from_bytes(RomBuilder::new().code(&[0x18, 0xFE]).build()).unwrap());
+    core.set_accuracy(AccuracyProfile::Cycle);
+    while core.bus.ppu.mode != PpuMode::Drawing { core.step().unwrap(); }
+    let state = core.save_state();
+    core.load_state(&state).unwrap();
+    assert!(!core.bus.ppu.fifo.as_ref().unwrap().active());
+    while core.bus.ppu.mode != PpuMode::HBlank { core.step().unwrap(); }
+    assert_eq!(core.bus.ppu.mode3_dots, 172);
+}
@@ -0,0 +1,76 @@
+//! Per-scanline PPU mode timeline
+
+use gb_core::*;
+
+fn recording() -> GbCore {
+    let rom = RomBuilder::new().title("TIMELINE").code(&[0x18, 0xFE]).build();
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    core.bus.ppu_timeline = Some(Box::default());
+    core
+}
+
+fn last(core: &GbCore) -> Option<FrameTimeline> {
+    core.bus.ppu_timeline.as_ref().and_then(|t| t.last_frame()).cloned()
+}
+
+#[test]
+fn records_mode_lengths_per_line() {
+    let mut core = recording();
+    for _ in 0..3 { core.run_frame().unwrap(); }
+    let frame = last(&core).expect("a whole frame");
+    assert_eq!(frame.lines.len(), LCD_HEIGHT);
+    assert_eq!(frame.dots() as u64, CYCLES_PER_FRAME);
+    assert_eq!(frame.vblank, DOTS_PER_LINE * (SCANLINES - PPU_VBLANK_LINE));
+    for (i, l) in frame.lines.iter().enumerate() {
+        assert_eq!(l.ly as usize, i);
+        assert_eq!(l.start, i as u32 * DOTS_PER_LINE, "line {i}");
+        assert_eq!((l.oam, l.draw, l.hblank), (PPU_MODE2_CYCLES, PPU_MODE3_CYCLES, PPU_MODE0_CYCLES), "line {i}");
+    }
+}
+
+#[test]
+fn lcd_off_drops_the_frame_in_progress() {
+    let mut core = recording();
+    core.run_frame().unwrap();
+    assert!(last(&core).is_none(), "recording starts at the next frame start");
+    core.run_frame().unwrap();
+    let seen = last(&core).unwrap().frame;
+
+    core.run_scanline().unwrap();
+    core.bus.ppu.lcdc &= !0x80;
+    core.run_frame().unwrap();
+    core.bus.ppu.lcdc |= 0x80;
+    core.run_frame().unwrap();
+    assert_eq!(last(&core).unwrap().frame, seen, "no frame is made of the pieces either side");
+}
+
+#[test]
+fn json_round_trip_and_comparison() {
+    let mut core = recording();
+    for _ in 0..2 { core.run_frame().unwrap(); }
+    let frame = last(&core).unwrap();
+    let json = frame.to_json();
+    assert!(json.starts_with(r#"{"version":"mrom.ppu_timeline.v1","frame":0,"dots":70224,"vblank":4560,"lines":[{"ly":0,"start":0,"oam":80,"#), "{json}");
+    let back = FrameTimeline::from_json(&json).unwrap();
+    assert_eq!(back, frame);
+    assert!(frame.mismatches(&back).is_empty());
+
+    let mut hardware = back.clone();
+    hardware.lines[10].draw += 12;
+    hardware.lines[10].hblank -= 12;
+    hardware.lines.pop();
+    assert_eq!(frame.mismatches(&hardware), [10, 143]);
+
+    assert!(FrameTimeline::from_json(r#"{"version":"mrom.ppu_timeline.v0","lines":[]}"#).is_err());
+    assert!(FrameTimeline::from_json(r#"{"version":"mrom.ppu_timeline.v1","vblank":0,"lines":[{"ly":0}]}"#).unwrap_err().contains("start"));
+}
+
+#[test]
+fn svg_has_a_row_per_line() {
+    let mut core = recording();
+    for _ in 0..2 { core.run_frame().unwrap(); }
+    let svg = last(&core).unwrap().to_svg();
+    assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>"));
+    assert_eq!(svg.matches("<rect").count(), LCD_HEIGHT * 3 + 1);
+    assert!(svg.contains(r#"height="308""#), "154 rows of 2 px");
+}
@@ -0,0 +1,49 @@
+//! Per-opcode / per-address execution profiler (feature `profile`)
+#![cfg(feature = "profile")]
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+/// LD A,0x07 / loop: SWAP A / JR loop
+const PROG: [u8; 6] = [0x3E, 0x07, 0xCB, 0x37, 0x18, 0xFC];
+
+#[test]
+fn off_by_default() {
+    let mut core = core_with(&PROG);
+    core.step().unwrap();
+    assert!(core.profiler.is_none());
+    assert_eq!(core.profile_json(), None);
+}
+
+#[test]
+fn counts_opcodes_and_sites() {
+    let mut core = core_with(&PROG);
+    core.profiler = Some(Box::default());
+    for _ in 0..21 { core.step().unwrap(); }
+    let p = core.profiler.as_ref().unwrap();
+
+    assert_eq!((p.opcode(0x3E, false).count, p.opcode(0x3E, false).cycles), (1, 8));
+    assert_eq!((p.opcode(0x37, true).count, p.opcode(0x37, true).cycles), (10, 80));
+    assert_eq!((p.opcode(0x18, false).count, p.opcode(0x18, false).cycles), (10, 120));
+    assert_eq!(p.opcode(0x37, false).count, 0, "CB opcodes are kept apart");
+    assert_eq!(p.site(0, 0x0104).count, 10);
+    assert_eq!((p.total().count, p.total().cycles), (21, 208));
+    assert_eq!(p.hot_sites(1)[0].0, (0, 0x0104), "JR costs the most cycles");
+}
+
+#[test]
+fn json_lists_opcodes_by_count_and_hot_sites() {
+    let mut core = core_with(&PROG);
+    core.profiler = Some(Box::default());
+    for _ in 0..21 { core.step().unwrap(); }
+    let doc = Json::parse(&core.profile_json().unwrap()).unwrap();
+    assert_eq!(doc.get("instructions").and_then(Json::as_u64), Some(21));
+    let ops = doc.get("opcodes").and_then(Json::as_array).unwrap();
+    let names: Vec<&str> = ops.iter().filter_map(|o| o.get("op").and_then(Json::as_str)).collect();
+    assert_eq!(names, ["18", "CB 37", "3E"], "ties by opcode");
+    let hot = doc.get("hot").and_then(Json::as_array).unwrap();
+    assert_eq!(hot.len(), 3);
+    assert_eq!(hot[0].get("addr").and_then(Json::as_u64), Some(0x0104));
+}
@@ -0,0 +1,98 @@
+//! Per-scanline scroll / window register capture
+
+use gb_core::*;
+
+/// VBlank sets SCX 0 / SCY 16, an LYC interrupt at line 64 sets SCX 32:
+/// a two-band raster split
+const SPLIT: &str = "
+        ld a, 64
+        ldh [$45], a
+        ld a, $40
+        ldh [$41], a
+        ld a, $03
+        ldh [$ff], a
+        ei
+    loop:
+        halt
+        jr loop
+        org $40
+        xor a
+        ldh [$43], a
+        ld a, 16
+        ldh [$42], a
+        reti
+        org $48
+        ld a, 32
+        ldh [$43], a
+        reti
+";
+
+fn core(fifo: bool) -> GbCore {
+    let mut core = GbCore::new(RomBuilder::new().asm(SPLIT).unwrap().cartridge().unwrap());
+    core.bus.raster = Some(Box::default());
+    if fifo { core.bus.ppu.fifo = Some(Box::default()); }
+    core
+}
+
+fn until_vblank(core: &mut GbCore) {
+    while core.bus.ppu.mode == PpuMode::VBlank { core.step().unwrap(); }
+    while core.bus.ppu.mode != PpuMode::VBlank { core.step().unwrap(); }
+}
+
+#[test]
+fn lines_record_the_registers_they_were_drawn_with() {
+    let mut core = core(false);
+    assert!(core.bus.raster.as_ref().unwrap().last_frame().is_empty());
+    for _ in 0..3 { core.run_frame().unwrap(); }
+    let lines = core.bus.raster.as_ref().unwrap().last_frame().to_vec();
+    assert_eq!(lines.len(), LCD_HEIGHT);
+    assert!(lines.iter().enumerate().all(|(i, l)| l.ly as usize == i && l.scy == 16 && !l.window && !l.mid_line));
+    assert!(lines[..64].iter().all(|l| l.scx == 0) && lines[64..].iter().all(|l| l.scx == 32));
+
+    let json = raster_json(&lines);
+    assert_eq!(json, "[[0,0,16,0,0,0,0],[64,32,16,0,0,0,0]]", "one run per band");
+    assert_eq!(raster_from_json(&Json::parse(&json).unwrap()).unwrap(), lines);
+    for bad in ["[[1,0,0,0,0,0,0]]", "[[0,0,0,0,0,0]]", "[[0,0,0,0,0,0,0],[0,1,0,0,0,0,0]]", "[[0,256,0,0,0,0,0]]"] {
+        assert!(raster_from_json(&Json::parse(bad).unwrap()).is_err(), "{bad}");
+    }
+
+    // The same lines through the pixel FIFO
+    let mut fifo = self::core(true);
+    for _ in 0..3 { fifo.run_frame().unwrap(); }
+    assert_eq!(fifo.bus.raster.as_ref().unwrap().last_frame(), &lines[..]);
+
+    // Replay frames carry them as "rs"
+    let mut replay = ReplayCapture::new(4, "RASTER");
+    replay.capture(&core);
+    assert!(replay.to_json().contains(&format!("\"rs\":{json},")));
+}
+
+#[test]
+fn writes_during_mode_3_mark_the_line() {
+    let mut core = core(false);
+    core.bus.ppu.wx = 7;
+    core.bus.write(0xFF40, core.bus.ppu.lcdc | 0x20);
+    until_vblank(&mut core);
+    while !(core.bus.ppu.ly == 10 && core.bus.ppu.mode == PpuMode::Drawing) { core.step().unwrap(); }
+    core.bus.write(0xFF4A, 100);
+    until_vblank(&mut core);
+    let lines = core.bus.raster.as_ref().unwrap().last_frame();
+    assert_eq!(lines.iter().filter(|l| l.mid_line).map(|l| l.ly).collect::<Vec<_>>(), [10]);
+    assert!(lines[..10].iter().all(|l| l.window && l.wy == 0), "WY 0, WX 7: the window covers the screen");
+    assert!(!lines[10].window && lines[10].wy == 100);
+    assert!(lines[100..].iter().all(|l| l.window));
+
+    // Switching the LCD off mid-frame drops that frame; the next one is
+    // recorded from line 0
+    let before = lines.to_vec();
+    while core.bus.ppu.ly != 50 { core.step().unwrap(); }
+    core.bus.write(0xFF4A, 0);
+    core.bus.write(0xFF40, 0x31);
+    core.step().unwrap();
+    core.bus.write(0xFF40, 0xB1);
+    until_vblank(&mut core);
+    assert_eq!(core.bus.raster.as_ref().unwrap().last_frame(), &before[..]);
+    until_vblank(&mut core);
+    let lines = core.bus.raster.as_ref().unwrap().last_frame();
+    assert!(lines.iter().enumerate().all(|(i, l)| l.ly as usize == i && l.wy == 0 && l.window));
+}
@@ -0,0 +1,51 @@
+//! Panic capture and the wall-clock run deadline used by letsplay_batch
+
+use gb_core::*;
+use std::time::{Duration, Instant};
+
+#[test]
+fn catch_run_returns_value_or_panic_record() {
+    assert_eq!(catch_run(|| 7), Ok(7));
+
+    let p = catch_run(|| -> u8 { panic!("bad opcode {:02x}", 0xD3) }).unwrap_err();
+    assert_eq!(p.message, "bad opcode d3");
+    assert!(p.location.contains("recover.rs"), "{}", p.location);
+    assert_eq!(p.backtrace_hash.len(), 16);
+    assert!(p.to_json().contains("\"message\":\"bad opcode d3\""));
+
+    // The guard is released: later runs work normally
+    assert_eq!(catch_run(|| "ok"), Ok("ok"));
+}
+
+#[test]
+fn backtrace_hash_ignores_addresses_and_frame_numbers() {
+    let a = "   0: gb_core::step\n             at ./src/lib.rs:10\n   1: main\n";
+    let b = "  12: gb_core::step\n             at ./src/lib.rs:10\n  13: main\n";
+    let c = "   0: gb_core::other\n   1: main\n";
+    assert_eq!(backtrace_hash(a), backtrace_hash(b));
+    assert_ne!(backtrace_hash(a), backtrace_hash(c));
+}
+
+#[test]
+fn deadline_interrupts_a_wedged_run() {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x0100..0x0102].copy_from_slice(&[0x18, 0xFE]); // JR -2
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    let deadline = RunDeadline::arm(core.interrupt_handle(), Duration::from_millis(20));
+    let start = Instant::now();
+    let err = loop {
+        if let Err(e) = core.run_frame() { break e; }
+        assert!(start.elapsed() < Duration::from_secs(10), "deadline never fired");
+    };
+    assert!(matches!(err, CoreError::Interrupted));
+    assert!(deadline.fired());
+}
+
+#[test]
+fn dropped_deadline_never_fires() {
+    let core = GbCore::new(Cartridge::from_bytes(vec![0u8; 32 * 1024]).unwrap());
+    let flag = core.interrupt_handle();
+    drop(RunDeadline::arm(flag.clone(), Duration::from_millis(10)));
+    std::thread::sleep(Duration::from_millis(40));
+    assert!(!flag.load(std::sync::atomic::Ordering::Acquire));
+}
@@ -0,0 +1,31 @@
+//! Per-frame IO register / HRAM diffs (mrom.train.v2)
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+#[test]
+fn frame_diff_lists_changed_registers_and_hram() {
+    // LD A,0x77 / LDH (24),A [NR50] / LDH (80),A [HRAM] / LD A,0xE4 / LDH (47),A [BGP] / JR -2
+    let mut core = core_with(&[0x3E, 0x77, 0xE0, 0x24, 0xE0, 0x80, 0x3E, 0xE4, 0xE0, 0x47, 0x18, 0xFE]);
+    let mut tracker = RegDiffTracker::new(&core.bus);
+    core.run_frame().unwrap();
+    let d = tracker.frame_diff(&core.bus);
+    assert!(d.io.contains(&(0xFF24, 0x77)), "APU writes are visible: {:?}", d.io);
+    assert!(d.io.contains(&(0xFF47, 0xE4)));
+    assert_eq!(d.hram, vec![(0xFF80, 0x77)]);
+
+    // Nothing new is written; only free-running registers may change
+    core.run_frame().unwrap();
+    let d = tracker.frame_diff(&core.bus);
+    assert!(d.hram.is_empty());
+    assert!(d.io.iter().all(|(a, _)| ![0xFF24, 0xFF47].contains(a)), "{:?}", d.io);
+}
+
+#[test]
+fn diff_json_is_address_value_pairs() {
+    let d = RegDiff { io: vec![(0xFF40, 0x91)], hram: vec![] };
+    assert_eq!(d.to_json_fields(), "\"io_diff\":[[65344,145]],\"hram_diff\":[],");
+    assert!(RegDiff::default().is_empty());
+}
@@ -0,0 +1,88 @@
+//! mrom.replay.v2: keyframes plus memory change lists
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+/// LD HL,0xC000 / loop: INC (HL) / INC L / JR loop — walks a counter
+/// across one WRAM page
+const WALKER: [u8; 7] = [0x21, 0x00, 0xC0, 0x34, 0x2C, 0x18, 0xFC];
+
+#[test]
+fn reader_rebuilds_every_frame() {
+    let mut core = core_with(&WALKER);
+    let mut replay = ReplayCapture::new(40, "WALKER").with_keyframes(Some(8));
+    let mut live = vec![];
+    for f in 0..40 {
+        core.run_frame().unwrap();
+        if f == 20 { core.bus.write(0xFE00, 0x42); core.bus.write(0x9800, 0x07); }
+        replay.capture(&core);
+        live.push((ReplayMemory::of(&core.bus), core.regs.pc, core.bus.ppu.lcdc));
+    }
+    let reader = ReplayReader::from_json(&replay.to_json()).unwrap();
+    assert_eq!(reader.len(), 40);
+    for i in [0, 1, 7, 8, 13, 20, 21, 39] {
+        let state = reader.state(i).unwrap();
+        assert!(state.memory == live[i].0, "frame {i}");
+        assert_eq!((state.regs.pc, state.lcdc, state.rom_bank), (live[i].1, live[i].2, 1));
+    }
+    assert_eq!(reader.state(21).unwrap().memory.oam[0], 0x42);
+    assert!(reader.state(40).is_none());
+}
+
+#[test]
+fn in_between_frames_only_carry_changes() {
+    let mut core = core_with(&WALKER);
+    let mut replay = ReplayCapture::new(3, "WALKER").with_keyframes(Some(4));
+    for _ in 0..3 { core.run_frame().unwrap(); replay.capture(&core); }
+    assert!(!replay.frames[0].snapshot.is_empty());
+    assert!(replay.frames[1].snapshot.is_empty());
+    let delta = replay.frames[1].memory.as_ref().unwrap();
+    assert!(delta.changes.iter().all(|c| matches!(c.0, "w" | "io" | "h")), "{:?}", delta.changes.iter().map(|c| c.0).collect::<Vec<_>>());
+    assert!(delta.changes.iter().any(|c| c.0 == "w" && c.1 == 0));
+    let doc = Json::parse(&replay.to_json()).unwrap();
+    assert_eq!(doc.get("version").and_then(Json::as_str), Some(REPLAY_V2_VERSION));
+    let frames = doc.get("frames").and_then(Json::as_array).unwrap();
+    assert_eq!(frames[0].get("key").and_then(Json::as_bool), Some(true));
+    assert!(frames[1].get("snap").is_none() && frames[1].get("mem").and_then(|m| m.get("v")).is_none());
+
+    let mem = ReplayMemory::of(&core.bus);
+    assert_eq!(MemoryDelta::between(&mem, &mem).to_json(), "{}");
+}
+
+#[test]
+fn v2_is_at_least_fifty_times_smaller_than_v1() {
+    let frames = 300;
+    let mut core = core_with(&WALKER);
+    let mut v1 = ReplayCapture::new(frames, "WALKER");
+    let mut v2 = ReplayCapture::new(frames, "WALKER").with_keyframes(Some(DEFAULT_KEYFRAME_INTERVAL));
+    for _ in 0..frames {
+        core.run_frame().unwrap();
+        v1.capture(&core);
+        v2.capture(&core);
+    }
+    let (a, b) = (v1.to_json().len(), v2.to_json().len());
+    assert!(a >= b * 50, "v1 {a} bytes, v2 {b} bytes");
+}
+
+#[test]
+fn scenes_read_v2_manifests() {
+    let mut core = core_with(&WALKER);
+    let mut replay = ReplayCapture::new(4, "WALKER").with_keyframes(Some(2)).with_phash(true);
+    for _ in 0..4 { core.run_frame().unwrap(); replay.capture(&core); }
+    let frames = scene_frames_from_manifest(&Json::parse(&replay.to_json()).unwrap()).unwrap();
+    assert_eq!(frames.len(), 4);
+    assert!(frames.iter().all(|f| f.phash.is_some() && f.lcd_on && f.rom_bank == 1));
+}
+
+#[test]
+fn reader_rejects_v1_and_headless_replays() {
+    let mut core = core_with(&WALKER);
+    let mut replay = ReplayCapture::new(1, "WALKER");
+    core.run_frame().unwrap();
+    replay.capture(&core);
+    assert!(ReplayReader::from_json(&replay.to_json()).is_err());
+    let headless = format!("{{\"version\":\"{REPLAY_V2_VERSION}\",\"frames\":[{{\"fi\":1,\"mem\":{{}}}}]}}");
+    assert!(ReplayReader::from_json(&headless).unwrap_err().contains("keyframe"));
+}
@@ -0,0 +1,73 @@
+//! GbCore::reset and swap_rom
+
+use gb_core::*;
+
+/// LD HL,0xC000 / loop: INC (HL) / JR loop
+const COUNTER: [u8; 6] = [0x21, 0x00, 0xC0, 0x34, 0x18, 0xFD];
+
+fn cart(title: &str, code: &[u8]) -> Cartridge {
+    let rom = RomBuilder::new().title(title).cart_type(0x03).ram_kb(8).code(code).build();
+    Cartridge::from_bytes(rom).unwrap()
+}
+
+#[test]
+fn reset_restarts_from_post_boot_state() {
+    let mut core = GbCore::new(cart("RESET", &COUNTER));
+    let boot = (format!("{:?}", core.regs), core.bus.io, core.bus.ppu.ly);
+    for _ in 0..3 { core.run_frame().unwrap(); }
+    assert_ne!(core.bus.peek(0xC000), 0);
+    let t = core.clock.t_cycles;
+
+    core.reset();
+    assert_eq!((format!("{:?}", core.regs), core.bus.io, core.bus.ppu.ly), boot);
+    assert_eq!(core.bus.peek(0xC000), 0, "work RAM cleared");
+    assert!(!core.halted && !core.ime);
+    assert_eq!(core.clock.t_cycles, t, "the clock keeps counting");
+    core.run_frame().unwrap();
+    assert_ne!(core.bus.peek(0xC000), 0, "the program runs again");
+}
+
+#[test]
+fn reset_keeps_cart_ram_and_host_attachments() {
+    let mut core = GbCore::new(cart("RESET", &COUNTER));
+    core.bus.write(0x0000, 0x0A);
+    core.bus.write(0xA000, 0x42);
+    core.set_buttons(BTN_START);
+    core.breakpoints.add(0x0200);
+    core.exec_coverage = Some(Box::new(ExecCoverage::for_bus(&core.bus)));
+    core.run_frame().unwrap();
+
+    core.reset();
+    assert_eq!(core.bus.ram[0], 0x42);
+    assert_eq!(core.bus.peek(0xA000), 0xFF, "RAM is disabled again after boot");
+    assert_eq!(core.bus.buttons, BTN_START);
+    assert_eq!(core.breakpoints.len(), 1);
+    assert!(core.exec_coverage.as_ref().unwrap().executed(&core.bus, CODE_START), "coverage carries over");
+}
+
+#[test]
+fn swap_rom_boots_the_new_cartridge() {
+    let mut core = GbCore::new(cart("FIRST", &COUNTER));
+    core.exec_coverage = Some(Box::new(ExecCoverage::for_bus(&core.bus)));
+    core.run_frame().unwrap();
+
+    let mut next = cart("SECOND", &[0x3E, 0x99, 0xEA, 0x00, 0xC1, 0x18, 0xFE]);
+    next.ram[0] = 0x77;
+    core.swap_rom(next);
+    assert_eq!(core.regs.pc, 0x0100);
+    assert_eq!(core.bus.ram[0], 0x77);
+    assert!(RomHeader::parse(&core.bus.rom).unwrap().title.starts_with("SECOND"));
+    assert!(!core.exec_coverage.as_ref().unwrap().executed(&core.bus, CODE_START), "coverage starts over");
+    core.run_frame().unwrap();
+    assert_eq!((core.bus.peek(0xC000), core.bus.peek(0xC100)), (0, 0x99));
+}
+
+#[test]
+fn locked_core_is_unlocked_by_reset() {
+    let mut core = GbCore::new(cart("LOCK", &[0xD3]));
+    assert!(matches!(core.run_frame(), Err(CoreError::CpuLocked { opcode: 0xD3, .. })));
+    assert!(core.locked);
+    core.reset();
+    assert!(!core.locked);
+    assert_eq!(core.regs.pc, 0x0100);
+}
@@ -0,0 +1,72 @@
+//! ROM builder, header editing and the Code emitter
+
+use gb_core::*;
+
+#[test]
+fn built_rom_has_valid_header() {
+    let rom = RomBuilder::new().title("BUILDER").mapper(CartridgeKind::Mbc5).rom_kb(100).ram_kb(32).cgb(0x80)
+        .code(Code::new(CODE_START).spin().bytes())
+        .build();
+    assert_eq!(rom.len(), 128 * 1024);
+    assert!(RomHeader::logo_ok(&rom));
+    assert!(RomHeader::checksums_ok(&rom));
+    assert_eq!(rom[0x100..0x104], [0x00, 0xC3, 0x50, 0x01]);
+    assert_eq!(rom[0x150..0x152], [0x18, 0xFE]);
+
+    let cart = Cartridge::from_bytes(rom).unwrap();
+    assert_eq!(cart.title, "BUILDER");
+    assert!(matches!(cart.kind, CartridgeKind::Mbc5));
+    assert_eq!((cart.rom_size_kb, cart.ram_size_kb), (128, 32));
+    assert!(cart.is_cgb);
+    assert_eq!(RomHeader::parse(&cart.rom).unwrap().cart_type, 0x1B);
+}
+
+#[test]
+fn org_past_the_end_grows_the_image() {
+    let rom = RomBuilder::new().org(3 * 0x4000, &[0xAB]).build();
+    assert_eq!(rom.len(), 64 * 1024);
+    assert_eq!(rom[0x148], 1);
+    assert_eq!(rom[0xC000], 0xAB);
+}
+
+#[test]
+fn header_edit_refixes_checksums() {
+    let mut rom = RomBuilder::new().title("OLD").build();
+    let mut h = RomHeader::parse(&rom).unwrap();
+    h.title = "A VERY LONG TITLE INDEED".into();
+    h.apply(&mut rom);
+    assert_eq!(RomHeader::parse(&rom).unwrap().title, "A VERY LONG TIT");
+    assert!(RomHeader::checksums_ok(&rom));
+    rom[0x200] ^= 1;
+    assert!(!RomHeader::checksums_ok(&rom));
+}
+
+#[test]
+fn code_emitter_runs() {
+    let code = Code::new(CODE_START).serial_print("hi").set_io(0x80, 0x42);
+    let top = code.here();
+    let code = code.nop().jr(top);
+    assert_eq!(&code.bytes()[code.bytes().len() - 2..], &[0x18, 0xFD]);
+    let mut core = GbCore::new(RomBuilder::new().code(code.bytes()).cartridge().unwrap());
+    core.run_frame().unwrap();
+    assert_eq!(core.console_text(), "hi");
+    assert_eq!(core.bus.hram[0], 0x42);
+}
+
+#[test]
+fn jp_call_and_ret_transfer_control() {
+    // main: LD SP / CALL sub / LD HL,done / JP (HL) ... done: spin;  sub: HRAM[1]=0x99 / RET
+    let sub = 0x0200;
+    let done = 0x0180;
+    let main = Code::new(CODE_START).ld_sp(0xFFFE).call(sub).ld_hl(done).raw(&[0xE9]).set_io(0x81, 0xEE);
+    let rom = RomBuilder::new()
+        .code(main.bytes())
+        .org(done as usize, Code::new(done).set_io(0x82, 0x77).spin().bytes())
+        .org(sub as usize, Code::new(sub).set_io(0x81, 0x99).ret().bytes())
+        .build();
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    core.run_frame().unwrap();
+    assert_eq!(core.bus.hram[1], 0x99, "CALL reached the subroutine; JP (HL) skipped the fallthrough write");
+    assert_eq!(core.bus.hram[2], 0x77, "JP (HL) reached its target");
+    assert_eq!(core.regs.sp, 0xFFFE);
+}
@@ -0,0 +1,35 @@
+//! MBC3 RTC driven by an injected HostClock
+
+use gb_core::{Cartridge, FixedClock, GbCore};
+
+fn mbc3_rom() -> Vec<u8> {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x147] = 0x10; // MBC3+TIMER+RAM+BATTERY
+    rom[0x149] = 0x02;
+    rom
+}
+
+#[test]
+fn rtc_follows_fixed_clock() {
+    let clock = FixedClock::new(0);
+    let mut core = GbCore::new(Cartridge::from_bytes(mbc3_rom()).unwrap());
+    core.set_host_clock(Box::new(clock.clone()));
+
+    core.run_frame().unwrap();
+    assert_eq!(core.bus.mbc.rtc_reg, [0, 0, 0, 0, 0]);
+
+    clock.advance(86_400_000_000 + 3_661_000_000); // 1 day, 1h 1m 1s
+    core.run_frame().unwrap();
+    assert_eq!(core.bus.mbc.rtc_reg, [1, 1, 1, 1, 0]);
+}
+
+#[test]
+fn rtc_halt_flag_stops_clock() {
+    let clock = FixedClock::new(0);
+    let mut core = GbCore::new(Cartridge::from_bytes(mbc3_rom()).unwrap());
+    core.set_host_clock(Box::new(clock.clone()));
+    core.bus.mbc.rtc_reg[4] = 0x40;
+    clock.advance(10_000_000);
+    core.run_frame().unwrap();
+    assert_eq!(core.bus.mbc.rtc_reg[0], 0);
+}
@@ -0,0 +1,58 @@
+//! Sub-frame pacing: run_cycles and run_scanline
+
+use gb_core::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+mod common;
+use common::core_with;
+
+/// LD HL,0xC000 / loop: INC (HL) / JR loop — 12-cycle instructions
+const COUNTER: [u8; 6] = [0x21, 0x00, 0xC0, 0x34, 0x18, 0xFD];
+
+#[test]
+fn run_cycles_runs_whole_instructions() {
+    let mut core = core_with(&COUNTER);
+    assert_eq!(core.run_cycles(0).unwrap(), 0);
+    assert_eq!(core.run_cycles(100).unwrap(), 108);
+    assert_eq!(core.run_cycles(12).unwrap(), 12);
+    assert_eq!(core.clock.t_cycles, 120);
+}
+
+#[test]
+fn run_scanline_advances_ly_by_one() {
+    let mut core = core_with(&[0x18, 0xFE]);
+    core.bus.ppu.lcdc = 0x91;
+    core.run_scanline().unwrap();
+    let (ly, t) = (core.bus.ppu.ly, core.clock.t_cycles);
+    for i in 1..=SCANLINES as u64 {
+        let cycles = core.run_scanline().unwrap();
+        assert!(cycles.abs_diff(DOTS_PER_LINE as u64) < 12, "line {i}: {cycles}");
+        assert_eq!(core.bus.ppu.ly as u64, (ly as u64 + i) % SCANLINES as u64);
+    }
+    assert!((core.clock.t_cycles - t).abs_diff(CYCLES_PER_FRAME) < 12, "a whole frame");
+}
+
+#[test]
+fn run_scanline_with_the_lcd_off_runs_one_lines_worth() {
+    let mut core = core_with(&[0x18, 0xFE]);
+    core.bus.ppu.lcdc = 0x00;
+    let ly = core.bus.ppu.ly;
+    let cycles = core.run_scanline().unwrap();
+    assert!((DOTS_PER_LINE as u64..DOTS_PER_LINE as u64 + 12).contains(&cycles), "{cycles}");
+    assert_eq!(core.bus.ppu.ly, ly);
+}
+
+#[test]
+fn frame_boundaries_get_the_per_frame_host_work() {
+    let mut core = core_with(&COUNTER);
+    let updates = Arc::new(AtomicU64::new(0));
+    let seen = Arc::clone(&updates);
+    core.set_stimulus_provider(Some(Box::new(move |_: &StimulusContext, _: &mut StimulusInputs| {
+        seen.fetch_add(1, Ordering::Relaxed);
+    })));
+    core.run_cycles(CYCLES_PER_FRAME / 2).unwrap();
+    assert_eq!(updates.load(Ordering::Relaxed), 0);
+    core.run_cycles(CYCLES_PER_FRAME * 3).unwrap();
+    assert_eq!(updates.load(Ordering::Relaxed), 3, "once per frame started");
+}
@@ -0,0 +1,52 @@
+//! GbCore::run_until: run to an arbitrary condition
+
+use gb_core::*;
+use std::sync::atomic::Ordering;
+
+mod common;
+use common::core_with;
+
+/// LD HL,0xC000 / loop: INC (HL) / JR loop
+const COUNTER: [u8; 6] = [0x21, 0x00, 0xC0, 0x34, 0x18, 0xFD];
+
+#[test]
+fn stops_when_the_condition_holds() {
+    let mut core = core_with(&COUNTER);
+    assert!(core.run_until(|c| c.regs.pc == 0x0104, 1000).unwrap());
+    assert_eq!(core.regs.pc, 0x0104);
+
+    core.bus.write(0xC000, 0);
+    assert!(core.run_until(|c| c.bus.peek(0xC000) == 3, 1000).unwrap());
+    assert_eq!((core.bus.peek(0xC000), core.regs.pc), (3, 0x0104), "stops right after the write");
+
+    let mut core = core_with(&COUNTER);
+    assert!(core.run_until(|c| c.bus.ppu.ly == 100, CYCLES_PER_FRAME).unwrap());
+    assert_eq!(core.bus.ppu.ly, 100);
+}
+
+#[test]
+fn condition_already_true_runs_nothing() {
+    let mut core = core_with(&COUNTER);
+    let t = core.clock.t_cycles;
+    assert!(core.run_until(|_| true, 1000).unwrap());
+    assert_eq!((core.clock.t_cycles, core.regs.pc), (t, 0x0100));
+}
+
+#[test]
+fn gives_up_after_max_cycles() {
+    let mut core = core_with(&COUNTER);
+    let t = core.clock.t_cycles;
+    let mut checks = 0;
+    assert!(!core.run_until(|_| { checks += 1; false }, 100).unwrap());
+    let ran = core.clock.t_cycles - t;
+    assert_eq!(ran, 108, "nine 12-cycle instructions");
+    assert_eq!(checks, 10, "checked before each instruction and once at the end");
+}
+
+#[test]
+fn interrupt_handle_ends_the_run() {
+    let mut core = core_with(&COUNTER);
+    core.interrupt_handle().store(true, Ordering::Release);
+    assert!(matches!(core.run_until(|_| false, 1000), Err(CoreError::Interrupted)));
+    assert!(!core.run_until(|_| false, 8).unwrap(), "the flag is consumed");
+}
@@ -0,0 +1,46 @@
+//! Savestate round-trips at instruction and frame boundaries
+
+use gb_core::{Cartridge, CoreError, GbCore, SavePoint};
+
+fn core() -> GbCore {
+    let rom = vec![0x00u8; 32 * 1024];
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    core.bus.ppu.lcdc = 0x91;
+    core
+}
+
+#[test]
+fn mid_frame_state_resumes_identically() {
+    let mut a = core();
+    for _ in 0..1234 { a.step().unwrap(); }
+    let state = a.save_state();
+
+    let mut b = core();
+    b.load_state(&state).unwrap();
+    assert_eq!(b.bus.ppu.ly, a.bus.ppu.ly);
+    assert_eq!(b.bus.ppu.dot, a.bus.ppu.dot);
+
+    a.run_frame().unwrap();
+    b.run_frame().unwrap();
+    assert_eq!(a.save_state(), b.save_state());
+}
+
+#[test]
+fn frame_save_point_waits_for_vblank() {
+    let mut core = core();
+    core.step().unwrap();
+    assert!(matches!(core.save_state_at(SavePoint::Frame), Err(CoreError::InvalidState(_))));
+
+    core.save_state_at_next_vblank();
+    core.run_frame().unwrap();
+    let state = core.take_vblank_state().expect("vblank state captured");
+    assert!(std::str::from_utf8(&state).unwrap().contains("\"save_point\":\"frame\""));
+    assert!(core.take_vblank_state().is_none());
+}
+
+#[test]
+fn unknown_save_point_is_rejected() {
+    let mut core = core();
+    let state = String::from_utf8(core.save_state()).unwrap().replace("\"instruction\"", "\"scanline\"");
+    assert!(matches!(core.load_state(state.as_bytes()), Err(CoreError::InvalidState(_))));
+}
@@ -0,0 +1,63 @@
+//! Scene segmentation, manifest parsing and PNG keyframe encoding
+
+use gb_core::{detect_scenes, encode_png_rgb, scene_frames_from_manifest, Json, SceneConfig, SceneCut, SceneFrame};
+
+fn frame(frame: u64, phash: u64, lcd_on: bool, rom_bank: u16) -> SceneFrame {
+    SceneFrame { frame, phash: Some(phash), lcd_on, rom_bank }
+}
+
+#[test]
+fn cuts_on_visual_delta_lcd_and_bank_switch() {
+    let mut frames = Vec::new();
+    for f in 0..20 { frames.push(frame(f, 0, true, 1)); }
+    for f in 20..40 { frames.push(frame(f, u64::MAX, true, 1)); }   // big visual change
+    for f in 40..45 { frames.push(frame(f, u64::MAX, false, 1)); }  // LCD off
+    for f in 45..60 { frames.push(frame(f, u64::MAX, true, 1)); }   // LCD on
+    for f in 60..80 { frames.push(frame(f, u64::MAX, true, 3)); }   // bank switch
+
+    let scenes = detect_scenes(&frames, &SceneConfig::default());
+    let cuts: Vec<SceneCut> = scenes.iter().map(|s| s.cut).collect();
+    assert_eq!(cuts, vec![
+        SceneCut::Start, SceneCut::Visual { distance: 64 }, SceneCut::LcdOff, SceneCut::LcdOn,
+        SceneCut::BankSwitch { from: 1, to: 3 },
+    ]);
+    assert_eq!((scenes[1].start_frame, scenes[1].end_frame, scenes[1].keyframe), (20, 39, 29));
+    assert_eq!(scenes[4].end_frame, 79);
+}
+
+#[test]
+fn short_flicker_does_not_cut() {
+    let frames: Vec<SceneFrame> = (0..30).map(|f| frame(f, if f % 2 == 0 { 0 } else { u64::MAX }, true, 1)).collect();
+    let scenes = detect_scenes(&frames, &SceneConfig { phash_threshold: 16, min_scene_frames: 100 });
+    assert_eq!(scenes.len(), 1);
+}
+
+#[test]
+fn frame_numbers_that_go_back_do_not_panic() {
+    // Two captures spliced out of order, the second switching banks, then the LCD goes off
+    let mut frames: Vec<SceneFrame> = (100..110).map(|f| frame(f, 0, true, 1)).collect();
+    frames.extend((50..60).map(|f| frame(f, 0, true, 2)));
+    frames.push(frame(60, 0, false, 2));
+    let scenes = detect_scenes(&frames, &SceneConfig::default());
+    let cuts: Vec<SceneCut> = scenes.iter().map(|s| s.cut).collect();
+    assert_eq!(cuts, vec![SceneCut::Start, SceneCut::LcdOff], "the bank switch lands in a scene that looks too short");
+    assert_eq!((scenes[0].start_frame, scenes[0].end_frame, scenes[0].keyframe), (100, 59, 100));
+}
+
+#[test]
+fn reads_training_manifest() {
+    let doc = Json::parse(r#"{"version":"mrom.train.v1","frames":[
+        {"frame":0,"lcdc":145,"rom_bank":1,"phash":"00000000000000ff"},
+        {"frame":1,"lcdc":17,"rom_bank":2}]}"#).unwrap();
+    let frames = scene_frames_from_manifest(&doc).unwrap();
+    assert_eq!(frames[0], SceneFrame { frame: 0, phash: Some(0xFF), lcd_on: true, rom_bank: 1 });
+    assert_eq!(frames[1], SceneFrame { frame: 1, phash: None, lcd_on: false, rom_bank: 2 });
+}
+
+#[test]
+fn png_has_valid_signature_and_chunks() {
+    let png = encode_png_rgb(2, 2, &[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255]);
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+}
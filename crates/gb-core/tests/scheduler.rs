@@ -0,0 +1,112 @@
+//! Multi-ROM job queue: priorities, frame budgets, retries and live edits
+
+use gb_core::*;
+use std::path::PathBuf;
+
+fn queue_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mrom_queue_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("queue.json")
+}
+
+fn job(rom: &str, priority: i32) -> QueueJob { QueueJob { priority, ..QueueJob::new(rom) } }
+
+#[test]
+fn jobs_run_by_priority_then_queue_order() {
+    let path = queue_path("order");
+    JobQueue::update(&path, |q| {
+        q.add(job("a.gb", 0));
+        q.add(QueueJob { frames: Some(60), ..job("b.gb", 5) });
+        q.add(job("c.gb", 5));
+        q.add(job("d.gb", -1));
+    }).unwrap();
+
+    let mut order = vec![];
+    while let Some(j) = JobQueue::update(&path, |q| q.claim_next()).unwrap() {
+        assert_eq!((j.status, j.attempts), (JobStatus::Running, 1));
+        order.push((j.rom.clone(), j.frames));
+        JobQueue::update(&path, |q| q.finish(&j.rom, JobOutcome::Done)).unwrap();
+    }
+    assert_eq!(order, [("b.gb".into(), Some(60)), ("c.gb".into(), None), ("a.gb".into(), None), ("d.gb".into(), None)]);
+
+    let q = JobQueue::open(&path).unwrap();
+    assert!(q.jobs().iter().all(|j| j.status == JobStatus::Done && j.error.is_none()));
+    assert_eq!(q.job("b.gb").unwrap().frames, Some(60), "settings survive the file");
+    assert!(!PathBuf::from(format!("{}.lock", path.display())).exists(), "the lock is released");
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn retry_policies_decide_which_failures_run_again() {
+    let path = queue_path("retry");
+    let mut q = JobQueue::open(&path).unwrap();
+    assert!(q.jobs().is_empty(), "a missing file is an empty queue");
+    q.add(QueueJob { retries: 1, ..job("flaky.gb", 1) });
+    q.add(job("other.gb", 1));
+    q.add(QueueJob { retry: RetryPolicy::Always, retries: 1, ..job("panics.gb", 0) });
+    q.add(QueueJob { retry: RetryPolicy::Never, ..job("never.gb", 0) });
+
+    // A watchdog trip is transient: retried, behind its equals
+    assert_eq!(q.claim_next().unwrap().rom, "flaky.gb");
+    assert_eq!(q.finish("flaky.gb", JobOutcome::Transient("watchdog".into())), Some(JobStatus::Pending));
+    assert_eq!(q.job("flaky.gb").unwrap().error.as_deref(), Some("watchdog"));
+    assert_eq!(q.claim_next().unwrap().rom, "other.gb");
+    assert_eq!(q.finish("other.gb", JobOutcome::Failed("panic".into())), Some(JobStatus::Failed), "the default policy does not retry panics");
+    let again = q.claim_next().unwrap();
+    assert_eq!((again.rom.as_str(), again.attempts), ("flaky.gb", 2));
+    assert_eq!(q.finish("flaky.gb", JobOutcome::Transient("watchdog".into())), Some(JobStatus::Failed), "out of retries");
+
+    assert_eq!(q.claim_next().unwrap().rom, "panics.gb");
+    assert_eq!(q.finish("panics.gb", JobOutcome::Failed("panic".into())), Some(JobStatus::Pending));
+    assert_eq!(q.claim_next().unwrap().rom, "never.gb");
+    assert_eq!(q.finish("never.gb", JobOutcome::Transient("write error".into())), Some(JobStatus::Failed));
+    assert_eq!(q.claim_next().unwrap().rom, "panics.gb");
+    assert_eq!(q.finish("panics.gb", JobOutcome::Done), Some(JobStatus::Done));
+    assert_eq!(q.job("panics.gb").unwrap().error, None);
+    assert!(q.claim_next().is_none());
+
+    q.save().unwrap();
+    let reread = JobQueue::open(&path).unwrap();
+    assert_eq!(reread.jobs(), q.jobs());
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn other_processes_edit_the_queue_between_jobs() {
+    let path = queue_path("live");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    // Hand-written: only "rom" is required
+    std::fs::write(&path, r#"{"version":"mrom.queue.v1","jobs":[{"rom":"a.gb"},{"rom":"b.gb","priority":-2}]}"#).unwrap();
+    let running = JobQueue::update(&path, |q| q.claim_next()).unwrap().unwrap();
+    assert_eq!(running.rom, "a.gb");
+
+    // While a.gb runs: a high-priority ROM arrives, b.gb is dropped, a.gb re-prioritised
+    JobQueue::update(&path, |q| {
+        q.add(job("urgent.gb", 9));
+        assert!(q.remove("b.gb") && !q.remove("b.gb"));
+        q.add(job("a.gb", 3));
+    }).unwrap();
+    let a = JobQueue::open(&path).unwrap().job("a.gb").cloned().unwrap();
+    assert_eq!((a.status, a.attempts, a.priority), (JobStatus::Running, 1, 3), "a running job only takes the new settings");
+    assert_eq!(JobQueue::update(&path, |q| q.claim_next()).unwrap().unwrap().rom, "urgent.gb");
+
+    // A removed job's result is dropped; re-adding a finished job runs it again
+    JobQueue::update(&path, |q| q.remove("urgent.gb")).unwrap();
+    assert_eq!(JobQueue::update(&path, |q| q.finish("urgent.gb", JobOutcome::Done)).unwrap(), None);
+    JobQueue::update(&path, |q| q.finish("a.gb", JobOutcome::Done)).unwrap();
+    JobQueue::update(&path, |q| q.add(job("a.gb", 0))).unwrap();
+    assert_eq!(JobQueue::open(&path).unwrap().pending(), 1);
+
+    // A crashed runner leaves jobs running and maybe its lock behind
+    JobQueue::update(&path, |q| q.claim_next()).unwrap();
+    let lock = PathBuf::from(format!("{}.lock", path.display()));
+    std::fs::write(&lock, "").unwrap();
+    let old = std::time::SystemTime::now() - LOCK_STALE * 2;
+    std::fs::File::options().write(true).open(&lock).unwrap().set_modified(old).unwrap();
+    assert_eq!(JobQueue::update(&path, |q| q.requeue_running()).unwrap(), 1);
+    assert!(!lock.exists());
+
+    std::fs::write(&path, r#"{"version":"mrom.queue.v1","jobs":[{"rom":"x.gb","retry":"sometimes"}]}"#).unwrap();
+    assert_eq!(JobQueue::open(&path).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
@@ -0,0 +1,61 @@
+//! Test-ROM harness verdicts and scorecard regression detection
+
+use gb_core::{parse_suites, run_test_rom, sha256_hex, Json, ScoreEntry, Scorecard, Suite, TestOutcome};
+
+mod common;
+use common::rom_with;
+
+fn rom(prog: &[u8]) -> Vec<u8> {
+    let mut rom = rom_with(&[0xC3, 0x50, 0x01]); // JP 0x0150, past the header
+    rom[0x0150..0x0150 + prog.len()].copy_from_slice(prog);
+    rom
+}
+
+#[test]
+fn mooneye_signature_passes() {
+    // LD B,3 / LD C,5 / LD D,8 / LD E,13 / LD H,21 / LD L,34 / JR -2
+    let run = run_test_rom(rom(&[0x06, 3, 0x0E, 5, 0x16, 8, 0x1E, 13, 0x26, 21, 0x2E, 34, 0x18, 0xFE]), 10);
+    assert_eq!(run.outcome, TestOutcome::Pass);
+    assert_eq!(run.frames, 1);
+}
+
+#[test]
+fn serial_failed_and_timeout() {
+    let mut prog = vec![];
+    for &c in b"op 3\nFailed\n" {
+        prog.extend_from_slice(&[0x3E, c, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02]);
+    }
+    prog.extend_from_slice(&[0x18, 0xFE]);
+    let run = run_test_rom(rom(&prog), 10);
+    assert_eq!(run.outcome, TestOutcome::Fail("op 3".into()));
+
+    let run = run_test_rom(rom(&[0x18, 0xFE]), 3);
+    assert_eq!((run.outcome, run.frames), (TestOutcome::Timeout, 3));
+}
+
+#[test]
+fn scorecard_round_trips_and_flags_regressions() {
+    let suites = parse_suites(&Json::parse(r#"{"suites":[{"name":"cpu_instrs","subsystem":"cpu"},{"name":"timer","frames":60}]}"#).unwrap()).unwrap();
+    assert_eq!(suites[0], Suite { name: "cpu_instrs".into(), subsystem: "cpu".into(), dir: "cpu_instrs".into(), frames: 3600 });
+    assert_eq!(suites[1].frames, 60);
+
+    let pass = run_test_rom(rom(&[0x06, 3, 0x0E, 5, 0x16, 8, 0x1E, 13, 0x26, 21, 0x2E, 34, 0x18, 0xFE]), 5);
+    let hang = run_test_rom(rom(&[0x18, 0xFE]), 2);
+    let before = Scorecard { core: "gb-core".into(), artifact_id: Some("tetris_gb".into()), rom_sha: Some(sha256_hex(b"tetris")), entries: vec![
+        ScoreEntry::from_run(&suites[0], "01.gb", &pass),
+        ScoreEntry::from_run(&suites[1], "div.gb", &pass),
+    ] };
+    let doc = Json::parse(&before.to_json()).unwrap();
+    assert_eq!(doc.get("version").and_then(Json::as_str), Some("mrom.scorecard.v1"));
+    assert_eq!(doc.get("pass_rate").and_then(Json::as_f64), Some(1.0));
+    assert_eq!(doc.get("artifact_id").and_then(Json::as_str), Some("tetris_gb"));
+    assert_eq!(Scorecard::from_json(&doc).unwrap(), before);
+
+    let after = Scorecard { core: "gb-core".into(), artifact_id: None, rom_sha: None, entries: vec![
+        ScoreEntry::from_run(&suites[0], "01.gb", &pass),
+        ScoreEntry::from_run(&suites[1], "div.gb", &hang),
+    ] };
+    assert!(after.regressions(&after).is_empty());
+    let r = after.regressions(&before);
+    assert_eq!(r, vec!["timer/div.gb: pass → timeout".to_string(), "subsystem timer: 1 → 0 passing".to_string()]);
+}
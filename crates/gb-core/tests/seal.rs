@@ -0,0 +1,99 @@
+//! Sealed (encrypted) savestate and replay files
+
+use gb_core::*;
+
+const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+fn core() -> GbCore {
+    let mut core = GbCore::new(RomBuilder::new().code(&[0x3C, 0x18, 0xFD]).cartridge().unwrap());
+    core.run_frame().unwrap();
+    core
+}
+
+#[test]
+fn keys_parse_from_hex() {
+    let key = StateKey::from_hex(&format!(" {KEY}\n")).unwrap();
+    assert_eq!(key, StateKey::new(std::array::from_fn(|i| i as u8)));
+    assert_eq!(format!("{key:?}"), "StateKey(..)", "the key never shows in logs");
+    for bad in ["", &KEY[..62], &format!("{}zz", &KEY[..62]), &format!("{KEY}00")] {
+        assert!(StateKey::from_hex(bad).is_err(), "{bad:?}");
+    }
+}
+
+#[cfg(feature = "encrypt")]
+#[test]
+fn sealed_data_round_trips_and_authenticates() {
+    let key = StateKey::from_hex(KEY).unwrap();
+    let state = core().save_state();
+    let sealed = seal(&key, &state);
+    assert!(is_sealed(&sealed) && !is_sealed(&state));
+    assert_eq!(sealed.len(), state.len() + 9 + 12 + 16);
+    assert_ne!(seal(&key, &state), sealed, "fresh nonce per file");
+    assert_eq!(unseal(&key, &sealed).unwrap(), state);
+
+    let other = StateKey::new([7; 32]);
+    assert!(unseal(&other, &sealed).is_err(), "wrong key");
+    let mut flipped = sealed.clone();
+    flipped[40] ^= 1;
+    assert!(unseal(&key, &flipped).is_err(), "altered ciphertext");
+    let mut version = sealed.clone();
+    version[8] = 2;
+    assert!(unseal(&key, &version).is_err(), "the header is authenticated");
+    assert!(unseal(&key, &sealed[..sealed.len() - 1]).is_err(), "truncated");
+}
+
+/// Everything that reads METAROM_STATE_KEY runs here, one step at a time
+#[test]
+fn files_are_sealed_with_the_environment_key() {
+    let dir = std::env::temp_dir().join(format!("mrom_seal_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (state_path, replay_path) = (dir.join("a.mrom.sav"), dir.join("replay.json"));
+    let mut core = core();
+    let mut replay = ReplayCapture::new(10, "SEAL");
+    replay.capture(&core);
+
+    // No key: plain files, as before
+    std::env::remove_var(STATE_KEY_ENV);
+    core.save_state_to_file(&state_path).unwrap();
+    assert!(!is_sealed(&std::fs::read(&state_path).unwrap()));
+    let sealed_looking = dir.join("sealed.mrom.sav");
+    std::fs::write(&sealed_looking, [&SEAL_MAGIC[..], &[1; 40]].concat()).unwrap();
+    assert!(core.load_state_from_file(&sealed_looking).unwrap_err().to_string().contains(STATE_KEY_ENV));
+
+    std::env::set_var(STATE_KEY_ENV, KEY);
+    // In-memory round trips ignore the key
+    core.load_state(&core.save_state()).unwrap();
+    // Plain files are refused unless migration is switched on
+    assert!(core.load_state_from_file(&state_path).unwrap_err().to_string().contains(ALLOW_PLAINTEXT_ENV));
+    assert_eq!(read_sealed(&state_path).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    std::env::set_var(ALLOW_PLAINTEXT_ENV, "1");
+    core.load_state_from_file(&state_path).unwrap();
+    std::env::remove_var(ALLOW_PLAINTEXT_ENV);
+
+    let written = core.save_state_to_file(&state_path);
+    let replay_written = replay.save(&replay_path);
+    #[cfg(feature = "encrypt")]
+    {
+        written.unwrap();
+        replay_written.unwrap();
+        let bytes = std::fs::read(&state_path).unwrap();
+        assert!(is_sealed(&bytes));
+        assert!(!bytes.windows(8).any(|w| w == b"mrom.sav"), "no plaintext on disk");
+        let mut loaded = GbCore::new(RomBuilder::new().code(&[0x3C, 0x18, 0xFD]).cartridge().unwrap());
+        loaded.load_state_from_file(&state_path).unwrap();
+        assert_eq!(loaded.save_state(), core.save_state());
+        assert_eq!(read_sealed(&replay_path).unwrap(), replay.to_json().into_bytes());
+
+        // Sealed files need the key they were sealed with
+        std::env::set_var(STATE_KEY_ENV, "ff".repeat(32));
+        assert!(loaded.load_state_from_file(&state_path).is_err());
+    }
+    #[cfg(not(feature = "encrypt"))]
+    {
+        // Never fall back to writing in the clear
+        assert_eq!(written.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert!(replay_written.is_err());
+    }
+    std::env::remove_var(STATE_KEY_ENV);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,88 @@
+//! Monitoring endpoints (feature `http`)
+#![cfg(feature = "http")]
+
+use gb_core::*;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn snapshot() -> ServeSnapshot {
+    let code = Code::new(CODE_START).ld_a(0x5A).st_a(0xC010).spin();
+    let mut core = GbCore::new(RomBuilder::new().title("SERVE").code(code.bytes()).cartridge().unwrap());
+    core.run_frame().unwrap();
+    ServeSnapshot::capture(&core, "SERVE", 59.5)
+}
+
+fn get(snap: &ServeSnapshot, stats: &Metrics, target: &str) -> HttpResponse {
+    route(&format!("GET {target} HTTP/1.1"), snap, stats, Duration::from_secs(3))
+}
+
+#[test]
+fn endpoints_answer_from_the_snapshot() {
+    let (snap, stats) = (snapshot(), Metrics::new().with_label("rom", "SERVE"));
+    let state = get(&snap, &stats, "/state");
+    assert_eq!((state.status, state.content_type), (200, "application/json"));
+    assert!(String::from_utf8(state.body).unwrap().starts_with("{\"v\":\"mrom.snap.v1\""));
+
+    let mem = String::from_utf8(get(&snap, &stats, "/memory/wram?offset=0x10&len=2").body).unwrap();
+    assert!(mem.contains("\"address\":49168,\"offset\":16,\"len\":2,\"hex\":\"5a00\""), "{mem}");
+    let tail = String::from_utf8(get(&snap, &stats, "/memory/wram?offset=8190&len=99").body).unwrap();
+    assert!(tail.contains("\"len\":2"), "clamped to the WRAM view: {tail}");
+
+    let png = get(&snap, &stats, "/screenshot.png");
+    assert_eq!(&png.body[1..4], b"PNG");
+
+    let metrics = String::from_utf8(get(&snap, &stats, "/metrics").body).unwrap();
+    assert!(metrics.contains("# TYPE mrom_frames_total counter\nmrom_frames_total{rom=\"SERVE\"} 1\n"), "{metrics}");
+    assert!(metrics.contains("mrom_fps{rom=\"SERVE\"} 59.5\n"));
+    assert!(metrics.contains("mrom_http_requests_total{rom=\"SERVE\"} 5"));
+}
+
+#[test]
+fn bad_requests_are_rejected() {
+    let (snap, stats) = (snapshot(), Metrics::new().with_label("rom", "SERVE"));
+    assert_eq!(get(&snap, &stats, "/nope").status, 404);
+    assert_eq!(get(&snap, &stats, "/memory/wram?offset=x").status, 400);
+    assert_eq!(get(&snap, &stats, "/memory/wram?offset=8192").status, 400);
+    assert_eq!(route("POST /state HTTP/1.1", &snap, &stats, Duration::ZERO).status, 405);
+    assert_eq!(stats.get(METRIC_HTTP_ERRORS), Some(4.0));
+}
+
+#[test]
+fn server_round_trip() {
+    let server = HttpServer::spawn("127.0.0.1:0", Arc::new(Mutex::new(snapshot())), Arc::new(Metrics::new())).unwrap();
+    let mut s = std::net::TcpStream::connect(server.addr).unwrap();
+    s.write_all(b"GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+    let mut resp = String::new();
+    s.read_to_string(&mut resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.0 200 OK\r\n"), "{resp}");
+    assert!(resp.contains("mrom_cycles_total"));
+}
+
+#[test]
+fn oversized_heads_are_rejected_and_slow_clients_do_not_block_others() {
+    let metrics = Arc::new(Metrics::new());
+    let server = HttpServer::spawn("127.0.0.1:0", Arc::new(Mutex::new(snapshot())), Arc::clone(&metrics)).unwrap();
+    // Connected but silent: must not hold up the requests below
+    let _idle = std::net::TcpStream::connect(server.addr).unwrap();
+
+    let mut s = std::net::TcpStream::connect(server.addr).unwrap();
+    let _ = s.write_all(&vec![b'A'; MAX_REQUEST_HEAD * 4]);
+    let mut resp = String::new();
+    let _ = s.read_to_string(&mut resp);
+    assert!(resp.starts_with("HTTP/1.0 400 Bad Request\r\n"), "{resp}");
+
+    let mut s = std::net::TcpStream::connect(server.addr).unwrap();
+    let header = format!("X-Long: {}\r\n", "b".repeat(1000));
+    let _ = s.write_all(format!("GET /state HTTP/1.1\r\n{}\r\n", header.repeat(9)).as_bytes());
+    let mut resp = String::new();
+    let _ = s.read_to_string(&mut resp);
+    assert!(resp.starts_with("HTTP/1.0 400"), "headers count towards the limit: {resp}");
+
+    let mut s = std::net::TcpStream::connect(server.addr).unwrap();
+    s.write_all(format!("GET /state HTTP/1.1\r\n{header}\r\n").as_bytes()).unwrap();
+    let mut resp = String::new();
+    s.read_to_string(&mut resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.0 200 OK\r\n"), "{resp}");
+    assert_eq!(metrics.get(METRIC_HTTP_ERRORS), Some(2.0));
+}
@@ -0,0 +1,65 @@
+//! Session provenance manifest (mrom.session.v1)
+
+use gb_core::*;
+
+fn out(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("mrom_session_{name}_{}", std::process::id()))
+}
+
+#[test]
+fn session_links_config_plan_and_checksummed_files() {
+    let out = out("files");
+    let rom = RomBuilder::new().title("SESS").build();
+    let a = RomArtifacts::new(&out, &rom);
+    a.create().unwrap();
+    std::fs::write(a.replay(), "{\"version\":\"mrom.replay.v1\"}").unwrap();
+    std::fs::write(a.train(), "{}").unwrap();
+    let plan = out.join("plan.json");
+    std::fs::write(&plan, r#"{"plan_id":"plan-gb-pc","strategy":"emulate"}"#).unwrap();
+
+    let config = CoreConfig { model: HardwareModel::Cgb, mem_seed: 7, ..Default::default() };
+    let mut s = SessionManifest::new(&a, "SESS", "roms/sess.gb", &config);
+    s.frames = 120;
+    s.set_plan(&plan).unwrap();
+    s.add(SessionRole::Replay, &a.replay()).unwrap();
+    s.add(SessionRole::Training, &a.train()).unwrap();
+    assert_eq!(s.write().unwrap(), a.session());
+
+    let doc = Json::parse(&std::fs::read_to_string(a.session()).unwrap()).unwrap();
+    assert_eq!(doc.get("version").and_then(Json::as_str), Some(SESSION_VERSION));
+    assert_eq!(doc.get("rom_hash").and_then(Json::as_str), Some(a.rom_hash.as_str()));
+    assert_eq!(doc.get("frames").and_then(Json::as_f64), Some(120.0));
+    assert_eq!(doc.get("config").and_then(|c| c.get("model")).and_then(Json::as_str), Some("cgb"));
+
+    let plan = doc.get("plan").unwrap();
+    assert_eq!(plan.get("plan_id").and_then(Json::as_str), Some("plan-gb-pc"));
+    assert_eq!(plan.get("strategy").and_then(Json::as_str), Some("emulate"));
+
+    let Some(Json::Arr(files)) = doc.get("files") else { panic!("files array") };
+    let replay = &files[0];
+    assert_eq!(replay.get("role").and_then(Json::as_str), Some("replay"));
+    assert_eq!(replay.get("path").and_then(Json::as_str), Some("replay.json"), "relative to the artifact dir");
+    assert_eq!(replay.get("bytes").and_then(Json::as_f64), Some(28.0));
+    assert_eq!(files[1].get("fnv1a").and_then(Json::as_str), Some(rom_hash(b"{}").as_str()), "same hash as rom_hash");
+    std::fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn unplanned_run_has_null_plan() {
+    let out = out("noplan");
+    let a = RomArtifacts::new(&out, &RomBuilder::new().build());
+    a.create().unwrap();
+    SessionManifest::new(&a, "", "rom.gb", &CoreConfig::default()).write().unwrap();
+    let doc = Json::parse(&std::fs::read_to_string(a.session()).unwrap()).unwrap();
+    assert_eq!(doc.get("plan"), Some(&Json::Null));
+    assert_eq!(doc.get("files"), Some(&Json::Arr(vec![])));
+    std::fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn missing_file_is_an_error() {
+    let a = RomArtifacts::new(&out("missing"), &RomBuilder::new().build());
+    let mut s = SessionManifest::new(&a, "", "rom.gb", &CoreConfig::default());
+    assert!(s.add(SessionRole::State, &a.state("nope")).is_err());
+    assert!(s.files.is_empty());
+}
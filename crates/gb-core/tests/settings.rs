@@ -0,0 +1,72 @@
+//! Persistent settings store with per-game overrides (mrom.settings.v1)
+
+use gb_core::*;
+
+fn path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("mrom_settings_{name}_{}", std::process::id())).join("settings.json")
+}
+
+#[test]
+fn missing_file_is_an_empty_store() {
+    let store = SettingsStore::open(&path("missing")).unwrap();
+    assert!(store.defaults.is_empty());
+    assert_eq!(store.games().count(), 0);
+}
+
+#[test]
+fn round_trips_through_the_file() {
+    let p = path("roundtrip");
+    let mut store = SettingsStore::open(&p).unwrap();
+    store.defaults.lite = Some(LiteMode::LITE);
+    let game = GameSettings {
+        palette: Some([(0xe0, 0xf8, 0xd0), (0x88, 0xc0, 0x70), (0x34, 0x68, 0x56), (0x08, 0x18, 0x20)]),
+        model: Some(HardwareModel::Mgb),
+        cheats: vec!["01FF16D0".into(), "00A-17B-C49".into()],
+        input_map: Some(InputMapping::parse("key.j = a\nkey.k = b").unwrap()),
+        ..Default::default()
+    };
+    store.set_game("1234abcd", "ZELDA \"DX\"", game.clone());
+    store.save().unwrap();
+
+    let back = SettingsStore::open(&p).unwrap();
+    assert_eq!(back.defaults, store.defaults);
+    assert_eq!(back.game("1234abcd"), Some(&game));
+    assert_eq!(back.games().next().unwrap().title, "ZELDA \"DX\"");
+    let doc = Json::parse(&std::fs::read_to_string(&p).unwrap()).unwrap();
+    assert_eq!(doc.get("version").and_then(Json::as_str), Some(SETTINGS_VERSION));
+}
+
+#[test]
+fn game_overrides_win_over_defaults_at_load() {
+    let rom = RomBuilder::new().title("SETTINGS").build();
+    let mut store = SettingsStore::open(&path("overlay")).unwrap();
+    store.defaults = GameSettings { model: Some(HardwareModel::Sgb), cheats: vec!["010203C0".into()], ..Default::default() };
+    let palette = [(1, 2, 3), (4, 5, 6), (7, 8, 9), (10, 11, 12)];
+    store.set_game(&rom_hash(&rom), "SETTINGS", GameSettings { model: Some(HardwareModel::Cgb), palette: Some(palette), ..Default::default() });
+
+    let cart = Cartridge::from_bytes(rom).unwrap();
+    let resolved = store.for_cartridge(&cart);
+    assert_eq!((resolved.model, resolved.cheats.len()), (Some(HardwareModel::Cgb), 1));
+
+    let core = store.core_for(cart, CoreConfig::default());
+    assert_eq!((core.config.model, core.dmg_colors), (HardwareModel::Cgb, DmgColors::uniform(palette)));
+    assert_eq!(&core.framebuffer_rgb()[..3], &[1, 2, 3], "blank screen is shade 0");
+
+    let other = Cartridge::from_bytes(RomBuilder::new().title("OTHER").build()).unwrap();
+    assert_eq!(store.core_for(other, CoreConfig::default()).config.model, HardwareModel::Sgb);
+}
+
+#[test]
+fn empty_settings_remove_a_game_and_bad_files_are_errors() {
+    let p = path("errors");
+    let mut store = SettingsStore::open(&p).unwrap();
+    store.set_game("aa", "A", GameSettings { model: Some(HardwareModel::Cgb), ..Default::default() });
+    store.set_game("aa", "A", GameSettings::default());
+    assert!(store.game("aa").is_none());
+
+    std::fs::create_dir_all(p.parent().unwrap()).unwrap();
+    std::fs::write(&p, r##"{"version":"mrom.settings.v1","games":{"aa":{"palette":["#fff"]}}}"##).unwrap();
+    let err = SettingsStore::open(&p).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("aa: palette"), "{err}");
+}
@@ -0,0 +1,67 @@
+//! Visible sprite lists (object-detection labels)
+
+use gb_core::*;
+
+fn core_with_oam(entries: &[[u8; 4]]) -> GbCore {
+    let rom = RomBuilder::new().code(&[0x18, 0xFE]).build();
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    for (i, e) in entries.iter().enumerate() { core.bus.oam[i * 4..i * 4 + 4].copy_from_slice(e); }
+    core.bus.ppu.lcdc |= 0x02;
+    core
+}
+
+#[test]
+fn lists_on_screen_sprites_with_boxes_and_attributes() {
+    let core = core_with_oam(&[
+        [0, 50, 1, 0],        // above the screen
+        [40, 4, 2, 0xF0],     // partly off the left edge, every attribute set
+        [100, 168, 3, 0],     // right of the screen
+        [159, 90, 4, 0x10],   // bottom line only
+        [160, 90, 5, 0],      // below the screen
+    ]);
+    let sprites = visible_sprites(&core.bus);
+    assert_eq!(sprites, [
+        VisibleSprite { index: 1, x: -4, y: 24, height: 8, tile: 2, palette: 1, x_flip: true, y_flip: true, behind_bg: true },
+        VisibleSprite { index: 3, x: 82, y: 143, height: 8, tile: 4, palette: 1, x_flip: false, y_flip: false, behind_bg: false },
+    ]);
+    assert_eq!(sprites[0].to_json(), "{\"i\":1,\"x\":-4,\"y\":24,\"w\":8,\"h\":8,\"tile\":2,\"pal\":1,\"xflip\":true,\"yflip\":true,\"behind\":true}");
+}
+
+#[test]
+fn tall_sprites_and_the_per_line_limit() {
+    // Eleven sprites on lines 0-7: the eleventh never makes it on screen
+    let mut oam: Vec<[u8; 4]> = (0..11).map(|i| [16, 8 + i * 8, 0, 0]).collect();
+    oam.push([40, 80, 7, 0]);
+    let mut core = core_with_oam(&oam);
+    assert_eq!(visible_sprites(&core.bus).iter().map(|s| s.index).collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 11]);
+
+    core.bus.ppu.lcdc |= 0x04;
+    let tall = *visible_sprites(&core.bus).last().unwrap();
+    assert_eq!((tall.index, tall.height, tall.tile), (11, 16, 6));
+}
+
+#[test]
+fn nothing_visible_with_sprites_or_lcd_off() {
+    let mut core = core_with_oam(&[[40, 40, 1, 0]]);
+    assert_eq!(visible_sprites(&core.bus).len(), 1);
+    core.bus.ppu.lcdc &= !0x02;
+    assert!(visible_sprites(&core.bus).is_empty());
+    core.bus.ppu.lcdc = 0x03;
+    assert!(visible_sprites(&core.bus).is_empty());
+}
+
+#[test]
+fn replay_frames_carry_sprites_when_enabled() {
+    let mut core = core_with_oam(&[[40, 40, 1, 0]]);
+    core.run_frame().unwrap();
+    let mut plain = ReplayCapture::new(1, "SPR");
+    let mut labelled = ReplayCapture::new(1, "SPR").with_sprites(true);
+    plain.capture(&core);
+    labelled.capture(&core);
+    assert!(!plain.to_json().contains("\"spr\""));
+    let doc = Json::parse(&labelled.to_json()).unwrap();
+    let frame = &doc.get("frames").and_then(Json::as_array).unwrap()[0];
+    let spr = frame.get("spr").and_then(Json::as_array).unwrap();
+    assert_eq!(spr.len(), 1);
+    assert_eq!(spr[0].get("x").and_then(Json::as_f64), Some(32.0));
+}
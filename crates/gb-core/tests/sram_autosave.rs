@@ -0,0 +1,94 @@
+//! SRAM autosave: battery RAM flushed on RAM disable and on a cadence
+
+use gb_core::*;
+use std::path::PathBuf;
+
+/// 32K MBC1+RAM+BATTERY cart with 8K of RAM
+fn battery_core(prog: &[u8]) -> GbCore {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x147] = 0x03;
+    rom[0x149] = 0x02;
+    rom[0x0100..0x0100 + prog.len()].copy_from_slice(prog);
+    GbCore::new(Cartridge::from_bytes(rom).unwrap())
+}
+
+fn temp_save(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mrom_autosave_{}_{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("battery.sav")
+}
+
+const ENABLE: [u8; 5] = [0x3E, 0x0A, 0xEA, 0x00, 0x00];  // LD A,0A / LD (0000),A
+const DISABLE: [u8; 4] = [0xAF, 0xEA, 0x00, 0x00];       // XOR A / LD (0000),A
+
+#[test]
+fn disabling_ram_flushes_it_at_the_end_of_the_frame() {
+    // Enable, write 0x42 to A000, disable, spin
+    let prog: Vec<u8> = ENABLE.into_iter().chain([0x3E, 0x42, 0xEA, 0x00, 0xA0]).chain(DISABLE).chain([0x18, 0xFE]).collect();
+    let mut core = battery_core(&prog);
+    let path = temp_save("disable");
+    core.autosave = Some(Box::new(SramAutosave::new(&path).with_interval(None)));
+    core.run_frame().unwrap();
+    let saved = std::fs::read(&path).unwrap();
+    assert_eq!((saved.len(), saved[0]), (0x2000, 0x42));
+    assert!(!path.with_file_name("battery.sav.tmp").exists());
+    for _ in 0..5 { core.run_frame().unwrap(); }
+    assert_eq!(core.autosave.as_ref().unwrap().flushes, 1);
+
+    let mut fresh = battery_core(&prog);
+    let mut save = SramAutosave::new(&path);
+    assert!(save.load_into(&mut fresh.bus).unwrap());
+    assert_eq!(fresh.bus.ram[0], 0x42);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn many_disables_in_a_frame_coalesce_into_one_write() {
+    // loop: enable / INC (A000) / disable / JR loop
+    let prog: Vec<u8> = ENABLE.into_iter().chain([0x21, 0x00, 0xA0, 0x34]).chain(DISABLE).chain([0x18, 0xF1]).collect();
+    let mut core = battery_core(&prog);
+    let path = temp_save("coalesce");
+    core.autosave = Some(Box::new(SramAutosave::new(&path).with_interval(None)));
+    for _ in 0..3 { core.run_frame().unwrap(); }
+    assert_eq!(core.autosave.as_ref().unwrap().flushes, 3);
+    assert_eq!(std::fs::read(&path).unwrap()[0], core.bus.ram[0]);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn ram_left_enabled_is_flushed_on_the_interval() {
+    // Enable once, then INC (A000) forever
+    let prog: Vec<u8> = ENABLE.into_iter().chain([0x21, 0x00, 0xA0, 0x34, 0x18, 0xFD]).collect();
+    let mut core = battery_core(&prog);
+    let path = temp_save("interval");
+    core.autosave = Some(Box::new(SramAutosave::new(&path).with_interval(Some(10))));
+    for _ in 0..25 { core.run_frame().unwrap(); }
+    assert_eq!(core.autosave.as_ref().unwrap().flushes, 2);
+    assert!(core.flush_sram().unwrap(), "RAM moved on since the last flush");
+    assert_eq!(std::fs::read(&path).unwrap()[0], core.bus.ram[0]);
+    assert!(!core.flush_sram().unwrap(), "nothing new to write");
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn swapping_the_rom_saves_the_outgoing_game() {
+    let prog: Vec<u8> = ENABLE.into_iter().chain([0x3E, 0x07, 0xEA, 0x00, 0xA0, 0x18, 0xFE]).collect();
+    let mut core = battery_core(&prog);
+    let path = temp_save("swap");
+    core.autosave = Some(Box::new(SramAutosave::new(&path).with_interval(None)));
+    core.run_frame().unwrap();
+    assert!(!path.exists(), "RAM is still enabled");
+    core.swap_rom(Cartridge::from_bytes(vec![0x00; 32 * 1024]).unwrap());
+    assert_eq!(std::fs::read(&path).unwrap()[0], 0x07);
+    assert!(core.autosave.is_none());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn battery_carts_are_recognised_by_header() {
+    let mut rom = vec![0u8; 0x150];
+    for (kind, battery) in [(0x01, false), (0x03, true), (0x13, true), (0x1B, true), (0x19, false)] {
+        rom[0x147] = kind;
+        assert_eq!(has_battery(&rom), battery, "{kind:#04x}");
+    }
+}
@@ -0,0 +1,98 @@
+//! SRAM journal: flushes append changed bytes, replayed on load, compacted on shutdown
+
+use gb_core::*;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// 32K MBC1+RAM+BATTERY cart with 8K of RAM
+fn battery_core(prog: &[u8]) -> GbCore {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x147] = 0x03;
+    rom[0x149] = 0x02;
+    rom[0x0100..0x0100 + prog.len()].copy_from_slice(prog);
+    GbCore::new(Cartridge::from_bytes(rom).unwrap())
+}
+
+fn temp_save(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mrom_journal_{}_{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("battery.sav")
+}
+
+/// Enable RAM once, then INC (A000) / INC (A123) forever
+const PROG: [u8; 15] = [0x3E, 0x0A, 0xEA, 0x00, 0x00, 0x21, 0x00, 0xA0, 0x34, 0x21, 0x23, 0xA1, 0x34, 0x18, 0xF6];
+
+#[test]
+fn flushes_append_to_the_journal_and_load_replays_it() {
+    let mut core = battery_core(&PROG);
+    let path = temp_save("replay");
+    core.autosave = Some(Box::new(SramAutosave::new(&path).with_interval(Some(10)).with_journal(true)));
+    while core.autosave.as_ref().unwrap().flushes < 3 { core.run_frame().unwrap(); }
+    let flushed = core.bus.ram.clone();
+    let file = std::fs::read(&path).unwrap();
+    assert_ne!(file, flushed, "only the first flush wrote the file");
+    let journal = journal_path(&path);
+    assert_eq!(std::fs::metadata(&journal).unwrap().len(), 16 + 2 * (4 + 2 * 5 + 4));
+
+    let mut fresh = battery_core(&PROG);
+    let mut save = SramAutosave::new(&path).with_journal(true);
+    assert!(save.load_into(&mut fresh.bus).unwrap());
+    assert_eq!(fresh.bus.ram, flushed);
+    let mut plain = battery_core(&PROG);
+    SramAutosave::new(&path).load_into(&mut plain.bus).unwrap();
+    assert_eq!(plain.bus.ram[..file.len()], file[..], "without the journal the file alone loads");
+
+    for _ in 0..3 { core.run_frame().unwrap(); }
+    assert!(core.flush_sram().unwrap());
+    assert!(!journal.exists(), "shutdown folds the journal into the file");
+    assert_eq!(std::fs::read(&path).unwrap(), core.bus.ram);
+    assert!(!core.flush_sram().unwrap(), "nothing new to write");
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn a_torn_record_is_dropped_and_cut_before_the_next_append() {
+    let path = temp_save("torn");
+    let base = save_hash(&[0; 4]);
+    let mut journal = SramJournal::new(&journal_path(&path));
+    journal.replay(base, &mut [0; 4]).unwrap();
+    journal.append(&[(0, 1), (2, 3)]).unwrap();
+    journal.append(&[(1, 9)]).unwrap();
+    // A crash in the middle of the third append
+    let mut f = std::fs::OpenOptions::new().append(true).open(&journal.path).unwrap();
+    f.write_all(&[2, 0, 0, 0, 3, 0, 0]).unwrap();
+    drop(f);
+
+    let mut ram = [0u8; 4];
+    let mut reopened = SramJournal::new(&journal.path);
+    assert_eq!(reopened.replay(base, &mut ram).unwrap(), 2);
+    assert_eq!(ram, [1, 9, 3, 0]);
+    reopened.append(&[(3, 7)]).unwrap();
+    let mut ram = [0u8; 4];
+    assert_eq!(SramJournal::new(&journal.path).replay(base, &mut ram).unwrap(), 3);
+    assert_eq!(ram, [1, 9, 3, 7]);
+
+    // A flipped byte fails the record's checksum
+    let mut data = std::fs::read(&journal.path).unwrap();
+    data[16 + 4] ^= 0xFF;
+    std::fs::write(&journal.path, &data).unwrap();
+    let mut ram = [0u8; 4];
+    assert_eq!(SramJournal::new(&journal.path).replay(base, &mut ram).unwrap(), 0);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn a_journal_for_another_save_file_is_ignored() {
+    let path = temp_save("stale");
+    let mut journal = SramJournal::new(&journal_path(&path));
+    journal.replay(save_hash(b"old"), &mut []).unwrap();
+    journal.append(&[(0, 0x55)]).unwrap();
+    let mut ram = [0u8; 2];
+    let mut reopened = SramJournal::new(&journal.path);
+    assert_eq!(reopened.replay(save_hash(b"new"), &mut ram).unwrap(), 0);
+    assert!(reopened.is_empty());
+    assert_eq!(ram, [0, 0]);
+    assert_eq!(sram_diff(&[1, 2, 3], &[1, 5, 3]), vec![(1, 5)]);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,94 @@
+//! GbCore::state_hash: one digest over all emulated state
+
+use gb_core::*;
+
+mod common;
+
+/// MBC1 with 8 KiB RAM, so cart RAM is part of the hashed state
+fn mbc1_core(prog: &[u8]) -> GbCore {
+    let mut rom = common::rom_with(prog);
+    rom[0x147] = 0x03;
+    rom[0x149] = 0x02;
+    GbCore::new(Cartridge::from_bytes(rom).unwrap())
+}
+
+/// LD HL,0xC000 / loop: INC (HL) / INC L / JR loop
+const WALKER: [u8; 7] = [0x21, 0x00, 0xC0, 0x34, 0x2C, 0x18, 0xFC];
+
+#[test]
+fn equal_runs_hash_equal_frame_by_frame() {
+    let (mut a, mut b) = (mbc1_core(&WALKER), mbc1_core(&WALKER));
+    let mut last = a.state_hash();
+    for _ in 0..20 {
+        a.run_frame().unwrap();
+        b.run_frame().unwrap();
+        assert_eq!(a.state_hash(), b.state_hash());
+        assert_ne!(a.state_hash(), last);
+        last = a.state_hash();
+    }
+    a.step().unwrap();
+    assert_ne!(a.state_hash(), b.state_hash());
+}
+
+type Poke = (&'static str, fn(&mut GbCore));
+
+#[test]
+fn every_region_feeds_the_hash() {
+    let mut base = mbc1_core(&WALKER);
+    base.run_frame().unwrap();
+    let h = base.state_hash();
+    let pokes: [Poke; 12] = [
+        ("a", |c| c.regs.a ^= 1),
+        ("ime", |c| c.ime = !c.ime),
+        ("vram bank 1", |c| c.bus.vram[1][0x1FFF] ^= 1),
+        ("wram bank 7", |c| c.bus.wram[7][0] ^= 1),
+        ("hram", |c| c.bus.hram[0x10] ^= 1),
+        ("oam", |c| c.bus.oam[0x9F] ^= 1),
+        ("sram", |c| c.bus.ram[0x1FFF] ^= 1),
+        ("obj palette", |c| c.bus.obj_cpal[5] ^= 1),
+        ("wave ram", |c| c.bus.apu.wave.wave_ram[3] ^= 1),
+        ("noise lfsr", |c| c.bus.apu.noise.lfsr ^= 1),
+        ("tma", |c| c.bus.timer.tma ^= 1),
+        ("rom bank", |c| c.bus.mbc.rom_bank ^= 2),
+    ];
+    for (name, poke) in pokes {
+        let mut core = mbc1_core(&WALKER);
+        core.run_frame().unwrap();
+        poke(&mut core);
+        assert_ne!(core.state_hash(), h, "{name}");
+    }
+}
+
+#[test]
+fn host_outputs_are_left_out() {
+    let mut core = mbc1_core(&WALKER);
+    core.run_frame().unwrap();
+    let h = core.state_hash();
+    core.bus.apu.sample_buffer.clear();
+    core.bus.ppu.framebuffer.fill(3);
+    core.trace = Some(TraceRing::new(16));
+    assert_eq!(core.state_hash(), h);
+
+    let mut lite = GbCore::with_config(
+        Cartridge::from_bytes(mbc1_core(&WALKER).bus.rom.clone()).unwrap(),
+        CoreConfig { lite: LiteMode { skip_audio: false, render: RenderSkip::AlternateScanlines }, ..CoreConfig::default() },
+    );
+    lite.run_frame().unwrap();
+    assert_eq!(lite.state_hash(), h, "rendering less does not change the machine");
+}
+
+#[test]
+fn cores_loading_one_savestate_stay_in_step() {
+    let mut core = mbc1_core(&WALKER);
+    for _ in 0..7 { core.run_frame().unwrap(); }
+    core.run_cycles(1234).unwrap();
+    let state = core.save_state();
+    let (mut a, mut b) = (mbc1_core(&WALKER), mbc1_core(&WALKER));
+    a.load_state(&state).unwrap();
+    b.load_state(&state).unwrap();
+    for _ in 0..5 {
+        assert_eq!(a.state_hash(), b.state_hash());
+        a.run_frame().unwrap();
+        b.run_frame().unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+//! Savestate import from Gambatte and VBA-M
+
+use gb_core::*;
+
+fn core(title: &str) -> GbCore {
+    let rom = RomBuilder::new().title(title).cart_type(0x03).ram_kb(8).code(&[0x18, 0xFE]).build();
+    GbCore::new(Cartridge::from_bytes(rom).unwrap())
+}
+
+/// Gambatte's labelled layout: label, NUL, 24-bit big-endian length, value
+fn gqs(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut out = vec![0x00, 0x01, 0x00, 0x00, 0x00];
+    for (label, value) in entries {
+        out.extend_from_slice(label.as_bytes());
+        out.push(0);
+        out.extend_from_slice(&(value.len() as u32).to_be_bytes()[1..]);
+        out.extend_from_slice(value);
+    }
+    out
+}
+
+#[test]
+fn imports_a_gambatte_state() {
+    let mut ioamhram = vec![0u8; 0x200];
+    ioamhram[0x00] = 0x50;          // OAM: first sprite's Y
+    ioamhram[0x100 + 0x0F] = 0x01;  // IF
+    ioamhram[0x100 + 0x40] = 0x91;  // LCDC
+    ioamhram[0x100 + 0x43] = 0x28;  // SCX
+    ioamhram[0x100 + 0x44] = 0x90;  // LY, in VBlank
+    ioamhram[0x180] = 0x99;         // HRAM
+    ioamhram[0x1FF] = 0x05;         // IE
+    let mut wram = vec![0u8; 0x2000];
+    wram[0x0010] = 0xAB;
+    wram[0x1020] = 0xCD;
+    let state = gqs(&[
+        ("cc", &[0, 0, 0x12, 0x34]),
+        ("pc", &[0x01, 0x50]), ("sp", &[0xDF, 0xF0]),
+        ("a", &[0x11]), ("b", &[0x22]), ("c", &[0x33]), ("d", &[0x44]), ("e", &[0x55]),
+        ("f", &[0xB7]), ("h", &[0x66]), ("l", &[0x77]),
+        ("skip", &[0]), ("halt", &[1]), ("ime", &[1]),
+        ("vram", &[0x3C; 0x2000]), ("sram", &[0x5A; 0x2000]), ("wram", &wram), ("hram", &ioamhram),
+        ("rombank", &[0x00, 0x01]), ("sramon", &[1]),
+        ("rtcbase", &[0; 4]),
+    ]);
+    assert_eq!(ForeignState::detect(&state), Some(ForeignState::Gambatte));
+
+    let mut c = core("IMPORT");
+    let report = import_state(&mut c, &state).unwrap();
+    assert_eq!(report.format, ForeignState::Gambatte);
+    assert_eq!(report.unconverted, ["cc", "rtcbase"]);
+    assert!(report.converted.iter().any(|l| l == "hram"));
+
+    let r = &c.regs;
+    assert_eq!((r.pc, r.sp, r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l), (0x0150, 0xDFF0, 0x11, 0xB0, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77));
+    assert!(c.halted && c.ime);
+    assert_eq!((c.bus.peek(0xC010), c.bus.peek(0xD020), c.bus.peek(0x8000), c.bus.peek(0xA000)), (0xAB, 0xCD, 0x3C, 0x5A));
+    assert_eq!((c.bus.peek(0xFE00), c.bus.peek(0xFF80), c.bus.ie, c.bus.if_reg), (0x50, 0x99, 0x05, 0x01));
+    assert_eq!((c.bus.ppu.lcdc, c.bus.ppu.scx, c.bus.ppu.ly, c.bus.ppu.mode), (0x91, 0x28, 0x90, PpuMode::VBlank));
+    c.run_frame().unwrap();
+
+    let json = Json::parse(&report.to_json()).unwrap();
+    assert_eq!(json.get("format").and_then(Json::as_str), Some("gambatte"));
+}
+
+#[test]
+fn importing_drops_blocks_cached_from_the_old_memory() {
+    let mut c = core("IMPORT");
+    c.bus.block_cache = Some(Box::default());
+    for (i, b) in [0x3C, 0x18, 0xFD].into_iter().enumerate() { c.bus.write(0xC000 + i as u16, b); } // inc a; jr -3
+    c.regs.pc = 0xC000;
+    for _ in 0..10 { c.step().unwrap(); }
+    assert!(!c.bus.block_cache.as_ref().unwrap().is_empty() && c.regs.a > 0x01);
+
+    let mut wram = vec![0u8; 0x2000];
+    wram[..3].copy_from_slice(&[0x3D, 0x18, 0xFD]); // dec a; jr -3
+    import_state(&mut c, &gqs(&[("pc", &[0xC0, 0x00]), ("a", &[0x10]), ("wram", &wram)])).unwrap();
+    for _ in 0..10 { c.step().unwrap(); }
+    assert!(c.regs.a < 0x10, "ran the imported code, not the cached block: a = {:#04x}", c.regs.a);
+}
+
+#[test]
+fn importing_starts_a_new_time_travel_history() {
+    let mut c = core("IMPORT");
+    c.time_travel = Some(Box::new(TimeTravel::new(10, 10)));
+    c.run_frame().unwrap();
+    assert!(c.time_travel.as_ref().unwrap().keyframes() > 0);
+    import_state(&mut c, &gqs(&[("pc", &[0x01, 0x50])])).unwrap();
+    assert_eq!(c.time_travel.as_ref().unwrap().keyframes(), 0);
+    assert!(!c.reverse_step(), "no rewinding into the state from before the import");
+}
+
+#[test]
+fn gambatte_states_from_another_cartridge_are_refused_untouched() {
+    let mut c = core("IMPORT");
+    let pc = c.regs.pc;
+    let err = import_state(&mut c, &gqs(&[("pc", &[0x12, 0x34]), ("sram", &[0; 0x8000])])).unwrap_err().to_string();
+    assert!(err.contains("32768 bytes of cart RAM, \"IMPORT\" has 8192"), "{err}");
+    let err = import_state(&mut c, &gqs(&[("pc", &[0x12, 0x34]), ("vram", &[0; 0x4000])])).unwrap_err().to_string();
+    assert!(err.contains("CGB game"), "{err}");
+    assert_eq!(c.regs.pc, pc);
+}
+
+/// gzip of a VBA-M v12 state head for title "IMPORT": PC 0150, SP DFF0,
+/// AF 11B0, BC 0013, DE 00D8, HL 014D, IFF 81, then filler (dynamic Huffman)
+const SGM_GZ: [u8; 126] = [
+    0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xED, 0xCA, 0xB1, 0x09, 0xC2, 0x50,
+    0x14, 0x40, 0xD1, 0x97, 0xDE, 0x46, 0x5C, 0xE0, 0xED, 0xE1, 0x04, 0x16, 0xC1, 0x20, 0x2E, 0x90,
+    0xE2, 0x07, 0x85, 0x60, 0xC0, 0xFF, 0x17, 0x70, 0x23, 0xC7, 0xB1, 0x73, 0x05, 0x47, 0x10, 0x1B,
+    0x71, 0x05, 0xE1, 0x9C, 0xEA, 0x16, 0x77, 0x15, 0x11, 0xBB, 0x7E, 0xD8, 0x1F, 0x8E, 0xF1, 0x6B,
+    0xE8, 0x5E, 0xCF, 0xFB, 0x7A, 0x13, 0x8F, 0xE8, 0xBB, 0x5B, 0x3B, 0x95, 0xBC, 0x96, 0xDA, 0x72,
+    0x99, 0xF2, 0xD3, 0xB5, 0x8D, 0xAD, 0xE4, 0xB9, 0xE6, 0x5C, 0xA6, 0x96, 0xE3, 0xBC, 0x5C, 0xCA,
+    0x36, 0x4D, 0x26, 0x93, 0xC9, 0x64, 0x32, 0x99, 0x4C, 0xDF, 0x29, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x80, 0xBF, 0xF7, 0x06, 0xB4, 0xBD, 0x3F, 0xD4, 0x64, 0x29, 0x00, 0x00,
+];
+
+#[test]
+fn imports_vbam_registers_from_a_compressed_state() {
+    assert_eq!(ForeignState::detect(&SGM_GZ), Some(ForeignState::VbaM));
+    let mut c = core("IMPORT");
+    let report = import_state(&mut c, &SGM_GZ).unwrap();
+    assert_eq!(report.format, ForeignState::VbaM);
+    assert!(report.unconverted.iter().any(|s| s == "memory"));
+    let r = &c.regs;
+    assert_eq!((r.pc, r.sp, r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l), (0x0150, 0xDFF0, 0x11, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D));
+    assert!(c.ime && c.halted);
+
+    let err = import_state(&mut core("OTHER"), &SGM_GZ).unwrap_err().to_string();
+    assert!(err.contains("state is for \"IMPORT\""), "{err}");
+}
+
+#[test]
+fn old_uncompressed_vbam_states_have_no_bios_flag() {
+    let mut sgm = 6u32.to_le_bytes().to_vec();
+    sgm.extend_from_slice(b"IMPORT\0\0\0\0\0\0\0\0\0");
+    for w in [0x0200u16, 0xFFFE, 0x01B0, 0x0013, 0x00D8, 0x014D] { sgm.extend_from_slice(&w.to_le_bytes()); }
+    sgm.push(0x00);
+    let mut c = core("IMPORT");
+    import_state(&mut c, &sgm).unwrap();
+    assert_eq!((c.regs.pc, c.regs.sp, c.regs.a), (0x0200, 0xFFFE, 0x01));
+    assert!(!c.ime && !c.halted);
+}
+
+/// gzip of one fixed-Huffman block: a zero literal, then `copies` 258-byte
+/// back-references at distance 1 (1 + 258·copies zero bytes)
+fn zeros_gz(copies: usize) -> Vec<u8> {
+    let mut gz = vec![0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF];
+    let mut bits = vec![1, 1, 0];
+    let mut code = |c: u32, len: u32| bits.extend((0..len).rev().map(|i| (c >> i) & 1));
+    code(0x30, 8);
+    for _ in 0..copies { code(0xC5, 8); code(0, 5); }
+    code(0, 7);
+    gz.extend(bits.chunks(8).map(|byte| byte.iter().enumerate().fold(0u8, |acc, (i, &b)| acc | (b as u8) << i)));
+    gz
+}
+
+#[test]
+fn compressed_states_are_capped_before_inflating_past_any_real_state() {
+    let mut c = core("IMPORT");
+    let small = import_state(&mut c, &zeros_gz(100)).unwrap_err().to_string();
+    assert!(small.contains("malformed VBA-M state"), "inflated, then rejected as a state: {small}");
+    let bomb = import_state(&mut c, &zeros_gz(8 * 1024)).unwrap_err().to_string();
+    assert!(bomb.contains("output larger than"), "{bomb}");
+}
+
+#[test]
+fn rejects_other_data() {
+    let mut c = core("IMPORT");
+    assert_eq!(ForeignState::detect(b"{\"version\":\"mrom.sav.v1\"}"), None);
+    assert!(import_state(&mut c, b"{\"version\":\"mrom.sav.v1\"}").is_err());
+    assert!(import_state(&mut c, &SGM_GZ[..40]).is_err(), "truncated gzip");
+    let mut broken = gqs(&[("pc", &[0x01, 0x50])]);
+    broken.truncate(broken.len() - 1);
+    assert_ne!(ForeignState::detect(&broken), Some(ForeignState::Gambatte));
+}
@@ -0,0 +1,39 @@
+//! Savestate metadata and the slot index
+
+use gb_core::{Cartridge, GbCore, StateIndex, StateMeta, THUMB_HEIGHT, THUMB_WIDTH};
+
+#[test]
+fn states_carry_meta_and_index_by_directory() {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x0134..0x0139].copy_from_slice(b"SL{T}");
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    core.bus.ppu.lcdc = 0x91;
+    for _ in 0..3 { core.run_frame().unwrap(); }
+
+    let state = core.save_state();
+    let meta = StateMeta::from_state(&state).expect("meta");
+    assert_eq!(meta.rom_title, "SL{T}");
+    assert_eq!(meta.frame, 3);
+    assert_eq!(meta.play_time_ms, core.clock.t_cycles * 1000 / gb_core::CPU_HZ);
+    assert_eq!(meta.save_point, "instruction");
+    assert_eq!(meta.thumbnail.len(), THUMB_WIDTH * THUMB_HEIGHT * 3);
+
+    // Meta does not disturb restoring
+    let mut other = GbCore::new(Cartridge::from_bytes(vec![0x00u8; 32 * 1024]).unwrap());
+    other.load_state(&state).unwrap();
+    assert_eq!(other.clock.t_cycles, core.clock.t_cycles);
+
+    let dir = std::env::temp_dir().join(format!("mrom_state_index_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    core.save_state_to_file(&dir.join("slot1.mrom.sav")).unwrap();
+    std::fs::write(dir.join("old.mrom.sav"), b"{\"version\":\"mrom.sav.v1\"}").unwrap();
+    std::fs::write(dir.join("notes.txt"), b"x").unwrap();
+
+    let index = StateIndex::scan(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).ok();
+    assert_eq!(index.slots.len(), 1);
+    assert_eq!(index.skipped.len(), 1);
+    assert_eq!(index.slots[0].meta, meta);
+    assert_eq!(index.for_rom(&meta.rom_hash).count(), 1);
+    assert!(index.to_json().contains("mrom.state_index.v1"));
+}
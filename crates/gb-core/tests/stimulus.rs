@@ -0,0 +1,48 @@
+//! External stimulus providers feeding the IR port
+
+use gb_core::{Cartridge, EveryCycles, GbCore, StimulusContext, StimulusInputs};
+use std::sync::{Arc, Mutex};
+
+fn core() -> GbCore {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x0100] = 0x18; rom[0x0101] = 0xFE; // JR -2
+    GbCore::new(Cartridge::from_bytes(rom).unwrap())
+}
+
+#[test]
+fn frame_provider_drives_ir_receiver() {
+    let mut core = core();
+    core.bus.write(0xFF56, 0xC0); // enable reading
+    assert_eq!(core.bus.read(0xFF56) & 0x02, 0x02);
+
+    core.set_stimulus_provider(Some(Box::new(|ctx: &StimulusContext, inp: &mut StimulusInputs| {
+        inp.ir_light = ctx.frame % 2 == 1;
+    })));
+    core.run_frame().unwrap(); // frame 0: dark
+    assert_eq!(core.bus.read(0xFF56) & 0x02, 0x02);
+    core.run_frame().unwrap(); // frame 1: light
+    assert_eq!(core.bus.read(0xFF56) & 0x02, 0x00);
+
+    core.bus.write(0xFF56, 0x00); // reading disabled masks the receiver
+    assert_eq!(core.bus.read(0xFF56) & 0x02, 0x02);
+}
+
+#[test]
+fn cycle_provider_sees_led() {
+    let mut core = core();
+    let seen = Arc::new(Mutex::new((0u32, false)));
+    let log = Arc::clone(&seen);
+    core.set_stimulus_provider(Some(Box::new(EveryCycles {
+        n: 456,
+        inner: move |ctx: &StimulusContext, _: &mut StimulusInputs| {
+            let mut l = log.lock().unwrap();
+            l.0 += 1;
+            l.1 |= ctx.ir_led;
+        },
+    })));
+    core.bus.write(0xFF56, 0x01);
+    core.run_frame().unwrap();
+    let (calls, led) = *seen.lock().unwrap();
+    assert!((154..=156).contains(&calls), "{calls} updates");
+    assert!(led);
+}
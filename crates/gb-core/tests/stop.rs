@@ -0,0 +1,80 @@
+//! STOP: stop mode, joypad wake-up, DIV reset and the CGB speed switch
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+/// LD A,<p1> / LDH (P1),A / STOP / INC A (skipped) / LD B,0x42 / JR -2
+fn stopping(p1: u8) -> GbCore { core_with(&[0x3E, p1, 0xE0, 0x00, 0x10, 0x3C, 0x06, 0x42, 0x18, 0xFE]) }
+
+/// Run up to the STOP opcode with DIV well away from zero
+fn to_stop(core: &mut GbCore) {
+    core.bus.timer.set_div_counter(0x1234);
+    core.step().unwrap();
+    core.step().unwrap();
+    assert_eq!(core.regs.pc, 0x0104);
+}
+
+#[test]
+fn stop_freezes_cpu_lcd_and_div_until_a_selected_button() {
+    let mut core = stopping(0x20); // directions selected
+    to_stop(&mut core);
+    core.step().unwrap();
+    assert!(core.stopped);
+    assert_eq!(core.regs.pc, 0x0106, "the byte after STOP is skipped");
+    let div = core.bus.timer.div_counter();
+    assert!(div < 0x10, "STOP resets DIV");
+
+    let (ly, dot, t) = (core.bus.ppu.ly, core.bus.ppu.dot, core.clock.t_cycles);
+    core.run_frame().unwrap();
+    assert!(core.stopped);
+    assert!(core.clock.t_cycles >= t + CYCLES_PER_FRAME, "time still passes for the host");
+    assert_eq!((core.bus.ppu.ly, core.bus.ppu.dot, core.bus.timer.div_counter()), (ly, dot, div));
+
+    core.set_buttons(BTN_A);
+    for _ in 0..4 { core.step().unwrap(); }
+    assert!(core.stopped, "A is on the deselected line");
+    core.set_buttons(BTN_A | BTN_RIGHT);
+    for _ in 0..2 { core.step().unwrap(); }
+    assert!(!core.stopped);
+    assert_eq!((core.regs.a, core.regs.b), (0x20, 0x42));
+}
+
+#[test]
+fn held_button_halts_instead() {
+    let mut core = stopping(0x10); // buttons selected
+    core.set_buttons(BTN_START);
+    core.bus.if_reg = 0;
+    to_stop(&mut core);
+    core.step().unwrap();
+    assert!(!core.stopped);
+    assert!(core.halted);
+    assert_eq!(core.regs.pc, 0x0106);
+    assert!(core.bus.timer.div_counter() > 0x1234, "DIV is not reset");
+}
+
+#[test]
+fn armed_speed_switch_does_not_stop() {
+    let mut core = stopping(0x30);
+    core.bus.speed_switch_armed = true;
+    to_stop(&mut core);
+    core.step().unwrap();
+    assert!(!core.stopped);
+    assert!(core.bus.double_speed && !core.bus.speed_switch_armed);
+    assert_eq!(core.regs.pc, 0x0106);
+    assert!(core.bus.timer.div_counter() < 0x100, "DIV was reset");
+}
+
+#[test]
+fn stop_mode_survives_savestates() {
+    let mut core = stopping(0x20);
+    to_stop(&mut core);
+    core.step().unwrap();
+    let state = core.save_state();
+    let mut other = stopping(0x20);
+    other.load_state(&state).unwrap();
+    assert!(other.stopped);
+    other.step().unwrap();
+    assert_eq!(other.regs.pc, 0x0106);
+}
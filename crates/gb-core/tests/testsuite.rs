@@ -0,0 +1,66 @@
+//! Mixed test-ROM directory classified into one report
+
+use gb_core::*;
+
+fn rom(code: Code) -> Vec<u8> { RomBuilder::new().title("SUITE").code(code.bytes()).build() }
+
+fn mooneye_pass() -> Vec<u8> {
+    rom(Code::new(CODE_START).ld_bc(0x0305).ld_de(0x080D).ld_hl(0x1522).raw(&[0x40]).spin())
+}
+
+/// LCD on, then LD B,B with no register signature: a picture test
+fn picture() -> Vec<u8> {
+    rom(Code::new(CODE_START).ld_a(0x91).st_a(0xFF40).raw(&[0x40]).spin())
+}
+
+#[test]
+fn each_rom_is_classified_by_how_it_reports() {
+    let serial = run_suite_rom("blargg/01.gb", rom(Code::new(CODE_START).serial_print("Passed\n").spin()), None, 10);
+    assert_eq!((serial.method, serial.outcome.clone()), (TestMethod::Serial, TestOutcome::Pass));
+
+    let mooneye = run_suite_rom("mts/ie_push.gb", mooneye_pass(), None, 10);
+    assert_eq!((mooneye.method, mooneye.outcome, mooneye.frames), (TestMethod::Mooneye, TestOutcome::Pass, 1));
+
+    let unreferenced = run_suite_rom("dmg-acid2.gb", picture(), None, 5);
+    assert_eq!((unreferenced.method, unreferenced.outcome.clone()), (TestMethod::None, TestOutcome::Timeout));
+
+    let good = unreferenced.frame_hash;
+    let matched = run_suite_rom("dmg-acid2.gb", picture(), Some(good), 5);
+    assert_eq!((matched.method, matched.outcome, matched.frames), (TestMethod::FrameHash, TestOutcome::Pass, 1));
+    let wrong = run_suite_rom("dmg-acid2.gb", picture(), Some(good ^ 1), 5);
+    assert_eq!(wrong.outcome, TestOutcome::Fail(format!("frame hash {good:016x}, expected {:016x}", good ^ 1)));
+
+    let bad = run_suite_rom("bad.gb", vec![0; 16], None, 5);
+    assert!(matches!(bad.outcome, TestOutcome::Fail(_)));
+}
+
+#[test]
+fn a_directory_run_reports_json_and_seeds_reference_hashes() {
+    let dir = std::env::temp_dir().join(format!("mrom_testsuite_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("mooneye")).unwrap();
+    std::fs::write(dir.join("mooneye/ie_push.gb"), mooneye_pass()).unwrap();
+    std::fs::write(dir.join("dmg-acid2.gb"), picture()).unwrap();
+    std::fs::write(dir.join("hang.gbc"), rom(Code::new(CODE_START).spin())).unwrap();
+    std::fs::write(dir.join("notes.txt"), "not a ROM").unwrap();
+
+    let mut seen = vec![];
+    let first = run_suite_dir(&dir, &[], 3, |r| seen.push(r.rom.clone())).unwrap();
+    assert_eq!(seen, ["dmg-acid2.gb", "hang.gbc", "mooneye/ie_push.gb"]);
+    assert_eq!((first.passed(), first.total()), (1, 3));
+
+    let hashes = parse_frame_hashes(&Json::parse(&first.frame_hashes_json()).unwrap()).unwrap();
+    assert_eq!(hashes.len(), 3);
+    let picture_only: Vec<(String, u64)> = hashes.into_iter().filter(|(rom, _)| rom.contains("acid")).collect();
+    let second = run_suite_dir(&dir, &picture_only, 3, |_| {}).unwrap();
+    assert_eq!(second.by_method(), [(TestMethod::FrameHash, 1, 1), (TestMethod::Mooneye, 1, 1), (TestMethod::None, 0, 1)]);
+
+    let doc = Json::parse(&second.to_json()).unwrap();
+    assert_eq!(doc.get("version").and_then(Json::as_str), Some("mrom.testsuite.v1"));
+    assert_eq!(doc.get("passed").and_then(Json::as_u64), Some(2));
+    let results = doc.get("results").and_then(Json::as_array).unwrap();
+    assert_eq!(results[0].get("method").and_then(Json::as_str), Some("frame_hash"));
+    assert_eq!(results[1].get("outcome").and_then(Json::as_str), Some("timeout"));
+    assert_eq!(results[2].get("rom").and_then(Json::as_str), Some("mooneye/ie_push.gb"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,112 @@
+//! On-screen text extraction through glyph tables
+
+use gb_core::*;
+
+fn alphabet() -> GlyphTable {
+    let mut t = GlyphTable::default();
+    t.set_run(0x7F, " ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+    t
+}
+
+fn tiles(s: &str) -> Vec<u8> { s.bytes().map(|b| if b == b' ' { 0x7F } else { b - b'A' + 0x80 }).collect() }
+
+/// Write `s` into the tilemap at `map` (0x1800 / 0x1C00), cell (col, row)
+fn put(core: &mut GbCore, map: usize, col: usize, row: usize, s: &str) {
+    let at = map + row * 32 + col;
+    core.bus.vram[0][at..at + s.len()].copy_from_slice(&tiles(s));
+}
+
+fn screen() -> GbCore {
+    let rom = RomBuilder::new().title("TEXTTEST").code(&[0x18, 0xFE]).build();
+    let mut core = GbCore::new(Cartridge::from_bytes(rom).unwrap());
+    core.bus.ppu.lcdc = 0x91;
+    core
+}
+
+fn line(layer: TextLayer, col: u8, row: u8, text: &str) -> ScreenText { ScreenText { layer, col, row, text: text.into() } }
+
+#[test]
+fn reads_runs_of_known_glyphs_from_the_bg() {
+    let mut core = screen();
+    put(&mut core, 0x1800, 1, 2, "  HELLO WORLD ");
+    put(&mut core, 0x1800, 3, 5, "X");
+    put(&mut core, 0x1800, 21, 6, "OFFSCREEN");
+    core.bus.vram[0][0x1800 + 7 * 32..][..5].copy_from_slice(&[0x80, 0x81, 0x01, 0x82, 0x83]);
+    assert_eq!(screen_text(&core.bus, &alphabet()), [
+        line(TextLayer::Bg, 3, 2, "HELLO WORLD"),
+        line(TextLayer::Bg, 0, 7, "AB"),
+        line(TextLayer::Bg, 3, 7, "CD"),
+    ]);
+
+    core.bus.ppu.scx = 8;
+    core.bus.ppu.scy = 16;
+    assert_eq!(screen_text(&core.bus, &alphabet())[0], line(TextLayer::Bg, 2, 0, "HELLO WORLD"), "scrolled a tile left and two up");
+}
+
+#[test]
+fn window_covers_the_bg() {
+    let mut core = screen();
+    put(&mut core, 0x1800, 0, 1, "TITLE");
+    put(&mut core, 0x1800, 0, 15, "HIDDEN");
+    put(&mut core, 0x1C00, 2, 0, "HP");
+    put(&mut core, 0x1C00, 0, 2, "FIGHT");
+    core.bus.ppu.lcdc |= 0x60;
+    core.bus.ppu.wx = 7;
+    core.bus.ppu.wy = 112;
+    assert_eq!(screen_text(&core.bus, &alphabet()), [
+        line(TextLayer::Bg, 0, 1, "TITLE"),
+        line(TextLayer::Window, 2, 14, "HP"),
+        line(TextLayer::Window, 0, 16, "FIGHT"),
+    ]);
+    assert_eq!(screen_text(&core.bus, &alphabet())[1].to_json(), r#"{"layer":"win","col":2,"row":14,"text":"HP"}"#);
+
+    core.bus.ppu.lcdc &= !0x80;
+    assert!(screen_text(&core.bus, &alphabet()).is_empty(), "LCD off");
+}
+
+#[test]
+fn tables_by_hash_beat_tables_by_title() {
+    let core = screen();
+    let hash = rom_hash(&core.bus.rom);
+    let mut tables = GlyphTables::default();
+    let mut digits = GlyphTable::default();
+    digits.set_run(0x10, "0123456789");
+    tables.set_title("TEXTTEST", alphabet());
+    assert_eq!(tables.for_core(&core), Some(&alphabet()));
+    tables.set_game(&hash, "TEXTTEST", digits.clone());
+    assert_eq!(tables.for_core(&core), Some(&digits));
+    assert_eq!(tables.lookup("0000", "NOPE"), None);
+
+    let builtin = GlyphTables::builtin();
+    assert_eq!(builtin.lookup("", "POKEMON RED").and_then(|t| t.get(0x80)), Some('A'));
+}
+
+#[test]
+fn json_round_trip() {
+    let mut tables = GlyphTables::builtin();
+    tables.set_game("abcd", "SOME \"GAME\"", alphabet());
+    let back = GlyphTables::from_json(&tables.to_json()).unwrap();
+    assert_eq!(back, tables);
+    assert_eq!(alphabet().to_json(), r#"{"7F":" ABCDEFGHIJKLMNOPQRSTUVWXYZ"}"#);
+
+    assert!(GlyphTables::from_json(r#"{"version":"mrom.glyphs.v0"}"#).is_err());
+    let err = GlyphTables::from_json(r#"{"version":"mrom.glyphs.v1","titles":{"X":{"FE":"abc"}}}"#).unwrap_err();
+    assert!(err.contains("past tile FF"), "{err}");
+    assert!(GlyphTables::from_json(r#"{"version":"mrom.glyphs.v1","titles":{"X":{"G0":"a"}}}"#).is_err());
+}
+
+#[test]
+fn replay_frames_carry_text_when_a_table_is_set() {
+    let mut core = screen();
+    core.run_frame().unwrap();
+    put(&mut core, 0x1800, 0, 0, "START");
+    let mut plain = ReplayCapture::new(1, "TEXT");
+    let mut reading = ReplayCapture::new(1, "TEXT").with_text(Some(alphabet()));
+    plain.capture(&core);
+    reading.capture(&core);
+    assert!(!plain.to_json().contains("\"txt\""));
+    let doc = Json::parse(&reading.to_json()).unwrap();
+    let frame = &doc.get("frames").and_then(Json::as_array).unwrap()[0];
+    let txt = frame.get("txt").and_then(Json::as_array).unwrap();
+    assert_eq!(txt[0].get("text").and_then(Json::as_str), Some("START"));
+}
@@ -0,0 +1,52 @@
+//! Throttle pacing and speed measurement against a fixed clock
+
+use gb_core::*;
+
+#[test]
+fn clock_sync_schedules_frames_from_the_start() {
+    let mut t = Throttle::new().with_fps(50.0);
+    assert_eq!(t.frame_delay_us(0, 0), 20_000);
+    // The frame took 5 ms: only the rest of the period is slept
+    assert_eq!(t.frame_delay_us(25_000, 0), 15_000);
+    // A late frame is made up on the next one
+    assert_eq!(t.frame_delay_us(63_000, 0), 0);
+    assert_eq!(t.frame_delay_us(70_000, 0), 10_000);
+    assert!((t.speed_percent() - 50.0 / GB_FPS * 100.0 * 3.0 / 3.5).abs() < 0.01, "{}", t.speed_percent());
+
+    // A stall longer than MAX_LAG_FRAMES restarts the schedule instead of bursting
+    assert_eq!(t.frame_delay_us(1_000_000, 0), 0);
+    assert_eq!(t.frame_delay_us(1_001_000, 0), 19_000);
+
+    t.set_turbo(2.0);
+    assert_eq!(t.frame_delay_us(1_020_000, 0), 10_000);
+    t.set_turbo(0.0);
+    assert_eq!((t.frame_us(), t.frame_delay_us(1_021_000, 0)), (0.0, 0));
+}
+
+#[test]
+fn audio_sync_waits_for_the_queue_to_drain() {
+    let mut t = Throttle::new().with_audio_sync(48_000, 1_600);
+    assert_eq!(t.frame_delay_us(0, 800), 0);
+    assert_eq!(t.frame_delay_us(1_000, 2_080), 10_000);
+    // Turbo outruns the audio device, so it falls back to the clock
+    t.set_turbo(4.0);
+    assert_eq!(t.frame_delay_us(2_000, 10_000), (1e6 / (GB_FPS * 4.0)) as u64);
+}
+
+#[test]
+fn speed_is_measured_over_a_rolling_window() {
+    let clock = FixedClock::new(0);
+    let mut t = Throttle::new().with_turbo(0.0);
+    assert_eq!(t.speed_percent(), 0.0);
+    let half_speed = (2e6 / GB_FPS) as u64;
+    for _ in 0..80 {
+        t.wait(&clock, 0);
+        clock.advance(half_speed);
+    }
+    assert!((t.speed_percent() - 50.0).abs() < 0.5, "{}", t.speed_percent());
+    for _ in 0..80 {
+        t.wait(&clock, 0);
+        clock.advance(half_speed / 4);
+    }
+    assert!((t.speed_percent() - 200.0).abs() < 2.0, "{}", t.speed_percent());
+}
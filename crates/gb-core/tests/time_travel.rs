@@ -0,0 +1,137 @@
+//! Time travel: reverse stepping and reverse continue by replay from keyframes
+
+use gb_core::*;
+
+/// Counts loop passes in WRAM, copies the button row to $C001, calls a
+/// counter at $0200 and halts until the next VBlank or timer interrupt,
+/// with square 1 playing
+const LOOP: &str = "
+        ld a, $80
+        ldh [$26], a
+        ld a, $f0
+        ldh [$12], a
+        ld a, $c7
+        ldh [$14], a
+        ld a, $05
+        ldh [$07], a
+        ld a, $05
+        ldh [$ff], a
+        ei
+        ld hl, $c000
+    loop:
+        inc [hl]
+        ld a, $10
+        ldh [$00], a
+        ldh a, [$00]
+        ld [$c001], a
+        call count
+        halt
+        jr loop
+        org $40
+        reti
+        org $50
+        reti
+        org $200
+    count:
+        ldh a, [$80]
+        inc a
+        ldh [$80], a
+        ret
+";
+
+fn core(interval: u64, capacity: usize) -> GbCore {
+    let mut core = GbCore::new(RomBuilder::new().asm(LOOP).unwrap().cartridge().unwrap());
+    core.fast_halt = true;
+    core.time_travel = Some(Box::new(TimeTravel::new(interval, capacity)));
+    core
+}
+
+fn position(core: &GbCore) -> u64 { core.time_travel.as_ref().unwrap().position() }
+
+#[test]
+fn reverse_step_retraces_every_step() {
+    let mut core = core(50, 8);
+    core.trace = Some(TraceRing::new(1000));
+    core.run_frame().unwrap();
+    let mut hashes = vec![];
+    for i in 0..600 {
+        if i == 200 { core.set_buttons(BTN_A); }
+        if i == 450 { core.set_buttons(0); }
+        hashes.push((position(&core), core.state_hash()));
+        core.step().unwrap();
+    }
+    let earliest = core.time_travel.as_ref().unwrap().earliest().unwrap();
+    assert_eq!(core.time_travel.as_ref().unwrap().keyframes(), 8);
+    while core.reverse_step() {
+        let pos = position(&core);
+        let &(_, hash) = hashes.iter().find(|(p, _)| *p == pos).unwrap_or(&(pos, core.state_hash()));
+        assert_eq!(core.state_hash(), hash, "position {pos}");
+        let last = core.trace.as_ref().unwrap().last().unwrap();
+        assert!(last.t_cycles < core.clock.t_cycles, "the trace ends before the restored point");
+    }
+    assert_eq!(position(&core), earliest, "history reaches back 8 keyframes");
+    assert!(earliest > hashes[0].0);
+
+    // Stepping forward again replays the same future
+    for &(pos, hash) in hashes.iter().filter(|(p, _)| *p >= earliest).take(100) {
+        if pos == hashes[200].0 { core.set_buttons(BTN_A); }
+        assert_eq!((position(&core), core.state_hash()), (pos, hash));
+        core.step().unwrap();
+    }
+}
+
+#[test]
+fn reverse_step_undoes_whole_frames() {
+    let mut core = core(500, 1000);
+    core.run_frame().unwrap();
+    let start = (position(&core), core.state_hash(), core.bus.ppu.framebuffer.clone());
+    for _ in 0..3 { core.run_frame().unwrap(); }
+    core.bus.apu.drain_samples();
+    while position(&core) > start.0 { assert!(core.reverse_step()); }
+    assert_eq!((position(&core), core.state_hash(), core.bus.ppu.framebuffer.clone()), start);
+    assert!(core.bus.apu.drain_samples().is_empty());
+}
+
+#[test]
+fn reverse_continue_stops_before_the_last_breakpoint_hit() {
+    let mut core = core(100, 100);
+    for _ in 0..3 { core.run_frame().unwrap(); }
+    let calls = core.bus.read(0xFF80);
+    assert!(calls > 2);
+    let id = core.breakpoints.add(0x0200);
+
+    let hit = core.reverse_continue().unwrap();
+    assert_eq!((hit.id, hit.pc, hit.hits, hit.t_cycles), (id, 0x0200, 0, core.clock.t_cycles));
+    assert_eq!((core.regs.pc, core.bus.read(0xFF80)), (0x0200, calls - 1), "the last call, not yet counted");
+    assert_eq!(core.reverse_continue().unwrap().pc, 0x0200);
+    assert_eq!(core.bus.read(0xFF80), calls - 2);
+
+    // Forward from the stop runs the instruction at the breakpoint
+    core.step().unwrap();
+    assert_eq!(core.regs.pc, 0x0202);
+    assert!(matches!(core.run_frame(), Err(CoreError::Break(BreakHit { pc: 0x0200, hits: 1, .. }))));
+
+    // A condition that never held: back to the start of the history
+    core.breakpoints.clear();
+    core.breakpoints.add_if(0x0200, vec![BreakCondition::new(BreakReg::SP, BreakCmp::Eq, 0x1234)]);
+    assert_eq!(core.reverse_continue(), None);
+    assert_eq!(Some(position(&core)), core.time_travel.as_ref().unwrap().earliest());
+}
+
+#[test]
+fn reset_and_state_loads_start_a_new_history() {
+    let mut core = core(10, 10);
+    core.run_frame().unwrap();
+    let state = core.save_state();
+    core.load_state(&state).unwrap();
+    assert_eq!(core.time_travel.as_ref().unwrap().keyframes(), 0);
+    assert!(!core.reverse_step());
+    core.step().unwrap();
+    assert!(core.reverse_step());
+    core.reset();
+    assert!(!core.reverse_step());
+
+    let mut off = GbCore::new(RomBuilder::new().asm(LOOP).unwrap().cartridge().unwrap());
+    off.step().unwrap();
+    assert!(!off.reverse_step() && off.reverse_continue().is_none());
+}
@@ -0,0 +1,65 @@
+//! Instruction trace ring buffer
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+#[test]
+fn tracing_is_off_by_default() {
+    let mut core = core_with(&[0x00, 0x00]);
+    core.step().unwrap();
+    assert!(core.trace.is_none());
+}
+
+#[test]
+fn records_pc_opcode_and_registers_before_execution() {
+    // LD A,0x42 / SWAP A / JR -2
+    let mut core = core_with(&[0x3E, 0x42, 0xCB, 0x37, 0x18, 0xFE]);
+    core.trace = Some(TraceRing::new(8));
+    let t0 = core.clock.t_cycles;
+    for _ in 0..3 { core.step().unwrap(); }
+
+    let trace = core.trace.as_ref().unwrap();
+    let e: Vec<_> = trace.iter().collect();
+    assert_eq!(e.len(), 3);
+    assert_eq!((e[0].pc, e[0].opcode, e[0].cb, e[0].regs.a, e[0].t_cycles), (0x0100, 0x3E, None, 0x01, t0));
+    assert_eq!((e[1].pc, e[1].opcode, e[1].cb, e[1].regs.a, e[1].t_cycles), (0x0102, 0xCB, Some(0x37), 0x42, t0 + 8));
+    assert_eq!((e[2].pc, e[2].opcode, e[2].regs.a), (0x0104, 0x18, 0x24));
+}
+
+#[test]
+fn ring_keeps_only_the_newest_entries() {
+    let mut core = core_with(&[0x00; 16]);
+    core.trace = Some(TraceRing::new(4));
+    for _ in 0..10 { core.step().unwrap(); }
+    let trace = core.trace.as_ref().unwrap();
+    assert_eq!(trace.len(), 4);
+    let pcs: Vec<u16> = trace.iter().map(|e| e.pc).collect();
+    assert_eq!(pcs, vec![0x0106, 0x0107, 0x0108, 0x0109]);
+    assert_eq!(trace.last().unwrap().pc, 0x0109);
+}
+
+#[test]
+fn dumps_as_text_and_json() {
+    let mut core = core_with(&[0x3E, 0x42, 0xCB, 0x37]);
+    core.trace = Some(TraceRing::new(2));
+    core.step().unwrap();
+    core.step().unwrap();
+    let trace = core.trace.as_ref().unwrap();
+
+    let text = trace.to_text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("PC=0100 op=3E A=01"), "{}", lines[0]);
+    assert!(lines[1].contains("PC=0102 op=CB 37 A=42"), "{}", lines[1]);
+    assert!(lines[0].ends_with("  ld a, $42") && lines[1].ends_with("  swap a"), "{text}");
+
+    let json = Json::parse(&trace.to_json()).unwrap();
+    assert_eq!(json.get("capacity").and_then(Json::as_f64), Some(2.0));
+    let Some(Json::Arr(entries)) = json.get("entries") else { panic!("entries array") };
+    assert_eq!(entries[1].get("cb").and_then(Json::as_f64), Some(0x37 as f64));
+    assert_eq!(entries[1].get("regs").and_then(|r| r.get("a")).and_then(Json::as_f64), Some(0x42 as f64));
+    assert_eq!(entries[0].get("cb"), Some(&Json::Null));
+    assert_eq!(entries[1].get("asm").and_then(Json::as_str), Some("swap a"));
+}
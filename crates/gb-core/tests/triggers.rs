@@ -0,0 +1,99 @@
+//! Watch triggers: per-frame memory rules that capture labelled datasets
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+fn idle() -> GbCore { core_with(&[0x18, 0xFE]) }
+
+fn frames(core: &mut GbCore, triggers: &mut WatchTriggers, n: usize) -> Vec<Vec<String>> {
+    (0..n).map(|_| { core.run_frame().unwrap(); triggers.evaluate(core) }).collect()
+}
+
+#[test]
+fn parses_rules_and_reports_bad_lines() {
+    let t = WatchTriggers::parse("# dataset\nlevel_up: C0A0 increases\n\nscore: 0xC0F0.w >= 0x100 -> screenshot cooldown=30 # milestone\n").unwrap();
+    assert_eq!(t.rules.len(), 2);
+    assert_eq!((t.rules[0].addr, t.rules[0].wide, t.rules[0].when), (0xC0A0, false, TriggerWhen::Increases));
+    assert!(t.rules[0].screenshot && t.rules[0].savestate, "no actions means both captures");
+    let score = &t.rules[1];
+    assert_eq!((score.addr, score.wide, score.when), (0xC0F0, true, TriggerWhen::Becomes(BreakCmp::Ge, 0x100)));
+    assert!(score.screenshot && !score.savestate && score.cooldown == 30);
+
+    for (text, err) in [
+        ("a: C0A0 increases\nb C0A0 changes", "line 2"),
+        ("a: XYZ increases", "address"),
+        ("a: C0A0 wobbles", "condition"),
+        ("a: C0A0 == 1 -> print", "action"),
+    ] {
+        let e = WatchTriggers::parse(text).unwrap_err();
+        assert!(e.contains(err), "{text:?}: {e}");
+    }
+}
+
+#[test]
+fn change_rules_fire_on_the_frame_memory_moves() {
+    let mut core = idle();
+    let mut t = WatchTriggers::parse("up: C0A0 increases\ndown: C0A0 decreases -> savestate\nany: C0A0 changes -> screenshot").unwrap();
+    frames(&mut core, &mut t, 2);
+    core.bus.write(0xC0A0, 5);
+    assert_eq!(frames(&mut core, &mut t, 1)[0], ["up", "any"]);
+    core.bus.write(0xC0A0, 2);
+    assert_eq!(frames(&mut core, &mut t, 2), [vec!["down", "any"], vec![]]);
+    assert_eq!(t.captures.len(), 4);
+    let down = &t.captures[2];
+    assert_eq!((down.label.as_str(), down.old, down.new), ("down", 5, 2));
+    assert!(down.png.is_none() && down.state.is_some());
+    assert!(t.captures[3].png.as_ref().is_some_and(|p| p.starts_with(b"\x89PNG")));
+
+    let mut resumed = idle();
+    resumed.load_state(down.state.as_ref().unwrap()).unwrap();
+    assert_eq!(resumed.bus.peek(0xC0A0), 2);
+}
+
+#[test]
+fn comparisons_fire_when_they_become_true_and_honour_cooldown() {
+    let mut core = idle();
+    let mut t = WatchTriggers::parse("milestone: C0F0.w >= 1000 -> screenshot\nzero: C0A0 == 0 -> screenshot cooldown=3").unwrap();
+    frames(&mut core, &mut t, 1);
+    assert!(t.captures.is_empty(), "the first frame only records values");
+    core.bus.write(0xC0F0, 0xE8);
+    core.bus.write(0xC0F1, 0x03);
+    assert_eq!(frames(&mut core, &mut t, 2), [vec!["milestone"], vec![]], "1000 stays >= 1000");
+
+    let mut fired = vec![];
+    for f in 0..8 {
+        core.bus.write(0xC0A0, (f % 2) as u8 ^ 1);
+        fired.push(frames(&mut core, &mut t, 1)[0].contains(&"zero".to_string()));
+    }
+    assert_eq!(fired, [false, true, false, false, false, true, false, false]);
+}
+
+#[test]
+fn replay_frames_carry_event_labels_and_the_dataset_is_indexed() {
+    let mut core = idle();
+    let mut t = WatchTriggers::parse("hit: C0A0 changes").unwrap();
+    let mut replay = ReplayCapture::new(4, "IDLE");
+    for f in 0..4 {
+        if f == 2 { core.bus.write(0xC0A0, 1); }
+        core.run_frame().unwrap();
+        replay.capture(&core);
+        for label in t.evaluate(&core) { replay.mark(&label); }
+    }
+    let doc = Json::parse(&replay.to_json()).unwrap();
+    let frames = doc.get("frames").and_then(Json::as_array).unwrap();
+    assert!(frames[1].get("ev").is_none());
+    let ev = frames[2].get("ev").and_then(Json::as_array).unwrap();
+    assert_eq!(ev.iter().filter_map(Json::as_str).collect::<Vec<_>>(), ["hit"]);
+
+    let dir = std::env::temp_dir().join(format!("mrom_triggers_{}", std::process::id()));
+    t.write_dataset(&dir).unwrap();
+    let index = Json::parse(&std::fs::read_to_string(dir.join("triggers.json")).unwrap()).unwrap();
+    assert_eq!(index.get("version").and_then(Json::as_str), Some(TRIGGERS_VERSION));
+    let cap = &index.get("captures").and_then(Json::as_array).unwrap()[0];
+    let png = cap.get("png").and_then(Json::as_str).unwrap();
+    assert_eq!(png, format!("hit_f{:06}.png", t.captures[0].frame));
+    assert!(dir.join(png).exists() && dir.join(cap.get("state").and_then(Json::as_str).unwrap()).exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
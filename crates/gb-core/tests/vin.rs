@@ -0,0 +1,63 @@
+//! Cartridge audio-in (VIN) routing and the external audio hook
+
+use gb_core::*;
+
+fn core() -> GbCore {
+    let mut rom = vec![0x00u8; 32 * 1024];
+    rom[0x0100..0x0102].copy_from_slice(&[0x18, 0xFE]); // JR -2
+    GbCore::new(Cartridge::from_bytes(rom).unwrap())
+}
+
+#[test]
+fn nr50_routes_and_scales_vin() {
+    assert_eq!(vin_mix(100, 800, 0x00), (100, 100));
+    assert_eq!(vin_mix(100, 800, 0xF0), (900, 100));      // left only, full volume
+    assert_eq!(vin_mix(100, 800, 0x0B), (100, 500));      // right only, volume 3 → 4/8
+    assert_eq!(vin_mix(i16::MAX, 800, 0xFF), (i16::MAX, i16::MAX));
+}
+
+#[test]
+fn vin_queue_drops_oldest_when_full() {
+    let mut vin = VinInput::default();
+    vin.push(&vec![1; VIN_MAX_QUEUED]);
+    vin.push(&[2, 3]);
+    assert_eq!(vin.queued(), VIN_MAX_QUEUED);
+    assert_eq!(vin.dropped, 2);
+    assert_eq!(vin.next_sample(), 1);
+    vin.clear();
+    assert_eq!(vin.next_sample(), 0);
+}
+
+#[test]
+fn routed_vin_reaches_the_sample_buffer() {
+    let mut core = core();
+    core.bus.write(0xFF24, 0x80 | 0x70); // VIN → left at full volume
+    core.set_vin_source(Some(Box::new(|_frame: u64, n: usize| vec![1000i16; n])));
+    core.run_frame().unwrap();
+    let samples = core.bus.apu.drain_samples();
+    assert!(!samples.is_empty());
+    for lr in samples.chunks(2) {
+        assert_eq!(lr[0] as i32 - lr[1] as i32, 1000, "left carries VIN, right does not");
+    }
+}
+
+#[test]
+fn unrouted_vin_is_consumed_but_silent() {
+    let mut core = core();
+    core.push_vin(&[5000; 2000]);
+    core.run_frame().unwrap();
+    let samples = core.bus.apu.drain_samples();
+    assert!(samples.chunks(2).all(|lr| lr[0] == lr[1]));
+    assert!(core.bus.apu.vin.queued() < 2000);
+}
+
+#[test]
+fn pcm_clip_plays_once_or_loops() {
+    let mut once = PcmVin::new(vec![1, 2, 3], false);
+    assert_eq!(once.samples(0, 2), vec![1, 2]);
+    assert_eq!(once.samples(1, 2), vec![3]);
+    assert!(once.finished());
+    let mut looped = PcmVin::new(vec![1, 2, 3], true);
+    assert_eq!(looped.samples(0, 5), vec![1, 2, 3, 1, 2]);
+    assert!(!looped.finished());
+}
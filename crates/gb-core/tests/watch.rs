@@ -0,0 +1,75 @@
+//! Memory read / write watchpoints reported through step / run_frame
+
+use gb_core::*;
+
+mod common;
+use common::core_with;
+
+fn expect_watch(r: Result<impl std::fmt::Debug, CoreError>) -> WatchHit {
+    match r {
+        Err(CoreError::Watch(hit)) => hit,
+        other => panic!("expected a watchpoint, got {other:?}"),
+    }
+}
+
+/// LD A,0x42 / LD (0xC010),A / LD B,(HL) with HL = 0xC020 / NOP
+const PROG: [u8; 11] = [0x3E, 0x42, 0xEA, 0x10, 0xC0, 0x21, 0x20, 0xC0, 0x46, 0x00, 0x00];
+
+#[test]
+fn write_hit_reports_pc_value_and_stops_after_the_instruction() {
+    let mut core = core_with(&PROG);
+    let id = core.bus.watchpoints.add_write(0xC010);
+    core.step().unwrap();
+    let hit = expect_watch(core.step());
+    assert_eq!(hit, WatchHit { id, pc: 0x0102, addr: 0xC010, value: 0x42, access: WatchAccess::Write });
+    assert_eq!((core.regs.pc, core.bus.peek(0xC010)), (0x0105, 0x42), "the write has happened");
+    assert_eq!(core.bus.watchpoints.get(id).unwrap().hits, 1);
+
+    core.step().unwrap();
+    assert_eq!(core.regs.pc, 0x0108, "calling again continues");
+}
+
+#[test]
+fn read_hit_and_range() {
+    let mut core = core_with(&PROG);
+    core.bus.write(0xC020, 0x99);
+    let id = core.bus.watchpoints.add(0xC000, 0xC0FF, true, false);
+    for _ in 0..3 { core.step().unwrap(); }
+    let hit = expect_watch(core.step());
+    assert_eq!((hit.id, hit.pc, hit.addr, hit.value, hit.access), (id, 0x0108, 0xC020, 0x99, WatchAccess::Read));
+    assert_eq!(core.regs.b, 0x99);
+}
+
+#[test]
+fn operand_fetches_count_as_reads() {
+    let mut core = core_with(&PROG);
+    core.bus.watchpoints.add_read(0x0101);
+    let hit = expect_watch(core.step());
+    assert_eq!((hit.pc, hit.addr, hit.value), (0x0100, 0x0101, 0x42));
+}
+
+#[test]
+fn non_stopping_watchpoints_only_log() {
+    let mut core = core_with(&PROG);
+    let id = core.bus.watchpoints.add(0xC000, 0xDFFF, true, true);
+    core.bus.watchpoints.get_mut(id).unwrap().stop = false;
+    for _ in 0..5 { core.step().unwrap(); }
+    let log = core.bus.watchpoints.take_hits();
+    let seen: Vec<_> = log.iter().map(|h| (h.pc, h.addr, h.access)).collect();
+    assert_eq!(seen, [(0x0102, 0xC010, WatchAccess::Write), (0x0108, 0xC020, WatchAccess::Read)]);
+    assert!(core.bus.watchpoints.hits().is_empty());
+}
+
+#[test]
+fn run_frame_returns_the_hit_and_host_accesses_do_not_fire() {
+    let mut core = core_with(&PROG);
+    let id = core.bus.watchpoints.add_write(0xC010);
+    core.bus.write(0xC010, 1);
+    assert_eq!(core.bus.read(0xC010), 1);
+    assert!(core.bus.watchpoints.hits().is_empty(), "outside an instruction");
+
+    assert_eq!(expect_watch(core.run_frame()).id, id);
+    core.bus.watchpoints.get_mut(id).unwrap().enabled = false;
+    core.run_frame().unwrap();
+    assert_eq!(core.bus.watchpoints.hits().len(), 1);
+}
@@ -0,0 +1,345 @@
+//! metarom — one CLI for the whole workspace
+//!
+//! Subcommands dispatch straight into the libraries:
+//!   run     — gb-core: run one ROM for N frames
+//!   batch   — gb-core: run every ROM in a directory (panics are contained per ROM)
+//!   probe   — gb-core: cartridge header, checksums and the model it boots on
+//!   verify  — gb-core: test ROM(s) to a pass / fail / timeout verdict
+//!   savedump — gb-core: a battery save decoded by the game's adapter
+//!   importstate — gb-core: a Gambatte / VBA-M savestate converted to .mrom.sav
+//!   plan    — ucf-planner: compatibility plan (same flags as `ucf-planner plan`)
+//!   execute — mrom-host: run a plan's pipeline against gb-core over the ecore ABI
+//!
+//! Conventions shared by every subcommand:
+//! - stdout carries exactly one JSON document (pretty; `--compact` for one line)
+//! - progress and warnings go to stderr as `metarom: ...` (`--quiet` silences progress)
+//! - flags accept `--name value` and `--name=value`
+//! - exit 0 on success, 1 when the work failed (errors, panics, failing tests), 2 on usage errors
+
+use gb_core::{catch_run, global_checksum, header_checksum, import_state, rom_hash, run_test, Cartridge, CoreConfig, GameAdapters, GbCore, HardwareModel, RomHeader, TestOutcome, DEFAULT_SUITE_FRAMES};
+use mrom_host::{gb_ecore_handle, PlanDriver, DEFAULT_RUN_FRAMES};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const DEFAULT_FRAMES: u64 = 60;
+
+/// Flags common to the emulator subcommands, plus positionals
+struct Opts {
+    positional: Vec<String>,
+    frames: Option<u64>,
+    /// None = pick from the cartridge header
+    model: Option<HardwareModel>,
+    compact: bool,
+    quiet: bool,
+}
+
+impl Opts {
+    fn parse(args: &[String]) -> Result<Opts, String> {
+        let mut o = Opts { positional: vec![], frames: None, model: None, compact: false, quiet: false };
+        let mut i = 0;
+        while i < args.len() {
+            let (name, inline) = match args[i].split_once('=') {
+                Some((n, v)) if n.starts_with("--") => (n, Some(v.to_string())),
+                _ => (args[i].as_str(), None),
+            };
+            let mut value = |flag: &str| -> Result<String, String> {
+                if let Some(v) = &inline { return Ok(v.clone()); }
+                i += 1;
+                args.get(i).cloned().ok_or_else(|| format!("missing value for {flag}"))
+            };
+            match name {
+                "--frames" => o.frames = Some(value("--frames")?.parse().map_err(|e| format!("--frames: {e}"))?),
+                "--model" => {
+                    let m = value("--model")?;
+                    o.model = match m.as_str() {
+                        "auto" => None,
+                        _ => Some(HardwareModel::parse(&m).ok_or_else(|| format!("unknown --model {m:?} (dmg, mgb, sgb, cgb, auto)"))?),
+                    };
+                }
+                "--compact" => o.compact = true,
+                "--quiet" | "-q" => o.quiet = true,
+                flag if flag.starts_with('-') => return Err(format!("unexpected argument: {flag}")),
+                _ => o.positional.push(args[i].clone()),
+            }
+            i += 1;
+        }
+        Ok(o)
+    }
+
+    fn log(&self, msg: impl AsRef<str>) {
+        if !self.quiet { eprintln!("metarom: {}", msg.as_ref()); }
+    }
+
+    fn model_for(&self, rom: &[u8]) -> HardwareModel {
+        self.model.unwrap_or_else(|| HardwareModel::for_rom(rom))
+    }
+}
+
+/// The outcome of a subcommand: its JSON document and whether the work succeeded
+struct Report {
+    doc: Value,
+    ok: bool,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(command) = args.first() else { print_help(); return ExitCode::from(2); };
+    let rest = &args[1..];
+    let compact = rest.iter().any(|a| a == "--compact");
+
+    let result: Result<Report, Box<dyn Error>> = match command.as_str() {
+        "plan" => {
+            // ucf-planner's own flags; only the output conventions are ours
+            let forwarded: Vec<String> = rest.iter().filter(|a| *a != "--compact").cloned().collect();
+            ucf_planner::cli::plan_command(&forwarded)
+                .and_then(|plan| Ok(Report { doc: serde_json::to_value(plan)?, ok: true }))
+        }
+        "run" | "batch" | "probe" | "verify" | "savedump" | "importstate" | "execute" => match Opts::parse(rest) {
+            Ok(opts) => match command.as_str() {
+                "run" => cmd_run(&opts),
+                "batch" => cmd_batch(&opts),
+                "probe" => cmd_probe(&opts),
+                "savedump" => cmd_savedump(&opts),
+                "importstate" => cmd_importstate(&opts),
+                "execute" => cmd_execute(&opts),
+                _ => cmd_verify(&opts),
+            },
+            Err(e) => { eprintln!("metarom: {e}"); return ExitCode::from(2); }
+        },
+        "help" | "--help" | "-h" => { print_help(); return ExitCode::SUCCESS; }
+        other => { eprintln!("metarom: unknown command: {other}"); print_help(); return ExitCode::from(2); }
+    };
+
+    match result {
+        Ok(report) => {
+            let text = if compact { report.doc.to_string() } else { serde_json::to_string_pretty(&report.doc).unwrap_or_default() };
+            println!("{text}");
+            if report.ok { ExitCode::SUCCESS } else { ExitCode::from(1) }
+        }
+        Err(e) => {
+            eprintln!("metarom: error: {e}");
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn one_rom(opts: &Opts) -> Result<PathBuf, Box<dyn Error>> {
+    match opts.positional.as_slice() {
+        [rom] => Ok(PathBuf::from(rom)),
+        _ => Err("expected exactly one <rom>".into()),
+    }
+}
+
+fn rom_files(dir: &Path) -> Vec<PathBuf> {
+    let mut roms: Vec<PathBuf> = std::fs::read_dir(dir).into_iter().flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let ext = p.extension().and_then(|s| s.to_str()).unwrap_or("");
+            matches!(ext.to_lowercase().as_str(), "gb" | "gbc")
+        })
+        .collect();
+    roms.sort();
+    roms
+}
+
+/// A ROM path, or every ROM in a directory
+fn rom_paths(opts: &Opts) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let path = one_rom(opts)?;
+    if path.is_dir() { Ok(rom_files(&path)) } else { Ok(vec![path]) }
+}
+
+fn rom_identity(path: &Path, rom: &[u8]) -> Value {
+    let title = RomHeader::parse(rom).map(|h| h.title).unwrap_or_default();
+    json!({ "path": path.display().to_string(), "title": title, "rom_hash": rom_hash(rom) })
+}
+
+/// Run one ROM for `frames`; panics are caught and reported like errors
+fn run_rom(path: &Path, frames: u64, opts: &Opts) -> Value {
+    let rom = match std::fs::read(path) {
+        Ok(r) => r,
+        Err(e) => return json!({ "rom": { "path": path.display().to_string() }, "outcome": "error", "error": format!("read error: {e}") }),
+    };
+    let identity = rom_identity(path, &rom);
+    let model = opts.model_for(&rom);
+    let cart = match Cartridge::from_bytes(rom) {
+        Ok(c) => c,
+        Err(e) => return json!({ "rom": identity, "outcome": "error", "error": e.to_string() }),
+    };
+    let run = catch_run(|| {
+        let mut core = GbCore::with_config(cart, CoreConfig { model, ..Default::default() });
+        let mut error = None;
+        for _ in 0..frames {
+            if let Err(e) = core.run_frame() { error = Some(e.to_string()); break; }
+        }
+        let diagnostics: Value = serde_json::from_str(&core.diagnostics_json()).unwrap_or(Value::Null);
+        (core.clock.frame_count(), core.clock.t_cycles, core.console_text(), diagnostics, error)
+    });
+    match run {
+        Ok((frames_run, t_cycles, console, diagnostics, error)) => json!({
+            "rom": identity, "model": model.as_str(),
+            "outcome": if error.is_some() { "error" } else { "ok" }, "error": error,
+            "frames": frames_run, "t_cycles": t_cycles, "console": console, "diagnostics": diagnostics,
+        }),
+        Err(panic) => json!({
+            "rom": identity, "model": model.as_str(), "outcome": "panic",
+            "panic": { "message": panic.message, "location": panic.location, "backtrace_hash": panic.backtrace_hash },
+        }),
+    }
+}
+
+fn cmd_run(opts: &Opts) -> Result<Report, Box<dyn Error>> {
+    let path = one_rom(opts)?;
+    let frames = opts.frames.unwrap_or(DEFAULT_FRAMES);
+    opts.log(format!("running {} for {frames} frames", path.display()));
+    let result = run_rom(&path, frames, opts);
+    let ok = result["outcome"] == "ok";
+    Ok(Report { doc: json!({ "command": "run", "result": result }), ok })
+}
+
+fn cmd_batch(opts: &Opts) -> Result<Report, Box<dyn Error>> {
+    let dir = one_rom(opts)?;
+    if !dir.is_dir() { return Err(format!("{} is not a directory", dir.display()).into()); }
+    let frames = opts.frames.unwrap_or(DEFAULT_FRAMES);
+    let roms = rom_files(&dir);
+    opts.log(format!("{} ROM(s) in {}, {frames} frames each", roms.len(), dir.display()));
+    let mut results = vec![];
+    for (n, path) in roms.iter().enumerate() {
+        let result = run_rom(path, frames, opts);
+        opts.log(format!("[{}/{}] {} {}", n + 1, roms.len(), result["outcome"].as_str().unwrap_or("?"), path.display()));
+        results.push(result);
+    }
+    let failed = results.iter().filter(|r| r["outcome"] != "ok").count();
+    Ok(Report { doc: json!({ "command": "batch", "frames": frames, "total": results.len(), "failed": failed, "results": results }), ok: failed == 0 })
+}
+
+fn cmd_probe(opts: &Opts) -> Result<Report, Box<dyn Error>> {
+    let path = one_rom(opts)?;
+    let rom = std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let header = RomHeader::parse(&rom)?;
+    let cart = Cartridge::from_bytes(rom.clone())?;
+    let global = u16::from_be_bytes([rom[0x14E], rom[0x14F]]);
+    let doc = json!({
+        "command": "probe",
+        "rom": rom_identity(&path, &rom),
+        "size_bytes": rom.len(),
+        "cartridge": format!("{:?}", cart.kind),
+        "cart_type": header.cart_type,
+        "cgb_flag": header.cgb_flag,
+        "rom_size_kb": cart.rom_size_kb,
+        "ram_size_kb": cart.ram_size_kb,
+        "header_checksum_ok": rom[0x14D] == header_checksum(&rom),
+        "global_checksum_ok": global == global_checksum(&rom),
+        "model": opts.model_for(&rom).as_str(),
+    });
+    Ok(Report { doc, ok: true })
+}
+
+fn cmd_verify(opts: &Opts) -> Result<Report, Box<dyn Error>> {
+    let roms = rom_paths(opts)?;
+    let frames = opts.frames.unwrap_or(DEFAULT_SUITE_FRAMES);
+    let mut results = vec![];
+    for path in &roms {
+        let rom = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let identity = rom_identity(path, &rom);
+        let model = opts.model_for(&rom);
+        let run = Cartridge::from_bytes(rom).map_err(|e| e.to_string()).and_then(|cart| {
+            catch_run(|| run_test(&mut GbCore::with_config(cart, CoreConfig { model, ..Default::default() }), frames))
+                .map_err(|p| format!("panic: {}", p.message))
+        });
+        let (outcome, detail, frames_run) = match run {
+            Ok(run) => {
+                let detail = match &run.outcome { TestOutcome::Fail(d) => d.clone(), _ => String::new() };
+                (run.outcome.as_str(), detail, run.frames)
+            }
+            Err(e) => ("fail", e, 0),
+        };
+        opts.log(format!("{outcome:<7} {}", path.display()));
+        results.push(json!({ "rom": identity, "model": model.as_str(), "outcome": outcome, "detail": detail, "frames": frames_run }));
+    }
+    let passed = results.iter().filter(|r| r["outcome"] == "pass").count();
+    let ok = !results.is_empty() && passed == results.len();
+    Ok(Report { doc: json!({ "command": "verify", "total": results.len(), "passed": passed, "results": results }), ok })
+}
+
+fn cmd_savedump(opts: &Opts) -> Result<Report, Box<dyn Error>> {
+    let [rom_path, sav_path] = opts.positional.as_slice() else { return Err("expected <rom> <sav>".into()) };
+    let rom = std::fs::read(rom_path).map_err(|e| format!("{rom_path}: {e}"))?;
+    let sav = std::fs::read(sav_path).map_err(|e| format!("{sav_path}: {e}"))?;
+    let adapters = GameAdapters::builtin();
+    let header = RomHeader::parse(&rom)?;
+    let adapter = adapters.for_header(&header)
+        .ok_or_else(|| format!("no save decoder for {:?} (known: {})", header.title, adapters.ids().join(", ")))?;
+    opts.log(format!("decoding {sav_path} with {}", adapter.id()));
+    let save: Value = serde_json::from_str(&adapter.decode_save(&sav)?)?;
+    let doc = json!({ "command": "savedump", "rom": rom_identity(Path::new(rom_path), &rom), "save_bytes": sav.len(), "save": save });
+    Ok(Report { doc, ok: true })
+}
+
+fn cmd_importstate(opts: &Opts) -> Result<Report, Box<dyn Error>> {
+    let (rom_path, state_path, out) = match opts.positional.as_slice() {
+        [rom, state] => (rom, state, Path::new(state).with_extension("mrom.sav")),
+        [rom, state, out] => (rom, state, PathBuf::from(out)),
+        _ => return Err("expected <rom> <state> [out.mrom.sav]".into()),
+    };
+    let rom = std::fs::read(rom_path).map_err(|e| format!("{rom_path}: {e}"))?;
+    let state = std::fs::read(state_path).map_err(|e| format!("{state_path}: {e}"))?;
+    let identity = rom_identity(Path::new(rom_path), &rom);
+    let model = opts.model_for(&rom);
+    let mut core = GbCore::with_config(Cartridge::from_bytes(rom)?, CoreConfig { model, ..Default::default() });
+    let import = import_state(&mut core, &state)?;
+    opts.log(format!("{state_path}: {} state, {} field(s) not converted", import.format.as_str(), import.unconverted.len()));
+    core.save_state_to_file(&out).map_err(|e| format!("{}: {e}", out.display()))?;
+    let doc = json!({
+        "command": "importstate", "rom": identity, "model": model.as_str(), "output": out.display().to_string(),
+        "import": serde_json::from_str::<Value>(&import.to_json())?,
+    });
+    Ok(Report { doc, ok: true })
+}
+
+fn cmd_execute(opts: &Opts) -> Result<Report, Box<dyn Error>> {
+    let [plan_path, rom_path] = opts.positional.as_slice() else { return Err("expected <plan.json> <rom>".into()) };
+    let plan_text = std::fs::read_to_string(plan_path).map_err(|e| format!("{plan_path}: {e}"))?;
+    let plan: ucf_planner::model::CompatibilityPlan = serde_json::from_str(&plan_text).map_err(|e| format!("{plan_path}: {e}"))?;
+    let rom = std::fs::read(rom_path).map_err(|e| format!("{rom_path}: {e}"))?;
+    let frames = opts.frames.unwrap_or(DEFAULT_RUN_FRAMES);
+    opts.log(format!("executing {} ({} steps) on {rom_path}", plan.plan_id, plan.strategy_pipeline.len()));
+    let core = gb_ecore_handle();
+    let execution = PlanDriver::new(&core, &rom).with_frames(frames).execute(&plan);
+    core.unload_rom();
+    for step in &execution.steps {
+        opts.log(format!("{:<8} {} {}", format!("{:?}", step.status).to_lowercase(), step.id, step.detail));
+    }
+    let doc = json!({
+        "command": "execute", "rom": rom_identity(Path::new(rom_path), &rom),
+        "execution": serde_json::to_value(&execution)?,
+    });
+    Ok(Report { doc, ok: execution.ok })
+}
+
+fn print_help() {
+    eprintln!("\
+metarom <command> [args]
+
+Commands:
+  run <rom> [--frames N] [--model M]          run a ROM (default {DEFAULT_FRAMES} frames)
+  batch <dir> [--frames N] [--model M]        run every .gb/.gbc in a directory
+  probe <rom> [--model M]                     header, checksums and boot model
+  verify <rom|dir> [--frames N] [--model M]   test ROM verdicts (default {DEFAULT_SUITE_FRAMES} frames); exit 1 unless all pass
+  savedump <rom> <sav>                        battery save decoded to JSON (Pokémon R/B/Y, Link's Awakening)
+  importstate <rom> <state> [out.mrom.sav]    Gambatte .gqs / VBA-M .sgm state to .mrom.sav (best effort)
+  execute <plan.json> <rom> [--frames N]      run a plan's pipeline against gb-core (default {DEFAULT_RUN_FRAMES} frames); exit 1 unless every step is done
+  plan --artifact <req.json> --target <cap.json> [...]
+                                              compatibility plan; flags as for `ucf-planner plan`
+
+Options:
+  --model dmg|mgb|sgb|cgb|auto   hardware model (auto: CGB for CGB-flagged carts, else DMG)
+  --compact                      print the JSON document on one line
+  --quiet, -q                    no progress on stderr
+
+Output: one JSON document on stdout; progress and warnings on stderr.
+Exit status: 0 ok, 1 failed run / test / plan error, 2 usage error.
+");
+}
@@ -4,12 +4,22 @@
 //! discovers via `mrom_ecore_init()`. All callbacks are extern "C" and safe to
 //! call across shared-library boundaries.
 
-use std::ffi::{c_char, c_int, c_uint, c_void};
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr, CString};
 use std::os::raw::c_uchar;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 // ── Version sentinel ─────────────────────────────────────────────────────────
 
-pub const MROM_ABI_VERSION: u32 = 1;
+pub const MROM_ABI_VERSION: u32 = 3;
+
+/// First ABI version whose vtable carries the watchdog entries
+pub const MROM_ABI_WATCHDOG: u32 = 2;
+
+/// First ABI version whose vtable carries `reset`
+pub const MROM_ABI_RESET: u32 = 3;
 
 // ── Core info block (returned by ecore_info) ──────────────────────────────────
 
@@ -48,6 +58,96 @@ pub struct AudioFrame {
     pub sample_rate_hz: c_uint,
 }
 
+// ── Watchdog status (returned by watchdog_status) ────────────────────────────
+
+/// Host-side liveness snapshot. Safe to query from any thread, including
+/// while another thread is inside `run_frame`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WatchdogStatus {
+    /// Wall time of the last completed (or interrupted) run_frame, microseconds
+    pub last_frame_us: u64,
+    /// Time spent so far in the current run_frame (0 when not inside one)
+    pub in_frame_us: u64,
+    /// 1 while a run_frame call is executing
+    pub in_run_frame: u8,
+    /// 1 when in_frame_us exceeds the core's wedge threshold
+    pub wedged: u8,
+    /// 1 while a request_interrupt/abort_frame has not been honoured yet
+    pub interrupt_pending: u8,
+    pub frames_completed: u64,
+    /// run_frame calls cut short by request_interrupt/abort_frame
+    pub frames_interrupted: u64,
+}
+
+// ── Diagnostics (JSON returned by diagnostics) ───────────────────────────────
+
+/// One output reduction a core is running with (e.g. a lite mode for weak
+/// hosts). Same shape as the planner's `Degradation`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreDegradation {
+    pub subsystem: String,
+    pub description: String,
+    pub equivalence_impact: String,
+}
+
+/// Press-to-screen input latency a core measured for the running game.
+/// Hosts forward `ms` to the planner as telemetry (`input_latency_ms`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoreInputLatency {
+    /// Emulated frames until a press was visible; None if nothing reacted
+    pub frames: Option<u32>,
+    pub ms: Option<f64>,
+}
+
+/// One sprite that moved since the previous frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpriteMotion {
+    pub index: u8,
+    pub dx: i16,
+    pub dy: i16,
+}
+
+/// What moved in the last frame, for hosts interpolating to a higher
+/// refresh rate. Reported once `CoreOptions::motion_metadata` is on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameMotion {
+    pub frame: u64,
+    /// Background scroll change; positive is the view moving right / down
+    pub scroll_dx: i8,
+    pub scroll_dy: i8,
+    pub window_dx: i16,
+    pub window_dy: i16,
+    pub window_visible: bool,
+    #[serde(default)]
+    pub sprites: Vec<SpriteMotion>,
+}
+
+/// The fields of the diagnostics JSON the host interprets; cores may add
+/// others, which are ignored
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoreDiagnostics {
+    #[serde(default)]
+    pub degradations: Vec<CoreDegradation>,
+    /// Present once the core has measured it
+    #[serde(default)]
+    pub input_latency: Option<CoreInputLatency>,
+    /// Last frame's motion, when `motion_metadata` is on
+    #[serde(default)]
+    pub motion: Option<FrameMotion>,
+}
+
+// ── Options (JSON passed to configure) ───────────────────────────────────────
+
+/// Options a host may pass to `configure`. Cores ignore keys they do not
+/// know, so every field is optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreOptions {
+    /// Report per-frame `FrameMotion` in the diagnostics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion_metadata: Option<bool>,
+}
+
 // ── Virtual table ─────────────────────────────────────────────────────────────
 
 /// Function pointer table exposed by each emulator core.
@@ -81,20 +181,43 @@ pub struct EcoreVtable {
     /// Bit layout is core-defined; host should query ecore_info for input schema.
     pub set_input: unsafe extern "C" fn(player: c_uint, input_word: u32),
 
-    /// Optional: host calls this to forward a JSON config blob.
-    /// Core may ignore. Returns 0 on success, non-zero if config rejected.
+    /// Optional: host calls this to forward a JSON config blob (see
+    /// `CoreOptions`). Core may ignore. Returns 0 on success, non-zero if
+    /// config rejected.
     pub configure: unsafe extern "C" fn(json_cfg: *const c_char) -> c_int,
 
     /// Optional: return a null-terminated JSON string describing current core state.
     /// Caller must NOT free; pointer valid until next call.
+    /// Cores running with reduced output list it under `"degradations"`,
+    /// and a measured input latency under `"input_latency"` (see `CoreDiagnostics`).
     pub diagnostics: unsafe extern "C" fn() -> *const c_char,
+
+    // ── abi_version >= 2 (MROM_ABI_WATCHDOG) ──
+
+    /// Fill `out` with the current liveness snapshot. Returns 0 on success.
+    /// Callable from any thread, concurrently with run_frame.
+    pub watchdog_status: unsafe extern "C" fn(out: *mut WatchdogStatus) -> c_int,
+
+    /// Ask the core to leave run_frame at the next instruction boundary.
+    /// Machine state stays consistent; the next run_frame continues from there.
+    pub request_interrupt: unsafe extern "C" fn(),
+
+    /// Like request_interrupt, but the partial frame's video/audio output is
+    /// dropped. Use when the core is wedged; the host should then load_state
+    /// or reload the ROM. Callable from any thread.
+    pub abort_frame: unsafe extern "C" fn(),
+
+    // ── abi_version >= 3 (MROM_ABI_RESET) ──
+
+    /// Soft reset: restart the loaded ROM from its post-boot state, keeping
+    /// battery RAM and the RTC. Cheaper than unload_rom + load_rom.
+    pub reset: unsafe extern "C" fn(),
 }
 
 // ── Host-side entrypoint symbol ───────────────────────────────────────────────
 
 /// Symbol the host runtime looks for in each .mrom shared object.
 /// Implementations return a pointer to a static EcoreVtable.
-#[no_mangle]
 pub type MromEcoreInitFn = unsafe extern "C" fn() -> *const EcoreVtable;
 
 // ── Rust helper: safe wrapper over an EcoreVtable pointer ────────────────────
@@ -130,4 +253,137 @@ impl EcoreHandle {
     pub fn run_frame(&self, video: &mut VideoFrame, audio: &mut AudioFrame) {
         unsafe { ((*self.vtable).run_frame)(video, audio) }
     }
+
+    fn abi_version(&self) -> u32 {
+        let info = self.info();
+        if info.is_null() { 0 } else { unsafe { (*info).abi_version } }
+    }
+
+    fn has_watchdog(&self) -> bool { self.abi_version() >= MROM_ABI_WATCHDOG }
+
+    /// Raw diagnostics JSON; None if the core returns null or invalid UTF-8
+    pub fn diagnostics_json(&self) -> Option<String> {
+        let p = unsafe { ((*self.vtable).diagnostics)() };
+        if p.is_null() { return None; }
+        unsafe { CStr::from_ptr(p) }.to_str().ok().map(str::to_string)
+    }
+
+    /// Forward `options` to the core's `configure`; true if accepted
+    pub fn configure(&self, options: &CoreOptions) -> bool {
+        let Ok(json) = serde_json::to_string(options) else { return false };
+        let Ok(c) = CString::new(json) else { return false };
+        unsafe { ((*self.vtable).configure)(c.as_ptr()) == 0 }
+    }
+
+    /// Parsed diagnostics; a missing or malformed document reads as "no
+    /// degradations"
+    pub fn diagnostics(&self) -> CoreDiagnostics {
+        self.diagnostics_json().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+    }
+
+    /// Liveness snapshot; None for cores built against ABI v1
+    pub fn watchdog_status(&self) -> Option<WatchdogStatus> {
+        if !self.has_watchdog() { return None; }
+        let mut out = WatchdogStatus::default();
+        (unsafe { ((*self.vtable).watchdog_status)(&mut out) } == 0).then_some(out)
+    }
+
+    /// Returns false if the core predates the watchdog ABI
+    pub fn request_interrupt(&self) -> bool {
+        self.has_watchdog() && { unsafe { ((*self.vtable).request_interrupt)() }; true }
+    }
+
+    /// Returns false if the core predates the watchdog ABI
+    pub fn abort_frame(&self) -> bool {
+        self.has_watchdog() && { unsafe { ((*self.vtable).abort_frame)() }; true }
+    }
+
+    /// Returns false if the core predates the reset ABI; reload the ROM instead
+    pub fn reset(&self) -> bool {
+        self.abi_version() >= MROM_ABI_RESET && { unsafe { ((*self.vtable).reset)() }; true }
+    }
+}
+
+// ── Rust helper: core-side watchdog bookkeeping ──────────────────────────────
+
+/// Default time inside one run_frame after which a core counts as wedged
+pub const DEFAULT_WEDGE_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Core-side state behind `watchdog_status` / `request_interrupt` / `abort_frame`.
+/// Keep one in a static; call `frame_begin`/`frame_end` around the emulation
+/// loop and poll `should_stop()` between instructions.
+pub struct Watchdog {
+    started: Mutex<Option<Instant>>,
+    last_frame_us: AtomicU64,
+    frames_completed: AtomicU64,
+    frames_interrupted: AtomicU64,
+    interrupt: AtomicBool,
+    abort: AtomicBool,
+    wedge_threshold: Duration,
+}
+
+impl Watchdog {
+    pub const fn new(wedge_threshold: Duration) -> Self {
+        Self {
+            started: Mutex::new(None),
+            last_frame_us: AtomicU64::new(0),
+            frames_completed: AtomicU64::new(0),
+            frames_interrupted: AtomicU64::new(0),
+            interrupt: AtomicBool::new(false),
+            abort: AtomicBool::new(false),
+            wedge_threshold,
+        }
+    }
+
+    pub fn frame_begin(&self) {
+        *self.started.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+    }
+
+    /// Close the frame. Returns false if it was aborted (drop its output).
+    pub fn frame_end(&self) -> bool {
+        let started = self.started.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(t) = started {
+            self.last_frame_us.store(t.elapsed().as_micros() as u64, Ordering::Relaxed);
+        }
+        let interrupted = self.interrupt.swap(false, Ordering::AcqRel);
+        let aborted = self.abort.swap(false, Ordering::AcqRel);
+        if interrupted || aborted {
+            self.frames_interrupted.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.frames_completed.fetch_add(1, Ordering::Relaxed);
+        }
+        !aborted
+    }
+
+    /// Polled by the core's inner loop
+    pub fn should_stop(&self) -> bool {
+        self.interrupt.load(Ordering::Acquire)
+    }
+
+    pub fn request_interrupt(&self) {
+        self.interrupt.store(true, Ordering::Release);
+    }
+
+    pub fn abort_frame(&self) {
+        self.abort.store(true, Ordering::Release);
+        self.interrupt.store(true, Ordering::Release);
+    }
+
+    pub fn status(&self) -> WatchdogStatus {
+        let in_frame = self.started.lock().unwrap_or_else(|e| e.into_inner()).map(|t| t.elapsed());
+        let in_frame_us = in_frame.map_or(0, |d| d.as_micros() as u64);
+        WatchdogStatus {
+            last_frame_us: self.last_frame_us.load(Ordering::Relaxed),
+            in_frame_us,
+            in_run_frame: in_frame.is_some() as u8,
+            wedged: in_frame.is_some_and(|d| d > self.wedge_threshold) as u8,
+            interrupt_pending: self.interrupt.load(Ordering::Acquire) as u8,
+            frames_completed: self.frames_completed.load(Ordering::Relaxed),
+            frames_interrupted: self.frames_interrupted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self { Self::new(DEFAULT_WEDGE_THRESHOLD) }
 }
@@ -0,0 +1,197 @@
+//! driver — execute a CompatibilityPlan's pipeline against an emulator core
+//!
+//! `PlanDriver::execute` walks `strategy_pipeline` in order. A step runs when
+//! everything it `requires` has been produced (the ROM counts as
+//! `"artifact"` from the start) and the driver has an action for its id; its
+//! `produces` then become available to later steps. The Emulate pipeline is
+//! covered end to end:
+//!
+//! - `load_emulator_core` — checks the core's `ECoreInfo` (ABI version, id);
+//!   a plan with a `core_selection` fails here on any other core
+//! - `map_bios_rom` — `load_rom` with the artifact
+//! - `run_emulation_loop` — `frames` calls to `run_frame`, hashing each video frame
+//! - `verify_equivalence` — reloads the ROM, reruns the same frames and
+//!   requires every frame hash to match; plans asking for
+//!   `L4_RENDER_EQ` or better also fail on reported degradations
+//!
+//! Steps without an action (translation, network, manual work) are
+//! `Skipped`, steps whose inputs are missing are `Blocked`, and after the
+//! first `Failed` step the rest are `Blocked`. The run is `ok` only when
+//! every step is `Done`.
+
+use mrom_ecore_abi::{AudioFrame, EcoreHandle, VideoFrame, MROM_ABI_VERSION};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ffi::CStr;
+use std::ptr;
+use std::time::Instant;
+use ucf_planner::model::{CompatibilityPlan, EquivalenceLevel, StrategyClass};
+use ucf_planner::{PipelineStep, StepOwner};
+
+/// Frames `run_emulation_loop` runs: ten seconds of Game Boy time
+pub const DEFAULT_RUN_FRAMES: u64 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Done,
+    Failed,
+    /// The driver has no action for the step
+    Skipped,
+    /// Not run: inputs missing or an earlier step failed
+    Blocked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepReport {
+    pub id: String,
+    pub owner: StepOwner,
+    pub status: StepStatus,
+    pub detail: String,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanExecution {
+    pub plan_id: String,
+    pub artifact_id: String,
+    pub strategy: StrategyClass,
+    pub steps: Vec<StepReport>,
+    /// Frames run by `run_emulation_loop`
+    pub frames_run: u64,
+    pub ok: bool,
+}
+
+impl PlanExecution {
+    pub fn step(&self, id: &str) -> Option<&StepReport> { self.steps.iter().find(|s| s.id == id) }
+}
+
+/// Runs plans against one core and one ROM
+pub struct PlanDriver<'a> {
+    core: &'a EcoreHandle,
+    rom: &'a [u8],
+    pub frames: u64,
+    available: BTreeSet<String>,
+    /// FNV-1a of each video frame from `run_emulation_loop`
+    frame_hashes: Vec<u64>,
+}
+
+impl<'a> PlanDriver<'a> {
+    pub fn new(core: &'a EcoreHandle, rom: &'a [u8]) -> Self {
+        PlanDriver { core, rom, frames: DEFAULT_RUN_FRAMES, available: BTreeSet::new(), frame_hashes: vec![] }
+    }
+    pub fn with_frames(mut self, frames: u64) -> Self { self.frames = frames.max(1); self }
+
+    /// Run `plan`'s pipeline; the core is left holding the ROM afterwards
+    pub fn execute(&mut self, plan: &CompatibilityPlan) -> PlanExecution {
+        self.available = BTreeSet::from(["artifact".to_string()]);
+        self.frame_hashes.clear();
+        let mut failed: Option<&str> = None;
+        let mut steps = vec![];
+        for step in &plan.strategy_pipeline {
+            let start = Instant::now();
+            let missing: Vec<&str> = step.requires.iter().filter(|r| !self.available.contains(*r)).map(String::as_str).collect();
+            let (status, detail) = if let Some(prev) = failed {
+                (StepStatus::Blocked, format!("not run: {prev} failed"))
+            } else if !missing.is_empty() {
+                (StepStatus::Blocked, format!("missing {}", missing.join(", ")))
+            } else {
+                match self.run_step(step, plan) {
+                    None => (StepStatus::Skipped, format!("no host action for {:?} step", step.owner)),
+                    Some(Ok(detail)) => {
+                        self.available.extend(step.produces.iter().cloned());
+                        (StepStatus::Done, detail)
+                    }
+                    Some(Err(detail)) => {
+                        failed = Some(&step.id);
+                        (StepStatus::Failed, detail)
+                    }
+                }
+            };
+            steps.push(StepReport {
+                id: step.id.clone(), owner: step.owner, status, detail,
+                elapsed_ms: start.elapsed().as_millis() as u64,
+            });
+        }
+        PlanExecution {
+            plan_id: plan.plan_id.clone(),
+            artifact_id: plan.artifact_id.clone(),
+            strategy: plan.strategy.clone(),
+            ok: !steps.is_empty() && steps.iter().all(|s| s.status == StepStatus::Done),
+            frames_run: self.frame_hashes.len() as u64,
+            steps,
+        }
+    }
+
+    /// None when the step has no action
+    fn run_step(&mut self, step: &PipelineStep, plan: &CompatibilityPlan) -> Option<Result<String, String>> {
+        Some(match step.id.as_str() {
+            "load_emulator_core" => self.load_core(plan),
+            "map_bios_rom" => self.map_rom(),
+            "run_emulation_loop" => self.run_loop().map(|hashes| {
+                let n = hashes.len();
+                self.frame_hashes = hashes;
+                format!("{n} frames")
+            }),
+            "verify_equivalence" => self.verify(&plan.verification_target.equivalence_min),
+            _ => return None,
+        })
+    }
+
+    fn load_core(&self, plan: &CompatibilityPlan) -> Result<String, String> {
+        let info = self.core.info();
+        if info.is_null() { return Err("core returned no info".into()); }
+        let info = unsafe { &*info };
+        if info.abi_version == 0 || info.abi_version > MROM_ABI_VERSION {
+            return Err(format!("core ABI v{} (host speaks up to v{MROM_ABI_VERSION})", info.abi_version));
+        }
+        let id = if info.core_id.is_null() { "?".into() } else { unsafe { CStr::from_ptr(info.core_id) }.to_string_lossy() };
+        if let Some(chosen) = plan.core_selection.as_ref().and_then(|s| s.chosen.as_deref()) {
+            if chosen != id { return Err(format!("plan selected core {chosen}, host loaded {id}")); }
+        }
+        Ok(format!("{id} (ABI v{})", info.abi_version))
+    }
+
+    fn map_rom(&self) -> Result<String, String> {
+        match self.core.load_rom(self.rom) {
+            0 => Ok(format!("{} bytes", self.rom.len())),
+            code => Err(format!("load_rom returned {code}")),
+        }
+    }
+
+    /// Run `frames` frames; the hash of each video frame
+    fn run_loop(&self) -> Result<Vec<u64>, String> {
+        let mut hashes = Vec::with_capacity(self.frames as usize);
+        for frame in 0..self.frames {
+            let mut video = VideoFrame { data: ptr::null(), width: 0, height: 0, pitch: 0, pixel_format: 0 };
+            let mut audio = AudioFrame { samples: ptr::null(), sample_count: 0, sample_rate_hz: 0 };
+            self.core.run_frame(&mut video, &mut audio);
+            if video.data.is_null() || video.height == 0 {
+                return Err(format!("no video at frame {frame}"));
+            }
+            let bytes = unsafe { std::slice::from_raw_parts(video.data, (video.pitch * video.height) as usize) };
+            hashes.push(fnv1a(bytes));
+        }
+        Ok(hashes)
+    }
+
+    fn verify(&self, min: &EquivalenceLevel) -> Result<String, String> {
+        if self.frame_hashes.is_empty() { return Err("no emulation run to verify".into()); }
+        let degradations = self.core.diagnostics().degradations;
+        if *min >= EquivalenceLevel::L4_RENDER_EQ && !degradations.is_empty() {
+            let subsystems: Vec<&str> = degradations.iter().map(|d| d.subsystem.as_str()).collect();
+            return Err(format!("{min:?} needs full output, core degrades {}", subsystems.join(", ")));
+        }
+        self.core.unload_rom();
+        self.map_rom()?;
+        let rerun = self.run_loop()?;
+        match rerun.iter().zip(&self.frame_hashes).position(|(a, b)| a != b) {
+            Some(frame) => Err(format!("rerun diverged at frame {frame}")),
+            None => Ok(format!("rerun reproduced {} frames (target {min:?})", rerun.len())),
+        }
+    }
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
@@ -0,0 +1,221 @@
+//! gb_ecore — gb-core behind the ecore ABI, linked in-process
+//!
+//! `gb_ecore_handle()` gives hosts the same `EcoreHandle` they would get from
+//! a .mrom module's `mrom_ecore_init()`, backed by `GbCore`. The ABI's
+//! callbacks carry no instance pointer, so the vtable drives one core per
+//! process; callers that run several plans at once must take turns.
+//!
+//! Video is RGB888 (`PIXEL_FORMAT_RGB24`, 160×144, pitch 480), audio the
+//! APU's interleaved stereo PCM-16. Input word bits are gb-core's `BTN_*`
+//! mask for player 0. `request_interrupt` / `abort_frame` raise both the
+//! `Watchdog` and `GbCore::interrupt_handle`, so `run_frame` returns at the
+//! next instruction boundary.
+
+use gb_core::{Cartridge, CoreConfig, GbCore, HardwareModel, APU_SAMPLE_RATE, LCD_HEIGHT, LCD_WIDTH};
+use mrom_ecore_abi::{
+    AudioFrame, ECoreInfo, EcoreHandle, EcoreVtable, VideoFrame, Watchdog, WatchdogStatus,
+    DEFAULT_WEDGE_THRESHOLD, MROM_ABI_VERSION,
+};
+use std::ffi::{c_char, c_int, c_uint, CStr, CString};
+use std::os::raw::c_uchar;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use ucf_planner::{CoreAccuracy, CoreProfile};
+
+/// FOURCC "RGB3": packed 8-bit R, G, B
+pub const PIXEL_FORMAT_RGB24: u32 = u32::from_le_bytes(*b"RGB3");
+/// `ECoreInfo::core_id`
+pub const GB_ECORE_ID: &str = "gb_core";
+
+/// The loaded game and the buffers its last frame points into
+struct Slot {
+    core: GbCore,
+    video: Vec<u8>,
+    audio: Vec<i16>,
+    diagnostics: CString,
+}
+
+static SLOT: Mutex<Option<Slot>> = Mutex::new(None);
+/// The core's interrupt flag, reachable while `run_frame` holds `SLOT`
+static INTERRUPT: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+static WATCHDOG: Watchdog = Watchdog::new(DEFAULT_WEDGE_THRESHOLD);
+
+fn slot() -> MutexGuard<'static, Option<Slot>> { SLOT.lock().unwrap_or_else(|e| e.into_inner()) }
+
+fn raise_interrupt() {
+    if let Some(flag) = INTERRUPT.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        flag.store(true, Ordering::Release);
+    }
+}
+
+// ── Static info ──────────────────────────────────────────────────────────────
+
+/// Raw pointers to 'static C strings, shared read-only
+struct Shared<T>(T);
+unsafe impl<T> Sync for Shared<T> {}
+
+static MIME_TYPES: Shared<[*const c_char; 3]> = Shared([
+    c"application/x-gameboy-rom".as_ptr(),
+    c"application/x-gameboy-color-rom".as_ptr(),
+    ptr::null(),
+]);
+
+static INFO: Shared<ECoreInfo> = Shared(ECoreInfo {
+    abi_version: MROM_ABI_VERSION,
+    core_id: c"gb_core".as_ptr(),
+    label: c"MetaROM gb-core (DMG / CGB)".as_ptr(),
+    mime_types: MIME_TYPES.0.as_ptr(),
+    save_state_version: 1,
+});
+
+// ── Callbacks ────────────────────────────────────────────────────────────────
+
+unsafe extern "C" fn ecore_info() -> *const ECoreInfo { &INFO.0 }
+
+unsafe extern "C" fn load_rom(data: *const c_uchar, len: c_uint) -> c_int {
+    if data.is_null() { return 1; }
+    let rom = unsafe { std::slice::from_raw_parts(data, len as usize) }.to_vec();
+    let model = HardwareModel::for_rom(&rom);
+    let Ok(cart) = Cartridge::from_bytes(rom) else { return 1 };
+    let core = GbCore::with_config(cart, CoreConfig { model, ..CoreConfig::default() });
+    *INTERRUPT.lock().unwrap_or_else(|e| e.into_inner()) = Some(core.interrupt_handle());
+    *slot() = Some(Slot { core, video: vec![], audio: vec![], diagnostics: CString::default() });
+    0
+}
+
+unsafe extern "C" fn unload_rom() {
+    *slot() = None;
+    *INTERRUPT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+unsafe extern "C" fn run_frame(video_out: *mut VideoFrame, audio_out: *mut AudioFrame) {
+    let mut guard = slot();
+    let (video, audio): (&[u8], &[i16]) = match guard.as_mut() {
+        None => (&[], &[]),
+        Some(s) => {
+            WATCHDOG.frame_begin();
+            // Breakpoints, lockups and interrupts all just end the frame early
+            let _ = s.core.run_frame();
+            let samples = s.core.bus.apu.drain_samples();
+            if WATCHDOG.frame_end() {
+                s.video = s.core.framebuffer_rgb();
+                s.audio = samples;
+            } else {
+                s.video.clear();
+                s.audio.clear();
+            }
+            (&s.video, &s.audio)
+        }
+    };
+    if let Some(v) = unsafe { video_out.as_mut() } {
+        let shown = !video.is_empty();
+        *v = VideoFrame {
+            data: if shown { video.as_ptr() } else { ptr::null() },
+            width: if shown { LCD_WIDTH as c_uint } else { 0 },
+            height: if shown { LCD_HEIGHT as c_uint } else { 0 },
+            pitch: if shown { (LCD_WIDTH * 3) as c_uint } else { 0 },
+            pixel_format: PIXEL_FORMAT_RGB24,
+        };
+    }
+    if let Some(a) = unsafe { audio_out.as_mut() } {
+        *a = AudioFrame {
+            samples: if audio.is_empty() { ptr::null() } else { audio.as_ptr() },
+            sample_count: (audio.len() / 2) as c_uint,
+            sample_rate_hz: APU_SAMPLE_RATE as c_uint,
+        };
+    }
+}
+
+unsafe extern "C" fn save_state(buf: *mut c_uchar, buf_len: c_uint) -> c_uint {
+    let guard = slot();
+    let Some(s) = guard.as_ref() else { return 0 };
+    let state = s.core.save_state();
+    if buf.is_null() { return state.len() as c_uint; }
+    if (buf_len as usize) < state.len() { return 0; }
+    unsafe { ptr::copy_nonoverlapping(state.as_ptr(), buf, state.len()) };
+    state.len() as c_uint
+}
+
+unsafe extern "C" fn load_state(buf: *const c_uchar, buf_len: c_uint) -> c_int {
+    if buf.is_null() { return 1; }
+    let data = unsafe { std::slice::from_raw_parts(buf, buf_len as usize) };
+    let ok = slot().as_mut().is_some_and(|s| s.core.load_state(data).is_ok());
+    (!ok) as c_int
+}
+
+unsafe extern "C" fn set_input(player: c_uint, input_word: u32) {
+    if player != 0 { return; }
+    if let Some(s) = slot().as_mut() { s.core.set_buttons(input_word as u8); }
+}
+
+unsafe extern "C" fn configure(json_cfg: *const c_char) -> c_int {
+    if json_cfg.is_null() { return 1; }
+    let Ok(json) = unsafe { CStr::from_ptr(json_cfg) }.to_str() else { return 1 };
+    let ok = slot().as_mut().is_some_and(|s| s.core.configure(json).is_ok());
+    (!ok) as c_int
+}
+
+unsafe extern "C" fn diagnostics() -> *const c_char {
+    match slot().as_mut() {
+        None => c"{}".as_ptr(),
+        Some(s) => {
+            s.diagnostics = CString::new(s.core.diagnostics_json()).unwrap_or_default();
+            s.diagnostics.as_ptr()
+        }
+    }
+}
+
+unsafe extern "C" fn watchdog_status(out: *mut WatchdogStatus) -> c_int {
+    match unsafe { out.as_mut() } {
+        Some(out) => { *out = WATCHDOG.status(); 0 }
+        None => 1,
+    }
+}
+
+unsafe extern "C" fn request_interrupt() {
+    WATCHDOG.request_interrupt();
+    raise_interrupt();
+}
+
+unsafe extern "C" fn abort_frame() {
+    WATCHDOG.abort_frame();
+    raise_interrupt();
+}
+
+unsafe extern "C" fn reset() {
+    if let Some(s) = slot().as_mut() { s.core.reset(); }
+}
+
+static VTABLE: EcoreVtable = EcoreVtable {
+    ecore_info, load_rom, unload_rom, run_frame, save_state, load_state, set_input,
+    configure, diagnostics, watchdog_status, request_interrupt, abort_frame, reset,
+};
+
+/// gb-core's vtable, as a .mrom module's `mrom_ecore_init()` would return it
+pub fn gb_ecore_vtable() -> *const EcoreVtable { &VTABLE }
+
+/// A handle on the in-process gb-core
+pub fn gb_ecore_handle() -> EcoreHandle {
+    // SAFETY: VTABLE is a static and there is no library to keep loaded
+    unsafe { EcoreHandle::new(&VTABLE, ptr::null_mut()) }
+}
+
+/// gb-core as a planner core candidate (`plan --core`): its `ECoreInfo`,
+/// the Game Boy models it runs and its determinism. The test-suite pass
+/// rate is left for the host to fill in from an `mrom-testsuite` report.
+pub fn gb_core_profile() -> CoreProfile {
+    let info = &INFO.0;
+    // SAFETY: INFO and MIME_TYPES hold 'static, null-terminated C strings
+    let text = |p: *const c_char| unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned();
+    CoreProfile {
+        core_id: text(info.core_id),
+        label: text(info.label),
+        abi_version: info.abi_version,
+        mime_types: MIME_TYPES.0.iter().take_while(|p| !p.is_null()).map(|&p| text(p)).collect(),
+        save_state_version: info.save_state_version,
+        platforms: ["gb_dmg", "gb_mgb", "gb_sgb", "gb_cgb"].map(String::from).to_vec(),
+        isas: vec!["sm83".into()],
+        accuracy: CoreAccuracy { deterministic: true, ..CoreAccuracy::default() },
+    }
+}
@@ -0,0 +1,13 @@
+//! mrom-host — MetaROM host runtime
+//!
+//! Turns a `CompatibilityPlan` from a document into a run: `PlanDriver`
+//! walks the plan's typed pipeline and performs each step it has an action
+//! for against an emulator core loaded through the ecore ABI, reporting
+//! per-step status. `gb_ecore` exposes gb-core through that ABI in-process,
+//! so Game Boy plans execute without a separate .mrom module.
+
+pub mod driver;
+pub mod gb_ecore;
+
+pub use crate::driver::*;
+pub use crate::gb_ecore::*;
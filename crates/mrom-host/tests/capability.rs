@@ -0,0 +1,23 @@
+//! gb-core's self-description read as a planner capability graph
+
+use ucf_planner::gap::{analyze_gaps, GapSeverity};
+use ucf_planner::model::{CapabilityGraph, GameRequirement};
+use ucf_planner::{parse_document, Strictness};
+
+#[test]
+fn gb_core_profile_is_a_strict_capability_graph() {
+    let graph: CapabilityGraph = parse_document(&gb_core::capability_profile(), Strictness::DenyUnknownFields).unwrap();
+    assert_eq!(graph.platform_id, mrom_host::GB_ECORE_ID);
+    assert_eq!(graph.platform_id, gb_core::CAPABILITY_PLATFORM_ID);
+
+    let tetris: GameRequirement = serde_json::from_value(serde_json::json!({
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]},
+        "gpu": {"required_apis": ["dmg_ppu"]},
+    })).unwrap();
+    let gaps = analyze_gaps(&tetris, &graph);
+    assert!(!gaps.has_any_hard(), "{gaps:?}");
+    for status in [&gaps.cpu, &gaps.gpu, &gaps.runtime] {
+        assert_eq!(status.severity, GapSeverity::None, "{status:?}");
+    }
+}
@@ -0,0 +1,37 @@
+//! gb-core checkpoint reports graded by the planner
+
+use gb_core::{parse_checkpoints, run_checkpoints, Cartridge, Code, GbCore, RomBuilder, CODE_START};
+use ucf_planner::model::{CompatibilityPlan, EquivalenceLevel};
+
+fn plan(equivalence_min: &str) -> CompatibilityPlan {
+    serde_json::from_value(serde_json::json!({
+        "plan_version": "0.1", "plan_id": "plan-1", "artifact_id": "gb:checks",
+        "target_platform_id": "pc", "helper_platform_ids": [],
+        "strategy": "Emulate", "strategy_pipeline": [], "rationale": [],
+        "gaps": {"cpu_gap": "none", "gpu_gap": "none", "memory_gap": "none", "runtime_gap": "none", "timing_gap": "none"},
+        "degradations": [], "requirements_for_user": {"firmware": [], "network": {}, "setup_steps": []},
+        "scores": {"fidelity": 90, "latency": 90, "engineering_effort": 10, "runtime_cost": 10,
+                   "legal_risk": 0, "determinism": 90, "user_friction": 10, "total": 80},
+        "verification_target": {"equivalence_min": equivalence_min, "test_profile": "default"},
+        "confidence": 0.9,
+    })).unwrap()
+}
+
+#[test]
+fn a_gb_core_checkpoint_report_meets_the_plan_target() {
+    let code = Code::new(CODE_START).ld_a(0x91).st_a(0xFF40).serial_print("Passed\n").spin();
+    let mut core = GbCore::new(Cartridge::from_bytes(RomBuilder::new().title("CHECKS").code(code.bytes()).build()).unwrap());
+    let checkpoints = parse_checkpoints("
+        boot:   pc 0150 within 2
+        video:  ly cycles within 3 -> L1_STABLE
+        passed: serial \"Passed\" within 5 -> L2_INTERACTIVE
+        never:  pc 7000 within 4 -> L4_RENDER_EQ
+    ").unwrap();
+    let report: ucf_planner::CheckpointReport = serde_json::from_str(&run_checkpoints(&mut core, &checkpoints).unwrap().to_json()).unwrap();
+    assert_eq!(report.results[0].state_hash.as_ref().map(String::len), Some(16));
+
+    let outcome = ucf_planner::evaluate_verification(&plan("L2_INTERACTIVE"), &report).unwrap();
+    assert_eq!((outcome.achieved, outcome.met), (Some(EquivalenceLevel::L2_INTERACTIVE), true));
+    assert_eq!(outcome.failed, ["never (L4_RENDER_EQ): not reached within 4 frames"]);
+    assert!(!ucf_planner::evaluate_verification(&plan("L4_RENDER_EQ"), &report).unwrap().met);
+}
@@ -0,0 +1,145 @@
+//! Plan execution against the in-process gb-core
+
+use gb_core::{Code, RomBuilder, CODE_START};
+use mrom_ecore_abi::{AudioFrame, VideoFrame};
+use mrom_host::*;
+use std::ffi::CStr;
+use std::sync::{Mutex, MutexGuard};
+use ucf_planner::model::CompatibilityPlan;
+
+/// The vtable drives one core per process; tests take turns
+static CORE: Mutex<()> = Mutex::new(());
+
+fn lock() -> MutexGuard<'static, ()> { CORE.lock().unwrap_or_else(|e| e.into_inner()) }
+
+fn rom() -> Vec<u8> {
+    // LCD on, then INC (C000) forever: the same picture every frame
+    let code = Code::new(CODE_START).ld_a(0x91).st_a(0xFF40);
+    let top = code.here();
+    let code = code.ld_hl(0xC000).raw(&[0x34]).jr(top);
+    RomBuilder::new().title("PLANRUN").code(code.bytes()).build()
+}
+
+fn plan(strategy: &str, steps: &[&str]) -> CompatibilityPlan {
+    serde_json::from_value(serde_json::json!({
+        "plan_version": "0.1", "plan_id": "plan-1", "artifact_id": "gb:planrun",
+        "target_platform_id": "pc", "helper_platform_ids": [],
+        "strategy": strategy, "strategy_pipeline": steps, "rationale": [],
+        "gaps": {"cpu_gap": "none", "gpu_gap": "none", "memory_gap": "none", "runtime_gap": "none", "timing_gap": "none"},
+        "degradations": [], "requirements_for_user": {"firmware": [], "network": {}, "setup_steps": []},
+        "scores": {"fidelity": 90, "latency": 90, "engineering_effort": 10, "runtime_cost": 10,
+                   "legal_risk": 0, "determinism": 90, "user_friction": 10, "total": 80},
+        "verification_target": {"equivalence_min": "L4_RENDER_EQ", "test_profile": "default"},
+        "confidence": 0.9,
+    })).unwrap()
+}
+
+const EMULATE: [&str; 4] = ["load_emulator_core", "map_bios_rom", "run_emulation_loop", "verify_equivalence"];
+
+#[test]
+fn emulate_pipeline_runs_end_to_end() {
+    let _turn = lock();
+    let (core, rom) = (gb_ecore_handle(), rom());
+    let run = PlanDriver::new(&core, &rom).with_frames(30).execute(&plan("Emulate", &EMULATE));
+    assert!(run.ok, "{run:#?}");
+    assert_eq!(run.frames_run, 30);
+    let statuses: Vec<StepStatus> = run.steps.iter().map(|s| s.status).collect();
+    assert_eq!(statuses, [StepStatus::Done; 4]);
+    assert!(run.step("load_emulator_core").unwrap().detail.starts_with("gb_core (ABI v"));
+    assert_eq!(run.step("verify_equivalence").unwrap().detail, "rerun reproduced 30 frames (target L4_RENDER_EQ)");
+
+    let report = serde_json::to_value(&run).unwrap();
+    assert_eq!(report["steps"][2]["status"], "done");
+    assert_eq!(report["steps"][2]["owner"], "emulator_core");
+    core.unload_rom();
+}
+
+#[test]
+fn unrunnable_steps_are_skipped_and_their_dependents_blocked() {
+    let _turn = lock();
+    let (core, rom) = (gb_ecore_handle(), rom());
+    core.unload_rom();
+    let steps = ["load_emulator_core", "api_translation_layer", "shader_transpile", "run_emulation_loop", "verify_equivalence"];
+    let run = PlanDriver::new(&core, &rom).with_frames(5).execute(&plan("EmulatePlusTranslate", &steps));
+    assert!(!run.ok);
+    let statuses: Vec<StepStatus> = run.steps.iter().map(|s| s.status).collect();
+    assert_eq!(statuses, [StepStatus::Done, StepStatus::Skipped, StepStatus::Blocked, StepStatus::Failed, StepStatus::Blocked]);
+    assert_eq!(run.steps[1].detail, "no host action for Translation step");
+    assert_eq!(run.steps[2].detail, "missing translated_api");
+    assert_eq!(run.steps[3].detail, "no video at frame 0", "this pipeline never maps the ROM");
+}
+
+#[test]
+fn a_failed_step_blocks_the_rest() {
+    let _turn = lock();
+    let core = gb_ecore_handle();
+    let run = PlanDriver::new(&core, &[0u8; 16]).execute(&plan("Emulate", &EMULATE));
+    let statuses: Vec<StepStatus> = run.steps.iter().map(|s| s.status).collect();
+    assert_eq!(statuses, [StepStatus::Done, StepStatus::Failed, StepStatus::Blocked, StepStatus::Blocked]);
+    assert_eq!(run.steps[3].detail, "not run: map_bios_rom failed");
+    assert_eq!(run.frames_run, 0);
+}
+
+#[test]
+fn the_plan_selected_core_must_be_the_one_loaded() {
+    let _turn = lock();
+    let (core, rom) = (gb_ecore_handle(), rom());
+    let game: ucf_planner::model::GameRequirement = serde_json::from_value(serde_json::json!({
+        "artifact_id": "gb:planrun", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]},
+    })).unwrap();
+    let other: ucf_planner::CoreProfile = serde_json::from_value(serde_json::json!({
+        "core_id": "other_gb", "abi_version": 3, "platforms": ["gb_dmg"],
+        "accuracy": {"test_pass_rate": 1.0, "verified_equivalence": "L5_BIT_EXACT", "deterministic": true},
+    })).unwrap();
+    let profile = gb_core_profile();
+    assert_eq!((profile.core_id.as_str(), profile.abi_version), (GB_ECORE_ID, mrom_ecore_abi::MROM_ABI_VERSION));
+    assert_eq!(profile.mime_types.len(), 2);
+
+    let mut ours = plan("Emulate", &EMULATE);
+    ucf_planner::apply_core_selection(&mut ours, std::slice::from_ref(&profile), &game);
+    let run = PlanDriver::new(&core, &rom).with_frames(2).execute(&ours);
+    assert_eq!(run.steps[0].status, StepStatus::Done);
+
+    let mut theirs = plan("Emulate", &EMULATE);
+    ucf_planner::apply_core_selection(&mut theirs, &[profile, other], &game);
+    assert_eq!(theirs.core_selection.as_ref().unwrap().chosen.as_deref(), Some("other_gb"));
+    let run = PlanDriver::new(&core, &rom).with_frames(2).execute(&theirs);
+    assert_eq!(run.steps[0].status, StepStatus::Failed);
+    assert_eq!(run.steps[0].detail, "plan selected core other_gb, host loaded gb_core");
+    core.unload_rom();
+}
+
+#[test]
+fn gb_core_speaks_the_ecore_abi() {
+    let _turn = lock();
+    let core = gb_ecore_handle();
+    let info = unsafe { &*core.info() };
+    assert_eq!(unsafe { CStr::from_ptr(info.core_id) }.to_str().unwrap(), GB_ECORE_ID);
+    assert!(!unsafe { *info.mime_types }.is_null());
+
+    assert_eq!(core.load_rom(&rom()), 0);
+    let before = core.watchdog_status().unwrap().frames_completed;
+    let mut video = VideoFrame { data: std::ptr::null(), width: 0, height: 0, pitch: 0, pixel_format: 0 };
+    let mut audio = AudioFrame { samples: std::ptr::null(), sample_count: 0, sample_rate_hz: 0 };
+    core.run_frame(&mut video, &mut audio);
+    assert_eq!((video.width, video.height, video.pitch, video.pixel_format), (160, 144, 480, PIXEL_FORMAT_RGB24));
+    assert!(audio.sample_count > 0);
+    assert_eq!(core.watchdog_status().unwrap().frames_completed, before + 1);
+
+    // Motion is a delta, so it needs two frames with the tracker on
+    assert!(core.configure(&mrom_ecore_abi::CoreOptions { motion_metadata: Some(true) }));
+    core.run_frame(&mut video, &mut audio);
+    core.run_frame(&mut video, &mut audio);
+    let diag = core.diagnostics_json().unwrap();
+    assert!(diag.contains("\"frame\":3"), "{diag}");
+    assert!(core.diagnostics().motion.is_some(), "{diag}");
+
+    // An interrupt requested between frames cuts the next one short
+    assert!(core.request_interrupt());
+    core.run_frame(&mut video, &mut audio);
+    assert!(core.diagnostics_json().unwrap().contains("\"frame\":3"));
+    assert!(core.reset());
+    core.unload_rom();
+    assert_eq!(core.diagnostics_json().unwrap(), "{}");
+}
@@ -0,0 +1,28 @@
+//! authoring.rs — parsing hand-authored input documents
+//!
+//! Model types accept partial documents (see the defaults in `model.rs`). By
+//! default unknown fields are ignored, which keeps older tools reading newer
+//! files; `Strictness::DenyUnknownFields` opts in to rejecting them instead, so
+//! a typo like `"ram_mbb"` is reported rather than silently defaulted.
+
+use serde::de::DeserializeOwned;
+use std::error::Error;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness {
+    #[default]
+    Lenient,
+    DenyUnknownFields,
+}
+
+/// Parse a JSON document into `T`, applying model defaults and `strictness`.
+pub fn parse_document<T: DeserializeOwned>(text: &str, strictness: Strictness) -> Result<T, Box<dyn Error>> {
+    // Unknown keys are the ones serde itself skips, reported by their path
+    // (`memory.ram_mbb`, `helpers.0.extra`); null or empty known fields are fine.
+    let mut unknown = vec![];
+    let doc: T = serde_ignored::deserialize(&mut serde_json::Deserializer::from_str(text), |path| unknown.push(path.to_string()))?;
+    if strictness == Strictness::DenyUnknownFields && !unknown.is_empty() {
+        return Err(format!("unknown field(s): {}", unknown.join(", ")).into());
+    }
+    Ok(doc)
+}
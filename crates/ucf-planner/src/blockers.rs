@@ -0,0 +1,142 @@
+//! blockers.rs — next steps for a NotFeasible plan
+//!
+//! Everything here is read back from `planner::gate_blocks()`: a blocker is a
+//! hard gap or policy flag, the strategies it eliminated, and the capability
+//! or policy change that lifts it. Nothing is inferred beyond the gate rules.
+
+use crate::gap::{GapKind, GapSeverity, GapStatus, GapVector};
+use crate::model::{CapabilityGraph, GameRequirement, PlanScores, PolicyProfile, StrategyClass};
+use crate::planner::{gate_blocks, GateBlock};
+use crate::strategy::Strategy;
+use serde::{Deserialize, Serialize};
+
+/// What a blocker is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BlockerCause {
+    /// Hard gap on `subsystem` (gap reason codes as reported by `analyze_gaps`)
+    HardGap { subsystem: GapKind, codes: Vec<String> },
+    /// `PolicyProfile` flag that is off
+    Policy { flag: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Blocker {
+    pub cause: BlockerCause,
+    /// Strategies the gate rejected because of this blocker
+    pub eliminated: Vec<StrategyClass>,
+    /// Capability/policy changes that lift it
+    pub unblock: Vec<String>,
+}
+
+/// The highest-scoring eliminated strategy and everything blocking it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnblockPath {
+    pub strategy: StrategyClass,
+    pub total_score: u8,
+    pub unblock: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockerReport {
+    pub blockers: Vec<Blocker>,
+    pub best_alternative: Option<UnblockPath>,
+}
+
+/// Build the report from the planner's scored candidates (sorted best first).
+pub fn report_blockers(
+    scored: &[(Strategy, PlanScores)],
+    gaps: &GapVector,
+    policy: &PolicyProfile,
+    game: &GameRequirement,
+    target: &CapabilityGraph,
+) -> BlockerReport {
+    let gated: Vec<(Strategy, u8, Vec<GateBlock>)> = scored
+        .iter()
+        .filter(|(s, _)| *s != Strategy::NotFeasible)
+        .map(|(s, scores)| (*s, scores.total, gate_blocks(*s, scores, gaps, policy)))
+        .collect();
+    let eliminated_by = |block: &GateBlock| -> Vec<StrategyClass> {
+        gated.iter().filter(|(_, _, b)| b.contains(block)).map(|(s, _, _)| (*s).into()).collect()
+    };
+
+    let mut blockers = vec![];
+    for (kind, status) in subsystems(gaps) {
+        if status.severity != GapSeverity::Hard { continue; }
+        blockers.push(Blocker {
+            cause: BlockerCause::HardGap {
+                subsystem: kind.clone(),
+                codes: status.reasons.iter().map(|r| r.code.clone()).collect(),
+            },
+            eliminated: eliminated_by(&GateBlock::HardGap(kind.clone())),
+            unblock: gap_hints(&kind, status, game, target),
+        });
+    }
+    let mut flags: Vec<&'static str> = vec![];
+    for (_, _, blocks) in &gated {
+        for b in blocks {
+            if let GateBlock::Policy(flag) = b {
+                if !flags.contains(flag) { flags.push(flag); }
+            }
+        }
+    }
+    for flag in flags {
+        blockers.push(Blocker {
+            cause: BlockerCause::Policy { flag: flag.into() },
+            eliminated: eliminated_by(&GateBlock::Policy(flag)),
+            unblock: vec![policy_hint(flag)],
+        });
+    }
+
+    let best_alternative = gated.iter().find(|(_, _, b)| !b.is_empty()).map(|(s, total, blocks)| {
+        let mut unblock: Vec<String> = vec![];
+        for b in blocks {
+            let hints = match b {
+                GateBlock::Policy(flag) => vec![policy_hint(flag)],
+                GateBlock::HardGap(kind) => {
+                    let status = subsystems(gaps).into_iter().find(|(k, _)| k == kind).map(|(_, st)| st);
+                    status.map(|st| gap_hints(kind, st, game, target)).unwrap_or_default()
+                }
+            };
+            for h in hints {
+                if !unblock.contains(&h) { unblock.push(h); }
+            }
+        }
+        UnblockPath { strategy: (*s).into(), total_score: *total, unblock }
+    });
+
+    BlockerReport { blockers, best_alternative }
+}
+
+fn subsystems(gaps: &GapVector) -> [(GapKind, &GapStatus); 7] {
+    [
+        (GapKind::Cpu, &gaps.cpu), (GapKind::Gpu, &gaps.gpu), (GapKind::Memory, &gaps.memory),
+        (GapKind::Runtime, &gaps.runtime), (GapKind::Io, &gaps.io), (GapKind::Timing, &gaps.timing),
+        (GapKind::Legal, &gaps.legal),
+    ]
+}
+
+fn policy_hint(flag: &str) -> String {
+    match flag {
+        "max_legal_risk" => "raise max_legal_risk, or clear the legal / runtime gaps behind the legal risk".into(),
+        _ => format!("enable {flag}"),
+    }
+}
+
+/// Target change that clears each hard reason code (mirrors the checks in `gap.rs`)
+fn gap_hints(kind: &GapKind, status: &GapStatus, game: &GameRequirement, target: &CapabilityGraph) -> Vec<String> {
+    status.reasons.iter().map(|r| match r.code.as_str() {
+        "ISA_MISMATCH" => format!("target cpu.isas must include one of {:?}", game.cpu.required_isa),
+        "GPU_API_AND_SHADER_MODEL_MISMATCH" => match &game.gpu.shader_model {
+            Some(sm) => format!("target gpu.apis must include one of {:?} or gpu.shader_models must include {sm:?}", game.gpu.required_apis),
+            None => format!("target gpu.apis must include one of {:?}", game.gpu.required_apis),
+        },
+        "RAM_BELOW_MIN" => format!(
+            "add ≥{}MB RAM (memory.ram_mb {} → {})",
+            game.memory.ram_min_mb.saturating_sub(target.memory.ram_mb), target.memory.ram_mb, game.memory.ram_min_mb
+        ),
+        "OS_FAMILY_MISMATCH" => format!("target host_os.family must be one of {:?}", game.runtime.os_families),
+        "ONLINE_REQUIRED_NETWORK_UNAVAILABLE" => "make io.network.available on the target".into(),
+        code => format!("resolve {code} on the {kind:?} subsystem"),
+    }).collect()
+}
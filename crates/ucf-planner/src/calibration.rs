@@ -0,0 +1,115 @@
+//! calibration.rs — plan confidence calibrated by past verification outcomes
+//!
+//! `derive_confidence()` starts from a heuristic: the winning score minus a
+//! penalty per gap. A `CalibrationStore` remembers, for each strategy and
+//! gap signature, how many plans later passed or failed verification. When
+//! a store is supplied with the `PlanningRequest`, the heuristic counts as
+//! `PRIOR_WEIGHT` runs and is blended with the recorded ones, so confidence
+//! converges on the observed pass rate as outcomes accumulate. Hosts call
+//! `CalibrationStore::record()` with each plan once it has been verified.
+
+use crate::model::{CompatibilityPlan, GapSummary, StrategyClass};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+/// `calibration_version` of stores this planner writes
+pub const CALIBRATION_VERSION: &str = "0.1";
+/// How many recorded runs the heuristic confidence is worth
+pub const PRIOR_WEIGHT: f32 = 5.0;
+
+/// The plan's non-`none` gaps in a fixed order, e.g. `cpu:hard,runtime:hard`;
+/// `none` when there are no gaps
+pub fn gap_signature(gaps: &GapSummary) -> String {
+    let axes = [
+        ("cpu", Some(&gaps.cpu_gap)), ("gpu", Some(&gaps.gpu_gap)), ("memory", Some(&gaps.memory_gap)),
+        ("runtime", Some(&gaps.runtime_gap)), ("io", gaps.io_gap.as_ref()), ("timing", Some(&gaps.timing_gap)),
+        ("legal", gaps.legal_gap.as_ref()),
+    ];
+    let parts: Vec<String> = axes.iter()
+        .filter_map(|(axis, sev)| sev.filter(|s| *s != "none").map(|s| format!("{axis}:{s}")))
+        .collect();
+    if parts.is_empty() { "none".into() } else { parts.join(",") }
+}
+
+/// Verification outcomes for one strategy against one gap signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalibrationEntry {
+    pub strategy: StrategyClass,
+    pub gap_signature: String,
+    pub passed: u32,
+    pub failed: u32,
+}
+
+impl CalibrationEntry {
+    pub fn runs(&self) -> u32 { self.passed + self.failed }
+    pub fn pass_rate(&self) -> f32 {
+        if self.runs() == 0 { 0.0 } else { self.passed as f32 / self.runs() as f32 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalibrationStore {
+    #[serde(default = "default_calibration_version")]
+    pub calibration_version: String,
+    #[serde(default)]
+    pub entries: Vec<CalibrationEntry>,
+}
+
+fn default_calibration_version() -> String { CALIBRATION_VERSION.into() }
+
+impl Default for CalibrationStore {
+    fn default() -> Self { Self { calibration_version: CALIBRATION_VERSION.into(), entries: vec![] } }
+}
+
+impl CalibrationStore {
+    /// Read a store; a missing file is an empty store
+    pub fn load(path: &Path) -> Result<CalibrationStore, Box<dyn Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()).into()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(CalibrationStore::default()),
+            Err(e) => Err(format!("{}: {e}", path.display()).into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn entry(&self, strategy: &StrategyClass, gap_signature: &str) -> Option<&CalibrationEntry> {
+        self.entries.iter().find(|e| e.strategy == *strategy && e.gap_signature == gap_signature)
+    }
+
+    /// Count one verification outcome
+    pub fn record_outcome(&mut self, strategy: StrategyClass, gap_signature: &str, passed: bool) -> &CalibrationEntry {
+        let i = match self.entries.iter().position(|e| e.strategy == strategy && e.gap_signature == gap_signature) {
+            Some(i) => i,
+            None => {
+                self.entries.push(CalibrationEntry { strategy, gap_signature: gap_signature.into(), passed: 0, failed: 0 });
+                self.entries.len() - 1
+            }
+        };
+        let e = &mut self.entries[i];
+        if passed { e.passed += 1 } else { e.failed += 1 }
+        e
+    }
+
+    /// Count the verification outcome of `plan`
+    pub fn record(&mut self, plan: &CompatibilityPlan, passed: bool) -> &CalibrationEntry {
+        self.record_outcome(plan.strategy.clone(), &gap_signature(&plan.gaps), passed)
+    }
+
+    /// `heuristic` blended with the recorded outcomes for this strategy and
+    /// gap signature, plus the rationale line explaining it (None when
+    /// nothing has been recorded)
+    pub fn calibrate(&self, strategy: &StrategyClass, gap_signature: &str, heuristic: f32) -> (f32, Option<String>) {
+        let Some(e) = self.entry(strategy, gap_signature).filter(|e| e.runs() > 0) else { return (heuristic, None) };
+        let calibrated = ((heuristic * PRIOR_WEIGHT + e.passed as f32) / (PRIOR_WEIGHT + e.runs() as f32)).clamp(0.05, 0.99);
+        let note = format!(
+            "Calibration: {}/{} verification run(s) passed for {strategy:?} with gaps {gap_signature}; confidence {heuristic:.2} → {calibrated:.2}",
+            e.passed, e.runs()
+        );
+        (calibrated, Some(note))
+    }
+}
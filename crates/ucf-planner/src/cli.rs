@@ -1,5 +1,14 @@
-use crate::model::{CapabilityGraph, GameRequirement, PlanningRequest, PolicyProfile};
+use crate::authoring::{parse_document, Strictness};
+use crate::calibration::{gap_signature, CalibrationEntry, CalibrationStore};
+use crate::cores::{chosen_core, CoreProfile};
+use crate::evidence::Evidence;
+use crate::fixtures::{reference_profile, reference_profiles};
+use crate::model::{CapabilityGraph, CompatibilityPlan, GameRequirement, PlanningRequest, PolicyProfile};
+use crate::modes::{plan_all_modes, AllModesPlan};
 use crate::planner::plan_execution;
+use crate::telemetry::{ingest_telemetry, replan_on_telemetry, TelemetryOutcome, TelemetrySample};
+use crate::verification::{evaluate_verification, CheckpointReport, VerificationOutcome};
+use serde::Serialize;
 use std::error::Error;
 use std::fs;
 use std::path::PathBuf;
@@ -8,17 +17,47 @@ pub fn run() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
     if args.len() < 2 { print_help(); std::process::exit(2); }
     match args[1].as_str() {
-        "plan" => run_plan(&args[2..]),
+        "plan" => print_json(plan_command(&args[2..])?),
+        "telemetry" => print_json(telemetry_command(&args[2..])?),
+        "capabilities" => capabilities_command(&args[2..]),
+        "record-outcome" => print_json(record_outcome_command(&args[2..])?),
+        "verify" => {
+            let outcome = verify_command(&args[2..])?;
+            let met = outcome.met;
+            print_json(outcome)?;
+            if !met { std::process::exit(1); }
+            Ok(())
+        }
         _ => { eprintln!("unknown command: {}", args[1]); print_help(); std::process::exit(2); }
     }
 }
 
-fn run_plan(args: &[String]) -> Result<(), Box<dyn Error>> {
+fn print_json(doc: impl serde::Serialize) -> Result<(), Box<dyn Error>> {
+    println!("{}", serde_json::to_string_pretty(&doc)?);
+    Ok(())
+}
+
+/// What `plan` produces: one plan, or the `--all-modes` document
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum PlanOutput {
+    Plan(Box<CompatibilityPlan>),
+    AllModes(AllModesPlan),
+}
+
+/// `plan` subcommand without the printing: parse flags and plan. Warnings go
+/// to stderr. Shared with the `metarom` CLI.
+pub fn plan_command(args: &[String]) -> Result<PlanOutput, Box<dyn Error>> {
     let mut artifact_path: Option<PathBuf> = None;
     let mut target_path: Option<PathBuf> = None;
     let mut helper_paths: Vec<PathBuf> = vec![];
     let mut policy_path: Option<PathBuf> = None;
     let mut mode_id: Option<String> = None;
+    let mut evidence_paths: Vec<PathBuf> = vec![];
+    let mut calibration_path: Option<PathBuf> = None;
+    let mut core_paths: Vec<PathBuf> = vec![];
+    let mut strictness = Strictness::Lenient;
+    let mut all_modes = false;
 
     let mut i = 0usize;
     while i < args.len() {
@@ -28,27 +67,188 @@ fn run_plan(args: &[String]) -> Result<(), Box<dyn Error>> {
             "--helper"   => { i += 1; helper_paths.push(PathBuf::from(require_arg(args, i, "--helper")?)); }
             "--policy"   => { i += 1; policy_path = Some(PathBuf::from(require_arg(args, i, "--policy")?)); }
             "--mode"     => { i += 1; mode_id = Some(require_arg(args, i, "--mode")?.to_string()); }
+            "--evidence" => { i += 1; evidence_paths.push(PathBuf::from(require_arg(args, i, "--evidence")?)); }
+            "--calibration" => { i += 1; calibration_path = Some(PathBuf::from(require_arg(args, i, "--calibration")?)); }
+            "--core"     => { i += 1; core_paths.push(PathBuf::from(require_arg(args, i, "--core")?)); }
+            "--strict"   => { strictness = Strictness::DenyUnknownFields; }
+            "--all-modes" => { all_modes = true; }
             other => { return Err(format!("unexpected argument: {other}").into()); }
         }
         i += 1;
     }
 
+    if all_modes && mode_id.is_some() { return Err("--all-modes and --mode are exclusive".into()); }
     let artifact_path = artifact_path.ok_or("missing --artifact <req.json>")?;
     let target_path = target_path.ok_or("missing --target <cap.json>")?;
-    let game: GameRequirement = read_json(&artifact_path)?;
-    let target: CapabilityGraph = read_json(&target_path)?;
-    let helpers: Vec<CapabilityGraph> = helper_paths.iter().map(read_json).collect::<Result<Vec<_>, _>>()?;
-    let policy: PolicyProfile = if let Some(p) = policy_path { read_json(&p)? } else { default_policy() };
+    let game: GameRequirement = read_json(&artifact_path, strictness)?;
+    let target: CapabilityGraph = read_json(&target_path, strictness)?;
+    let helpers: Vec<CapabilityGraph> = helper_paths.iter().map(|p| read_json(p, strictness)).collect::<Result<Vec<_>, _>>()?;
+    let policy: PolicyProfile = if let Some(p) = policy_path { read_json(&p, strictness)? } else { default_policy() };
+    let evidence: Vec<Evidence> = evidence_paths.iter().map(read_evidence).collect::<Result<Vec<_>, _>>()?;
+    let calibration = calibration_path.map(|p| CalibrationStore::load(&p)).transpose()?;
+    let cores: Vec<CoreProfile> = core_paths.iter().map(|p| read_json(p, strictness)).collect::<Result<Vec<_>, _>>()?;
+    let core = chosen_core(&cores, &game, mode_id.as_deref());
+    for e in &evidence {
+        if let Some(why) = e.rejection(&game, core.as_deref()) { eprintln!("warning: evidence {} {why}, ignored", e.source); }
+    }
 
-    let req = PlanningRequest { game: &game, target: &target, helpers: &helpers, policy: &policy, mode_id: mode_id.as_deref() };
+    let req = PlanningRequest {
+        game: &game, target: &target, helpers: &helpers, policy: &policy,
+        mode_id: mode_id.as_deref(), evidence: &evidence, calibration: calibration.as_ref(), cores: &cores,
+    };
+    if all_modes {
+        let plans = plan_all_modes(req)?;
+        for w in plans.plans.iter().flat_map(|m| &m.plan.input_versions.warnings) { eprintln!("warning: {w}"); }
+        return Ok(PlanOutput::AllModes(plans));
+    }
     let plan = plan_execution(req)?;
-    println!("{}", serde_json::to_string_pretty(&plan)?);
-    Ok(())
+    for w in &plan.input_versions.warnings { eprintln!("warning: {w}"); }
+    Ok(PlanOutput::Plan(Box::new(plan)))
+}
+
+/// Fold a telemetry sample into the stored target graph (rewritten in place)
+/// and return the assumption check, plus a new plan if one was triggered
+pub fn telemetry_command(args: &[String]) -> Result<TelemetryOutcome, Box<dyn Error>> {
+    let mut plan_path: Option<PathBuf> = None;
+    let mut sample_path: Option<PathBuf> = None;
+    let mut artifact_path: Option<PathBuf> = None;
+    let mut target_path: Option<PathBuf> = None;
+    let mut helper_paths: Vec<PathBuf> = vec![];
+    let mut policy_path: Option<PathBuf> = None;
+    let mut mode_id: Option<String> = None;
+
+    let mut i = 0usize;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--plan"      => { i += 1; plan_path = Some(PathBuf::from(require_arg(args, i, "--plan")?)); }
+            "--telemetry" => { i += 1; sample_path = Some(PathBuf::from(require_arg(args, i, "--telemetry")?)); }
+            "--artifact"  => { i += 1; artifact_path = Some(PathBuf::from(require_arg(args, i, "--artifact")?)); }
+            "--target"    => { i += 1; target_path = Some(PathBuf::from(require_arg(args, i, "--target")?)); }
+            "--helper"    => { i += 1; helper_paths.push(PathBuf::from(require_arg(args, i, "--helper")?)); }
+            "--policy"    => { i += 1; policy_path = Some(PathBuf::from(require_arg(args, i, "--policy")?)); }
+            "--mode"      => { i += 1; mode_id = Some(require_arg(args, i, "--mode")?.to_string()); }
+            other => { return Err(format!("unexpected argument: {other}").into()); }
+        }
+        i += 1;
+    }
+
+    let plan_path = plan_path.ok_or("missing --plan <plan.json>")?;
+    let sample_path = sample_path.ok_or("missing --telemetry <sample.json>")?;
+    let artifact_path = artifact_path.ok_or("missing --artifact <req.json>")?;
+    let target_path = target_path.ok_or("missing --target <cap.json>")?;
+    let plan: CompatibilityPlan = serde_json::from_str(&fs::read_to_string(&plan_path)?)
+        .map_err(|e| format!("{}: {e}", plan_path.display()))?;
+    let sample: TelemetrySample = serde_json::from_str(&fs::read_to_string(&sample_path)?)
+        .map_err(|e| format!("{}: {e}", sample_path.display()))?;
+    let game: GameRequirement = read_json(&artifact_path, Strictness::Lenient)?;
+    let mut target: CapabilityGraph = read_json(&target_path, Strictness::Lenient)?;
+    let helpers: Vec<CapabilityGraph> = helper_paths.iter().map(|p| read_json(p, Strictness::Lenient)).collect::<Result<Vec<_>, _>>()?;
+    let policy: PolicyProfile = if let Some(p) = policy_path { read_json(&p, Strictness::Lenient)? } else { default_policy() };
+
+    ingest_telemetry(&mut target, &sample);
+    fs::write(&target_path, serde_json::to_string_pretty(&target)?)?;
+    let req = PlanningRequest {
+        game: &game, target: &target, helpers: &helpers, policy: &policy,
+        mode_id: mode_id.as_deref(), evidence: &[], calibration: None, cores: &[],
+    };
+    let outcome = replan_on_telemetry(&plan, req)?;
+    for v in &outcome.violations { eprintln!("assumption broken: {}", v.describe()); }
+    Ok(outcome)
 }
 
-fn read_json<T: serde::de::DeserializeOwned>(path: &PathBuf) -> Result<T, Box<dyn Error>> {
+/// `record-outcome`: count a verification run of a plan in the calibration
+/// store (created when missing) and return the updated entry
+pub fn record_outcome_command(args: &[String]) -> Result<CalibrationEntry, Box<dyn Error>> {
+    let mut plan_path: Option<PathBuf> = None;
+    let mut store_path: Option<PathBuf> = None;
+    let mut passed: Option<bool> = None;
+
+    let mut i = 0usize;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--plan"   => { i += 1; plan_path = Some(PathBuf::from(require_arg(args, i, "--plan")?)); }
+            "--store"  => { i += 1; store_path = Some(PathBuf::from(require_arg(args, i, "--store")?)); }
+            "--passed" => { passed = Some(true); }
+            "--failed" => { passed = Some(false); }
+            other => { return Err(format!("unexpected argument: {other}").into()); }
+        }
+        i += 1;
+    }
+
+    let plan_path = plan_path.ok_or("missing --plan <plan.json>")?;
+    let store_path = store_path.ok_or("missing --store <calibration.json>")?;
+    let passed = passed.ok_or("missing --passed or --failed")?;
+    let plan: CompatibilityPlan = serde_json::from_str(&fs::read_to_string(&plan_path)?)
+        .map_err(|e| format!("{}: {e}", plan_path.display()))?;
+    let mut store = CalibrationStore::load(&store_path)?;
+    store.record(&plan, passed);
+    store.save(&store_path)?;
+    let entry = store.entry(&plan.strategy, &gap_signature(&plan.gaps)).cloned().ok_or("outcome was not recorded")?;
+    Ok(entry)
+}
+
+/// `verify`: grade a checkpoint report against the plan's verification
+/// target; with `--store`, also count the outcome in the calibration store
+pub fn verify_command(args: &[String]) -> Result<VerificationOutcome, Box<dyn Error>> {
+    let mut plan_path: Option<PathBuf> = None;
+    let mut report_path: Option<PathBuf> = None;
+    let mut store_path: Option<PathBuf> = None;
+
+    let mut i = 0usize;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--plan"   => { i += 1; plan_path = Some(PathBuf::from(require_arg(args, i, "--plan")?)); }
+            "--report" => { i += 1; report_path = Some(PathBuf::from(require_arg(args, i, "--report")?)); }
+            "--store"  => { i += 1; store_path = Some(PathBuf::from(require_arg(args, i, "--store")?)); }
+            other => { return Err(format!("unexpected argument: {other}").into()); }
+        }
+        i += 1;
+    }
+
+    let plan_path = plan_path.ok_or("missing --plan <plan.json>")?;
+    let report_path = report_path.ok_or("missing --report <checkpoints.json>")?;
+    let plan: CompatibilityPlan = serde_json::from_str(&fs::read_to_string(&plan_path)?)
+        .map_err(|e| format!("{}: {e}", plan_path.display()))?;
+    let report: CheckpointReport = serde_json::from_str(&fs::read_to_string(&report_path)?)
+        .map_err(|e| format!("{}: {e}", report_path.display()))?;
+    let outcome = evaluate_verification(&plan, &report).map_err(|e| format!("{}: {e}", report_path.display()))?;
+    if let Some(store_path) = store_path {
+        let mut store = CalibrationStore::load(&store_path)?;
+        store.record(&plan, outcome.met);
+        store.save(&store_path)?;
+    }
+    Ok(outcome)
+}
+
+/// `capabilities`: list the reference graphs, print one, or write them all
+/// out as `<platform_id>.json`
+fn capabilities_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args.first().map(String::as_str) {
+        None => {
+            for g in reference_profiles() { println!("{:<22} {:<9} {}", g.platform_id, g.class, g.label); }
+            Ok(())
+        }
+        Some("--write") => {
+            let dir = PathBuf::from(require_arg(args, 1, "--write")?);
+            fs::create_dir_all(&dir)?;
+            for g in reference_profiles() {
+                let path = dir.join(format!("{}.json", g.platform_id));
+                fs::write(&path, serde_json::to_string_pretty(&g)? + "\n")?;
+                eprintln!("wrote {}", path.display());
+            }
+            Ok(())
+        }
+        Some(id) => print_json(reference_profile(id).ok_or_else(|| format!("no reference profile {id:?}"))?),
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &PathBuf, strictness: Strictness) -> Result<T, Box<dyn Error>> {
     let s = fs::read_to_string(path)?;
-    Ok(serde_json::from_str::<T>(&s)?)
+    parse_document(&s, strictness).map_err(|e| format!("{}: {e}", path.display()).into())
+}
+
+fn read_evidence(path: &PathBuf) -> Result<Evidence, Box<dyn Error>> {
+    Evidence::from_json(&path.display().to_string(), &fs::read_to_string(path)?)
 }
 
 fn require_arg<'a>(args: &'a [String], idx: usize, flag: &str) -> Result<&'a str, Box<dyn Error>> {
@@ -70,9 +270,41 @@ ucf-planner <command>
 
 Commands:
   plan --artifact <req.json> --target <cap.json> [--helper <cap.json> ...] [--policy <policy.json>] [--mode <mode_id>]
+       [--evidence <mrom.train.json | scorecard.json> ...] [--calibration <calibration.json>] [--core <core.json> ...]
+       [--strict] [--all-modes]
+
+  telemetry --plan <plan.json> --telemetry <sample.json> --artifact <req.json> --target <cap.json>
+       [--helper <cap.json> ...] [--policy <policy.json>] [--mode <mode_id>]
+
+  capabilities [<platform_id> | --write <dir>]
+
+  record-outcome --plan <plan.json> --store <calibration.json> (--passed | --failed)
+
+  verify --plan <plan.json> --report <checkpoints.json> [--store <calibration.json>]
+
+  --strict  reject unknown fields in input documents (default: ignore them)
+  --all-modes  plan every declared fidelity mode and recommend one (instead of --mode)
+  --calibration  blend confidence with past verification outcomes of the same strategy and gaps
+  --core    an emulator core available on the target (ECoreInfo plus platforms and accuracy); plans
+            that load a core pick the best-scoring one and list the others as alternatives; --evidence
+            run on any other core is ignored
+  record-outcome counts a verified plan as passed or failed in the calibration store (created if missing)
+  verify    grades an mrom.checkpoints.v1 report against the plan's verification target (exit 1 when
+            the achieved equivalence level is below it); --store records the outcome as record-outcome does
+  telemetry folds the sample into the target graph's profiles (file rewritten) and
+            re-plans when achieved fps / RTT / dropped frames break the plan's assumptions
+  capabilities lists the built-in reference capability graphs (DMG, CGB, PCs, phone, Pi),
+            prints one, or writes them all as <platform_id>.json
 
 Examples:
   ucf-planner plan --artifact game_req.json --target ps2_cap.json --helper pc_cap.json
   ucf-planner plan --artifact game_req.json --target win11_cap.json --mode baseline
+  ucf-planner plan --artifact game_req.json --target win11_cap.json --all-modes
+  ucf-planner plan --artifact tetris_req.json --target pc_cap.json --evidence tetris.mrom.train.json
+  ucf-planner plan --artifact tetris_req.json --target pc_cap.json --core gb_core.json --core other_gb.json
+  ucf-planner capabilities raspberry_pi_4 > pi4_cap.json
+  ucf-planner record-outcome --plan plan.json --store calibration.json --passed
+  ucf-planner verify --plan plan.json --report output/<rom_hash>/checkpoints/checkpoints.json --store calibration.json
+  ucf-planner telemetry --plan plan.json --telemetry run.json --artifact game_req.json --target pc_cap.json
 ");
 }
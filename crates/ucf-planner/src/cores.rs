@@ -0,0 +1,141 @@
+//! cores.rs — emulator core selection for plans that load a core
+//!
+//! Several emulator cores may be able to run one artifact (e.g. different
+//! Game Boy cores behind the ecore ABI). Each `CoreProfile` — the core's
+//! `ECoreInfo` plus what it emulates and how accurately — gets a suitability
+//! score against the `GameRequirement` and the plan's equivalence target;
+//! the best eligible core is embedded in the plan, with the others ranked as
+//! alternatives. Gap analysis and strategy scoring are untouched: the choice
+//! of core only matters once a strategy has decided to emulate.
+
+use crate::model::{CompatibilityPlan, EquivalenceLevel, GameRequirement};
+use serde::{Deserialize, Serialize};
+
+/// Step whose presence in a pipeline means the plan loads an emulator core
+pub const CORE_STEP: &str = "load_emulator_core";
+
+/// An emulator core the host can load. Essential: `core_id`, `abi_version`,
+/// `platforms`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoreProfile {
+    /// `ECoreInfo::core_id`
+    pub core_id: String,
+    /// Default: ""
+    #[serde(default)]
+    pub label: String,
+    /// `ECoreInfo::abi_version`
+    pub abi_version: u32,
+    /// Default: []
+    #[serde(default)]
+    pub mime_types: Vec<String>,
+    /// Default: 0 (no save states)
+    #[serde(default)]
+    pub save_state_version: u32,
+    /// Original platforms emulated, as in `GameRequirement::targets_original`
+    pub platforms: Vec<String>,
+    /// Default: [] (CPU ISAs emulated)
+    #[serde(default)]
+    pub isas: Vec<String>,
+    /// Default: nothing measured
+    #[serde(default)]
+    pub accuracy: CoreAccuracy,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoreAccuracy {
+    /// Test-ROM suite pass rate in 0.0..=1.0 (`mrom.testsuite.v1`, scorecards)
+    pub test_pass_rate: Option<f32>,
+    /// Strongest equivalence level runs on this core have been verified at
+    pub verified_equivalence: Option<EquivalenceLevel>,
+    /// Same inputs give the same frames, run after run
+    pub deterministic: bool,
+}
+
+/// One core's suitability for the artifact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoreScore {
+    pub core_id: String,
+    /// 0-100; meaningless when not `eligible`
+    pub score: u8,
+    /// The core emulates one of the artifact's original platforms, or at
+    /// least its CPU ISA
+    pub eligible: bool,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoreSelection {
+    /// Highest-scoring eligible core (None when no supplied core is eligible)
+    pub chosen: Option<String>,
+    /// Every supplied core, eligible ones first, each group by score
+    pub candidates: Vec<CoreScore>,
+}
+
+impl CoreSelection {
+    /// Eligible cores other than the chosen one, best first
+    pub fn alternatives(&self) -> impl Iterator<Item = &CoreScore> {
+        self.candidates.iter().filter(|c| c.eligible).skip(1)
+    }
+}
+
+/// Platform match 40 (ISA-only match 20), accuracy up to 30 (unknown 10),
+/// verified equivalence 20 (unknown 5), determinism 5, save states 5
+pub fn score_core(core: &CoreProfile, game: &GameRequirement, equivalence_min: &EquivalenceLevel) -> CoreScore {
+    let mut reasons = vec![];
+    let mut score = 0u32;
+    let platform = game.targets_original.iter().find(|p| core.platforms.iter().any(|c| c.eq_ignore_ascii_case(p)));
+    let isa = game.cpu.required_isa.iter().find(|i| core.isas.iter().any(|c| c.eq_ignore_ascii_case(i)));
+    let eligible = match (platform, isa) {
+        (Some(p), _) => { score += 40; reasons.push(format!("emulates {p}")); true }
+        (None, Some(i)) => { score += 20; reasons.push(format!("emulates the {i} CPU, not the original platform")); true }
+        (None, None) => { reasons.push("emulates none of the artifact's platforms or ISAs".into()); false }
+    };
+    match core.accuracy.test_pass_rate {
+        Some(rate) => {
+            let rate = rate.clamp(0.0, 1.0);
+            score += (rate * 30.0).round() as u32;
+            reasons.push(format!("test suite {:.0}% passed", rate * 100.0));
+        }
+        None => { score += 10; reasons.push("test suite pass rate unknown".into()); }
+    }
+    match &core.accuracy.verified_equivalence {
+        Some(level) if level >= equivalence_min => { score += 20; reasons.push(format!("verified at {level:?}")); }
+        Some(level) => reasons.push(format!("verified only at {level:?}, plan needs {equivalence_min:?}")),
+        None => { score += 5; reasons.push("equivalence unverified".into()); }
+    }
+    if core.accuracy.deterministic { score += 5; reasons.push("deterministic".into()); }
+    if core.save_state_version > 0 { score += 5; reasons.push(format!("save states v{}", core.save_state_version)); }
+    CoreScore { core_id: core.core_id.clone(), score: score.min(100) as u8, eligible, reasons }
+}
+
+/// Rank `cores` for the artifact; None when no cores were supplied
+pub fn select_core(cores: &[CoreProfile], game: &GameRequirement, equivalence_min: &EquivalenceLevel) -> Option<CoreSelection> {
+    if cores.is_empty() { return None; }
+    let mut candidates: Vec<CoreScore> = cores.iter().map(|c| score_core(c, game, equivalence_min)).collect();
+    candidates.sort_by(|a, b| b.eligible.cmp(&a.eligible).then(b.score.cmp(&a.score)).then(a.core_id.cmp(&b.core_id)));
+    let chosen = candidates.first().filter(|c| c.eligible).map(|c| c.core_id.clone());
+    Some(CoreSelection { chosen, candidates })
+}
+
+/// The core `select_core` picks for the artifact in `mode_id`, if any
+pub fn chosen_core(cores: &[CoreProfile], game: &GameRequirement, mode_id: Option<&str>) -> Option<String> {
+    select_core(cores, game, &crate::plan::resolve_equivalence_level(game, mode_id)).and_then(|s| s.chosen)
+}
+
+/// Select a core for `plan` when its pipeline loads one, with a `Core:`
+/// rationale line
+pub fn apply_core_selection(plan: &mut CompatibilityPlan, cores: &[CoreProfile], game: &GameRequirement) {
+    if !plan.strategy_pipeline.iter().any(|s| s.id == CORE_STEP) { return; }
+    let Some(selection) = select_core(cores, game, &plan.verification_target.equivalence_min) else { return };
+    let line = match (&selection.chosen, selection.candidates.first()) {
+        (Some(id), Some(best)) => {
+            let others: Vec<String> = selection.alternatives().map(|c| format!("{} ({})", c.core_id, c.score)).collect();
+            let over = if others.is_empty() { String::new() } else { format!(" over {}", others.join(", ")) };
+            format!("Core: {id} (score {}){over}", best.score)
+        }
+        _ => format!("Core: none of the {} supplied core(s) emulates the artifact's platform or ISA", cores.len()),
+    };
+    plan.rationale.push(line);
+    plan.core_selection = Some(selection);
+}
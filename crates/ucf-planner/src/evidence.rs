@@ -0,0 +1,161 @@
+//! evidence.rs — emulator run evidence as a planning input
+//!
+//! Training files (`mrom.train.v1`) and accuracy scorecards produced by the
+//! MetaROM cores are proof that an artifact actually boots and runs on a core.
+//! When such evidence is supplied for the planned artifact, Emulate-family
+//! strategies get a fidelity adjustment and a confidence boost; gap analysis
+//! itself is untouched (evidence is about the candidate, not the gap).
+
+use crate::model::{GameRequirement, PlanScores};
+use crate::strategy::{total_score, ScoreWeights, Strategy};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// Core assumed for evidence files that do not name one
+pub const DEFAULT_EVIDENCE_CORE: &str = "gb-core";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvidenceKind { Training, Scorecard }
+
+/// One evidence file, reduced to what the planner needs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Evidence {
+    pub kind: EvidenceKind,
+    pub source: String,
+    /// Explicit artifact binding, if the file carries one
+    pub artifact_id: Option<String>,
+    pub rom_title: Option<String>,
+    pub rom_sha: Option<String>,
+    pub core: String,
+    pub frames_run: u64,
+    /// The ROM reached a visible picture (LCD enabled) during the run
+    pub booted: bool,
+    /// Scorecard pass rate in 0.0..=1.0
+    pub pass_rate: Option<f32>,
+}
+
+impl Evidence {
+    /// Parse an `mrom.train.v1` or accuracy scorecard JSON document
+    pub fn from_json(source: &str, text: &str) -> Result<Evidence, Box<dyn Error>> {
+        let v: serde_json::Value = serde_json::from_str(text)?;
+        let version = v.get("version").and_then(|x| x.as_str()).unwrap_or("");
+        let s = |k: &str| v.get(k).and_then(|x| x.as_str()).map(str::to_string);
+        let core = s("core").unwrap_or_else(|| DEFAULT_EVIDENCE_CORE.into());
+        if version.starts_with("mrom.train.") {
+            let frames = v.get("frames").and_then(|f| f.as_array()).map(Vec::as_slice).unwrap_or(&[]);
+            let frames_run = v.get("total_frames").and_then(|x| x.as_u64()).unwrap_or(frames.len() as u64);
+            let booted = frames.iter().any(|f| f.get("lcdc").and_then(|x| x.as_u64()).unwrap_or(0) & 0x80 != 0);
+            Ok(Evidence {
+                kind: EvidenceKind::Training, source: source.into(), artifact_id: s("artifact_id"),
+                rom_title: s("rom_title"), rom_sha: s("rom_sha"), core, frames_run, booted, pass_rate: None,
+            })
+        } else if version.starts_with("mrom.scorecard.") {
+            let passed = v.get("passed").and_then(|x| x.as_u64()).unwrap_or(0);
+            let total = v.get("total").and_then(|x| x.as_u64()).unwrap_or(0);
+            let pass_rate = v.get("pass_rate").and_then(|x| x.as_f64()).map(|p| p as f32)
+                .or_else(|| (total > 0).then(|| passed as f32 / total as f32));
+            Ok(Evidence {
+                kind: EvidenceKind::Scorecard, source: source.into(), artifact_id: s("artifact_id"),
+                rom_title: s("rom_title"), rom_sha: s("rom_sha"), core,
+                frames_run: v.get("frames").and_then(|x| x.as_u64()).unwrap_or(0),
+                booted: passed > 0, pass_rate,
+            })
+        } else {
+            Err(format!("{source}: unsupported evidence version {version:?}").into())
+        }
+    }
+
+    /// Evidence applies only when its `artifact_id` or `rom_sha` matches the
+    /// artifact's and, once a core has been chosen, it was produced on that core
+    pub fn applies_to(&self, game: &GameRequirement, core: Option<&str>) -> bool {
+        self.rejection(game, core).is_none()
+    }
+
+    /// Why this evidence does not apply, or None when it does
+    pub fn rejection(&self, game: &GameRequirement, core: Option<&str>) -> Option<String> {
+        let same = |a: Option<&str>, b: Option<&str>| a.zip(b).is_some_and(|(a, b)| a.eq_ignore_ascii_case(b));
+        if self.is_unbound() { return Some("names no artifact_id or rom_sha".into()); }
+        if !same(self.artifact_id.as_deref(), Some(&game.artifact_id)) && !same(self.rom_sha.as_deref(), game.rom_sha.as_deref()) {
+            return Some(format!("is for artifact {:?} / rom {:?}",
+                self.artifact_id.as_deref().unwrap_or(""), self.rom_sha.as_deref().unwrap_or("")));
+        }
+        match core {
+            Some(core) if !self.core.eq_ignore_ascii_case(core) => Some(format!("was run on core {:?}, plan uses {core:?}", self.core)),
+            _ => None,
+        }
+    }
+
+    /// Carries neither an `artifact_id` nor a `rom_sha`, so it cannot be bound to anything
+    pub fn is_unbound(&self) -> bool { self.artifact_id.is_none() && self.rom_sha.is_none() }
+}
+
+/// Aggregate of all evidence that applies to one artifact
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvidenceSummary {
+    pub runs: usize,
+    pub cores: Vec<String>,
+    pub total_frames: u64,
+    pub booted: bool,
+    pub best_pass_rate: Option<f32>,
+}
+
+impl EvidenceSummary {
+    /// `core` is the core chosen for the artifact (`chosen_core`), if any
+    pub fn collect(evidence: &[Evidence], game: &GameRequirement, core: Option<&str>) -> EvidenceSummary {
+        let mut s = EvidenceSummary::default();
+        for e in evidence.iter().filter(|e| e.applies_to(game, core)) {
+            s.runs += 1;
+            if !s.cores.contains(&e.core) { s.cores.push(e.core.clone()); }
+            s.total_frames += e.frames_run;
+            s.booted |= e.booted;
+            if let Some(p) = e.pass_rate {
+                s.best_pass_rate = Some(s.best_pass_rate.map_or(p, |b| b.max(p)));
+            }
+        }
+        s
+    }
+
+    pub fn is_empty(&self) -> bool { self.runs == 0 }
+
+    /// Fidelity points added to (or removed from) Emulate-family strategies
+    pub fn fidelity_delta(&self) -> i32 {
+        let mut d = 0;
+        if self.booted { d += 5; }
+        if self.booted && self.total_frames >= 600 { d += 3; }
+        if let Some(p) = self.best_pass_rate { d += ((p - 0.5) * 20.0).round() as i32; }
+        d.clamp(-10, 15)
+    }
+
+    pub fn confidence_boost(&self) -> f32 {
+        let mut b = 0.0;
+        if self.booted { b += 0.10; }
+        if let Some(p) = self.best_pass_rate { b += 0.15 * p; }
+        b
+    }
+}
+
+fn is_emulate_family(strategy: Strategy) -> bool {
+    matches!(strategy, Strategy::Emulate | Strategy::EmulatePlusTranslate)
+}
+
+/// Adjust one strategy's scores by the evidence (no-op for non-emulation strategies)
+pub fn apply_evidence_scores(strategy: Strategy, scores: PlanScores, evidence: &EvidenceSummary, weights: ScoreWeights) -> PlanScores {
+    if evidence.is_empty() || !is_emulate_family(strategy) { return scores; }
+    let mut s = scores;
+    s.fidelity = (s.fidelity as i32 + evidence.fidelity_delta()).clamp(0, 100) as u8;
+    if evidence.booted { s.determinism = (s.determinism as i32 + 5).clamp(0, 100) as u8; }
+    s.total = total_score(&s, weights);
+    s
+}
+
+/// Confidence after evidence, plus the rationale line explaining it
+pub fn apply_evidence_confidence(strategy: Strategy, confidence: f32, evidence: &EvidenceSummary) -> (f32, Option<String>) {
+    if evidence.is_empty() || !is_emulate_family(strategy) { return (confidence, None); }
+    let pass = evidence.best_pass_rate.map(|p| format!(", best pass rate {:.0}%", p * 100.0)).unwrap_or_default();
+    let note = format!(
+        "Evidence: {} run(s) on {} ({} frames, booted={}{}); fidelity {:+}, confidence +{:.2}",
+        evidence.runs, evidence.cores.join(","), evidence.total_frames, evidence.booted, pass,
+        evidence.fidelity_delta(), evidence.confidence_boost()
+    );
+    ((confidence + evidence.confidence_boost()).clamp(0.05, 0.99), Some(note))
+}
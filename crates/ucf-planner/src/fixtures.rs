@@ -0,0 +1,224 @@
+//! fixtures.rs — reference capability graphs and a constructor API for them
+//!
+//! `CapabilityGraph::new()` starts from the essentials (platform id, OS
+//! family, ISAs, RAM) with every other field at its documented default, and
+//! the `with_*` methods fill in the rest. The reference profiles below are
+//! built that way; `examples/capabilities/<platform_id>.json` holds the same
+//! graphs as documents (`ucf-planner capabilities --write <dir>` regenerates
+//! them). Figures are typical retail configurations, not measurements.
+
+use crate::model::{
+    CapabilityGraph, CpuCapability, GpuCapability, HostOs, IoCapability, LegalCapability, MemoryCapability,
+    NetworkCapability, ProfilesMeta, SecurityCapability, StorageCapability, TimingCapability,
+};
+
+/// `profiles.source` of the reference graphs
+pub const REFERENCE_SOURCE: &str = "reference_fixture";
+
+/// Platform ids of the reference graphs, in `reference_profiles()` order
+pub const REFERENCE_PLATFORM_IDS: [&str; 7] =
+    ["gb_dmg", "gb_cgb", "pc_windows_x64", "pc_linux_x64", "mac_arm64", "android_phone_arm64", "raspberry_pi_4"];
+
+fn strings(items: &[&str]) -> Vec<String> { items.iter().map(|s| s.to_string()).collect() }
+
+impl CapabilityGraph {
+    /// A graph with only the essential fields set
+    pub fn new(platform_id: &str, os_family: &str, isas: &[&str], ram_mb: u32) -> Self {
+        CapabilityGraph {
+            capability_version: "0.1".into(),
+            platform_id: platform_id.into(),
+            label: String::new(),
+            class: "other".into(),
+            host_os: HostOs { family: os_family.into(), version: String::new(), abi: vec![], syscalls: vec![] },
+            cpu: CpuCapability {
+                isas: strings(isas), cores: 1, threads: 1, clock_mhz: 0.0, simd: vec![],
+                features: serde_json::Value::Object(Default::default()),
+            },
+            gpu: GpuCapability::default(),
+            memory: MemoryCapability { ram_mb, bandwidth_gbps: 0.0, storage: StorageCapability::default() },
+            io: IoCapability::default(),
+            timing: TimingCapability::default(),
+            security: SecurityCapability::default(),
+            legal: LegalCapability::default(),
+            profiles: ProfilesMeta::default(),
+        }
+    }
+
+    /// `class` is one of the schema's: pc, console, handheld, mobile, server, embedded, other
+    pub fn with_label(mut self, label: &str, class: &str) -> Self {
+        self.label = label.into();
+        self.class = class.into();
+        self
+    }
+    pub fn with_os_version(mut self, version: &str, abi: &[&str]) -> Self {
+        self.host_os.version = version.into();
+        self.host_os.abi = strings(abi);
+        self
+    }
+    pub fn with_cpu(mut self, cores: u32, threads: u32, clock_mhz: f64, simd: &[&str]) -> Self {
+        self.cpu.cores = cores.max(1);
+        self.cpu.threads = threads.max(1);
+        self.cpu.clock_mhz = clock_mhz;
+        self.cpu.simd = strings(simd);
+        self
+    }
+    pub fn with_gpu(mut self, apis: &[&str], shader_models: &[&str], vram_mb: u32) -> Self {
+        self.gpu.apis = strings(apis);
+        self.gpu.shader_models = strings(shader_models);
+        self.gpu.vram_mb = vram_mb;
+        self
+    }
+    pub fn with_memory(mut self, bandwidth_gbps: f64, storage: StorageCapability) -> Self {
+        self.memory.bandwidth_gbps = bandwidth_gbps;
+        self.memory.storage = storage;
+        self
+    }
+    pub fn with_io(mut self, inputs: &[&str], video_out: &[&str], audio_out: bool) -> Self {
+        self.io.inputs = strings(inputs);
+        self.io.video_out = strings(video_out);
+        self.io.audio_out = audio_out;
+        self
+    }
+    /// An available network link
+    pub fn with_network(mut self, bandwidth_mbps: f64, rtt_ms: f64, jitter_ms: f64) -> Self {
+        self.io.network = NetworkCapability {
+            available: true, bandwidth_mbps: Some(bandwidth_mbps), rtt_ms: Some(rtt_ms), jitter_ms: Some(jitter_ms),
+        };
+        self
+    }
+    pub fn with_timing(mut self, display_modes_hz: &[f64], timer_resolution_us: u32, interrupt_model: &str) -> Self {
+        self.timing = TimingCapability {
+            display_modes_hz: display_modes_hz.to_vec(),
+            timer_resolution_us: timer_resolution_us.max(1),
+            interrupt_model: interrupt_model.into(),
+        };
+        self
+    }
+    /// `external_coprocessor_support` is "yes", "no" or "unknown"
+    pub fn with_security(mut self, unsigned_code_allowed: bool, external_coprocessor_support: &str) -> Self {
+        self.security = SecurityCapability {
+            unsigned_code_allowed, external_coprocessor_support: external_coprocessor_support.into(),
+        };
+        self
+    }
+    pub fn with_firmware(mut self, required: bool, redistributable: bool) -> Self {
+        self.legal = LegalCapability { firmware_required: required, redistributable_firmware: redistributable };
+        self
+    }
+    pub fn with_source(mut self, source: &str, measured: bool) -> Self {
+        self.profiles.source = source.into();
+        self.profiles.measured = measured;
+        self
+    }
+}
+
+// ── Reference profiles ────────────────────────────────────────────────────────
+
+/// Original Game Boy: SM83 at 4.19 MHz, 8 KiB WRAM (`ram_mb` rounds to 0)
+pub fn gb_dmg() -> CapabilityGraph {
+    CapabilityGraph::new("gb_dmg", "gb_bare_metal", &["sm83"], 0)
+        .with_label("Nintendo Game Boy (DMG-01)", "handheld")
+        .with_cpu(1, 1, 4.194304, &[])
+        .with_gpu(&["dmg_ppu"], &[], 0)
+        .with_io(&["gb_joypad"], &["lcd_160x144_2bpp"], true)
+        .with_timing(&[59.7275], 4, "vectored_ime")
+        .with_security(true, "no")
+        .with_source(REFERENCE_SOURCE, false)
+}
+
+/// Game Boy Color: as the DMG plus double speed, 32 KiB WRAM and 15-bit colour
+pub fn gb_cgb() -> CapabilityGraph {
+    CapabilityGraph::new("gb_cgb", "gb_bare_metal", &["sm83"], 0)
+        .with_label("Nintendo Game Boy Color (CGB-001)", "handheld")
+        .with_cpu(1, 1, 8.388608, &[])
+        .with_gpu(&["dmg_ppu", "cgb_ppu"], &[], 0)
+        .with_io(&["gb_joypad", "ir_port"], &["lcd_160x144_15bit"], true)
+        .with_timing(&[59.7275], 2, "vectored_ime")
+        .with_security(true, "no")
+        .with_source(REFERENCE_SOURCE, false)
+}
+
+/// Mid-range Windows 11 gaming desktop
+pub fn pc_windows_x64() -> CapabilityGraph {
+    CapabilityGraph::new("pc_windows_x64", "windows", &["x86_64"], 16384)
+        .with_label("Windows 11 desktop (8C/16T, 8 GB GPU)", "pc")
+        .with_os_version("11", &["win64"])
+        .with_cpu(8, 16, 3600.0, &["sse4_2", "avx2"])
+        .with_gpu(&["d3d12", "d3d11", "vulkan", "opengl"], &["sm_6_6"], 8192)
+        .with_memory(51.2, StorageCapability { internal_mb: 1_024_000, streaming_read_mbps: 3500.0, seek_latency_ms: 0.1 })
+        .with_io(&["keyboard", "mouse", "xinput"], &["hdmi", "displayport"], true)
+        .with_network(1000.0, 20.0, 2.0)
+        .with_timing(&[60.0, 144.0], 500, "preemptive_os")
+        .with_security(true, "yes")
+        .with_source(REFERENCE_SOURCE, false)
+}
+
+/// Mid-range Linux desktop, same hardware as `pc_windows_x64`
+pub fn pc_linux_x64() -> CapabilityGraph {
+    CapabilityGraph::new("pc_linux_x64", "linux", &["x86_64"], 16384)
+        .with_label("Linux desktop (8C/16T, 8 GB GPU)", "pc")
+        .with_os_version("6.8", &["elf64", "glibc"])
+        .with_cpu(8, 16, 3600.0, &["sse4_2", "avx2"])
+        .with_gpu(&["vulkan", "opengl"], &["spirv_1_6"], 8192)
+        .with_memory(51.2, StorageCapability { internal_mb: 1_024_000, streaming_read_mbps: 3500.0, seek_latency_ms: 0.1 })
+        .with_io(&["keyboard", "mouse", "evdev_gamepad"], &["hdmi", "displayport"], true)
+        .with_network(1000.0, 20.0, 2.0)
+        .with_timing(&[60.0, 144.0], 1, "preemptive_os")
+        .with_security(true, "yes")
+        .with_source(REFERENCE_SOURCE, false)
+}
+
+/// Apple silicon laptop; the GPU shares the 16 GB of unified memory
+pub fn mac_arm64() -> CapabilityGraph {
+    CapabilityGraph::new("mac_arm64", "macos", &["arm64"], 16384)
+        .with_label("MacBook Air (Apple M2)", "pc")
+        .with_os_version("14", &["macho64"])
+        .with_cpu(8, 8, 3490.0, &["neon"])
+        .with_gpu(&["metal"], &["msl_3_1"], 0)
+        .with_memory(100.0, StorageCapability { internal_mb: 512_000, streaming_read_mbps: 2900.0, seek_latency_ms: 0.1 })
+        .with_io(&["keyboard", "trackpad", "mfi_gamepad"], &["builtin_lcd", "displayport"], true)
+        .with_network(400.0, 25.0, 4.0)
+        .with_timing(&[60.0], 1, "preemptive_os")
+        .with_security(true, "yes")
+        .with_source(REFERENCE_SOURCE, false)
+}
+
+/// Mid-range Android phone on Wi-Fi
+pub fn android_phone_arm64() -> CapabilityGraph {
+    CapabilityGraph::new("android_phone_arm64", "android", &["arm64"], 8192)
+        .with_label("Android 14 phone (Snapdragon 7-class)", "mobile")
+        .with_os_version("14", &["bionic", "elf64"])
+        .with_cpu(8, 8, 2400.0, &["neon"])
+        .with_gpu(&["vulkan", "opengl_es"], &["spirv_1_3"], 0)
+        .with_memory(25.6, StorageCapability { internal_mb: 128_000, streaming_read_mbps: 1500.0, seek_latency_ms: 0.2 })
+        .with_io(&["touch", "bluetooth_gamepad"], &["builtin_oled"], true)
+        .with_network(150.0, 40.0, 10.0)
+        .with_timing(&[60.0, 120.0], 1000, "preemptive_os")
+        .with_security(true, "unknown")
+        .with_source(REFERENCE_SOURCE, false)
+}
+
+/// Raspberry Pi 4 Model B (4 GB) booting from SD card
+pub fn raspberry_pi_4() -> CapabilityGraph {
+    CapabilityGraph::new("raspberry_pi_4", "linux", &["arm64"], 4096)
+        .with_label("Raspberry Pi 4 Model B (4 GB)", "embedded")
+        .with_os_version("6.6", &["elf64", "glibc"])
+        .with_cpu(4, 4, 1800.0, &["neon"])
+        .with_gpu(&["opengl_es", "vulkan"], &[], 76)
+        .with_memory(4.0, StorageCapability { internal_mb: 32_000, streaming_read_mbps: 45.0, seek_latency_ms: 1.0 })
+        .with_io(&["usb_hid", "usb_gamepad", "gpio"], &["hdmi"], true)
+        .with_network(1000.0, 20.0, 3.0)
+        .with_timing(&[60.0], 1, "preemptive_os")
+        .with_security(true, "yes")
+        .with_source(REFERENCE_SOURCE, false)
+}
+
+/// Every reference graph, in `REFERENCE_PLATFORM_IDS` order
+pub fn reference_profiles() -> Vec<CapabilityGraph> {
+    vec![gb_dmg(), gb_cgb(), pc_windows_x64(), pc_linux_x64(), mac_arm64(), android_phone_arm64(), raspberry_pi_4()]
+}
+
+/// The reference graph for `platform_id`
+pub fn reference_profile(platform_id: &str) -> Option<CapabilityGraph> {
+    reference_profiles().into_iter().find(|g| g.platform_id == platform_id)
+}
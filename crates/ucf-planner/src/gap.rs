@@ -117,7 +117,8 @@ fn analyze_cpu_gap(game: &GameRequirement, target: &CapabilityGraph) -> GapStatu
 }
 
 fn analyze_gpu_gap(game: &GameRequirement, target: &CapabilityGraph) -> GapStatus {
-    let api_match = game.gpu.required_apis.iter().any(|req_api| {
+    // An empty requirement list (the authoring default) means "any API"
+    let api_match = game.gpu.required_apis.is_empty() || game.gpu.required_apis.iter().any(|req_api| {
         target.gpu.apis.iter().any(|api| api.eq_ignore_ascii_case(req_api))
     });
     let shader_model_ok = match &game.gpu.shader_model {
@@ -184,6 +185,23 @@ fn analyze_io_gap(game: &GameRequirement, target: &CapabilityGraph) -> GapStatus
 }
 
 fn analyze_timing_gap(game: &GameRequirement, target: &CapabilityGraph) -> GapStatus {
+    let mut status = analyze_declared_timing(game, target);
+    // Runtime telemetry (telemetry.rs) adds what the graph's declared timing cannot show
+    if let Some(observed) = &target.profiles.observed {
+        if let (Some(target_fps), Some(fps)) = (game.timing.target_fps, observed.achieved_fps) {
+            if fps < target_fps * (1.0 - crate::telemetry::FPS_TOLERANCE) {
+                status.push_reason(GapReason::with_detail("MEASURED_FPS_BELOW_TARGET", format!("{fps:.1} < {target_fps:.1}")));
+            }
+        }
+        if observed.dropped_frame_ratio() > crate::telemetry::MAX_DROPPED_FRAME_RATIO {
+            status.push_reason(GapReason::with_detail("MEASURED_FRAME_DROPS",
+                format!("{:.1}% of {} frames", observed.dropped_frame_ratio() * 100.0, observed.frames)));
+        }
+    }
+    status
+}
+
+fn analyze_declared_timing(game: &GameRequirement, target: &CapabilityGraph) -> GapStatus {
     if game.timing.frame_pacing_sensitive.unwrap_or(false) && target.timing.timer_resolution_us > 5_000 {
         return GapStatus::soft(GapReason::new("TIMER_RESOLUTION_COARSE"));
     }
@@ -1,12 +1,36 @@
+pub mod authoring;
+pub mod blockers;
+pub mod calibration;
+pub mod cores;
+pub mod evidence;
+pub mod fixtures;
 pub mod gap;
 pub mod model;
+pub mod modes;
+pub mod pipeline;
 pub mod plan;
 pub mod planner;
 pub mod ranked;
+pub mod risks;
 pub mod strategy;
+pub mod telemetry;
+pub mod verification;
+pub mod version;
 pub mod cli;
 
+pub use crate::authoring::*;
+pub use crate::blockers::*;
+pub use crate::calibration::*;
+pub use crate::cores::*;
+pub use crate::evidence::*;
+pub use crate::fixtures::*;
 pub use crate::gap::*;
+pub use crate::modes::*;
+pub use crate::pipeline::*;
 pub use crate::plan::*;
 pub use crate::ranked::*;
+pub use crate::risks::*;
 pub use crate::strategy::*;
+pub use crate::telemetry::*;
+pub use crate::verification::*;
+pub use crate::version::*;
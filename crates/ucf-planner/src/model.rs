@@ -2,98 +2,206 @@
 //! 
 //! TODO: expand from the full UCF v0.1 spec.
 //! All types must be kept in sync with the JSON schemas in /schemas/.
+//!
+//! Hand-authored documents only need the essential fields; everything else
+//! falls back to the default documented on the field (see `authoring.rs` for
+//! opt-in rejection of unknown fields).
 
+use crate::pipeline::PipelineStep;
 use serde::{Deserialize, Serialize};
 
 // ── Re-exported from schemas ──────────────────────────────────────────────────
 
+/// Essential: `platform_id`, `host_os.family`, `cpu.isas`, `memory.ram_mb`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CapabilityGraph {
+    /// Default: "0.1"
+    #[serde(default = "default_doc_version")]
     pub capability_version: String,
     pub platform_id: String,
+    /// Default: ""
+    #[serde(default)]
     pub label: String,
+    /// Default: "other"
+    #[serde(default = "default_other")]
     pub class: String,
     pub host_os: HostOs,
     pub cpu: CpuCapability,
+    /// Default: no graphics APIs, 0 MB VRAM
+    #[serde(default)]
     pub gpu: GpuCapability,
     pub memory: MemoryCapability,
+    /// Default: no inputs, audio out, no network
+    #[serde(default)]
     pub io: IoCapability,
+    /// Default: 60 Hz display, 1000 µs timer, "unknown" interrupt model
+    #[serde(default)]
     pub timing: TimingCapability,
+    /// Default: unsigned code not allowed, coprocessor support "unknown"
+    #[serde(default)]
     pub security: SecurityCapability,
+    /// Default: no firmware required
+    #[serde(default)]
     pub legal: LegalCapability,
+    /// Default: unmeasured, source "hand_authored"
+    #[serde(default)]
     pub profiles: ProfilesMeta,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HostOs { pub family: String, pub version: String, pub abi: Vec<String>, pub syscalls: Vec<String> }
+pub struct HostOs {
+    pub family: String,
+    #[serde(default)] pub version: String,
+    #[serde(default)] pub abi: Vec<String>,
+    #[serde(default)] pub syscalls: Vec<String>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CpuCapability { pub isas: Vec<String>, pub cores: u32, pub threads: u32, pub clock_mhz: f64, pub simd: Vec<String>, pub features: serde_json::Value }
+pub struct CpuCapability {
+    pub isas: Vec<String>,
+    /// Default: 1
+    #[serde(default = "default_one")] pub cores: u32,
+    /// Default: 1
+    #[serde(default = "default_one")] pub threads: u32,
+    #[serde(default)] pub clock_mhz: f64,
+    #[serde(default)] pub simd: Vec<String>,
+    /// Default: {}
+    #[serde(default = "empty_object")] pub features: serde_json::Value,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GpuCapability { pub apis: Vec<String>, pub shader_models: Vec<String>, pub features: serde_json::Value, pub vram_mb: u32, pub throughput_hint: serde_json::Value }
+impl Default for GpuCapability {
+    fn default() -> Self {
+        Self { apis: vec![], shader_models: vec![], features: empty_object(), vram_mb: 0, throughput_hint: empty_object() }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MemoryCapability { pub ram_mb: u32, pub bandwidth_gbps: f64, pub storage: StorageCapability }
+pub struct MemoryCapability {
+    pub ram_mb: u32,
+    #[serde(default)] pub bandwidth_gbps: f64,
+    /// Default: 0 MB, 0 MB/s streaming, 0 ms seek
+    #[serde(default)] pub storage: StorageCapability,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StorageCapability { pub internal_mb: u32, pub streaming_read_mbps: f64, pub seek_latency_ms: f64 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct IoCapability { pub inputs: Vec<String>, pub audio_out: bool, pub video_out: Vec<String>, pub network: NetworkCapability }
+impl Default for IoCapability {
+    fn default() -> Self { Self { inputs: vec![], audio_out: true, video_out: vec![], network: NetworkCapability::default() } }
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NetworkCapability { pub available: bool, pub bandwidth_mbps: Option<f64>, pub rtt_ms: Option<f64>, pub jitter_ms: Option<f64> }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TimingCapability { pub display_modes_hz: Vec<f64>, pub timer_resolution_us: u32, pub interrupt_model: String }
+impl Default for TimingCapability {
+    fn default() -> Self { Self { display_modes_hz: vec![60.0], timer_resolution_us: 1000, interrupt_model: "unknown".into() } }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SecurityCapability { pub unsigned_code_allowed: bool, pub external_coprocessor_support: String }
+impl Default for SecurityCapability {
+    fn default() -> Self { Self { unsigned_code_allowed: false, external_coprocessor_support: "unknown".into() } }
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LegalCapability { pub firmware_required: bool, pub redistributable_firmware: bool }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProfilesMeta { pub measured: bool, pub source: String }
+#[serde(default)]
+pub struct ProfilesMeta {
+    pub measured: bool,
+    pub source: String,
+    /// Runtime observations folded in by `telemetry::ingest_telemetry()`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observed: Option<crate::telemetry::ObservedProfile>,
+}
+impl Default for ProfilesMeta {
+    fn default() -> Self { Self { measured: false, source: "hand_authored".into(), observed: None } }
+}
 
 // ── Game requirement ──────────────────────────────────────────────────────────
 
+/// Essential: `artifact_id`, `cpu.required_isa`, `runtime.os_families`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameRequirement {
+    /// Default: "0.1"
+    #[serde(default = "default_doc_version")]
     pub requirement_version: String,
     pub artifact_id: String,
+    /// SHA-256 of the ROM image, hex (`rom_sha` in training files and scorecards); binds evidence that carries no `artifact_id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rom_sha: Option<String>,
+    /// Default: "other"
+    #[serde(default = "default_other")]
     pub kind: String,
+    /// Default: "unknown"
+    #[serde(default = "default_unknown")]
     pub source_type: String,
+    #[serde(default)]
     pub targets_original: Vec<String>,
     pub cpu: CpuRequirement,
+    /// Default: no graphics API requirement
+    #[serde(default)]
     pub gpu: GpuRequirement,
+    /// Default: 0 MB RAM, no storage constraints
+    #[serde(default)]
     pub memory: MemoryRequirement,
     pub runtime: RuntimeRequirement,
+    /// Default: no required inputs, offline
+    #[serde(default)]
     pub io: IoRequirement,
+    /// Default: no timing constraints
+    #[serde(default)]
     pub timing: TimingRequirement,
+    /// Default: [] (plans then verify against L2_INTERACTIVE)
+    #[serde(default)]
     pub fidelity_modes: Vec<FidelityMode>,
+    /// Default: 0.0 for every extractor
+    #[serde(default)]
     pub extractor_confidence: ExtractorConfidence,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CpuRequirement {
     pub required_isa: Vec<String>,
+    /// Default: 1
+    #[serde(default = "default_one")]
     pub min_cores: u32,
+    /// Default: "unknown"
+    #[serde(default = "default_unknown")]
     pub threading_model: String,
     pub simd_required: Option<Vec<String>>,
     pub perf_budget_hint: Option<serde_json::Value>,
 }
 
+/// An empty `required_apis` list means "no API requirement".
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GpuRequirement {
     pub required_apis: Vec<String>,
     pub shader_model: Option<String>,
     pub features_required: serde_json::Value,
     pub vram_min_mb: Option<u32>,
 }
+impl Default for GpuRequirement {
+    fn default() -> Self { Self { required_apis: vec![], shader_model: None, features_required: empty_object(), vram_min_mb: None } }
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MemoryRequirement {
     pub ram_min_mb: u32,
     pub storage_install_mb: Option<u32>,
@@ -110,10 +218,12 @@ pub struct RuntimeRequirement {
     pub drm: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct IoRequirement { pub required_inputs: Vec<String>, pub online_required: bool }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TimingRequirement {
     pub target_fps: Option<f64>,
     pub frame_pacing_sensitive: Option<bool>,
@@ -137,14 +247,25 @@ pub struct SplitExecutionPrefs {
     pub latency_budget_ms_override: Option<f64>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Ordered from weakest to strongest; variant names are the schema's level identifiers
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum EquivalenceLevel {
     L0_BOOT, L1_STABLE, L2_INTERACTIVE, L3_GAMEPLAY_EQ, L4_RENDER_EQ, L5_BIT_EXACT,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ExtractorConfidence { pub static_analysis: f32, pub runtime_probe: f32, pub trace_inference: f32 }
 
+// ── Field defaults ────────────────────────────────────────────────────────────
+
+fn default_doc_version() -> String { "0.1".into() }
+fn default_other() -> String { "other".into() }
+fn default_unknown() -> String { "unknown".into() }
+fn default_one() -> u32 { 1 }
+fn empty_object() -> serde_json::Value { serde_json::Value::Object(Default::default()) }
+
 // ── Policy ────────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -171,7 +292,8 @@ pub struct CompatibilityPlan {
     pub target_platform_id: String,
     pub helper_platform_ids: Vec<String>,
     pub strategy: StrategyClass,
-    pub strategy_pipeline: Vec<String>,
+    /// Ordered steps; legacy plans with bare step-id strings still deserialize
+    pub strategy_pipeline: Vec<PipelineStep>,
     pub rationale: Vec<String>,
     pub gaps: GapSummary,
     pub degradations: Vec<Degradation>,
@@ -179,9 +301,22 @@ pub struct CompatibilityPlan {
     pub scores: PlanScores,
     pub verification_target: VerificationTarget,
     pub confidence: f32,
+    /// Schema versions of the documents this plan was computed from
+    #[serde(default)]
+    pub input_versions: crate::version::InputVersions,
+    /// Present when the plan is NotFeasible: what blocked each strategy and how to unblock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blockers: Option<crate::blockers::BlockerReport>,
+    /// Legal / DRM / anti-cheat findings, their mitigations and whether `max_legal_risk` decided the plan
+    #[serde(default)]
+    pub risks: crate::risks::RiskRegister,
+    /// Present when the pipeline loads an emulator core and cores were supplied:
+    /// the chosen core and the ranked alternatives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub core_selection: Option<crate::cores::CoreSelection>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StrategyClass {
     NativeBc, Emulate, TranslateApi, RuntimeShim, EmulatePlusTranslate,
     DownportRequired, StreamingRecommended, SplitExecutionRecommended,
@@ -229,4 +364,10 @@ pub struct PlanningRequest<'game, 'target, 'helpers, 'policy> {
     pub helpers: &'helpers [CapabilityGraph],
     pub policy: &'policy PolicyProfile,
     pub mode_id: Option<&'game str>,
+    /// Emulator run evidence (training files / scorecards) supplied with the policy
+    pub evidence: &'policy [crate::evidence::Evidence],
+    /// Past verification outcomes that calibrate plan confidence
+    pub calibration: Option<&'policy crate::calibration::CalibrationStore>,
+    /// Emulator cores available on the target, ranked for plans that load one
+    pub cores: &'target [crate::cores::CoreProfile],
 }
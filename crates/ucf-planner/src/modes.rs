@@ -0,0 +1,101 @@
+//! modes.rs — one plan per declared fidelity mode, plus a recommendation
+//!
+//! `plan_execution()` plans a single `mode_id`. `plan_all_modes()` runs it for
+//! every entry of `fidelity_modes` and recommends the mode that fits the
+//! target and policy best: the winning strategy must be able to deliver the
+//! mode's `acceptable_equivalence_min` and meet `min_fidelity_score`; among
+//! the modes that fit, the most demanding one wins (then confidence, then
+//! total score).
+
+use crate::model::{CompatibilityPlan, EquivalenceLevel, PlanningRequest, StrategyClass};
+use crate::planner::plan_execution;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModePlan {
+    /// None when the game declares no fidelity modes
+    pub mode_id: Option<String>,
+    pub priority: Option<String>,
+    /// The strategy can deliver the mode's equivalence and the policy's minimum fidelity
+    pub fits: bool,
+    pub plan: CompatibilityPlan,
+}
+
+/// Combined `--all-modes` document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllModesPlan {
+    pub artifact_id: String,
+    pub target_platform_id: String,
+    pub plans: Vec<ModePlan>,
+    pub recommended_mode: Option<String>,
+    pub recommendation: Vec<String>,
+}
+
+/// Highest equivalence a strategy can be expected to reach
+pub fn max_equivalence(strategy: &StrategyClass) -> EquivalenceLevel {
+    use EquivalenceLevel::*;
+    match strategy {
+        StrategyClass::NativeBc | StrategyClass::Emulate => L5_BIT_EXACT,
+        StrategyClass::RuntimeShim | StrategyClass::TranslateApi | StrategyClass::EmulatePlusTranslate => L4_RENDER_EQ,
+        StrategyClass::StreamingRecommended | StrategyClass::SplitExecutionRecommended
+        | StrategyClass::AugmentationRequired => L3_GAMEPLAY_EQ,
+        StrategyClass::DownportRequired => L2_INTERACTIVE,
+        StrategyClass::NotFeasible => L0_BOOT,
+    }
+}
+
+/// Plan every declared fidelity mode (`req.mode_id` is ignored)
+pub fn plan_all_modes(req: PlanningRequest<'_, '_, '_, '_>) -> Result<AllModesPlan, Box<dyn Error>> {
+    let modes: Vec<Option<&str>> = if req.game.fidelity_modes.is_empty() {
+        vec![None]
+    } else {
+        req.game.fidelity_modes.iter().map(|m| Some(m.mode_id.as_str())).collect()
+    };
+
+    let mut plans = Vec::with_capacity(modes.len());
+    for mode_id in modes {
+        let plan = plan_execution(PlanningRequest { mode_id, ..req })?;
+        let fits = !matches!(plan.strategy, StrategyClass::NotFeasible)
+            && max_equivalence(&plan.strategy) >= plan.verification_target.equivalence_min
+            && plan.scores.fidelity >= req.policy.min_fidelity_score;
+        let priority = req.game.fidelity_modes.iter().find(|m| Some(m.mode_id.as_str()) == mode_id).map(|m| m.priority.clone());
+        plans.push(ModePlan { mode_id: mode_id.map(str::to_string), priority, fits, plan });
+    }
+
+    let best = plans.iter().filter(|m| m.fits).max_by(|a, b| {
+        a.plan.verification_target.equivalence_min.cmp(&b.plan.verification_target.equivalence_min)
+            .then(a.plan.confidence.total_cmp(&b.plan.confidence))
+            .then(a.plan.scores.total.cmp(&b.plan.scores.total))
+    });
+    let mut recommendation = vec![];
+    let recommended_mode = match best {
+        Some(m) => {
+            recommendation.push(format!(
+                "Recommended mode: {} ({:?} via {:?}, confidence {:.2})",
+                m.mode_id.as_deref().unwrap_or("default"), m.plan.verification_target.equivalence_min,
+                m.plan.strategy, m.plan.confidence
+            ));
+            m.mode_id.clone()
+        }
+        None => {
+            recommendation.push("No declared mode fits this target and policy".into());
+            None
+        }
+    };
+    for m in plans.iter().filter(|m| !m.fits) {
+        recommendation.push(format!(
+            "Mode {} does not fit: {:?} reaches at most {:?} (needs {:?}), fidelity {} (policy min {})",
+            m.mode_id.as_deref().unwrap_or("default"), m.plan.strategy, max_equivalence(&m.plan.strategy),
+            m.plan.verification_target.equivalence_min, m.plan.scores.fidelity, req.policy.min_fidelity_score
+        ));
+    }
+
+    Ok(AllModesPlan {
+        artifact_id: req.game.artifact_id.clone(),
+        target_platform_id: req.target.platform_id.clone(),
+        plans,
+        recommended_mode,
+        recommendation,
+    })
+}
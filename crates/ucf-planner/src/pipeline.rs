@@ -0,0 +1,113 @@
+//! pipeline.rs — typed strategy pipeline steps
+//!
+//! A plan's `strategy_pipeline` is a list of `PipelineStep`s the MetaROM host
+//! can orchestrate: what each step needs, what it produces, who runs it and
+//! roughly how long it takes. Steps serialize as objects, but plans written
+//! before this schema (plain step-id strings) still load. Each id is filled
+//! in from `PipelineStep::for_id()`, and `Display` / `CompatibilityPlan::
+//! pipeline_ids()` give back the old string rendering.
+
+use crate::model::CompatibilityPlan;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+/// Who executes a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepOwner {
+    Planner,
+    HostRuntime,
+    EmulatorCore,
+    Translation,
+    Network,
+    Verification,
+    Manual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PipelineStep {
+    pub id: String,
+    pub description: String,
+    /// Artifacts or resources that must exist before the step runs
+    pub requires: Vec<String>,
+    /// Artifacts or resources the step leaves for later steps
+    pub produces: Vec<String>,
+    /// Rough wall-clock estimate; None for steps that run for the whole session
+    pub estimated_duration_ms: Option<u64>,
+    pub owner: StepOwner,
+}
+
+impl PipelineStep {
+    /// The catalog entry for `id`; unknown ids become a manual step with no
+    /// declared inputs or outputs
+    pub fn for_id(id: &str) -> PipelineStep {
+        use StepOwner::*;
+        let (description, requires, produces, ms, owner): (&str, &[&str], &[&str], Option<u64>, StepOwner) = match id {
+            "native_bc_check" => ("Confirm the target runs the artifact natively", &["artifact"], &["running_session"], Some(5_000), HostRuntime),
+            "detect_runtime_mismatches" => ("Probe the artifact for missing runtime APIs", &["artifact"], &["runtime_mismatch_report"], Some(10_000), HostRuntime),
+            "inject_shims" => ("Load shims for the reported runtime mismatches", &["artifact", "runtime_mismatch_report"], &["running_session"], Some(2_000), HostRuntime),
+            "api_translation_layer" => ("Start the graphics API translation layer", &["artifact"], &["translated_api"], Some(2_000), Translation),
+            "shader_transpile" => ("Transpile shaders for the target API", &["translated_api"], &["shader_cache"], Some(60_000), Translation),
+            "load_emulator_core" => ("Load the emulator core for the source platform", &["artifact"], &["emulator_core"], Some(1_000), HostRuntime),
+            "map_bios_rom" => ("Map firmware and the ROM image into the core", &["emulator_core", "artifact"], &["mapped_firmware"], Some(500), EmulatorCore),
+            "run_emulation_loop" => ("Run the emulation loop", &["emulator_core"], &["running_session"], None, EmulatorCore),
+            "split_partition_analysis" => ("Partition simulation from UI / audio", &["artifact"], &["partition_map"], Some(30_000), Planner),
+            "launch_remote_simulation" => ("Start the simulation partition on the helper", &["partition_map", "helper_platform"], &["remote_simulation"], Some(10_000), Network),
+            "launch_local_ui_audio" => ("Start the UI / audio partition locally", &["partition_map"], &["local_frontend"], Some(2_000), HostRuntime),
+            "sync_state_channel" => ("Open the state sync channel between partitions", &["remote_simulation", "local_frontend"], &["running_session"], None, Network),
+            "setup_stream_session" => ("Negotiate a streaming session", &["helper_platform"], &["stream_session"], Some(5_000), Network),
+            "launch_remote_render" => ("Run and render the artifact on the helper", &["stream_session", "artifact"], &["remote_render"], Some(10_000), Network),
+            "stream_av_to_client" => ("Stream audio / video to the target", &["remote_render"], &["running_session"], None, Network),
+            "input_relay_channel" => ("Relay target input to the helper", &["stream_session"], &["input_channel"], None, Network),
+            "downport_analysis" => ("Analyse what the downport must cut or rebuild", &["artifact"], &["downport_spec"], None, Manual),
+            "asset_reduction" => ("Reduce assets to fit the target", &["downport_spec"], &["reduced_assets"], None, Manual),
+            "feature_fallback_map" => ("Map unsupported features to fallbacks", &["downport_spec"], &["fallback_map"], None, Manual),
+            "augmentation_spec" => ("Specify the hardware augmentation needed", &["artifact"], &["augmentation_spec"], None, Manual),
+            "hardware_probe" => ("Probe the target for the augmentation hardware", &["augmentation_spec"], &["probe_report"], Some(10_000), HostRuntime),
+            "verify_equivalence" => ("Check the session against the plan's verification target", &["artifact"], &["equivalence_report"], Some(120_000), Verification),
+            "feasibility_report" => ("Report why no strategy is feasible", &[], &["feasibility_report"], Some(0), Planner),
+            _ => ("", &[], &[], None, Manual),
+        };
+        let owned = |v: &[&str]| v.iter().map(|s| s.to_string()).collect();
+        PipelineStep {
+            id: id.to_string(), description: description.to_string(),
+            requires: owned(requires), produces: owned(produces), estimated_duration_ms: ms, owner,
+        }
+    }
+}
+
+impl fmt::Display for PipelineStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.id) }
+}
+
+/// Full step object, or a legacy bare id
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StepRepr {
+    Id(String),
+    Full {
+        id: String,
+        #[serde(default)] description: String,
+        #[serde(default)] requires: Vec<String>,
+        #[serde(default)] produces: Vec<String>,
+        #[serde(default)] estimated_duration_ms: Option<u64>,
+        owner: StepOwner,
+    },
+}
+
+impl<'de> Deserialize<'de> for PipelineStep {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        Ok(match StepRepr::deserialize(d)? {
+            StepRepr::Id(id) => PipelineStep::for_id(&id),
+            StepRepr::Full { id, description, requires, produces, estimated_duration_ms, owner } =>
+                PipelineStep { id, description, requires, produces, estimated_duration_ms, owner },
+        })
+    }
+}
+
+impl CompatibilityPlan {
+    /// Step ids in order: the pre-typed `strategy_pipeline` rendering
+    pub fn pipeline_ids(&self) -> Vec<&str> {
+        self.strategy_pipeline.iter().map(|s| s.id.as_str()).collect()
+    }
+}
@@ -1,6 +1,8 @@
 use crate::gap::{GapKind, GapSeverity, GapVector};
 use crate::model::{CompatibilityPlan, Degradation, EquivalenceLevel, GameRequirement, NetworkRequirements, UserRequirements, VerificationTarget};
-use crate::strategy::Strategy;
+use crate::model::PlanScores;
+use crate::pipeline::PipelineStep;
+use crate::strategy::{total_score, ScoreWeights, Strategy};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use uuid::Uuid;
@@ -15,10 +17,72 @@ pub enum Compensation {
 
 pub type CompensationMap = BTreeMap<GapKind, Vec<Compensation>>;
 
+/// Score-axis penalties one compensation adds to a plan (points on the 0-100 axes)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompensationCost { pub latency: u16, pub effort: u16, pub determinism: u16, pub friction: u16 }
+
+impl CompensationCost {
+    const fn new(latency: u16, effort: u16, determinism: u16, friction: u16) -> Self {
+        Self { latency, effort, determinism, friction }
+    }
+}
+impl std::ops::Add for CompensationCost {
+    type Output = Self;
+    fn add(self, o: Self) -> Self {
+        Self::new(self.latency + o.latency, self.effort + o.effort, self.determinism + o.determinism, self.friction + o.friction)
+    }
+}
+
+impl Compensation {
+    /// Cost vector: (latency, engineering effort, determinism, user friction)
+    pub fn cost(&self) -> CompensationCost {
+        use Compensation::*;
+        let (l, e, d, f) = match self {
+            Emulation => (5, 5, 0, 0),
+            ApiTranslation => (4, 8, 3, 0),
+            RuntimeShim => (1, 6, 4, 0),
+            TimingShim => (2, 3, 3, 0),
+            FramePacingControl => (1, 2, 0, 0),
+            InputMapper => (0, 2, 0, 2),
+            VirtualInput => (1, 2, 0, 3),
+            RemoteInputBridge => (6, 5, 3, 2),
+            AssetPrefetch => (0, 4, 1, 0),
+            FeatureFallback => (0, 6, 2, 0),
+            Streaming => (10, 6, 6, 3),
+            SplitExecution => (6, 10, 5, 2),
+            Downport => (0, 15, 2, 0),
+            Augmentation => (0, 12, 0, 10),
+            RequiresUserSuppliedFirmware => (0, 0, 0, 10),
+            ProbeRuntime => (0, 3, 0, 0),
+            ManualReview => (0, 8, 0, 5),
+        };
+        CompensationCost::new(l, e, d, f)
+    }
+}
+
+/// Total cost of every compensation in the map
+pub fn compensation_cost(map: &CompensationMap) -> CompensationCost {
+    map.values().flatten().fold(CompensationCost::default(), |acc, c| acc + c.cost())
+}
+
+/// Charge a compensation stack against a strategy's scores and re-total them
+pub fn apply_compensation_costs(scores: PlanScores, map: &CompensationMap, weights: ScoreWeights) -> PlanScores {
+    let c = compensation_cost(map);
+    let dec = |v: u8, by: u16| (v as i32 - by as i32).clamp(0, 100) as u8;
+    let inc = |v: u8, by: u16| (v as i32 + by as i32).clamp(0, 100) as u8;
+    let mut s = scores;
+    s.latency = dec(s.latency, c.latency);
+    s.engineering_effort = inc(s.engineering_effort, c.effort);
+    s.determinism = dec(s.determinism, c.determinism);
+    s.user_friction = inc(s.user_friction, c.friction);
+    s.total = total_score(&s, weights);
+    s
+}
+
 #[derive(Debug, Clone)]
 pub struct PlanCandidate {
     pub strategy: Strategy,
-    pub pipeline: Vec<String>,
+    pub pipeline: Vec<PipelineStep>,
     pub compensation_map: CompensationMap,
     pub rationale: Vec<String>,
     pub degradations: Vec<Degradation>,
@@ -104,7 +168,7 @@ pub fn build_compatibility_plan(
 ) -> CompatibilityPlan {
     let equivalence_min = resolve_equivalence_level(game, mode_id);
     CompatibilityPlan {
-        plan_version: "0.1".into(),
+        plan_version: crate::version::PLAN_VERSION.into(),
         plan_id: format!("plan_{}", Uuid::new_v4().simple()),
         artifact_id: game.artifact_id.clone(),
         target_platform_id: target.platform_id.clone(),
@@ -118,10 +182,16 @@ pub fn build_compatibility_plan(
         scores: candidate.scores,
         verification_target: VerificationTarget { equivalence_min, test_profile: "smoke_plus_input_latency".into() },
         confidence: candidate.confidence,
+        input_versions: Default::default(),
+        blockers: None,
+        risks: Default::default(),
+        core_selection: None,
     }
 }
 
-fn resolve_equivalence_level(game: &GameRequirement, mode_id: Option<&str>) -> EquivalenceLevel {
+/// Minimum equivalence for the requested fidelity mode (the first mode when
+/// none is named, L2 when the artifact declares none)
+pub fn resolve_equivalence_level(game: &GameRequirement, mode_id: Option<&str>) -> EquivalenceLevel {
     if let Some(id) = mode_id {
         if let Some(m) = game.fidelity_modes.iter().find(|m| m.mode_id == id) {
             return m.acceptable_equivalence_min.clone();
@@ -6,16 +6,23 @@
 //!   PlanCandidate + comp_map()   → carries compensation decisions, decoupled from gap analysis
 //!   build_compatibility_plan()   → materializes final CompatibilityPlan from winning candidate
 
+use crate::blockers::report_blockers;
+use crate::calibration::{gap_signature, CalibrationStore};
+use crate::cores::{apply_core_selection, chosen_core};
+use crate::evidence::{apply_evidence_confidence, apply_evidence_scores, EvidenceSummary};
 use crate::gap::{analyze_gaps, GapVector};
 use crate::model::{CompatibilityPlan, PlanningRequest};
+use crate::pipeline::PipelineStep;
 use crate::plan::{
-    build_compatibility_plan, default_compensation_map_for, PlanCandidate,
+    apply_compensation_costs, build_compatibility_plan, compensation_cost, default_compensation_map_for, PlanCandidate,
 };
+use crate::risks::report_risks;
 use crate::strategy::{score_strategy, ScoreWeights, Strategy};
+use crate::version::negotiate_versions;
 use std::error::Error;
 
 /// All strategies considered during planning (ordered from least to most invasive).
-const CANDIDATE_STRATEGIES: &[Strategy] = &[
+pub(crate) const CANDIDATE_STRATEGIES: &[Strategy] = &[
     Strategy::NativeBc,
     Strategy::RuntimeShim,
     Strategy::TranslateApi,
@@ -30,16 +37,21 @@ const CANDIDATE_STRATEGIES: &[Strategy] = &[
 
 /// Entry point: produce a ranked best plan for the given request.
 pub fn plan_execution(req: PlanningRequest<'_, '_, '_, '_>) -> Result<CompatibilityPlan, Box<dyn Error>> {
+    let input_versions = negotiate_versions(&req)?;
     let gaps = analyze_gaps(req.game, req.target);
     let helper_present = !req.helpers.is_empty();
     let weights = ScoreWeights::default();
+    let core = chosen_core(req.cores, req.game, req.mode_id);
+    let evidence = EvidenceSummary::collect(req.evidence, req.game, core.as_deref());
 
-    // 1) Score all candidate strategies against the same GapVector
+    // 1) Score all candidate strategies against the same GapVector (adjusted by run
+    //    evidence), charging each for the compensation stack it would need
     let mut scored: Vec<(Strategy, crate::model::PlanScores)> = CANDIDATE_STRATEGIES
         .iter()
         .map(|&s| {
             let scores = score_strategy(s, &gaps, req.policy, req.target, helper_present, weights);
-            (s, scores)
+            let scores = apply_compensation_costs(scores, &default_compensation_map_for(s, &gaps), weights);
+            (s, apply_evidence_scores(s, scores, &evidence, weights))
         })
         .collect();
 
@@ -53,7 +65,7 @@ pub fn plan_execution(req: PlanningRequest<'_, '_, '_, '_>) -> Result<Compatibil
     // 3) Select winner — skip strategies blocked by policy or hard gaps
     let (winning_strategy, winning_scores) = scored
         .iter()
-        .find(|(s, _)| is_allowed(*s, &gaps, req.policy))
+        .find(|(s, scores)| is_allowed(*s, scores, &gaps, req.policy))
         .copied()
         .unwrap_or((Strategy::NotFeasible, crate::model::PlanScores::default()));
 
@@ -63,7 +75,12 @@ pub fn plan_execution(req: PlanningRequest<'_, '_, '_, '_>) -> Result<Compatibil
     candidate.scores = winning_scores;
     candidate.pipeline = strategy_pipeline(winning_strategy, &gaps);
     candidate.rationale = build_rationale(winning_strategy, &gaps, &winning_scores);
-    candidate.confidence = derive_confidence(&gaps, winning_scores.total);
+    candidate.rationale.extend(compensation_rationale(&candidate.compensation_map));
+    let (heuristic, calibration_note) = derive_confidence(winning_strategy, &gaps, winning_scores.total, req.calibration);
+    let (confidence, evidence_note) = apply_evidence_confidence(winning_strategy, heuristic, &evidence);
+    candidate.confidence = confidence;
+    candidate.rationale.extend(calibration_note);
+    candidate.rationale.extend(evidence_note);
 
     // 5) Apply mode-aware split/rollback preferences
     if let Some(mode_id) = req.mode_id {
@@ -71,111 +88,149 @@ pub fn plan_execution(req: PlanningRequest<'_, '_, '_, '_>) -> Result<Compatibil
     }
 
     // 6) Materialize CompatibilityPlan
-    Ok(build_compatibility_plan(
+    let risks = report_risks(&scored, winning_strategy, &gaps, &candidate.compensation_map, req.policy);
+    let mut plan = build_compatibility_plan(
         candidate,
         req.game,
         req.target,
         req.helpers,
         &gaps,
         req.mode_id,
-    ))
+    );
+    plan.input_versions = input_versions;
+    plan.risks = risks;
+    apply_core_selection(&mut plan, req.cores, req.game);
+    if winning_strategy == Strategy::NotFeasible {
+        plan.blockers = Some(report_blockers(&scored, &gaps, req.policy, req.game, req.target));
+    }
+    Ok(plan)
 }
 
 // ── Strategy gate: policy + hard gap guards ──────────────────────────────────
 
-fn is_allowed(
+/// One reason the gate rejects a strategy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GateBlock {
+    /// A `PolicyProfile` flag (by field name) is off
+    Policy(&'static str),
+    /// A hard gap on this subsystem rules the strategy out
+    HardGap(crate::gap::GapKind),
+}
+
+pub(crate) fn is_allowed(
     strategy: Strategy,
+    scores: &crate::model::PlanScores,
     gaps: &GapVector,
     policy: &crate::model::PolicyProfile,
 ) -> bool {
-    use crate::gap::GapSeverity;
+    gate_blocks(strategy, scores, gaps, policy).is_empty()
+}
+
+/// Every gate rule `strategy` fails. Empty means the strategy is selectable;
+/// `blockers.rs` reads the same rules back to explain a NotFeasible plan.
+pub fn gate_blocks(
+    strategy: Strategy,
+    scores: &crate::model::PlanScores,
+    gaps: &GapVector,
+    policy: &crate::model::PolicyProfile,
+) -> Vec<GateBlock> {
+    use crate::gap::{GapKind, GapSeverity};
     use Strategy::*;
+    let mut blocks = vec![];
 
     match strategy {
         // Streaming/split blocked by policy
-        StreamingRecommended if !policy.allow_streaming => return false,
-        SplitExecutionRecommended if !policy.allow_split_execution => return false,
-        DownportRequired if !policy.allow_downport_classification => return false,
+        StreamingRecommended if !policy.allow_streaming => blocks.push(GateBlock::Policy("allow_streaming")),
+        SplitExecutionRecommended if !policy.allow_split_execution => blocks.push(GateBlock::Policy("allow_split_execution")),
+        DownportRequired if !policy.allow_downport_classification => blocks.push(GateBlock::Policy("allow_downport_classification")),
 
         // Native BC requires no hard CPU/GPU gap
-        NativeBc if gaps.cpu.severity == GapSeverity::Hard => return false,
-        NativeBc if gaps.gpu.severity == GapSeverity::Hard => return false,
-        NativeBc if gaps.runtime.severity == GapSeverity::Hard => return false,
+        NativeBc => {
+            for (kind, status) in [(GapKind::Cpu, &gaps.cpu), (GapKind::Gpu, &gaps.gpu), (GapKind::Runtime, &gaps.runtime)] {
+                if status.severity == GapSeverity::Hard { blocks.push(GateBlock::HardGap(kind)); }
+            }
+        }
 
         // Emulation doesn't help with hard IO/network gaps
-        Emulate | EmulatePlusTranslate if gaps.io.severity == GapSeverity::Hard => return false,
+        Emulate | EmulatePlusTranslate if gaps.io.severity == GapSeverity::Hard => blocks.push(GateBlock::HardGap(GapKind::Io)),
 
         _ => {}
     }
 
-    true
+    // Legal / runtime risk above what the policy accepts
+    if strategy != NotFeasible && scores.legal_risk > policy.max_legal_risk {
+        blocks.push(GateBlock::Policy("max_legal_risk"));
+    }
+
+    blocks
 }
 
-// ── Strategy pipeline strings ─────────────────────────────────────────────────
+// ── Strategy pipeline ─────────────────────────────────────────────────────────
 
-fn strategy_pipeline(strategy: Strategy, _gaps: &GapVector) -> Vec<String> {
-    match strategy {
-        Strategy::NativeBc => vec![
-            "native_bc_check".into(),
-            "verify_equivalence".into(),
+pub(crate) fn strategy_pipeline(strategy: Strategy, _gaps: &GapVector) -> Vec<PipelineStep> {
+    let ids: &[&str] = match strategy {
+        Strategy::NativeBc => &[
+            "native_bc_check",
+            "verify_equivalence",
         ],
-        Strategy::RuntimeShim => vec![
-            "detect_runtime_mismatches".into(),
-            "inject_shims".into(),
-            "verify_equivalence".into(),
+        Strategy::RuntimeShim => &[
+            "detect_runtime_mismatches",
+            "inject_shims",
+            "verify_equivalence",
         ],
-        Strategy::TranslateApi => vec![
-            "api_translation_layer".into(),
-            "shader_transpile".into(),
-            "verify_equivalence".into(),
+        Strategy::TranslateApi => &[
+            "api_translation_layer",
+            "shader_transpile",
+            "verify_equivalence",
         ],
-        Strategy::Emulate => vec![
-            "load_emulator_core".into(),
-            "map_bios_rom".into(),
-            "run_emulation_loop".into(),
-            "verify_equivalence".into(),
+        Strategy::Emulate => &[
+            "load_emulator_core",
+            "map_bios_rom",
+            "run_emulation_loop",
+            "verify_equivalence",
         ],
-        Strategy::EmulatePlusTranslate => vec![
-            "load_emulator_core".into(),
-            "api_translation_layer".into(),
-            "shader_transpile".into(),
-            "run_emulation_loop".into(),
-            "verify_equivalence".into(),
+        Strategy::EmulatePlusTranslate => &[
+            "load_emulator_core",
+            "api_translation_layer",
+            "shader_transpile",
+            "run_emulation_loop",
+            "verify_equivalence",
         ],
-        Strategy::SplitExecutionRecommended => vec![
-            "split_partition_analysis".into(),
-            "launch_remote_simulation".into(),
-            "launch_local_ui_audio".into(),
-            "sync_state_channel".into(),
-            "verify_equivalence".into(),
+        Strategy::SplitExecutionRecommended => &[
+            "split_partition_analysis",
+            "launch_remote_simulation",
+            "launch_local_ui_audio",
+            "sync_state_channel",
+            "verify_equivalence",
         ],
-        Strategy::StreamingRecommended => vec![
-            "setup_stream_session".into(),
-            "launch_remote_render".into(),
-            "stream_av_to_client".into(),
-            "input_relay_channel".into(),
-            "verify_equivalence".into(),
+        Strategy::StreamingRecommended => &[
+            "setup_stream_session",
+            "launch_remote_render",
+            "stream_av_to_client",
+            "input_relay_channel",
+            "verify_equivalence",
         ],
-        Strategy::DownportRequired => vec![
-            "downport_analysis".into(),
-            "asset_reduction".into(),
-            "feature_fallback_map".into(),
-            "verify_equivalence".into(),
+        Strategy::DownportRequired => &[
+            "downport_analysis",
+            "asset_reduction",
+            "feature_fallback_map",
+            "verify_equivalence",
         ],
-        Strategy::AugmentationRequired => vec![
-            "augmentation_spec".into(),
-            "hardware_probe".into(),
-            "verify_equivalence".into(),
+        Strategy::AugmentationRequired => &[
+            "augmentation_spec",
+            "hardware_probe",
+            "verify_equivalence",
         ],
-        Strategy::NotFeasible => vec![
-            "feasibility_report".into(),
+        Strategy::NotFeasible => &[
+            "feasibility_report",
         ],
-    }
+    };
+    ids.iter().map(|id| PipelineStep::for_id(id)).collect()
 }
 
 // ── Rationale builder ─────────────────────────────────────────────────────────
 
-fn build_rationale(
+pub(crate) fn build_rationale(
     strategy: Strategy,
     gaps: &GapVector,
     scores: &crate::model::PlanScores,
@@ -209,21 +264,45 @@ fn build_rationale(
     r
 }
 
+// ── Compensation cost summary ─────────────────────────────────────────────────
+
+pub(crate) fn compensation_rationale(map: &crate::plan::CompensationMap) -> Option<String> {
+    let count: usize = map.values().map(Vec::len).sum();
+    if count == 0 { return None; }
+    let c = compensation_cost(map);
+    Some(format!(
+        "Compensation cost: {count} compensation(s) → latency -{}, effort +{}, determinism -{}, friction +{}",
+        c.latency, c.effort, c.determinism, c.friction
+    ))
+}
+
 // ── Confidence heuristic ──────────────────────────────────────────────────────
 
-fn derive_confidence(gaps: &GapVector, total_score: u8) -> f32 {
+/// Score minus a per-gap penalty, then blended with past verification
+/// outcomes for this strategy and gap signature when a calibration store is
+/// supplied; returns the rationale line for the calibration, if any
+pub(crate) fn derive_confidence(
+    strategy: Strategy,
+    gaps: &GapVector,
+    total_score: u8,
+    calibration: Option<&CalibrationStore>,
+) -> (f32, Option<String>) {
     use crate::gap::GapSeverity;
     let hard_count = gaps.statuses().filter(|s| s.severity == GapSeverity::Hard).count();
     let soft_count = gaps.statuses().filter(|s| s.severity == GapSeverity::Soft).count();
 
     let base = total_score as f32 / 100.0;
     let penalty = (hard_count as f32 * 0.15) + (soft_count as f32 * 0.05);
-    (base - penalty).clamp(0.05, 0.99)
+    let heuristic = (base - penalty).clamp(0.05, 0.99);
+    match calibration {
+        Some(store) => store.calibrate(&strategy.into(), &gap_signature(&gaps.summary_strings()), heuristic),
+        None => (heuristic, None),
+    }
 }
 
 // ── Mode-aware split/rollback preferences ────────────────────────────────────
 
-fn apply_mode_split_prefs(
+pub(crate) fn apply_mode_split_prefs(
     candidate: &mut PlanCandidate,
     game: &crate::model::GameRequirement,
     mode_id: &str,
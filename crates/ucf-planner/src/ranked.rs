@@ -1,3 +1,18 @@
+use crate::gap::analyze_gaps;
+use crate::model::{CompatibilityPlan, PlanningRequest};
+use crate::plan::{build_compatibility_plan, default_compensation_map_for, PlanCandidate};
+use crate::planner::*;
+use crate::strategy::{score_strategy, ScoreWeights, Strategy};
+use std::error::Error;
+
+use crate::blockers::report_blockers;
+use crate::cores::{apply_core_selection, chosen_core};
+use crate::evidence::{apply_evidence_confidence, apply_evidence_scores, EvidenceSummary};
+use crate::plan::apply_compensation_costs;
+use crate::planner::compensation_rationale;
+use crate::risks::report_risks;
+use crate::version::negotiate_versions;
+
 // ── Ranked planning: returns top-3 candidates ────────────────────────────────
 
 /// Result carrying the winning plan plus up to 2 runner-up candidates.
@@ -14,16 +29,20 @@ pub struct RankedPlans {
 /// `winner` is fully materialized as a `CompatibilityPlan`.
 /// `runners_up` are returned as `PlanCandidate` for lightweight inspection (scores, rationale).
 pub fn plan_execution_ranked(req: PlanningRequest<'_, '_, '_, '_>) -> Result<RankedPlans, Box<dyn Error>> {
+    let input_versions = negotiate_versions(&req)?;
     let gaps = analyze_gaps(req.game, req.target);
     let helper_present = !req.helpers.is_empty();
     let weights = ScoreWeights::default();
+    let core = chosen_core(req.cores, req.game, req.mode_id);
+    let evidence = EvidenceSummary::collect(req.evidence, req.game, core.as_deref());
 
-    // 1) Score all candidate strategies
+    // 1) Score all candidate strategies (adjusted by run evidence, charged for compensations)
     let mut scored: Vec<(Strategy, crate::model::PlanScores)> = CANDIDATE_STRATEGIES
         .iter()
         .map(|&s| {
             let scores = score_strategy(s, &gaps, req.policy, req.target, helper_present, weights);
-            (s, scores)
+            let scores = apply_compensation_costs(scores, &default_compensation_map_for(s, &gaps), weights);
+            (s, apply_evidence_scores(s, scores, &evidence, weights))
         })
         .collect();
 
@@ -37,7 +56,7 @@ pub fn plan_execution_ranked(req: PlanningRequest<'_, '_, '_, '_>) -> Result<Ran
     // 3) Collect top-3 allowed candidates
     let mut allowed_candidates: Vec<PlanCandidate> = scored
         .iter()
-        .filter(|(s, _)| is_allowed(*s, &gaps, req.policy))
+        .filter(|(s, scores)| is_allowed(*s, scores, &gaps, req.policy))
         .take(3)
         .map(|(s, scores)| {
             let mut c = PlanCandidate::new(*s);
@@ -45,7 +64,12 @@ pub fn plan_execution_ranked(req: PlanningRequest<'_, '_, '_, '_>) -> Result<Ran
             c.scores = *scores;
             c.pipeline = strategy_pipeline(*s, &gaps);
             c.rationale = build_rationale(*s, &gaps, scores);
-            c.confidence = derive_confidence(&gaps, scores.total);
+            c.rationale.extend(compensation_rationale(&c.compensation_map));
+            let (heuristic, calibration_note) = derive_confidence(*s, &gaps, scores.total, req.calibration);
+            let (confidence, evidence_note) = apply_evidence_confidence(*s, heuristic, &evidence);
+            c.confidence = confidence;
+            c.rationale.extend(calibration_note);
+            c.rationale.extend(evidence_note);
             if let Some(mode_id) = req.mode_id {
                 apply_mode_split_prefs(&mut c, req.game, mode_id);
             }
@@ -62,7 +86,8 @@ pub fn plan_execution_ranked(req: PlanningRequest<'_, '_, '_, '_>) -> Result<Ran
 
     // 4) Winner is first candidate — materialize into CompatibilityPlan
     let winner_candidate = allowed_candidates.remove(0);
-    let winner = build_compatibility_plan(
+    let risks = report_risks(&scored, winner_candidate.strategy, &gaps, &winner_candidate.compensation_map, req.policy);
+    let mut winner = build_compatibility_plan(
         winner_candidate,
         req.game,
         req.target,
@@ -70,6 +95,12 @@ pub fn plan_execution_ranked(req: PlanningRequest<'_, '_, '_, '_>) -> Result<Ran
         &gaps,
         req.mode_id,
     );
+    winner.input_versions = input_versions;
+    winner.risks = risks;
+    apply_core_selection(&mut winner, req.cores, req.game);
+    if matches!(winner.strategy, crate::model::StrategyClass::NotFeasible) {
+        winner.blockers = Some(report_blockers(&scored, &gaps, req.policy, req.game, req.target));
+    }
 
     Ok(RankedPlans {
         winner,
@@ -0,0 +1,105 @@
+//! risks.rs — risk register for compliance review
+//!
+//! Legal, DRM and anti-cheat findings live as reason codes inside the legal
+//! and runtime gap statuses. `RiskRegister` lists them in one place, each with
+//! the compensations the plan applies against it and whether the policy's
+//! `max_legal_risk` decided the plan. Both subsystems feed the `legal_risk`
+//! score (`strategy::severity_risk_delta`), so both are listed.
+
+use crate::gap::{GapKind, GapSeverity, GapVector};
+use crate::model::{PlanScores, PolicyProfile};
+use crate::plan::{Compensation, CompensationMap};
+use crate::planner::{gate_blocks, GateBlock};
+use crate::strategy::{severity_risk_delta, Strategy};
+use serde::{Deserialize, Serialize};
+
+const MAX_LEGAL_RISK: GateBlock = GateBlock::Policy("max_legal_risk");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskCategory {
+    Firmware,
+    Drm,
+    AntiCheat,
+    Runtime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Risk {
+    /// Gap reason code, e.g. `DRM_UNKNOWN`
+    pub code: String,
+    pub category: RiskCategory,
+    pub severity: GapSeverity,
+    pub detail: Option<String>,
+    /// Compensations the plan applies to the risk's subsystem
+    pub mitigation: Vec<Compensation>,
+    /// `max_legal_risk` decided the plan, and without this risk's subsystem
+    /// the legal risk would have been within it
+    pub deciding: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RiskRegister {
+    pub risks: Vec<Risk>,
+    /// Legal risk score the policy limit was checked against
+    pub legal_risk: u8,
+    pub max_legal_risk: u8,
+    /// A higher-ranked strategy was rejected only because `legal_risk > max_legal_risk`
+    pub max_legal_risk_deciding: bool,
+}
+
+/// Build the register from the planner's scored candidates (sorted best first).
+pub fn report_risks(
+    scored: &[(Strategy, PlanScores)],
+    winner: Strategy,
+    gaps: &GapVector,
+    compensations: &CompensationMap,
+    policy: &PolicyProfile,
+) -> RiskRegister {
+    // NotFeasible scores a flat 100; report the risk of what was actually considered
+    let legal_risk = scored
+        .iter()
+        .find(|(s, _)| *s == winner && winner != Strategy::NotFeasible)
+        .or_else(|| scored.iter().find(|(s, _)| *s != Strategy::NotFeasible))
+        .map(|(_, scores)| scores.legal_risk)
+        .unwrap_or(0);
+    let deciding = max_legal_risk_deciding(scored, gaps, policy);
+
+    let mut risks = vec![];
+    for (kind, status) in [(GapKind::Legal, &gaps.legal), (GapKind::Runtime, &gaps.runtime)] {
+        let mitigation = compensations.get(&kind).cloned().unwrap_or_default();
+        let within_without = legal_risk as i32 - severity_risk_delta(status.severity) <= policy.max_legal_risk as i32;
+        for reason in &status.reasons {
+            risks.push(Risk {
+                code: reason.code.clone(),
+                category: category(&reason.code),
+                severity: status.severity,
+                detail: reason.detail.clone(),
+                mitigation: mitigation.clone(),
+                deciding: deciding && within_without,
+            });
+        }
+    }
+
+    RiskRegister { risks, legal_risk, max_legal_risk: policy.max_legal_risk, max_legal_risk_deciding: deciding }
+}
+
+/// The best strategy the other gate rules allow was blocked by `max_legal_risk`
+fn max_legal_risk_deciding(scored: &[(Strategy, PlanScores)], gaps: &GapVector, policy: &PolicyProfile) -> bool {
+    for (s, scores) in scored.iter().filter(|(s, _)| *s != Strategy::NotFeasible) {
+        let blocks = gate_blocks(*s, scores, gaps, policy);
+        if blocks.iter().all(|b| *b == MAX_LEGAL_RISK) {
+            return !blocks.is_empty();
+        }
+    }
+    false
+}
+
+fn category(code: &str) -> RiskCategory {
+    match code {
+        "USER_SUPPLIED_FIRMWARE_REQUIRED" => RiskCategory::Firmware,
+        "DRM_UNKNOWN" => RiskCategory::Drm,
+        "ANTI_CHEAT_POTENTIAL_BLOCKER" => RiskCategory::AntiCheat,
+        _ => RiskCategory::Runtime,
+    }
+}
@@ -41,6 +41,7 @@ impl Default for ScoreWeights {
     }
 }
 
+#[derive(Clone, Copy)]
 struct WorkingAxes {
     fidelity: i32, latency: i32, engineering_effort: i32,
     runtime_cost: i32, legal_risk: i32, determinism: i32, user_friction: i32,
@@ -58,25 +59,32 @@ impl WorkingAxes {
             legal_risk: clamp(self.legal_risk), determinism: clamp(self.determinism),
             user_friction: clamp(self.user_friction), total: 0,
         };
-        let total_i =
-            (s.fidelity as i32 * weights.fidelity as i32) +
-            (s.latency as i32 * weights.latency as i32) +
-            ((100 - s.engineering_effort as i32) * weights.engineering_effort_inverted as i32) +
-            ((100 - s.runtime_cost as i32) * weights.runtime_cost_inverted as i32) +
-            ((100 - s.legal_risk as i32) * weights.legal_risk_inverted as i32) +
-            (s.determinism as i32 * weights.determinism as i32) +
-            ((100 - s.user_friction as i32) * weights.user_friction_inverted as i32);
-        let max_total = 100 * (weights.fidelity as i32 + weights.latency as i32 +
-            weights.engineering_effort_inverted as i32 + weights.runtime_cost_inverted as i32 +
-            weights.legal_risk_inverted as i32 + weights.determinism as i32 + weights.user_friction_inverted as i32);
-        s.total = clamp(((total_i as f32 / max_total as f32) * 100.0).round() as i32);
+        s.total = total_score(&s, weights);
         s
     }
 }
 
+/// Weighted 0-100 total of the individual axes (the `total` field is ignored)
+pub fn total_score(s: &PlanScores, weights: ScoreWeights) -> u8 {
+    let total_i =
+        (s.fidelity as i32 * weights.fidelity as i32) +
+        (s.latency as i32 * weights.latency as i32) +
+        ((100 - s.engineering_effort as i32) * weights.engineering_effort_inverted as i32) +
+        ((100 - s.runtime_cost as i32) * weights.runtime_cost_inverted as i32) +
+        ((100 - s.legal_risk as i32) * weights.legal_risk_inverted as i32) +
+        (s.determinism as i32 * weights.determinism as i32) +
+        ((100 - s.user_friction as i32) * weights.user_friction_inverted as i32);
+    let max_total = 100 * (weights.fidelity as i32 + weights.latency as i32 +
+        weights.engineering_effort_inverted as i32 + weights.runtime_cost_inverted as i32 +
+        weights.legal_risk_inverted as i32 + weights.determinism as i32 + weights.user_friction_inverted as i32);
+    // All-zero weights: nothing is valued, rather than 0/0
+    if max_total == 0 { return 0; }
+    clamp(((total_i as f32 / max_total as f32) * 100.0).round() as i32)
+}
+
 pub fn score_strategy(
     strategy: Strategy, gaps: &GapVector, policy: &PolicyProfile,
-    _target: &CapabilityGraph, helper_present: bool, weights: ScoreWeights,
+    target: &CapabilityGraph, helper_present: bool, weights: ScoreWeights,
 ) -> PlanScores {
     let mut a = WorkingAxes::baseline();
     penalize(&mut a.fidelity, gaps.cpu.severity, 10, 25);
@@ -93,11 +101,11 @@ pub fn score_strategy(
         Strategy::EmulatePlusTranslate => { a.latency -= 20; a.engineering_effort -= 20; a.runtime_cost -= 25; a.determinism -= 5; }
         Strategy::DownportRequired => { a.fidelity -= 15; a.engineering_effort -= 35; a.user_friction += 10; }
         Strategy::StreamingRecommended => {
-            a.fidelity -= 10; a.latency -= 25; a.runtime_cost -= 10; a.determinism -= 20; a.user_friction -= 10;
+            a.fidelity -= 10; a.latency -= remote_latency_penalty(25, target, policy); a.runtime_cost -= 10; a.determinism -= 20; a.user_friction -= 10;
             if helper_present { a.latency += 5; }
         }
         Strategy::SplitExecutionRecommended => {
-            a.fidelity -= 5; a.latency -= 15; a.engineering_effort -= 25; a.runtime_cost -= 15; a.determinism -= 15; a.user_friction -= 10;
+            a.fidelity -= 5; a.latency -= remote_latency_penalty(15, target, policy); a.engineering_effort -= 25; a.runtime_cost -= 15; a.determinism -= 15; a.user_friction -= 10;
             if helper_present { a.latency += 8; }
         }
         Strategy::AugmentationRequired => { a.engineering_effort -= 30; a.runtime_cost -= 20; a.user_friction -= 25; }
@@ -112,10 +120,25 @@ pub fn score_strategy(
     a.to_plan_scores(weights)
 }
 
+/// Latency penalty of a remote strategy. `guess` assumes input latency plus
+/// RTT at half the policy budget; once telemetry has measured both (the core's
+/// `input_latency_ms` and the network RTT) the penalty scales with the measured
+/// share of the budget instead, up to three times the guess.
+fn remote_latency_penalty(guess: i32, target: &CapabilityGraph, policy: &PolicyProfile) -> i32 {
+    let input = target.profiles.observed.as_ref().and_then(|o| o.input_latency_ms);
+    match (input, target.io.network.rtt_ms) {
+        (Some(input), Some(rtt)) if policy.latency_budget_ms > 0.0 => {
+            let share = (input + rtt).max(0.0) / (policy.latency_budget_ms * 0.5);
+            ((guess as f64 * share).round() as i32).min(guess * 3)
+        }
+        _ => guess,
+    }
+}
+
 fn penalize(field: &mut i32, sev: GapSeverity, soft_penalty: i32, hard_penalty: i32) {
     match sev { GapSeverity::None => {}, GapSeverity::Soft => *field -= soft_penalty, GapSeverity::Hard => *field -= hard_penalty }
 }
-fn severity_risk_delta(sev: GapSeverity) -> i32 {
+pub(crate) fn severity_risk_delta(sev: GapSeverity) -> i32 {
     match sev { GapSeverity::None => 0, GapSeverity::Soft => 20, GapSeverity::Hard => 40 }
 }
 fn clamp(v: i32) -> u8 { if v < 0 { 0 } else if v > 100 { 100 } else { v as u8 } }
\ No newline at end of file
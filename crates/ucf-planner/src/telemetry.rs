@@ -0,0 +1,178 @@
+//! telemetry.rs — host runtime telemetry fed back into capability graphs
+//!
+//! A MetaROM host running a plan reports what it actually achieved (fps,
+//! network RTT, dropped frames, and the emulator core's measured input
+//! latency). `ingest_telemetry()` folds each sample into
+//! the target graph's `profiles.observed` and marks the profile measured, so
+//! the stored graph converges on the real platform. `replan_on_telemetry()`
+//! checks the observations against the assumptions the running plan was
+//! built on and, when one no longer holds, plans again against the updated
+//! graph. Observed shortfalls also surface as timing gaps (`gap.rs`), which
+//! is what moves the new plan.
+
+use crate::model::{CapabilityGraph, CompatibilityPlan, PlanningRequest, StrategyClass};
+use crate::planner::plan_execution;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// `profiles.source` of a graph updated from telemetry
+pub const TELEMETRY_SOURCE: &str = "runtime_telemetry";
+/// Achieved fps may fall this far (as a fraction) below the target
+pub const FPS_TOLERANCE: f64 = 0.05;
+/// Largest acceptable dropped / total frame ratio
+pub const MAX_DROPPED_FRAME_RATIO: f64 = 0.01;
+
+/// One telemetry report from the host runtime
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetrySample {
+    /// Plan the host was running, if it knows
+    pub plan_id: Option<String>,
+    /// Frames covered by this report
+    pub frames: u64,
+    pub achieved_fps: Option<f64>,
+    pub rtt_ms: Option<f64>,
+    pub dropped_frames: u64,
+    /// Press-to-screen latency the core measured (gb-core `input_latency`,
+    /// reported through the core's diagnostics)
+    pub input_latency_ms: Option<f64>,
+}
+
+/// Accumulated observations, stored under `profiles.observed`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObservedProfile {
+    pub samples: u64,
+    pub frames: u64,
+    /// Frame-weighted mean over all samples that reported it
+    pub achieved_fps: Option<f64>,
+    /// Frame-weighted mean over all samples that reported it
+    pub rtt_ms: Option<f64>,
+    pub dropped_frames: u64,
+    /// Frame-weighted mean over all samples that reported it
+    pub input_latency_ms: Option<f64>,
+    /// Plan of the most recent sample
+    pub last_plan_id: Option<String>,
+    /// Weights behind the means, kept so later samples merge correctly
+    fps_frames: u64,
+    rtt_frames: u64,
+    latency_frames: u64,
+}
+
+impl ObservedProfile {
+    pub fn merge(&mut self, s: &TelemetrySample) {
+        // A sample without a frame count still counts once
+        let weight = s.frames.max(1);
+        let mean = |old: Option<f64>, old_w: u64, new: f64| {
+            old.map_or(new, |o| (o * old_w as f64 + new * weight as f64) / (old_w + weight) as f64)
+        };
+        if let Some(fps) = s.achieved_fps {
+            self.achieved_fps = Some(mean(self.achieved_fps, self.fps_frames, fps));
+            self.fps_frames += weight;
+        }
+        if let Some(rtt) = s.rtt_ms {
+            self.rtt_ms = Some(mean(self.rtt_ms, self.rtt_frames, rtt));
+            self.rtt_frames += weight;
+        }
+        if let Some(latency) = s.input_latency_ms {
+            self.input_latency_ms = Some(mean(self.input_latency_ms, self.latency_frames, latency));
+            self.latency_frames += weight;
+        }
+        self.samples += 1;
+        self.frames += s.frames;
+        self.dropped_frames += s.dropped_frames;
+        if s.plan_id.is_some() { self.last_plan_id = s.plan_id.clone(); }
+    }
+
+    pub fn dropped_frame_ratio(&self) -> f64 {
+        if self.frames == 0 { 0.0 } else { self.dropped_frames as f64 / self.frames as f64 }
+    }
+}
+
+/// Fold `sample` into `target.profiles` (measured, source `runtime_telemetry`);
+/// a measured RTT also becomes the graph's `io.network.rtt_ms`
+pub fn ingest_telemetry<'a>(target: &'a mut CapabilityGraph, sample: &TelemetrySample) -> &'a ObservedProfile {
+    target.profiles.measured = true;
+    target.profiles.source = TELEMETRY_SOURCE.into();
+    let observed = target.profiles.observed.get_or_insert_with(Default::default);
+    observed.merge(sample);
+    if let Some(rtt) = observed.rtt_ms { target.io.network.rtt_ms = Some(rtt); }
+    observed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Assumption { Fps, Rtt, DroppedFrames, InputLatency }
+
+/// An observation outside what the plan assumed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssumptionViolation {
+    pub assumption: Assumption,
+    /// The bound the plan relied on
+    pub expected: f64,
+    pub observed: f64,
+}
+
+impl AssumptionViolation {
+    pub fn describe(&self) -> String {
+        match self.assumption {
+            Assumption::Fps => format!("achieved {:.1} fps, plan assumes {:.1}", self.observed, self.expected),
+            Assumption::Rtt => format!("measured RTT {:.1} ms, plan allows {:.1}", self.observed, self.expected),
+            Assumption::InputLatency => format!("input latency {:.1} ms end to end, plan allows {:.1}", self.observed, self.expected),
+            Assumption::DroppedFrames => format!("dropped {:.1}% of frames, plan allows {:.1}%", self.observed * 100.0, self.expected * 100.0),
+        }
+    }
+}
+
+/// Observations that break the running plan's assumptions: the game's target
+/// fps, the plan's `max_rtt_ms` (else the policy latency budget),
+/// `MAX_DROPPED_FRAME_RATIO`, and the policy latency budget for the measured
+/// input latency (plus the RTT when the plan runs remotely)
+pub fn check_assumptions(plan: &CompatibilityPlan, req: &PlanningRequest<'_, '_, '_, '_>, observed: &ObservedProfile) -> Vec<AssumptionViolation> {
+    let mut out = vec![];
+    if let (Some(target_fps), Some(fps)) = (req.game.timing.target_fps, observed.achieved_fps) {
+        if fps < target_fps * (1.0 - FPS_TOLERANCE) {
+            out.push(AssumptionViolation { assumption: Assumption::Fps, expected: target_fps, observed: fps });
+        }
+    }
+    if let Some(rtt) = observed.rtt_ms {
+        let budget = plan.requirements_for_user.network.max_rtt_ms.unwrap_or(req.policy.latency_budget_ms);
+        if rtt > budget {
+            out.push(AssumptionViolation { assumption: Assumption::Rtt, expected: budget, observed: rtt });
+        }
+    }
+    if let Some(input) = observed.input_latency_ms {
+        let remote = matches!(plan.strategy, StrategyClass::StreamingRecommended | StrategyClass::SplitExecutionRecommended);
+        let total = input + if remote { observed.rtt_ms.unwrap_or(0.0) } else { 0.0 };
+        if total > req.policy.latency_budget_ms {
+            out.push(AssumptionViolation { assumption: Assumption::InputLatency, expected: req.policy.latency_budget_ms, observed: total });
+        }
+    }
+    let ratio = observed.dropped_frame_ratio();
+    if ratio > MAX_DROPPED_FRAME_RATIO {
+        out.push(AssumptionViolation { assumption: Assumption::DroppedFrames, expected: MAX_DROPPED_FRAME_RATIO, observed: ratio });
+    }
+    out
+}
+
+/// What one round of telemetry did to a running plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryOutcome {
+    pub violations: Vec<AssumptionViolation>,
+    /// New plan, present when a violation triggered re-planning
+    pub replan: Option<CompatibilityPlan>,
+}
+
+/// Check `plan` against the observations in `req.target` (already passed
+/// through `ingest_telemetry`) and re-plan if any assumption broke. The new
+/// plan's rationale names the violations that triggered it.
+pub fn replan_on_telemetry(plan: &CompatibilityPlan, req: PlanningRequest<'_, '_, '_, '_>) -> Result<TelemetryOutcome, Box<dyn Error>> {
+    let Some(observed) = req.target.profiles.observed.as_ref() else {
+        return Ok(TelemetryOutcome { violations: vec![], replan: None });
+    };
+    let violations = check_assumptions(plan, &req, observed);
+    if violations.is_empty() { return Ok(TelemetryOutcome { violations, replan: None }); }
+    let mut replan = plan_execution(req)?;
+    let reasons: Vec<String> = violations.iter().map(AssumptionViolation::describe).collect();
+    replan.rationale.push(format!("Re-planned from telemetry (replaces {}): {}", plan.plan_id, reasons.join("; ")));
+    Ok(TelemetryOutcome { violations, replan: Some(replan) })
+}
@@ -0,0 +1,80 @@
+//! verification.rs — checkpoint reports graded against a plan's VerificationTarget
+//!
+//! A verification harness runs the plan's artifact against checkpoints
+//! ("PC reaches 0x150 within 10 frames", "LY cycles through all 154
+//! values"), each tagged with the `EquivalenceLevel` it is evidence for, and
+//! writes an `mrom.checkpoints.v1` report (gb-core's `run_checkpoints`).
+//! `evaluate_verification()` recomputes the achieved level from the
+//! individual results — the highest level with checkpoints where those and
+//! every lower level's all passed — and compares it with the target's
+//! `equivalence_min`. The outcome can be recorded in a `CalibrationStore`.
+
+use crate::model::{CompatibilityPlan, EquivalenceLevel};
+use serde::{Deserialize, Serialize};
+
+/// `version` of checkpoint reports this planner reads
+pub const CHECKPOINTS_VERSION: &str = "mrom.checkpoints.v1";
+
+/// One checkpoint's result as the harness reported it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointResult {
+    pub id: String,
+    /// `pc`, `ly_cycles`, `mem_equals`, `serial`, ...
+    pub kind: String,
+    pub level: EquivalenceLevel,
+    pub passed: bool,
+    /// Frame it passed at (None when it failed)
+    #[serde(default)]
+    pub frame: Option<u64>,
+    #[serde(default)]
+    pub detail: String,
+    /// Hex digest of the machine state at the checkpoint
+    #[serde(default)]
+    pub state_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointReport {
+    pub version: String,
+    #[serde(default)]
+    pub frames_run: u64,
+    pub results: Vec<CheckpointResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationOutcome {
+    pub equivalence_min: EquivalenceLevel,
+    /// None when no level's checkpoints all passed
+    pub achieved: Option<EquivalenceLevel>,
+    pub met: bool,
+    /// `id (LEVEL): detail` for each failed checkpoint
+    pub failed: Vec<String>,
+    /// One line for logs: checkpoints passed, achieved and target level
+    pub summary: String,
+}
+
+/// Grade `report` against `plan`'s verification target
+pub fn evaluate_verification(plan: &CompatibilityPlan, report: &CheckpointReport) -> Result<VerificationOutcome, String> {
+    if report.version != CHECKPOINTS_VERSION {
+        return Err(format!("checkpoint report version {:?}, expected {CHECKPOINTS_VERSION}", report.version));
+    }
+    let min = plan.verification_target.equivalence_min.clone();
+    let mut levels: Vec<&EquivalenceLevel> = report.results.iter().map(|r| &r.level).collect();
+    levels.sort();
+    levels.dedup();
+    let mut achieved = None;
+    for level in levels {
+        if !report.results.iter().filter(|r| r.level == *level).all(|r| r.passed) { break; }
+        achieved = Some(level.clone());
+    }
+    let failed: Vec<String> = report.results.iter().filter(|r| !r.passed)
+        .map(|r| format!("{} ({:?}): {}", r.id, r.level, r.detail))
+        .collect();
+    let met = achieved.as_ref().is_some_and(|a| *a >= min);
+    let passed = report.results.len() - failed.len();
+    let summary = match &achieved {
+        Some(a) => format!("{passed}/{} checkpoint(s) passed; achieved {a:?}, target {min:?}", report.results.len()),
+        None => format!("{passed}/{} checkpoint(s) passed; no level achieved, target {min:?}", report.results.len()),
+    };
+    Ok(VerificationOutcome { equivalence_min: min, achieved, met, failed, summary })
+}
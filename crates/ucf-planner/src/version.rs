@@ -0,0 +1,133 @@
+//! version.rs — input document version negotiation
+//!
+//! Every input carries a schema version string (`capability_version`,
+//! `requirement_version`, `policy_version`). The planner accepts any document
+//! on a release line it implements — the major version, or `0.MINOR` while
+//! the major is 0, as semver treats every 0.x minor as breaking. A newer
+//! minor (or, on a 0.x line, patch) is planned anyway with a warning
+//! (unknown additions are ignored, see `authoring.rs`). The versions
+//! that were actually consumed are echoed in the plan's `input_versions`.
+
+use crate::model::PlanningRequest;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version of the plan document this planner emits
+pub const PLAN_VERSION: &str = "0.1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SchemaVersion { pub major: u32, pub minor: u32, pub patch: u32 }
+
+impl SchemaVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self { Self { major, minor, patch } }
+
+    /// Parse "MAJOR[.MINOR[.PATCH]]" (missing components are 0, a leading "v" is allowed)
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().trim_start_matches('v');
+        let mut parts = s.split('.');
+        let mut next = |required: bool| match parts.next() {
+            Some(p) => p.parse::<u32>().ok(),
+            None if required => None,
+            None => Some(0),
+        };
+        let v = Self::new(next(true)?, next(false)?, next(false)?);
+        parts.next().is_none().then_some(v)
+    }
+
+    /// Same release line: equal majors, and for 0.x equal minors too
+    pub fn compatible_with(&self, other: &SchemaVersion) -> bool {
+        self.major == other.major && (self.major != 0 || self.minor == other.minor)
+    }
+
+    /// The release line, e.g. "1.x" or "0.1.x"
+    pub fn line(&self) -> String {
+        if self.major == 0 { format!("0.{}.x", self.minor) } else { format!("{}.x", self.major) }
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DocumentKind { Capability, Requirement, Policy }
+
+impl DocumentKind {
+    /// Newest schema version of this document kind the planner implements
+    pub fn supported(&self) -> SchemaVersion {
+        match self {
+            DocumentKind::Capability | DocumentKind::Requirement | DocumentKind::Policy => SchemaVersion::new(0, 1, 0),
+        }
+    }
+    pub fn field(&self) -> &'static str {
+        match self {
+            DocumentKind::Capability => "capability_version",
+            DocumentKind::Requirement => "requirement_version",
+            DocumentKind::Policy => "policy_version",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionError {
+    Unparseable { kind: DocumentKind, id: String, version: String },
+    /// Not on the supported release line (`SchemaVersion::compatible_with`)
+    Unsupported { kind: DocumentKind, id: String, found: SchemaVersion, supported: SchemaVersion },
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionError::Unparseable { kind, id, version } =>
+                write!(f, "{id}: {} {version:?} is not a version number", kind.field()),
+            VersionError::Unsupported { kind, id, found, supported } =>
+                write!(f, "{id}: {} {found} is not supported (planner implements {}, up to {supported})",
+                       kind.field(), supported.line()),
+        }
+    }
+}
+impl std::error::Error for VersionError {}
+
+/// Check one document version; `Ok(Some(warning))` for a newer version on
+/// the supported line
+pub fn check_version(kind: DocumentKind, id: &str, version: &str) -> Result<Option<String>, VersionError> {
+    let found = SchemaVersion::parse(version)
+        .ok_or_else(|| VersionError::Unparseable { kind, id: id.into(), version: version.into() })?;
+    let supported = kind.supported();
+    if !found.compatible_with(&supported) {
+        return Err(VersionError::Unsupported { kind, id: id.into(), found, supported });
+    }
+    Ok((found > supported).then(|| format!(
+        "{id}: {} {found} is newer than supported {supported}; newer fields are ignored", kind.field()
+    )))
+}
+
+/// Schema generations that produced a plan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputVersions {
+    pub planner: String,
+    pub requirement: String,
+    pub capability: String,
+    pub helpers: Vec<String>,
+    pub policy: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// Validate every input document of a request and collect its versions
+pub fn negotiate_versions(req: &PlanningRequest<'_, '_, '_, '_>) -> Result<InputVersions, VersionError> {
+    let mut warnings = vec![];
+    let mut check = |kind, id: &str, version: &str| -> Result<String, VersionError> {
+        warnings.extend(check_version(kind, id, version)?);
+        Ok(version.to_string())
+    };
+    let requirement = check(DocumentKind::Requirement, &req.game.artifact_id, &req.game.requirement_version)?;
+    let capability = check(DocumentKind::Capability, &req.target.platform_id, &req.target.capability_version)?;
+    let helpers = req.helpers.iter()
+        .map(|h| check(DocumentKind::Capability, &h.platform_id, &h.capability_version))
+        .collect::<Result<Vec<_>, _>>()?;
+    let policy = check(DocumentKind::Policy, &req.policy.profile_id, &req.policy.policy_version)?;
+    Ok(InputVersions { planner: PLAN_VERSION.into(), requirement, capability, helpers, policy, warnings })
+}
@@ -0,0 +1,44 @@
+//! Strict parsing rejects unknown fields only
+
+use ucf_planner::model::{CapabilityGraph, CompatibilityPlan, GameRequirement, PlanningRequest, PolicyProfile};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+fn strict<T: serde::de::DeserializeOwned>(doc: &serde_json::Value) -> Result<T, String> {
+    parse_document(&doc.to_string(), Strictness::DenyUnknownFields).map_err(|e| e.to_string())
+}
+
+#[test]
+fn unknown_fields_are_reported_by_path() {
+    let mut doc = serde_json::to_value(pc_linux_x64()).unwrap();
+    doc["memory"]["ram_mbb"] = 4096.into();
+    doc["helpers_note"] = "x".into();
+    let err = strict::<CapabilityGraph>(&doc).unwrap_err();
+    assert!(err.contains("memory.ram_mbb") && err.contains("helpers_note"), "{err}");
+    assert!(parse_document::<CapabilityGraph>(&doc.to_string(), Strictness::Lenient).is_ok());
+}
+
+#[test]
+fn null_and_empty_known_fields_are_not_unknown() {
+    let mut doc = serde_json::to_value(pc_linux_x64()).unwrap();
+    doc["profiles"]["observed"] = serde_json::Value::Null;
+    strict::<CapabilityGraph>(&doc).unwrap();
+
+    let game: GameRequirement = serde_json::from_str(r#"{
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]}
+    }"#).unwrap();
+    let policy = PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "authoring".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    };
+    let host = pc_linux_x64();
+    let plan = plan_execution(PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    }).unwrap();
+    let mut doc = serde_json::to_value(&plan).unwrap();
+    doc["input_versions"]["warnings"] = serde_json::json!([]);
+    strict::<CompatibilityPlan>(&doc).unwrap();
+}
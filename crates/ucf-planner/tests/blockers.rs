@@ -0,0 +1,79 @@
+//! Gate blockers drop candidates from both planners and are reported
+
+use ucf_planner::model::{CapabilityGraph, CompatibilityPlan, GameRequirement, PlanningRequest, PolicyProfile, StrategyClass};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+/// The PS2 example, online-only and without a controller requirement so the
+/// IO gap is decided by the network alone
+fn online_ps2() -> GameRequirement {
+    let mut doc: serde_json::Value = serde_json::from_str(include_str!("../../../examples/ps2_to_pc_req.json")).unwrap();
+    doc["io"] = serde_json::json!({"required_inputs": [], "online_required": true});
+    serde_json::from_value(doc).unwrap()
+}
+
+fn policy(max_legal_risk: u8, allow_streaming: bool) -> PolicyProfile {
+    PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "blockers".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk,
+        prefer_local_execution: true, allow_streaming, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    }
+}
+
+fn offline_host() -> CapabilityGraph {
+    let mut host = pc_linux_x64();
+    host.io.network.available = false;
+    host
+}
+
+fn plan_both(game: &GameRequirement, host: &CapabilityGraph, policy: &PolicyProfile) -> (CompatibilityPlan, RankedPlans) {
+    let req = || PlanningRequest {
+        game, target: host, helpers: &[], policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    };
+    (plan_execution(req()).unwrap(), plan_execution_ranked(req()).unwrap())
+}
+
+fn strategies(ranked: &RankedPlans) -> Vec<Strategy> { ranked.runners_up.iter().map(|c| c.strategy).collect() }
+
+#[test]
+fn a_hard_io_gap_removes_emulation_from_the_ranking() {
+    let (game, policy) = (online_ps2(), policy(70, true));
+    let (_, online) = plan_both(&game, &pc_linux_x64(), &policy);
+    assert!(strategies(&online).contains(&Strategy::Emulate), "{:?}", strategies(&online));
+
+    let (plan, offline) = plan_both(&game, &offline_host(), &policy);
+    assert!(!strategies(&offline).iter().any(|s| matches!(s, Strategy::Emulate | Strategy::EmulatePlusTranslate)), "{:?}", strategies(&offline));
+    assert_eq!(plan.strategy, offline.winner.strategy, "both planners gate the same way");
+    assert!(plan.blockers.is_none() && offline.winner.blockers.is_none(), "a feasible plan has no blocker report");
+}
+
+#[test]
+fn both_planners_report_what_blocked_every_candidate() {
+    let game = online_ps2();
+    let (plan, ranked) = plan_both(&game, &offline_host(), &policy(49, false));
+    assert_eq!(plan.strategy, StrategyClass::NotFeasible);
+    assert_eq!(ranked.winner.strategy, StrategyClass::NotFeasible);
+    assert!(ranked.runners_up.is_empty());
+
+    let report = plan.blockers.expect("NotFeasible plans explain themselves");
+    assert_eq!(serde_json::to_value(&report).unwrap(), serde_json::to_value(ranked.winner.blockers.as_ref().unwrap()).unwrap());
+
+    let find = |pred: &dyn Fn(&BlockerCause) -> bool| report.blockers.iter().find(|b| pred(&b.cause)).expect("blocker");
+    let io = find(&|c| matches!(c, BlockerCause::HardGap { subsystem: GapKind::Io, .. }));
+    assert_eq!(io.eliminated, [StrategyClass::Emulate, StrategyClass::EmulatePlusTranslate]);
+    assert_eq!(io.unblock, ["make io.network.available on the target"]);
+    let BlockerCause::HardGap { codes, .. } = &io.cause else { unreachable!() };
+    assert_eq!(codes, &["ONLINE_REQUIRED_NETWORK_UNAVAILABLE"]);
+
+    let streaming = find(&|c| matches!(c, BlockerCause::Policy { flag } if flag == "allow_streaming"));
+    assert_eq!(streaming.eliminated, [StrategyClass::StreamingRecommended]);
+    assert_eq!(streaming.unblock, ["enable allow_streaming"]);
+    let legal = find(&|c| matches!(c, BlockerCause::Policy { flag } if flag == "max_legal_risk"));
+    assert_eq!(legal.eliminated.len(), 9, "legal risk 50 is over 49 for every strategy: {:?}", legal.eliminated);
+
+    let best = report.best_alternative.expect("an eliminated strategy to aim for");
+    assert_eq!(best.strategy, StrategyClass::NativeBc, "the highest-scoring eliminated strategy");
+    assert!(best.unblock.iter().any(|h| h.starts_with("target cpu.isas must include")), "{:?}", best.unblock);
+    assert_eq!(best.unblock.last().map(String::as_str), Some("raise max_legal_risk, or clear the legal / runtime gaps behind the legal risk"));
+}
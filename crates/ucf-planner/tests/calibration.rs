@@ -0,0 +1,69 @@
+//! Confidence calibrated by recorded verification outcomes
+
+use ucf_planner::model::{GameRequirement, PlanningRequest, PolicyProfile, StrategyClass};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+fn tetris() -> GameRequirement {
+    serde_json::from_str(r#"{
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]},
+        "gpu": {"required_apis": ["dmg_ppu"]}
+    }"#).unwrap()
+}
+
+fn policy() -> PolicyProfile {
+    PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "calibration".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    }
+}
+
+#[test]
+fn outcomes_pull_the_heuristic_towards_the_observed_pass_rate() {
+    let mut store = CalibrationStore::default();
+    assert_eq!(store.calibrate(&StrategyClass::Emulate, "cpu:hard", 0.6), (0.6, None));
+
+    for passed in [true, true, true, true, false] { store.record_outcome(StrategyClass::Emulate, "cpu:hard", passed); }
+    let entry = store.entry(&StrategyClass::Emulate, "cpu:hard").unwrap();
+    assert_eq!((entry.passed, entry.failed, entry.runs()), (4, 1, 5));
+    assert!((entry.pass_rate() - 0.8).abs() < 1e-6);
+
+    let (confidence, note) = store.calibrate(&StrategyClass::Emulate, "cpu:hard", 0.6);
+    assert!((confidence - 0.7).abs() < 1e-6, "(0.6·5 + 4) / (5 + 5), got {confidence}");
+    assert!(note.unwrap().starts_with("Calibration: 4/5 verification run(s) passed"));
+    assert_eq!(store.calibrate(&StrategyClass::NativeBc, "cpu:hard", 0.6).1, None, "other strategies are untouched");
+    assert_eq!(store.calibrate(&StrategyClass::Emulate, "none", 0.6).1, None, "other gap signatures are untouched");
+
+    for _ in 0..200 { store.record_outcome(StrategyClass::Emulate, "cpu:hard", false); }
+    assert_eq!(store.calibrate(&StrategyClass::Emulate, "cpu:hard", 0.6).0, 0.05, "clamped like the heuristic");
+}
+
+#[test]
+fn plans_use_the_store_and_recorded_plans_round_trip() {
+    let (game, host, policy) = (tetris(), pc_linux_x64(), policy());
+    let request = |calibration| PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration, cores: &[],
+    };
+    let uncalibrated = plan_execution(request(None)).unwrap();
+    assert!(!uncalibrated.rationale.iter().any(|r| r.starts_with("Calibration:")));
+
+    let mut store = CalibrationStore::default();
+    for _ in 0..15 { store.record(&uncalibrated, false); }
+    let signature = gap_signature(&uncalibrated.gaps);
+    assert_eq!(store.entry(&uncalibrated.strategy, &signature).unwrap().failed, 15);
+
+    let path = std::env::temp_dir().join(format!("ucf_calibration_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(CalibrationStore::load(&path).unwrap(), CalibrationStore::default(), "a missing store is empty");
+    store.save(&path).unwrap();
+    let loaded = CalibrationStore::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, store);
+
+    let calibrated = plan_execution(request(Some(&loaded))).unwrap();
+    assert!(calibrated.confidence < uncalibrated.confidence, "{} vs {}", calibrated.confidence, uncalibrated.confidence);
+    assert!(calibrated.rationale.iter().any(|r| r.starts_with("Calibration: 0/15")));
+}
@@ -0,0 +1,76 @@
+//! Compensation costs are charged before strategies are ranked
+
+use ucf_planner::model::{GameRequirement, PlanScores, PlanningRequest, PolicyProfile};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+fn tetris() -> GameRequirement {
+    serde_json::from_str(r#"{
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]}
+    }"#).unwrap()
+}
+
+fn policy() -> PolicyProfile {
+    PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "compensation".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    }
+}
+
+/// An x86-64 Windows game on Linux: only the runtime gap is open
+fn windows_game() -> GameRequirement {
+    serde_json::from_str(r#"{
+        "artifact_id": "win_game", "targets_original": ["pc_windows_x64"],
+        "cpu": {"required_isa": ["x86_64"]}, "gpu": {"required_apis": ["vulkan"]}, "runtime": {"os_families": ["windows"]}
+    }"#).unwrap()
+}
+
+#[test]
+fn the_compensation_stack_reorders_the_runners_up() {
+    let (game, host, policy) = (windows_game(), pc_linux_x64(), policy());
+    let gaps = analyze_gaps(&game, &host);
+    let weights = ScoreWeights::default();
+    let raw = |s: Strategy| score_strategy(s, &gaps, &policy, &host, false, weights);
+    let charged = |s: Strategy| apply_compensation_costs(raw(s), &default_compensation_map_for(s, &gaps), weights);
+
+    // The runtime shim pays for its shim; emulation and API translation pay
+    // nothing here, as neither the CPU nor the GPU gap is open
+    assert!(raw(Strategy::RuntimeShim).total >= raw(Strategy::Emulate).total);
+    assert!(raw(Strategy::RuntimeShim).total > raw(Strategy::TranslateApi).total);
+    assert!(charged(Strategy::RuntimeShim).total < charged(Strategy::Emulate).total);
+    assert!(charged(Strategy::RuntimeShim).total < charged(Strategy::TranslateApi).total);
+
+    let req = PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    };
+    let ranked = plan_execution_ranked(req).unwrap();
+    let order: Vec<Strategy> = ranked.runners_up.iter().map(|c| c.strategy).collect();
+    assert_eq!(order[0], Strategy::Emulate, "{order:?}");
+    assert!(!order.contains(&Strategy::RuntimeShim), "uncharged, RuntimeShim would be first: {order:?}");
+    for c in &ranked.runners_up { assert_eq!(c.scores.total, charged(c.strategy).total); }
+}
+
+#[test]
+fn the_winner_carries_its_charged_scores_and_a_cost_line() {
+    let (game, host, policy) = (tetris(), pc_linux_x64(), policy());
+    let gaps = analyze_gaps(&game, &host);
+    let weights = ScoreWeights::default();
+    let plan = plan_execution(PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    }).unwrap();
+    assert_eq!(plan.strategy, Strategy::AugmentationRequired.into());
+
+    let map = default_compensation_map_for(Strategy::AugmentationRequired, &gaps);
+    let raw: PlanScores = score_strategy(Strategy::AugmentationRequired, &gaps, &policy, &host, false, weights);
+    let cost = compensation_cost(&map);
+    assert_eq!(plan.scores.total, apply_compensation_costs(raw, &map, weights).total);
+    assert_eq!(plan.scores.engineering_effort, raw.engineering_effort + cost.effort as u8);
+    assert!(plan.scores.total < raw.total);
+    assert!(plan.rationale.iter().any(|l| l == &format!(
+        "Compensation cost: 1 compensation(s) → latency -{}, effort +{}, determinism -{}, friction +{}",
+        cost.latency, cost.effort, cost.determinism, cost.friction
+    )), "{:?}", plan.rationale);
+}
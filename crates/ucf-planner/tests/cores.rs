@@ -0,0 +1,90 @@
+//! Emulator core selection for plans that load a core
+
+use ucf_planner::model::{CompatibilityPlan, EquivalenceLevel, GameRequirement, PlanningRequest, PolicyProfile};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+fn tetris() -> GameRequirement {
+    serde_json::from_str(r#"{
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]},
+        "gpu": {"required_apis": ["dmg_ppu"]}
+    }"#).unwrap()
+}
+
+fn policy() -> PolicyProfile {
+    PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "cores".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    }
+}
+
+fn core(json: &str) -> CoreProfile { serde_json::from_str(json).unwrap() }
+
+fn cores() -> Vec<CoreProfile> {
+    vec![
+        core(r#"{"core_id": "sm83_generic", "abi_version": 3, "platforms": ["gb_pocket_clone"], "isas": ["sm83"]}"#),
+        core(r#"{"core_id": "gb_core", "abi_version": 3, "save_state_version": 1, "platforms": ["gb_dmg", "gb_cgb"],
+                 "accuracy": {"test_pass_rate": 0.9, "verified_equivalence": "L4_RENDER_EQ", "deterministic": true}}"#),
+        core(r#"{"core_id": "nes_core", "abi_version": 3, "platforms": ["nes"], "isas": ["6502"]}"#),
+        core(r#"{"core_id": "fast_gb", "abi_version": 3, "platforms": ["GB_DMG"],
+                 "accuracy": {"test_pass_rate": 0.6, "verified_equivalence": "L1_STABLE"}}"#),
+    ]
+}
+
+#[test]
+fn cores_are_scored_against_the_requirement() {
+    let game = tetris();
+    let score = |i: usize| score_core(&cores()[i], &game, &EquivalenceLevel::L2_INTERACTIVE);
+    let best = score(1);
+    assert_eq!((best.score, best.eligible), (40 + 27 + 20 + 5 + 5, true));
+    assert_eq!(best.reasons[0], "emulates gb_dmg");
+    assert_eq!((score(0).score, score(0).eligible), (20 + 10 + 5, true), "ISA-only match");
+    assert!(!score(2).eligible);
+    let weak = score(3);
+    assert_eq!(weak.score, 40 + 18, "platform ids match case-insensitively; L1 misses the target");
+    assert_eq!(weak.reasons[2], "verified only at L1_STABLE, plan needs L2_INTERACTIVE");
+
+    let selection = select_core(&cores(), &game, &EquivalenceLevel::L2_INTERACTIVE).unwrap();
+    assert_eq!(selection.chosen.as_deref(), Some("gb_core"));
+    let order: Vec<&str> = selection.candidates.iter().map(|c| c.core_id.as_str()).collect();
+    assert_eq!(order, ["gb_core", "fast_gb", "sm83_generic", "nes_core"]);
+    assert_eq!(selection.alternatives().count(), 2);
+
+    assert!(select_core(&[], &game, &EquivalenceLevel::L2_INTERACTIVE).is_none());
+    let none = select_core(&cores()[2..3], &game, &EquivalenceLevel::L2_INTERACTIVE).unwrap();
+    assert_eq!(none.chosen, None);
+}
+
+#[test]
+fn only_plans_that_load_a_core_carry_the_selection() {
+    let (game, host, policy, cores) = (tetris(), pc_linux_x64(), policy(), cores());
+    let req = PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &cores,
+    };
+    let plan = plan_execution(req).unwrap();
+    let loads_core = plan.strategy_pipeline.iter().any(|s| s.id == CORE_STEP);
+    assert_eq!(plan.core_selection.is_some(), loads_core, "{:?}", plan.strategy);
+
+    let mut emulate: CompatibilityPlan = plan.clone();
+    emulate.strategy_pipeline = ["load_emulator_core", "map_bios_rom", "run_emulation_loop", "verify_equivalence"]
+        .iter().map(|id| PipelineStep::for_id(id)).collect();
+    emulate.rationale.clear();
+    emulate.core_selection = None;
+    apply_core_selection(&mut emulate, &cores, &game);
+    assert_eq!(emulate.core_selection.as_ref().unwrap().chosen.as_deref(), Some("gb_core"));
+    assert_eq!(emulate.rationale, ["Core: gb_core (score 97) over fast_gb (58), sm83_generic (35)"]);
+
+    let doc = serde_json::to_value(&emulate).unwrap();
+    assert_eq!(doc["core_selection"]["candidates"][3]["eligible"], false);
+    let back: CompatibilityPlan = serde_json::from_value(doc).unwrap();
+    assert_eq!(back.core_selection, emulate.core_selection);
+
+    let mut untouched = emulate.clone();
+    untouched.strategy_pipeline.remove(0);
+    untouched.core_selection = None;
+    apply_core_selection(&mut untouched, &cores, &game);
+    assert!(untouched.core_selection.is_none());
+}
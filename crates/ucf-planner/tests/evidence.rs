@@ -0,0 +1,96 @@
+//! Run evidence only counts for the artifact and core it is bound to
+
+use gb_core::{sha256_hex, ScoreEntry, Scorecard};
+use ucf_planner::model::{GameRequirement, PlanningRequest, PolicyProfile};
+use ucf_planner::*;
+
+const ROM: &[u8] = b"TETRIS rom image";
+
+fn tetris() -> GameRequirement {
+    serde_json::from_value(serde_json::json!({
+        "artifact_id": "tetris_gb", "rom_sha": sha256_hex(ROM), "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]}
+    })).unwrap()
+}
+
+fn training(binding: &str) -> Evidence {
+    let text = format!(r#"{{"version": "mrom.train.v1", {binding} "total_frames": 900, "frames": [{{"lcdc": 145}}]}}"#);
+    Evidence::from_json("run.json", &text).unwrap()
+}
+
+fn cores() -> Vec<CoreProfile> {
+    ["gb-core", "fast_gb"].iter().map(|id| serde_json::from_value(serde_json::json!({
+        "core_id": id, "abi_version": 3, "platforms": ["gb_dmg"],
+        "accuracy": {"test_pass_rate": if *id == "gb-core" { 0.9 } else { 0.5 }}
+    })).unwrap()).collect()
+}
+
+/// A scorecard written by `letsplay_scorecard` for tetris_gb on `core`
+fn scorecard(core: &str) -> Evidence {
+    let entry = |rom: &str, outcome: &str| ScoreEntry {
+        suite: "cpu_instrs".into(), subsystem: "cpu".into(), rom: rom.into(), outcome: outcome.into(), frames: 400, detail: String::new(),
+    };
+    let card = Scorecard {
+        core: core.into(), artifact_id: None, rom_sha: Some(sha256_hex(ROM)),
+        entries: vec![entry("01.gb", "pass"), entry("02.gb", "pass"), entry("03.gb", "pass"), entry("04.gb", "fail")],
+    };
+    Evidence::from_json("scorecard.json", &card.to_json()).unwrap()
+}
+
+#[test]
+fn evidence_binds_by_artifact_id_or_rom_sha_and_unbound_evidence_is_dropped() {
+    let game = tetris();
+    let by_id = training(r#""artifact_id": "TETRIS_GB","#);
+    let by_sha = training(&format!(r#""rom_sha": "{}","#, sha256_hex(ROM).to_uppercase()));
+    let other = training(r#""artifact_id": "zelda_gb", "rom_sha": "00000000","#);
+    let unbound = training("");
+
+    assert!(by_id.applies_to(&game, None) && by_sha.applies_to(&game, None));
+    assert!(!other.applies_to(&game, None) && !other.is_unbound());
+    assert!(!unbound.applies_to(&game, None) && unbound.is_unbound(), "no binding means no match, not a wildcard");
+
+    let summary = EvidenceSummary::collect(&[by_id, by_sha, other, unbound], &game, None);
+    assert_eq!((summary.runs, summary.total_frames, summary.booted), (2, 1800, true));
+    assert!(EvidenceSummary::collect(&[training("")], &game, None).is_empty());
+}
+
+#[test]
+fn evidence_from_another_core_is_rejected() {
+    let game = tetris();
+    let chosen = chosen_core(&cores(), &game, None);
+    assert_eq!(chosen.as_deref(), Some("gb-core"));
+
+    let own = training(r#""artifact_id": "tetris_gb", "core": "GB-CORE","#);
+    let foreign = training(r#""artifact_id": "tetris_gb", "core": "fast_gb","#);
+    assert!(own.applies_to(&game, chosen.as_deref()));
+    assert_eq!(foreign.rejection(&game, chosen.as_deref()).as_deref(), Some(r#"was run on core "fast_gb", plan uses "gb-core""#));
+    assert!(foreign.applies_to(&game, None), "without a chosen core there is nothing to compare against");
+    assert_eq!(EvidenceSummary::collect(&[own, foreign], &game, chosen.as_deref()).runs, 1);
+}
+
+#[test]
+fn a_real_scorecard_reranks_the_plans_only_on_the_chosen_core() {
+    let game = tetris();
+    let host = pc_linux_x64();
+    let policy = PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "evidence".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    };
+    let cores = cores();
+    let ranked = |evidence: &[Evidence]| {
+        let r = plan_execution_ranked(PlanningRequest {
+            game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence, calibration: None, cores: &cores,
+        }).unwrap();
+        let emulate = r.runners_up.iter().find(|c| c.strategy == Strategy::Emulate).map(|c| c.scores.fidelity);
+        (r.runners_up.iter().map(|c| c.strategy).collect::<Vec<_>>(), emulate)
+    };
+
+    let (baseline, _) = ranked(&[]);
+    assert_eq!(baseline, [Strategy::RuntimeShim, Strategy::TranslateApi]);
+    let (with_card, emulate_fidelity) = ranked(&[scorecard("gb-core")]);
+    assert_eq!(with_card, [Strategy::Emulate, Strategy::EmulatePlusTranslate], "75% pass rate lifts emulation into the top 3");
+    assert!(emulate_fidelity > Some(55));
+    assert_eq!(ranked(&[scorecard("fast_gb")]).0, baseline, "a scorecard from a core the plan does not use changes nothing");
+}
@@ -0,0 +1,80 @@
+//! Reference capability graphs and the examples/capabilities documents
+
+use std::path::PathBuf;
+use ucf_planner::model::{CapabilityGraph, GameRequirement, PlanningRequest, PolicyProfile};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+fn example(platform_id: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples/capabilities").join(format!("{platform_id}.json"));
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+fn tetris() -> GameRequirement {
+    serde_json::from_str(r#"{
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]},
+        "gpu": {"required_apis": ["dmg_ppu"]}
+    }"#).unwrap()
+}
+
+fn policy() -> PolicyProfile {
+    PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "fixtures".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    }
+}
+
+#[test]
+fn example_documents_match_the_constructors() {
+    let profiles = reference_profiles();
+    let ids: Vec<&str> = profiles.iter().map(|g| g.platform_id.as_str()).collect();
+    assert_eq!(ids, REFERENCE_PLATFORM_IDS);
+    for graph in &profiles {
+        let doc: CapabilityGraph = parse_document(&example(&graph.platform_id), Strictness::DenyUnknownFields)
+            .unwrap_or_else(|e| panic!("{}: {e}", graph.platform_id));
+        assert_eq!(
+            serde_json::to_value(&doc).unwrap(), serde_json::to_value(graph).unwrap(),
+            "examples/capabilities/{}.json is stale: ucf-planner capabilities --write examples/capabilities", graph.platform_id,
+        );
+        assert!(!graph.label.is_empty());
+        assert_eq!(graph.profiles.source, REFERENCE_SOURCE);
+    }
+    assert_eq!(reference_profile("raspberry_pi_4").unwrap().cpu.isas, ["arm64"]);
+    assert!(reference_profile("ps5").is_none());
+}
+
+#[test]
+fn new_fills_in_the_documented_defaults() {
+    let built = CapabilityGraph::new("min_pc", "linux", &["x86_64"], 8192);
+    let parsed: CapabilityGraph = serde_json::from_str(r#"{
+        "platform_id": "min_pc", "host_os": {"family": "linux"}, "cpu": {"isas": ["x86_64"]}, "memory": {"ram_mb": 8192}
+    }"#).unwrap();
+    assert_eq!(serde_json::to_value(&built).unwrap(), serde_json::to_value(&parsed).unwrap());
+
+    let tuned = built.with_cpu(0, 0, 3000.0, &["avx2"]).with_timing(&[60.0, 120.0], 0, "preemptive_os");
+    assert_eq!((tuned.cpu.cores, tuned.cpu.threads), (1, 1), "clamped to the schema minimum");
+    assert_eq!(tuned.timing.timer_resolution_us, 1);
+    assert!(!tuned.io.network.available);
+    assert!(tuned.with_network(100.0, 30.0, 5.0).io.network.available);
+}
+
+#[test]
+fn reference_profiles_place_a_game_boy_game() {
+    let game = tetris();
+    for native in [gb_dmg(), gb_cgb()] {
+        let gaps = analyze_gaps(&game, &native);
+        assert_eq!(gaps.hardest(), GapSeverity::None, "{}: {gaps:?}", native.platform_id);
+    }
+    for host in [pc_windows_x64(), pc_linux_x64(), mac_arm64(), android_phone_arm64(), raspberry_pi_4()] {
+        let gaps = analyze_gaps(&game, &host);
+        assert_eq!((gaps.cpu.severity, gaps.runtime.severity), (GapSeverity::Hard, GapSeverity::Hard), "{}", host.platform_id);
+        assert_eq!(gaps.memory.severity, GapSeverity::None);
+        // Every host plans without erroring
+        let policy = policy();
+        let req = PlanningRequest { game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[] };
+        assert_eq!(plan_execution(req).unwrap().target_platform_id, host.platform_id);
+    }
+}
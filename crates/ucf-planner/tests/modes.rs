@@ -0,0 +1,128 @@
+//! Per-mode planning: each fidelity mode is gated on its own equivalence and
+//! the most demanding mode that fits is recommended
+
+use ucf_planner::model::{CapabilityGraph, EquivalenceLevel, GameRequirement, PlanningRequest, PolicyProfile, StrategyClass};
+use ucf_planner::*;
+
+/// The PS2 example (modes gameplay at L3 and archival at L5) plus a casual L1
+/// mode with a latency budget override
+fn ps2() -> GameRequirement {
+    let mut doc: serde_json::Value = serde_json::from_str(include_str!("../../../examples/ps2_to_pc_req.json")).unwrap();
+    doc["fidelity_modes"].as_array_mut().unwrap().push(serde_json::json!({
+        "mode_id": "casual", "priority": "casual", "acceptable_equivalence_min": "L1_STABLE",
+        "split_execution": {"latency_budget_ms_override": 120.0}
+    }));
+    serde_json::from_value(doc).unwrap()
+}
+
+fn tetris() -> GameRequirement {
+    serde_json::from_str(r#"{
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]},
+        "fidelity_modes": [
+            {"mode_id": "casual", "priority": "casual", "acceptable_equivalence_min": "L1_STABLE"},
+            {"mode_id": "archival", "priority": "archival", "acceptable_equivalence_min": "L5_BIT_EXACT"},
+            {"mode_id": "gameplay", "priority": "gameplay", "acceptable_equivalence_min": "L3_GAMEPLAY_EQ"}
+        ]
+    }"#).unwrap()
+}
+
+fn policy(min_fidelity_score: u8) -> PolicyProfile {
+    PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "modes".into(),
+        latency_budget_ms: 60.0, min_fidelity_score, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    }
+}
+
+fn plan_modes(game: &GameRequirement, host: &CapabilityGraph, policy: &PolicyProfile) -> AllModesPlan {
+    plan_all_modes(PlanningRequest {
+        game, target: host, helpers: &[], policy, mode_id: Some("ignored"), evidence: &[], calibration: None, cores: &[],
+    }).unwrap()
+}
+
+fn mode<'a>(all: &'a AllModesPlan, id: &str) -> &'a ModePlan {
+    all.plans.iter().find(|m| m.mode_id.as_deref() == Some(id)).expect("a plan per declared mode")
+}
+
+#[test]
+fn each_mode_is_gated_on_its_own_equivalence() {
+    let all = plan_modes(&ps2(), &pc_linux_x64(), &policy(40));
+    let ids: Vec<_> = all.plans.iter().map(|m| m.mode_id.as_deref().unwrap()).collect();
+    assert_eq!(ids, ["gameplay", "archival", "casual"], "one plan per mode, in declaration order");
+
+    // Augmentation reaches L3: enough for gameplay and casual, not for archival
+    let gameplay = mode(&all, "gameplay");
+    assert_eq!(gameplay.plan.strategy, StrategyClass::AugmentationRequired);
+    assert_eq!(gameplay.plan.verification_target.equivalence_min, EquivalenceLevel::L3_GAMEPLAY_EQ);
+    assert_eq!(gameplay.priority.as_deref(), Some("gameplay"));
+    assert!(gameplay.fits);
+    assert!(gameplay.plan.rationale.iter().any(|l| l == "Mode 'gameplay' rollback window: target=4 max=8 frames"), "{:?}", gameplay.plan.rationale);
+
+    let archival = mode(&all, "archival");
+    assert_eq!(archival.plan.strategy, StrategyClass::AugmentationRequired);
+    assert_eq!(archival.plan.verification_target.equivalence_min, EquivalenceLevel::L5_BIT_EXACT);
+    assert!(!archival.fits, "AugmentationRequired cannot deliver L5");
+    assert!(archival.plan.rationale.iter().any(|l| l == "Mode 'archival' rollback window: target=0 max=4 frames"), "{:?}", archival.plan.rationale);
+
+    let casual = mode(&all, "casual");
+    assert_eq!(casual.plan.verification_target.equivalence_min, EquivalenceLevel::L1_STABLE);
+    assert!(casual.fits);
+    assert!(casual.plan.rationale.iter().any(|l| l == "Mode 'casual' latency budget override: 120ms"), "{:?}", casual.plan.rationale);
+
+    // Both gameplay and casual fit; gameplay asks for more, so it wins
+    assert_eq!(all.recommended_mode.as_deref(), Some("gameplay"));
+    assert_eq!(all.recommendation.len(), 2, "{:?}", all.recommendation);
+    assert!(all.recommendation[0].starts_with("Recommended mode: gameplay (L3_GAMEPLAY_EQ via AugmentationRequired, confidence "), "{:?}", all.recommendation);
+    assert_eq!(all.recommendation[1], format!(
+        "Mode archival does not fit: AugmentationRequired reaches at most L3_GAMEPLAY_EQ (needs L5_BIT_EXACT), fidelity {} (policy min 40)",
+        archival.plan.scores.fidelity
+    ));
+}
+
+#[test]
+fn declaration_order_does_not_change_the_recommendation() {
+    // casual is declared first, archival second; neither wins
+    let all = plan_modes(&tetris(), &gb_dmg(), &policy(40));
+    let fits: Vec<_> = all.plans.iter().map(|m| (m.mode_id.as_deref().unwrap(), m.fits)).collect();
+    assert_eq!(fits, [("casual", true), ("archival", false), ("gameplay", true)]);
+    assert_eq!(all.recommended_mode.as_deref(), Some("gameplay"));
+}
+
+#[test]
+fn the_fidelity_floor_depends_on_the_target() {
+    // Augmentation scores fidelity 80 on the game's own hardware and 55 on a PC
+    let game = tetris();
+    let native = plan_modes(&game, &gb_dmg(), &policy(60));
+    assert_eq!(native.recommended_mode.as_deref(), Some("gameplay"));
+    assert!(mode(&native, "casual").fits);
+
+    let pc = plan_modes(&game, &pc_linux_x64(), &policy(60));
+    assert!(pc.plans.iter().all(|m| !m.fits), "fidelity {} is under 60", mode(&pc, "casual").plan.scores.fidelity);
+    assert_eq!(pc.recommended_mode, None);
+}
+
+#[test]
+fn min_fidelity_score_gates_every_mode() {
+    let all = plan_modes(&ps2(), &pc_linux_x64(), &policy(90));
+    assert!(all.plans.iter().all(|m| !m.fits));
+    assert_eq!(all.recommended_mode, None);
+    assert_eq!(all.recommendation[0], "No declared mode fits this target and policy");
+    assert_eq!(all.recommendation.len(), 4, "one line per mode that does not fit");
+    assert!(all.recommendation[1].starts_with("Mode gameplay does not fit: AugmentationRequired reaches at most L3_GAMEPLAY_EQ (needs L3_GAMEPLAY_EQ)"));
+    assert!(all.recommendation[1].ends_with("(policy min 90)"));
+}
+
+#[test]
+fn a_game_without_modes_gets_one_default_plan() {
+    let mut game = tetris();
+    game.fidelity_modes.clear();
+    let all = plan_modes(&game, &gb_dmg(), &policy(40));
+    assert_eq!(all.plans.len(), 1);
+    assert_eq!((all.plans[0].mode_id.as_deref(), all.plans[0].priority.as_deref()), (None, None));
+    assert!(all.plans[0].fits);
+    assert_eq!(all.plans[0].plan.verification_target.equivalence_min, EquivalenceLevel::L2_INTERACTIVE);
+    assert_eq!(all.recommended_mode, None, "the default plan has no mode id to recommend");
+    assert!(all.recommendation[0].starts_with("Recommended mode: default (L2_INTERACTIVE via AugmentationRequired"), "{:?}", all.recommendation);
+}
@@ -0,0 +1,104 @@
+//! Strategy pipelines end to end: planned steps run in dataflow order, and
+//! an orchestrator walking them stops at the first blocked step
+
+use ucf_planner::model::{CompatibilityPlan, GameRequirement, PlanningRequest, PolicyProfile, StrategyClass};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+fn tetris() -> GameRequirement {
+    serde_json::from_str(r#"{
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]}
+    }"#).unwrap()
+}
+
+fn ps2() -> GameRequirement {
+    serde_json::from_str(include_str!("../../../examples/ps2_to_pc_req.json")).unwrap()
+}
+
+fn policy(max_legal_risk: u8) -> PolicyProfile {
+    PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "pipeline".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    }
+}
+
+/// Walk `steps` the way the host does, starting with only the artifact:
+/// the ids run, and the first step whose inputs are missing, if any
+fn walk(steps: &[PipelineStep]) -> (Vec<&str>, Option<&str>) {
+    let mut available = vec!["artifact"];
+    let mut ran = vec![];
+    for step in steps {
+        if !step.requires.iter().all(|r| available.contains(&r.as_str())) { return (ran, Some(&step.id)); }
+        available.extend(step.produces.iter().map(String::as_str));
+        ran.push(step.id.as_str());
+    }
+    (ran, None)
+}
+
+#[test]
+fn every_planned_pipeline_runs_in_order() {
+    let policy = policy(100);
+    let mut seen = vec![];
+    for (game, host) in [(tetris(), gb_dmg()), (tetris(), pc_linux_x64()), (ps2(), pc_linux_x64())] {
+        let ranked = plan_execution_ranked(PlanningRequest {
+            game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+        }).unwrap();
+        let (ran, blocked) = walk(&ranked.winner.strategy_pipeline);
+        assert_eq!(blocked, None, "{:?}", ranked.winner.strategy);
+        assert_eq!(ran, ranked.winner.pipeline_ids());
+        for c in &ranked.runners_up {
+            let (ran, blocked) = walk(&c.pipeline);
+            assert_eq!(blocked, None, "{:?}", c.strategy);
+            assert_eq!(ran.len(), c.pipeline.len());
+            assert_eq!(ran.last(), Some(&"verify_equivalence"), "{:?} verifies last", c.strategy);
+            seen.push((c.strategy, ran.join(" > ")));
+        }
+    }
+    let order = |s: Strategy| seen.iter().find(|(x, _)| *x == s).map(|(_, o)| o.as_str()).unwrap();
+    assert_eq!(order(Strategy::Emulate), "load_emulator_core > map_bios_rom > run_emulation_loop > verify_equivalence");
+    assert_eq!(order(Strategy::RuntimeShim), "detect_runtime_mismatches > inject_shims > verify_equivalence");
+    assert_eq!(order(Strategy::TranslateApi), "api_translation_layer > shader_transpile > verify_equivalence");
+    assert_eq!(order(Strategy::NativeBc), "native_bc_check > verify_equivalence");
+}
+
+#[test]
+fn a_blocked_plan_short_circuits_to_its_feasibility_report() {
+    let (game, host, policy) = (tetris(), pc_linux_x64(), policy(0));
+    let plan = plan_execution(PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    }).unwrap();
+    assert_eq!(plan.strategy, StrategyClass::NotFeasible);
+    assert_eq!(plan.pipeline_ids(), ["feasibility_report"], "no execution stage is planned");
+    let step = &plan.strategy_pipeline[0];
+    assert!(step.requires.is_empty());
+    assert_eq!((step.owner, step.estimated_duration_ms), (StepOwner::Planner, Some(0)));
+    assert_eq!(walk(&plan.strategy_pipeline), (vec!["feasibility_report"], None));
+}
+
+#[test]
+fn a_step_out_of_order_blocks_the_walk_there() {
+    let (game, host, policy) = (tetris(), gb_dmg(), policy(100));
+    let plan = plan_execution(PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    }).unwrap();
+
+    // A legacy plan lists bare ids; the catalog restores what each needs.
+    // Hardware probing before its spec exists stops everything after it
+    let mut doc = serde_json::to_value(&plan).unwrap();
+    doc["strategy_pipeline"] = serde_json::json!(["hardware_probe", "augmentation_spec", "verify_equivalence"]);
+    let swapped: CompatibilityPlan = serde_json::from_value(doc.clone()).unwrap();
+    assert_eq!(walk(&swapped.strategy_pipeline), (vec![], Some("hardware_probe")));
+
+    // Steps the catalog does not know are manual and need nothing
+    doc["strategy_pipeline"] = serde_json::json!(["augmentation_spec", "solder_cartridge", "hardware_probe"]);
+    let manual: CompatibilityPlan = serde_json::from_value(doc).unwrap();
+    assert_eq!(manual.strategy_pipeline[1].owner, StepOwner::Manual);
+    assert_eq!(walk(&manual.strategy_pipeline), (vec!["augmentation_spec", "solder_cartridge", "hardware_probe"], None));
+
+    // The typed rendering round-trips unchanged
+    let back: CompatibilityPlan = serde_json::from_value(serde_json::to_value(&plan).unwrap()).unwrap();
+    assert_eq!(back.strategy_pipeline, plan.strategy_pipeline);
+}
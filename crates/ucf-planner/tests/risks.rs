@@ -0,0 +1,105 @@
+//! Legal risk scoring, the max_legal_risk gate and the risk register
+
+use ucf_planner::model::{CapabilityGraph, CompatibilityPlan, GameRequirement, PlanningRequest, PolicyProfile, StrategyClass};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+/// Tetris, optionally with unknown DRM (a soft legal gap)
+fn tetris(drm_unknown: bool) -> GameRequirement {
+    let mut doc = serde_json::json!({
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]}
+    });
+    if drm_unknown { doc["runtime"]["drm"] = "unknown".into(); }
+    serde_json::from_value(doc).unwrap()
+}
+
+fn policy(max_legal_risk: u8) -> PolicyProfile {
+    PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "risks".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    }
+}
+
+fn plan_both(game: &GameRequirement, host: &CapabilityGraph, max_legal_risk: u8) -> (CompatibilityPlan, RankedPlans) {
+    let policy = policy(max_legal_risk);
+    let req = || PlanningRequest {
+        game, target: host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    };
+    (plan_execution(req()).unwrap(), plan_execution_ranked(req()).unwrap())
+}
+
+fn codes(register: &RiskRegister) -> Vec<(&str, RiskCategory, GapSeverity, bool)> {
+    register.risks.iter().map(|r| (r.code.as_str(), r.category, r.severity, r.deciding)).collect()
+}
+
+#[test]
+fn legal_risk_adds_up_the_legal_and_runtime_gaps() {
+    // 10 base, +20 per soft and +40 per hard legal / runtime gap
+    for (game, host, expected) in [
+        (tetris(false), gb_dmg(), 10),
+        (tetris(true), gb_dmg(), 30),
+        (tetris(false), pc_linux_x64(), 50),
+        (tetris(true), pc_linux_x64(), 70),
+    ] {
+        let (plan, ranked) = plan_both(&game, &host, 100);
+        assert_eq!((plan.scores.legal_risk, plan.risks.legal_risk), (expected, expected), "{} on {}", game.runtime.drm.is_some(), host.platform_id);
+        assert!(ranked.runners_up.iter().all(|c| c.scores.legal_risk == expected));
+    }
+
+    let (plan, _) = plan_both(&tetris(true), &pc_linux_x64(), 100);
+    assert_eq!(codes(&plan.risks), [
+        ("DRM_UNKNOWN", RiskCategory::Drm, GapSeverity::Soft, false),
+        ("OS_FAMILY_MISMATCH", RiskCategory::Runtime, GapSeverity::Hard, false),
+    ]);
+    assert!(!plan.risks.max_legal_risk_deciding);
+}
+
+#[test]
+fn max_legal_risk_is_inclusive() {
+    let (game, host) = (tetris(true), pc_linux_x64());
+    let (at_limit, ranked) = plan_both(&game, &host, 70);
+    assert_ne!(at_limit.strategy, StrategyClass::NotFeasible, "legal_risk == max_legal_risk is allowed");
+    assert_ne!(ranked.winner.strategy, StrategyClass::NotFeasible);
+    assert!(!at_limit.risks.max_legal_risk_deciding);
+
+    let (over, ranked) = plan_both(&game, &host, 69);
+    assert_eq!(over.strategy, StrategyClass::NotFeasible);
+    assert_eq!(ranked.winner.strategy, StrategyClass::NotFeasible);
+    assert!(ranked.runners_up.is_empty(), "legal risk is the same for every strategy, so all are gated");
+    assert_eq!((over.risks.legal_risk, over.risks.max_legal_risk), (70, 69), "the register keeps the considered score, not NotFeasible's 100");
+    assert!(over.risks.max_legal_risk_deciding && ranked.winner.risks.max_legal_risk_deciding);
+    // Without either gap the risk (50 or 30) would be within 69
+    assert!(over.risks.risks.iter().all(|r| r.deciding));
+}
+
+#[test]
+fn a_risk_is_deciding_only_if_dropping_it_gets_under_the_limit() {
+    let (game, host) = (tetris(true), pc_linux_x64());
+    // 70 - 20 (soft DRM) = 50 is over 49, 70 - 40 (hard OS) = 30 is within it
+    let (plan, _) = plan_both(&game, &host, 49);
+    assert_eq!(codes(&plan.risks), [
+        ("DRM_UNKNOWN", RiskCategory::Drm, GapSeverity::Soft, false),
+        ("OS_FAMILY_MISMATCH", RiskCategory::Runtime, GapSeverity::Hard, true),
+    ]);
+    // Exactly at the boundary: 70 - 20 = 50 <= 50
+    let (plan, _) = plan_both(&game, &host, 50);
+    assert!(plan.risks.risks.iter().all(|r| r.deciding));
+    // Nothing helps below 30
+    let (plan, _) = plan_both(&game, &host, 29);
+    assert!(plan.risks.max_legal_risk_deciding && plan.risks.risks.iter().all(|r| !r.deciding));
+}
+
+#[test]
+fn legal_risk_lowers_every_total_without_reordering() {
+    let host = pc_linux_x64();
+    let (clean, clean_ranked) = plan_both(&tetris(false), &host, 100);
+    let (risky, risky_ranked) = plan_both(&tetris(true), &host, 100);
+    assert_eq!(risky.strategy, clean.strategy);
+    assert!(risky.scores.total < clean.scores.total, "{} vs {}", risky.scores.total, clean.scores.total);
+    let order = |r: &RankedPlans| r.runners_up.iter().map(|c| c.strategy).collect::<Vec<_>>();
+    assert_eq!(order(&risky_ranked), order(&clean_ranked));
+    for (a, b) in risky_ranked.runners_up.iter().zip(&clean_ranked.runners_up) { assert!(a.scores.total < b.scores.total); }
+}
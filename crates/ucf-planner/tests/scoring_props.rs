@@ -0,0 +1,151 @@
+//! Scoring and gating invariants (proptest)
+
+use proptest::prelude::{any, prop, prop_assert, prop_assert_eq, prop_oneof, proptest, Just};
+use proptest::strategy::Strategy as Gen;
+use ucf_planner::model::{CapabilityGraph, PlanScores, PolicyProfile};
+use ucf_planner::planner::gate_blocks;
+use ucf_planner::*;
+
+const STRATEGIES: [Strategy; 10] = [
+    Strategy::NativeBc, Strategy::Emulate, Strategy::TranslateApi, Strategy::RuntimeShim,
+    Strategy::EmulatePlusTranslate, Strategy::DownportRequired, Strategy::StreamingRecommended,
+    Strategy::SplitExecutionRecommended, Strategy::AugmentationRequired, Strategy::NotFeasible,
+];
+const SEVERITIES: [GapSeverity; 3] = [GapSeverity::None, GapSeverity::Soft, GapSeverity::Hard];
+/// Reason codes the compensation map keys on
+const LEGAL_CODES: [&str; 2] = ["USER_SUPPLIED_FIRMWARE_REQUIRED", "DRM_UNKNOWN"];
+
+fn target() -> CapabilityGraph {
+    serde_json::from_str(r#"{
+        "capability_version": "0.1", "platform_id": "props_pc",
+        "host_os": {"family": "linux"}, "cpu": {"isas": ["x86_64"]}, "memory": {"ram_mb": 8192}
+    }"#).unwrap()
+}
+
+fn status(sev: GapSeverity, code: &str) -> GapStatus {
+    match sev {
+        GapSeverity::None => GapStatus::none(),
+        GapSeverity::Soft => GapStatus::soft(GapReason::new(code)),
+        GapSeverity::Hard => GapStatus::hard(GapReason::new(code)),
+    }
+}
+
+fn gaps(sev: &[GapSeverity; 7], legal_code: &str) -> GapVector {
+    GapVector {
+        cpu: status(sev[0], "PROP"), gpu: status(sev[1], "PROP"), memory: status(sev[2], "PROP"),
+        runtime: status(sev[3], "PROP"), io: status(sev[4], "PROP"), timing: status(sev[5], "PROP"),
+        legal: status(sev[6], legal_code),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Policy {
+    flags: [bool; 4],
+    max_legal_risk: u8,
+    prefer_local_execution: bool,
+}
+
+impl Policy {
+    fn profile(&self) -> PolicyProfile {
+        PolicyProfile {
+            policy_version: "0.1".into(), profile_id: "props".into(),
+            latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: self.max_legal_risk,
+            prefer_local_execution: self.prefer_local_execution,
+            allow_streaming: self.flags[0], allow_split_execution: self.flags[1],
+            allow_downport_classification: self.flags[2], allow_unverified_plans: self.flags[3],
+        }
+    }
+}
+
+fn severities() -> impl Gen<Value = [GapSeverity; 7]> {
+    prop::array::uniform7(prop::sample::select(SEVERITIES.to_vec()))
+}
+
+fn policies() -> impl Gen<Value = Policy> {
+    (prop::array::uniform4(any::<bool>()), any::<u8>(), any::<bool>())
+        .prop_map(|(flags, max_legal_risk, prefer_local_execution)| Policy { flags, max_legal_risk, prefer_local_execution })
+}
+
+fn weights() -> impl Gen<Value = ScoreWeights> {
+    let w = prop_oneof![Just(0u16), Just(u16::MAX), any::<u16>()];
+    prop::array::uniform7(w).prop_map(|w| ScoreWeights {
+        fidelity: w[0], latency: w[1], engineering_effort_inverted: w[2], runtime_cost_inverted: w[3],
+        legal_risk_inverted: w[4], determinism: w[5], user_friction_inverted: w[6],
+    })
+}
+
+/// The score the planner ranks on: strategy axes plus the compensation stack
+fn planner_score(s: Strategy, g: &GapVector, policy: &PolicyProfile, helper: bool, w: ScoreWeights) -> PlanScores {
+    let scores = score_strategy(s, g, policy, &target(), helper, w);
+    apply_compensation_costs(scores, &default_compensation_map_for(s, g), w)
+}
+
+fn all_axes(s: &PlanScores) -> [u8; 8] {
+    [s.fidelity, s.latency, s.engineering_effort, s.runtime_cost, s.legal_risk, s.determinism, s.user_friction, s.total]
+}
+
+proptest! {
+    #[test]
+    fn worsening_one_gap_never_raises_the_total(
+        mut sev in severities(), which in 0usize..7, from in 0usize..2, policy in policies(), helper in any::<bool>(),
+        w in weights(), legal_code in prop::sample::select(LEGAL_CODES.to_vec()),
+    ) {
+        sev[which] = SEVERITIES[from];
+        let mut worse = sev;
+        worse[which] = SEVERITIES[from + 1];
+        let (before, after) = (gaps(&sev, legal_code), gaps(&worse, legal_code));
+        let policy = policy.profile();
+        for s in STRATEGIES {
+            let (a, b) = (score_strategy(s, &before, &policy, &target(), helper, w), score_strategy(s, &after, &policy, &target(), helper, w));
+            prop_assert!(b.total <= a.total, "{s:?} strategy score rose {} -> {} when gap {which} worsened", a.total, b.total);
+            let (a, b) = (planner_score(s, &before, &policy, helper, w), planner_score(s, &after, &policy, helper, w));
+            prop_assert!(b.total <= a.total, "{s:?} planner score rose {} -> {} when gap {which} worsened", a.total, b.total);
+        }
+    }
+
+    #[test]
+    fn enabling_a_permission_never_removes_a_strategy(
+        sev in severities(), policy in policies(), flag in 0usize..5, raise in 1u8..=255,
+        legal_code in prop::sample::select(LEGAL_CODES.to_vec()),
+    ) {
+        let g = gaps(&sev, legal_code);
+        let mut looser = policy.clone();
+        match flag {
+            4 => looser.max_legal_risk = policy.max_legal_risk.saturating_add(raise),
+            f => looser.flags[f] = true,
+        }
+        let (strict, loose) = (policy.profile(), looser.profile());
+        for s in STRATEGIES {
+            let w = ScoreWeights::default();
+            let strict_scores = planner_score(s, &g, &strict, false, w);
+            let loose_scores = planner_score(s, &g, &loose, false, w);
+            if gate_blocks(s, &strict_scores, &g, &strict).is_empty() {
+                prop_assert!(gate_blocks(s, &loose_scores, &g, &loose).is_empty(), "{s:?} lost by enabling permission {flag}");
+            }
+        }
+    }
+
+    #[test]
+    fn scores_stay_in_range_for_extreme_weights(
+        sev in severities(), policy in policies(), helper in any::<bool>(), w in weights(),
+        legal_code in prop::sample::select(LEGAL_CODES.to_vec()),
+    ) {
+        let g = gaps(&sev, legal_code);
+        let policy = policy.profile();
+        for s in STRATEGIES {
+            for scores in [score_strategy(s, &g, &policy, &target(), helper, w), planner_score(s, &g, &policy, helper, w)] {
+                prop_assert!(all_axes(&scores).iter().all(|&v| v <= 100), "{s:?} {scores:?}");
+                prop_assert_eq!(scores.total, total_score(&scores, w));
+            }
+        }
+    }
+}
+
+#[test]
+fn all_zero_weights_score_zero() {
+    let zero = ScoreWeights {
+        fidelity: 0, latency: 0, engineering_effort_inverted: 0, runtime_cost_inverted: 0,
+        legal_risk_inverted: 0, determinism: 0, user_friction_inverted: 0,
+    };
+    assert_eq!(total_score(&PlanScores::default(), zero), 0);
+}
@@ -0,0 +1,91 @@
+//! Telemetry from one run of a plan: what is recorded and what re-plans
+
+use ucf_planner::model::{CapabilityGraph, GameRequirement, PlanningRequest, PolicyProfile};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+fn tetris() -> GameRequirement {
+    serde_json::from_str(r#"{
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]},
+        "timing": {"target_fps": 59.7}
+    }"#).unwrap()
+}
+
+fn policy() -> PolicyProfile {
+    PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "telemetry".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    }
+}
+
+fn sample(plan_id: &str, frames: u64, fps: f64, rtt: f64, dropped: u64, latency: f64) -> TelemetrySample {
+    TelemetrySample {
+        plan_id: Some(plan_id.into()), frames, achieved_fps: Some(fps), rtt_ms: Some(rtt), dropped_frames: dropped,
+        input_latency_ms: Some(latency),
+    }
+}
+
+#[test]
+fn one_run_is_recorded_as_a_frame_weighted_observed_profile() {
+    let (game, policy) = (tetris(), policy());
+    let mut host = pc_linux_x64();
+    let plan = plan_execution(PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    }).unwrap();
+
+    ingest_telemetry(&mut host, &sample(&plan.plan_id, 600, 60.0, 10.0, 0, 30.0));
+    ingest_telemetry(&mut host, &TelemetrySample { frames: 200, achieved_fps: Some(58.0), ..Default::default() });
+    let observed = ingest_telemetry(&mut host, &sample(&plan.plan_id, 200, 56.0, 40.0, 2, 50.0)).clone();
+
+    assert_eq!((observed.samples, observed.frames, observed.dropped_frames), (3, 1000, 2));
+    assert_eq!(observed.achieved_fps, Some((600.0 * 60.0 + 200.0 * 58.0 + 200.0 * 56.0) / 1000.0));
+    assert_eq!(observed.rtt_ms, Some((600.0 * 10.0 + 200.0 * 40.0) / 800.0), "only samples that report RTT weigh in");
+    assert_eq!(observed.input_latency_ms, Some((600.0 * 30.0 + 200.0 * 50.0) / 800.0));
+    assert_eq!(observed.last_plan_id.as_deref(), Some(plan.plan_id.as_str()), "a sample without a plan id keeps the last one");
+    assert!(host.profiles.measured);
+    assert_eq!(host.profiles.source, TELEMETRY_SOURCE);
+    assert_eq!(host.io.network.rtt_ms, observed.rtt_ms);
+
+    // The record survives the capability graph's JSON round trip
+    let doc = serde_json::to_value(&host).unwrap();
+    assert_eq!(doc["profiles"]["observed"]["frames"], 1000);
+    let back: CapabilityGraph = serde_json::from_value(doc).unwrap();
+    assert_eq!(back.profiles.observed.as_ref(), Some(&observed));
+
+    // Within every assumption: nothing to re-plan
+    let outcome = replan_on_telemetry(&plan, PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    }).unwrap();
+    assert!(outcome.violations.is_empty() && outcome.replan.is_none(), "{:?}", outcome.violations);
+}
+
+#[test]
+fn broken_assumptions_are_reported_and_re_planned() {
+    let (game, policy) = (tetris(), policy());
+    let mut host = pc_linux_x64();
+    let plan = plan_execution(PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    }).unwrap();
+    ingest_telemetry(&mut host, &sample(&plan.plan_id, 1000, 50.0, 80.0, 30, 70.0));
+
+    let outcome = replan_on_telemetry(&plan, PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    }).unwrap();
+    let kinds: Vec<Assumption> = outcome.violations.iter().map(|v| v.assumption).collect();
+    assert_eq!(kinds, [Assumption::Fps, Assumption::Rtt, Assumption::InputLatency, Assumption::DroppedFrames]);
+    assert_eq!(outcome.violations[0].describe(), "achieved 50.0 fps, plan assumes 59.7");
+    assert_eq!(outcome.violations[3].describe(), "dropped 3.0% of frames, plan allows 1.0%");
+
+    let replan = outcome.replan.expect("a violation re-plans");
+    assert_ne!(replan.plan_id, plan.plan_id);
+    let line = replan.rationale.last().unwrap();
+    assert!(line.starts_with(&format!("Re-planned from telemetry (replaces {}): achieved 50.0 fps", plan.plan_id)), "{line}");
+    assert_eq!(replan.gaps.timing_gap, "soft", "the observed shortfall is now a timing gap");
+    assert!(replan.rationale.iter().any(|l| l.starts_with("Timing gap") && l.contains("MEASURED_FPS_BELOW_TARGET")), "{:?}", replan.rationale);
+
+    let doc = serde_json::to_value(&outcome.violations).unwrap();
+    assert_eq!(doc[1], serde_json::json!({"assumption": "Rtt", "expected": 60.0, "observed": 80.0}));
+}
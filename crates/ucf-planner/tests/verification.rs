@@ -0,0 +1,82 @@
+//! Checkpoint reports graded against the plan's verification target
+
+use ucf_planner::model::{EquivalenceLevel, GameRequirement, PlanningRequest, PolicyProfile};
+use ucf_planner::planner::plan_execution;
+use ucf_planner::*;
+
+fn plan() -> ucf_planner::model::CompatibilityPlan {
+    let game: GameRequirement = serde_json::from_str(r#"{
+        "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]},
+        "fidelity_modes": [{"mode_id": "default", "priority": "balanced", "acceptable_equivalence_min": "L2_INTERACTIVE", "split_execution": null}]
+    }"#).unwrap();
+    let policy = PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "verification".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    };
+    let host = pc_linux_x64();
+    plan_execution(PlanningRequest {
+        game: &game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    }).unwrap()
+}
+
+fn report(results: &[(&str, &str, bool)]) -> CheckpointReport {
+    let results: Vec<serde_json::Value> = results.iter().map(|(id, level, passed)| serde_json::json!({
+        "id": id, "kind": "pc", "level": level, "within_frames": 10, "passed": passed,
+        "frame": if *passed { Some(3) } else { None }, "t_cycles": null,
+        "detail": if *passed { "PC reached 0150 at frame 3" } else { "not reached within 10 frames" },
+        "state_hash": null, "state": null,
+    })).collect();
+    serde_json::from_value(serde_json::json!({
+        "version": "mrom.checkpoints.v1", "frames_run": 10, "passed": 0, "total": results.len(),
+        "achieved_level": null, "results": results,
+    })).unwrap()
+}
+
+#[test]
+fn the_achieved_level_is_the_highest_unbroken_one() {
+    let plan = plan();
+    assert_eq!(plan.verification_target.equivalence_min, EquivalenceLevel::L2_INTERACTIVE);
+
+    let met = evaluate_verification(&plan, &report(&[
+        ("boot", "L0_BOOT", true), ("video", "L1_STABLE", true), ("title", "L2_INTERACTIVE", true), ("ending", "L3_GAMEPLAY_EQ", false),
+    ])).unwrap();
+    assert_eq!((met.achieved, met.met), (Some(EquivalenceLevel::L2_INTERACTIVE), true));
+    assert_eq!(met.failed, ["ending (L3_GAMEPLAY_EQ): not reached within 10 frames"]);
+    assert_eq!(met.summary, "3/4 checkpoint(s) passed; achieved L2_INTERACTIVE, target L2_INTERACTIVE");
+
+    // A failure below the target caps the level, whatever passed above it
+    let capped = evaluate_verification(&plan, &report(&[
+        ("boot", "L0_BOOT", true), ("video", "L1_STABLE", false), ("title", "L2_INTERACTIVE", true),
+    ])).unwrap();
+    assert_eq!((capped.achieved, capped.met), (Some(EquivalenceLevel::L0_BOOT), false));
+
+    // Checkpoints that stop short of the target never meet it
+    let short = evaluate_verification(&plan, &report(&[("boot", "L0_BOOT", true)])).unwrap();
+    assert_eq!((short.achieved, short.met), (Some(EquivalenceLevel::L0_BOOT), false));
+    let none = evaluate_verification(&plan, &report(&[("boot", "L0_BOOT", false)])).unwrap();
+    assert_eq!((none.achieved, none.met), (None, false));
+
+    let mut old = report(&[]);
+    old.version = "mrom.checkpoints.v0".into();
+    assert!(evaluate_verification(&plan, &old).unwrap_err().contains("mrom.checkpoints.v0"));
+}
+
+#[test]
+fn verify_command_records_the_outcome() {
+    let dir = std::env::temp_dir().join(format!("ucf_verify_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (plan_path, report_path, store_path) = (dir.join("plan.json"), dir.join("checkpoints.json"), dir.join("calibration.json"));
+    let plan = plan();
+    std::fs::write(&plan_path, serde_json::to_string(&plan).unwrap()).unwrap();
+    std::fs::write(&report_path, serde_json::to_string(&report(&[("boot", "L0_BOOT", true), ("title", "L2_INTERACTIVE", true)])).unwrap()).unwrap();
+
+    let args: Vec<String> = ["--plan", plan_path.to_str().unwrap(), "--report", report_path.to_str().unwrap(), "--store", store_path.to_str().unwrap()]
+        .map(String::from).to_vec();
+    assert!(ucf_planner::cli::verify_command(&args).unwrap().met);
+    let store = CalibrationStore::load(&store_path).unwrap();
+    assert_eq!(store.entry(&plan.strategy, &gap_signature(&plan.gaps)).map(|e| (e.passed, e.failed)), Some((1, 0)));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
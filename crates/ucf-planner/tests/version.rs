@@ -0,0 +1,66 @@
+//! Input document version negotiation
+
+use ucf_planner::model::{GameRequirement, PlanningRequest, PolicyProfile};
+use ucf_planner::*;
+
+fn check(version: &str) -> Result<Option<String>, VersionError> {
+    check_version(DocumentKind::Requirement, "tetris_gb", version)
+}
+
+#[test]
+fn the_supported_line_is_accepted_silently() {
+    for v in ["0.1", "0.1.0", "v0.1", " 0.1 "] { assert_eq!(check(v), Ok(None), "{v:?}"); }
+}
+
+#[test]
+fn a_newer_patch_on_the_line_is_accepted_with_a_warning() {
+    let warning = check("0.1.3").unwrap().expect("a warning");
+    assert_eq!(warning, "tetris_gb: requirement_version 0.1.3 is newer than supported 0.1.0; newer fields are ignored");
+}
+
+#[test]
+fn any_other_0x_minor_or_major_is_rejected() {
+    for v in ["0.2", "0.0.9", "1.0", "1.1.0"] {
+        let Err(VersionError::Unsupported { found, supported, .. }) = check(v) else { panic!("{v} accepted") };
+        assert_eq!((found, supported), (SchemaVersion::parse(v).unwrap(), SchemaVersion::new(0, 1, 0)));
+    }
+    assert_eq!(check("0.2").unwrap_err().to_string(),
+        "tetris_gb: requirement_version 0.2.0 is not supported (planner implements 0.1.x, up to 0.1.0)");
+    assert!(matches!(check("0.x"), Err(VersionError::Unparseable { .. })));
+    assert!(matches!(check("0.1.0.1"), Err(VersionError::Unparseable { .. })));
+}
+
+#[test]
+fn release_lines_follow_semver() {
+    let v = |s: &str| SchemaVersion::parse(s).unwrap();
+    assert!(v("1.4").compatible_with(&v("1.0")));
+    assert!(!v("2.0").compatible_with(&v("1.9")));
+    assert!(v("0.3.1").compatible_with(&v("0.3.0")));
+    assert!(!v("0.3").compatible_with(&v("0.4")));
+    assert_eq!((v("1.4").line(), v("0.3.1").line()), ("1.x".to_string(), "0.3.x".to_string()));
+}
+
+#[test]
+fn planning_refuses_a_0x_minor_mismatch_and_echoes_warnings() {
+    let game = |version: &str| -> GameRequirement { serde_json::from_value(serde_json::json!({
+        "requirement_version": version, "artifact_id": "tetris_gb", "targets_original": ["gb_dmg"],
+        "cpu": {"required_isa": ["sm83"]}, "runtime": {"os_families": ["gb_bare_metal"]}
+    })).unwrap() };
+    let policy = PolicyProfile {
+        policy_version: "0.1".into(), profile_id: "version".into(),
+        latency_budget_ms: 60.0, min_fidelity_score: 40, max_legal_risk: 70,
+        prefer_local_execution: true, allow_streaming: true, allow_split_execution: true,
+        allow_downport_classification: true, allow_unverified_plans: false,
+    };
+    let host = pc_linux_x64();
+    let plan = |game: &GameRequirement| plan_execution_ranked(PlanningRequest {
+        game, target: &host, helpers: &[], policy: &policy, mode_id: None, evidence: &[], calibration: None, cores: &[],
+    });
+
+    let warned = plan(&game("0.1.2")).unwrap().winner;
+    assert_eq!(warned.input_versions.requirement, "0.1.2");
+    assert_eq!(warned.input_versions.warnings.len(), 1);
+    let Err(err) = plan(&game("0.2")) else { panic!("0.2 is a breaking change from 0.1") };
+    let err = err.to_string();
+    assert!(err.contains("planner implements 0.1.x"), "{err}");
+}